@@ -0,0 +1,192 @@
+//! Spend budgets for AI requests
+//!
+//! A [`BudgetGuard`] tracks what each tag (a team, tenant or API key) has
+//! spent today and this month, and refuses requests whose estimated cost
+//! would take the tag past either allowance. The estimate is reserved when
+//! the request is admitted, so concurrent requests cannot overspend, and is
+//! replaced by the actual cost once the request completes. Spend resets at
+//! UTC day and month boundaries.
+
+use crate::{AiError, AiResult};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+/// Spending allowance of a tag, in the currency of provider pricing
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Budget {
+    pub daily: Option<f64>,
+    pub monthly: Option<f64>,
+}
+
+/// Spend of a tag in the current day and month
+#[derive(Debug, Clone, Copy)]
+struct Spend {
+    day: NaiveDate,
+    daily: f64,
+    monthly: f64,
+}
+
+impl Spend {
+    fn new(now: DateTime<Utc>) -> Self {
+        Self { day: now.date_naive(), daily: 0.0, monthly: 0.0 }
+    }
+
+    /// Reset the counters whose period has ended
+    fn roll_over(&mut self, now: DateTime<Utc>) {
+        let today = now.date_naive();
+        if (today.year(), today.month()) != (self.day.year(), self.day.month()) {
+            self.monthly = 0.0;
+        }
+        if today != self.day {
+            self.daily = 0.0;
+        }
+        self.day = today;
+    }
+
+    /// Allowance of `budget` left after this spend, or `None` without a limit
+    fn remaining(&self, budget: &Budget) -> Option<f64> {
+        let daily = budget.daily.map(|limit| limit - self.daily);
+        let monthly = budget.monthly.map(|limit| limit - self.monthly);
+        match (daily, monthly) {
+            (Some(daily), Some(monthly)) => Some(daily.min(monthly).max(0.0)),
+            (Some(remaining), None) | (None, Some(remaining)) => Some(remaining.max(0.0)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Estimated cost held against a tag's budget until the request settles
+#[derive(Debug)]
+#[must_use = "a reservation holds budget until it is settled"]
+pub struct BudgetReservation {
+    tag: String,
+    amount: f64,
+    day: NaiveDate,
+}
+
+/// Refuses requests that would exceed a tag's remaining budget
+#[derive(Default)]
+pub struct BudgetGuard {
+    budgets: RwLock<HashMap<String, Budget>>,
+    spend: RwLock<HashMap<String, Spend>>,
+}
+
+impl BudgetGuard {
+    /// Create a guard without budgets; untracked tags are never refused
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the budget of a tag
+    pub async fn set_budget(&self, tag: &str, budget: Budget) {
+        self.budgets.write().await.insert(tag.to_string(), budget);
+    }
+
+    /// Remaining allowance of a tag: the smaller of its daily and monthly remainders
+    ///
+    /// Returns `None` for tags without a limit.
+    pub async fn remaining(&self, tag: &str) -> Option<f64> {
+        self.remaining_at(tag, Utc::now()).await
+    }
+
+    async fn remaining_at(&self, tag: &str, now: DateTime<Utc>) -> Option<f64> {
+        let budget = self.budgets.read().await.get(tag).copied()?;
+        let mut spend = self.spend.read().await.get(tag).copied().unwrap_or_else(|| Spend::new(now));
+        spend.roll_over(now);
+        spend.remaining(&budget)
+    }
+
+    /// Reserve `estimated_cost` of the tag's budget for a request
+    ///
+    /// Fails with [`AiError::BudgetExceeded`] when the estimate does not fit
+    /// in what is left after earlier reservations. The reservation must be
+    /// passed to [`BudgetGuard::settle`] once the request has completed or failed.
+    pub async fn reserve(&self, tag: &str, estimated_cost: f64) -> AiResult<BudgetReservation> {
+        let now = Utc::now();
+        let budget = self.budgets.read().await.get(tag).copied().unwrap_or_default();
+        let mut spend = self.spend.write().await;
+        let spend = spend.entry(tag.to_string()).or_insert_with(|| Spend::new(now));
+        spend.roll_over(now);
+
+        if let Some(remaining) = spend.remaining(&budget).filter(|remaining| estimated_cost > *remaining) {
+            warn!("Refusing request for {}: estimated {:.4} exceeds remaining {:.4}", tag, estimated_cost, remaining);
+            return Err(AiError::BudgetExceeded {
+                tag: tag.to_string(),
+                estimated_cost,
+                remaining,
+            });
+        }
+        spend.daily += estimated_cost;
+        spend.monthly += estimated_cost;
+        Ok(BudgetReservation { tag: tag.to_string(), amount: estimated_cost, day: spend.day })
+    }
+
+    /// Replace a reservation with the actual cost of its request
+    ///
+    /// Failed requests settle at zero to release the reservation. A
+    /// reservation from an earlier day or month is only released from the
+    /// periods it was charged to.
+    pub async fn settle(&self, reservation: BudgetReservation, cost: f64) {
+        let now = Utc::now();
+        let mut spend = self.spend.write().await;
+        let spend = spend.entry(reservation.tag.clone()).or_insert_with(|| Spend::new(now));
+        spend.roll_over(now);
+        if spend.day == reservation.day {
+            spend.daily = (spend.daily - reservation.amount).max(0.0);
+        }
+        if (spend.day.year(), spend.day.month()) == (reservation.day.year(), reservation.day.month()) {
+            spend.monthly = (spend.monthly - reservation.amount).max(0.0);
+        }
+        spend.daily += cost;
+        spend.monthly += cost;
+        debug!("Charged {:.4} to {} ({:.4} today)", cost, reservation.tag, spend.daily);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn test_daily_spend_resets_but_monthly_carries_over() {
+        let guard = BudgetGuard::new();
+        guard.set_budget("team", Budget { daily: Some(1.0), monthly: Some(1.5) }).await;
+
+        let day_one = Utc.with_ymd_and_hms(2026, 3, 10, 12, 0, 0).unwrap();
+        guard.spend.write().await.insert("team".to_string(), Spend { day: day_one.date_naive(), daily: 0.8, monthly: 0.8 });
+        assert!((guard.remaining_at("team", day_one).await.unwrap() - 0.2).abs() < 1e-9);
+
+        let day_two = Utc.with_ymd_and_hms(2026, 3, 11, 0, 0, 1).unwrap();
+        assert!((guard.remaining_at("team", day_two).await.unwrap() - 0.7).abs() < 1e-9);
+
+        let next_month = Utc.with_ymd_and_hms(2026, 4, 1, 0, 0, 1).unwrap();
+        assert_eq!(guard.remaining_at("team", next_month).await, Some(1.0));
+        assert_eq!(guard.remaining_at("unbudgeted", day_one).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_reservations_cannot_overspend() {
+        let guard = std::sync::Arc::new(BudgetGuard::new());
+        guard.set_budget("team", Budget { daily: Some(1.0), monthly: None }).await;
+
+        let attempts = (0..10).map(|_| {
+            let guard = guard.clone();
+            tokio::spawn(async move { guard.reserve("team", 0.3).await })
+        });
+        let reservations: Vec<_> = futures::future::join_all(attempts).await
+            .into_iter()
+            .filter_map(|attempt| attempt.unwrap().ok())
+            .collect();
+        assert_eq!(reservations.len(), 3);
+        assert!((guard.remaining("team").await.unwrap() - 0.1).abs() < 1e-9);
+
+        // Settling replaces each estimate with the actual cost, failures release theirs
+        let mut reservations = reservations.into_iter();
+        guard.settle(reservations.next().unwrap(), 0.5).await;
+        guard.settle(reservations.next().unwrap(), 0.0).await;
+        assert!((guard.remaining("team").await.unwrap() - 0.2).abs() < 1e-9);
+    }
+}
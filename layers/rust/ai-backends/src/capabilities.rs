@@ -0,0 +1,87 @@
+//! Provider capabilities
+//!
+//! Each provider describes the features it supports with
+//! [`ProviderCapabilities`]; the router and the client derive the features a
+//! request needs the same way and only consider providers that cover them.
+
+use crate::{ChatRequest, ContentPart, MessageContent};
+use serde::Serialize;
+
+/// Features a provider supports, or a request needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct ProviderCapabilities {
+    /// Streams chat completions incrementally rather than as one chunk
+    pub streaming: bool,
+    /// Accepts tool and function definitions
+    pub tools: bool,
+    /// Accepts image content in messages
+    pub vision: bool,
+    /// Creates embeddings
+    pub embeddings: bool,
+}
+
+impl ProviderCapabilities {
+    /// Every capability
+    pub fn all() -> Self {
+        Self { streaming: true, tools: true, vision: true, embeddings: true }
+    }
+
+    /// Capabilities a chat request needs
+    pub fn required_by(request: &ChatRequest) -> Self {
+        Self {
+            streaming: request.stream == Some(true),
+            tools: request.tools.as_ref().is_some_and(|tools| !tools.is_empty())
+                || request.functions.as_ref().is_some_and(|functions| !functions.is_empty()),
+            vision: request.messages.iter().any(|message| match &message.content {
+                MessageContent::MultiModal(parts) => parts.iter().any(|part| matches!(part, ContentPart::ImageUrl { .. })),
+                MessageContent::Text(_) => false,
+            }),
+            embeddings: false,
+        }
+    }
+
+    /// Capabilities an embedding request needs
+    pub fn for_embeddings() -> Self {
+        Self { embeddings: true, ..Self::default() }
+    }
+
+    /// Names of the capabilities in `needs` this set lacks
+    pub fn missing(&self, needs: &ProviderCapabilities) -> Vec<&'static str> {
+        [
+            (needs.streaming && !self.streaming, "streaming"),
+            (needs.tools && !self.tools, "tools"),
+            (needs.vision && !self.vision, "vision"),
+            (needs.embeddings && !self.embeddings, "embeddings"),
+        ]
+        .into_iter()
+        .filter_map(|(missing, name)| missing.then_some(name))
+        .collect()
+    }
+
+    /// Capabilities in either set
+    pub fn union(&self, other: &ProviderCapabilities) -> Self {
+        Self {
+            streaming: self.streaming || other.streaming,
+            tools: self.tools || other.tools,
+            vision: self.vision || other.vision,
+            embeddings: self.embeddings || other.embeddings,
+        }
+    }
+
+    /// Whether this set covers every capability in `needs`
+    pub fn satisfies(&self, needs: &ProviderCapabilities) -> bool {
+        self.missing(needs).is_empty()
+    }
+
+    /// Names of the capabilities in `needs` that providers offering `offered` fall short of
+    ///
+    /// These are the capabilities no provider offers; when each is offered by
+    /// some provider but none offers them together, all of `needs`.
+    pub fn unmet_by(offered: impl IntoIterator<Item = ProviderCapabilities>, needs: &ProviderCapabilities) -> Vec<&'static str> {
+        let union = offered.into_iter().fold(Self::default(), |union, capabilities| union.union(&capabilities));
+        match union.missing(needs) {
+            missing if missing.is_empty() => Self::default().missing(needs),
+            missing => missing,
+        }
+    }
+}
@@ -1,17 +1,71 @@
 //! AI Backend Client
 
-use crate::{AiResult, AiError, AiProviderTrait, ProviderFactory, ProviderConfig, ChatRequest, ChatResponse, CompletionRequest, CompletionResponse, EmbeddingRequest, EmbeddingResponse, BackendMetrics};
+use crate::{AiResult, AiError, AiProviderTrait, BudgetGuard, OutputSchema, ResponseFormat, StructuredSchema, fit_response_format, CompletionCache, ContextFit, ContextFitStrategy, ProviderFactory, ProviderConfig, ChatRequest, ChatResponse, ChatChoice, ChatMessage, ChatCompletionChunk, ChatCompletionStream, CompletionRequest, CompletionResponse, EmbeddingRequest, EmbeddingResponse, BackendMetrics, ContentModeration, MessageRole, ModelCatalog, ModelListing, PriorityLimiter, ProviderCapabilities, PriorityPermit, RequestPriority, ModerationFlag, Moderator, ModerationPolicy, ProviderRateLimiter, RaceConfig, RaceResponse, RateLimits, Usage};
 use async_trait::async_trait;
-use std::collections::HashMap;
+use serde::de::DeserializeOwned;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
-use tracing::{info, warn, error};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn, error};
+
+/// Tokens reserved for the summary replacing dropped messages
+const SUMMARY_MAX_TOKENS: u32 = 200;
+
+const SUMMARY_SYSTEM_PROMPT: &str = "Summarize the following conversation in a few sentences. \
+Keep names, facts, decisions and open questions; leave out pleasantries.";
+
+/// Transcript of the leading `messages` that fit in `room` tokens
+///
+/// Returns the transcript and how many messages it covers. At least one
+/// message is taken, truncated when it is longer than `room` on its own.
+fn transcript_chunk(messages: &[ChatMessage], room: u64) -> (String, usize) {
+    let max_chars = usize::try_from(room.saturating_mul(4)).unwrap_or(usize::MAX);
+    let mut chunk = String::new();
+    let mut taken = 0;
+    for message in messages {
+        let line = format!("{}: {}", message.role.as_str(), message.content.text());
+        if taken == 0 {
+            chunk = line.chars().take(max_chars).collect();
+        } else if chunk.len() + 1 + line.len() <= max_chars {
+            chunk.push('\n');
+            chunk.push_str(&line);
+        } else {
+            break;
+        }
+        taken += 1;
+    }
+    (chunk, taken)
+}
 
 /// AI Backend Client
 pub struct AiBackendClient {
-    providers: Arc<RwLock<HashMap<String, Box<dyn AiProviderTrait>>>>,
+    providers: Arc<RwLock<HashMap<String, Arc<dyn AiProviderTrait>>>>,
     metrics: Arc<RwLock<HashMap<String, BackendMetrics>>>,
     default_provider: Option<String>,
+    /// Max-in-flight limits per provider; providers without one are unlimited
+    concurrency_limits: Arc<RwLock<HashMap<String, Arc<PriorityLimiter>>>>,
+    queue_timeout: Duration,
+    /// Request and token pacing per provider; providers without one are unpaced
+    rate_limiters: Arc<RwLock<HashMap<String, Arc<ProviderRateLimiter>>>>,
+    moderation: Option<ContentModeration>,
+    catalog: Arc<ModelCatalog>,
+    budget_guard: Option<Arc<BudgetGuard>>,
+    /// Context window per model, in tokens
+    context_limits: Arc<RwLock<HashMap<String, u32>>>,
+    context_fit_strategy: ContextFitStrategy,
+    /// Model summarizing dropped messages; the request's own model when unset
+    summary_model: Option<String>,
+    completion_cache: Option<Arc<CompletionCache>>,
+    /// Providers to try in order per model, overriding provider selection
+    fallback_chains: Arc<RwLock<HashMap<String, Vec<String>>>>,
+    /// Other names for models, resolved before a provider is selected
+    model_aliases: Arc<RwLock<HashMap<String, String>>>,
+    /// Replies requested by `structured_output` before giving up on valid JSON
+    structured_output_attempts: u32,
 }
 
 impl AiBackendClient {
@@ -21,15 +75,41 @@ impl AiBackendClient {
             providers: Arc::new(RwLock::new(HashMap::new())),
             metrics: Arc::new(RwLock::new(HashMap::new())),
             default_provider: None,
+            concurrency_limits: Arc::new(RwLock::new(HashMap::new())),
+            queue_timeout: Duration::from_secs(30),
+            rate_limiters: Arc::new(RwLock::new(HashMap::new())),
+            moderation: None,
+            catalog: Arc::new(ModelCatalog::default()),
+            budget_guard: None,
+            context_limits: Arc::new(RwLock::new(HashMap::new())),
+            context_fit_strategy: ContextFitStrategy::default(),
+            summary_model: None,
+            completion_cache: None,
+            fallback_chains: Arc::new(RwLock::new(HashMap::new())),
+            model_aliases: Arc::new(RwLock::new(HashMap::new())),
+            structured_output_attempts: 3,
         }
     }
 
     /// Add a provider
     pub async fn add_provider(&self, name: &str, config: ProviderConfig) -> AiResult<()> {
+        {
+            let mut context_limits = self.context_limits.write().await;
+            for model in &config.models {
+                if let Some(context_window) = model.context_window {
+                    context_limits.insert(model.id.clone(), context_window);
+                }
+            }
+        }
+
         let provider = ProviderFactory::create_provider(config)?;
+        self.register_provider(name, provider).await
+    }
 
+    /// Add an already constructed provider
+    pub async fn register_provider(&self, name: &str, provider: Box<dyn AiProviderTrait>) -> AiResult<()> {
         let mut providers = self.providers.write().await;
-        providers.insert(name.to_string(), provider);
+        providers.insert(name.to_string(), Arc::from(provider));
 
         let mut metrics = self.metrics.write().await;
         metrics.insert(name.to_string(), BackendMetrics::default());
@@ -46,6 +126,9 @@ impl AiBackendClient {
         if removed.is_some() {
             let mut metrics = self.metrics.write().await;
             metrics.remove(name);
+            self.concurrency_limits.write().await.remove(name);
+            self.rate_limiters.write().await.remove(name);
+            self.catalog.invalidate(name).await;
             info!("Removed AI provider: {}", name);
             Ok(())
         } else {
@@ -58,6 +141,239 @@ impl AiBackendClient {
         self.default_provider = Some(name.to_string());
     }
 
+    /// Limit how many requests may be in flight to a provider at once
+    ///
+    /// Requests waiting for a slot are served interactive before batch, in
+    /// arrival order within each [`RequestPriority`].
+    pub async fn set_max_in_flight(&self, provider_name: &str, max_in_flight: usize) -> AiResult<()> {
+        if max_in_flight == 0 {
+            return Err(AiError::Config("max_in_flight must be at least 1".to_string()));
+        }
+
+        let mut limits = self.concurrency_limits.write().await;
+        limits.insert(provider_name.to_string(), Arc::new(PriorityLimiter::new(max_in_flight)));
+        info!("Limited provider {} to {} in-flight requests", provider_name, max_in_flight);
+        Ok(())
+    }
+
+    /// Set how long a request may wait for a concurrency slot or for its rate limits
+    pub fn set_queue_timeout(&mut self, timeout: Duration) {
+        self.queue_timeout = timeout;
+    }
+
+    /// Pace requests to a provider to stay under its published per-minute limits
+    ///
+    /// Requests that would exceed a limit wait until the provider's token
+    /// bucket has refilled enough, and provider selection prefers providers
+    /// with capacity left.
+    pub async fn set_rate_limits(&self, provider_name: &str, limits: RateLimits) -> AiResult<()> {
+        if limits.requests_per_minute == Some(0) || limits.tokens_per_minute == Some(0) {
+            return Err(AiError::Config("Rate limits must allow at least 1 per minute".to_string()));
+        }
+
+        let mut limiters = self.rate_limiters.write().await;
+        limiters.insert(provider_name.to_string(), Arc::new(ProviderRateLimiter::new(limits)));
+        info!("Rate limited provider {} to {:?}", provider_name, limits);
+        Ok(())
+    }
+
+    /// Moderate prompts and completions with the given policy
+    pub fn set_moderator(&mut self, moderator: Arc<dyn Moderator>, policy: ModerationPolicy) {
+        self.moderation = Some(ContentModeration::new(moderator, policy));
+    }
+
+    /// Check tagged requests against spend budgets
+    pub fn set_budget_guard(&mut self, guard: Arc<BudgetGuard>) {
+        self.budget_guard = Some(guard);
+    }
+
+    /// Set the context window of a model, overriding the one from provider config
+    pub async fn set_context_limit(&self, model: &str, tokens: u32) {
+        self.context_limits.write().await.insert(model.to_string(), tokens);
+    }
+
+    /// Set the providers to try, in order, for a model
+    ///
+    /// Chat completions for the model go to the first provider; on a
+    /// retryable failure they move on to the next one. An empty chain
+    /// restores automatic provider selection.
+    pub async fn set_fallback_chain(&self, model: &str, providers: Vec<String>) {
+        let mut chains = self.fallback_chains.write().await;
+        if providers.is_empty() {
+            chains.remove(model);
+        } else {
+            chains.insert(model.to_string(), providers);
+        }
+    }
+
+    /// Let requests name `model` as `alias`
+    ///
+    /// Requests are routed, priced and sent to providers under the model the
+    /// alias resolves to; a model named like an alias can no longer be
+    /// requested by that name.
+    pub async fn set_model_alias(&self, alias: &str, model: &str) {
+        if alias != model {
+            self.model_aliases.write().await.insert(alias.to_string(), model.to_string());
+        }
+    }
+
+    /// The model an alias resolves to, or `model` itself
+    async fn resolve_model(&self, model: &str) -> String {
+        self.model_aliases.read().await.get(model).cloned().unwrap_or_else(|| model.to_string())
+    }
+
+    /// Set how many replies `structured_output` requests before giving up
+    pub fn set_structured_output_attempts(&mut self, attempts: u32) {
+        self.structured_output_attempts = attempts.max(1);
+    }
+
+    /// Set how conversations too long for their model's context window are shortened
+    pub fn set_context_fit_strategy(&mut self, strategy: ContextFitStrategy) {
+        self.context_fit_strategy = strategy;
+    }
+
+    /// Summarize dropped messages with `model`, typically a cheap one, rather than the request's model
+    pub fn set_summary_model(&mut self, model: impl Into<String>) {
+        self.summary_model = Some(model.into());
+    }
+
+    /// Reuse responses of deterministic chat requests from a cache
+    pub fn set_completion_cache(&mut self, cache: Arc<CompletionCache>) {
+        self.completion_cache = Some(cache);
+    }
+
+    /// Set how long a provider's model catalog is cached before it is refreshed
+    pub fn set_model_catalog_ttl(&mut self, ttl: Duration) {
+        self.catalog = Arc::new(ModelCatalog::new(ttl).with_refresh_timeout(self.catalog.refresh_timeout()));
+    }
+
+    /// Set how long fetching a provider's model catalog may take before it counts as failed
+    pub fn set_model_catalog_refresh_timeout(&mut self, timeout: Duration) {
+        self.catalog = Arc::new(ModelCatalog::new(self.catalog.ttl()).with_refresh_timeout(timeout));
+    }
+
+    /// Refresh the model catalog of every provider now, concurrently
+    pub async fn refresh_model_catalogs(&self) {
+        let providers = self.provider_snapshot().await;
+        futures::future::join_all(providers.iter().map(|(name, provider)| self.catalog.refresh(name, provider.as_ref()))).await;
+    }
+
+    /// Registered providers by name, cloned so no lock is held while they are called
+    async fn provider_snapshot(&self) -> Vec<(String, Arc<dyn AiProviderTrait>)> {
+        self.providers.read().await.iter()
+            .map(|(name, provider)| (name.clone(), provider.clone()))
+            .collect()
+    }
+
+    /// Names of the providers serving a model according to their catalogs
+    ///
+    /// Stale catalogs are refreshed concurrently and without holding the
+    /// provider lock, so a slow provider delays selection by at most the
+    /// catalog's refresh timeout.
+    async fn providers_serving(&self, model: &str) -> Vec<(String, Arc<dyn AiProviderTrait>)> {
+        let providers = self.provider_snapshot().await;
+        let serving = futures::future::join_all(
+            providers.iter().map(|(name, provider)| self.catalog.supports(name, provider.as_ref(), model)),
+        ).await;
+        providers.into_iter()
+            .zip(serving)
+            .filter_map(|(provider, serves)| serves.then_some(provider))
+            .collect()
+    }
+
+    /// Providers serving a model that cover every capability in `needs`
+    ///
+    /// When providers serve the model but none covers `needs`, the error
+    /// names the capabilities they fall short of.
+    async fn capable_providers(&self, model: &str, needs: &ProviderCapabilities) -> AiResult<Vec<(String, Arc<dyn AiProviderTrait>)>> {
+        let serving = self.providers_serving(model).await;
+        if serving.is_empty() {
+            return Err(AiError::ModelNotAvailable(format!("No provider supports model: {}", model)));
+        }
+
+        let offered: Vec<ProviderCapabilities> = serving.iter().map(|(_, provider)| provider.capabilities()).collect();
+        let capable: Vec<_> = serving.into_iter()
+            .filter(|(_, provider)| provider.capabilities().satisfies(needs))
+            .collect();
+        if capable.is_empty() {
+            let unmet = ProviderCapabilities::unmet_by(offered, needs);
+            return Err(AiError::ModelNotAvailable(format!(
+                "No provider for model {} supports {}", model, unmet.join(" and ")
+            )));
+        }
+        Ok(capable)
+    }
+
+    /// Refresh the model catalogs in the background at a fixed interval
+    pub fn spawn_model_catalog_refresh(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                self.refresh_model_catalogs().await;
+            }
+        })
+    }
+
+    /// Wait for a concurrency slot on a provider in the lane of `priority`, if it is limited
+    async fn acquire_slot(&self, provider_name: &str, priority: RequestPriority) -> AiResult<Option<PriorityPermit>> {
+        let limiter = match self.concurrency_limits.read().await.get(provider_name) {
+            Some(limiter) => limiter.clone(),
+            None => return Ok(None),
+        };
+
+        if let Some(metrics) = self.metrics.write().await.get_mut(provider_name) {
+            metrics.queue_depth += 1;
+        }
+
+        let permit = tokio::time::timeout(self.queue_timeout, limiter.acquire(priority)).await;
+
+        let mut metrics = self.metrics.write().await;
+        if let Some(metrics) = metrics.get_mut(provider_name) {
+            metrics.queue_depth = metrics.queue_depth.saturating_sub(1);
+        }
+
+        match permit {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            Ok(Err(e)) => Err(e),
+            Err(_) => {
+                if let Some(metrics) = metrics.get_mut(provider_name) {
+                    metrics.requests_failed += 1;
+                }
+                warn!("Timed out waiting for a slot on provider {}", provider_name);
+                Err(AiError::Timeout(format!(
+                    "Provider '{}' is at its concurrency limit; waited {:?}",
+                    provider_name, self.queue_timeout
+                )))
+            }
+        }
+    }
+
+    /// Wait until a provider's rate limits allow a request of `tokens`
+    ///
+    /// The wait is bounded by the queue timeout. Returns the provider's
+    /// limiter so actual usage can be settled.
+    async fn pace(&self, provider_name: &str, tokens: u64) -> AiResult<Option<Arc<ProviderRateLimiter>>> {
+        let Some(limiter) = self.rate_limiters.read().await.get(provider_name).cloned() else {
+            return Ok(None);
+        };
+        let waited = tokio::time::timeout(self.queue_timeout, limiter.acquire(tokens)).await
+            .map_err(|_| AiError::RateLimit(format!(
+                "Provider '{}' rate limits would delay the request beyond {:?}",
+                provider_name, self.queue_timeout
+            )))?;
+        if !waited.is_zero() {
+            debug!("Delayed request to {} by {:?} to respect its rate limits", provider_name, waited);
+        }
+        Ok(Some(limiter))
+    }
+
+    /// A registered provider, cloned so the provider lock is not held while it is called
+    async fn provider(&self, provider_name: &str) -> AiResult<Arc<dyn AiProviderTrait>> {
+        self.providers.read().await.get(provider_name).cloned()
+            .ok_or_else(|| AiError::Config(format!("Provider '{}' not found", provider_name)))
+    }
+
     /// Get available providers
     pub async fn get_providers(&self) -> Vec<String> {
         let providers = self.providers.read().await;
@@ -74,31 +390,312 @@ impl AiBackendClient {
         }
     }
 
+    /// Models served across all providers, from their cached catalogs
+    ///
+    /// A model served by several providers is listed once, with the aliases
+    /// resolving to it. Stale catalogs are fetched concurrently without
+    /// holding the provider lock; providers whose catalog cannot be fetched
+    /// in time are left out.
+    pub async fn list_models(&self) -> Vec<ModelListing> {
+        let providers = self.provider_snapshot().await;
+        let catalogs = futures::future::join_all(
+            providers.iter().map(|(name, provider)| self.catalog.models(name, provider.as_ref())),
+        ).await;
+        let aliases = self.model_aliases.read().await.clone();
+        let mut listings: HashMap<String, ModelListing> = HashMap::new();
+
+        for ((name, provider), models) in providers.iter().zip(catalogs) {
+            let Some(models) = models else {
+                continue;
+            };
+            let capabilities = provider.capabilities();
+
+            // A model shadowed by an alias cannot be requested by its own name
+            for model in models.into_iter().filter(|model| !aliases.contains_key(model)) {
+                let pricing = provider.get_model_pricing(&model);
+                let listing = listings.entry(model.clone()).or_insert_with(|| ModelListing {
+                    id: model,
+                    providers: Vec::new(),
+                    aliases: Vec::new(),
+                    capabilities: ProviderCapabilities::default(),
+                    pricing: None,
+                });
+                listing.providers.push(name.clone());
+                listing.capabilities = listing.capabilities.union(&capabilities);
+                listing.pricing = match (listing.pricing, pricing) {
+                    (Some(current), Some(price)) => Some(current.min(price)),
+                    (current, price) => current.or(price),
+                };
+            }
+        }
+
+        for (alias, model) in aliases {
+            if let Some(listing) = listings.get_mut(&model) {
+                listing.aliases.push(alias);
+            }
+        }
+
+        let mut listings: Vec<ModelListing> = listings.into_values().collect();
+        for listing in &mut listings {
+            listing.providers.sort();
+            listing.aliases.sort();
+        }
+        listings.sort_by(|a, b| a.id.cmp(&b.id));
+        listings
+    }
+
     /// Chat completion with automatic provider selection
-    pub async fn chat_completion(&self, request: ChatRequest) -> AiResult<ChatResponse> {
-        let provider_name = self.select_provider_for_model(&request.model).await?;
-        self.chat_completion_with_provider(&provider_name, request).await
+    pub async fn chat_completion(&self, mut request: ChatRequest) -> AiResult<ChatResponse> {
+        request.model = self.resolve_model(&request.model).await;
+        let chain = self.fallback_chains.read().await.get(&request.model).cloned();
+        if let Some(chain) = chain {
+            let fit = self.fit_to_context(&mut request).await?;
+            let response = self.chat_completion_with_fallback(&chain, request).await?;
+            return Ok(ChatResponse { context_fit: fit.if_trimmed(), ..response });
+        }
+
+        let provider_name = self.select_provider_for_model(&request.model, Self::estimate_paced_tokens(&request), ProviderCapabilities::required_by(&request)).await?;
+        let fit = self.fit_to_context(&mut request).await?;
+        let response = self.chat_completion_with_provider(&provider_name, request).await?;
+        Ok(ChatResponse { context_fit: fit.if_trimmed(), ..response })
+    }
+
+    /// Walk a fallback chain until a provider answers
+    ///
+    /// Providers that are not registered are skipped; a non-retryable error
+    /// ends the walk, since the next provider would refuse the request too.
+    async fn chat_completion_with_fallback(&self, chain: &[String], request: ChatRequest) -> AiResult<ChatResponse> {
+        let mut last_error = None;
+        for provider_name in chain {
+            if !self.providers.read().await.contains_key(provider_name) {
+                warn!("Skipping unregistered provider '{}' in the fallback chain of {}", provider_name, request.model);
+                continue;
+            }
+
+            match self.chat_completion_with_provider(provider_name, request.clone()).await {
+                Ok(response) => return Ok(response),
+                Err(e) if e.is_retryable() => {
+                    warn!("Provider '{}' failed for {}, falling back: {}", provider_name, request.model, e);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            AiError::ModelNotAvailable(format!("No provider in the fallback chain of {} is registered", request.model))
+        }))
+    }
+
+    /// Chat completion whose reply is parsed as a `T`
+    ///
+    /// The provider serving each attempt is asked for JSON matching `T`'s
+    /// schema through its JSON mode, or in a system message when it has
+    /// none. A reply that is not valid JSON or does not match the schema is
+    /// answered with a corrective message, up to the configured number of
+    /// attempts.
+    pub async fn structured_output<T: DeserializeOwned + OutputSchema>(&self, mut request: ChatRequest) -> AiResult<T> {
+        let schema = StructuredSchema::of::<T>()?;
+        request.response_format = Some(ResponseFormat::JsonSchema { json_schema: schema.response_schema() });
+
+        let mut problem = String::new();
+        for attempt in 1..=self.structured_output_attempts {
+            let response = self.chat_completion(request.clone()).await?;
+            let reply = response.choices.first().map(|choice| choice.message.content.text()).unwrap_or_default();
+            match schema.parse(&reply) {
+                Ok(output) => return Ok(output),
+                Err(e) => {
+                    warn!("Attempt {} at a structured {} failed: {}", attempt, schema.name(), e);
+                    request.messages.push(ChatMessage::assistant(reply));
+                    request.messages.push(ChatMessage::user(format!(
+                        "That reply cannot be used: {}. Reply again with only the corrected JSON.", e
+                    )));
+                    problem = e;
+                }
+            }
+        }
+
+        Err(AiError::Parse(format!(
+            "No valid {} after {} attempts: {}", schema.name(), self.structured_output_attempts, problem
+        )))
+    }
+
+    /// Chat completion charged to a budget tag
+    ///
+    /// With a budget guard set, the request's estimated prompt cost is
+    /// reserved from the tag's remaining allowance before it reaches the
+    /// provider, refusing it when it does not fit, and the cost of the
+    /// completed request is charged to the tag in its place.
+    pub async fn chat_completion_tagged(&self, tag: &str, mut request: ChatRequest) -> AiResult<ChatResponse> {
+        request.model = self.resolve_model(&request.model).await;
+        let provider_name = self.select_provider_for_model(&request.model, Self::estimate_paced_tokens(&request), ProviderCapabilities::required_by(&request)).await?;
+        let context_fit = self.fit_to_context(&mut request).await?.if_trimmed();
+        let (Some(guard), Some(price_per_1k)) = (self.budget_guard.as_ref(), self.model_pricing(&provider_name, &request.model).await) else {
+            let response = self.chat_completion_with_provider(&provider_name, request).await?;
+            return Ok(ChatResponse { context_fit, ..response });
+        };
+
+        let prompt_tokens = Self::estimate_prompt_tokens(&request);
+        let reservation = guard.reserve(tag, price_per_1k * prompt_tokens as f64 / 1000.0).await?;
+
+        let result = self.chat_completion_with_provider(&provider_name, request).await;
+        let tokens = match &result {
+            Ok(response) => response.usage.as_ref().map(|usage| usage.total_tokens as u64).unwrap_or(prompt_tokens),
+            Err(_) => 0,
+        };
+        guard.settle(reservation, price_per_1k * tokens as f64 / 1000.0).await;
+        result.map(|response| ChatResponse { context_fit, ..response })
+    }
+
+    /// Shorten a conversation to fit its model's context window
+    ///
+    /// The prompt must leave room for `max_tokens` (256 when unset) of
+    /// completion. Models without a known context window are left untouched.
+    /// Returns what was dropped; the latest message is always kept.
+    pub async fn fit_to_context(&self, request: &mut ChatRequest) -> AiResult<ContextFit> {
+        let Some(limit) = self.context_limits.read().await.get(&request.model).copied() else {
+            return Ok(ContextFit::default());
+        };
+        let budget = (limit as u64).saturating_sub(request.max_tokens.unwrap_or(256) as u64);
+        let prompt_tokens: u64 = request.messages.iter().map(crate::estimate_message_tokens).sum();
+        if prompt_tokens <= budget {
+            return Ok(ContextFit::default());
+        }
+
+        if self.context_fit_strategy == ContextFitStrategy::Summarize {
+            // Make room for the summary; without it, fall back to dropping
+            let mut messages = request.messages.clone();
+            if let Ok(dropped) = crate::trim_to_budget(&mut messages, budget.saturating_sub(SUMMARY_MAX_TOKENS as u64), true) {
+                let summary_model = self.summary_model.as_deref().unwrap_or(&request.model);
+                match self.summarize(summary_model, &dropped).await {
+                    Ok(summary) => {
+                        let position = messages.iter().take_while(|m| m.role == MessageRole::System).count();
+                        messages.insert(position, summary);
+                        request.messages = messages;
+                        let fit = ContextFit {
+                            dropped_messages: dropped.len(),
+                            dropped_tokens: dropped.iter().map(crate::estimate_message_tokens).sum(),
+                            summarized: true,
+                        };
+                        info!("Summarized {} messages to fit the {} context window", fit.dropped_messages, request.model);
+                        return Ok(fit);
+                    }
+                    Err(e) => warn!("Summarizing dropped messages failed, dropping them instead: {}", e),
+                }
+            }
+        }
+
+        let keep_system = self.context_fit_strategy != ContextFitStrategy::DropOldest;
+        let dropped = crate::trim_to_budget(&mut request.messages, budget, keep_system)?;
+        let fit = ContextFit {
+            dropped_messages: dropped.len(),
+            dropped_tokens: dropped.iter().map(crate::estimate_message_tokens).sum(),
+            summarized: false,
+        };
+        info!("Dropped {} messages to fit the {} context window", fit.dropped_messages, request.model);
+        Ok(fit)
+    }
+
+    /// Summarize messages into a system message with `model`
+    ///
+    /// A transcript too long for the summary model's context window is
+    /// summarized in chunks, each carrying the summary of those before it.
+    async fn summarize(&self, model: &str, messages: &[ChatMessage]) -> AiResult<ChatMessage> {
+        // Room for transcript next to the prompt and the reply
+        let room = match self.context_limits.read().await.get(model).copied() {
+            Some(limit) => (limit as u64)
+                .saturating_sub(SUMMARY_MAX_TOKENS as u64 + (SUMMARY_SYSTEM_PROMPT.len() / 4) as u64),
+            None => u64::MAX,
+        };
+
+        let mut summary: Option<String> = None;
+        let mut remaining = messages;
+        while !remaining.is_empty() {
+            let carried = summary.as_ref().map_or(String::new(), |summary| format!("Summary so far: {}\n", summary));
+            let chunk_room = room.saturating_sub((carried.len() / 4 + 1) as u64);
+            if chunk_room == 0 {
+                return Err(AiError::Config(format!("Context window of {} is too small to summarize with", model)));
+            }
+            let (chunk, taken) = transcript_chunk(remaining, chunk_room);
+            summary = Some(self.summarize_transcript(model, carried + &chunk).await?);
+            remaining = &remaining[taken..];
+        }
+        let summary = summary.ok_or_else(|| AiError::Config("No messages to summarize".to_string()))?;
+        Ok(ChatMessage::system(format!("Summary of the earlier conversation: {}", summary)))
+    }
+
+    /// Summary of a transcript that fits the summary model's context window
+    async fn summarize_transcript(&self, model: &str, transcript: String) -> AiResult<String> {
+        let request = ChatRequest {
+            model: model.to_string(),
+            messages: vec![ChatMessage::system(SUMMARY_SYSTEM_PROMPT), ChatMessage::user(transcript)],
+            max_tokens: Some(SUMMARY_MAX_TOKENS),
+            ..Default::default()
+        };
+
+        let provider_name = self.select_provider_for_model(model, Self::estimate_paced_tokens(&request), ProviderCapabilities::required_by(&request)).await?;
+        let response = self.chat_completion_with_provider(&provider_name, request).await?;
+        let summary = response.choices.first()
+            .map(|choice| choice.message.content.text())
+            .filter(|text| !text.trim().is_empty())
+            .ok_or_else(|| AiError::Parse("Summary response had no content".to_string()))?;
+
+        // Providers may overrun max_tokens; the summary must stay within its reserve
+        Ok(summary.chars().take(SUMMARY_MAX_TOKENS as usize * 4).collect())
+    }
+
+    /// Response of an identical deterministic request, if cached
+    async fn cached_response(&self, request: &ChatRequest) -> Option<ChatResponse> {
+        self.completion_cache.as_ref()?.get(request).await
+    }
+
+    /// Price per 1K tokens of a model on a provider
+    async fn model_pricing(&self, provider_name: &str, model: &str) -> Option<f64> {
+        self.providers.read().await.get(provider_name)?.get_model_pricing(model)
     }
 
     /// Chat completion with specific provider
-    pub async fn chat_completion_with_provider(&self, provider_name: &str, request: ChatRequest) -> AiResult<ChatResponse> {
+    pub async fn chat_completion_with_provider(&self, provider_name: &str, mut request: ChatRequest) -> AiResult<ChatResponse> {
+        if let Some(response) = self.cached_response(&request).await {
+            debug!("Serving chat completion for {} from cache", request.model);
+            return Ok(response);
+        }
+        let cache_key = self.completion_cache.as_ref()
+            .filter(|_| CompletionCache::is_cacheable(&request))
+            .map(|_| request.clone());
+
+        let provider = self.provider(provider_name).await?;
+
+        // Blocked prompts never reach the provider
+        let prompt_flags = match &self.moderation {
+            Some(moderation) => moderation.moderate_chat_request(&mut request).await?,
+            None => Vec::new(),
+        };
+
+        let request = provider.transform_request(fit_response_format(request, provider.supports_json_mode()));
+        let estimated_tokens = Self::estimate_paced_tokens(&request);
+        let rate_limiter = self.pace(provider_name, estimated_tokens).await?;
+        let _permit = self.acquire_slot(provider_name, request.priority).await?;
         let start_time = std::time::Instant::now();
 
-        let providers = self.providers.read().await;
-        let provider = providers.get(provider_name)
-            .ok_or_else(|| AiError::Config(format!("Provider '{}' not found", provider_name)))?;
+        {
+            let mut metrics = self.metrics.write().await;
+            let provider_metrics = metrics.get_mut(provider_name).unwrap();
+            provider_metrics.requests_total += 1;
+            provider_metrics.last_request_at = Some(std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64);
+        }
+
+        // Metrics are not locked during the call so concurrent requests are not serialized
+        let result = provider.chat_completion(&request).await;
 
         let mut metrics = self.metrics.write().await;
         let provider_metrics = metrics.get_mut(provider_name).unwrap();
 
-        provider_metrics.requests_total += 1;
-        provider_metrics.last_request_at = Some(std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64);
-
-        match provider.chat_completion(&request).await {
-            Ok(response) => {
+        match result {
+            Ok(mut response) => {
                 let elapsed = start_time.elapsed().as_millis() as f64;
                 provider_metrics.response_time_avg = (provider_metrics.response_time_avg + elapsed) / 2.0;
 
@@ -109,6 +706,18 @@ impl AiBackendClient {
                 info!("Chat completion successful: {} tokens, {:.2}ms", 
                       response.usage.as_ref().map(|u| u.total_tokens).unwrap_or(0), 
                       elapsed);
+                drop(metrics);
+
+                if let (Some(limiter), Some(usage)) = (&rate_limiter, &response.usage) {
+                    limiter.settle(estimated_tokens, usage.total_tokens as u64).await;
+                }
+                response.moderation_flags.extend(prompt_flags);
+                if let Some(moderation) = &self.moderation {
+                    moderation.moderate_chat_response(&mut response).await?;
+                }
+                if let (Some(cache), Some(request)) = (&self.completion_cache, &cache_key) {
+                    cache.insert(request, &response).await;
+                }
                 Ok(response)
             }
             Err(e) => {
@@ -119,33 +728,356 @@ impl AiBackendClient {
         }
     }
 
+    /// Chat completion raced across the fastest healthy providers of the model
+    ///
+    /// The request is sent to up to `config.max_providers` providers at once,
+    /// ranked by failure rate and then average latency; providers failing more
+    /// often than `config.max_failure_rate` are left out. The first successful
+    /// response wins and the requests still running are cancelled. Contenders
+    /// are only added while the combined estimated cost stays within
+    /// `config.max_estimated_cost`; the best ranked provider always runs.
+    pub async fn chat_completion_race(&self, mut request: ChatRequest, config: &RaceConfig) -> AiResult<RaceResponse> {
+        request.model = self.resolve_model(&request.model).await;
+        let contenders = self.race_contenders(&request, config).await?;
+        if contenders.is_empty() {
+            return Err(AiError::ModelNotAvailable(format!("No provider supports model: {}", request.model)));
+        }
+        info!("Racing chat completion across {:?}", contenders);
+
+        let mut race: FuturesUnordered<_> = contenders.iter()
+            .map(|name| {
+                let request = request.clone();
+                async move { (name, self.chat_completion_with_provider(name, request).await) }
+            })
+            .collect();
+
+        let mut running: Vec<&String> = contenders.iter().collect();
+        let mut last_error = None;
+        while let Some((name, result)) = race.next().await {
+            running.retain(|contender| *contender != name);
+            match result {
+                Ok(response) => {
+                    // Dropping the remaining futures cancels the requests still running;
+                    // contenders that already failed were counted as failed
+                    drop(race);
+                    let mut metrics = self.metrics.write().await;
+                    for loser in running {
+                        if let Some(metrics) = metrics.get_mut(loser) {
+                            metrics.requests_cancelled += 1;
+                        }
+                    }
+                    drop(metrics);
+
+                    return Ok(RaceResponse {
+                        winner: name.clone(),
+                        contenders: contenders.clone(),
+                        response,
+                    });
+                }
+                Err(e) => {
+                    warn!("Raced provider {} failed: {:?}", name, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| AiError::Unknown("Race finished without a response".to_string())))
+    }
+
+    /// Providers to race a request across, fastest expected first
+    async fn race_contenders(&self, request: &ChatRequest, config: &RaceConfig) -> AiResult<Vec<String>> {
+        // Raced requests are answered whole, so streaming is not needed
+        let needs = ProviderCapabilities { streaming: false, ..ProviderCapabilities::required_by(request) };
+        let providers = self.capable_providers(&request.model, &needs).await?;
+        let metrics = self.metrics.read().await;
+
+        let mut ranked = Vec::new();
+        for (name, provider) in &providers {
+            // Rates count completed requests only; providers without successes have no latency to go by
+            let (failure_rate, latency) = match metrics.get(name) {
+                Some(m) if m.requests_total > m.requests_failed + m.requests_cancelled => (
+                    m.requests_failed as f64 / (m.requests_total - m.requests_cancelled) as f64,
+                    m.response_time_avg,
+                ),
+                Some(m) if m.requests_failed > 0 => (1.0, f64::MAX),
+                _ => (0.0, f64::MAX),
+            };
+            if failure_rate > config.max_failure_rate {
+                debug!("Not racing unhealthy provider {} (failure rate {:.2})", name, failure_rate);
+                continue;
+            }
+            let cost = provider.get_model_pricing(&request.model)
+                .map(|price_per_1k| price_per_1k * Self::estimate_tokens(request) as f64 / 1000.0)
+                .unwrap_or(0.0);
+            ranked.push((name.clone(), failure_rate, latency, cost));
+        }
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.2.total_cmp(&b.2)).then_with(|| a.0.cmp(&b.0)));
+
+        let mut contenders = Vec::new();
+        let mut total_cost = 0.0;
+        for (name, _, _, cost) in ranked {
+            if contenders.len() >= config.max_providers.max(1) {
+                break;
+            }
+            let within_cap = config.max_estimated_cost.is_none_or(|cap| total_cost + cost <= cap);
+            if !contenders.is_empty() && !within_cap {
+                continue;
+            }
+            total_cost += cost;
+            contenders.push(name);
+        }
+        Ok(contenders)
+    }
+
+    /// Rough token count of a request: prompt tokens plus the completion budget
+    fn estimate_tokens(request: &ChatRequest) -> u64 {
+        Self::estimate_prompt_tokens(request) + request.max_tokens.unwrap_or(256) as u64
+    }
+
+    /// Rough prompt token count: prompt characters / 4
+    fn estimate_prompt_tokens(request: &ChatRequest) -> u64 {
+        let prompt_chars: usize = request.messages.iter().map(|m| m.content.text().len()).sum();
+        (prompt_chars / 4) as u64
+    }
+
+    /// Tokens a chat request takes from a rate limit: prompt plus the requested completion
+    fn estimate_paced_tokens(request: &ChatRequest) -> u64 {
+        Self::estimate_prompt_tokens(request) + request.max_tokens.unwrap_or(0) as u64
+    }
+
+    /// Tokens a text completion takes from a rate limit
+    fn estimate_completion_tokens(request: &CompletionRequest) -> u64 {
+        (request.prompt.len() / 4) as u64 + request.max_tokens.unwrap_or(0) as u64
+    }
+
+    /// Tokens an embedding request takes from a rate limit
+    fn estimate_embedding_tokens(request: &EmbeddingRequest) -> u64 {
+        let input_chars: usize = request.input.iter().map(|text| text.len()).sum();
+        (input_chars / 4) as u64
+    }
+
+    /// Streaming chat completion with automatic provider selection
+    ///
+    /// The prompt is moderated before the stream is opened; streamed
+    /// completions are passed through unmoderated.
+    pub async fn chat_completion_stream(&self, mut request: ChatRequest) -> AiResult<ChatCompletionStream> {
+        request.model = self.resolve_model(&request.model).await;
+        let provider_name = self.select_provider_for_model(&request.model, Self::estimate_paced_tokens(&request), ProviderCapabilities::required_by(&request)).await?;
+        let mut context_fit = self.fit_to_context(&mut request).await?.if_trimmed();
+        let (stream, _prompt_flags) = self.open_chat_stream(&provider_name, request).await?;
+        Ok(stream
+            .map(move |chunk| chunk.map(|chunk| ChatCompletionChunk { context_fit: context_fit.take(), ..chunk }))
+            .boxed())
+    }
+
+    /// Chat completion streamed from the provider and collected into one response
+    ///
+    /// Deltas are concatenated per choice and usage is summed over all chunks,
+    /// so callers get a complete [`ChatResponse`] without handling chunks.
+    pub async fn chat_completion_collecting(&self, mut request: ChatRequest) -> AiResult<ChatResponse> {
+        request.model = self.resolve_model(&request.model).await;
+        let provider_name = self.select_provider_for_model(&request.model, Self::estimate_paced_tokens(&request), ProviderCapabilities::required_by(&request)).await?;
+        let context_fit = self.fit_to_context(&mut request).await?.if_trimmed();
+        let (mut stream, prompt_flags) = self.open_chat_stream(&provider_name, request).await?;
+
+        let mut response = ChatResponse {
+            id: String::new(),
+            object: "chat.completion".to_string(),
+            created: 0,
+            model: String::new(),
+            choices: Vec::new(),
+            usage: None,
+            moderation_flags: prompt_flags,
+            context_fit,
+        };
+        let mut choices: BTreeMap<u32, (Option<MessageRole>, String, Option<String>)> = BTreeMap::new(); // index -> (role, content, finish reason)
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            if response.id.is_empty() {
+                response.id = chunk.id;
+                response.created = chunk.created;
+                response.model = chunk.model;
+            }
+
+            for choice in chunk.choices {
+                let (role, content, finish_reason) = choices.entry(choice.index).or_default();
+                if choice.delta.role.is_some() {
+                    *role = choice.delta.role;
+                }
+                if let Some(delta) = choice.delta.content {
+                    content.push_str(&delta);
+                }
+                if choice.finish_reason.is_some() {
+                    *finish_reason = choice.finish_reason;
+                }
+            }
+
+            if let Some(usage) = chunk.usage {
+                response.usage.get_or_insert_with(Usage::default).add(&usage);
+            }
+        }
+
+        response.choices = choices.into_iter()
+            .map(|(index, (role, content, finish_reason))| ChatChoice {
+                index,
+                message: ChatMessage::new(role.unwrap_or(MessageRole::Assistant), content),
+                finish_reason,
+            })
+            .collect();
+
+        if let Some(moderation) = &self.moderation {
+            moderation.moderate_chat_response(&mut response).await?;
+        }
+        Ok(response)
+    }
+
+    /// Open a chat stream on a provider, returning it with the prompt's moderation flags
+    async fn open_chat_stream(&self, provider_name: &str, mut request: ChatRequest) -> AiResult<(ChatCompletionStream, Vec<ModerationFlag>)> {
+        let provider = self.provider(provider_name).await?;
+
+        // Blocked prompts never reach the provider
+        let prompt_flags = match &self.moderation {
+            Some(moderation) => moderation.moderate_chat_request(&mut request).await?,
+            None => Vec::new(),
+        };
+
+        let request = provider.transform_request(fit_response_format(request, provider.supports_json_mode()));
+        self.pace(provider_name, Self::estimate_paced_tokens(&request)).await?;
+        let permit = self.acquire_slot(provider_name, request.priority).await?;
+
+        {
+            let mut metrics = self.metrics.write().await;
+            let provider_metrics = metrics.get_mut(provider_name).unwrap();
+            provider_metrics.requests_total += 1;
+            provider_metrics.last_request_at = Some(std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64);
+        }
+
+        let start_time = std::time::Instant::now();
+        match provider.chat_completion_stream(&request).await {
+            Ok(stream) => Ok((self.record_stream(provider_name, stream, permit, start_time), prompt_flags)),
+            Err(e) => {
+                if let Some(metrics) = self.metrics.write().await.get_mut(provider_name) {
+                    metrics.requests_failed += 1;
+                }
+                error!("Chat completion stream failed: {:?}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Wrap a provider stream so its outcome is recorded in the provider's metrics
+    ///
+    /// Latency and tokens are recorded when the stream ends, a failure when it
+    /// yields an error, after which it ends. The concurrency slot is held
+    /// until the stream is dropped.
+    fn record_stream(
+        &self,
+        provider_name: &str,
+        stream: ChatCompletionStream,
+        permit: Option<PriorityPermit>,
+        start_time: std::time::Instant,
+    ) -> ChatCompletionStream {
+        let metrics = self.metrics.clone();
+        let provider_name = provider_name.to_string();
+
+        futures::stream::unfold((stream, permit, 0u64, false), move |(mut stream, permit, tokens, failed)| {
+            let metrics = metrics.clone();
+            let provider_name = provider_name.clone();
+            async move {
+                if failed {
+                    return None;
+                }
+                match stream.next().await {
+                    Some(Ok(chunk)) => {
+                        let tokens = tokens + chunk.usage.as_ref().map_or(0, |usage| usage.total_tokens as u64);
+                        Some((Ok(chunk), (stream, permit, tokens, false)))
+                    }
+                    Some(Err(e)) => {
+                        if let Some(metrics) = metrics.write().await.get_mut(&provider_name) {
+                            metrics.requests_failed += 1;
+                        }
+                        error!("Chat completion stream failed mid-stream: {:?}", e);
+                        Some((Err(e), (stream, permit, tokens, true)))
+                    }
+                    None => {
+                        let elapsed = start_time.elapsed().as_millis() as f64;
+                        if let Some(metrics) = metrics.write().await.get_mut(&provider_name) {
+                            metrics.response_time_avg = (metrics.response_time_avg + elapsed) / 2.0;
+                            metrics.tokens_used += tokens;
+                        }
+                        None
+                    }
+                }
+            }
+        })
+        .boxed()
+    }
+
     /// Text completion
-    pub async fn text_completion(&self, request: CompletionRequest) -> AiResult<CompletionResponse> {
-        let provider_name = self.select_provider_for_model(&request.model).await?;
+    pub async fn text_completion(&self, mut request: CompletionRequest) -> AiResult<CompletionResponse> {
+        request.model = self.resolve_model(&request.model).await;
+        let provider_name = self.select_provider_for_model(&request.model, Self::estimate_completion_tokens(&request), ProviderCapabilities::default()).await?;
         self.text_completion_with_provider(&provider_name, request).await
     }
 
     /// Text completion with specific provider
-    pub async fn text_completion_with_provider(&self, provider_name: &str, request: CompletionRequest) -> AiResult<CompletionResponse> {
-        let providers = self.providers.read().await;
-        let provider = providers.get(provider_name)
-            .ok_or_else(|| AiError::Config(format!("Provider '{}' not found", provider_name)))?;
+    pub async fn text_completion_with_provider(&self, provider_name: &str, mut request: CompletionRequest) -> AiResult<CompletionResponse> {
+        let provider = self.provider(provider_name).await?;
+
+        let prompt_flags = match &self.moderation {
+            Some(moderation) => moderation.moderate_completion_request(&mut request).await?,
+            None => Vec::new(),
+        };
+
+        self.pace(provider_name, Self::estimate_completion_tokens(&request)).await?;
+        let _permit = self.acquire_slot(provider_name, RequestPriority::default()).await?;
+        let mut response = provider.text_completion(&request).await?;
 
-        provider.text_completion(&request).await
+        response.moderation_flags.extend(prompt_flags);
+        if let Some(moderation) = &self.moderation {
+            moderation.moderate_completion_response(&mut response).await?;
+        }
+        Ok(response)
     }
 
     /// Create embeddings
-    pub async fn create_embeddings(&self, request: EmbeddingRequest) -> AiResult<EmbeddingResponse> {
-        let provider_name = self.select_provider_for_model(&request.model).await?;
+    pub async fn create_embeddings(&self, mut request: EmbeddingRequest) -> AiResult<EmbeddingResponse> {
+        request.model = self.resolve_model(&request.model).await;
+        let provider_name = self.select_provider_for_model(&request.model, Self::estimate_embedding_tokens(&request), ProviderCapabilities::for_embeddings()).await?;
         self.create_embeddings_with_provider(&provider_name, request).await
     }
 
+    /// Chat completion abandoned with [`AiError::Cancelled`] once `cancellation` fires
+    ///
+    /// The in-flight provider call is dropped, so callers whose client went
+    /// away or timed out stop spending provider tokens.
+    pub async fn chat_completion_cancellable(&self, request: ChatRequest, cancellation: &CancellationToken) -> AiResult<ChatResponse> {
+        Self::until_cancelled(cancellation, self.chat_completion(request)).await
+    }
+
+    /// Create embeddings, abandoned with [`AiError::Cancelled`] once `cancellation` fires
+    pub async fn create_embeddings_cancellable(&self, request: EmbeddingRequest, cancellation: &CancellationToken) -> AiResult<EmbeddingResponse> {
+        Self::until_cancelled(cancellation, self.create_embeddings(request)).await
+    }
+
+    async fn until_cancelled<T>(cancellation: &CancellationToken, work: impl std::future::Future<Output = AiResult<T>>) -> AiResult<T> {
+        tokio::select! {
+            biased;
+            _ = cancellation.cancelled() => Err(AiError::Cancelled("Caller cancelled the request".to_string())),
+            result = work => result,
+        }
+    }
+
     /// Create embeddings with specific provider
     pub async fn create_embeddings_with_provider(&self, provider_name: &str, request: EmbeddingRequest) -> AiResult<EmbeddingResponse> {
-        let providers = self.providers.read().await;
-        let provider = providers.get(provider_name)
-            .ok_or_else(|| AiError::Config(format!("Provider '{}' not found", provider_name)))?;
+        let provider = self.provider(provider_name).await?;
 
+        self.pace(provider_name, Self::estimate_embedding_tokens(&request)).await?;
+        let _permit = self.acquire_slot(provider_name, RequestPriority::default()).await?;
         provider.create_embeddings(&request).await
     }
 
@@ -162,40 +1094,62 @@ impl AiBackendClient {
     }
 
     /// Select appropriate provider for a model
-    async fn select_provider_for_model(&self, model: &str) -> AiResult<String> {
-        let providers = self.providers.read().await;
+    ///
+    /// The default provider comes first, then any other provider supporting
+    /// the model and covering `needs`; providers whose rate limits cannot
+    /// take a request of `tokens` right now are passed over while another can.
+    /// Streaming is preferred rather than required: providers that stream
+    /// come first, and the others answer with a single chunk.
+    async fn select_provider_for_model(&self, model: &str, tokens: u64, needs: ProviderCapabilities) -> AiResult<String> {
+        let required = ProviderCapabilities { streaming: false, ..needs };
+        let mut candidates = self.capable_providers(model, &required).await?;
 
-        // First try default provider
-        if let Some(default) = &self.default_provider {
-            if let Some(provider) = providers.get(default) {
-                if provider.supports_model(model) {
-                    return Ok(default.clone());
-                }
-            }
+        // The default provider comes first
+        if let Some(position) = candidates.iter().position(|(name, _)| Some(name) == self.default_provider.as_ref()) {
+            let default = candidates.remove(position);
+            candidates.insert(0, default);
+        }
+        if needs.streaming {
+            candidates.sort_by_key(|(_, provider)| !provider.capabilities().streaming);
         }
+        let mut candidates: Vec<String> = candidates.into_iter().map(|(name, _)| name).collect();
 
-        // Find any provider that supports the model
-        for (name, provider) in providers.iter() {
-            if provider.supports_model(model) {
-                return Ok(name.clone());
+        let rate_limiters = self.rate_limiters.read().await;
+        for name in &candidates {
+            match rate_limiters.get(name) {
+                Some(limiter) if !limiter.has_capacity(tokens).await => continue,
+                _ => return Ok(name.clone()),
             }
         }
 
-        Err(AiError::ModelNotAvailable(format!("No provider supports model: {}", model)))
+        Ok(candidates.remove(0))
     }
 
     /// Health check for all providers
     pub async fn health_check(&self) -> HashMap<String, bool> {
-        let providers = self.providers.read().await;
-        let mut results = HashMap::new();
+        let providers = self.provider_snapshot().await;
+        // A provider is healthy when its model catalog can be fetched
+        let healthy = futures::future::join_all(
+            providers.iter().map(|(name, provider)| self.catalog.refresh(name, provider.as_ref())),
+        ).await;
 
-        for (name, provider) in providers.iter() {
-            // Simple health check - try to get available models
-            let is_healthy = !provider.available_models().is_empty();
-            results.insert(name.clone(), is_healthy);
-        }
+        providers.into_iter()
+            .zip(healthy)
+            .map(|((name, _), result)| (name, result.is_ok()))
+            .collect()
+    }
 
-        results
+    /// Health of every provider as of its last catalog refresh, without fetching
+    ///
+    /// `None` for providers whose catalog has not been fetched yet.
+    pub async fn cached_health(&self) -> HashMap<String, Option<bool>> {
+        let names: Vec<String> = self.providers.read().await.keys().cloned().collect();
+        let mut health = HashMap::new();
+        for name in names {
+            let available = self.catalog.is_available(&name).await;
+            health.insert(name, available);
+        }
+        health
     }
 }
 
@@ -208,7 +1162,7 @@ impl Default for AiBackendClient {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{AiProvider, MessageRole, MessageContent};
+    use crate::{AiProvider, MessageContent, MockListing, MockLog, MockProvider};
 
     #[tokio::test]
     async fn test_client_creation() {
@@ -230,6 +1184,8 @@ mod tests {
             timeout_seconds: 30,
             max_retries: 3,
             models: vec![],
+            deployments: HashMap::new(),
+            api_version: None,
         };
 
         client.add_provider("openai", config).await.unwrap();
@@ -260,6 +1216,8 @@ mod tests {
             timeout_seconds: 30,
             max_retries: 3,
             models: vec![],
+            deployments: HashMap::new(),
+            api_version: None,
         };
 
         client.add_provider("openai", config).await.unwrap();
@@ -275,12 +1233,7 @@ mod tests {
     #[tokio::test]
     async fn test_chat_request_creation() {
         let messages = vec![
-            crate::ChatMessage {
-                role: MessageRole::User,
-                content: MessageContent::Text("Hello!".to_string()),
-                name: None,
-                function_call: None,
-            }
+            crate::ChatMessage::user("Hello!")
         ];
 
         let request = ChatRequest {
@@ -292,15 +1245,732 @@ mod tests {
             stream: Some(false),
             functions: None,
             function_call: None,
+            tools: None,
             stop: None,
             presence_penalty: None,
             frequency_penalty: None,
             logit_bias: None,
             user: None,
+            response_format: None,
+            priority: RequestPriority::Interactive,
         };
 
         assert_eq!(request.messages.len(), 1);
         assert_eq!(request.model, "gpt-3.5-turbo");
         assert_eq!(request.temperature, Some(0.7));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_max_in_flight_queues_requests() {
+        let provider = MockProvider::new("slow", &["slow-model"]).with_delay(Duration::from_millis(100));
+        let log = provider.log();
+
+        let client = Arc::new(AiBackendClient::new());
+        client.register_provider("slow", provider.boxed()).await.unwrap();
+        client.set_max_in_flight("slow", 1).await.unwrap();
+
+        let request = ChatRequest {
+            model: "slow-model".to_string(),
+            ..Default::default()
+        };
+
+        let start = tokio::time::Instant::now();
+        let first = tokio::spawn({
+            let client = client.clone();
+            let request = request.clone();
+            async move { client.chat_completion(request).await.map(|_| start.elapsed()) }
+        });
+        // Paused time only moves once every task is idle, so the first request is in flight
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let second = tokio::spawn({
+            let client = client.clone();
+            async move { client.chat_completion(request).await.map(|_| start.elapsed()) }
+        });
+
+        // The second request is queued behind the first
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(client.get_metrics("slow").await.unwrap().queue_depth, 1);
+        assert_eq!(log.calls(), 1);
+
+        assert_eq!(first.await.unwrap().unwrap(), Duration::from_millis(100));
+        assert_eq!(second.await.unwrap().unwrap(), Duration::from_millis(200));
+        assert_eq!(log.peak_in_flight(), 1);
+
+        let metrics = client.get_metrics("slow").await.unwrap();
+        assert_eq!(metrics.queue_depth, 0);
+        assert_eq!(metrics.requests_total, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_interactive_request_jumps_batch_backlog() {
+        let client = Arc::new(AiBackendClient::new());
+        let provider = MockProvider::new("slow", &["slow-model"]).with_delay(Duration::from_millis(30));
+        let log = provider.log();
+        client.register_provider("slow", provider.boxed()).await.unwrap();
+        client.set_max_in_flight("slow", 1).await.unwrap();
+
+        // Priority arrives with the request body
+        let lanes = [("batch-1", "batch"), ("batch-2", "batch"), ("batch-3", "batch"), ("interactive", "interactive")];
+        let mut requests = Vec::new();
+        for (sent, (user, priority)) in lanes.into_iter().enumerate() {
+            let request: ChatRequest = serde_json::from_value(serde_json::json!({
+                "model": "slow-model",
+                "messages": [],
+                "user": user,
+                "priority": priority,
+            })).unwrap();
+            requests.push(tokio::spawn({
+                let client = client.clone();
+                async move { client.chat_completion(request).await }
+            }));
+
+            // Each request reaches the provider or its lane before the next is sent
+            while log.calls() as u64 + client.get_metrics("slow").await.unwrap().queue_depth <= sent as u64 {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        for request in requests {
+            request.await.unwrap().unwrap();
+        }
+        // Only the batch request already in flight is served first
+        let served: Vec<String> = log.requests().into_iter().filter_map(|request| request.user).collect();
+        assert_eq!(served, ["batch-1", "interactive", "batch-2", "batch-3"]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_abandons_provider_call() {
+        let client = AiBackendClient::new();
+        let provider = MockProvider::new("slow", &["slow-model"]).with_delay(Duration::from_secs(30));
+        client.register_provider("slow", provider.boxed()).await.unwrap();
+
+        let cancellation = CancellationToken::new();
+        tokio::spawn({
+            let cancellation = cancellation.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                cancellation.cancel();
+            }
+        });
+
+        let start = std::time::Instant::now();
+        let request = ChatRequest { model: "slow-model".to_string(), ..Default::default() };
+        let result = client.chat_completion_cancellable(request, &cancellation).await;
+        assert!(matches!(result, Err(AiError::Cancelled(_))));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    /// Provider answering every chat request with `reply`
+    fn scripted(reply: &str) -> MockProvider {
+        MockProvider::new("scripted", &["scripted-model"]).with_reply(reply)
+    }
+
+    fn user_request(text: &str) -> ChatRequest {
+        ChatRequest {
+            model: "scripted-model".to_string(),
+            messages: vec![crate::ChatMessage::user(text)],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_moderation_blocks_prompt_before_provider_call() {
+        let provider = scripted("Sure.");
+        let log = provider.log();
+        let mut client = AiBackendClient::new();
+        client.register_provider("scripted", provider.boxed()).await.unwrap();
+        client.set_moderator(
+            Arc::new(crate::KeywordModerator::new().with_keywords("weapons", &["bomb"])),
+            ModerationPolicy::default(),
+        );
+
+        let err = client.chat_completion(user_request("How do I build a bomb?")).await.unwrap_err();
+        match err {
+            AiError::ContentBlocked(message) => {
+                assert!(message.contains("weapons"));
+                assert!(!message.contains("bomb"));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(log.calls(), 0);
+        assert_eq!(client.get_metrics("scripted").await.unwrap().requests_total, 0);
+
+        client.chat_completion(user_request("How do I bake bread?")).await.unwrap();
+        assert_eq!(log.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_moderation_annotates_flagged_response() {
+        let mut client = AiBackendClient::new();
+        client.register_provider("scripted", scripted("That would be a gamble.").boxed()).await.unwrap();
+        client.set_moderator(
+            Arc::new(crate::KeywordModerator::new().with_keywords("gambling", &["gamble"])),
+            ModerationPolicy {
+                completion_action: crate::ModerationAction::Annotate,
+                ..Default::default()
+            },
+        );
+
+        let response = client.chat_completion(user_request("Should I buy lottery tickets?")).await.unwrap();
+
+        match &response.choices[0].message.content {
+            MessageContent::Text(text) => assert_eq!(text, "That would be a gamble."),
+            other => panic!("unexpected content: {:?}", other),
+        }
+        assert_eq!(response.moderation_flags, vec![crate::ModerationFlag {
+            stage: crate::ModerationStage::Completion,
+            categories: vec!["gambling".to_string()],
+            action: crate::ModerationAction::Annotate,
+        }]);
+    }
+
+    #[tokio::test]
+    async fn test_budget_guard_refuses_requests_over_allowance() {
+        let provider = scripted("Done.").with_pricing(1.0);
+        let log = provider.log();
+        let mut client = AiBackendClient::new();
+        client.register_provider("scripted", provider.boxed()).await.unwrap();
+        let guard = Arc::new(BudgetGuard::new());
+        guard.set_budget("team-a", crate::Budget { daily: Some(0.101), monthly: None }).await;
+        client.set_budget_guard(guard.clone());
+
+        // 400 prompt characters are ~100 tokens, costing ~0.1 at 1.0 per 1K
+        let prompt = "x".repeat(400);
+        client.chat_completion_tagged("team-a", user_request(&prompt)).await.unwrap();
+        assert_eq!(log.calls(), 1);
+        assert!(guard.remaining("team-a").await.unwrap() < 0.01);
+
+        // The next request no longer fits and never reaches the provider
+        let err = client.chat_completion_tagged("team-a", user_request(&prompt)).await.unwrap_err();
+        match err {
+            AiError::BudgetExceeded { tag, estimated_cost, remaining } => {
+                assert_eq!(tag, "team-a");
+                assert!((estimated_cost - 0.1).abs() < 1e-9);
+                assert!(remaining < 0.01);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(log.calls(), 1);
+
+        // Tags without a budget are not limited
+        client.chat_completion_tagged("team-b", user_request(&prompt)).await.unwrap();
+        assert_eq!(log.calls(), 2);
+    }
+
+    fn conversation(turns: usize) -> Vec<crate::ChatMessage> {
+        // 10 tokens of system prompt, 30 tokens per earlier turn, 10 tokens of question
+        let mut messages = vec![crate::ChatMessage::new(MessageRole::System, "s".repeat(40))];
+        for turn in 0..turns {
+            let role = if turn % 2 == 0 { MessageRole::User } else { MessageRole::Assistant };
+            messages.push(crate::ChatMessage::new(role, format!("{:<120}", turn)));
+        }
+        messages.push(crate::ChatMessage::new(MessageRole::User, "q".repeat(40)));
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_fit_to_context_trims_oldest_and_keeps_system() {
+        let mut client = AiBackendClient::new();
+        client.register_provider("scripted", scripted("They agreed on a plan.").boxed()).await.unwrap();
+        client.set_context_limit("scripted-model", 100).await;
+
+        // 170 prompt tokens, but only 80 fit next to the 20 completion tokens
+        let mut request = ChatRequest { messages: conversation(5), max_tokens: Some(20), ..user_request("") };
+        let fit = client.fit_to_context(&mut request).await.unwrap();
+        assert_eq!(fit, ContextFit { dropped_messages: 3, dropped_tokens: 90, summarized: false });
+        assert_eq!(request.messages.len(), 4);
+        assert_eq!(request.messages[0].role, MessageRole::System);
+        assert_eq!(request.messages[1].content.text().trim(), "3");
+        assert_eq!(request.messages[3].content.text(), "q".repeat(40));
+        assert!(!client.fit_to_context(&mut request).await.unwrap().is_trimmed());
+
+        // Summarizing keeps the system prompt first and puts the summary after it
+        client.set_context_fit_strategy(ContextFitStrategy::Summarize);
+        client.set_context_limit("scripted-model", 400).await;
+        let mut request = ChatRequest { messages: conversation(14), max_tokens: Some(20), ..user_request("") };
+        let fit = client.fit_to_context(&mut request).await.unwrap();
+        assert_eq!(fit, ContextFit { dropped_messages: 9, dropped_tokens: 270, summarized: true });
+        assert_eq!(request.messages[0].content.text(), "s".repeat(40));
+        assert!(request.messages[1].content.text().ends_with("They agreed on a plan."));
+        assert_eq!(request.messages[2].content.text().trim(), "9");
+        assert_eq!(request.messages.len(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_summaries_use_the_configured_summary_model() {
+        let expensive = scripted("Expensive summary.");
+        let log = expensive.log();
+        let summary = MockProvider::new("summary", &["cheap-model"]).with_reply("They agreed on a plan.");
+        let summary_log = summary.log();
+        let mut client = AiBackendClient::new();
+        client.register_provider("scripted", expensive.boxed()).await.unwrap();
+        client.register_provider("summary", summary.boxed()).await.unwrap();
+        client.set_context_fit_strategy(ContextFitStrategy::Summarize);
+        client.set_summary_model("cheap-model");
+        client.set_context_limit("scripted-model", 400).await;
+
+        let mut request = ChatRequest { messages: conversation(14), max_tokens: Some(20), ..user_request("") };
+        let fit = client.fit_to_context(&mut request).await.unwrap();
+        assert!(fit.summarized);
+
+        // The summarized conversation fits next to the completion budget
+        let prompt_tokens: u64 = request.messages.iter().map(crate::estimate_message_tokens).sum();
+        assert!(prompt_tokens <= 400 - 20);
+        assert!(request.messages[1].content.text().ends_with("They agreed on a plan."));
+
+        let summary_models: Vec<String> = summary_log.requests().into_iter().map(|request| request.model).collect();
+        assert_eq!(summary_models, vec!["cheap-model".to_string()]);
+        assert_eq!(log.calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_responses_report_how_the_conversation_was_fitted() {
+        let client = AiBackendClient::new();
+        client.register_provider("scripted", scripted("Done.").with_stream(&["Do", "ne."]).boxed()).await.unwrap();
+        client.set_context_limit("scripted-model", 100).await;
+
+        let request = ChatRequest { messages: conversation(5), max_tokens: Some(20), ..user_request("") };
+        let response = client.chat_completion(request.clone()).await.unwrap();
+        assert_eq!(response.context_fit, Some(ContextFit { dropped_messages: 3, dropped_tokens: 90, summarized: false }));
+
+        // Streams carry the fit on their first chunk
+        let chunks: Vec<_> = client.chat_completion_stream(request).await.unwrap().collect().await;
+        assert_eq!(chunks[0].as_ref().unwrap().context_fit.as_ref().map(|fit| fit.dropped_messages), Some(3));
+        assert!(chunks[1..].iter().all(|chunk| chunk.as_ref().unwrap().context_fit.is_none()));
+
+        // Conversations that fit report nothing
+        let response = client.chat_completion(user_request("hi")).await.unwrap();
+        assert!(response.context_fit.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_long_transcripts_are_summarized_in_chunks_fitting_the_summary_model() {
+        let summary = MockProvider::new("summary", &["cheap-model"]).with_replies(&["They met.", "They agreed on a plan."]);
+        let summary_log = summary.log();
+        let mut client = AiBackendClient::new();
+        client.register_provider("scripted", scripted("unused").boxed()).await.unwrap();
+        client.register_provider("summary", summary.boxed()).await.unwrap();
+        client.set_context_fit_strategy(ContextFitStrategy::Summarize);
+        client.set_summary_model("cheap-model");
+        client.set_context_limit("scripted-model", 400).await;
+        client.set_context_limit("cheap-model", 400).await;
+
+        // 270 tokens of dropped turns do not fit next to the summary reserve in 400
+        let mut request = ChatRequest { messages: conversation(14), max_tokens: Some(20), ..user_request("") };
+        let fit = client.fit_to_context(&mut request).await.unwrap();
+        assert!(fit.summarized);
+        assert!(request.messages[1].content.text().ends_with("They agreed on a plan."));
+
+        let requests = summary_log.requests();
+        assert_eq!(requests.len(), 2);
+        for summary_request in &requests {
+            assert!(AiBackendClient::estimate_paced_tokens(summary_request) <= 400);
+        }
+        assert!(requests[1].messages[1].content.text().starts_with("Summary so far: They met."));
+    }
+
+    #[tokio::test]
+    async fn test_completion_cache_serves_deterministic_requests() {
+        let provider = scripted("Paris.");
+        let log = provider.log();
+        let mut client = AiBackendClient::new();
+        client.register_provider("scripted", provider.boxed()).await.unwrap();
+        client.set_completion_cache(Arc::new(CompletionCache::default()));
+
+        let deterministic = ChatRequest { temperature: Some(0.0), ..user_request("Capital of France?") };
+        client.chat_completion(deterministic.clone()).await.unwrap();
+        let cached = client.chat_completion(ChatRequest { temperature: Some(0.0), ..user_request("  Capital of France? ") }).await.unwrap();
+        assert_eq!(cached.choices[0].message.content.text(), "Paris.");
+        assert_eq!(log.calls(), 1);
+        assert_eq!(client.get_metrics("scripted").await.unwrap().requests_total, 1);
+
+        // Sampled requests always reach the provider
+        for _ in 0..2 {
+            client.chat_completion(ChatRequest { temperature: Some(0.7), ..user_request("Capital of France?") }).await.unwrap();
+        }
+        assert_eq!(log.calls(), 3);
+
+        // Different parameters are a different request
+        client.chat_completion(ChatRequest { max_tokens: Some(5), ..deterministic }).await.unwrap();
+        assert_eq!(log.calls(), 4);
+    }
+
+    /// Provider streaming "Hello world" in three deltas
+    fn streaming() -> MockProvider {
+        MockProvider::new("streaming", &["streaming-model"])
+            .with_stream(&["Hel", "lo", " world"])
+            .with_usage(Usage { prompt_tokens: 5, completion_tokens: 3, total_tokens: 8, prompt_tokens_details: None })
+    }
+
+    #[tokio::test]
+    async fn test_collecting_aggregates_streamed_chunks() {
+        let client = AiBackendClient::new();
+        client.register_provider("streaming", streaming().boxed()).await.unwrap();
+
+        let response = client.chat_completion_collecting(ChatRequest {
+            model: "streaming-model".to_string(),
+            ..Default::default()
+        }).await.unwrap();
+
+        assert_eq!(response.id, "streaming-1");
+        assert_eq!(response.object, "chat.completion");
+        assert_eq!(response.choices.len(), 1);
+        assert_eq!(response.choices[0].message.role, MessageRole::Assistant);
+        assert_eq!(response.choices[0].message.content.text(), "Hello world");
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("stop"));
+
+        let usage = response.usage.unwrap();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens, usage.total_tokens), (5, 3, 8));
+        let metrics = client.get_metrics("streaming").await.unwrap();
+        assert_eq!((metrics.requests_total, metrics.requests_failed, metrics.tokens_used), (1, 0, 8));
+    }
+
+    #[tokio::test]
+    async fn test_stream_failure_is_recorded_and_ends_the_stream() {
+        let client = AiBackendClient::new();
+        client.register_provider("streaming", streaming().with_stream_error_after(1).boxed()).await.unwrap();
+
+        let request = ChatRequest { model: "streaming-model".to_string(), ..Default::default() };
+        let items: Vec<_> = client.chat_completion_stream(request).await.unwrap().collect().await;
+
+        // The chunk before the error is delivered, nothing after it
+        assert_eq!(items.len(), 2);
+        assert!(items[0].is_ok());
+        assert!(matches!(items[1], Err(AiError::Http(_))));
+
+        let metrics = client.get_metrics("streaming").await.unwrap();
+        assert_eq!((metrics.requests_total, metrics.requests_failed), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_race_returns_fastest_and_cancels_losers() {
+        let racer = |name: &str, delay| MockProvider::new(name, &["race-model"]).with_delay(delay).with_pricing(0.01);
+        let (fast, slow) = (racer("fast", Duration::from_millis(10)), racer("slow", Duration::from_secs(5)));
+        let (fast_log, slow_log) = (fast.log(), slow.log());
+
+        let client = AiBackendClient::new();
+        client.register_provider("fast", fast.boxed()).await.unwrap();
+        client.register_provider("slow", slow.boxed()).await.unwrap();
+
+        let request = ChatRequest {
+            model: "race-model".to_string(),
+            max_tokens: Some(1000),
+            ..Default::default()
+        };
+
+        let raced = tokio::time::timeout(
+            Duration::from_secs(1),
+            client.chat_completion_race(request.clone(), &RaceConfig::default()),
+        ).await.unwrap().unwrap();
+
+        assert_eq!(raced.winner, "fast");
+        assert_eq!(raced.response.id, "fast-1");
+        assert_eq!(raced.contenders.len(), 2);
+        assert_eq!(slow_log.cancelled(), 1);
+        assert_eq!(fast_log.cancelled(), 0);
+
+        // A cost cap covering one contender (~$0.01 each) races only the best ranked provider
+        let capped = client.chat_completion_race(request, &RaceConfig {
+            max_estimated_cost: Some(0.015),
+            ..RaceConfig::default()
+        }).await.unwrap();
+        assert_eq!(capped.contenders, vec!["fast".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_race_skips_unhealthy_providers_and_counts_failed_losers_once() {
+        let client = AiBackendClient::new();
+        let racer = |name: &str, delay| MockProvider::new(name, &["race-model"]).with_delay(delay);
+        client.register_provider("broken", racer("broken", Duration::ZERO).failing_with(AiError::ProviderUnavailable).boxed()).await.unwrap();
+        client.register_provider("fast", racer("fast", Duration::from_millis(10)).boxed()).await.unwrap();
+        client.register_provider("slow", racer("slow", Duration::from_secs(5)).boxed()).await.unwrap();
+
+        let request = ChatRequest { model: "race-model".to_string(), ..Default::default() };
+        let config = RaceConfig { max_providers: 3, ..RaceConfig::default() };
+        let raced = client.chat_completion_race(request.clone(), &config).await.unwrap();
+        assert_eq!(raced.winner, "fast");
+        assert_eq!(raced.contenders.len(), 3);
+
+        // The failed contender is a failure, not also a cancellation
+        let broken = client.get_metrics("broken").await.unwrap();
+        assert_eq!((broken.requests_failed, broken.requests_cancelled), (1, 0));
+        assert_eq!(client.get_metrics("slow").await.unwrap().requests_cancelled, 1);
+
+        // Having only failed, it is no longer raced
+        let raced = client.chat_completion_race(request, &config).await.unwrap();
+        assert!(!raced.contenders.contains(&"broken".to_string()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limits_pace_requests_and_steer_to_free_providers() {
+        let limits = RateLimits { requests_per_minute: Some(1), tokens_per_minute: None };
+
+        // With one provider, the second request waits for the bucket to refill
+        let mut client = AiBackendClient::new();
+        client.set_queue_timeout(Duration::from_secs(90));
+        client.register_provider("only", scripted("ok").boxed()).await.unwrap();
+        client.set_rate_limits("only", limits).await.unwrap();
+
+        let start = tokio::time::Instant::now();
+        client.chat_completion(user_request("one")).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        client.chat_completion(user_request("two")).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(60));
+
+        // but no longer than the queue timeout
+        client.set_queue_timeout(Duration::from_secs(30));
+        let start = tokio::time::Instant::now();
+        let error = client.chat_completion(user_request("three")).await.unwrap_err();
+        assert!(matches!(error, AiError::RateLimit(_)));
+        assert_eq!(start.elapsed(), Duration::from_secs(30));
+
+        // With a second provider, the request goes there instead of waiting
+        let (primary, backup) = (scripted("ok"), scripted("ok"));
+        let (primary_log, backup_log) = (primary.log(), backup.log());
+        let mut client = AiBackendClient::new();
+        client.register_provider("primary", primary.boxed()).await.unwrap();
+        client.register_provider("backup", backup.boxed()).await.unwrap();
+        client.set_default_provider("primary");
+        client.set_rate_limits("primary", limits).await.unwrap();
+
+        let start = tokio::time::Instant::now();
+        client.chat_completion(user_request("one")).await.unwrap();
+        client.chat_completion(user_request("two")).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        assert_eq!(primary_log.calls(), 1);
+        assert_eq!(backup_log.calls(), 1);
+
+        // A request too large for the token bucket is steered away too
+        let (primary, backup) = (scripted("ok"), scripted("ok"));
+        let (primary_log, backup_log) = (primary.log(), backup.log());
+        let mut client = AiBackendClient::new();
+        client.register_provider("primary", primary.boxed()).await.unwrap();
+        client.register_provider("backup", backup.boxed()).await.unwrap();
+        client.set_default_provider("primary");
+        client.set_rate_limits("primary", RateLimits { requests_per_minute: None, tokens_per_minute: Some(100) }).await.unwrap();
+
+        let large = ChatRequest { max_tokens: Some(100), ..user_request("one") };
+        client.chat_completion(large.clone()).await.unwrap();
+        client.chat_completion(large).await.unwrap();
+        assert_eq!((primary_log.calls(), backup_log.calls()), (1, 1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_catalog_refresh_blocks_neither_registration_nor_other_providers() {
+        let mut client = AiBackendClient::new();
+        client.set_model_catalog_refresh_timeout(Duration::from_secs(5));
+        let hanging = MockProvider::new("hanging", &["scripted-model"]).with_listing(MockListing::Hanging);
+        client.register_provider("hanging", hanging.boxed()).await.unwrap();
+        let client = Arc::new(client);
+
+        let pending = tokio::spawn({
+            let client = client.clone();
+            async move { client.chat_completion(user_request("one")).await }
+        });
+        tokio::task::yield_now().await;
+
+        // Registering does not wait for the refresh in progress
+        let register = client.register_provider("backup", scripted("ok").boxed());
+        tokio::time::timeout(Duration::from_secs(1), register).await.unwrap().unwrap();
+
+        // The hanging listing times out and counts as serving nothing
+        assert!(matches!(pending.await.unwrap(), Err(AiError::ModelNotAvailable(_))));
+        let start = tokio::time::Instant::now();
+        client.chat_completion(user_request("two")).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_aliases_resolve_before_routing_and_list_on_their_model() {
+        let provider = scripted("ok");
+        let log = provider.log();
+        let client = AiBackendClient::new();
+        client.register_provider("scripted", provider.boxed()).await.unwrap();
+        client.set_model_alias("default-chat", "scripted-model").await;
+
+        let request = ChatRequest { model: "default-chat".to_string(), ..user_request("hello") };
+        client.chat_completion(request).await.unwrap();
+        assert_eq!(log.requests()[0].model, "scripted-model");
+
+        let listings = client.list_models().await;
+        assert_eq!(listings.len(), 1);
+        assert_eq!(listings[0].id, "scripted-model");
+        assert_eq!(listings[0].aliases, vec!["default-chat".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_selection_skips_providers_lacking_needed_capabilities() {
+        let (plain, tooled) = (scripted("plain"), scripted("tooled").with_capabilities(ProviderCapabilities { tools: true, ..ProviderCapabilities::default() }));
+        let (plain_log, tooled_log) = (plain.log(), tooled.log());
+        let mut client = AiBackendClient::new();
+        client.register_provider("plain", plain.boxed()).await.unwrap();
+        client.register_provider("tooled", tooled.boxed()).await.unwrap();
+        client.set_default_provider("plain");
+
+        let mut request = user_request("look it up");
+        request.tools = Some(vec![crate::ToolSpec {
+            name: "lookup".to_string(),
+            description: None,
+            parameters: serde_json::json!({ "type": "object" }),
+        }]);
+        client.chat_completion(request.clone()).await.unwrap();
+        assert_eq!((plain_log.calls(), tooled_log.calls()), (0, 1));
+
+        // Streaming requests prefer a provider that streams
+        let streamer = scripted("streamer").with_capabilities(ProviderCapabilities::all());
+        let streamer_log = streamer.log();
+        client.register_provider("streamer", streamer.boxed()).await.unwrap();
+        request.stream = Some(true);
+        client.chat_completion_collecting(request.clone()).await.unwrap();
+        assert_eq!((tooled_log.calls(), streamer_log.calls()), (1, 1));
+
+        // Needs no provider covers are named
+        request.messages = vec![crate::ChatMessage {
+            content: MessageContent::MultiModal(vec![crate::ContentPart::ImageUrl {
+                image_url: crate::ImageUrl { url: "https://example.com/cat.png".to_string(), detail: None },
+            }]),
+            ..crate::ChatMessage::user("")
+        }];
+        client.remove_provider("streamer").await.unwrap();
+        let error = client.chat_completion(request).await.unwrap_err();
+        assert_eq!(error.to_string(), "Model not available: No provider for model scripted-model supports vision");
+    }
+
+    /// Provider answering with its name, or failing as overloaded, logging calls to `log`
+    fn chain_link(name: &str, fails: bool, log: &Arc<MockLog>) -> Box<dyn AiProviderTrait> {
+        let provider = MockProvider::new(name, &["scripted-model"]).with_reply(name).with_log(log);
+        match fails {
+            true => provider.failing_with(AiError::RateLimit).boxed(),
+            false => provider.boxed(),
+        }
+    }
+
+    fn reply_text(response: &ChatResponse) -> &str {
+        match &response.choices[0].message.content {
+            MessageContent::Text(text) => text,
+            _ => panic!("expected a text reply"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fallback_chain_is_walked_in_configured_order() {
+        let log = Arc::new(MockLog::default());
+        let client = AiBackendClient::new();
+        client.register_provider("a", chain_link("a", true, &log)).await.unwrap();
+        client.register_provider("b", chain_link("b", true, &log)).await.unwrap();
+        client.register_provider("c", chain_link("c", false, &log)).await.unwrap();
+        client.set_fallback_chain("scripted-model", vec!["b".to_string(), "a".to_string(), "c".to_string()]).await;
+
+        let response = client.chat_completion(user_request("hi")).await.unwrap();
+        assert_eq!(reply_text(&response), "c");
+        assert_eq!(log.providers(), vec!["b", "a", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_chain_stops_at_first_success() {
+        let log = Arc::new(MockLog::default());
+        let client = AiBackendClient::new();
+        client.register_provider("a", chain_link("a", true, &log)).await.unwrap();
+        client.register_provider("b", chain_link("b", false, &log)).await.unwrap();
+        client.register_provider("c", chain_link("c", false, &log)).await.unwrap();
+        client.set_fallback_chain("scripted-model", vec!["a".to_string(), "b".to_string(), "c".to_string()]).await;
+
+        let response = client.chat_completion(user_request("hi")).await.unwrap();
+        assert_eq!(reply_text(&response), "b");
+        assert_eq!(log.providers(), vec!["a", "b"]);
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct City {
+        name: String,
+        population: u64,
+    }
+
+    impl OutputSchema for City {
+        fn schema_name() -> String {
+            "city".to_string()
+        }
+
+        fn json_schema() -> serde_json::Value {
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "population": { "type": "integer", "minimum": 0 }
+                },
+                "required": ["name", "population"]
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_retries_until_valid_json() {
+        let client = AiBackendClient::new();
+        let replies = [
+            "Sure! Paris has about 2.1 million people.",
+            r#"{"name": "Paris", "population": "2.1 million"}"#,
+            "```json\n{\"name\": \"Paris\", \"population\": 2100000}\n```",
+        ];
+        let provider = MockProvider::new("json", &["scripted-model"]).with_replies(&replies).with_json_mode();
+        let log = provider.log();
+        client.register_provider("json", provider.boxed()).await.unwrap();
+
+        let city: City = client.structured_output(user_request("Largest city of France?")).await.unwrap();
+        assert_eq!(city, City { name: "Paris".to_string(), population: 2100000 });
+
+        let requests = log.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(matches!(
+            &requests[0].response_format,
+            Some(ResponseFormat::JsonSchema { json_schema }) if json_schema.name == "city" && !json_schema.strict
+        ));
+        // Each invalid reply is followed by a correction naming the problem
+        let last = &requests[2].messages;
+        assert_eq!(last.len(), 5);
+        assert!(last[2].content.text().contains("not valid JSON"));
+        assert!(last[4].content.text().contains("/population"));
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_gives_up_after_bounded_attempts() {
+        let mut client = AiBackendClient::new();
+        client.set_structured_output_attempts(2);
+        let replies = ["no", "still no", r#"{"name": "Paris", "population": 2100000}"#];
+        let provider = MockProvider::new("plain", &["scripted-model"]).with_replies(&replies);
+        let log = provider.log();
+        client.register_provider("plain", provider.boxed()).await.unwrap();
+
+        let result = client.structured_output::<City>(user_request("Largest city of France?")).await;
+        assert!(matches!(result, Err(AiError::Parse(_))));
+
+        // Without a JSON mode the schema is given in a system message instead
+        let requests = log.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].response_format.is_none());
+        assert_eq!(requests[0].messages[0].role, MessageRole::System);
+        assert!(requests[0].messages[0].content.text().contains("\"population\""));
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_adapts_to_the_fallback_provider_serving_it() {
+        let log = Arc::new(MockLog::default());
+        let client = AiBackendClient::new();
+        let primary = MockProvider::new("json", &["scripted-model"]).with_json_mode().with_log(&log).failing_with(AiError::RateLimit);
+        let backup = MockProvider::new("plain", &["scripted-model"])
+            .with_reply(r#"{"name": "Paris", "population": 2100000}"#)
+            .with_log(&log);
+        client.register_provider("json", primary.boxed()).await.unwrap();
+        client.register_provider("plain", backup.boxed()).await.unwrap();
+        client.set_fallback_chain("scripted-model", vec!["json".to_string(), "plain".to_string()]).await;
+
+        let city: City = client.structured_output(user_request("Largest city of France?")).await.unwrap();
+        assert_eq!(city.name, "Paris");
+
+        // The JSON-mode provider got the schema as a response format, the backup in a system message
+        let requests = log.requests();
+        assert_eq!(log.providers(), vec!["json", "plain"]);
+        assert!(matches!(requests[0].response_format, Some(ResponseFormat::JsonSchema { .. })));
+        assert!(requests[1].response_format.is_none());
+        assert!(requests[1].messages[0].content.text().contains("\"population\""));
+    }
 }
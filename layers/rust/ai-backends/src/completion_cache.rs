@@ -0,0 +1,205 @@
+//! Cache of deterministic chat completions
+//!
+//! A request with `temperature: 0` asks for the same answer every time, so
+//! its response can be reused for an identical request instead of calling
+//! the provider again. Requests are identical when their model, messages,
+//! sampling parameters, response format and `user` match, so responses are
+//! never shared across users; surrounding whitespace in text messages is
+//! ignored. Entries expire after the cache TTL, and the oldest entry is
+//! evicted once the cache is full.
+
+use crate::{ChatRequest, ChatResponse, MessageContent};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Cached response and when it was stored
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    response: ChatResponse,
+    stored_at: Instant,
+}
+
+/// Opt-in cache of chat completions for deterministic requests
+#[derive(Debug)]
+pub struct CompletionCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: RwLock<HashMap<String, CacheEntry>>,
+}
+
+impl CompletionCache {
+    /// Create a cache holding up to `max_entries` responses for `ttl` each
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a request's response may be cached
+    ///
+    /// Only non-streaming requests with temperature 0 are deterministic
+    /// enough to reuse.
+    pub fn is_cacheable(request: &ChatRequest) -> bool {
+        request.temperature == Some(0.0) && request.stream != Some(true)
+    }
+
+    /// Cached response for a request, if a fresh one exists
+    pub async fn get(&self, request: &ChatRequest) -> Option<ChatResponse> {
+        if !Self::is_cacheable(request) {
+            return None;
+        }
+
+        let key = Self::key(request);
+        let entries = self.entries.read().await;
+        entries.get(&key)
+            .filter(|entry| entry.stored_at.elapsed() < self.ttl)
+            .map(|entry| entry.response.clone())
+    }
+
+    /// Store the response of a cacheable request
+    pub async fn insert(&self, request: &ChatRequest, response: &ChatResponse) {
+        if !Self::is_cacheable(request) || self.max_entries == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.write().await;
+        entries.retain(|_, entry| entry.stored_at.elapsed() < self.ttl);
+        if entries.len() >= self.max_entries {
+            let oldest = entries.iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(Self::key(request), CacheEntry {
+            response: response.clone(),
+            stored_at: Instant::now(),
+        });
+    }
+
+    /// Number of cached responses, including expired ones not yet evicted
+    pub async fn len(&self) -> usize {
+        self.entries.read().await.len()
+    }
+
+    /// Whether nothing is cached
+    pub async fn is_empty(&self) -> bool {
+        self.entries.read().await.is_empty()
+    }
+
+    /// Drop every cached response
+    pub async fn clear(&self) {
+        self.entries.write().await.clear();
+    }
+
+    /// Cache key: the normalized request, its response format and user as canonical JSON
+    fn key(request: &ChatRequest) -> String {
+        let mut key = Self::normalized(request);
+        key["response_format"] = json!(request.response_format);
+        key["user"] = json!(request.user);
+        key.to_string()
+    }
+
+    /// The model, normalized messages and parameters other than temperature
+    pub(crate) fn normalized(request: &ChatRequest) -> serde_json::Value {
+        let messages: Vec<serde_json::Value> = request.messages.iter()
+            .map(|message| {
+                let content = match &message.content {
+                    MessageContent::Text(text) => json!(text.trim()),
+                    content => json!(content),
+                };
+                json!({
+                    "role": message.role,
+                    "content": content,
+                    "name": message.name,
+                    "function_call": message.function_call,
+                    "tool_calls": message.tool_calls,
+                })
+            })
+            .collect();
+
+        // HashMap order is unstable, so logit biases are keyed in sorted order
+        let logit_bias = request.logit_bias.as_ref()
+            .map(|bias| bias.iter().collect::<BTreeMap<_, _>>());
+
+        json!({
+            "model": request.model,
+            "messages": messages,
+            "top_p": request.top_p,
+            "max_tokens": request.max_tokens,
+            "stop": request.stop,
+            "presence_penalty": request.presence_penalty,
+            "frequency_penalty": request.frequency_penalty,
+            "logit_bias": logit_bias,
+            "functions": request.functions,
+            "function_call": request.function_call,
+            "tools": request.tools,
+        })
+    }
+}
+
+impl Default for CompletionCache {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(300), 1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChatMessage;
+
+    fn request(text: &str) -> ChatRequest {
+        ChatRequest {
+            model: "model".to_string(),
+            messages: vec![ChatMessage::user(text)],
+            temperature: Some(0.0),
+            ..Default::default()
+        }
+    }
+
+    fn response(id: &str) -> ChatResponse {
+        ChatResponse {
+            id: id.to_string(),
+            object: "chat.completion".to_string(),
+            created: 0,
+            model: "model".to_string(),
+            choices: vec![],
+            usage: None,
+            moderation_flags: vec![],
+            context_fit: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_expires_and_evicts_oldest() {
+        let cache = CompletionCache::new(Duration::from_millis(50), 2);
+        cache.insert(&request("a"), &response("a")).await;
+        cache.insert(&request("b"), &response("b")).await;
+        cache.insert(&request("c"), &response("c")).await;
+
+        assert!(cache.get(&request("a")).await.is_none());
+        assert_eq!(cache.get(&request("b")).await.unwrap().id, "b");
+        assert_eq!(cache.len().await, 2);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(cache.get(&request("c")).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_responses_are_not_shared_across_users() {
+        let cache = CompletionCache::default();
+        let for_user = |user: &str| ChatRequest { user: Some(user.to_string()), ..request("a") };
+        cache.insert(&for_user("alice"), &response("alice")).await;
+
+        assert_eq!(cache.get(&for_user("alice")).await.unwrap().id, "alice");
+        assert!(cache.get(&for_user("bob")).await.is_none());
+        assert!(cache.get(&request("a")).await.is_none());
+    }
+}
@@ -0,0 +1,89 @@
+//! Fitting conversations into a model's context window
+//!
+//! When a conversation plus the completion budget is larger than the model's
+//! context window, the oldest messages are dropped until it fits. The latest
+//! message is never dropped; depending on the [`ContextFitStrategy`], system
+//! messages are kept and the dropped turns are replaced by a summary.
+
+use crate::{AiError, AiResult, ChatMessage, MessageRole};
+use serde::{Deserialize, Serialize};
+
+/// How an over-long conversation is shortened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContextFitStrategy {
+    /// Drop the oldest messages, system messages included
+    DropOldest,
+    /// Drop the oldest messages but keep every system message
+    #[default]
+    KeepSystem,
+    /// Like `KeepSystem`, replacing the dropped messages with a summary
+    Summarize,
+}
+
+/// What was removed to fit a conversation into the context window
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextFit {
+    /// Number of messages removed from the conversation
+    pub dropped_messages: usize,
+    /// Estimated prompt tokens removed
+    pub dropped_tokens: u64,
+    /// Whether the removed messages were replaced by a summary
+    pub summarized: bool,
+}
+
+impl ContextFit {
+    /// Whether the conversation was changed
+    pub fn is_trimmed(&self) -> bool {
+        self.dropped_messages > 0
+    }
+
+    /// The fit, if the conversation was changed, for reporting on a response
+    pub fn if_trimmed(self) -> Option<Self> {
+        Some(self).filter(Self::is_trimmed)
+    }
+}
+
+/// Rough token count of a message: characters / 4
+pub fn estimate_message_tokens(message: &ChatMessage) -> u64 {
+    (message.content.text().len() / 4) as u64
+}
+
+/// Drop the oldest messages until the conversation is at most `budget` tokens
+///
+/// Returns the dropped messages, oldest first. Fails when the conversation
+/// cannot be brought under budget without dropping protected messages.
+pub fn trim_to_budget(messages: &mut Vec<ChatMessage>, budget: u64, keep_system: bool) -> AiResult<Vec<ChatMessage>> {
+    let mut total: u64 = messages.iter().map(estimate_message_tokens).sum();
+    let last = messages.len().saturating_sub(1);
+
+    let mut drop = vec![false; messages.len()];
+    for (index, message) in messages.iter().enumerate() {
+        if total <= budget {
+            break;
+        }
+        if index == last || (keep_system && message.role == MessageRole::System) {
+            continue;
+        }
+        drop[index] = true;
+        total -= estimate_message_tokens(message);
+    }
+
+    if total > budget {
+        return Err(AiError::InvalidRequest(format!(
+            "Conversation needs {} tokens after trimming but only {} fit in the context window",
+            total, budget
+        )));
+    }
+
+    let mut dropped = Vec::new();
+    let mut kept = Vec::with_capacity(messages.len());
+    for (message, drop) in messages.drain(..).zip(drop) {
+        if drop {
+            dropped.push(message);
+        } else {
+            kept.push(message);
+        }
+    }
+    *messages = kept;
+    Ok(dropped)
+}
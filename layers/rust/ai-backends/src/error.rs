@@ -1,9 +1,10 @@
 //! Error types for Sira AI Backends
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// AI backend error types
-#[derive(Debug, Error)]
+#[derive(Debug, Error, Serialize, Deserialize)]
 pub enum AiError {
     #[error("HTTP error: {0}")]
     Http(String),
@@ -38,10 +39,67 @@ pub enum AiError {
     #[error("Quota exceeded: {0}")]
     QuotaExceeded(String),
 
+    #[error("Content blocked: {0}")]
+    ContentBlocked(String),
+
+    #[error("Context length exceeded: {0}")]
+    ContextLengthExceeded(String),
+
+    #[error("Provider unavailable: {0}")]
+    ProviderUnavailable(String),
+
+    #[error("Request cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Budget exceeded for '{tag}': estimated cost {estimated_cost:.4} exceeds remaining {remaining:.4}")]
+    BudgetExceeded { tag: String, estimated_cost: f64, remaining: f64 },
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
 
+impl AiError {
+    /// Whether another attempt, possibly on another provider, may succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            AiError::Http(_)
+                | AiError::Api(_)
+                | AiError::RateLimit(_)
+                | AiError::Provider { .. }
+                | AiError::ProviderUnavailable(_)
+                | AiError::Timeout(_)
+        )
+    }
+
+    /// Normalized category of the error, for branching on it
+    pub fn kind(&self) -> AiErrorKind {
+        match self {
+            AiError::RateLimit(_) | AiError::QuotaExceeded(_) => AiErrorKind::RateLimited,
+            AiError::ContextLengthExceeded(_) => AiErrorKind::ContextLengthExceeded,
+            AiError::ContentBlocked(_) => AiErrorKind::ContentFiltered,
+            AiError::InvalidRequest(_) | AiError::ModelNotAvailable(_) => AiErrorKind::InvalidRequest,
+            AiError::Http(_) | AiError::ProviderUnavailable(_) => AiErrorKind::ProviderUnavailable,
+            AiError::Timeout(_) => AiErrorKind::Timeout,
+            AiError::Auth(_) => AiErrorKind::Authentication,
+            _ => AiErrorKind::Other,
+        }
+    }
+}
+
+/// Provider-independent category of an [`AiError`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiErrorKind {
+    RateLimited,
+    ContextLengthExceeded,
+    ContentFiltered,
+    InvalidRequest,
+    ProviderUnavailable,
+    Timeout,
+    Authentication,
+    Other,
+}
+
 /// API response status
 #[derive(Debug, Clone)]
 pub enum ApiStatus {
@@ -59,8 +117,7 @@ impl ApiStatus {
     pub fn from_code(code: u16) -> Self {
         match code {
             200..=299 => ApiStatus::Success,
-            401 => ApiStatus::AuthenticationFailed,
-            403 => ApiStatus::QuotaExceeded,
+            401 | 403 => ApiStatus::AuthenticationFailed,
             404 => ApiStatus::ModelNotFound,
             400 => ApiStatus::InvalidRequest,
             429 => ApiStatus::RateLimited,
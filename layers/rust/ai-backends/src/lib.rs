@@ -8,6 +8,20 @@ pub mod providers;
 pub mod router;
 pub mod load_balancer;
 pub mod client;
+pub mod tool_calling;
+pub mod moderation;
+pub mod model_catalog;
+pub mod budget;
+pub mod context_window;
+pub mod completion_cache;
+pub mod rate_limit;
+pub mod taxonomy;
+pub mod structured_output;
+pub mod capabilities;
+pub mod priority;
+pub mod record_replay;
+#[cfg(any(test, feature = "testing"))]
+pub mod mock_provider;
 
 /// Result type alias for AI backend operations
 pub type AiResult<T> = Result<T, AiError>;
@@ -19,3 +33,17 @@ pub use error::*;
 pub use types::*;
 pub use providers::*;
 pub use client::*;
+pub use tool_calling::*;
+pub use moderation::*;
+pub use model_catalog::*;
+pub use budget::*;
+pub use context_window::*;
+pub use completion_cache::*;
+pub use rate_limit::*;
+pub use taxonomy::*;
+pub use structured_output::*;
+pub use capabilities::*;
+pub use priority::*;
+pub use record_replay::*;
+#[cfg(any(test, feature = "testing"))]
+pub use mock_provider::*;
@@ -1,7 +1,7 @@
 //! Load balancing and failover mechanisms for AI backends
 
 use crate::{AiResult, AiError, AiProviderTrait, BackendMetrics, RoutingDecision};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{info, warn, error};
@@ -117,12 +117,31 @@ pub enum CircuitBreakerState {
     HalfOpen, // Testing if service recovered
 }
 
+/// State of a backend in the smooth weighted round-robin of one pool
+#[derive(Debug, Clone, Copy)]
+struct SmoothWeight {
+    /// Accumulated weight; the backend with the highest value is selected next
+    current_weight: i64,
+    /// Configured weight, lowered after failures and restored on selection
+    effective_weight: i64,
+}
+
+/// Rotation key of selections made across all backends rather than a pool
+const ALL_BACKENDS: &str = "";
+
 /// Load balancer with failover capabilities
+///
+/// Backends can be grouped into named pools, such as "fast" or "cheap", so
+/// operators can segment capacity. Selecting from a pool only considers its
+/// members, and round-robin and weighted rotation are kept per pool.
 pub struct LoadBalancer {
     backends: Arc<RwLock<HashMap<String, BackendInstance>>>,
+    pools: Arc<RwLock<HashMap<String, HashSet<String>>>>,
     config: LoadBalancerConfig,
     strategy: LoadBalancingStrategy,
-    round_robin_index: Arc<RwLock<usize>>,
+    round_robin_index: Arc<RwLock<HashMap<String, usize>>>,
+    /// Weighted rotation state keyed by pool and backend
+    smooth_weights: Arc<RwLock<HashMap<(String, String), SmoothWeight>>>,
     circuit_breakers: Arc<RwLock<HashMap<String, CircuitBreakerState>>>,
     running: Arc<RwLock<bool>>,
 }
@@ -132,9 +151,11 @@ impl LoadBalancer {
     pub fn new(strategy: LoadBalancingStrategy, config: LoadBalancerConfig) -> Self {
         Self {
             backends: Arc::new(RwLock::new(HashMap::new())),
+            pools: Arc::new(RwLock::new(HashMap::new())),
             config,
             strategy,
-            round_robin_index: Arc::new(RwLock::new(0)),
+            round_robin_index: Arc::new(RwLock::new(HashMap::new())),
+            smooth_weights: Arc::new(RwLock::new(HashMap::new())),
             circuit_breakers: Arc::new(RwLock::new(HashMap::new())),
             running: Arc::new(RwLock::new(false)),
         }
@@ -189,23 +210,103 @@ impl LoadBalancer {
         let mut circuit_breakers = self.circuit_breakers.write().await;
         circuit_breakers.remove(name);
 
+        self.smooth_weights.write().await.retain(|(_, backend), _| backend != name);
+
+        for members in self.pools.write().await.values_mut() {
+            members.remove(name);
+        }
+
         info!("Removed backend '{}' from load balancer", name);
         Ok(())
     }
 
+    /// Set the weight of a backend for weighted round-robin selection
+    pub async fn set_backend_weight(&self, name: &str, weight: u32) -> AiResult<()> {
+        let mut backends = self.backends.write().await;
+        let backend = backends.get_mut(name)
+            .ok_or_else(|| AiError::Config(format!("Backend '{}' not found", name)))?;
+        backend.weight = weight;
+
+        // Restart the backend's rotation in every pool from its new weight
+        self.smooth_weights.write().await.retain(|(_, backend), _| backend != name);
+
+        info!("Set weight of backend '{}' to {}", name, weight);
+        Ok(())
+    }
+
+    /// Define a named pool of backends, replacing any pool of the same name
+    ///
+    /// A backend may belong to several pools.
+    pub async fn create_pool(&self, pool: &str, backend_names: &[&str]) -> AiResult<()> {
+        if pool.is_empty() {
+            return Err(AiError::Config("Backend pool name must not be empty".to_string()));
+        }
+
+        let backends = self.backends.read().await;
+        if let Some(missing) = backend_names.iter().find(|name| !backends.contains_key(**name)) {
+            return Err(AiError::Config(format!("Backend '{}' not found", missing)));
+        }
+
+        let members = backend_names.iter().map(|name| name.to_string()).collect();
+        self.pools.write().await.insert(pool.to_string(), members);
+        self.round_robin_index.write().await.remove(pool);
+        self.smooth_weights.write().await.retain(|(weighted_pool, _), _| weighted_pool != pool);
+
+        info!("Defined backend pool '{}' with {} backends", pool, backend_names.len());
+        Ok(())
+    }
+
+    /// Remove a backend pool; its backends stay registered
+    pub async fn remove_pool(&self, pool: &str) -> AiResult<()> {
+        self.pools.write().await.remove(pool)
+            .ok_or_else(|| AiError::Config(format!("Backend pool '{}' not found", pool)))?;
+        self.round_robin_index.write().await.remove(pool);
+        self.smooth_weights.write().await.retain(|(weighted_pool, _), _| weighted_pool != pool);
+
+        info!("Removed backend pool '{}'", pool);
+        Ok(())
+    }
+
+    /// Names of the backends in a pool
+    pub async fn pool_members(&self, pool: &str) -> Option<Vec<String>> {
+        self.pools.read().await.get(pool).map(|members| members.iter().cloned().collect())
+    }
+
     /// Select a backend for request processing
     pub async fn select_backend(&self, model: &str, client_ip: Option<&str>) -> AiResult<String> {
+        self.select_from(None, model, client_ip).await
+    }
+
+    /// Select a backend from a named pool
+    ///
+    /// Only the pool's backends are considered, so an exhausted or unhealthy
+    /// pool fails rather than spilling over into other pools.
+    pub async fn select_backend_in_pool(&self, pool: &str, model: &str, client_ip: Option<&str>) -> AiResult<String> {
+        self.select_from(Some(pool), model, client_ip).await
+    }
+
+    async fn select_from(&self, pool: Option<&str>, model: &str, client_ip: Option<&str>) -> AiResult<String> {
+        let members = match pool {
+            Some(pool) => Some(self.pools.read().await.get(pool).cloned()
+                .ok_or_else(|| AiError::Config(format!("Backend pool '{}' not found", pool)))?),
+            None => None,
+        };
+
         let backends = self.backends.read().await;
         let circuit_breakers = self.circuit_breakers.read().await;
 
-        if backends.is_empty() {
-            return Err(AiError::Config("No backends available".to_string()));
+        if backends.is_empty() || members.as_ref().is_some_and(HashSet::is_empty) {
+            return Err(AiError::Config(match pool {
+                Some(pool) => format!("No backends in pool '{}'", pool),
+                None => "No backends available".to_string(),
+            }));
         }
 
         // Filter healthy backends that support the model
         let mut available_backends: Vec<&BackendInstance> = backends
             .values()
             .filter(|backend| {
+                members.as_ref().is_none_or(|members| members.contains(&backend.name)) &&
                 backend.health == BackendHealth::Healthy &&
                 backend.can_accept_connection() &&
                 backend.provider.supports_model(model) &&
@@ -214,13 +315,17 @@ impl LoadBalancer {
             .collect();
 
         if available_backends.is_empty() {
-            return Err(AiError::Config(format!("No healthy backends available for model '{}'", model)));
+            return Err(AiError::Config(match pool {
+                Some(pool) => format!("No healthy backends in pool '{}' for model '{}'", pool, model),
+                None => format!("No healthy backends available for model '{}'", model),
+            }));
         }
 
         // Apply load balancing strategy
+        let rotation = pool.unwrap_or(ALL_BACKENDS);
         let selected_backend = match self.strategy {
-            LoadBalancingStrategy::RoundRobin => self.select_round_robin(&available_backends).await,
-            LoadBalancingStrategy::WeightedRoundRobin => self.select_weighted_round_robin(&available_backends).await,
+            LoadBalancingStrategy::RoundRobin => self.select_round_robin(rotation, &available_backends).await,
+            LoadBalancingStrategy::WeightedRoundRobin => self.select_weighted_round_robin(rotation, &available_backends).await,
             LoadBalancingStrategy::LeastConnections => self.select_least_connections(&available_backends).await,
             LoadBalancingStrategy::Random => self.select_random(&available_backends).await,
             LoadBalancingStrategy::IpHash => self.select_ip_hash(&available_backends, client_ip).await,
@@ -243,8 +348,8 @@ impl LoadBalancer {
 
         if let Some(backend) = backends.get_mut(backend_name) {
             // Check circuit breaker
-            let circuit_breakers = self.circuit_breakers.read().await;
-            if *circuit_breakers.get(backend_name).unwrap_or(&CircuitBreakerState::Closed) == CircuitBreakerState::Open {
+            let state = self.circuit_breakers.read().await.get(backend_name).copied();
+            if state == Some(CircuitBreakerState::Open) {
                 return Err(AiError::Provider { provider: "CircuitBreaker".to_string(), message: format!("Circuit breaker open for backend '{}'", backend_name) });
             }
 
@@ -265,6 +370,14 @@ impl LoadBalancer {
                     // Failure - update health and potentially trigger failover
                     backend.update_health(BackendHealth::Degraded);
 
+                    // Send less weighted traffic to the backend, in every pool, until it recovers
+                    let penalty = (backend.weight / self.config.max_consecutive_failures.max(1)).max(1) as i64;
+                    for ((_, weighted), state) in self.smooth_weights.write().await.iter_mut() {
+                        if weighted == backend_name {
+                            state.effective_weight = (state.effective_weight - penalty).max(0);
+                        }
+                    }
+
                     if backend.consecutive_failures >= self.config.max_consecutive_failures {
                         backend.update_health(BackendHealth::Unhealthy);
                         warn!("Backend '{}' marked as unhealthy after {} consecutive failures",
@@ -300,6 +413,8 @@ impl LoadBalancer {
             tokens_used: 0, // Would be tracked separately
             response_time_avg: 0.0, // Would be tracked separately
             last_request_at: Some(backend.last_health_check),
+            queue_depth: 0,
+            requests_cancelled: 0,
         })
     }
 
@@ -314,33 +429,64 @@ impl LoadBalancer {
             .collect()
     }
 
-    /// Round-robin selection
-    async fn select_round_robin<'a>(&self, backends: &[&'a BackendInstance]) -> &'a BackendInstance {
-        let mut index = self.round_robin_index.write().await;
+    /// Get the statuses of the backends in a pool
+    pub async fn get_pool_statuses(&self, pool: &str) -> AiResult<HashMap<String, (BackendHealth, u32)>> {
+        let members = self.pools.read().await.get(pool).cloned()
+            .ok_or_else(|| AiError::Config(format!("Backend pool '{}' not found", pool)))?;
+
+        let mut statuses = self.get_backend_statuses().await;
+        statuses.retain(|name, _| members.contains(name));
+        Ok(statuses)
+    }
+
+    /// Round-robin selection, rotating separately for each pool
+    async fn select_round_robin<'a>(&self, pool: &str, backends: &[&'a BackendInstance]) -> &'a BackendInstance {
+        let mut indexes = self.round_robin_index.write().await;
+        let index = indexes.entry(pool.to_string()).or_insert(0);
         let selected = &backends[*index % backends.len()];
         *index = (*index + 1) % backends.len();
         selected
     }
 
-    /// Weighted round-robin selection
-    async fn select_weighted_round_robin<'a>(&self, backends: &[&'a BackendInstance]) -> &'a BackendInstance {
-        let total_weight: u32 = backends.iter().map(|b| b.weight).sum();
-        if total_weight == 0 {
-            return backends[0];
-        }
-
-        let mut index = self.round_robin_index.write().await;
-        let mut current_weight = 0u32;
+    /// Smooth weighted round-robin selection, rotating separately for each pool
+    ///
+    /// Every backend's current weight grows by its effective weight on each
+    /// selection; the backend with the highest current weight is chosen and
+    /// its current weight reduced by the total. This spreads picks evenly, so
+    /// weights 3:1 yield A A B A rather than bursts of A.
+    async fn select_weighted_round_robin<'a>(&self, pool: &str, backends: &[&'a BackendInstance]) -> &'a BackendInstance {
+        let mut smooth_weights = self.smooth_weights.write().await;
+        let mut total_weight = 0i64;
+        let mut selected: Option<(&'a BackendInstance, i64)> = None;
 
         for backend in backends {
-            current_weight += backend.weight;
-            if *index < current_weight as usize {
-                *index = (*index + 1) % total_weight as usize;
-                return backend;
+            let state = smooth_weights.entry((pool.to_string(), backend.name.clone())).or_insert(SmoothWeight {
+                current_weight: 0,
+                effective_weight: backend.weight as i64,
+            });
+
+            state.current_weight += state.effective_weight;
+            total_weight += state.effective_weight;
+
+            // Failed backends regain weight gradually as they keep being considered
+            if state.effective_weight < backend.weight as i64 {
+                state.effective_weight += 1;
+            }
+
+            if selected.is_none_or(|(_, best)| state.current_weight > best) {
+                selected = Some((backend, state.current_weight));
             }
         }
 
-        backends[0]
+        match selected {
+            Some((backend, _)) if total_weight > 0 => {
+                if let Some(state) = smooth_weights.get_mut(&(pool.to_string(), backend.name.clone())) {
+                    state.current_weight -= total_weight;
+                }
+                backend
+            }
+            _ => backends[0],
+        }
     }
 
     /// Least connections selection
@@ -445,6 +591,7 @@ impl Default for LoadBalancer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock_provider::MockProvider;
 
     #[tokio::test]
     async fn test_load_balancer_creation() {
@@ -458,4 +605,80 @@ mod tests {
         let result = lb.select_backend("gpt-3.5-turbo", None).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_weighted_round_robin_ratio() {
+        let lb = LoadBalancer::new(LoadBalancingStrategy::WeightedRoundRobin, LoadBalancerConfig::default());
+        lb.add_backend("heavy", MockProvider::serving_any_model("heavy").boxed(), 100).await.unwrap();
+        lb.add_backend("light", MockProvider::serving_any_model("light").boxed(), 100).await.unwrap();
+        lb.set_backend_weight("heavy", 3).await.unwrap();
+        assert!(lb.set_backend_weight("missing", 1).await.is_err());
+
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        let mut heavy_streak = 0;
+        let mut longest_heavy_streak = 0;
+        for _ in 0..1000 {
+            let selected = lb.select_backend("any-model", None).await.unwrap();
+            heavy_streak = if selected == "heavy" { heavy_streak + 1 } else { 0 };
+            longest_heavy_streak = longest_heavy_streak.max(heavy_streak);
+            *counts.entry(selected).or_default() += 1;
+        }
+
+        let ratio = counts["heavy"] as f64 / counts["light"] as f64;
+        assert!((ratio - 3.0).abs() < 0.05, "selection ratio {} is not 3:1", ratio);
+
+        // Smooth: the light backend is interleaved rather than starved in bursts
+        assert!(longest_heavy_streak <= 3);
+    }
+
+    #[tokio::test]
+    async fn test_weighted_rotation_is_kept_per_pool() {
+        let lb = LoadBalancer::new(LoadBalancingStrategy::WeightedRoundRobin, LoadBalancerConfig::default());
+        for name in ["shared", "fast", "cheap"] {
+            lb.add_backend(name, MockProvider::serving_any_model(name).boxed(), 100).await.unwrap();
+        }
+        lb.set_backend_weight("cheap", 9).await.unwrap();
+        lb.create_pool("fast", &["shared", "fast"]).await.unwrap();
+        lb.create_pool("cheap", &["shared", "cheap"]).await.unwrap();
+
+        // Rotating through the 1:9 pool builds up no credit for the shared backend elsewhere
+        for _ in 0..3 {
+            assert_eq!(lb.select_backend_in_pool("cheap", "any-model", None).await.unwrap(), "cheap");
+        }
+        let mut fast = Vec::new();
+        for _ in 0..4 {
+            fast.push(lb.select_backend_in_pool("fast", "any-model", None).await.unwrap());
+        }
+        assert!(fast.windows(2).all(|pair| pair[0] != pair[1]), "fast picked {:?}", fast);
+    }
+
+    #[tokio::test]
+    async fn test_pools_are_balanced_in_isolation() {
+        let lb = LoadBalancer::new(LoadBalancingStrategy::RoundRobin, LoadBalancerConfig::default());
+        for name in ["fast-1", "fast-2", "cheap-1"] {
+            lb.add_backend(name, MockProvider::serving_any_model(name).boxed(), 100).await.unwrap();
+        }
+        lb.create_pool("fast", &["fast-1", "fast-2"]).await.unwrap();
+        lb.create_pool("cheap", &["cheap-1"]).await.unwrap();
+        assert!(lb.create_pool("vision", &["missing"]).await.is_err());
+
+        let mut fast = HashSet::new();
+        for _ in 0..4 {
+            fast.insert(lb.select_backend_in_pool("fast", "any-model", None).await.unwrap());
+            assert_eq!(lb.select_backend_in_pool("cheap", "any-model", None).await.unwrap(), "cheap-1");
+        }
+        assert_eq!(fast, HashSet::from(["fast-1".to_string(), "fast-2".to_string()]));
+
+        // A failing pool does not spill over into the others
+        for name in ["fast-1", "fast-2"] {
+            let result: AiResult<()> = lb.execute_with_failover(name, |_| async {
+                Err(AiError::Unknown("down".to_string()))
+            }).await;
+            assert!(result.is_err());
+        }
+        assert!(lb.select_backend_in_pool("fast", "any-model", None).await.is_err());
+        assert_eq!(lb.select_backend_in_pool("cheap", "any-model", None).await.unwrap(), "cheap-1");
+        assert_eq!(lb.get_pool_statuses("fast").await.unwrap().len(), 2);
+        assert!(lb.select_backend_in_pool("vision", "any-model", None).await.is_err());
+    }
 }
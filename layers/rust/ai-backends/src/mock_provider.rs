@@ -0,0 +1,379 @@
+//! Configurable provider for tests
+//!
+//! [`MockProvider`] serves a fixed set of models and answers from scripted
+//! replies, optionally after a delay, failing, or streaming in deltas. What
+//! it is asked is recorded in a [`MockLog`] the test keeps a handle to,
+//! which several providers may share to observe the order they are called in.
+//!
+//! Compiled for this crate's tests and with the `testing` feature.
+
+use crate::{
+    AiError, AiProviderTrait, AiResult, ChatChoice, ChatChunkChoice, ChatCompletionChunk, ChatCompletionStream,
+    ChatDelta, ChatMessage, ChatRequest, ChatResponse, CompletionRequest, CompletionResponse, EmbeddingData,
+    EmbeddingRequest, EmbeddingResponse, MessageRole, ProviderCapabilities, Usage,
+};
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How a mock answers requests for its model list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockListing {
+    /// Its static models, unless the log is set to fail listings
+    Static,
+    /// Never answers
+    Hanging,
+}
+
+/// Requests received by mock providers
+#[derive(Debug, Default)]
+pub struct MockLog {
+    /// Chat requests with the name of the provider receiving them
+    requests: Mutex<Vec<(String, ChatRequest)>>,
+    embedding_batches: Mutex<Vec<usize>>,
+    calls: AtomicUsize,
+    listings: AtomicUsize,
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+    cancelled: AtomicUsize,
+    listing_fails: AtomicBool,
+}
+
+impl MockLog {
+    /// Chat and embedding calls received
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// Chat requests received, in order
+    pub fn requests(&self) -> Vec<ChatRequest> {
+        self.requests.lock().unwrap().iter().map(|(_, request)| request.clone()).collect()
+    }
+
+    /// Names of the providers that received chat requests, in order
+    pub fn providers(&self) -> Vec<String> {
+        self.requests.lock().unwrap().iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Number of inputs of every embedding request, in order
+    pub fn embedding_batches(&self) -> Vec<usize> {
+        self.embedding_batches.lock().unwrap().clone()
+    }
+
+    /// Model listings requested
+    pub fn listings(&self) -> usize {
+        self.listings.load(Ordering::SeqCst)
+    }
+
+    /// Most chat requests in flight at once
+    pub fn peak_in_flight(&self) -> usize {
+        self.peak_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Chat requests dropped before they were answered
+    pub fn cancelled(&self) -> usize {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Make model listings fail, or succeed again
+    pub fn set_listing_fails(&self, fails: bool) {
+        self.listing_fails.store(fails, Ordering::SeqCst);
+    }
+
+    /// Record a chat request, returning its call number
+    fn record(&self, provider: &str, request: &ChatRequest) -> usize {
+        self.requests.lock().unwrap().push((provider.to_string(), request.clone()));
+        self.calls.fetch_add(1, Ordering::SeqCst) + 1
+    }
+}
+
+/// Tracks a chat request in flight, counting it as cancelled if dropped unanswered
+struct InFlight<'a> {
+    log: &'a MockLog,
+    answered: bool,
+}
+
+impl<'a> InFlight<'a> {
+    fn enter(log: &'a MockLog) -> Self {
+        let current = log.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        log.peak_in_flight.fetch_max(current, Ordering::SeqCst);
+        Self { log, answered: false }
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.log.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if !self.answered {
+            self.log.cancelled.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Provider answering from a script, for tests
+pub struct MockProvider {
+    name: String,
+    /// Models served; `None` serves every model
+    models: Option<Vec<String>>,
+    reply: String,
+    /// Replies given before falling back to `reply`
+    replies: Mutex<VecDeque<String>>,
+    error: Option<fn(String) -> AiError>,
+    delay: Duration,
+    price_per_1k: Option<f64>,
+    json_mode: bool,
+    capabilities: ProviderCapabilities,
+    stream_deltas: Option<Vec<String>>,
+    /// Chunks streamed before the stream fails
+    stream_error_after: Option<usize>,
+    usage: Option<Usage>,
+    listing: MockListing,
+    log: Arc<MockLog>,
+}
+
+impl MockProvider {
+    /// Create a mock serving `models` and replying "ok"
+    pub fn new(name: impl Into<String>, models: &[&str]) -> Self {
+        Self {
+            name: name.into(),
+            models: Some(models.iter().map(|model| model.to_string()).collect()),
+            reply: "ok".to_string(),
+            replies: Mutex::new(VecDeque::new()),
+            error: None,
+            delay: Duration::ZERO,
+            price_per_1k: None,
+            json_mode: false,
+            capabilities: ProviderCapabilities { embeddings: true, ..ProviderCapabilities::default() },
+            stream_deltas: None,
+            stream_error_after: None,
+            usage: None,
+            listing: MockListing::Static,
+            log: Arc::new(MockLog::default()),
+        }
+    }
+
+    /// Create a mock serving every model, listing none
+    pub fn serving_any_model(name: impl Into<String>) -> Self {
+        Self { models: None, ..Self::new(name, &[]) }
+    }
+
+    /// Reply with `reply` once scripted replies run out
+    pub fn with_reply(mut self, reply: impl Into<String>) -> Self {
+        self.reply = reply.into();
+        self
+    }
+
+    /// Give `replies` in order before falling back to the default reply
+    pub fn with_replies(self, replies: &[&str]) -> Self {
+        self.replies.lock().unwrap().extend(replies.iter().map(|reply| reply.to_string()));
+        self
+    }
+
+    /// Fail every request with `error`, e.g. `AiError::RateLimit`
+    pub fn failing_with(mut self, error: fn(String) -> AiError) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// Take `delay` to answer every chat request
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_pricing(mut self, price_per_1k: f64) -> Self {
+        self.price_per_1k = Some(price_per_1k);
+        self
+    }
+
+    pub fn with_json_mode(mut self) -> Self {
+        self.json_mode = true;
+        self
+    }
+
+    pub fn with_capabilities(mut self, capabilities: ProviderCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Stream replies as `deltas`, reporting prompt usage on the first chunk and completion usage on the last
+    pub fn with_stream(mut self, deltas: &[&str]) -> Self {
+        self.stream_deltas = Some(deltas.iter().map(|delta| delta.to_string()).collect());
+        self
+    }
+
+    /// Fail streams after `chunks` chunks
+    pub fn with_stream_error_after(mut self, chunks: usize) -> Self {
+        self.stream_error_after = Some(chunks);
+        self
+    }
+
+    pub fn with_usage(mut self, usage: Usage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    pub fn with_listing(mut self, listing: MockListing) -> Self {
+        self.listing = listing;
+        self
+    }
+
+    /// Record into `log`, shared with other mocks
+    pub fn with_log(mut self, log: &Arc<MockLog>) -> Self {
+        self.log = log.clone();
+        self
+    }
+
+    /// Log of what the mock is asked
+    pub fn log(&self) -> Arc<MockLog> {
+        self.log.clone()
+    }
+
+    pub fn boxed(self) -> Box<dyn AiProviderTrait> {
+        Box::new(self)
+    }
+
+    fn failure(&self) -> AiResult<()> {
+        match self.error {
+            Some(error) => Err(error(format!("{} is failing", self.name))),
+            None => Ok(()),
+        }
+    }
+
+    fn next_reply(&self) -> String {
+        self.replies.lock().unwrap().pop_front().unwrap_or_else(|| self.reply.clone())
+    }
+
+    fn chunk(&self, request: &ChatRequest, call: usize, delta: ChatDelta, finish_reason: Option<&str>, usage: Option<Usage>) -> ChatCompletionChunk {
+        ChatCompletionChunk {
+            id: format!("{}-{}", self.name, call),
+            object: "chat.completion.chunk".to_string(),
+            created: call as u64,
+            model: request.model.clone(),
+            choices: vec![ChatChunkChoice { index: 0, delta, finish_reason: finish_reason.map(str::to_string) }],
+            usage,
+            context_fit: None,
+        }
+    }
+}
+
+#[async_trait]
+impl AiProviderTrait for MockProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn available_models(&self) -> Vec<String> {
+        self.models.clone().unwrap_or_default()
+    }
+
+    async fn list_models(&self) -> AiResult<Vec<String>> {
+        self.log.listings.fetch_add(1, Ordering::SeqCst);
+        if self.listing == MockListing::Hanging {
+            std::future::pending::<()>().await;
+        }
+        if self.log.listing_fails.load(Ordering::SeqCst) {
+            return Err(AiError::Http("connection refused".to_string()));
+        }
+        Ok(self.available_models())
+    }
+
+    async fn chat_completion(&self, request: &ChatRequest) -> AiResult<ChatResponse> {
+        let call = self.log.record(&self.name, request);
+        let mut in_flight = InFlight::enter(&self.log);
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+        in_flight.answered = true;
+        self.failure()?;
+
+        Ok(ChatResponse {
+            id: format!("{}-{}", self.name, call),
+            object: "chat.completion".to_string(),
+            created: call as u64,
+            model: request.model.clone(),
+            choices: vec![ChatChoice {
+                index: 0,
+                message: ChatMessage::assistant(self.next_reply()),
+                finish_reason: Some("stop".to_string()),
+            }],
+            usage: self.usage.clone(),
+            moderation_flags: vec![],
+            context_fit: None,
+        })
+    }
+
+    async fn chat_completion_stream(&self, request: &ChatRequest) -> AiResult<ChatCompletionStream> {
+        let Some(deltas) = &self.stream_deltas else {
+            let response = self.chat_completion(request).await?;
+            return Ok(stream::iter(ChatCompletionChunk::from_response(response).into_iter().map(Ok)).boxed());
+        };
+        let call = self.log.record(&self.name, request);
+        self.failure()?;
+
+        let last = deltas.len().saturating_sub(1);
+        let mut chunks: Vec<AiResult<ChatCompletionChunk>> = deltas.iter()
+            .enumerate()
+            .map(|(position, text)| {
+                let usage = self.usage.as_ref().and_then(|usage| match position {
+                    _ if last == 0 => Some(usage.clone()),
+                    0 => Some(Usage { prompt_tokens: usage.prompt_tokens, completion_tokens: 0, total_tokens: usage.prompt_tokens, prompt_tokens_details: None }),
+                    _ if position == last => Some(Usage { prompt_tokens: 0, completion_tokens: usage.completion_tokens, total_tokens: usage.completion_tokens, prompt_tokens_details: None }),
+                    _ => None,
+                });
+                let role = (position == 0).then_some(MessageRole::Assistant);
+                let finish_reason = (position == last).then_some("stop");
+                Ok(self.chunk(request, call, ChatDelta { role, content: Some(text.clone()) }, finish_reason, usage))
+            })
+            .collect();
+        if let Some(after) = self.stream_error_after {
+            chunks.truncate(after);
+            chunks.push(Err(AiError::Http(format!("{} dropped the stream", self.name))));
+        }
+        Ok(stream::iter(chunks).boxed())
+    }
+
+    async fn text_completion(&self, _request: &CompletionRequest) -> AiResult<CompletionResponse> {
+        Err(AiError::Unknown("not supported".to_string()))
+    }
+
+    /// Embeds each text as its length
+    async fn create_embeddings(&self, request: &EmbeddingRequest) -> AiResult<EmbeddingResponse> {
+        self.log.calls.fetch_add(1, Ordering::SeqCst);
+        self.log.embedding_batches.lock().unwrap().push(request.input.len());
+        self.failure()?;
+
+        let tokens = request.input.len() as u32;
+        Ok(EmbeddingResponse {
+            object: "list".to_string(),
+            data: request.input.iter().enumerate()
+                .map(|(index, text)| EmbeddingData {
+                    object: "embedding".to_string(),
+                    embedding: vec![text.len() as f32],
+                    index: index as u32,
+                })
+                .collect(),
+            model: request.model.clone(),
+            usage: Usage { prompt_tokens: tokens, completion_tokens: 0, total_tokens: tokens, prompt_tokens_details: None },
+        })
+    }
+
+    fn supports_model(&self, model: &str) -> bool {
+        self.models.as_ref().is_none_or(|models| models.iter().any(|served| served == model))
+    }
+
+    fn get_model_pricing(&self, _model: &str) -> Option<f64> {
+        self.price_per_1k
+    }
+
+    fn supports_json_mode(&self) -> bool {
+        self.json_mode
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        self.capabilities
+    }
+}
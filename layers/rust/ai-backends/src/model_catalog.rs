@@ -0,0 +1,200 @@
+//! Cached model catalogs per provider
+//!
+//! Asking a provider which models it serves can be slow, so the answer is
+//! cached per provider and refreshed once it is older than the catalog TTL.
+//! A provider whose refresh fails or times out is treated as serving no
+//! models until a later refresh succeeds. The catalog is the client's only
+//! source of which provider serves which model.
+
+use crate::{AiError, AiProviderTrait, AiResult, ProviderCapabilities};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+/// Catalog state of one provider
+#[derive(Debug, Clone)]
+struct CatalogEntry {
+    models: HashSet<String>,
+    refreshed_at: Instant,
+    /// Error of the last refresh, if it failed
+    last_error: Option<String>,
+}
+
+/// Model catalog cache shared by all providers of a client
+#[derive(Debug)]
+pub struct ModelCatalog {
+    ttl: Duration,
+    /// Longest a provider's model listing may take
+    refresh_timeout: Duration,
+    entries: RwLock<HashMap<String, CatalogEntry>>,
+}
+
+impl ModelCatalog {
+    /// Create a catalog whose entries expire after `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            refresh_timeout: Duration::from_secs(10),
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Fail refreshes whose model listing takes longer than `timeout`
+    pub fn with_refresh_timeout(mut self, timeout: Duration) -> Self {
+        self.refresh_timeout = timeout;
+        self
+    }
+
+    /// Time after which a provider's catalog is refreshed
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Longest a provider's model listing may take
+    pub fn refresh_timeout(&self) -> Duration {
+        self.refresh_timeout
+    }
+
+    /// Whether a provider serves a model, refreshing its catalog when stale
+    pub async fn supports(&self, name: &str, provider: &dyn AiProviderTrait, model: &str) -> bool {
+        {
+            let entries = self.entries.read().await;
+            if let Some(entry) = entries.get(name).filter(|entry| entry.refreshed_at.elapsed() < self.ttl) {
+                return entry.models.contains(model);
+            }
+        }
+
+        // A failed refresh leaves the provider with an empty catalog
+        let _ = self.refresh(name, provider).await;
+        self.entries.read().await
+            .get(name)
+            .is_some_and(|entry| entry.models.contains(model))
+    }
+
+    /// Models a provider serves, refreshing its catalog when stale
+    ///
+    /// `None` when the provider's last refresh failed.
+    pub async fn models(&self, name: &str, provider: &dyn AiProviderTrait) -> Option<HashSet<String>> {
+        {
+            let entries = self.entries.read().await;
+            if let Some(entry) = entries.get(name).filter(|entry| entry.refreshed_at.elapsed() < self.ttl) {
+                return entry.last_error.is_none().then(|| entry.models.clone());
+            }
+        }
+
+        self.refresh(name, provider).await.ok()?;
+        self.entries.read().await.get(name).map(|entry| entry.models.clone())
+    }
+
+    /// Fetch a provider's models now, replacing its cached catalog
+    pub async fn refresh(&self, name: &str, provider: &dyn AiProviderTrait) -> AiResult<()> {
+        let result = match tokio::time::timeout(self.refresh_timeout, provider.list_models()).await {
+            Ok(result) => result,
+            Err(_) => Err(AiError::Timeout(format!("Listing models took longer than {:?}", self.refresh_timeout))),
+        };
+
+        let entry = match &result {
+            Ok(models) => CatalogEntry {
+                models: models.iter().cloned().collect(),
+                refreshed_at: Instant::now(),
+                last_error: None,
+            },
+            Err(e) => {
+                warn!("Model catalog refresh failed for provider '{}': {}", name, e);
+                CatalogEntry {
+                    models: HashSet::new(),
+                    refreshed_at: Instant::now(),
+                    last_error: Some(e.to_string()),
+                }
+            }
+        };
+        self.entries.write().await.insert(name.to_string(), entry);
+
+        result.map(|_| ())
+    }
+
+    /// Whether the last refresh of a provider succeeded; `None` before the first refresh
+    pub async fn is_available(&self, name: &str) -> Option<bool> {
+        self.entries.read().await.get(name).map(|entry| entry.last_error.is_none())
+    }
+
+    /// Drop a provider's cached catalog
+    pub async fn invalidate(&self, name: &str) {
+        self.entries.write().await.remove(name);
+    }
+}
+
+/// A model served by one or more providers
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelListing {
+    pub id: String,
+    /// Providers serving the model, by name
+    pub providers: Vec<String>,
+    /// Other names the model can be requested by
+    pub aliases: Vec<String>,
+    /// Features at least one of the providers supports
+    pub capabilities: ProviderCapabilities,
+    /// Cheapest price any of the providers quotes for the model
+    pub pricing: Option<f64>,
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(300))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_provider::{MockListing, MockProvider};
+
+    #[tokio::test]
+    async fn test_catalog_served_from_cache_until_ttl_expires() {
+        let catalog = ModelCatalog::new(Duration::from_millis(50));
+        let provider = MockProvider::new("listing", &["listed-model"]);
+        let log = provider.log();
+
+        assert!(catalog.supports("listing", &provider, "listed-model").await);
+        assert!(!catalog.supports("listing", &provider, "other-model").await);
+        assert_eq!(log.listings(), 1);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(catalog.supports("listing", &provider, "listed-model").await);
+        assert_eq!(log.listings(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_degrades_availability_until_recovery() {
+        let catalog = ModelCatalog::new(Duration::from_millis(50));
+        let provider = MockProvider::new("listing", &["listed-model"]);
+        let log = provider.log();
+        log.set_listing_fails(true);
+
+        assert!(!catalog.supports("listing", &provider, "listed-model").await);
+        assert_eq!(catalog.is_available("listing").await, Some(false));
+
+        // The failure is cached too, so the provider is not hammered
+        assert!(!catalog.supports("listing", &provider, "listed-model").await);
+        assert_eq!(log.listings(), 1);
+
+        log.set_listing_fails(false);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(catalog.supports("listing", &provider, "listed-model").await);
+        assert_eq!(catalog.is_available("listing").await, Some(true));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hanging_listing_times_out_as_unavailable() {
+        let catalog = ModelCatalog::new(Duration::from_secs(300)).with_refresh_timeout(Duration::from_secs(2));
+        let provider = MockProvider::new("hanging", &["listed-model"]).with_listing(MockListing::Hanging);
+
+        let started = tokio::time::Instant::now();
+        assert!(matches!(catalog.refresh("hanging", &provider).await, Err(AiError::Timeout(_))));
+        assert_eq!(started.elapsed(), Duration::from_secs(2));
+        assert!(!catalog.supports("hanging", &provider, "listed-model").await);
+        assert_eq!(catalog.is_available("hanging").await, Some(false));
+    }
+}
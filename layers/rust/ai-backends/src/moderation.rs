@@ -0,0 +1,288 @@
+//! Content moderation for prompts and completions
+//!
+//! A [`Moderator`] inspects inbound prompts before they reach a provider and
+//! outbound completions before they are returned. A [`ModerationPolicy`]
+//! decides whether flagged content is blocked, redacted or only annotated;
+//! annotations are reported on the response as [`ModerationFlag`]s.
+
+use crate::{AiError, AiResult, ChatMessage, ChatRequest, ChatResponse, CompletionRequest, CompletionResponse, ContentPart, MessageContent};
+use async_trait::async_trait;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Point in the request lifecycle at which content is moderated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationStage {
+    Prompt,
+    Completion,
+}
+
+/// A span of text flagged by a moderator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModerationMatch {
+    pub category: String,
+    pub text: String,
+}
+
+/// Outcome of moderating one piece of text
+#[derive(Debug, Clone, Default)]
+pub struct ModerationResult {
+    pub matches: Vec<ModerationMatch>,
+}
+
+impl ModerationResult {
+    /// Whether any content was flagged
+    pub fn is_flagged(&self) -> bool {
+        !self.matches.is_empty()
+    }
+
+    /// Distinct categories of the flagged content
+    pub fn categories(&self) -> Vec<String> {
+        let mut categories: Vec<String> = self.matches.iter().map(|m| m.category.clone()).collect();
+        categories.sort();
+        categories.dedup();
+        categories
+    }
+}
+
+/// Content moderator trait
+#[async_trait]
+pub trait Moderator: Send + Sync {
+    /// Inspect a piece of text
+    async fn moderate(&self, text: &str, stage: ModerationStage) -> AiResult<ModerationResult>;
+}
+
+/// What to do with flagged content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    /// Reject with a [`AiError::ContentBlocked`] error
+    Block,
+    /// Replace the flagged spans and continue
+    Redact,
+    /// Pass the content through unchanged and report the flag
+    Annotate,
+}
+
+/// Moderation policy per stage
+#[derive(Debug, Clone)]
+pub struct ModerationPolicy {
+    pub prompt_action: ModerationAction,
+    pub completion_action: ModerationAction,
+    /// Replacement text for redacted spans
+    pub redaction: String,
+}
+
+impl Default for ModerationPolicy {
+    fn default() -> Self {
+        Self {
+            prompt_action: ModerationAction::Block,
+            completion_action: ModerationAction::Redact,
+            redaction: "[REDACTED]".to_string(),
+        }
+    }
+}
+
+/// Flag reported on a response for moderated content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModerationFlag {
+    pub stage: ModerationStage,
+    pub categories: Vec<String>,
+    pub action: ModerationAction,
+}
+
+/// Keyword and regex based moderator
+#[derive(Debug, Clone, Default)]
+pub struct KeywordModerator {
+    rules: Vec<(String, Regex)>, // (category, pattern)
+}
+
+impl KeywordModerator {
+    /// Create a moderator with no rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flag whole-word, case-insensitive occurrences of any keyword
+    pub fn with_keywords(mut self, category: &str, keywords: &[&str]) -> Self {
+        if keywords.is_empty() {
+            return self;
+        }
+
+        let alternatives: Vec<String> = keywords.iter().map(|k| regex::escape(k)).collect();
+        let pattern = RegexBuilder::new(&format!(r"\b(?:{})\b", alternatives.join("|")))
+            .case_insensitive(true)
+            .build()
+            .expect("escaped keywords always form a valid pattern");
+
+        self.rules.push((category.to_string(), pattern));
+        self
+    }
+
+    /// Flag matches of a regular expression
+    pub fn add_pattern(&mut self, category: &str, pattern: &str) -> AiResult<()> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| AiError::Config(format!("Invalid moderation pattern '{}': {}", pattern, e)))?;
+        self.rules.push((category.to_string(), regex));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Moderator for KeywordModerator {
+    async fn moderate(&self, text: &str, _stage: ModerationStage) -> AiResult<ModerationResult> {
+        let matches = self.rules.iter()
+            .flat_map(|(category, regex)| {
+                regex.find_iter(text).map(move |m| ModerationMatch {
+                    category: category.clone(),
+                    text: m.as_str().to_string(),
+                })
+            })
+            .collect();
+
+        Ok(ModerationResult { matches })
+    }
+}
+
+/// Applies a moderator to requests and responses according to a policy
+#[derive(Clone)]
+pub struct ContentModeration {
+    moderator: Arc<dyn Moderator>,
+    policy: ModerationPolicy,
+}
+
+impl ContentModeration {
+    /// Create a new content moderation stage
+    pub fn new(moderator: Arc<dyn Moderator>, policy: ModerationPolicy) -> Self {
+        Self { moderator, policy }
+    }
+
+    /// Moderation policy
+    pub fn policy(&self) -> &ModerationPolicy {
+        &self.policy
+    }
+
+    /// Moderate the messages of a chat request
+    pub async fn moderate_chat_request(&self, request: &mut ChatRequest) -> AiResult<Vec<ModerationFlag>> {
+        let mut flags = Vec::new();
+        for message in &mut request.messages {
+            for text in message_texts(message) {
+                flags.extend(self.apply(text, ModerationStage::Prompt).await?);
+            }
+        }
+        Ok(flags)
+    }
+
+    /// Moderate the choices of a chat response, recording flags on it
+    pub async fn moderate_chat_response(&self, response: &mut ChatResponse) -> AiResult<()> {
+        let mut flags = Vec::new();
+        for choice in &mut response.choices {
+            for text in message_texts(&mut choice.message) {
+                flags.extend(self.apply(text, ModerationStage::Completion).await?);
+            }
+        }
+        response.moderation_flags.extend(flags);
+        Ok(())
+    }
+
+    /// Moderate the prompt of a text completion request
+    pub async fn moderate_completion_request(&self, request: &mut CompletionRequest) -> AiResult<Vec<ModerationFlag>> {
+        Ok(self.apply(&mut request.prompt, ModerationStage::Prompt).await?.into_iter().collect())
+    }
+
+    /// Moderate the choices of a text completion response, recording flags on it
+    pub async fn moderate_completion_response(&self, response: &mut CompletionResponse) -> AiResult<()> {
+        let mut flags = Vec::new();
+        for choice in &mut response.choices {
+            flags.extend(self.apply(&mut choice.text, ModerationStage::Completion).await?);
+        }
+        response.moderation_flags.extend(flags);
+        Ok(())
+    }
+
+    /// Moderate one piece of text in place
+    async fn apply(&self, text: &mut String, stage: ModerationStage) -> AiResult<Option<ModerationFlag>> {
+        let result = self.moderator.moderate(text, stage).await?;
+        if !result.is_flagged() {
+            return Ok(None);
+        }
+
+        let categories = result.categories();
+        let action = match stage {
+            ModerationStage::Prompt => self.policy.prompt_action,
+            ModerationStage::Completion => self.policy.completion_action,
+        };
+
+        match action {
+            ModerationAction::Block => {
+                warn!("Blocked {:?} flagged as {:?}", stage, categories);
+                // The error names the categories only, never the flagged content
+                return Err(AiError::ContentBlocked(format!(
+                    "{} rejected by content policy ({})",
+                    match stage {
+                        ModerationStage::Prompt => "Prompt",
+                        ModerationStage::Completion => "Completion",
+                    },
+                    categories.join(", ")
+                )));
+            }
+            ModerationAction::Redact => {
+                for flagged in &result.matches {
+                    *text = text.replace(&flagged.text, &self.policy.redaction);
+                }
+            }
+            ModerationAction::Annotate => {}
+        }
+
+        Ok(Some(ModerationFlag { stage, categories, action }))
+    }
+}
+
+/// Mutable references to the text parts of a message
+fn message_texts(message: &mut ChatMessage) -> Vec<&mut String> {
+    match &mut message.content {
+        MessageContent::Text(text) => vec![text],
+        MessageContent::MultiModal(parts) => parts.iter_mut()
+            .filter_map(|part| match part {
+                ContentPart::Text { text } => Some(text),
+                ContentPart::ImageUrl { .. } => None,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_keyword_moderator() {
+        let mut moderator = KeywordModerator::new().with_keywords("violence", &["attack", "bomb"]);
+        moderator.add_pattern("pii", r"\b\d{3}-\d{2}-\d{4}\b").unwrap();
+
+        let result = moderator.moderate("Plan the ATTACK, SSN 123-45-6789", ModerationStage::Prompt).await.unwrap();
+        assert_eq!(result.categories(), vec!["pii", "violence"]);
+
+        // Keywords match whole words only
+        assert!(!moderator.moderate("bombastic remarks", ModerationStage::Prompt).await.unwrap().is_flagged());
+        assert!(moderator.add_pattern("broken", "(").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_redaction_replaces_flagged_spans() {
+        let moderation = ContentModeration::new(
+            Arc::new(KeywordModerator::new().with_keywords("secrets", &["hunter2"])),
+            ModerationPolicy::default(),
+        );
+
+        let mut text = "the password is hunter2".to_string();
+        let flag = moderation.apply(&mut text, ModerationStage::Completion).await.unwrap().unwrap();
+
+        assert_eq!(text, "the password is [REDACTED]");
+        assert_eq!(flag.action, ModerationAction::Redact);
+        assert_eq!(flag.categories, vec!["secrets"]);
+    }
+}
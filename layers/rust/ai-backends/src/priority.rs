@@ -0,0 +1,140 @@
+//! Request priority lanes
+//!
+//! A provider with a concurrency limit queues requests waiting for a slot in
+//! a [`PriorityLimiter`]. Freed slots go to the oldest interactive waiter
+//! first; batch waiters are only served once no interactive request waits.
+
+use crate::{AiError, AiResult};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Lane a request waits in when its provider is at its concurrency limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPriority {
+    /// A user is waiting on the response
+    #[default]
+    Interactive,
+    /// Background work that can wait behind interactive requests
+    Batch,
+}
+
+/// Free slots and the requests waiting for one, per lane
+#[derive(Default)]
+struct Lanes {
+    available: usize,
+    interactive: VecDeque<oneshot::Sender<PriorityPermit>>,
+    batch: VecDeque<oneshot::Sender<PriorityPermit>>,
+}
+
+/// Concurrency limit that serves waiting interactive requests before batch ones
+pub struct PriorityLimiter {
+    lanes: Arc<Mutex<Lanes>>,
+}
+
+impl PriorityLimiter {
+    /// Create a limiter allowing `max_in_flight` requests at once
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            lanes: Arc::new(Mutex::new(Lanes { available: max_in_flight, ..Lanes::default() })),
+        }
+    }
+
+    /// Wait for a slot in the lane of `priority`
+    ///
+    /// Dropping the returned future gives up the place in the queue.
+    pub async fn acquire(&self, priority: RequestPriority) -> AiResult<PriorityPermit> {
+        let receiver = {
+            let mut lanes = self.lanes.lock().unwrap();
+            if lanes.available > 0 && lanes.interactive.is_empty() && lanes.batch.is_empty() {
+                lanes.available -= 1;
+                return Ok(PriorityPermit { lanes: self.lanes.clone() });
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            match priority {
+                RequestPriority::Interactive => lanes.interactive.push_back(sender),
+                RequestPriority::Batch => lanes.batch.push_back(sender),
+            }
+            receiver
+        };
+
+        receiver.await.map_err(|_| AiError::Unknown("Concurrency limit was closed".to_string()))
+    }
+
+    /// Requests waiting for a slot in each lane, as (interactive, batch)
+    pub fn waiting(&self) -> (usize, usize) {
+        let lanes = self.lanes.lock().unwrap();
+        (lanes.interactive.len(), lanes.batch.len())
+    }
+}
+
+/// Slot held until dropped, when it passes to the next waiter
+pub struct PriorityPermit {
+    lanes: Arc<Mutex<Lanes>>,
+}
+
+impl Drop for PriorityPermit {
+    fn drop(&mut self) {
+        let next = {
+            let mut lanes = self.lanes.lock().unwrap();
+            match lanes.interactive.pop_front().or_else(|| lanes.batch.pop_front()) {
+                Some(next) => next,
+                None => {
+                    lanes.available += 1;
+                    return;
+                }
+            }
+        };
+
+        // A waiter that gave up hands the returned permit on when it is dropped
+        let _ = next.send(PriorityPermit { lanes: self.lanes.clone() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_interactive_waiter_is_served_before_batch_backlog() {
+        let limiter = Arc::new(PriorityLimiter::new(1));
+        let held = limiter.acquire(RequestPriority::Batch).await.unwrap();
+
+        let served = Arc::new(Mutex::new(Vec::new()));
+        let mut waiters = Vec::new();
+        for (queued, (name, priority)) in [("batch-1", RequestPriority::Batch), ("batch-2", RequestPriority::Batch), ("interactive", RequestPriority::Interactive)].into_iter().enumerate() {
+            let (waiter_limiter, served) = (limiter.clone(), served.clone());
+            waiters.push(tokio::spawn(async move {
+                let _permit = waiter_limiter.acquire(priority).await.unwrap();
+                served.lock().unwrap().push(name);
+            }));
+
+            // Queue the waiters in order
+            while limiter.waiting().0 + limiter.waiting().1 <= queued {
+                tokio::task::yield_now().await;
+            }
+        }
+        assert_eq!(limiter.waiting(), (1, 2));
+
+        drop(held);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+        assert_eq!(*served.lock().unwrap(), vec!["interactive", "batch-1", "batch-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiter_does_not_leak_its_slot() {
+        let limiter = PriorityLimiter::new(1);
+        let held = limiter.acquire(RequestPriority::Interactive).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(10), limiter.acquire(RequestPriority::Interactive)).await.is_err());
+
+        drop(held);
+        let permit = tokio::time::timeout(Duration::from_millis(100), limiter.acquire(RequestPriority::Batch)).await;
+        assert!(permit.is_ok());
+    }
+}
@@ -1,11 +1,17 @@
 //! AI provider implementations
 
-use crate::{AiResult, AiError, ProviderConfig, ChatRequest, ChatResponse, CompletionRequest, CompletionResponse, EmbeddingRequest, EmbeddingResponse, ApiStatus};
+use crate::{AiResult, AiError, AiProvider, ProviderConfig, ChatRequest, ChatResponse, ChatCompletionChunk, CompletionRequest, CompletionResponse, EmbeddingRequest, EmbeddingResponse, ApiStatus, FinishReason, ProviderCapabilities, ChatMessage, CacheControl, ContentPart, MessageContent, Usage, PromptTokensDetails};
+use crate::tool_calling::{normalize_openai_response, parse_tool_calls, tools_to_provider_format};
 use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
 use reqwest::Client;
 use serde_json::json;
+use std::collections::{HashMap, VecDeque};
 use std::time::Duration;
-use tracing::{info, warn, error};
+use tracing::{debug, info, warn, error};
+
+/// Stream of chat completion chunks
+pub type ChatCompletionStream = BoxStream<'static, AiResult<ChatCompletionChunk>>;
 
 /// AI provider trait
 #[async_trait]
@@ -16,9 +22,30 @@ pub trait AiProviderTrait: Send + Sync {
     /// Get available models
     fn available_models(&self) -> Vec<String>;
 
+    /// Fetch the models the provider currently serves; by default the static list
+    async fn list_models(&self) -> AiResult<Vec<String>> {
+        Ok(self.available_models())
+    }
+
+    /// Sanitize a normalized request into one the provider accepts
+    ///
+    /// Called by the client before every chat completion so requests routed
+    /// across providers never carry fields the target rejects. By default the
+    /// request is passed through unchanged.
+    fn transform_request(&self, request: ChatRequest) -> ChatRequest {
+        request
+    }
+
     /// Chat completion
     async fn chat_completion(&self, request: &ChatRequest) -> AiResult<ChatResponse>;
 
+    /// Streaming chat completion; by default the complete response is sent as one chunk per choice
+    async fn chat_completion_stream(&self, request: &ChatRequest) -> AiResult<ChatCompletionStream> {
+        let request = ChatRequest { stream: Some(false), ..request.clone() };
+        let response = self.chat_completion(&request).await?;
+        Ok(stream::iter(ChatCompletionChunk::from_response(response).into_iter().map(Ok)).boxed())
+    }
+
     /// Text completion
     async fn text_completion(&self, request: &CompletionRequest) -> AiResult<CompletionResponse>;
 
@@ -30,6 +57,142 @@ pub trait AiProviderTrait: Send + Sync {
 
     /// Get model pricing
     fn get_model_pricing(&self, model: &str) -> Option<f64>;
+
+    /// Whether the provider honors `response_format` to force JSON output
+    fn supports_json_mode(&self) -> bool {
+        false
+    }
+
+    /// Features the provider supports; by default plain chat, completions and embeddings
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities { embeddings: true, ..ProviderCapabilities::default() }
+    }
+}
+
+/// Body of an OpenAI-compatible chat completion request
+fn openai_chat_body(request: &ChatRequest, provider: AiProvider) -> serde_json::Value {
+    json!({
+        "model": request.model,
+        "messages": request.messages.iter().map(openai_message).collect::<Vec<_>>(),
+        "temperature": request.temperature,
+        "max_tokens": request.max_tokens,
+        "stream": request.stream,
+        "functions": request.functions,
+        "function_call": request.function_call,
+        "tools": request.tools.as_deref().map(|tools| tools_to_provider_format(provider, tools)),
+        "stop": request.stop,
+        "presence_penalty": request.presence_penalty,
+        "frequency_penalty": request.frequency_penalty,
+        "logit_bias": request.logit_bias,
+        "user": request.user,
+        "response_format": request.response_format,
+    })
+}
+
+/// A chat message in OpenAI-compatible form
+///
+/// These APIs cache long prompt prefixes on their own and reject unknown
+/// message fields, so cache markers are left out.
+fn openai_message(message: &ChatMessage) -> serde_json::Value {
+    let mut value = json!(message);
+    if let Some(object) = value.as_object_mut() {
+        object.remove("cache_control");
+        object.insert("role".to_string(), json!(message.role.as_str()));
+        if let Some(calls) = &message.tool_calls {
+            let calls: Vec<serde_json::Value> = calls.iter().map(|call| call.to_provider_format(AiProvider::OpenAI)).collect();
+            object.insert("tool_calls".to_string(), json!(calls));
+        }
+    }
+    value
+}
+
+/// Body of an OpenAI-compatible text completion request
+fn openai_completion_body(request: &CompletionRequest) -> serde_json::Value {
+    json!({
+        "model": request.model,
+        "prompt": request.prompt,
+        "temperature": request.temperature,
+        "max_tokens": request.max_tokens,
+        "stream": request.stream,
+        "stop": request.stop,
+        "echo": request.echo,
+        "presence_penalty": request.presence_penalty,
+        "frequency_penalty": request.frequency_penalty,
+    })
+}
+
+/// Body of an OpenAI-compatible embeddings request
+fn openai_embedding_body(request: &EmbeddingRequest) -> serde_json::Value {
+    json!({
+        "input": request.input,
+        "model": request.model,
+        "user": request.user,
+    })
+}
+
+/// Take the complete events off the front of a server-sent event buffer, returning their data
+///
+/// The buffer must have its carriage returns removed; an incomplete event
+/// is left in it until the rest arrives.
+fn drain_sse_data(buffer: &mut Vec<u8>) -> Vec<String> {
+    let mut events = Vec::new();
+    while let Some(end) = buffer.windows(2).position(|window| window == b"\n\n") {
+        let event: Vec<u8> = buffer.drain(..end + 2).collect();
+        let data: Vec<String> = String::from_utf8_lossy(&event)
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|data| data.trim_start().to_string())
+            .collect();
+        if !data.is_empty() {
+            events.push(data.join("\n"));
+        }
+    }
+    events
+}
+
+/// Chunks of an OpenAI-compatible streamed chat completion, read as they arrive
+///
+/// The stream ends at the `[DONE]` event; a chunk that cannot be parsed or
+/// a broken connection is reported as an error.
+fn openai_chunk_stream(response: reqwest::Response) -> ChatCompletionStream {
+    let state = (Some(response), Vec::new(), VecDeque::new());
+    stream::unfold(state, |(mut response, mut buffer, mut pending)| async move {
+        loop {
+            if let Some(chunk) = pending.pop_front() {
+                return Some((chunk, (response, buffer, pending)));
+            }
+            match response.as_mut()?.chunk().await {
+                Ok(Some(bytes)) => {
+                    buffer.extend(bytes.iter().filter(|byte| **byte != b'\r'));
+                    for data in drain_sse_data(&mut buffer) {
+                        if data == "[DONE]" {
+                            response = None;
+                            break;
+                        }
+                        pending.push_back(serde_json::from_str::<ChatCompletionChunk>(&data)
+                            .map_err(|e| AiError::Parse(format!("Failed to parse stream chunk: {}", e))));
+                    }
+                }
+                Ok(None) => response = None,
+                Err(e) => {
+                    response = None;
+                    pending.push_back(Err(AiError::Http(format!("Stream failed: {}", e))));
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
+/// Price per 1K tokens of an OpenAI model
+fn openai_model_pricing(model: &str) -> Option<f64> {
+    match model {
+        "gpt-3.5-turbo" => Some(0.002),
+        "gpt-4" => Some(0.03),
+        "gpt-4-turbo-preview" => Some(0.01),
+        "text-embedding-ada-002" => Some(0.0001),
+        _ => None,
+    }
 }
 
 /// OpenAI provider implementation
@@ -77,10 +240,7 @@ impl OpenAiProvider {
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(AiError::Provider {
-                provider: "OpenAI".to_string(),
-                message: format!("API error {}: {}", status_code, error_text),
-            });
+            return Err(AiError::from_provider_response(&AiProvider::OpenAI, status_code, &error_text));
         }
 
         let result = response.json::<T>().await
@@ -88,6 +248,27 @@ impl OpenAiProvider {
 
         Ok(result)
     }
+
+    /// Send a streaming request, returning the response once its status is known
+    async fn open_stream(&self, endpoint: &str, body: serde_json::Value) -> AiResult<reqwest::Response> {
+        let url = format!("{}/{}", self.get_base_url(), endpoint.trim_start_matches('/'));
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", self.get_auth_header())
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| AiError::Http(format!("Request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status_code = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AiError::from_provider_response(&AiProvider::OpenAI, status_code, &error_text));
+        }
+        Ok(response)
+    }
 }
 
 #[async_trait]
@@ -111,76 +292,369 @@ impl AiProviderTrait for OpenAiProvider {
         ]
     }
 
-    async fn chat_completion(&self, request: &ChatRequest) -> AiResult<ChatResponse> {
-        if !self.supports_model(&request.model) {
-            return Err(AiError::ModelNotAvailable(request.model.clone()));
+    async fn list_models(&self) -> AiResult<Vec<String>> {
+        let url = format!("{}/models", self.get_base_url());
+
+        let response = self.client
+            .get(&url)
+            .header("Authorization", self.get_auth_header())
+            .send()
+            .await
+            .map_err(|e| AiError::Http(format!("Request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status_code = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AiError::from_provider_response(&AiProvider::OpenAI, status_code, &error_text));
         }
 
-        let body = json!({
-            "model": request.model,
-            "messages": request.messages,
-            "temperature": request.temperature,
-            "max_tokens": request.max_tokens,
-            "stream": request.stream,
-            "functions": request.functions,
-            "function_call": request.function_call,
-            "stop": request.stop,
-            "presence_penalty": request.presence_penalty,
-            "frequency_penalty": request.frequency_penalty,
-            "logit_bias": request.logit_bias,
-            "user": request.user,
-        });
+        let body: serde_json::Value = response.json().await
+            .map_err(|e| AiError::Parse(format!("Failed to parse response: {}", e)))?;
 
-        self.make_request("chat/completions", body).await
+        Ok(body["data"].as_array()
+            .map(|models| models.iter()
+                .filter_map(|model| model["id"].as_str().map(str::to_string))
+                .collect())
+            .unwrap_or_default())
+    }
+
+    async fn chat_completion(&self, request: &ChatRequest) -> AiResult<ChatResponse> {
+        let body = openai_chat_body(request, AiProvider::OpenAI);
+        let response: serde_json::Value = self.make_request("chat/completions", body).await?;
+        serde_json::from_value(normalize_openai_response(response)?)
+            .map_err(|e| AiError::Parse(format!("Failed to parse response: {}", e)))
+    }
+
+    async fn chat_completion_stream(&self, request: &ChatRequest) -> AiResult<ChatCompletionStream> {
+        let mut body = openai_chat_body(request, AiProvider::OpenAI);
+        body["stream"] = json!(true);
+        body["stream_options"] = json!({ "include_usage": true });
+        Ok(openai_chunk_stream(self.open_stream("chat/completions", body).await?))
     }
 
     async fn text_completion(&self, request: &CompletionRequest) -> AiResult<CompletionResponse> {
-        if !self.supports_model(&request.model) {
-            return Err(AiError::ModelNotAvailable(request.model.clone()));
+        self.make_request("completions", openai_completion_body(request)).await
+    }
+
+    async fn create_embeddings(&self, request: &EmbeddingRequest) -> AiResult<EmbeddingResponse> {
+        self.make_request("embeddings", openai_embedding_body(request)).await
+    }
+
+    fn supports_model(&self, model: &str) -> bool {
+        self.available_models().contains(&model.to_string())
+    }
+
+    fn get_model_pricing(&self, model: &str) -> Option<f64> {
+        openai_model_pricing(model)
+    }
+
+    fn supports_json_mode(&self) -> bool {
+        true
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities::all()
+    }
+}
+
+/// Azure OpenAI REST API version used unless configured otherwise
+pub const AZURE_DEFAULT_API_VERSION: &str = "2024-02-01";
+
+/// Azure OpenAI provider implementation
+///
+/// Azure serves OpenAI models from named deployments on a resource endpoint
+/// (`base_url`, e.g. `https://my-resource.openai.azure.com`). Requests name
+/// a logical model such as `gpt-4`, which is mapped to its deployment; the
+/// request and response bodies are the OpenAI ones.
+pub struct AzureOpenAiProvider {
+    client: Client,
+    config: ProviderConfig,
+    api_version: String,
+    /// Logical model name -> deployment name
+    deployments: HashMap<String, String>,
+}
+
+impl AzureOpenAiProvider {
+    /// Create a provider serving the configured deployments
+    ///
+    /// Configured models without an entry in `config.deployments` are
+    /// served from a deployment of the same name.
+    pub fn new(config: ProviderConfig) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .expect("Failed to create HTTP client");
+        let mut deployments: HashMap<String, String> = config.models.iter()
+            .map(|model| (model.id.clone(), model.id.clone()))
+            .collect();
+        deployments.extend(config.deployments.clone());
+        let api_version = config.api_version.clone().unwrap_or_else(|| AZURE_DEFAULT_API_VERSION.to_string());
+
+        Self {
+            client,
+            config,
+            api_version,
+            deployments,
         }
+    }
 
-        let body = json!({
-            "model": request.model,
-            "prompt": request.prompt,
-            "temperature": request.temperature,
-            "max_tokens": request.max_tokens,
-            "stream": request.stream,
-            "stop": request.stop,
-            "echo": request.echo,
-            "presence_penalty": request.presence_penalty,
-            "frequency_penalty": request.frequency_penalty,
-        });
+    /// Use a specific `api-version`
+    pub fn with_api_version(mut self, api_version: impl Into<String>) -> Self {
+        self.api_version = api_version.into();
+        self
+    }
 
-        self.make_request("completions", body).await
+    /// Serve a logical model from a deployment
+    pub fn with_deployment(mut self, model: impl Into<String>, deployment: impl Into<String>) -> Self {
+        self.deployments.insert(model.into(), deployment.into());
+        self
     }
 
-    async fn create_embeddings(&self, request: &EmbeddingRequest) -> AiResult<EmbeddingResponse> {
-        if !self.supports_model(&request.model) {
-            return Err(AiError::ModelNotAvailable(request.model.clone()));
+    /// Deployment serving a logical model
+    pub fn deployment_for(&self, model: &str) -> AiResult<&str> {
+        self.deployments.get(model)
+            .map(String::as_str)
+            .ok_or_else(|| AiError::ModelNotAvailable(format!("No Azure deployment for model: {}", model)))
+    }
+
+    /// URL of an operation on a deployment, e.g. `chat/completions`
+    fn endpoint_url(&self, deployment: &str, operation: &str) -> String {
+        format!(
+            "{}/openai/deployments/{}/{}?api-version={}",
+            self.config.base_url.as_deref().unwrap_or_default().trim_end_matches('/'),
+            deployment,
+            operation.trim_start_matches('/'),
+            self.api_version,
+        )
+    }
+
+    async fn make_request<T: serde::de::DeserializeOwned>(
+        &self,
+        model: &str,
+        operation: &str,
+        body: serde_json::Value,
+    ) -> AiResult<T> {
+        let url = self.endpoint_url(self.deployment_for(model)?, operation);
+
+        let response = self.client
+            .post(&url)
+            .header("api-key", &self.config.api_key)
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| AiError::Http(format!("Request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status_code = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AiError::from_provider_response(&AiProvider::Azure, status_code, &error_text));
         }
 
-        let body = json!({
-            "input": request.input,
-            "model": request.model,
-            "user": request.user,
-        });
+        response.json::<T>().await
+            .map_err(|e| AiError::Parse(format!("Failed to parse response: {}", e)))
+    }
+
+    /// Send a streaming request, returning the response once its status is known
+    async fn open_stream(&self, model: &str, operation: &str, body: serde_json::Value) -> AiResult<reqwest::Response> {
+        let url = self.endpoint_url(self.deployment_for(model)?, operation);
 
-        self.make_request("embeddings", body).await
+        let response = self.client
+            .post(&url)
+            .header("api-key", &self.config.api_key)
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| AiError::Http(format!("Request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status_code = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AiError::from_provider_response(&AiProvider::Azure, status_code, &error_text));
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl AiProviderTrait for AzureOpenAiProvider {
+    fn name(&self) -> &str {
+        "Azure OpenAI"
+    }
+
+    fn available_models(&self) -> Vec<String> {
+        let mut models: Vec<String> = self.deployments.keys().cloned().collect();
+        models.sort();
+        models
+    }
+
+    async fn chat_completion(&self, request: &ChatRequest) -> AiResult<ChatResponse> {
+        let body = openai_chat_body(request, AiProvider::Azure);
+        let response: serde_json::Value = self.make_request(&request.model, "chat/completions", body).await?;
+        serde_json::from_value(normalize_openai_response(response)?)
+            .map_err(|e| AiError::Parse(format!("Failed to parse response: {}", e)))
+    }
+
+    async fn chat_completion_stream(&self, request: &ChatRequest) -> AiResult<ChatCompletionStream> {
+        let mut body = openai_chat_body(request, AiProvider::Azure);
+        body["stream"] = json!(true);
+        Ok(openai_chunk_stream(self.open_stream(&request.model, "chat/completions", body).await?))
+    }
+
+    async fn text_completion(&self, request: &CompletionRequest) -> AiResult<CompletionResponse> {
+        self.make_request(&request.model, "completions", openai_completion_body(request)).await
+    }
+
+    async fn create_embeddings(&self, request: &EmbeddingRequest) -> AiResult<EmbeddingResponse> {
+        self.make_request(&request.model, "embeddings", openai_embedding_body(request)).await
     }
 
     fn supports_model(&self, model: &str) -> bool {
-        self.available_models().contains(&model.to_string())
+        self.deployments.contains_key(model)
     }
 
     fn get_model_pricing(&self, model: &str) -> Option<f64> {
-        match model {
-            "gpt-3.5-turbo" => Some(0.002),
-            "gpt-4" => Some(0.03),
-            "gpt-4-turbo-preview" => Some(0.01),
-            "text-embedding-ada-002" => Some(0.0001),
-            _ => None,
+        openai_model_pricing(model)
+    }
+
+    fn supports_json_mode(&self) -> bool {
+        true
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities::all()
+    }
+}
+
+/// Output budget Anthropic requires when the request sets none
+const ANTHROPIC_DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Most cache markers Anthropic accepts in one request
+const ANTHROPIC_MAX_CACHE_BREAKPOINTS: usize = 4;
+
+/// Text content in Anthropic form, as a block carrying the cache marker if there is one
+fn anthropic_text(text: String, cache_control: Option<CacheControl>) -> serde_json::Value {
+    match cache_control {
+        Some(cache_control) => json!([{ "type": "text", "text": text, "cache_control": cache_control }]),
+        None => json!(text),
+    }
+}
+
+/// Content of an Anthropic message; images become image blocks
+fn anthropic_content(content: &MessageContent, cache_control: Option<CacheControl>) -> serde_json::Value {
+    let parts = match content {
+        MessageContent::Text(text) => return anthropic_text(text.clone(), cache_control),
+        MessageContent::MultiModal(parts) => parts,
+    };
+
+    let mut blocks: Vec<serde_json::Value> = parts.iter()
+        .map(|part| match part {
+            ContentPart::Text { text } => json!({ "type": "text", "text": text }),
+            ContentPart::ImageUrl { image_url } => json!({ "type": "image", "source": anthropic_image_source(&image_url.url) }),
+        })
+        .collect();
+    if let (Some(cache_control), Some(last)) = (cache_control, blocks.last_mut()) {
+        last["cache_control"] = json!(cache_control);
+    }
+    json!(blocks)
+}
+
+/// Content of an Anthropic turn; tool calls and tool results become tool blocks
+fn anthropic_turn_content(message: &ChatMessage, cache_control: Option<CacheControl>) -> serde_json::Value {
+    let mut blocks = Vec::new();
+    match (&message.role, &message.tool_calls) {
+        (crate::MessageRole::Tool, _) => blocks.push(json!({
+            "type": "tool_result",
+            "tool_use_id": message.tool_call_id,
+            "content": message.content.text(),
+        })),
+        (_, Some(calls)) if !calls.is_empty() => {
+            let text = message.content.text();
+            if !text.is_empty() {
+                blocks.push(json!({ "type": "text", "text": text }));
+            }
+            blocks.extend(calls.iter().map(|call| call.to_provider_format(AiProvider::Anthropic)));
         }
+        _ => return anthropic_content(&message.content, cache_control),
     }
+
+    if let (Some(cache_control), Some(last)) = (cache_control, blocks.last_mut()) {
+        last["cache_control"] = json!(cache_control);
+    }
+    json!(blocks)
+}
+
+/// Source of an image for Anthropic; data URLs are sent inline as base64
+fn anthropic_image_source(url: &str) -> serde_json::Value {
+    match url.strip_prefix("data:").and_then(|rest| rest.split_once(";base64,")) {
+        Some((media_type, data)) => json!({ "type": "base64", "media_type": media_type, "data": data }),
+        None => json!({ "type": "url", "url": url }),
+    }
+}
+
+/// Body of an Anthropic messages request
+///
+/// Only fields Anthropic accepts are sent; OpenAI-only parameters such as
+/// penalties, `logit_bias` and `user` are left out, and the temperature is
+/// clamped to Anthropic's 0-1 range. Only the last
+/// [`ANTHROPIC_MAX_CACHE_BREAKPOINTS`] cache markers are kept; a later
+/// cached prefix covers the earlier ones anyway.
+fn anthropic_chat_body(request: &ChatRequest) -> serde_json::Value {
+    // Only the first system message is sent; later ones are dropped with their markers
+    let mut system = None;
+    let mut turns = Vec::new();
+    for m in &request.messages {
+        let role = match m.role {
+            crate::MessageRole::System => {
+                if system.is_none() {
+                    system = Some((m.content.text(), m.cache_control));
+                }
+                continue;
+            }
+            crate::MessageRole::Assistant => "assistant",
+            // Tool results go back to Anthropic as user turns
+            _ => "user",
+        };
+        turns.push((role, m));
+    }
+
+    // Only markers that are sent count against the limit; the earliest are dropped
+    let system_marker = system.as_ref().is_some_and(|(text, cache_control)| !text.is_empty() && cache_control.is_some());
+    let markers = usize::from(system_marker) + turns.iter().filter(|(_, m)| m.cache_control.is_some()).count();
+    let mut skipped_markers = markers.saturating_sub(ANTHROPIC_MAX_CACHE_BREAKPOINTS);
+    if skipped_markers > 0 {
+        debug!("Dropped {} cache markers beyond Anthropic's limit of {}", skipped_markers, ANTHROPIC_MAX_CACHE_BREAKPOINTS);
+    }
+    let mut cache_control = |cache_control: Option<CacheControl>| match cache_control {
+        Some(_) if skipped_markers > 0 => {
+            skipped_markers -= 1;
+            None
+        }
+        cache_control => cache_control,
+    };
+
+    let system = match system {
+        Some((text, marker)) if !text.is_empty() => anthropic_text(text, cache_control(marker)),
+        _ => serde_json::Value::Null,
+    };
+    let messages: Vec<serde_json::Value> = turns.into_iter()
+        .map(|(role, m)| json!({
+            "role": role,
+            "content": anthropic_turn_content(m, cache_control(m.cache_control))
+        }))
+        .collect();
+
+    json!({
+        "model": request.model,
+        "max_tokens": request.max_tokens.unwrap_or(ANTHROPIC_DEFAULT_MAX_TOKENS),
+        "messages": messages,
+        "system": system,
+        "temperature": request.temperature.map(|t| t.clamp(0.0, 1.0)),
+        "top_p": request.top_p,
+        "stop_sequences": request.stop,
+        "tools": request.tools.as_deref().map(|tools| tools_to_provider_format(AiProvider::Anthropic, tools)),
+    })
 }
 
 /// Anthropic provider implementation
@@ -228,10 +702,7 @@ impl AnthropicProvider {
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(AiError::Provider {
-                provider: "Anthropic".to_string(),
-                message: format!("API error {}: {}", status_code, error_text),
-            });
+            return Err(AiError::from_provider_response(&AiProvider::Anthropic, status_code, &error_text));
         }
 
         let result = response.json::<T>().await
@@ -259,52 +730,21 @@ impl AiProviderTrait for AnthropicProvider {
         ]
     }
 
+    /// Unsupported parameters are dropped when the body is built; the
+    /// completion Anthropic is always asked for is made explicit so it is
+    /// paced like any other.
+    fn transform_request(&self, mut request: ChatRequest) -> ChatRequest {
+        request.max_tokens.get_or_insert(ANTHROPIC_DEFAULT_MAX_TOKENS);
+        request
+    }
+
     async fn chat_completion(&self, request: &ChatRequest) -> AiResult<ChatResponse> {
         if !self.supports_model(&request.model) {
             return Err(AiError::ModelNotAvailable(request.model.clone()));
         }
 
         // Convert OpenAI format to Anthropic format
-        let system_message = request.messages.iter()
-            .find(|m| m.role == crate::MessageRole::System)
-            .map(|m| match &m.content {
-                crate::MessageContent::Text(text) => text.clone(),
-                _ => String::new(),
-            })
-            .unwrap_or_default();
-
-        let messages: Vec<serde_json::Value> = request.messages.iter()
-            .filter(|m| m.role != crate::MessageRole::System)
-            .map(|m| {
-                let role = match m.role {
-                    crate::MessageRole::User => "user",
-                    crate::MessageRole::Assistant => "assistant",
-                    _ => "user",
-                };
-
-                let content = match &m.content {
-                    crate::MessageContent::Text(text) => text.clone(),
-                    _ => String::new(),
-                };
-
-                json!({
-                    "role": role,
-                    "content": content
-                })
-            })
-            .collect();
-
-        let max_tokens = request.max_tokens.unwrap_or(4096);
-
-        let body = json!({
-            "model": request.model,
-            "max_tokens": max_tokens,
-            "messages": messages,
-            "system": if system_message.is_empty() { serde_json::Value::Null } else { json!(system_message) },
-            "temperature": request.temperature,
-            "top_p": request.top_p,
-            "stop_sequences": request.stop,
-        });
+        let body = anthropic_chat_body(request);
 
         // Anthropic response format is different, we need to convert it
         let anthropic_response: serde_json::Value = self.make_request("messages", body).await?;
@@ -343,12 +783,42 @@ impl AiProviderTrait for AnthropicProvider {
             _ => Some(0.008), // Default Claude pricing
         }
     }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities { tools: true, vision: true, ..ProviderCapabilities::default() }
+    }
 }
 
 impl AnthropicProvider {
+    /// Usage in OpenAI form, where prompt tokens include those read from or written to the cache
+    fn convert_usage(usage: &serde_json::Value) -> Usage {
+        let tokens = |field: &str| usage[field].as_u64().unwrap_or(0) as u32;
+        let details = PromptTokensDetails {
+            cached_tokens: tokens("cache_read_input_tokens"),
+            cache_creation_tokens: tokens("cache_creation_input_tokens"),
+        };
+
+        let prompt_tokens = tokens("input_tokens") + details.cached_tokens + details.cache_creation_tokens;
+        let completion_tokens = tokens("output_tokens");
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            prompt_tokens_details: (details != PromptTokensDetails::default()).then_some(details),
+        }
+    }
+
     fn convert_anthropic_to_openai(&self, anthropic: serde_json::Value) -> AiResult<serde_json::Value> {
         // This is a simplified conversion - in practice you'd need more comprehensive mapping
-        let content = anthropic["content"][0]["text"].as_str().unwrap_or("");
+        let content: String = anthropic["content"].as_array()
+            .map(|blocks| blocks.iter().filter_map(|b| b["text"].as_str()).collect())
+            .unwrap_or_default();
+        let tool_calls = parse_tool_calls(AiProvider::Anthropic, &anthropic)?;
+        let finish_reason = match anthropic["stop_reason"].as_str() {
+            Some(reason) => FinishReason::from_provider(&AiProvider::Anthropic, reason),
+            None if tool_calls.is_empty() => FinishReason::Stop,
+            None => FinishReason::ToolCalls,
+        };
 
         let openai_response = json!({
             "id": format!("anthropic-{}", uuid::Uuid::new_v4()),
@@ -359,15 +829,12 @@ impl AnthropicProvider {
                 "index": 0,
                 "message": {
                     "role": "assistant",
-                    "content": content
+                    "content": content,
+                    "tool_calls": if tool_calls.is_empty() { serde_json::Value::Null } else { json!(tool_calls) }
                 },
-                "finish_reason": "stop"
+                "finish_reason": finish_reason.as_str()
             }],
-            "usage": {
-                "prompt_tokens": anthropic["usage"]["input_tokens"].as_u64().unwrap_or(0),
-                "completion_tokens": anthropic["usage"]["output_tokens"].as_u64().unwrap_or(0),
-                "total_tokens": 0
-            }
+            "usage": Self::convert_usage(&anthropic["usage"])
         });
 
         Ok(openai_response)
@@ -386,6 +853,12 @@ impl ProviderFactory {
             crate::AiProvider::Anthropic => {
                 Ok(Box::new(AnthropicProvider::new(config)))
             }
+            crate::AiProvider::Azure => {
+                if config.base_url.is_none() {
+                    return Err(AiError::Config("Azure OpenAI requires base_url, the resource endpoint".to_string()));
+                }
+                Ok(Box::new(AzureOpenAiProvider::new(config)))
+            }
             _ => Err(AiError::Provider {
                 provider: format!("{:?}", config.provider),
                 message: "Provider not implemented yet".to_string(),
@@ -393,3 +866,252 @@ impl ProviderFactory {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChatMessage, MessageRole, RequestPriority};
+    use std::collections::HashMap;
+
+    fn provider_config(provider: AiProvider) -> ProviderConfig {
+        ProviderConfig {
+            provider,
+            api_key: "test".to_string(),
+            base_url: None,
+            organization_id: None,
+            timeout_seconds: 5,
+            max_retries: 0,
+            models: vec![],
+            deployments: HashMap::new(),
+            api_version: None,
+        }
+    }
+
+    fn request(model: &str) -> ChatRequest {
+        ChatRequest {
+            messages: vec![ChatMessage::user("Hello")],
+            model: model.to_string(),
+            temperature: Some(1.5),
+            top_p: None,
+            max_tokens: None,
+            stream: None,
+            functions: None,
+            function_call: None,
+            tools: None,
+            stop: None,
+            presence_penalty: Some(0.5),
+            frequency_penalty: None,
+            logit_bias: Some(HashMap::from([("50256".to_string(), -100.0)])),
+            user: Some("user-1".to_string()),
+            response_format: None,
+            priority: RequestPriority::Interactive,
+        }
+    }
+
+    #[test]
+    fn test_anthropic_strips_unsupported_parameters() {
+        let provider = AnthropicProvider::new(provider_config(AiProvider::Anthropic));
+        let body = anthropic_chat_body(&provider.transform_request(request("claude-2")));
+
+        for parameter in ["presence_penalty", "frequency_penalty", "logit_bias", "user", "functions", "function_call"] {
+            assert!(body.get(parameter).is_none(), "{} was sent to Anthropic", parameter);
+        }
+        assert_eq!(body["max_tokens"], json!(ANTHROPIC_DEFAULT_MAX_TOKENS));
+        assert_eq!(body["temperature"], json!(1.0));
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+
+        // OpenAI is sent the parameters as-is
+        let provider = OpenAiProvider::new(provider_config(AiProvider::OpenAI));
+        let body = openai_chat_body(&provider.transform_request(request("gpt-4")), AiProvider::OpenAI);
+        assert_eq!(body["presence_penalty"], json!(0.5));
+        assert_eq!(body["user"], json!("user-1"));
+        assert!(body["max_tokens"].is_null());
+    }
+
+    #[test]
+    fn test_azure_maps_models_to_deployment_urls() {
+        let config = ProviderConfig {
+            base_url: Some("https://sira.openai.azure.com/".to_string()),
+            ..provider_config(AiProvider::Azure)
+        };
+        let provider = AzureOpenAiProvider::new(config)
+            .with_deployment("gpt-4", "prod-gpt4")
+            .with_deployment("text-embedding-ada-002", "embeddings")
+            .with_api_version("2024-06-01");
+
+        assert_eq!(provider.deployment_for("gpt-4").unwrap(), "prod-gpt4");
+        assert!(provider.supports_model("gpt-4"));
+        assert!(matches!(provider.deployment_for("gpt-3.5-turbo"), Err(AiError::ModelNotAvailable(_))));
+        assert_eq!(provider.available_models(), vec!["gpt-4", "text-embedding-ada-002"]);
+
+        assert_eq!(
+            provider.endpoint_url("prod-gpt4", "chat/completions"),
+            "https://sira.openai.azure.com/openai/deployments/prod-gpt4/chat/completions?api-version=2024-06-01"
+        );
+        assert_eq!(
+            provider.endpoint_url("embeddings", "embeddings"),
+            "https://sira.openai.azure.com/openai/deployments/embeddings/embeddings?api-version=2024-06-01"
+        );
+
+        // The factory needs the resource endpoint
+        assert!(matches!(ProviderFactory::create_provider(provider_config(AiProvider::Azure)), Err(AiError::Config(_))));
+    }
+
+    #[test]
+    fn test_azure_deployments_come_from_provider_config() {
+        let config: ProviderConfig = serde_json::from_value(json!({
+            "provider": "Azure",
+            "api_key": "test",
+            "base_url": "https://sira.openai.azure.com",
+            "organization_id": null,
+            "timeout_seconds": 5,
+            "max_retries": 0,
+            "models": [],
+            "deployments": { "gpt-4": "prod-gpt4" },
+            "api_version": "2024-06-01"
+        })).unwrap();
+        let provider = AzureOpenAiProvider::new(config);
+
+        assert_eq!(provider.deployment_for("gpt-4").unwrap(), "prod-gpt4");
+        assert_eq!(
+            provider.endpoint_url("prod-gpt4", "chat/completions"),
+            "https://sira.openai.azure.com/openai/deployments/prod-gpt4/chat/completions?api-version=2024-06-01"
+        );
+        assert!(ProviderFactory::create_provider(provider.config.clone()).unwrap().supports_model("gpt-4"));
+    }
+
+    #[test]
+    fn test_cache_markers_translate_per_provider() {
+        let message = |role, text: &str, cache_control: Option<CacheControl>| ChatMessage {
+            cache_control,
+            ..ChatMessage::new(role, text)
+        };
+        let mut request = request("claude-3-haiku-20240307");
+        request.messages = vec![
+            message(MessageRole::System, "Long instructions", Some(CacheControl::Ephemeral)),
+            message(MessageRole::User, "Long document", Some(CacheControl::Ephemeral)),
+            message(MessageRole::User, "Question", None),
+        ];
+
+        let body = anthropic_chat_body(&request);
+        assert_eq!(body["system"], json!([{ "type": "text", "text": "Long instructions", "cache_control": { "type": "ephemeral" } }]));
+        assert_eq!(body["messages"][0]["content"][0]["cache_control"], json!({ "type": "ephemeral" }));
+        assert_eq!(body["messages"][1]["content"], json!("Question"));
+
+        // OpenAI caches on its own and must not see the marker
+        let body = openai_chat_body(&request, AiProvider::OpenAI);
+        assert_eq!(body["messages"][1], json!({ "role": "user", "content": "Long document", "name": null, "function_call": null }));
+
+        // Only the last markers within Anthropic's limit are kept
+        request.messages = (0..6).map(|i| message(MessageRole::User, &i.to_string(), Some(CacheControl::Ephemeral))).collect();
+        let body = anthropic_chat_body(&request);
+        let marked: Vec<bool> = body["messages"].as_array().unwrap().iter().map(|m| m["content"].is_array()).collect();
+        assert_eq!(marked, vec![false, false, true, true, true, true]);
+
+        // Markers of dropped system messages do not use up the limit
+        request.messages = vec![
+            message(MessageRole::System, "Instructions", None),
+            message(MessageRole::System, "More instructions", Some(CacheControl::Ephemeral)),
+            message(MessageRole::System, "Even more", Some(CacheControl::Ephemeral)),
+        ];
+        request.messages.extend((0..4).map(|i| message(MessageRole::User, &i.to_string(), Some(CacheControl::Ephemeral))));
+        let body = anthropic_chat_body(&request);
+        assert_eq!(body["system"], json!("Instructions"));
+        assert!(body["messages"].as_array().unwrap().iter().all(|m| m["content"].is_array()));
+    }
+
+    #[test]
+    fn test_usage_distinguishes_cached_tokens() {
+        let usage = AnthropicProvider::convert_usage(&json!({
+            "input_tokens": 20,
+            "cache_read_input_tokens": 1000,
+            "cache_creation_input_tokens": 300,
+            "output_tokens": 50
+        }));
+        assert_eq!((usage.prompt_tokens, usage.total_tokens), (1320, 1370));
+        assert_eq!(usage.cached_tokens(), 1000);
+        assert_eq!(usage.prompt_tokens_details.unwrap().cache_creation_tokens, 300);
+
+        let uncached = AnthropicProvider::convert_usage(&json!({ "input_tokens": 20, "output_tokens": 5 }));
+        assert!(uncached.prompt_tokens_details.is_none());
+
+        // OpenAI reports cached tokens in the same shape
+        let usage: Usage = serde_json::from_value(json!({
+            "prompt_tokens": 2048,
+            "completion_tokens": 10,
+            "total_tokens": 2058,
+            "prompt_tokens_details": { "cached_tokens": 1920, "audio_tokens": 0 }
+        })).unwrap();
+        assert_eq!(usage.cached_tokens(), 1920);
+    }
+
+    #[test]
+    fn test_anthropic_sends_images_as_image_blocks() {
+        let mut request = request("claude-3-haiku-20240307");
+        request.messages = vec![ChatMessage {
+            content: MessageContent::MultiModal(vec![
+                ContentPart::Text { text: "What is in these?".to_string() },
+                ContentPart::ImageUrl { image_url: crate::ImageUrl { url: "data:image/png;base64,iVBORw0K".to_string(), detail: None } },
+                ContentPart::ImageUrl { image_url: crate::ImageUrl { url: "https://example.com/cat.jpg".to_string(), detail: None } },
+            ]),
+            cache_control: Some(CacheControl::Ephemeral),
+            ..ChatMessage::user("")
+        }];
+
+        let body = anthropic_chat_body(&request);
+        assert_eq!(body["messages"][0]["content"], json!([
+            { "type": "text", "text": "What is in these?" },
+            { "type": "image", "source": { "type": "base64", "media_type": "image/png", "data": "iVBORw0K" } },
+            { "type": "image", "source": { "type": "url", "url": "https://example.com/cat.jpg" }, "cache_control": { "type": "ephemeral" } },
+        ]));
+    }
+
+    #[test]
+    fn test_tool_turns_translate_per_provider() {
+        let call = crate::ToolCall { id: "call_1".to_string(), name: "get_weather".to_string(), arguments: json!({ "city": "Paris" }) };
+        let result = crate::ToolResult { call_id: "call_1".to_string(), name: "get_weather".to_string(), content: "Sunny".to_string(), is_error: false };
+        let mut request = request("claude-3-haiku-20240307");
+        request.messages = vec![
+            ChatMessage::user("Weather in Paris?"),
+            ChatMessage { tool_calls: Some(vec![call]), ..ChatMessage::assistant("") },
+            result.to_message(),
+        ];
+
+        let body = openai_chat_body(&request, AiProvider::OpenAI);
+        assert_eq!(body["messages"][1]["role"], "assistant");
+        assert_eq!(body["messages"][1]["tool_calls"][0]["function"]["name"], "get_weather");
+        assert_eq!(body["messages"][2]["role"], "tool");
+        assert_eq!(body["messages"][2]["tool_call_id"], "call_1");
+
+        let body = anthropic_chat_body(&request);
+        assert_eq!(body["messages"][1]["content"], json!([
+            { "type": "tool_use", "id": "call_1", "name": "get_weather", "input": { "city": "Paris" } },
+        ]));
+        assert_eq!(body["messages"][2], json!({
+            "role": "user",
+            "content": [{ "type": "tool_result", "tool_use_id": "call_1", "content": "Sunny" }],
+        }));
+
+        // Roles keep their serialized names; providers' lowercase names still parse
+        assert_eq!(json!(MessageRole::Tool), json!("Tool"));
+        assert_eq!(serde_json::from_value::<MessageRole>(json!("assistant")).unwrap(), MessageRole::Assistant);
+    }
+
+    #[test]
+    fn test_sse_events_are_drained_once_complete() {
+        let mut buffer = b"data: {\"a\":1}\n\n: keep-alive\n\ndata: [DO".to_vec();
+        assert_eq!(drain_sse_data(&mut buffer), vec![r#"{"a":1}"#.to_string()]);
+        assert_eq!(buffer, b"data: [DO");
+
+        buffer.extend_from_slice(b"NE]\n\n");
+        assert_eq!(drain_sse_data(&mut buffer), vec!["[DONE]".to_string()]);
+        assert!(buffer.is_empty());
+
+        // A streamed OpenAI chunk parses into a completion chunk
+        let chunk: ChatCompletionChunk = serde_json::from_str(r#"{
+            "id": "chatcmpl-1", "object": "chat.completion.chunk", "created": 1, "model": "gpt-4",
+            "system_fingerprint": "fp", "choices": [{ "index": 0, "delta": { "role": "assistant", "content": "Hi" }, "finish_reason": null }]
+        }"#).unwrap();
+        assert_eq!(chunk.choices[0].delta.content.as_deref(), Some("Hi"));
+    }
+}
@@ -0,0 +1,181 @@
+//! Client-side pacing of provider requests
+//!
+//! Providers publish per-minute limits on requests and tokens and answer
+//! with 429 once they are exceeded. A [`ProviderRateLimiter`] keeps a token
+//! bucket for each limit that refills continuously over the minute; a
+//! request waits until both buckets can cover it, so bursts are spread out
+//! before they reach the provider instead of being rejected by it.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::debug;
+
+/// Published per-minute limits of a provider; `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimits {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+}
+
+/// Bucket holding up to a minute's allowance, refilled at the per-minute rate
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    refill_per_sec: f64,
+}
+
+impl TokenBucket {
+    fn per_minute(limit: u32) -> Self {
+        let capacity = limit.max(1) as f64;
+        Self {
+            capacity,
+            available: capacity,
+            refill_per_sec: capacity / 60.0,
+        }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.available = (self.available + elapsed.as_secs_f64() * self.refill_per_sec).min(self.capacity);
+    }
+
+    /// Time until `amount` is available; requests above capacity wait for a full bucket
+    fn wait_for(&self, amount: f64) -> Duration {
+        let missing = amount.min(self.capacity) - self.available;
+        if missing <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing / self.refill_per_sec)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+    refilled_at: Instant,
+}
+
+impl Buckets {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at);
+        self.refilled_at = now;
+        for bucket in [&mut self.requests, &mut self.tokens].into_iter().flatten() {
+            bucket.refill(elapsed);
+        }
+    }
+
+    fn wait_for(&self, tokens: u64) -> Duration {
+        let requests = self.requests.as_ref().map(|b| b.wait_for(1.0)).unwrap_or_default();
+        let tokens = self.tokens.as_ref().map(|b| b.wait_for(tokens as f64)).unwrap_or_default();
+        requests.max(tokens)
+    }
+}
+
+/// Token buckets pacing the requests sent to one provider
+#[derive(Debug)]
+pub struct ProviderRateLimiter {
+    limits: RateLimits,
+    buckets: Mutex<Buckets>,
+}
+
+impl ProviderRateLimiter {
+    /// Create a limiter with full buckets
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            buckets: Mutex::new(Buckets {
+                requests: limits.requests_per_minute.map(TokenBucket::per_minute),
+                tokens: limits.tokens_per_minute.map(TokenBucket::per_minute),
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Limits this limiter enforces
+    pub fn limits(&self) -> RateLimits {
+        self.limits
+    }
+
+    /// Whether a request of `tokens` could be sent right now
+    pub async fn has_capacity(&self, tokens: u64) -> bool {
+        let mut buckets = self.buckets.lock().await;
+        buckets.refill();
+        buckets.wait_for(tokens).is_zero()
+    }
+
+    /// Wait until a request of `tokens` fits in the buckets, then take it out
+    ///
+    /// Returns how long the request was delayed.
+    pub async fn acquire(&self, tokens: u64) -> Duration {
+        let started = Instant::now();
+        loop {
+            let wait = {
+                let mut buckets = self.buckets.lock().await;
+                buckets.refill();
+                let wait = buckets.wait_for(tokens);
+                if wait.is_zero() {
+                    if let Some(bucket) = &mut buckets.requests {
+                        bucket.available -= 1.0;
+                    }
+                    if let Some(bucket) = &mut buckets.tokens {
+                        bucket.available -= (tokens as f64).min(bucket.capacity);
+                    }
+                    return started.elapsed();
+                }
+                wait
+            };
+            debug!("Pacing request of {} tokens for {:?}", tokens, wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Correct the token bucket once a request's actual usage is known
+    ///
+    /// Usage above the estimate is taken out (possibly into debt, delaying
+    /// later requests); usage below it is given back.
+    pub async fn settle(&self, estimated_tokens: u64, actual_tokens: u64) {
+        let mut buckets = self.buckets.lock().await;
+        if let Some(bucket) = &mut buckets.tokens {
+            let difference = actual_tokens as f64 - estimated_tokens as f64;
+            bucket.available = (bucket.available - difference).min(bucket.capacity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_are_paced_to_the_per_minute_rate() {
+        let limiter = ProviderRateLimiter::new(RateLimits { requests_per_minute: Some(60), tokens_per_minute: None });
+
+        // The bucket starts full, so a minute's worth goes out at once
+        for _ in 0..60 {
+            assert_eq!(limiter.acquire(0).await, Duration::ZERO);
+        }
+        assert!(!limiter.has_capacity(0).await);
+
+        // Then one request per second
+        assert_eq!(limiter.acquire(0).await, Duration::from_secs(1));
+        assert_eq!(limiter.acquire(0).await, Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_debt_delays_the_next_request() {
+        let limiter = ProviderRateLimiter::new(RateLimits { requests_per_minute: None, tokens_per_minute: Some(600) });
+
+        assert_eq!(limiter.acquire(100).await, Duration::ZERO);
+        // The request used the rest of the minute's tokens
+        limiter.settle(100, 600).await;
+        assert!(!limiter.has_capacity(100).await);
+
+        // 100 tokens refill in 10 seconds
+        assert_eq!(limiter.acquire(100).await, Duration::from_secs(10));
+    }
+}
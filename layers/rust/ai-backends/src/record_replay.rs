@@ -0,0 +1,371 @@
+//! Record and replay of provider interactions
+//!
+//! A [`RecordReplayProvider`] in record mode forwards requests to a real
+//! provider and appends each request and its response or error to a
+//! cassette file, replaced atomically so a crash never leaves it torn. In
+//! replay mode it answers from the cassette alone, so regression tests run
+//! against real provider output without live calls. Requests match when
+//! their normalized form is equal: chat requests are normalized like
+//! [`CompletionCache`](crate::CompletionCache) keys plus temperature and
+//! response format, and the `user` field is always ignored. Chat requests
+//! are recorded as the client sent them; the recorded provider's request
+//! transform and JSON-mode fitting are only applied when forwarding, so a
+//! cassette replays the same whichever provider recorded it. Interactions
+//! recorded for the same request are replayed in order, the last one
+//! repeating once all have been played.
+
+use crate::{
+    AiError, AiProviderTrait, AiResult, ChatRequest, ChatResponse, CompletionCache, CompletionRequest,
+    CompletionResponse, EmbeddingRequest, EmbeddingResponse, ProviderCapabilities, fit_response_format,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+/// Whether a [`RecordReplayProvider`] calls the real provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Forward requests and record the interactions
+    Record,
+    /// Answer from recorded interactions only
+    Replay,
+}
+
+/// Kind of request an interaction answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum InteractionKind {
+    Chat,
+    Completion,
+    Embeddings,
+}
+
+/// One recorded request and its response or error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    kind: InteractionKind,
+    request: serde_json::Value,
+    #[serde(default)]
+    response: serde_json::Value,
+    /// Error the provider failed with, in place of a response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<serde_json::Value>,
+    #[serde(skip)]
+    played: bool,
+}
+
+/// Contents of a cassette file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Cassette {
+    provider: String,
+    models: Vec<String>,
+    interactions: Vec<Interaction>,
+}
+
+/// Provider wrapper recording interactions to, or replaying them from, a cassette
+pub struct RecordReplayProvider {
+    mode: CassetteMode,
+    path: PathBuf,
+    inner: Option<Box<dyn AiProviderTrait>>,
+    name: String,
+    models: Vec<String>,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordReplayProvider {
+    /// Record the interactions of `inner` to a new cassette at `path`
+    ///
+    /// The cassette is written after every interaction, failed ones
+    /// included, replacing any existing file.
+    pub fn record(inner: Box<dyn AiProviderTrait>, path: impl Into<PathBuf>) -> Self {
+        let name = inner.name().to_string();
+        let models = inner.available_models();
+        Self {
+            mode: CassetteMode::Record,
+            path: path.into(),
+            cassette: Mutex::new(Cassette {
+                provider: name.clone(),
+                models: models.clone(),
+                interactions: Vec::new(),
+            }),
+            inner: Some(inner),
+            name,
+            models,
+        }
+    }
+
+    /// Replay the cassette at `path`, reporting the recorded provider's name and models
+    pub fn replay(path: impl Into<PathBuf>) -> AiResult<Self> {
+        let path = path.into();
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| AiError::Config(format!("Failed to read cassette {}: {}", path.display(), e)))?;
+        let cassette: Cassette = serde_json::from_str(&content)
+            .map_err(|e| AiError::Parse(format!("Invalid cassette {}: {}", path.display(), e)))?;
+
+        Ok(Self {
+            mode: CassetteMode::Replay,
+            path,
+            inner: None,
+            name: cassette.provider.clone(),
+            models: cassette.models.clone(),
+            cassette: Mutex::new(cassette),
+        })
+    }
+
+    /// Mode the provider runs in
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// Number of interactions in the cassette
+    pub async fn interaction_count(&self) -> usize {
+        self.cassette.lock().await.interactions.len()
+    }
+
+    /// Append an interaction and replace the cassette with a temporary file
+    async fn record_interaction<Resp: Serialize>(&self, kind: InteractionKind, request: serde_json::Value, result: &AiResult<Resp>) -> AiResult<()> {
+        let (response, error) = match result {
+            Ok(response) => (serde_json::to_value(response), None),
+            Err(error) => (Ok(serde_json::Value::Null), Some(serde_json::to_value(error))),
+        };
+        let response = response.map_err(|e| AiError::Parse(format!("Failed to record response: {}", e)))?;
+        let error = error.transpose().map_err(|e| AiError::Parse(format!("Failed to record error: {}", e)))?;
+
+        let mut cassette = self.cassette.lock().await;
+        cassette.interactions.push(Interaction { kind, request, response, error, played: false });
+        let content = serde_json::to_string_pretty(&*cassette)
+            .map_err(|e| AiError::Parse(format!("Failed to serialize cassette: {}", e)))?;
+
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        tokio::fs::write(&temp_path, content).await
+            .map_err(|e| AiError::Config(format!("Failed to write cassette {}: {}", self.path.display(), e)))?;
+        tokio::fs::rename(&temp_path, &self.path).await
+            .map_err(|e| AiError::Config(format!("Failed to write cassette {}: {}", self.path.display(), e)))
+    }
+
+    /// Response or error of the next recorded interaction matching a request
+    async fn replayed_response<Resp: DeserializeOwned>(&self, kind: InteractionKind, request: &serde_json::Value) -> AiResult<Resp> {
+        let mut cassette = self.cassette.lock().await;
+        let matching: Vec<usize> = cassette.interactions.iter()
+            .enumerate()
+            .filter(|(_, interaction)| interaction.kind == kind && &interaction.request == request)
+            .map(|(index, _)| index)
+            .collect();
+        let index = matching.iter()
+            .copied()
+            .find(|index| !cassette.interactions[*index].played)
+            .or_else(|| matching.last().copied())
+            .ok_or_else(|| AiError::InvalidRequest(format!(
+                "No recorded {:?} interaction in {} matches request {}", kind, self.path.display(), request
+            )))?;
+
+        let interaction = &mut cassette.interactions[index];
+        interaction.played = true;
+        debug!("Replaying interaction {} from {}", index, self.path.display());
+        if let Some(error) = &interaction.error {
+            let error: AiError = serde_json::from_value(error.clone())
+                .map_err(|e| AiError::Parse(format!("Invalid recorded error: {}", e)))?;
+            return Err(error);
+        }
+        serde_json::from_value(interaction.response.clone())
+            .map_err(|e| AiError::Parse(format!("Invalid recorded response: {}", e)))
+    }
+
+    fn normalized_chat(request: &ChatRequest) -> serde_json::Value {
+        let mut normalized = CompletionCache::normalized(request);
+        normalized["temperature"] = json!(request.temperature);
+        normalized["response_format"] = json!(request.response_format);
+        normalized
+    }
+
+    /// Any request serialized without its `user` field
+    fn normalized<T: Serialize>(request: &T) -> AiResult<serde_json::Value> {
+        let mut normalized = serde_json::to_value(request)
+            .map_err(|e| AiError::Parse(format!("Failed to normalize request: {}", e)))?;
+        if let Some(object) = normalized.as_object_mut() {
+            object.remove("user");
+        }
+        Ok(normalized)
+    }
+}
+
+#[async_trait::async_trait]
+impl AiProviderTrait for RecordReplayProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn available_models(&self) -> Vec<String> {
+        self.models.clone()
+    }
+
+    async fn chat_completion(&self, request: &ChatRequest) -> AiResult<ChatResponse> {
+        let normalized = Self::normalized_chat(request);
+        match &self.inner {
+            Some(inner) => {
+                let forwarded = inner.transform_request(fit_response_format(request.clone(), inner.supports_json_mode()));
+                let result = inner.chat_completion(&forwarded).await;
+                self.record_interaction(InteractionKind::Chat, normalized, &result).await?;
+                result
+            }
+            None => self.replayed_response(InteractionKind::Chat, &normalized).await,
+        }
+    }
+
+    async fn text_completion(&self, request: &CompletionRequest) -> AiResult<CompletionResponse> {
+        let normalized = Self::normalized(request)?;
+        match &self.inner {
+            Some(inner) => {
+                let result = inner.text_completion(request).await;
+                self.record_interaction(InteractionKind::Completion, normalized, &result).await?;
+                result
+            }
+            None => self.replayed_response(InteractionKind::Completion, &normalized).await,
+        }
+    }
+
+    async fn create_embeddings(&self, request: &EmbeddingRequest) -> AiResult<EmbeddingResponse> {
+        let normalized = Self::normalized(request)?;
+        match &self.inner {
+            Some(inner) => {
+                let result = inner.create_embeddings(request).await;
+                self.record_interaction(InteractionKind::Embeddings, normalized, &result).await?;
+                result
+            }
+            None => self.replayed_response(InteractionKind::Embeddings, &normalized).await,
+        }
+    }
+
+    fn supports_model(&self, model: &str) -> bool {
+        match &self.inner {
+            Some(inner) => inner.supports_model(model),
+            None => self.models.iter().any(|m| m == model),
+        }
+    }
+
+    fn get_model_pricing(&self, model: &str) -> Option<f64> {
+        self.inner.as_ref().and_then(|inner| inner.get_model_pricing(model))
+    }
+
+    /// Response formats reach the cassette as sent, fitted to the recorded provider only when forwarding
+    fn supports_json_mode(&self) -> bool {
+        true
+    }
+
+    /// While replaying, whatever the cassette holds is supported
+    fn capabilities(&self) -> ProviderCapabilities {
+        match &self.inner {
+            Some(inner) => inner.capabilities(),
+            None => ProviderCapabilities::all(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_provider::MockProvider;
+    use crate::{AiBackendClient, ChatMessage};
+
+    fn chat(text: &str, user: Option<&str>) -> ChatRequest {
+        ChatRequest {
+            model: "model".to_string(),
+            messages: vec![ChatMessage::user(text)],
+            temperature: Some(0.7),
+            user: user.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn as_json<T: Serialize>(value: &T) -> serde_json::Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replay_matches_recording_without_live_calls() {
+        let path = std::env::temp_dir().join(format!("sira-cassette-{}.json", uuid::Uuid::new_v4()));
+        // Replies carry the call number, so each recording differs
+        let live = MockProvider::new("live", &["model"]);
+        let log = live.log();
+        let embeddings = EmbeddingRequest { input: vec!["text".to_string()], model: "model".to_string(), user: None };
+
+        let recorder = RecordReplayProvider::record(live.boxed(), &path);
+        let recorded = [
+            as_json(&recorder.chat_completion(&chat("hello", None)).await.unwrap()),
+            as_json(&recorder.chat_completion(&chat("hello", None)).await.unwrap()),
+            as_json(&recorder.chat_completion(&chat("goodbye", None)).await.unwrap()),
+        ];
+        let recorded_embeddings = as_json(&recorder.create_embeddings(&embeddings).await.unwrap());
+        drop(recorder);
+        assert_eq!(log.calls(), 4);
+
+        let player = RecordReplayProvider::replay(&path).unwrap();
+        assert_eq!(player.mode(), CassetteMode::Replay);
+        assert_eq!(player.name(), "live");
+        assert_eq!(player.interaction_count().await, 4);
+
+        // Surrounding whitespace and the user field do not affect matching
+        assert_eq!(as_json(&player.chat_completion(&chat(" hello\n", Some("alice"))).await.unwrap()), recorded[0]);
+        assert_eq!(as_json(&player.chat_completion(&chat("goodbye", None)).await.unwrap()), recorded[2]);
+        assert_eq!(as_json(&player.chat_completion(&chat("hello", None)).await.unwrap()), recorded[1]);
+        // The last recording repeats once all have been played
+        assert_eq!(as_json(&player.chat_completion(&chat("hello", None)).await.unwrap()), recorded[1]);
+        assert_eq!(as_json(&player.create_embeddings(&embeddings).await.unwrap()), recorded_embeddings);
+
+        let unrecorded = ChatRequest { temperature: Some(0.0), ..chat("hello", None) };
+        assert!(matches!(player.chat_completion(&unrecorded).await, Err(AiError::InvalidRequest(_))));
+        assert_eq!(log.calls(), 4);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_interactions_are_recorded_and_replayed() {
+        let path = std::env::temp_dir().join(format!("sira-cassette-{}.json", uuid::Uuid::new_v4()));
+        let failing = MockProvider::new("live", &["model"]).failing_with(AiError::RateLimit);
+
+        let recorder = RecordReplayProvider::record(failing.boxed(), &path);
+        assert!(matches!(recorder.chat_completion(&chat("hello", None)).await, Err(AiError::RateLimit(_))));
+        drop(recorder);
+
+        // The cassette was replaced whole, leaving no temporary file behind
+        let mut temp_path = path.clone().into_os_string();
+        temp_path.push(".tmp");
+        assert!(!std::path::Path::new(&temp_path).exists());
+
+        let player = RecordReplayProvider::replay(&path).unwrap();
+        assert_eq!(player.interaction_count().await, 1);
+        assert!(matches!(player.chat_completion(&chat("hello", None)).await, Err(AiError::RateLimit(_))));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_replays_what_a_provider_without_json_mode_recorded() {
+        let path = std::env::temp_dir().join(format!("sira-cassette-{}.json", uuid::Uuid::new_v4()));
+        let live = MockProvider::new("live", &["model"]).with_reply(r#"{"answer": 42}"#);
+        let log = live.log();
+        let request = ChatRequest { response_format: Some(crate::ResponseFormat::JsonObject), ..chat("hello", None) };
+
+        let client = AiBackendClient::new();
+        client.register_provider("live", Box::new(RecordReplayProvider::record(live.boxed(), &path))).await.unwrap();
+        let recorded = as_json(&client.chat_completion(request.clone()).await.unwrap());
+
+        // The provider got its JSON-mode instructions as a system message
+        let forwarded = log.requests();
+        assert_eq!(forwarded.len(), 1);
+        assert!(forwarded[0].response_format.is_none());
+        assert_eq!(forwarded[0].messages[0].role, crate::MessageRole::System);
+
+        let client = AiBackendClient::new();
+        client.register_provider("live", Box::new(RecordReplayProvider::replay(&path).unwrap())).await.unwrap();
+        assert_eq!(as_json(&client.chat_completion(request).await.unwrap()), recorded);
+        assert_eq!(log.calls(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
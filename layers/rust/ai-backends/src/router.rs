@@ -1,6 +1,8 @@
 //! Intelligent routing algorithms for AI backends
 
-use crate::{AiResult, AiError, AiProviderTrait, BackendMetrics, ChatRequest, CompletionRequest, EmbeddingRequest};
+use crate::{AiResult, AiError, AiProviderTrait, BackendMetrics, ChatRequest, CompletionRequest, EmbeddingRequest, ProviderCapabilities};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -69,6 +71,7 @@ pub struct IntelligentRouter {
     strategy: RoutingStrategy,
     cost_weight: f64,      // Weight for cost in balanced strategy (0.0-1.0)
     perf_weight: f64,      // Weight for performance in balanced strategy (0.0-1.0)
+    rng: std::sync::Mutex<StdRng>,
 }
 
 impl IntelligentRouter {
@@ -81,6 +84,7 @@ impl IntelligentRouter {
             strategy,
             cost_weight: 0.3,  // 30% weight on cost
             perf_weight: 0.7,  // 70% weight on performance
+            rng: std::sync::Mutex::new(StdRng::from_entropy()),
         }
     }
 
@@ -143,23 +147,23 @@ impl IntelligentRouter {
         }
     }
 
-    /// Route a chat completion request
+    /// Route a chat completion request to a provider with the capabilities it needs
     pub async fn route_chat_completion(&self, request: &ChatRequest) -> AiResult<RoutingDecision> {
-        self.route_request(&request.model, "chat_completion").await
+        self.route_request(&request.model, "chat_completion", ProviderCapabilities::required_by(request)).await
     }
 
     /// Route a text completion request
     pub async fn route_text_completion(&self, request: &CompletionRequest) -> AiResult<RoutingDecision> {
-        self.route_request(&request.model, "text_completion").await
+        self.route_request(&request.model, "text_completion", ProviderCapabilities::default()).await
     }
 
     /// Route an embedding request
     pub async fn route_embeddings(&self, request: &EmbeddingRequest) -> AiResult<RoutingDecision> {
-        self.route_request(&request.model, "embeddings").await
+        self.route_request(&request.model, "embeddings", ProviderCapabilities::for_embeddings()).await
     }
 
     /// Core routing logic
-    async fn route_request(&self, model: &str, request_type: &str) -> AiResult<RoutingDecision> {
+    async fn route_request(&self, model: &str, request_type: &str, needs: ProviderCapabilities) -> AiResult<RoutingDecision> {
         let providers = self.providers.read().await;
         let performance = self.performance_metrics.read().await;
 
@@ -180,6 +184,16 @@ impl IntelligentRouter {
             return Err(AiError::ModelNotAvailable(format!("No provider supports model: {}", model)));
         }
 
+        // Only providers covering every capability the request needs
+        let offered: Vec<ProviderCapabilities> = available_providers.iter().map(|(_, provider, _)| provider.capabilities()).collect();
+        available_providers.retain(|(_, provider, _)| provider.capabilities().satisfies(&needs));
+        if available_providers.is_empty() {
+            let unmet = ProviderCapabilities::unmet_by(offered, &needs);
+            return Err(AiError::ModelNotAvailable(format!(
+                "No provider for model {} supports {}", model, unmet.join(" and ")
+            )));
+        }
+
         // Apply routing strategy
         let decision = match self.strategy {
             RoutingStrategy::RoundRobin => self.route_round_robin(&available_providers, request_type).await,
@@ -264,10 +278,12 @@ impl IntelligentRouter {
 
     /// Random routing
     async fn route_random(&self, providers: &[(&String, &Box<dyn AiProviderTrait>, &ProviderPerformance)], request_type: &str) -> RoutingDecision {
-        use rand::{thread_rng, Rng};
-        let mut rng = thread_rng();
-        let index = rng.gen_range(0..providers.len());
-        let selected_provider = providers[index].0.clone();
+        // Index into name order so a seeded router picks the same providers on every run
+        let mut names: Vec<&String> = providers.iter().map(|(name, _, _)| *name).collect();
+        names.sort();
+
+        let index = self.rng.lock().unwrap_or_else(|e| e.into_inner()).gen_range(0..names.len());
+        let selected_provider = names[index].clone();
 
         RoutingDecision {
             provider_name: selected_provider,
@@ -369,6 +385,11 @@ impl IntelligentRouter {
         info!("Changed routing strategy to {:?}", strategy);
     }
 
+    /// Seed random routing so that its decisions are reproducible
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = std::sync::Mutex::new(StdRng::seed_from_u64(seed));
+    }
+
     /// Set balanced routing weights
     pub fn set_balanced_weights(&mut self, cost_weight: f64, perf_weight: f64) {
         self.cost_weight = cost_weight;
@@ -398,7 +419,8 @@ impl Default for IntelligentRouter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{AiProvider, ProviderConfig};
+    use crate::mock_provider::MockProvider;
+    use crate::{AiProvider, ProviderConfig, RequestPriority};
 
     #[tokio::test]
     async fn test_router_creation() {
@@ -419,6 +441,8 @@ mod tests {
             timeout_seconds: 30,
             max_retries: 3,
             models: vec![],
+            deployments: HashMap::new(),
+            api_version: None,
         };
 
         // Note: In real implementation, we'd create actual provider instances
@@ -435,19 +459,84 @@ mod tests {
             messages: vec![],
             model: "gpt-3.5-turbo".to_string(),
             temperature: None,
+            top_p: None,
             max_tokens: None,
             stream: None,
             functions: None,
             function_call: None,
+            tools: None,
             stop: None,
             presence_penalty: None,
             frequency_penalty: None,
             logit_bias: None,
             user: None,
+            response_format: None,
+            priority: RequestPriority::Interactive,
         };
 
         // Should fail with no providers
         let result = router.route_chat_completion(&request).await;
         assert!(result.is_err());
     }
+
+    fn chat_request(stream: bool) -> ChatRequest {
+        ChatRequest {
+            messages: vec![],
+            model: "any".to_string(),
+            temperature: None,
+            top_p: None,
+            max_tokens: None,
+            stream: Some(stream),
+            functions: None,
+            function_call: None,
+            tools: None,
+            stop: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            logit_bias: None,
+            user: None,
+            response_format: None,
+            priority: RequestPriority::Interactive,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streaming_request_skips_non_streaming_provider() {
+        let router = IntelligentRouter::new(RoutingStrategy::CostOptimized);
+        let batch = ProviderCapabilities { tools: true, ..ProviderCapabilities::default() };
+        let streaming = ProviderCapabilities { streaming: true, ..ProviderCapabilities::default() };
+        router.add_provider("batch", MockProvider::serving_any_model("batch").with_capabilities(batch).boxed()).await;
+        router.add_provider("streaming", MockProvider::serving_any_model("streaming").with_capabilities(streaming).boxed()).await;
+
+        for _ in 0..5 {
+            let decision = router.route_chat_completion(&chat_request(true)).await.unwrap();
+            assert_eq!(decision.provider_name, "streaming");
+        }
+
+        // A streaming request needing tools has no provider left
+        let mut request = chat_request(true);
+        request.tools = Some(vec![crate::ToolSpec {
+            name: "lookup".to_string(),
+            description: None,
+            parameters: serde_json::json!({ "type": "object" }),
+        }]);
+        let error = router.route_chat_completion(&request).await.unwrap_err();
+        assert_eq!(error.to_string(), "Model not available: No provider for model any supports streaming and tools");
+
+        // Only what no provider offers is reported missing
+        let mut request = chat_request(false);
+        request.tools = Some(vec![crate::ToolSpec {
+            name: "lookup".to_string(),
+            description: None,
+            parameters: serde_json::json!({ "type": "object" }),
+        }]);
+        request.messages = vec![crate::ChatMessage {
+            content: crate::MessageContent::MultiModal(vec![crate::ContentPart::ImageUrl {
+                image_url: crate::ImageUrl { url: "https://example.com/cat.png".to_string(), detail: None },
+            }]),
+            ..crate::ChatMessage::user("")
+        }];
+        let error = router.route_chat_completion(&request).await.unwrap_err();
+        assert_eq!(error.to_string(), "Model not available: No provider for model any supports vision");
+    }
 }
@@ -0,0 +1,110 @@
+//! Structured output
+//!
+//! Types implementing [`OutputSchema`] can be requested from a model with
+//! [`crate::AiBackendClient::structured_output`]: providers with a JSON mode
+//! are asked to honor the schema, others are told about it in the prompt, and
+//! the reply is validated against the schema before it is deserialized.
+
+use crate::{AiError, AiResult, ChatMessage, ChatRequest, MessageRole, ResponseFormat, ResponseSchema};
+use jsonschema::JSONSchema;
+use serde::de::DeserializeOwned;
+
+/// Type requested as structured output, described by a JSON Schema
+pub trait OutputSchema {
+    /// Name of the schema as shown to providers
+    fn schema_name() -> String;
+
+    /// JSON Schema of the type's serialized form
+    fn json_schema() -> serde_json::Value;
+
+    /// Whether providers should enforce the schema strictly
+    ///
+    /// Strict mode only accepts schemas whose objects all set
+    /// `additionalProperties: false` and require every property, so it is
+    /// off unless the schema is written for it.
+    fn strict() -> bool {
+        false
+    }
+}
+
+/// Compiled schema of a structured output type
+pub(crate) struct StructuredSchema {
+    name: String,
+    schema: serde_json::Value,
+    strict: bool,
+    validator: JSONSchema,
+}
+
+impl StructuredSchema {
+    /// Compile the schema of `T`
+    pub(crate) fn of<T: OutputSchema>() -> AiResult<Self> {
+        let name = T::schema_name();
+        let schema = T::json_schema();
+        let validator = JSONSchema::compile(&schema)
+            .map_err(|e| AiError::Config(format!("Invalid schema for {}: {}", name, e)))?;
+        Ok(Self { name, schema, strict: T::strict(), validator })
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Schema in the form providers with a JSON mode accept
+    pub(crate) fn response_schema(&self) -> ResponseSchema {
+        ResponseSchema { name: self.name.clone(), schema: self.schema.clone(), strict: self.strict }
+    }
+
+    /// Parse a reply, describing what is wrong with it when it does not fit the schema
+    pub(crate) fn parse<T: DeserializeOwned>(&self, reply: &str) -> Result<T, String> {
+        let value: serde_json::Value = serde_json::from_str(strip_code_fence(reply))
+            .map_err(|e| format!("the reply is not valid JSON ({})", e))?;
+
+        if let Err(errors) = self.validator.validate(&value) {
+            let errors: Vec<String> = errors
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    if path.is_empty() { e.to_string() } else { format!("{}: {}", path, e) }
+                })
+                .collect();
+            return Err(format!("the reply does not match the schema ({})", errors.join("; ")));
+        }
+
+        serde_json::from_value(value).map_err(|e| format!("the reply does not match the schema ({})", e))
+    }
+}
+
+/// Fit a request's response format to the provider serving it
+///
+/// Providers with a JSON mode get the format as is; for the others it is
+/// replaced by a system message asking for JSON, matching the schema if
+/// one was given.
+pub(crate) fn fit_response_format(mut request: ChatRequest, json_mode: bool) -> ChatRequest {
+    if json_mode {
+        return request;
+    }
+    let instructions = match request.response_format.take() {
+        Some(ResponseFormat::JsonSchema { json_schema }) => format!(
+            "Reply with only a JSON value, without prose or code fences, matching this JSON Schema:\n{}",
+            json_schema.schema
+        ),
+        Some(ResponseFormat::JsonObject) => "Reply with only a JSON object, without prose or code fences.".to_string(),
+        format => {
+            request.response_format = format;
+            return request;
+        }
+    };
+
+    let position = request.messages.iter().take_while(|m| m.role == MessageRole::System).count();
+    request.messages.insert(position, ChatMessage::system(instructions));
+    request
+}
+
+/// Body of a reply wrapped in a Markdown code fence, or the reply itself
+fn strip_code_fence(reply: &str) -> &str {
+    let trimmed = reply.trim();
+    let Some(body) = trimmed.strip_prefix("```").and_then(|rest| rest.strip_suffix("```")) else {
+        return trimmed;
+    };
+    // Drop the info string, e.g. `json`
+    body.split_once('\n').map_or(body, |(_, code)| code).trim()
+}
@@ -0,0 +1,190 @@
+//! Normalized finish reasons and provider errors
+//!
+//! Providers name the same outcomes differently: a truncated completion is
+//! `length` for OpenAI, `max_tokens` for Anthropic and `MAX_TOKENS` for
+//! Google, and a full context window is reported through three unrelated
+//! error payloads. These mappings translate each provider's vocabulary into
+//! [`FinishReason`] and the [`AiError`] variants behind [`crate::AiErrorKind`].
+
+use crate::{AiError, AiProvider, ChatChoice, CompletionChoice};
+
+/// Why a provider stopped generating
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinishReason {
+    /// Natural end of the answer or a stop sequence
+    Stop,
+    /// Token limit reached
+    Length,
+    /// Output withheld by a safety filter
+    ContentFilter,
+    /// The model called tools
+    ToolCalls,
+    /// Reason this mapping does not know, as reported
+    Other(String),
+}
+
+impl FinishReason {
+    /// Normalize a finish reason as reported by a provider
+    pub fn from_provider(provider: &AiProvider, reason: &str) -> Self {
+        let normalized = match provider {
+            AiProvider::Anthropic => match reason {
+                "end_turn" | "stop_sequence" => Some(FinishReason::Stop),
+                "max_tokens" => Some(FinishReason::Length),
+                "tool_use" => Some(FinishReason::ToolCalls),
+                "refusal" => Some(FinishReason::ContentFilter),
+                _ => None,
+            },
+            AiProvider::Google => match reason {
+                "STOP" => Some(FinishReason::Stop),
+                "MAX_TOKENS" => Some(FinishReason::Length),
+                "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => Some(FinishReason::ContentFilter),
+                _ => None,
+            },
+            AiProvider::OpenAI | AiProvider::Azure | AiProvider::Local => match reason {
+                "stop" => Some(FinishReason::Stop),
+                "length" => Some(FinishReason::Length),
+                "content_filter" => Some(FinishReason::ContentFilter),
+                "tool_calls" | "function_call" => Some(FinishReason::ToolCalls),
+                _ => None,
+            },
+        };
+        normalized.unwrap_or_else(|| FinishReason::Other(reason.to_string()))
+    }
+
+    /// Name of the reason in the OpenAI vocabulary responses are normalized to
+    pub fn as_str(&self) -> &str {
+        match self {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::ContentFilter => "content_filter",
+            FinishReason::ToolCalls => "tool_calls",
+            FinishReason::Other(reason) => reason,
+        }
+    }
+}
+
+impl ChatChoice {
+    /// Finish reason of the choice, normalized
+    pub fn normalized_finish_reason(&self) -> Option<FinishReason> {
+        self.finish_reason.as_deref().map(|reason| FinishReason::from_provider(&AiProvider::OpenAI, reason))
+    }
+}
+
+impl CompletionChoice {
+    /// Finish reason of the choice, normalized
+    pub fn normalized_finish_reason(&self) -> Option<FinishReason> {
+        self.finish_reason.as_deref().map(|reason| FinishReason::from_provider(&AiProvider::OpenAI, reason))
+    }
+}
+
+impl AiError {
+    /// Classify an unsuccessful provider response
+    ///
+    /// The provider's own error code is trusted first; the HTTP status
+    /// decides when the body carries none this mapping knows.
+    pub fn from_provider_response(provider: &AiProvider, status: u16, body: &str) -> AiError {
+        let payload: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+        let error = &payload["error"];
+        let code = match provider {
+            AiProvider::Anthropic => error["type"].as_str(),
+            AiProvider::Google => error["status"].as_str(),
+            AiProvider::OpenAI | AiProvider::Azure | AiProvider::Local => error["code"].as_str().or(error["type"].as_str()),
+        }.unwrap_or_default();
+        let detail = error["message"].as_str().unwrap_or(body);
+        let message = format!("{:?}: {}", provider, detail);
+
+        let lowered = detail.to_lowercase();
+        if code == "context_length_exceeded"
+            || lowered.contains("prompt is too long")
+            || lowered.contains("maximum context length")
+            || lowered.contains("exceeds the maximum number of tokens")
+        {
+            return AiError::ContextLengthExceeded(message);
+        }
+
+        match code {
+            "rate_limit_exceeded" | "rate_limit_error" | "RESOURCE_EXHAUSTED" => AiError::RateLimit(message),
+            "insufficient_quota" => AiError::QuotaExceeded(message),
+            "content_filter" | "content_policy_violation" => AiError::ContentBlocked(message),
+            "overloaded_error" | "api_error" | "server_error" | "UNAVAILABLE" | "INTERNAL" => AiError::ProviderUnavailable(message),
+            "DEADLINE_EXCEEDED" => AiError::Timeout(message),
+            "authentication_error" | "permission_error" | "invalid_api_key" | "UNAUTHENTICATED" | "PERMISSION_DENIED" => AiError::Auth(message),
+            "invalid_request_error" | "INVALID_ARGUMENT" => AiError::InvalidRequest(message),
+            _ => match status {
+                400 | 413 | 422 => AiError::InvalidRequest(message),
+                401 | 403 => AiError::Auth(message),
+                404 => AiError::ModelNotAvailable(message),
+                408 | 504 => AiError::Timeout(message),
+                429 => AiError::RateLimit(message),
+                500..=599 => AiError::ProviderUnavailable(message),
+                _ => AiError::Provider {
+                    provider: format!("{:?}", provider),
+                    message: format!("API error {}: {}", status, detail),
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AiErrorKind;
+
+    #[test]
+    fn test_finish_reasons_are_normalized_per_provider() {
+        let cases = [
+            (AiProvider::OpenAI, "stop", FinishReason::Stop),
+            (AiProvider::OpenAI, "length", FinishReason::Length),
+            (AiProvider::OpenAI, "content_filter", FinishReason::ContentFilter),
+            (AiProvider::OpenAI, "function_call", FinishReason::ToolCalls),
+            (AiProvider::Azure, "tool_calls", FinishReason::ToolCalls),
+            (AiProvider::Anthropic, "end_turn", FinishReason::Stop),
+            (AiProvider::Anthropic, "stop_sequence", FinishReason::Stop),
+            (AiProvider::Anthropic, "max_tokens", FinishReason::Length),
+            (AiProvider::Anthropic, "tool_use", FinishReason::ToolCalls),
+            (AiProvider::Anthropic, "refusal", FinishReason::ContentFilter),
+            (AiProvider::Google, "STOP", FinishReason::Stop),
+            (AiProvider::Google, "MAX_TOKENS", FinishReason::Length),
+            (AiProvider::Google, "SAFETY", FinishReason::ContentFilter),
+            (AiProvider::Google, "RECITATION", FinishReason::ContentFilter),
+            (AiProvider::Local, "eos", FinishReason::Other("eos".to_string())),
+        ];
+        for (provider, reason, expected) in cases {
+            assert_eq!(FinishReason::from_provider(&provider, reason), expected, "{:?} {}", provider, reason);
+        }
+        assert_eq!(FinishReason::from_provider(&AiProvider::Anthropic, "max_tokens").as_str(), "length");
+    }
+
+    #[test]
+    fn test_provider_error_payloads_are_normalized() {
+        let cases = [
+            (AiProvider::OpenAI, 400, r#"{"error":{"message":"This model's maximum context length is 8192 tokens.","type":"invalid_request_error","code":"context_length_exceeded"}}"#, AiErrorKind::ContextLengthExceeded),
+            (AiProvider::OpenAI, 429, r#"{"error":{"message":"Rate limit reached","type":"requests","code":"rate_limit_exceeded"}}"#, AiErrorKind::RateLimited),
+            (AiProvider::OpenAI, 400, r#"{"error":{"message":"Unrecognized request argument","type":"invalid_request_error","code":null}}"#, AiErrorKind::InvalidRequest),
+            (AiProvider::OpenAI, 503, "upstream connect error", AiErrorKind::ProviderUnavailable),
+            (AiProvider::Azure, 400, r#"{"error":{"message":"The response was filtered","code":"content_filter"}}"#, AiErrorKind::ContentFiltered),
+            (AiProvider::Anthropic, 400, r#"{"type":"error","error":{"type":"invalid_request_error","message":"prompt is too long: 210000 tokens > 200000 maximum"}}"#, AiErrorKind::ContextLengthExceeded),
+            (AiProvider::Anthropic, 529, r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#, AiErrorKind::ProviderUnavailable),
+            (AiProvider::Anthropic, 429, r#"{"type":"error","error":{"type":"rate_limit_error","message":"Too many requests"}}"#, AiErrorKind::RateLimited),
+            (AiProvider::Google, 400, r#"{"error":{"code":400,"message":"The input token count exceeds the maximum number of tokens allowed","status":"INVALID_ARGUMENT"}}"#, AiErrorKind::ContextLengthExceeded),
+            (AiProvider::Google, 429, r#"{"error":{"code":429,"message":"Quota exceeded","status":"RESOURCE_EXHAUSTED"}}"#, AiErrorKind::RateLimited),
+            (AiProvider::Google, 504, r#"{"error":{"code":504,"message":"Deadline exceeded","status":"DEADLINE_EXCEEDED"}}"#, AiErrorKind::Timeout),
+        ];
+        for (provider, status, body, expected) in cases {
+            let error = AiError::from_provider_response(&provider, status, body);
+            assert_eq!(error.kind(), expected, "{:?} {}: {}", provider, status, error);
+        }
+
+        let error = AiError::from_provider_response(&AiProvider::Anthropic, 529, r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#);
+        assert_eq!(error.to_string(), "Provider unavailable: Anthropic: Overloaded");
+        assert!(error.is_retryable());
+    }
+    #[test]
+    fn test_api_status_agrees_with_error_taxonomy() {
+        for status in [401, 403] {
+            assert!(matches!(crate::ApiStatus::from_code(status), crate::ApiStatus::AuthenticationFailed));
+            assert_eq!(AiError::from_provider_response(&AiProvider::OpenAI, status, "denied").kind(), AiErrorKind::Authentication);
+        }
+    }
+}
@@ -0,0 +1,465 @@
+//! Provider-neutral tool calling
+//!
+//! OpenAI, Anthropic and Google each describe tools and tool calls with a
+//! different wire format. Tools are defined once as [`ToolSpec`]s and
+//! translated per provider; tool calls found in provider responses are parsed
+//! into [`ToolCall`]s, and [`ToolResult`]s are translated back into the
+//! provider's message format.
+
+use crate::{AiError, AiProvider, AiResult, ChatMessage};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Tool definition shared by all providers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: Option<String>,
+    /// JSON Schema describing the tool arguments
+    pub parameters: Value,
+}
+
+/// Tool invocation requested by a model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// Result of running a tool, sent back to the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolResult {
+    pub call_id: String,
+    pub name: String,
+    pub content: String,
+    pub is_error: bool,
+}
+
+/// Wire format family used by a provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ToolFormat {
+    OpenAi,
+    Anthropic,
+    Google,
+}
+
+impl From<AiProvider> for ToolFormat {
+    fn from(provider: AiProvider) -> Self {
+        match provider {
+            AiProvider::Anthropic => ToolFormat::Anthropic,
+            AiProvider::Google => ToolFormat::Google,
+            // Azure and local servers speak the OpenAI format
+            AiProvider::OpenAI | AiProvider::Azure | AiProvider::Local => ToolFormat::OpenAi,
+        }
+    }
+}
+
+impl ToolSpec {
+    /// Create a new tool specification
+    pub fn new(name: &str, description: &str, parameters: Value) -> Self {
+        Self {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            parameters,
+        }
+    }
+
+    /// Translate this tool into a provider's tool definition
+    pub fn to_provider_format(&self, provider: AiProvider) -> Value {
+        match ToolFormat::from(provider) {
+            ToolFormat::OpenAi => json!({
+                "type": "function",
+                "function": {
+                    "name": self.name,
+                    "description": self.description,
+                    "parameters": self.parameters,
+                }
+            }),
+            ToolFormat::Anthropic => json!({
+                "name": self.name,
+                "description": self.description,
+                "input_schema": self.parameters,
+            }),
+            ToolFormat::Google => json!({
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            }),
+        }
+    }
+}
+
+/// Translate a set of tools into the value of a provider's `tools` field
+pub fn tools_to_provider_format(provider: AiProvider, tools: &[ToolSpec]) -> Value {
+    let definitions: Vec<Value> = tools.iter().map(|t| t.to_provider_format(provider)).collect();
+
+    match ToolFormat::from(provider) {
+        ToolFormat::Google => json!([{ "function_declarations": definitions }]),
+        _ => Value::Array(definitions),
+    }
+}
+
+impl ToolCall {
+    /// Translate this call into the form a provider expects when the
+    /// assistant turn is replayed in a conversation
+    pub fn to_provider_format(&self, provider: AiProvider) -> Value {
+        match ToolFormat::from(provider) {
+            ToolFormat::OpenAi => json!({
+                "id": self.id,
+                "type": "function",
+                "function": {
+                    "name": self.name,
+                    "arguments": self.arguments.to_string(),
+                }
+            }),
+            ToolFormat::Anthropic => json!({
+                "type": "tool_use",
+                "id": self.id,
+                "name": self.name,
+                "input": self.arguments,
+            }),
+            ToolFormat::Google => json!({
+                "functionCall": {
+                    "name": self.name,
+                    "args": self.arguments,
+                }
+            }),
+        }
+    }
+}
+
+impl ToolResult {
+    /// Conversation message carrying this result, for any provider
+    pub fn to_message(&self) -> ChatMessage {
+        ChatMessage {
+            name: Some(self.name.clone()),
+            ..ChatMessage::tool(self.call_id.clone(), self.content.clone())
+        }
+    }
+
+    /// Translate this result into a provider conversation message
+    pub fn to_provider_message(&self, provider: AiProvider) -> Value {
+        match ToolFormat::from(provider) {
+            ToolFormat::OpenAi => json!({
+                "role": "tool",
+                "tool_call_id": self.call_id,
+                "content": self.content,
+            }),
+            ToolFormat::Anthropic => json!({
+                "role": "user",
+                "content": [{
+                    "type": "tool_result",
+                    "tool_use_id": self.call_id,
+                    "content": self.content,
+                    "is_error": self.is_error,
+                }]
+            }),
+            ToolFormat::Google => json!({
+                "role": "function",
+                "parts": [{
+                    "functionResponse": {
+                        "name": self.name,
+                        "response": {
+                            "content": self.content,
+                            "is_error": self.is_error,
+                        }
+                    }
+                }]
+            }),
+        }
+    }
+}
+
+/// Parse the tool calls contained in a raw provider response
+pub fn parse_tool_calls(provider: AiProvider, response: &Value) -> AiResult<Vec<ToolCall>> {
+    match ToolFormat::from(provider) {
+        ToolFormat::OpenAi => {
+            let message = &response["choices"][0]["message"];
+            parse_openai_message_tool_calls(message)
+        }
+        ToolFormat::Anthropic => {
+            let blocks = response["content"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+            blocks.iter()
+                .filter(|block| block["type"] == "tool_use")
+                .map(|block| {
+                    Ok(ToolCall {
+                        id: required_str(block, "id")?,
+                        name: required_str(block, "name")?,
+                        arguments: block["input"].clone(),
+                    })
+                })
+                .collect()
+        }
+        ToolFormat::Google => {
+            let parts = response["candidates"][0]["content"]["parts"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or(&[]);
+            parts.iter()
+                .filter_map(|part| part.get("functionCall"))
+                .enumerate()
+                .map(|(index, call)| {
+                    let name = required_str(call, "name")?;
+                    Ok(ToolCall {
+                        // Google does not assign call IDs; derive a stable one
+                        id: format!("{}-{}", name, index),
+                        name,
+                        arguments: call["args"].clone(),
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+/// Parse `tool_calls` (or a legacy `function_call`) from an OpenAI message
+fn parse_openai_message_tool_calls(message: &Value) -> AiResult<Vec<ToolCall>> {
+    if let Some(calls) = message["tool_calls"].as_array() {
+        return calls.iter()
+            .map(|call| {
+                let function = &call["function"];
+                Ok(ToolCall {
+                    id: required_str(call, "id")?,
+                    name: required_str(function, "name")?,
+                    arguments: parse_openai_arguments(&function["arguments"])?,
+                })
+            })
+            .collect();
+    }
+
+    if message["function_call"].is_object() {
+        let function = &message["function_call"];
+        let name = required_str(function, "name")?;
+        return Ok(vec![ToolCall {
+            id: name.clone(),
+            name,
+            arguments: parse_openai_arguments(&function["arguments"])?,
+        }]);
+    }
+
+    Ok(Vec::new())
+}
+
+/// OpenAI encodes arguments as a JSON string
+fn parse_openai_arguments(arguments: &Value) -> AiResult<Value> {
+    match arguments {
+        Value::String(raw) if raw.trim().is_empty() => Ok(json!({})),
+        Value::String(raw) => serde_json::from_str(raw)
+            .map_err(|e| AiError::Parse(format!("Invalid tool call arguments: {}", e))),
+        Value::Null => Ok(json!({})),
+        other => Ok(other.clone()),
+    }
+}
+
+fn required_str(value: &Value, field: &str) -> AiResult<String> {
+    value[field].as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AiError::Parse(format!("Tool call is missing '{}'", field)))
+}
+
+/// Rewrite an OpenAI chat response so its tool calls use the common shape
+/// and can be deserialized into a `ChatResponse`
+pub(crate) fn normalize_openai_response(mut response: Value) -> AiResult<Value> {
+    if let Some(choices) = response["choices"].as_array_mut() {
+        for choice in choices {
+            let message = &mut choice["message"];
+            let calls = parse_openai_message_tool_calls(message)?;
+
+            if let Some(message) = message.as_object_mut() {
+                message.remove("tool_calls");
+                if !calls.is_empty() {
+                    message.insert("tool_calls".to_string(), serde_json::to_value(calls)
+                        .map_err(|e| AiError::Parse(e.to_string()))?);
+                }
+                // Tool-call turns carry `content: null`
+                if message.get("content").is_none_or(Value::is_null) {
+                    message.insert("content".to_string(), json!(""));
+                }
+            }
+        }
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weather_tool() -> ToolSpec {
+        ToolSpec::new(
+            "get_weather",
+            "Get the current weather for a city",
+            json!({
+                "type": "object",
+                "properties": { "city": { "type": "string" } },
+                "required": ["city"]
+            }),
+        )
+    }
+
+    #[test]
+    fn test_tool_spec_provider_formats() {
+        let tools = vec![weather_tool()];
+        let tool = &tools[0];
+
+        let openai = tools_to_provider_format(AiProvider::OpenAI, &tools);
+        assert_eq!(openai[0]["type"], "function");
+        assert_eq!(openai[0]["function"]["name"], "get_weather");
+        assert_eq!(openai[0]["function"]["parameters"], tool.parameters);
+
+        let azure = tools_to_provider_format(AiProvider::Azure, &tools);
+        assert_eq!(azure, openai);
+
+        let anthropic = tools_to_provider_format(AiProvider::Anthropic, &tools);
+        assert_eq!(anthropic[0]["name"], "get_weather");
+        assert_eq!(anthropic[0]["input_schema"], tool.parameters);
+        assert!(anthropic[0].get("function").is_none());
+
+        let google = tools_to_provider_format(AiProvider::Google, &tools);
+        let declarations = &google[0]["function_declarations"];
+        assert_eq!(declarations[0]["name"], "get_weather");
+        assert_eq!(declarations[0]["parameters"], tool.parameters);
+    }
+
+    #[test]
+    fn test_parse_openai_tool_calls() {
+        let response = json!({
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        });
+
+        let calls = parse_tool_calls(AiProvider::OpenAI, &response).unwrap();
+        assert_eq!(calls, vec![ToolCall {
+            id: "call_1".to_string(),
+            name: "get_weather".to_string(),
+            arguments: json!({ "city": "Paris" }),
+        }]);
+    }
+
+    #[test]
+    fn test_parse_openai_legacy_function_call() {
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "function_call": { "name": "get_weather", "arguments": "{\"city\":\"Oslo\"}" }
+                }
+            }]
+        });
+
+        let calls = parse_tool_calls(AiProvider::OpenAI, &response).unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].arguments, json!({ "city": "Oslo" }));
+    }
+
+    #[test]
+    fn test_parse_anthropic_tool_calls() {
+        let response = json!({
+            "content": [
+                { "type": "text", "text": "Let me check." },
+                { "type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": { "city": "Paris" } }
+            ],
+            "stop_reason": "tool_use"
+        });
+
+        let calls = parse_tool_calls(AiProvider::Anthropic, &response).unwrap();
+        assert_eq!(calls, vec![ToolCall {
+            id: "toolu_1".to_string(),
+            name: "get_weather".to_string(),
+            arguments: json!({ "city": "Paris" }),
+        }]);
+    }
+
+    #[test]
+    fn test_parse_google_tool_calls() {
+        let response = json!({
+            "candidates": [{
+                "content": {
+                    "parts": [{ "functionCall": { "name": "get_weather", "args": { "city": "Paris" } } }]
+                }
+            }]
+        });
+
+        let calls = parse_tool_calls(AiProvider::Google, &response).unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "get_weather");
+        assert_eq!(calls[0].arguments, json!({ "city": "Paris" }));
+    }
+
+    #[test]
+    fn test_parse_invalid_openai_arguments() {
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "tool_calls": [{ "id": "call_1", "function": { "name": "f", "arguments": "{oops" } }]
+                }
+            }]
+        });
+
+        assert!(matches!(parse_tool_calls(AiProvider::OpenAI, &response), Err(AiError::Parse(_))));
+    }
+
+    #[test]
+    fn test_tool_result_messages() {
+        let result = ToolResult {
+            call_id: "call_1".to_string(),
+            name: "get_weather".to_string(),
+            content: "sunny".to_string(),
+            is_error: false,
+        };
+
+        let openai = result.to_provider_message(AiProvider::OpenAI);
+        assert_eq!(openai["role"], "tool");
+        assert_eq!(openai["tool_call_id"], "call_1");
+
+        let anthropic = result.to_provider_message(AiProvider::Anthropic);
+        assert_eq!(anthropic["content"][0]["type"], "tool_result");
+        assert_eq!(anthropic["content"][0]["tool_use_id"], "call_1");
+
+        let google = result.to_provider_message(AiProvider::Google);
+        assert_eq!(google["parts"][0]["functionResponse"]["name"], "get_weather");
+    }
+
+    #[test]
+    fn test_normalized_openai_response_deserializes() {
+        let response = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        });
+
+        let normalized = normalize_openai_response(response).unwrap();
+        let parsed: crate::ChatResponse = serde_json::from_value(normalized).unwrap();
+        let calls = parsed.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].name, "get_weather");
+        assert_eq!(calls[0].arguments, json!({ "city": "Paris" }));
+    }
+}
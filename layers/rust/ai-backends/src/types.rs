@@ -3,6 +3,11 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::context_window::ContextFit;
+use crate::moderation::ModerationFlag;
+use crate::priority::RequestPriority;
+use crate::tool_calling::{ToolCall, ToolSpec};
+
 /// AI provider types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AiProvider {
@@ -50,12 +55,22 @@ pub struct ModelPricing {
 }
 
 /// Chat message role
+///
+/// Serialized by variant name. Providers are sent the lowercase names from
+/// [`MessageRole::as_str`], which are also accepted when parsing.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MessageRole {
+    #[serde(alias = "system")]
     System,
+    #[serde(alias = "user")]
     User,
+    #[serde(alias = "assistant")]
     Assistant,
+    #[serde(alias = "function")]
     Function,
+    /// Result of a tool call, answering the call in `tool_call_id`
+    #[serde(alias = "tool")]
+    Tool,
 }
 
 impl MessageRole {
@@ -65,6 +80,7 @@ impl MessageRole {
             MessageRole::User => "user",
             MessageRole::Assistant => "assistant",
             MessageRole::Function => "function",
+            MessageRole::Tool => "tool",
         }
     }
 }
@@ -76,6 +92,68 @@ pub struct ChatMessage {
     pub content: MessageContent,
     pub name: Option<String>,
     pub function_call: Option<FunctionCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// Tool call a [`MessageRole::Tool`] message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Marks the end of a prompt prefix providers may cache, see [`CacheControl`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+impl ChatMessage {
+    /// Create a text message from `role`
+    pub fn new(role: MessageRole, text: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: MessageContent::Text(text.into()),
+            name: None,
+            function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            cache_control: None,
+        }
+    }
+
+    pub fn system(text: impl Into<String>) -> Self {
+        Self::new(MessageRole::System, text)
+    }
+
+    pub fn user(text: impl Into<String>) -> Self {
+        Self::new(MessageRole::User, text)
+    }
+
+    pub fn assistant(text: impl Into<String>) -> Self {
+        Self::new(MessageRole::Assistant, text)
+    }
+
+    /// Create a message answering the tool call `call_id`
+    pub fn tool(call_id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(call_id.into()),
+            ..Self::new(MessageRole::Tool, text)
+        }
+    }
+
+    /// Mark the message as the end of a cacheable prompt prefix
+    pub fn with_cache_control(mut self, cache_control: CacheControl) -> Self {
+        self.cache_control = Some(cache_control);
+        self
+    }
+}
+
+/// Prompt caching marker on a message
+///
+/// Everything up to and including a marked message is a prefix the provider
+/// may cache and reuse for later requests starting the same way. Anthropic
+/// caches only marked prefixes; OpenAI caches long prefixes on its own and
+/// ignores markers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CacheControl {
+    /// Cached for a few minutes after last use
+    Ephemeral,
 }
 
 /// Message content (supports text and multi-modal)
@@ -86,6 +164,21 @@ pub enum MessageContent {
     MultiModal(Vec<ContentPart>),
 }
 
+impl MessageContent {
+    /// Text of the message; the text parts of multi-modal content are concatenated
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::MultiModal(parts) => parts.iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    ContentPart::ImageUrl { .. } => None,
+                })
+                .collect(),
+        }
+    }
+}
+
 /// Content part for multi-modal messages
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -119,7 +212,7 @@ pub struct Function {
 }
 
 /// Chat completion request
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChatRequest {
     pub messages: Vec<ChatMessage>,
     pub model: String,
@@ -129,11 +222,38 @@ pub struct ChatRequest {
     pub stream: Option<bool>,
     pub functions: Option<Vec<Function>>,
     pub function_call: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<ToolSpec>>,
     pub stop: Option<Vec<String>>,
     pub presence_penalty: Option<f32>,
     pub frequency_penalty: Option<f32>,
     pub logit_bias: Option<HashMap<String, f32>>,
     pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Lane the request waits in at a provider's concurrency limit; never sent to providers
+    #[serde(default)]
+    pub priority: RequestPriority,
+}
+
+/// Output format requested from providers with a JSON mode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    /// Any valid JSON object
+    JsonObject,
+    /// JSON matching a schema
+    JsonSchema { json_schema: ResponseSchema },
+}
+
+/// Schema of a [`ResponseFormat::JsonSchema`] response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseSchema {
+    pub name: String,
+    pub schema: serde_json::Value,
+    #[serde(default)]
+    pub strict: bool,
 }
 
 /// Chat completion response
@@ -145,6 +265,11 @@ pub struct ChatResponse {
     pub model: String,
     pub choices: Vec<ChatChoice>,
     pub usage: Option<Usage>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub moderation_flags: Vec<ModerationFlag>,
+    /// What the client dropped to fit the conversation into the model's context window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_fit: Option<ContextFit>,
 }
 
 /// Chat choice
@@ -155,12 +280,106 @@ pub struct ChatChoice {
     pub finish_reason: Option<String>,
 }
 
-/// Token usage information
+/// Streamed chat completion chunk
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<ChatChunkChoice>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    /// What the client dropped to fit the conversation, on the first chunk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_fit: Option<ContextFit>,
+}
+
+impl ChatCompletionChunk {
+    /// Split a complete response into chunks, one per choice, with usage on the last
+    pub fn from_response(response: ChatResponse) -> Vec<Self> {
+        let count = response.choices.len();
+        let mut usage = response.usage;
+        let mut context_fit = response.context_fit;
+
+        response.choices.into_iter()
+            .enumerate()
+            .map(|(position, choice)| Self {
+                id: response.id.clone(),
+                object: "chat.completion.chunk".to_string(),
+                created: response.created,
+                model: response.model.clone(),
+                choices: vec![ChatChunkChoice {
+                    index: choice.index,
+                    delta: ChatDelta {
+                        role: Some(choice.message.role),
+                        content: Some(choice.message.content.text()),
+                    },
+                    finish_reason: choice.finish_reason,
+                }],
+                usage: if position + 1 == count { usage.take() } else { None },
+                context_fit: context_fit.take(),
+            })
+            .collect()
+    }
+}
+
+/// Choice within a streamed chunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatChunkChoice {
+    pub index: u32,
+    pub delta: ChatDelta,
+    pub finish_reason: Option<String>,
+}
+
+/// Incremental message content of a streamed choice
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatDelta {
+    #[serde(default)]
+    pub role: Option<MessageRole>,
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+/// Token usage information
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Usage {
+    /// All prompt tokens, including those read from or written to the prompt cache
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+impl Usage {
+    /// Prompt tokens served from the provider's prompt cache
+    pub fn cached_tokens(&self) -> u32 {
+        self.prompt_tokens_details.as_ref().map_or(0, |details| details.cached_tokens)
+    }
+
+    /// Add the usage of another response to this one
+    pub fn add(&mut self, other: &Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        if let Some(other_details) = &other.prompt_tokens_details {
+            let details = self.prompt_tokens_details.get_or_insert_with(PromptTokensDetails::default);
+            details.cached_tokens += other_details.cached_tokens;
+            details.cache_creation_tokens += other_details.cache_creation_tokens;
+        }
+    }
+}
+
+/// Breakdown of prompt tokens by prompt cache use
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptTokensDetails {
+    /// Tokens read from the cache, billed at a discount
+    #[serde(default)]
+    pub cached_tokens: u32,
+    /// Tokens written to the cache, which Anthropic bills at a premium
+    #[serde(default)]
+    pub cache_creation_tokens: u32,
 }
 
 /// Text completion request
@@ -186,6 +405,8 @@ pub struct CompletionResponse {
     pub model: String,
     pub choices: Vec<CompletionChoice>,
     pub usage: Option<Usage>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub moderation_flags: Vec<ModerationFlag>,
 }
 
 /// Completion choice
@@ -231,6 +452,12 @@ pub struct ProviderConfig {
     pub timeout_seconds: u64,
     pub max_retries: u32,
     pub models: Vec<ModelInfo>,
+    /// Azure deployment serving each model, by model name
+    #[serde(default)]
+    pub deployments: HashMap<String, String>,
+    /// REST API version requested from providers that version per request, such as Azure
+    #[serde(default)]
+    pub api_version: Option<String>,
 }
 
 /// Backend metrics
@@ -241,4 +468,39 @@ pub struct BackendMetrics {
     pub tokens_used: u64,
     pub response_time_avg: f64,
     pub last_request_at: Option<u64>,
+    /// Requests waiting for a concurrency slot
+    pub queue_depth: u64,
+    /// Requests abandoned before completing, e.g. losers of a race
+    pub requests_cancelled: u64,
+}
+
+/// Settings for racing one request across several providers
+#[derive(Debug, Clone)]
+pub struct RaceConfig {
+    /// Maximum number of providers raced at once
+    pub max_providers: usize,
+    /// Cap on the combined estimated cost of all contenders, in dollars
+    pub max_estimated_cost: Option<f64>,
+    /// Providers whose share of failed requests is higher are not raced
+    pub max_failure_rate: f64,
+}
+
+impl Default for RaceConfig {
+    fn default() -> Self {
+        Self {
+            max_providers: 2,
+            max_estimated_cost: None,
+            max_failure_rate: 0.5,
+        }
+    }
+}
+
+/// Response of a raced request
+#[derive(Debug, Clone)]
+pub struct RaceResponse {
+    /// Provider whose response was returned
+    pub winner: String,
+    /// Providers the request was sent to, fastest expected first
+    pub contenders: Vec<String>,
+    pub response: ChatResponse,
 }
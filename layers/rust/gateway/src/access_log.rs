@@ -0,0 +1,359 @@
+//! Structured access logging
+//!
+//! [`AccessLogMiddleware`] writes one JSON line per request to an
+//! [`AccessLogSink`]: method, path, status, latency, correlation ID, the
+//! authenticated principal, the upstream that served the request and the
+//! token usage it reported. Only allowlisted request headers are logged, so
+//! credentials in unexpected headers never reach the log. Latencies are also
+//! counted into a [`LatencyHistogram`] for metrics.
+
+use crate::{GatewayResult, HttpRequest, HttpResponse, KeyStore, Middleware};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Response header naming the upstream that served a request
+pub const UPSTREAM_PROVIDER_HEADER: &str = "X-Upstream-Provider";
+
+/// Request headers written to access logs by default
+pub const DEFAULT_LOGGED_HEADERS: &[&str] = &[
+    "accept",
+    "accept-encoding",
+    "content-length",
+    "content-type",
+    "user-agent",
+    "x-request-id",
+];
+
+/// Default latency bucket upper bounds, in milliseconds
+pub const DEFAULT_LATENCY_BUCKETS_MS: &[f64] = &[5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0];
+
+/// Token usage reported in a response body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+/// One access log line
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessLogEntry {
+    /// RFC 3339 time the response was logged
+    pub timestamp: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub latency_ms: f64,
+    pub correlation_id: String,
+    pub principal: Option<String>,
+    pub upstream_provider: Option<String>,
+    pub usage: Option<TokenUsage>,
+    /// Allowlisted request headers
+    pub headers: HashMap<String, String>,
+}
+
+/// Destination of access log lines
+pub trait AccessLogSink: Send + Sync {
+    /// Write one JSON line
+    fn write(&self, line: &str);
+}
+
+/// Sink writing access log lines as `tracing` events with target `access_log`
+pub struct TracingAccessLogSink;
+
+impl AccessLogSink for TracingAccessLogSink {
+    fn write(&self, line: &str) {
+        tracing::info!(target: "access_log", "{}", line);
+    }
+}
+
+/// Sink keeping access log lines in memory
+#[derive(Default)]
+pub struct MemoryAccessLogSink {
+    lines: Mutex<Vec<String>>,
+}
+
+impl MemoryAccessLogSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lines written so far, oldest first
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().clone()
+    }
+}
+
+impl AccessLogSink for MemoryAccessLogSink {
+    fn write(&self, line: &str) {
+        self.lines.lock().unwrap().push(line.to_string());
+    }
+}
+
+/// Cumulative count of requests at or below a latency bound
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyBucket {
+    /// Upper bound in milliseconds; infinite for the last bucket
+    pub le: f64,
+    pub count: u64,
+}
+
+/// Histogram of request latencies over fixed buckets
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    bounds: Vec<f64>,
+    /// Per-bucket counts, with a final overflow bucket
+    counts: Vec<u64>,
+    sum_ms: f64,
+}
+
+impl LatencyHistogram {
+    /// Create a histogram with the given bucket upper bounds in milliseconds
+    pub fn new(mut bounds: Vec<f64>) -> Self {
+        bounds.retain(|bound| bound.is_finite());
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup();
+        let counts = vec![0; bounds.len() + 1];
+        Self { bounds, counts, sum_ms: 0.0 }
+    }
+
+    /// Count one request latency
+    pub fn observe(&mut self, latency_ms: f64) {
+        let index = self.bounds.iter().position(|bound| latency_ms <= *bound).unwrap_or(self.bounds.len());
+        self.counts[index] += 1;
+        self.sum_ms += latency_ms;
+    }
+
+    /// Cumulative counts per bucket, ending with the `+Inf` bucket
+    pub fn buckets(&self) -> Vec<LatencyBucket> {
+        let mut cumulative = 0;
+        self.bounds.iter().copied()
+            .chain(std::iter::once(f64::INFINITY))
+            .zip(&self.counts)
+            .map(|(le, count)| {
+                cumulative += count;
+                LatencyBucket { le, count: cumulative }
+            })
+            .collect()
+    }
+
+    /// Number of requests observed
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Sum of observed latencies in milliseconds
+    pub fn sum_ms(&self) -> f64 {
+        self.sum_ms
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new(DEFAULT_LATENCY_BUCKETS_MS.to_vec())
+    }
+}
+
+/// Access logging middleware
+///
+/// Must run after [`crate::RequestIdMiddleware`] so requests carry their
+/// correlation ID, after [`crate::CompressionMiddleware`] so response bodies
+/// are read before they are compressed, and before middleware that rejects
+/// requests, such as rate limiting, so rejections are logged too. Lines are
+/// written from [`Middleware::complete_request`], so every request that
+/// reached this middleware is logged, however it ended; latency is counted
+/// from the request's timestamp.
+pub struct AccessLogMiddleware {
+    sink: Arc<dyn AccessLogSink>,
+    key_store: Option<Arc<KeyStore>>,
+    logged_headers: Vec<String>,
+    histogram: Mutex<LatencyHistogram>,
+}
+
+impl AccessLogMiddleware {
+    pub fn new(sink: Arc<dyn AccessLogSink>) -> Self {
+        Self {
+            sink,
+            key_store: None,
+            logged_headers: DEFAULT_LOGGED_HEADERS.iter().map(|name| name.to_string()).collect(),
+            histogram: Mutex::new(LatencyHistogram::default()),
+        }
+    }
+
+    /// Log the principal a request's bearer token authenticates as
+    pub fn with_key_store(mut self, key_store: Arc<KeyStore>) -> Self {
+        self.key_store = Some(key_store);
+        self
+    }
+
+    /// Log another request header
+    pub fn with_logged_header(mut self, name: impl Into<String>) -> Self {
+        self.logged_headers.push(name.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Count latencies into buckets with these upper bounds in milliseconds
+    pub fn with_latency_buckets(self, bounds: Vec<f64>) -> Self {
+        Self { histogram: Mutex::new(LatencyHistogram::new(bounds)), ..self }
+    }
+
+    /// Snapshot of the latency histogram
+    pub fn latency_histogram(&self) -> LatencyHistogram {
+        self.histogram.lock().unwrap().clone()
+    }
+
+    fn is_logged(&self, name: &str) -> bool {
+        self.logged_headers.iter().any(|logged| logged.eq_ignore_ascii_case(name))
+    }
+
+    async fn principal(&self, request: &HttpRequest) -> Option<String> {
+        let key_store = self.key_store.as_ref()?;
+        let token = request.headers.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Authorization"))
+            .and_then(|(_, value)| value.strip_prefix("Bearer "))?;
+        key_store.authenticate(token.trim()).await.ok().map(|principal| principal.user_id)
+    }
+
+    /// Token usage from a JSON response body with an OpenAI-style `usage` object
+    fn usage(response: &HttpResponse) -> Option<TokenUsage> {
+        let body: serde_json::Value = serde_json::from_slice(response.body.as_deref()?).ok()?;
+        let usage = body.get("usage")?;
+        let tokens = |field: &str| usage.get(field).and_then(serde_json::Value::as_u64).unwrap_or(0);
+        Some(TokenUsage {
+            prompt_tokens: tokens("prompt_tokens"),
+            completion_tokens: tokens("completion_tokens"),
+            total_tokens: tokens("total_tokens"),
+        })
+    }
+}
+
+#[async_trait]
+impl Middleware for AccessLogMiddleware {
+    fn name(&self) -> &str {
+        "access_log"
+    }
+
+    async fn process_request(&self, _request: &mut HttpRequest) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    async fn process_response(&self, _response: &mut HttpResponse) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    async fn complete_request(&self, request: &HttpRequest, response: &mut HttpResponse) -> GatewayResult<()> {
+        let now_ms = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let latency_ms = now_ms.saturating_sub(request.timestamp) as f64;
+        self.histogram.lock().unwrap().observe(latency_ms);
+
+        let entry = AccessLogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            method: request.method.as_str().to_string(),
+            path: request.path.clone(),
+            status: response.status_code,
+            latency_ms,
+            correlation_id: request.request_id.clone(),
+            principal: self.principal(request).await,
+            upstream_provider: response.headers.iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(UPSTREAM_PROVIDER_HEADER))
+                .map(|(_, value)| value.clone()),
+            usage: Self::usage(response),
+            headers: request.headers.iter()
+                .filter(|(name, _)| self.is_logged(name))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+        };
+        match serde_json::to_string(&entry) {
+            Ok(line) => self.sink.write(&line),
+            Err(e) => tracing::warn!("Failed to serialize access log entry: {}", e),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GatewayError, HttpMethod, MiddlewareChain, RateLimitMiddleware};
+
+    #[tokio::test]
+    async fn test_access_log_line_has_fields_without_authorization() {
+        let key_store = Arc::new(KeyStore::new());
+        key_store.add_key("sk-secret", "alice").await;
+        let sink = Arc::new(MemoryAccessLogSink::new());
+        let middleware = AccessLogMiddleware::new(sink.clone())
+            .with_key_store(key_store)
+            .with_latency_buckets(vec![1000.0, 60000.0]);
+
+        let request = HttpRequest::new(HttpMethod::POST, "/v1/chat/completions")
+            .with_request_id("req-1")
+            .with_header("authorization", "Bearer sk-secret")
+            .with_header("X-Session-Token", "session-secret")
+            .with_header("Content-Type", "application/json");
+        let mut response = HttpResponse {
+            status_code: 200,
+            headers: HashMap::from([(UPSTREAM_PROVIDER_HEADER.to_string(), "openai".to_string())]),
+            body: Some(br#"{"usage":{"prompt_tokens":12,"completion_tokens":30,"total_tokens":42}}"#.to_vec()),
+            request_id: "req-1".to_string(),
+        };
+        middleware.complete_request(&request, &mut response).await.unwrap();
+
+        let lines = sink.lines();
+        assert_eq!(lines.len(), 1);
+        let entry: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(entry["method"], "POST");
+        assert_eq!(entry["path"], "/v1/chat/completions");
+        assert_eq!(entry["status"], 200);
+        assert!(entry["latency_ms"].is_f64());
+        assert_eq!(entry["correlation_id"], "req-1");
+        assert_eq!(entry["principal"], "alice");
+        assert_eq!(entry["upstream_provider"], "openai");
+        assert_eq!(entry["usage"]["total_tokens"], 42);
+        assert_eq!(entry["headers"]["Content-Type"], "application/json");
+        assert!(!lines[0].to_lowercase().contains("authorization"));
+        assert!(!lines[0].contains("sk-secret"));
+        assert!(!lines[0].contains("session-secret"));
+
+        let histogram = middleware.latency_histogram();
+        assert_eq!(histogram.count(), 1);
+        let buckets = histogram.buckets();
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[0], LatencyBucket { le: 1000.0, count: 1 });
+        assert_eq!(buckets[2].count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_and_failed_requests_are_logged() {
+        let sink = Arc::new(MemoryAccessLogSink::new());
+        let chain = MiddlewareChain::new()
+            .add_middleware(AccessLogMiddleware::new(sink.clone()))
+            .add_middleware(RateLimitMiddleware::new(1, 60));
+
+        let mut first = HttpRequest::new(HttpMethod::POST, "/v1/chat/completions").with_request_id("failed");
+        let failed = chain.handle(&mut first, |_| async { Err(GatewayError::Backend("connection refused".to_string())) }).await;
+        assert!(failed.is_err());
+
+        let mut second = HttpRequest::new(HttpMethod::POST, "/v1/chat/completions").with_request_id("limited");
+        let limited = chain.handle(&mut second, |_| async { panic!("rate limited requests must not dispatch") }).await;
+        assert!(limited.is_err());
+
+        let statuses: Vec<(String, u64)> = sink.lines().iter()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .map(|entry| (entry["correlation_id"].as_str().unwrap().to_string(), entry["status"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(statuses, vec![("failed".to_string(), 502), ("limited".to_string(), 429)]);
+    }
+
+    #[test]
+    fn test_latency_histogram_buckets_are_cumulative() {
+        let mut histogram = LatencyHistogram::new(vec![100.0, 10.0]);
+        for latency in [5.0, 10.0, 50.0, 500.0] {
+            histogram.observe(latency);
+        }
+        let counts: Vec<u64> = histogram.buckets().iter().map(|bucket| bucket.count).collect();
+        assert_eq!(counts, vec![2, 3, 4]);
+        assert_eq!(histogram.sum_ms(), 565.0);
+    }
+}
@@ -0,0 +1,52 @@
+//! API key authentication for Sira Gateway
+//!
+//! A [`KeyStore`] maps API keys to the [`Principal`] they authenticate. Keys
+//! are held as SHA-256 digests, so the store never keeps a usable secret.
+
+use crate::{GatewayError, GatewayResult};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+/// Identity a request or connection was authenticated as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub user_id: String,
+}
+
+/// Store of API keys and the principals they belong to
+#[derive(Default)]
+pub struct KeyStore {
+    /// Key digest -> principal
+    keys: RwLock<HashMap<String, Principal>>,
+}
+
+impl KeyStore {
+    /// Create an empty key store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Issue `key` to a user, replacing any principal it was issued to before
+    pub async fn add_key(&self, key: &str, user_id: impl Into<String>) {
+        let principal = Principal { user_id: user_id.into() };
+        self.keys.write().await.insert(Self::digest(key), principal);
+    }
+
+    /// Revoke a key, returning whether it existed
+    pub async fn revoke_key(&self, key: &str) -> bool {
+        self.keys.write().await.remove(&Self::digest(key)).is_some()
+    }
+
+    /// Resolve the principal a key belongs to
+    pub async fn authenticate(&self, key: &str) -> GatewayResult<Principal> {
+        self.keys.read().await
+            .get(&Self::digest(key))
+            .cloned()
+            .ok_or_else(|| GatewayError::Auth("Invalid API key".to_string()))
+    }
+
+    fn digest(key: &str) -> String {
+        hex::encode(Sha256::digest(key.as_bytes()))
+    }
+}
@@ -1,7 +1,16 @@
 //! Error types for Sira Gateway
 
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json, Response};
+use serde::{Deserialize, Serialize};
+use sira_ai_backends::AiError;
+use sira_session::SessionError;
+use sira_storage_backends::StorageError;
+use std::collections::HashMap;
 use thiserror::Error;
 
+use crate::{HttpMethod, HttpResponse};
+
 /// Gateway error types
 #[derive(Debug, Error)]
 pub enum GatewayError {
@@ -11,6 +20,9 @@ pub enum GatewayError {
     #[error("Routing error: {0}")]
     Routing(String),
 
+    #[error("Method {} not allowed for {path}", .method.as_str())]
+    MethodNotAllowed { method: HttpMethod, path: String, allowed: Vec<HttpMethod> },
+
     #[error("Authentication error: {0}")]
     Auth(String),
 
@@ -29,9 +41,30 @@ pub enum GatewayError {
     #[error("Parse error: {0}")]
     Parse(String),
 
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Unprocessable request: {0}")]
+    Unprocessable(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Request body failed validation: {}", .0.iter().map(FieldError::to_string).collect::<Vec<_>>().join("; "))]
+    Validation(Vec<FieldError>),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+
     #[error("Timeout error: {0}")]
     Timeout(String),
 
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+
     #[error("Internal Server Error: {0}")]
     InternalServerError(String),
 
@@ -41,10 +74,205 @@ pub enum GatewayError {
     #[error("AI Backend error: {0}")]
     AiBackendError(#[from] sira_ai_backends::AiError),
 
+    #[error("Storage error: {0}")]
+    StorageError(#[from] sira_storage_backends::StorageError),
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
 
+/// A request body field that failed validation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// JSON Pointer to the field, empty for the body itself
+    pub field: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.field.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.field, self.message)
+        }
+    }
+}
+
+impl GatewayError {
+    /// HTTP status the error is reported with
+    pub fn status(&self) -> HttpStatus {
+        match self {
+            GatewayError::Http(_) | GatewayError::Parse(_) | GatewayError::InvalidRequest(_) => HttpStatus::BadRequest,
+            GatewayError::Unprocessable(_) | GatewayError::Validation(_) => HttpStatus::UnprocessableEntity,
+            GatewayError::Conflict(_) => HttpStatus::Conflict,
+            GatewayError::PayloadTooLarge(_) => HttpStatus::PayloadTooLarge,
+            GatewayError::UnsupportedMediaType(_) => HttpStatus::UnsupportedMediaType,
+            GatewayError::Routing(_) => HttpStatus::NotFound,
+            GatewayError::MethodNotAllowed { .. } => HttpStatus::MethodNotAllowed,
+            GatewayError::Auth(_) => HttpStatus::Unauthorized,
+            GatewayError::RateLimit(_) => HttpStatus::TooManyRequests,
+            GatewayError::Backend(_) => HttpStatus::BadGateway,
+            GatewayError::Timeout(_) => HttpStatus::GatewayTimeout,
+            GatewayError::Unavailable(_) => HttpStatus::ServiceUnavailable,
+            GatewayError::SessionError(e) => match e {
+                SessionError::SessionNotFound(_) => HttpStatus::NotFound,
+                SessionError::SessionExpired(_) => HttpStatus::Unauthorized,
+                SessionError::ValidationError(_) => HttpStatus::UnprocessableEntity,
+                _ => HttpStatus::InternalServerError,
+            },
+            GatewayError::AiBackendError(e) => match e {
+                AiError::RateLimit(_) | AiError::QuotaExceeded(_) | AiError::BudgetExceeded { .. } => HttpStatus::TooManyRequests,
+                AiError::InvalidRequest(_) | AiError::ModelNotAvailable(_) | AiError::ContextLengthExceeded(_) => HttpStatus::BadRequest,
+                AiError::ContentBlocked(_) => HttpStatus::UnprocessableEntity,
+                AiError::Timeout(_) | AiError::Cancelled(_) => HttpStatus::GatewayTimeout,
+                AiError::ProviderUnavailable(_) => HttpStatus::ServiceUnavailable,
+                AiError::Config(_) => HttpStatus::InternalServerError,
+                _ => HttpStatus::BadGateway,
+            },
+            GatewayError::StorageError(e) => match e {
+                StorageError::KeyNotFound(_) => HttpStatus::NotFound,
+                StorageError::ConnectionError(_) => HttpStatus::ServiceUnavailable,
+                _ => HttpStatus::InternalServerError,
+            },
+            GatewayError::Config(_)
+            | GatewayError::Io(_)
+            | GatewayError::InternalServerError(_)
+            | GatewayError::Unknown(_) => HttpStatus::InternalServerError,
+        }
+    }
+
+    /// Stable machine-readable error code
+    pub fn code(&self) -> &'static str {
+        match self {
+            GatewayError::Http(_) => "http_error",
+            GatewayError::Routing(_) => "not_found",
+            GatewayError::MethodNotAllowed { .. } => "method_not_allowed",
+            GatewayError::Auth(_) => "authentication_failed",
+            GatewayError::RateLimit(_) => "rate_limit_exceeded",
+            GatewayError::Backend(_) => "backend_error",
+            GatewayError::Config(_) => "configuration_error",
+            GatewayError::Io(_) => "io_error",
+            GatewayError::Parse(_) => "parse_error",
+            GatewayError::InvalidRequest(_) => "invalid_request",
+            GatewayError::Unprocessable(_) => "unprocessable_request",
+            GatewayError::Validation(_) => "validation_failed",
+            GatewayError::Conflict(_) => "conflict",
+            GatewayError::PayloadTooLarge(_) => "payload_too_large",
+            GatewayError::UnsupportedMediaType(_) => "unsupported_media_type",
+            GatewayError::Timeout(_) => "timeout",
+            GatewayError::Unavailable(_) => "service_unavailable",
+            GatewayError::InternalServerError(_) => "internal_error",
+            GatewayError::SessionError(_) => "session_error",
+            GatewayError::AiBackendError(AiError::ContentBlocked(_)) => "content_blocked",
+            GatewayError::AiBackendError(AiError::BudgetExceeded { .. }) => "budget_exceeded",
+            GatewayError::AiBackendError(AiError::ContextLengthExceeded(_)) => "context_length_exceeded",
+            GatewayError::AiBackendError(_) => "ai_backend_error",
+            GatewayError::StorageError(_) => "storage_error",
+            GatewayError::Unknown(_) => "unknown_error",
+        }
+    }
+
+    /// Structured context attached to the envelope, if any
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            GatewayError::AiBackendError(AiError::Provider { provider, .. }) => {
+                Some(serde_json::json!({ "provider": provider }))
+            }
+            GatewayError::Validation(errors) => Some(serde_json::json!({ "fields": errors })),
+            _ => None,
+        }
+    }
+
+    /// Value of the `Allow` header sent with a 405 response
+    pub fn allow_header(&self) -> Option<String> {
+        match self {
+            GatewayError::MethodNotAllowed { allowed, .. } => {
+                Some(allowed.iter().map(HttpMethod::as_str).collect::<Vec<_>>().join(", "))
+            }
+            _ => None,
+        }
+    }
+
+    /// Build the error envelope returned to clients
+    ///
+    /// Server-side failures are reported with a generic message so internal
+    /// details are only logged.
+    pub fn to_envelope(&self, correlation_id: Option<&str>) -> ErrorEnvelope {
+        let status = self.status();
+        let message = if status == HttpStatus::InternalServerError {
+            status.reason_phrase().to_string()
+        } else {
+            self.to_string()
+        };
+
+        ErrorEnvelope {
+            error: ErrorBody {
+                code: self.code().to_string(),
+                message,
+                correlation_id: correlation_id.filter(|id| !id.is_empty()).map(str::to_string),
+                details: self.details(),
+            },
+        }
+    }
+
+    /// Convert into an HTTP response carrying the request's correlation ID
+    pub fn into_response_with_correlation_id(self, correlation_id: &str) -> Response {
+        let status = self.status();
+        if status == HttpStatus::InternalServerError {
+            tracing::error!("Request {} failed: {:?}", correlation_id, self);
+        }
+
+        let status = StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = (status, Json(self.to_envelope(Some(correlation_id)))).into_response();
+        if let Some(allow) = self.allow_header().and_then(|allow| allow.parse().ok()) {
+            response.headers_mut().insert(axum::http::header::ALLOW, allow);
+        }
+        response
+    }
+
+    /// Convert into a gateway response carrying the request's correlation ID
+    pub fn into_http_response(self, request_id: String) -> HttpResponse {
+        self.to_http_response(request_id)
+    }
+
+    /// Gateway response describing the error, carrying the request's correlation ID
+    pub fn to_http_response(&self, request_id: String) -> HttpResponse {
+        let envelope = self.to_envelope(Some(&request_id));
+        let mut headers = HashMap::from([("Content-Type".to_string(), "application/json".to_string())]);
+        if let Some(allow) = self.allow_header() {
+            headers.insert("Allow".to_string(), allow);
+        }
+        HttpResponse {
+            status_code: self.status().as_u16(),
+            headers,
+            body: serde_json::to_vec(&envelope).ok(),
+            request_id,
+        }
+    }
+}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        self.into_response_with_correlation_id("")
+    }
+}
+
+/// JSON error envelope: `{ "error": { code, message, correlation_id, details } }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorEnvelope {
+    pub error: ErrorBody,
+}
+
+/// Body of an [`ErrorEnvelope`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    pub correlation_id: Option<String>,
+    pub details: Option<serde_json::Value>,
+}
+
 /// HTTP status codes
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HttpStatus {
@@ -54,6 +282,10 @@ pub enum HttpStatus {
     Forbidden = 403,
     NotFound = 404,
     MethodNotAllowed = 405,
+    Conflict = 409,
+    PayloadTooLarge = 413,
+    UnsupportedMediaType = 415,
+    UnprocessableEntity = 422,
     TooManyRequests = 429,
     InternalServerError = 500,
     BadGateway = 502,
@@ -74,6 +306,10 @@ impl HttpStatus {
             HttpStatus::Forbidden => "Forbidden",
             HttpStatus::NotFound => "Not Found",
             HttpStatus::MethodNotAllowed => "Method Not Allowed",
+            HttpStatus::Conflict => "Conflict",
+            HttpStatus::PayloadTooLarge => "Payload Too Large",
+            HttpStatus::UnsupportedMediaType => "Unsupported Media Type",
+            HttpStatus::UnprocessableEntity => "Unprocessable Entity",
             HttpStatus::TooManyRequests => "Too Many Requests",
             HttpStatus::InternalServerError => "Internal Server Error",
             HttpStatus::BadGateway => "Bad Gateway",
@@ -82,3 +318,53 @@ impl HttpStatus {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn envelope_of(response: Response) -> serde_json::Value {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_errors_map_to_status_and_envelope() {
+        let response = GatewayError::RateLimit("100 requests per minute".to_string())
+            .into_response_with_correlation_id("req-1");
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(envelope_of(response).await, serde_json::json!({
+            "error": {
+                "code": "rate_limit_exceeded",
+                "message": "Rate limit exceeded: 100 requests per minute",
+                "correlation_id": "req-1",
+                "details": null,
+            }
+        }));
+
+        let response = GatewayError::AiBackendError(AiError::Provider {
+            provider: "openai".to_string(),
+            message: "upstream failure".to_string(),
+        }).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        let envelope = envelope_of(response).await;
+        assert_eq!(envelope["error"]["code"], "ai_backend_error");
+        assert_eq!(envelope["error"]["correlation_id"], serde_json::Value::Null);
+        assert_eq!(envelope["error"]["details"], serde_json::json!({ "provider": "openai" }));
+
+        assert_eq!(GatewayError::Auth("bad token".to_string()).status(), HttpStatus::Unauthorized);
+        assert_eq!(GatewayError::Routing("/missing".to_string()).status(), HttpStatus::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_internal_errors_hide_details() {
+        let response = GatewayError::Config("database password is hunter2".to_string())
+            .into_response_with_correlation_id("req-2");
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let envelope = envelope_of(response).await;
+        assert_eq!(envelope["error"]["code"], "configuration_error");
+        assert_eq!(envelope["error"]["message"], "Internal Server Error");
+        assert_eq!(envelope["error"]["correlation_id"], "req-2");
+    }
+}
@@ -1,11 +1,18 @@
 //! Request handlers for Sira Gateway
 
-use crate::{GatewayResult, GatewayError, HttpRequest, HttpResponse, RequestHandler, RouteMatch, BackendConfig};
+use crate::{GatewayResult, GatewayError, HttpRequest, HttpResponse, HttpMethod, HttpStatus, RequestHandler, RouteMatch, BackendConfig, RouteTransform, RouteTransforms, UPSTREAM_PROVIDER_HEADER};
 use async_trait::async_trait;
 use hyper::{Client, Request, Body, Uri};
 use hyper::client::HttpConnector;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sira_ai_backends::{AiBackendClient, EmbeddingData, EmbeddingRequest, EmbeddingResponse, Usage};
+use sira_storage_backends::StorageClient;
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
 
 /// Backend request handler
 pub struct BackendHandler {
@@ -62,31 +69,42 @@ impl BackendHandler {
         let req = req_builder.body(body)
             .map_err(|e| GatewayError::Backend(format!("Failed to build request: {}", e)))?;
 
-        // Make the request with timeout
-        let response = timeout(
-            Duration::from_secs(backend.timeout),
-            self.client.request(req)
-        ).await
-            .map_err(|_| GatewayError::Timeout(format!("Request timeout after {}s", backend.timeout)))?
-            .map_err(|e| GatewayError::Backend(format!("Request failed: {}", e)))?;
+        // Make the request with timeout, abandoning it once the request is cancelled
+        let exchange = async {
+            let response = timeout(
+                Duration::from_secs(backend.timeout),
+                self.client.request(req)
+            ).await
+                .map_err(|_| GatewayError::Timeout(format!("Request timeout after {}s", backend.timeout)))?
+                .map_err(|e| GatewayError::Backend(format!("Request failed: {}", e)))?;
 
-        // Convert response
-        let status_code = response.status().as_u16();
+            // Convert response
+            let status_code = response.status().as_u16();
 
-        let headers = response.headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-            .collect();
+            let mut headers: HashMap<String, String> = response.headers()
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+                .collect();
+            headers.insert(UPSTREAM_PROVIDER_HEADER.to_string(), backend.name.clone());
 
-        let body_bytes = hyper::body::to_bytes(response.into_body()).await
-            .map_err(|e| GatewayError::Backend(format!("Failed to read response body: {}", e)))?;
+            let body_bytes = hyper::body::to_bytes(response.into_body()).await
+                .map_err(|e| GatewayError::Backend(format!("Failed to read response body: {}", e)))?;
 
-        Ok(HttpResponse {
-            status_code,
-            headers,
-            body: Some(body_bytes.to_vec()),
-            request_id: request.request_id.clone(),
-        })
+            Ok(HttpResponse {
+                status_code,
+                headers,
+                body: Some(body_bytes.to_vec()),
+                request_id: request.request_id.clone(),
+            })
+        };
+
+        tokio::select! {
+            biased;
+            _ = request.cancellation.cancelled() => {
+                Err(GatewayError::Timeout(format!("Request {} was cancelled", request.request_id)))
+            }
+            response = exchange => response,
+        }
     }
 }
 
@@ -154,10 +172,236 @@ impl RequestHandler for StaticResponseHandler {
     }
 }
 
+/// Path served by [`EmbeddingsHandler`]
+pub const EMBEDDINGS_PATH: &str = "/v1/embeddings";
+
+/// Input of an embeddings request: one text or a batch
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+/// OpenAI-compatible embeddings request body
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingsRequestBody {
+    pub model: String,
+    pub input: EmbeddingInput,
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+/// Embeddings handler
+///
+/// Serves OpenAI-compatible `POST /v1/embeddings`. Inputs already in the
+/// embeddings cache are answered from it; the rest are sent to a provider
+/// supporting the model in batches of at most `max_batch_size` and
+/// reassembled in input order.
+pub struct EmbeddingsHandler {
+    ai_client: Arc<AiBackendClient>,
+    cache: Option<Arc<dyn StorageClient>>,
+    cache_ttl_seconds: u64,
+    key_prefix: String,
+    max_batch_size: usize,
+}
+
+impl EmbeddingsHandler {
+    pub fn new(ai_client: Arc<AiBackendClient>) -> Self {
+        Self {
+            ai_client,
+            cache: None,
+            cache_ttl_seconds: 86400,
+            key_prefix: "embedding:".to_string(),
+            max_batch_size: 2048,
+        }
+    }
+
+    /// Cache embeddings in a storage backend for `ttl_seconds`
+    pub fn with_cache(mut self, storage: Arc<dyn StorageClient>, ttl_seconds: u64) -> Self {
+        self.cache = Some(storage);
+        self.cache_ttl_seconds = ttl_seconds;
+        self
+    }
+
+    /// Limit how many inputs are sent to the provider in one request
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+
+    /// Cache key of one input embedded with a model
+    fn cache_key(&self, model: &str, input: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(model.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(input.as_bytes());
+        format!("{}{}", self.key_prefix, hex::encode(hasher.finalize()))
+    }
+
+    /// Embed inputs, serving cached ones from the cache
+    ///
+    /// Provider calls are abandoned once `cancellation` is cancelled.
+    pub async fn embed(&self, model: &str, inputs: Vec<String>, user: Option<String>, cancellation: &CancellationToken) -> GatewayResult<EmbeddingResponse> {
+        let mut embeddings: Vec<Option<Vec<f32>>> = vec![None; inputs.len()];
+
+        if let Some(cache) = &self.cache {
+            for (index, input) in inputs.iter().enumerate() {
+                if let Some(entry) = cache.get(&self.cache_key(model, input)).await? {
+                    embeddings[index] = serde_json::from_value(entry.value).ok();
+                }
+            }
+        }
+
+        let misses: Vec<usize> = (0..inputs.len()).filter(|&i| embeddings[i].is_none()).collect();
+        let mut usage = Usage::default();
+
+        for batch in misses.chunks(self.max_batch_size) {
+            let request = EmbeddingRequest {
+                input: batch.iter().map(|&i| inputs[i].clone()).collect(),
+                model: model.to_string(),
+                user: user.clone(),
+            };
+            let response = self.ai_client.create_embeddings_cancellable(request, cancellation).await?;
+
+            if response.data.len() != batch.len() {
+                return Err(GatewayError::Backend(format!(
+                    "Provider returned {} embeddings for {} inputs", response.data.len(), batch.len()
+                )));
+            }
+
+            usage.prompt_tokens += response.usage.prompt_tokens;
+            usage.total_tokens += response.usage.total_tokens;
+
+            for data in response.data {
+                let index = *batch.get(data.index as usize)
+                    .ok_or_else(|| GatewayError::Backend(format!("Provider returned out-of-range embedding index {}", data.index)))?;
+
+                if let Some(cache) = &self.cache {
+                    let value = serde_json::to_value(&data.embedding).map_err(|e| GatewayError::Parse(e.to_string()))?;
+                    cache.set(&self.cache_key(model, &inputs[index]), value, Some(self.cache_ttl_seconds)).await?;
+                }
+                embeddings[index] = Some(data.embedding);
+            }
+        }
+
+        let data = embeddings.into_iter()
+            .enumerate()
+            .map(|(index, embedding)| EmbeddingData {
+                object: "embedding".to_string(),
+                embedding: embedding.unwrap_or_default(),
+                index: index as u32,
+            })
+            .collect();
+
+        Ok(EmbeddingResponse {
+            object: "list".to_string(),
+            data,
+            model: model.to_string(),
+            usage,
+        })
+    }
+
+    fn json_response(status: HttpStatus, body: serde_json::Value, request_id: String) -> HttpResponse {
+        HttpResponse {
+            status_code: status.as_u16(),
+            headers: HashMap::from([("Content-Type".to_string(), "application/json".to_string())]),
+            body: Some(body.to_string().into_bytes()),
+            request_id,
+        }
+    }
+}
+
+#[async_trait]
+impl RequestHandler for EmbeddingsHandler {
+    async fn handle(&self, request: HttpRequest) -> GatewayResult<HttpResponse> {
+        let bad_request = |message: String| {
+            Ok(GatewayError::InvalidRequest(message).into_http_response(request.request_id.clone()))
+        };
+
+        if request.method != HttpMethod::POST {
+            return bad_request(format!("{} requires POST", EMBEDDINGS_PATH));
+        }
+
+        let body: EmbeddingsRequestBody = match serde_json::from_slice(request.body.as_deref().unwrap_or_default()) {
+            Ok(body) => body,
+            Err(e) => return bad_request(format!("Invalid embeddings request: {}", e)),
+        };
+
+        let inputs = match body.input {
+            EmbeddingInput::Single(input) => vec![input],
+            EmbeddingInput::Batch(inputs) => inputs,
+        };
+        if inputs.is_empty() {
+            return bad_request("Embeddings input must not be empty".to_string());
+        }
+
+        let response = self.embed(&body.model, inputs, body.user, &request.cancellation).await?;
+        let body = serde_json::to_value(&response).map_err(|e| GatewayError::Parse(e.to_string()))?;
+        Ok(Self::json_response(HttpStatus::Ok, body, request.request_id))
+    }
+}
+
+/// Path served by [`ModelsHandler`]
+pub const MODELS_PATH: &str = "/v1/models";
+
+/// Models listing handler
+///
+/// Serves OpenAI-compatible `GET /v1/models` from the model catalogs of all
+/// providers. Each model is listed once with the providers serving it, the
+/// aliases resolving to it, their capabilities and the cheapest pricing;
+/// providers whose catalog cannot be fetched are left out.
+pub struct ModelsHandler {
+    ai_client: Arc<AiBackendClient>,
+}
+
+impl ModelsHandler {
+    pub fn new(ai_client: Arc<AiBackendClient>) -> Self {
+        Self { ai_client }
+    }
+
+    /// Models in the OpenAI list shape
+    pub async fn list(&self) -> serde_json::Value {
+        let data: Vec<serde_json::Value> = self.ai_client.list_models().await.into_iter()
+            .map(|listing| serde_json::json!({
+                "id": listing.id,
+                "object": "model",
+                "created": 0,
+                "owned_by": listing.providers.first().cloned().unwrap_or_default(),
+                "providers": listing.providers,
+                "aliases": listing.aliases,
+                "capabilities": listing.capabilities,
+                "pricing": listing.pricing,
+            }))
+            .collect();
+
+        serde_json::json!({ "object": "list", "data": data })
+    }
+}
+
+#[async_trait]
+impl RequestHandler for ModelsHandler {
+    async fn handle(&self, request: HttpRequest) -> GatewayResult<HttpResponse> {
+        if !matches!(request.method, HttpMethod::GET | HttpMethod::HEAD) {
+            let error = GatewayError::MethodNotAllowed {
+                method: request.method,
+                path: MODELS_PATH.to_string(),
+                allowed: vec![HttpMethod::GET, HttpMethod::HEAD],
+            };
+            return Ok(error.into_http_response(request.request_id));
+        }
+
+        Ok(EmbeddingsHandler::json_response(HttpStatus::Ok, self.list().await, request.request_id))
+    }
+}
+
 /// Request dispatcher - routes requests to appropriate handlers
 pub struct RequestDispatcher {
     backend_handler: BackendHandler,
     health_handler: HealthCheckHandler,
+    embeddings_handler: Option<EmbeddingsHandler>,
+    models_handler: Option<ModelsHandler>,
+    transforms: RouteTransforms,
 }
 
 impl RequestDispatcher {
@@ -165,10 +409,40 @@ impl RequestDispatcher {
         Self {
             backend_handler: BackendHandler::new(),
             health_handler: HealthCheckHandler::new(),
+            embeddings_handler: None,
+            models_handler: None,
+            transforms: RouteTransforms::new(),
         }
     }
 
-    pub async fn dispatch(&self, request: HttpRequest, route_match: Option<RouteMatch>) -> GatewayResult<HttpResponse> {
+    /// Serve `/v1/embeddings` with the given handler
+    pub fn set_embeddings_handler(&mut self, handler: EmbeddingsHandler) {
+        self.embeddings_handler = Some(handler);
+    }
+
+    /// Serve `/v1/models` with the given handler
+    pub fn set_models_handler(&mut self, handler: ModelsHandler) {
+        self.models_handler = Some(handler);
+    }
+
+    /// Append a transform to a route, identified by route ID or, for unrouted paths, by path
+    pub fn add_route_transform(&mut self, route: impl Into<String>, transform: Arc<dyn RouteTransform>) {
+        self.transforms.add(route, transform);
+    }
+
+    pub async fn dispatch(&self, mut request: HttpRequest, route_match: Option<RouteMatch>) -> GatewayResult<HttpResponse> {
+        let route = match &route_match {
+            Some(route) => route.route_id.clone(),
+            None => request.path.clone(),
+        };
+
+        self.transforms.apply_request(&route, &mut request).await?;
+        let mut response = self.handle(request, route_match).await?;
+        self.transforms.apply_response(&route, &mut response).await?;
+        Ok(response)
+    }
+
+    async fn handle(&self, request: HttpRequest, route_match: Option<RouteMatch>) -> GatewayResult<HttpResponse> {
         match route_match {
             Some(route) => {
                 // Route to backend
@@ -178,15 +452,13 @@ impl RequestDispatcher {
                 // Handle special routes
                 if request.path == "/health" {
                     self.health_handler.handle(request).await
+                } else if let Some(handler) = self.embeddings_handler.as_ref().filter(|_| request.path == EMBEDDINGS_PATH) {
+                    handler.handle(request).await
+                } else if let Some(handler) = self.models_handler.as_ref().filter(|_| request.path == MODELS_PATH) {
+                    handler.handle(request).await
                 } else {
-                    // Return 404
-                    Ok(HttpResponse {
-                        status_code: 404,
-                        headers: vec![("Content-Type".to_string(), "application/json".to_string())]
-                            .into_iter().collect(),
-                        body: Some(r#"{"error":"Not Found"}"#.as_bytes().to_vec()),
-                        request_id: request.request_id,
-                    })
+                    let error = GatewayError::Routing(format!("No route for {}", request.path));
+                    Ok(error.into_http_response(request.request_id))
                 }
             }
         }
@@ -202,7 +474,108 @@ impl Default for RequestDispatcher {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
+    use sira_ai_backends::{MockLog, MockProvider};
+
+    /// Handler over a mock embedding each text as its length
+    async fn embeddings_handler() -> (EmbeddingsHandler, Arc<MockLog>) {
+        let provider = MockProvider::new("length", &["embed-model"]);
+        let log = provider.log();
+        let client = AiBackendClient::new();
+        client.register_provider("length", provider.boxed()).await.unwrap();
+        (EmbeddingsHandler::new(Arc::new(client)), log)
+    }
+
+    fn embeddings_request(body: serde_json::Value) -> HttpRequest {
+        HttpRequest::new(crate::HttpMethod::POST, EMBEDDINGS_PATH)
+            .with_request_id("test")
+            .with_body(body.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_batch_served_from_cache_on_second_call() {
+        use sira_storage_backends::{GenericStorageClient, MemoryBackend, StorageConfig};
+
+        let backend = MemoryBackend::new(StorageConfig::default());
+        let (handler, log) = embeddings_handler().await;
+        let handler = handler.with_cache(Arc::new(GenericStorageClient::new(Box::new(backend))), 60);
+
+        let body = serde_json::json!({ "model": "embed-model", "input": ["a", "bb"] });
+        let first = handler.handle(embeddings_request(body.clone())).await.unwrap();
+        let second = handler.handle(embeddings_request(body)).await.unwrap();
+
+        let first: serde_json::Value = serde_json::from_slice(&first.body.unwrap()).unwrap();
+        let second: serde_json::Value = serde_json::from_slice(&second.body.unwrap()).unwrap();
+        assert_eq!(first["data"], second["data"]);
+        assert_eq!(first["usage"]["prompt_tokens"], 2);
+        assert_eq!(second["usage"]["prompt_tokens"], 0);
+
+        // Only the first call reached the provider
+        assert_eq!(log.embedding_batches(), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_split_into_provider_batches() {
+        let (handler, log) = embeddings_handler().await;
+        let handler = handler.with_max_batch_size(2);
+
+        let body = serde_json::json!({ "model": "embed-model", "input": ["a", "bb", "ccc", "dddd", "eeeee"] });
+        let response = handler.handle(embeddings_request(body)).await.unwrap();
+        assert_eq!(response.status_code, 200);
+
+        let response: EmbeddingResponse = serde_json::from_slice(&response.body.unwrap()).unwrap();
+        let embeddings: Vec<(u32, Vec<f32>)> = response.data.into_iter().map(|d| (d.index, d.embedding)).collect();
+        assert_eq!(embeddings, vec![
+            (0, vec![1.0]), (1, vec![2.0]), (2, vec![3.0]), (3, vec![4.0]), (4, vec![5.0]),
+        ]);
+        assert_eq!(response.usage.total_tokens, 5);
+        assert_eq!(log.embedding_batches(), vec![2, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_embeddings_request_never_reaches_provider() {
+        let (handler, log) = embeddings_handler().await;
+
+        let request = embeddings_request(serde_json::json!({ "model": "embed-model", "input": ["a"] }));
+        request.cancellation.cancel();
+        let error = handler.handle(request).await.unwrap_err();
+        assert_eq!(error.status(), HttpStatus::GatewayTimeout);
+        assert!(log.embedding_batches().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_models_listing_aggregates_providers_without_duplicates() {
+        let client = AiBackendClient::new();
+        let down = MockProvider::new("down", &[]);
+        down.log().set_listing_fails(true);
+        for (name, provider) in [
+            ("alpha", MockProvider::new("alpha", &["shared", "alpha-only"]).with_pricing(0.02)),
+            ("beta", MockProvider::new("beta", &["shared", "beta-only", "fast"]).with_pricing(0.01)),
+            ("down", down),
+        ] {
+            client.register_provider(name, provider.boxed()).await.unwrap();
+        }
+        client.set_model_alias("default-chat", "shared").await;
+        let handler = ModelsHandler::new(Arc::new(client));
+
+        let request = HttpRequest::new(crate::HttpMethod::GET, MODELS_PATH).with_request_id("models");
+        let response = handler.handle(request).await.unwrap();
+        assert_eq!(response.status_code, 200);
+
+        let body: serde_json::Value = serde_json::from_slice(&response.body.unwrap()).unwrap();
+        assert_eq!(body["object"], "list");
+        let ids: Vec<&str> = body["data"].as_array().unwrap().iter().map(|model| model["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec!["alpha-only", "beta-only", "fast", "shared"]);
+
+        let shared = &body["data"][3];
+        assert_eq!(shared["object"], "model");
+        assert_eq!(shared["providers"], serde_json::json!(["alpha", "beta"]));
+        assert_eq!(shared["aliases"], serde_json::json!(["default-chat"]));
+        assert_eq!(shared["pricing"], 0.01);
+        assert_eq!(shared["capabilities"]["embeddings"], true);
+
+        let request = HttpRequest::new(crate::HttpMethod::POST, MODELS_PATH).with_request_id("post");
+        assert_eq!(handler.handle(request).await.unwrap().status_code, 405);
+    }
 
     #[tokio::test]
     async fn test_health_check_handler() {
@@ -217,6 +590,7 @@ mod tests {
             remote_addr: None,
             request_id: "test".to_string(),
             timestamp: 0,
+            cancellation: tokio_util::sync::CancellationToken::new(),
         };
 
         let response = handler.handle(request).await.unwrap();
@@ -237,6 +611,7 @@ mod tests {
             remote_addr: None,
             request_id: "test".to_string(),
             timestamp: 0,
+            cancellation: tokio_util::sync::CancellationToken::new(),
         };
 
         let response = handler.handle(request).await.unwrap();
@@ -10,6 +10,11 @@ pub mod middleware;
 pub mod handlers;
 pub mod server;
 pub mod websocket;
+pub mod websocket_deflate;
+pub mod auth;
+pub mod transform;
+pub mod multipart;
+pub mod access_log;
 
 /// Result type alias for gateway operations
 pub type GatewayResult<T> = Result<T, GatewayError>;
@@ -22,3 +27,8 @@ pub use middleware::*;
 pub use handlers::*;
 pub use server::*;
 pub use websocket::*;
+pub use websocket_deflate::*;
+pub use auth::*;
+pub use transform::*;
+pub use multipart::*;
+pub use access_log::*;
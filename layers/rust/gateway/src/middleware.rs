@@ -1,9 +1,16 @@
 //! Middleware implementations for Sira Gateway
 
-use crate::{GatewayResult, GatewayError, HttpRequest, HttpResponse, Middleware};
+use crate::{FieldError, GatewayResult, GatewayError, HttpMethod, HttpRequest, HttpResponse, Middleware, Router};
+use jsonschema::{error::ValidationErrorKind, JSONSchema};
 use async_trait::async_trait;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sira_storage_backends::StorageClient;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::io::Write;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 /// CORS middleware
@@ -174,6 +181,784 @@ impl Middleware for RequestIdMiddleware {
     }
 }
 
+/// Header carrying the client-chosen idempotency key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Response recorded for an idempotency key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredResponse {
+    fingerprint: String,
+    status_code: u16,
+    headers: HashMap<String, String>,
+    body: Option<Vec<u8>>,
+}
+
+/// How often a duplicate request checks whether the original has finished
+const IDEMPOTENCY_POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+/// Idempotency middleware
+///
+/// Requests carrying an `Idempotency-Key` header have their first response
+/// stored in the storage backend; retries with the same key within the TTL are
+/// answered from storage instead of being dispatched again. Reusing a key for a
+/// different request is rejected with 422.
+///
+/// The first request reserves its key before being dispatched. Duplicates
+/// arriving while it is in flight wait for its response and replay it, or are
+/// answered 409 if it takes longer than the in-flight wait. The reservation is
+/// released once the request completes, however it ends, and expires on its
+/// own if the request never does.
+pub struct IdempotencyMiddleware {
+    storage: Arc<dyn StorageClient>,
+    ttl_seconds: u64,
+    key_prefix: String,
+    reservation_ttl_seconds: u64,
+    in_flight_wait: Duration,
+}
+
+impl IdempotencyMiddleware {
+    pub fn new(storage: Arc<dyn StorageClient>, ttl_seconds: u64) -> Self {
+        Self {
+            storage,
+            ttl_seconds,
+            key_prefix: "idempotency:".to_string(),
+            reservation_ttl_seconds: 300,
+            in_flight_wait: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = prefix.into();
+        self
+    }
+
+    /// How long a reservation outlives a request that never completes
+    pub fn with_reservation_ttl(mut self, ttl_seconds: u64) -> Self {
+        self.reservation_ttl_seconds = ttl_seconds;
+        self
+    }
+
+    /// How long a duplicate waits for the in-flight original before 409
+    pub fn with_in_flight_wait(mut self, wait: Duration) -> Self {
+        self.in_flight_wait = wait;
+        self
+    }
+
+    fn idempotency_key(request: &HttpRequest) -> Option<&str> {
+        request
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(IDEMPOTENCY_KEY_HEADER))
+            .map(|(_, value)| value.as_str())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Fingerprint of the parts of a request that must match on replay
+    fn fingerprint(request: &HttpRequest) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(request.method.as_str().as_bytes());
+        hasher.update(b" ");
+        hasher.update(request.path.as_bytes());
+        hasher.update(b"\n");
+        if let Some(body) = &request.body {
+            hasher.update(body);
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    fn lock_key(storage_key: &str) -> String {
+        format!("{}:lock", storage_key)
+    }
+
+    fn owner_key(storage_key: &str) -> String {
+        format!("{}:owner", storage_key)
+    }
+
+    /// Answer from the stored response for the key, if there is one
+    async fn stored_response(&self, request: &HttpRequest, key: &str, storage_key: &str) -> GatewayResult<Option<HttpResponse>> {
+        let Some(entry) = self.storage.get(storage_key).await? else {
+            return Ok(None);
+        };
+        let stored: StoredResponse = serde_json::from_value(entry.value)
+            .map_err(|e| GatewayError::Parse(e.to_string()))?;
+
+        if stored.fingerprint != Self::fingerprint(request) {
+            tracing::warn!("Idempotency key {} reused with a different request", key);
+            let error = GatewayError::Unprocessable(format!(
+                "Idempotency key '{}' was already used for a different request", key
+            ));
+            return Ok(Some(error.into_http_response(request.request_id.clone())));
+        }
+
+        tracing::debug!("Replaying stored response for idempotency key {}", key);
+        let mut headers = stored.headers;
+        headers.insert("Idempotent-Replayed".to_string(), "true".to_string());
+        Ok(Some(HttpResponse {
+            status_code: stored.status_code,
+            headers,
+            body: stored.body,
+            request_id: request.request_id.clone(),
+        }))
+    }
+
+    /// Reserve the key for the request; false if another request holds it
+    async fn reserve(&self, request: &HttpRequest, storage_key: &str) -> GatewayResult<bool> {
+        // Only the increment that takes the counter from zero wins the key
+        let lock_key = Self::lock_key(storage_key);
+        if self.storage.increment(&lock_key, 1).await? != 1 {
+            return Ok(false);
+        }
+        self.storage.expire(&lock_key, self.reservation_ttl_seconds).await?;
+        self.storage
+            .set(&Self::owner_key(storage_key), serde_json::json!(request.request_id), Some(self.reservation_ttl_seconds))
+            .await?;
+        Ok(true)
+    }
+
+    async fn release(&self, storage_key: &str) -> GatewayResult<()> {
+        self.storage.delete(&Self::owner_key(storage_key)).await?;
+        self.storage.delete(&Self::lock_key(storage_key)).await?;
+        Ok(())
+    }
+
+    async fn store(&self, request: &HttpRequest, storage_key: &str, response: &HttpResponse) -> GatewayResult<()> {
+        // Server errors are left unrecorded so the client can retry them
+        if response.status_code >= 500 {
+            return Ok(());
+        }
+
+        let stored = StoredResponse {
+            fingerprint: Self::fingerprint(request),
+            status_code: response.status_code,
+            headers: response.headers.clone(),
+            body: response.body.clone(),
+        };
+        let value = serde_json::to_value(&stored).map_err(|e| GatewayError::Parse(e.to_string()))?;
+        self.storage.set(storage_key, value, Some(self.ttl_seconds)).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Middleware for IdempotencyMiddleware {
+    fn name(&self) -> &str {
+        "idempotency"
+    }
+
+    async fn process_request(&self, _request: &mut HttpRequest) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    async fn intercept_request(&self, request: &HttpRequest) -> GatewayResult<Option<HttpResponse>> {
+        let key = match Self::idempotency_key(request) {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let storage_key = format!("{}{}", self.key_prefix, key);
+
+        let deadline = Instant::now() + self.in_flight_wait;
+        loop {
+            if let Some(response) = self.stored_response(request, key, &storage_key).await? {
+                return Ok(Some(response));
+            }
+
+            if self.reserve(request, &storage_key).await? {
+                // The previous holder may have stored its response just before releasing
+                if let Some(response) = self.stored_response(request, key, &storage_key).await? {
+                    self.release(&storage_key).await?;
+                    return Ok(Some(response));
+                }
+                return Ok(None);
+            }
+
+            if Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(IDEMPOTENCY_POLL_INTERVAL).await;
+        }
+
+        tracing::warn!("Idempotency key {} is still in use by another request", key);
+        let error = GatewayError::Conflict(format!(
+            "A request with idempotency key '{}' is already in progress", key
+        ));
+        Ok(Some(error.into_http_response(request.request_id.clone())))
+    }
+
+    async fn process_response(&self, _response: &mut HttpResponse) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    async fn complete_request(&self, request: &HttpRequest, response: &mut HttpResponse) -> GatewayResult<()> {
+        let Some(key) = Self::idempotency_key(request) else {
+            return Ok(());
+        };
+        let storage_key = format!("{}{}", self.key_prefix, key);
+
+        // Replays, rejections and duplicates never held the reservation
+        let owner = self.storage.get(&Self::owner_key(&storage_key)).await?;
+        if owner.is_none_or(|entry| entry.value != serde_json::json!(request.request_id)) {
+            return Ok(());
+        }
+
+        let stored = self.store(request, &storage_key, response).await;
+        self.release(&storage_key).await?;
+        stored
+    }
+}
+
+/// Request timeout middleware
+///
+/// Limits how long a request may take to be handled, per route prefix. When
+/// the limit is exceeded the chain answers 504 and cancels the request's
+/// cancellation token so downstream AI or tool work stops.
+pub struct TimeoutMiddleware {
+    default_timeout: Duration,
+    route_timeouts: Vec<(String, Duration)>, // (path prefix, timeout)
+}
+
+impl TimeoutMiddleware {
+    pub fn new(default_timeout: Duration) -> Self {
+        Self {
+            default_timeout,
+            route_timeouts: Vec::new(),
+        }
+    }
+
+    /// Override the timeout for paths starting with `prefix`
+    pub fn with_route_timeout(mut self, prefix: impl Into<String>, timeout: Duration) -> Self {
+        self.route_timeouts.push((prefix.into(), timeout));
+        self
+    }
+}
+
+#[async_trait]
+impl Middleware for TimeoutMiddleware {
+    fn name(&self) -> &str {
+        "timeout"
+    }
+
+    async fn process_request(&self, _request: &mut HttpRequest) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    async fn process_response(&self, _response: &mut HttpResponse) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    fn request_timeout(&self, request: &HttpRequest) -> Option<Duration> {
+        // The most specific (longest) matching prefix wins
+        let timeout = self.route_timeouts.iter()
+            .filter(|(prefix, _)| request.path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, timeout)| *timeout)
+            .unwrap_or(self.default_timeout);
+
+        Some(timeout)
+    }
+}
+
+/// Request body validation middleware
+///
+/// JSON bodies of routes with a registered JSON Schema are validated before
+/// the handler runs. Bodies that fail are answered with 422 listing each
+/// offending field; bodies that are not JSON at all get 400.
+///
+/// Requests are matched with the gateway's own router, so a schema applies to
+/// exactly the requests its route handles. Like route transforms, a route is
+/// identified by its route ID, or by its path when no configured route matched.
+pub struct ValidationMiddleware {
+    router: Arc<RwLock<Router>>,
+    /// Compiled schemas keyed by method and route
+    schemas: HashMap<(HttpMethod, String), JSONSchema>,
+}
+
+impl ValidationMiddleware {
+    pub fn new(router: Arc<RwLock<Router>>) -> Self {
+        Self {
+            router,
+            schemas: HashMap::new(),
+        }
+    }
+
+    /// Validate bodies of `method` requests to `route` against `schema`
+    ///
+    /// Fails if the schema does not compile.
+    pub fn with_schema(mut self, method: HttpMethod, route: impl Into<String>, schema: serde_json::Value) -> GatewayResult<Self> {
+        let route = route.into();
+        let compiled = JSONSchema::compile(&schema).map_err(|e| GatewayError::Config(format!(
+            "Invalid body schema for {} {}: {}", method.as_str(), route, e
+        )))?;
+
+        self.schemas.insert((method, route), compiled);
+        Ok(self)
+    }
+
+    /// Route a request is validated as: its route ID, or its path if unrouted
+    async fn route_of(&self, request: &HttpRequest) -> String {
+        match self.router.read().await.match_route(request) {
+            Ok(route_match) => route_match.route_id,
+            Err(_) => request.path.clone(),
+        }
+    }
+
+    /// Field-level errors of `body` against `schema`, empty if it is valid
+    fn field_errors(schema: &JSONSchema, body: &serde_json::Value) -> Vec<FieldError> {
+        let Err(errors) = schema.validate(body) else {
+            return Vec::new();
+        };
+
+        errors
+            .map(|e| {
+                let mut field = e.instance_path.to_string();
+                // A missing property is reported against the object holding it
+                if let ValidationErrorKind::Required { property } = &e.kind {
+                    if let Some(property) = property.as_str() {
+                        field = format!("{}/{}", field, property.replace('~', "~0").replace('/', "~1"));
+                    }
+                }
+                FieldError { field, message: e.to_string() }
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Middleware for ValidationMiddleware {
+    fn name(&self) -> &str {
+        "validation"
+    }
+
+    async fn process_request(&self, _request: &mut HttpRequest) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    async fn process_response(&self, _response: &mut HttpResponse) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    async fn intercept_request(&self, request: &HttpRequest) -> GatewayResult<Option<HttpResponse>> {
+        if !self.schemas.keys().any(|(method, _)| *method == request.method) {
+            return Ok(None);
+        }
+        let route = self.route_of(request).await;
+        let Some(schema) = self.schemas.get(&(request.method, route)) else {
+            return Ok(None);
+        };
+
+        let body = match serde_json::from_slice(request.body.as_deref().unwrap_or_default()) {
+            Ok(body) => body,
+            Err(e) => {
+                let error = GatewayError::InvalidRequest(format!("Request body is not valid JSON: {}", e));
+                return Ok(Some(error.into_http_response(request.request_id.clone())));
+            }
+        };
+
+        let errors = Self::field_errors(schema, &body);
+        if errors.is_empty() {
+            return Ok(None);
+        }
+
+        tracing::debug!("Request {} failed body validation with {} errors", request.request_id, errors.len());
+        Ok(Some(GatewayError::Validation(errors).into_http_response(request.request_id.clone())))
+    }
+}
+
+/// Circuit breaker settings
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Fraction of failed requests (0.0-1.0) in the window that opens the breaker
+    pub failure_rate_threshold: f64,
+    /// Requests the window must hold before the failure rate is trusted
+    pub min_requests: usize,
+    /// How far back request outcomes are counted
+    pub window: Duration,
+    /// How long the breaker stays open before probing the upstream
+    pub open_duration: Duration,
+    /// How long a probe may stay unanswered before another request replaces it
+    pub probe_timeout: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_rate_threshold: 0.5,
+            min_requests: 20,
+            window: Duration::from_secs(60),
+            open_duration: Duration::from_secs(30),
+            probe_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// State of a [`CircuitBreakerMiddleware`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests pass through
+    Closed,
+    /// Requests are rejected until the open period ends
+    Open,
+    /// A single probe request is testing whether the upstream recovered
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct CircuitBreakerState {
+    state: CircuitState,
+    opened_at: Option<Instant>,
+    /// Request ID of the half-open probe in flight, and when it was admitted
+    probe: Option<(String, Instant)>,
+    /// Admitted requests whose outcome is still to be counted, with when they were admitted
+    admitted: HashMap<String, Instant>,
+    /// (completed at, failed) of recent requests
+    outcomes: VecDeque<(Instant, bool)>,
+}
+
+/// Circuit breaker middleware
+///
+/// Counts upstream failures (5xx responses, including handler errors and
+/// timeouts) over a sliding window. Once the failure rate reaches the
+/// threshold the breaker opens and new requests are answered with 503 and
+/// `Retry-After` without being dispatched. After the open period a single
+/// probe request is let through: success closes the breaker, failure opens it
+/// again. A probe that is never answered, e.g. because the client went away,
+/// is replaced by the next request after `probe_timeout`.
+///
+/// Outcomes are recorded through [`Middleware::complete_request`], so the
+/// breaker must run in a [`MiddlewareChain`] driven by [`MiddlewareChain::handle`].
+pub struct CircuitBreakerMiddleware {
+    config: CircuitBreakerConfig,
+    route_prefixes: Vec<String>,
+    state: RwLock<CircuitBreakerState>,
+}
+
+impl CircuitBreakerMiddleware {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            route_prefixes: Vec::new(),
+            state: RwLock::new(CircuitBreakerState {
+                state: CircuitState::Closed,
+                opened_at: None,
+                probe: None,
+                admitted: HashMap::new(),
+                outcomes: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Only guard paths starting with `prefix`; by default every request is guarded
+    pub fn with_route_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.route_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Current breaker state
+    pub async fn state(&self) -> CircuitState {
+        self.state.read().await.state
+    }
+
+    fn guards(&self, request: &HttpRequest) -> bool {
+        self.route_prefixes.is_empty()
+            || self.route_prefixes.iter().any(|prefix| request.path.starts_with(prefix.as_str()))
+    }
+
+    fn reject(request: &HttpRequest, retry_after: Duration) -> HttpResponse {
+        let error = GatewayError::Unavailable("Upstream is failing, circuit breaker is open".to_string());
+        let mut response = error.into_http_response(request.request_id.clone());
+        let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        response.headers.insert("Retry-After".to_string(), seconds.max(1).to_string());
+        response
+    }
+
+    fn open(&self, breaker: &mut CircuitBreakerState, now: Instant) {
+        breaker.state = CircuitState::Open;
+        breaker.opened_at = Some(now);
+        breaker.probe = None;
+        breaker.outcomes.clear();
+    }
+}
+
+#[async_trait]
+impl Middleware for CircuitBreakerMiddleware {
+    fn name(&self) -> &str {
+        "circuit_breaker"
+    }
+
+    async fn process_request(&self, _request: &mut HttpRequest) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    async fn intercept_request(&self, request: &HttpRequest) -> GatewayResult<Option<HttpResponse>> {
+        if !self.guards(request) {
+            return Ok(None);
+        }
+
+        let now = Instant::now();
+        let mut breaker = self.state.write().await;
+
+        // Requests abandoned without an answer are never completed
+        let window = self.config.window.max(self.config.probe_timeout);
+        breaker.admitted.retain(|_, admitted_at| now - *admitted_at <= window);
+
+        match breaker.state {
+            CircuitState::Closed => {}
+            CircuitState::Open => {
+                let elapsed = breaker.opened_at.map_or(self.config.open_duration, |opened| now - opened);
+                if elapsed < self.config.open_duration {
+                    return Ok(Some(Self::reject(request, self.config.open_duration - elapsed)));
+                }
+
+                tracing::info!("Circuit breaker half-open, probing upstream with request {}", request.request_id);
+                breaker.state = CircuitState::HalfOpen;
+                breaker.probe = Some((request.request_id.clone(), now));
+            }
+            CircuitState::HalfOpen => {
+                let probe_age = breaker.probe.as_ref().map_or(self.config.probe_timeout, |(_, started)| now - *started);
+                if probe_age < self.config.probe_timeout {
+                    return Ok(Some(Self::reject(request, self.config.probe_timeout - probe_age)));
+                }
+
+                tracing::warn!("Circuit breaker probe went unanswered, probing again with request {}", request.request_id);
+                breaker.probe = Some((request.request_id.clone(), now));
+            }
+        }
+
+        breaker.admitted.insert(request.request_id.clone(), now);
+        Ok(None)
+    }
+
+    async fn process_response(&self, _response: &mut HttpResponse) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    async fn complete_request(&self, request: &HttpRequest, response: &mut HttpResponse) -> GatewayResult<()> {
+        let now = Instant::now();
+        let mut breaker = self.state.write().await;
+
+        // Only requests this breaker let through count, not its own rejections
+        if breaker.admitted.remove(&request.request_id).is_none() {
+            return Ok(());
+        }
+        let failed = response.status_code >= 500;
+
+        if breaker.probe.as_ref().is_some_and(|(probe, _)| *probe == request.request_id) {
+            if failed {
+                tracing::warn!("Circuit breaker probe failed with {}, reopening", response.status_code);
+                self.open(&mut breaker, now);
+            } else {
+                tracing::info!("Circuit breaker probe succeeded, closing");
+                breaker.state = CircuitState::Closed;
+                breaker.opened_at = None;
+                breaker.probe = None;
+                breaker.outcomes.clear();
+            }
+            return Ok(());
+        }
+
+        // Late responses to requests admitted before the breaker opened are not counted
+        if breaker.state != CircuitState::Closed {
+            return Ok(());
+        }
+
+        breaker.outcomes.push_back((now, failed));
+        while breaker.outcomes.front().is_some_and(|(at, _)| now - *at > self.config.window) {
+            breaker.outcomes.pop_front();
+        }
+
+        let total = breaker.outcomes.len();
+        let failures = breaker.outcomes.iter().filter(|(_, failed)| *failed).count();
+        if total >= self.config.min_requests
+            && failures as f64 / total as f64 >= self.config.failure_rate_threshold
+        {
+            tracing::warn!("Circuit breaker opening: {}/{} upstream requests failed", failures, total);
+            self.open(&mut breaker, now);
+        }
+
+        Ok(())
+    }
+}
+
+/// Content types that are already compressed or must not be buffered
+const UNCOMPRESSIBLE_CONTENT_TYPES: &[&str] = &[
+    "text/event-stream",
+    "image/",
+    "audio/",
+    "video/",
+    "font/woff",
+    "application/gzip",
+    "application/x-gzip",
+    "application/zip",
+    "application/zstd",
+    "application/octet-stream",
+];
+
+/// Content encoding negotiated by [`CompressionMiddleware`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Gzip,
+    Deflate,
+}
+
+impl ContentEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+
+    /// Pick the encoding with the highest quality in an `Accept-Encoding` header
+    ///
+    /// Gzip is preferred on ties; encodings with `q=0` are refused.
+    pub fn negotiate(accept_encoding: &str) -> Option<Self> {
+        let mut gzip = None;
+        let mut deflate = None;
+        let mut wildcard = None;
+
+        for entry in accept_encoding.split(',') {
+            let mut parts = entry.split(';');
+            let coding = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            match coding.as_str() {
+                "gzip" | "x-gzip" => gzip = Some(quality),
+                "deflate" => deflate = Some(quality),
+                "*" => wildcard = Some(quality),
+                _ => {}
+            }
+        }
+
+        let gzip = gzip.or(wildcard).unwrap_or(0.0);
+        let deflate = deflate.or(wildcard).unwrap_or(0.0);
+        if gzip <= 0.0 && deflate <= 0.0 {
+            None
+        } else if gzip >= deflate {
+            Some(ContentEncoding::Gzip)
+        } else {
+            Some(ContentEncoding::Deflate)
+        }
+    }
+
+    fn encode(&self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            ContentEncoding::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            ContentEncoding::Deflate => {
+                let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Response compression middleware
+///
+/// Compresses response bodies of at least `min_size` bytes with gzip or
+/// deflate, as negotiated from the request's `Accept-Encoding` header.
+/// Streaming (SSE) responses and already-compressed content types are left
+/// untouched.
+pub struct CompressionMiddleware {
+    min_size: usize,
+}
+
+impl CompressionMiddleware {
+    pub fn new() -> Self {
+        Self {
+            min_size: 1024,
+        }
+    }
+
+    /// Set the smallest body size worth compressing
+    pub fn with_min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn is_compressible(headers: &HashMap<String, String>) -> bool {
+        if Self::header(headers, "Content-Encoding").is_some_and(|encoding| !encoding.eq_ignore_ascii_case("identity")) {
+            return false;
+        }
+
+        let content_type = Self::header(headers, "Content-Type").unwrap_or("").to_ascii_lowercase();
+        !UNCOMPRESSIBLE_CONTENT_TYPES.iter().any(|prefix| content_type.starts_with(prefix))
+    }
+
+    fn add_vary(headers: &mut HashMap<String, String>) {
+        let key = headers.keys()
+            .find(|key| key.eq_ignore_ascii_case("Vary"))
+            .cloned()
+            .unwrap_or_else(|| "Vary".to_string());
+        let vary = headers.entry(key).or_default();
+        if !vary.split(',').any(|v| v.trim().eq_ignore_ascii_case("Accept-Encoding")) {
+            if !vary.is_empty() {
+                vary.push_str(", ");
+            }
+            vary.push_str("Accept-Encoding");
+        }
+    }
+}
+
+impl Default for CompressionMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Middleware for CompressionMiddleware {
+    fn name(&self) -> &str {
+        "compression"
+    }
+
+    async fn process_request(&self, _request: &mut HttpRequest) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    async fn process_response(&self, _response: &mut HttpResponse) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    async fn complete_request(&self, request: &HttpRequest, response: &mut HttpResponse) -> GatewayResult<()> {
+        let encoding = Self::header(&request.headers, "Accept-Encoding").and_then(ContentEncoding::negotiate);
+
+        if !Self::is_compressible(&response.headers) {
+            return Ok(());
+        }
+        Self::add_vary(&mut response.headers);
+
+        let (Some(encoding), Some(body)) = (encoding, response.body.as_ref()) else {
+            return Ok(());
+        };
+        if body.len() < self.min_size || response.status_code == 204 || response.status_code == 304 {
+            return Ok(());
+        }
+
+        let compressed = encoding.encode(body)
+            .map_err(|e| GatewayError::InternalServerError(format!("Failed to compress response: {}", e)))?;
+        tracing::debug!("Compressed response {} with {}: {} -> {} bytes", response.request_id, encoding.as_str(), body.len(), compressed.len());
+
+        response.headers.retain(|key, _| !key.eq_ignore_ascii_case("Content-Length"));
+        response.headers.insert("Content-Length".to_string(), compressed.len().to_string());
+        response.headers.insert("Content-Encoding".to_string(), encoding.as_str().to_string());
+        response.body = Some(compressed);
+        Ok(())
+    }
+}
+
 /// Middleware chain
 pub struct MiddlewareChain {
     middlewares: Vec<Box<dyn Middleware>>,
@@ -198,6 +983,16 @@ impl MiddlewareChain {
         Ok(())
     }
 
+    /// Return the first response produced by a middleware without dispatching
+    pub async fn intercept_request(&self, request: &HttpRequest) -> GatewayResult<Option<HttpResponse>> {
+        for middleware in &self.middlewares {
+            if let Some(response) = middleware.intercept_request(request).await? {
+                return Ok(Some(response));
+            }
+        }
+        Ok(None)
+    }
+
     pub async fn process_response(&self, response: &mut HttpResponse) -> GatewayResult<()> {
         // Process in reverse order for response
         for middleware in self.middlewares.iter().rev() {
@@ -205,6 +1000,83 @@ impl MiddlewareChain {
         }
         Ok(())
     }
+
+    /// Run a request through the middleware and `dispatch`
+    ///
+    /// Every middleware whose `process_request` ran gets `complete_request`
+    /// with the final response, in reverse order, however the request ended:
+    /// answered by `dispatch`, intercepted, rejected by a middleware or failed.
+    /// Errors are returned after the middleware observed their error response.
+    pub async fn handle<F, Fut>(&self, request: &mut HttpRequest, dispatch: F) -> GatewayResult<HttpResponse>
+    where
+        F: FnOnce(HttpRequest) -> Fut,
+        Fut: Future<Output = GatewayResult<HttpResponse>>,
+    {
+        let mut entered = 0;
+        let mut rejected = None;
+        for middleware in &self.middlewares {
+            entered += 1;
+            if let Err(e) = middleware.process_request(request).await {
+                rejected = Some(e);
+                break;
+            }
+        }
+
+        let result = match rejected {
+            Some(e) => Err(e),
+            None => match self.intercept_request(request).await {
+                Ok(Some(response)) => Ok(response),
+                Ok(None) => self.run_with_timeout(request, dispatch(request.clone())).await,
+                Err(e) => Err(e),
+            },
+        };
+
+        let (mut response, error) = match result {
+            Ok(response) => (response, None),
+            Err(e) => (e.to_http_response(request.request_id.clone()), Some(e)),
+        };
+        for middleware in self.middlewares[..entered].iter().rev() {
+            if let Err(e) = middleware.complete_request(request, &mut response).await {
+                tracing::error!("Response middleware {} failed for {}: {:?}", middleware.name(), request.request_id, e);
+            }
+        }
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(response),
+        }
+    }
+
+    /// Strictest timeout any middleware imposes on the request
+    pub fn request_timeout(&self, request: &HttpRequest) -> Option<Duration> {
+        self.middlewares.iter()
+            .filter_map(|middleware| middleware.request_timeout(request))
+            .min()
+    }
+
+    /// Run a request handler within the request's timeout
+    ///
+    /// On expiry the handler future is dropped, the request's cancellation
+    /// token is cancelled and a 504 response is returned.
+    pub async fn run_with_timeout<F>(&self, request: &HttpRequest, handler: F) -> GatewayResult<HttpResponse>
+    where
+        F: Future<Output = GatewayResult<HttpResponse>>,
+    {
+        let Some(limit) = self.request_timeout(request) else {
+            return handler.await;
+        };
+
+        match tokio::time::timeout(limit, handler).await {
+            Ok(result) => result,
+            Err(_) => {
+                request.cancellation.cancel();
+                tracing::warn!("Request {} to {} timed out after {:?}", request.request_id, request.path, limit);
+
+                let error = GatewayError::Timeout(format!("Request timed out after {}ms", limit.as_millis()));
+                Ok(error.into_http_response(request.request_id.clone()))
+            }
+        }
+    }
 }
 
 impl Default for MiddlewareChain {
@@ -245,6 +1117,7 @@ mod tests {
             remote_addr: None,
             request_id: String::new(),
             timestamp: 0,
+            cancellation: tokio_util::sync::CancellationToken::new(),
         };
 
         middleware.process_request(&mut request).await.unwrap();
@@ -266,6 +1139,7 @@ mod tests {
             remote_addr: Some("127.0.0.1".to_string()),
             request_id: "test1".to_string(),
             timestamp: 0,
+            cancellation: tokio_util::sync::CancellationToken::new(),
         };
 
         // First request should succeed
@@ -280,4 +1154,400 @@ mod tests {
         let result = middleware.process_request(&mut request).await;
         assert!(result.is_err());
     }
+
+    fn idempotent_request(request_id: &str, key: &str, body: &str) -> HttpRequest {
+        HttpRequest::new(crate::HttpMethod::POST, "/v1/chat/completions")
+            .with_request_id(request_id)
+            .with_header(IDEMPOTENCY_KEY_HEADER, key)
+            .with_body(body)
+    }
+
+    fn idempotency_middleware() -> IdempotencyMiddleware {
+        use sira_storage_backends::{GenericStorageClient, MemoryBackend, StorageConfig};
+
+        let backend = MemoryBackend::new(StorageConfig::default());
+        IdempotencyMiddleware::new(Arc::new(GenericStorageClient::new(Box::new(backend))), 60)
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_replays_cached_response() {
+        let middleware = idempotency_middleware();
+
+        let first = idempotent_request("req1", "key-1", r#"{"prompt":"hi"}"#);
+        assert!(middleware.intercept_request(&first).await.unwrap().is_none());
+
+        let mut response = HttpResponse {
+            status_code: 201,
+            headers: HashMap::new(),
+            body: Some(b"created".to_vec()),
+            request_id: "req1".to_string(),
+        };
+        middleware.complete_request(&first, &mut response).await.unwrap();
+
+        let retry = idempotent_request("req2", "key-1", r#"{"prompt":"hi"}"#);
+        let replayed = middleware.intercept_request(&retry).await.unwrap().unwrap();
+        assert_eq!(replayed.status_code, 201);
+        assert_eq!(replayed.body, Some(b"created".to_vec()));
+        assert_eq!(replayed.request_id, "req2");
+        assert_eq!(replayed.headers.get("Idempotent-Replayed"), Some(&"true".to_string()));
+
+        // Requests without a key are never intercepted
+        let mut plain = retry.clone();
+        plain.headers.clear();
+        assert!(middleware.intercept_request(&plain).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_reused_with_different_body() {
+        let middleware = idempotency_middleware();
+
+        let first = idempotent_request("req1", "key-1", r#"{"prompt":"hi"}"#);
+        assert!(middleware.intercept_request(&first).await.unwrap().is_none());
+        let mut response = HttpResponse {
+            status_code: 200,
+            headers: HashMap::new(),
+            body: None,
+            request_id: "req1".to_string(),
+        };
+        middleware.complete_request(&first, &mut response).await.unwrap();
+
+        let conflicting = idempotent_request("req2", "key-1", r#"{"prompt":"bye"}"#);
+        let rejected = middleware.intercept_request(&conflicting).await.unwrap().unwrap();
+        assert_eq!(rejected.status_code, 422);
+    }
+
+    /// Send an idempotent request through the chain, counting dispatches
+    async fn send_idempotent(chain: &MiddlewareChain, dispatched: &std::sync::atomic::AtomicUsize, request_id: &str, status_code: u16) -> u16 {
+        let mut request = idempotent_request(request_id, "key-1", r#"{"prompt":"hi"}"#);
+        let result = chain.handle(&mut request, |request| async move {
+            dispatched.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if status_code == 0 {
+                return Err(GatewayError::Backend("connection refused".to_string()));
+            }
+            Ok(HttpResponse { status_code, headers: HashMap::new(), body: None, request_id: request.request_id })
+        }).await;
+        result.map_or_else(|e| e.status().as_u16(), |response| response.status_code)
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_dispatches_concurrent_duplicates_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let chain = MiddlewareChain::new().add_middleware(idempotency_middleware());
+        let dispatched = AtomicUsize::new(0);
+
+        let (first, second) = tokio::join!(
+            send_idempotent(&chain, &dispatched, "req1", 201),
+            send_idempotent(&chain, &dispatched, "req2", 201),
+        );
+        assert_eq!((first, second), (201, 201));
+        assert_eq!(dispatched.load(Ordering::SeqCst), 1);
+
+        // A duplicate that outwaits the in-flight original is told so
+        let chain = MiddlewareChain::new()
+            .add_middleware(idempotency_middleware().with_in_flight_wait(Duration::from_millis(10)));
+        let (first, second) = tokio::join!(
+            send_idempotent(&chain, &dispatched, "req3", 201),
+            send_idempotent(&chain, &dispatched, "req4", 201),
+        );
+        assert_eq!((first, second), (201, 409));
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_releases_key_when_request_fails() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let chain = MiddlewareChain::new().add_middleware(idempotency_middleware());
+        let dispatched = AtomicUsize::new(0);
+
+        // Neither the handler error nor the 503 is recorded, so each retry is dispatched
+        assert_eq!(send_idempotent(&chain, &dispatched, "req1", 0).await, 502);
+        assert_eq!(send_idempotent(&chain, &dispatched, "req2", 503).await, 503);
+        assert_eq!(send_idempotent(&chain, &dispatched, "req3", 200).await, 200);
+        assert_eq!(send_idempotent(&chain, &dispatched, "req4", 200).await, 200);
+        assert_eq!(dispatched.load(Ordering::SeqCst), 3);
+    }
+
+    fn compression_request(request_id: &str, accept_encoding: &str) -> HttpRequest {
+        HttpRequest::new(crate::HttpMethod::POST, "/v1/embeddings")
+            .with_request_id(request_id)
+            .with_header("accept-encoding", accept_encoding)
+    }
+
+    #[tokio::test]
+    async fn test_compression_of_large_response() {
+        use std::io::Read;
+
+        let middleware = CompressionMiddleware::new();
+        assert_eq!(ContentEncoding::negotiate("deflate;q=0.5, gzip;q=0.8"), Some(ContentEncoding::Gzip));
+        assert_eq!(ContentEncoding::negotiate("gzip;q=0, deflate"), Some(ContentEncoding::Deflate));
+        assert_eq!(ContentEncoding::negotiate("br, identity"), None);
+
+        let request = compression_request("req1", "gzip, deflate");
+
+        let body = serde_json::to_vec(&serde_json::json!({ "embedding": vec![0.25; 512] })).unwrap();
+        let mut response = HttpResponse {
+            status_code: 200,
+            headers: HashMap::from([("Content-Type".to_string(), "application/json".to_string())]),
+            body: Some(body.clone()),
+            request_id: "req1".to_string(),
+        };
+        middleware.complete_request(&request, &mut response).await.unwrap();
+
+        assert_eq!(response.headers.get("Content-Encoding"), Some(&"gzip".to_string()));
+        assert_eq!(response.headers.get("Vary"), Some(&"Accept-Encoding".to_string()));
+        let compressed = response.body.unwrap();
+        assert!(compressed.len() < body.len());
+
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(compressed.as_slice()).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, body);
+    }
+
+    #[tokio::test]
+    async fn test_compression_skips_small_and_streaming_responses() {
+        let middleware = CompressionMiddleware::new().with_min_size(64);
+
+        let small_request = compression_request("small", "gzip");
+        let mut small = HttpResponse {
+            status_code: 200,
+            headers: HashMap::from([("Content-Type".to_string(), "application/json".to_string())]),
+            body: Some(b"{}".to_vec()),
+            request_id: "small".to_string(),
+        };
+        middleware.complete_request(&small_request, &mut small).await.unwrap();
+        assert!(!small.headers.contains_key("Content-Encoding"));
+        assert_eq!(small.body, Some(b"{}".to_vec()));
+
+        let stream_request = compression_request("stream", "gzip");
+        let events = "data: {}\n\n".repeat(100).into_bytes();
+        let mut stream = HttpResponse {
+            status_code: 200,
+            headers: HashMap::from([("Content-Type".to_string(), "text/event-stream".to_string())]),
+            body: Some(events.clone()),
+            request_id: "stream".to_string(),
+        };
+        middleware.complete_request(&stream_request, &mut stream).await.unwrap();
+        assert!(!stream.headers.contains_key("Content-Encoding"));
+        assert!(!stream.headers.contains_key("Vary"));
+        assert_eq!(stream.body, Some(events));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_cancels_downstream_work() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let chain = MiddlewareChain::new().add_middleware(
+            TimeoutMiddleware::new(Duration::from_secs(5))
+                .with_route_timeout("/v1/chat", Duration::from_millis(50)),
+        );
+
+        let request = HttpRequest::new(crate::HttpMethod::POST, "/v1/chat/completions").with_request_id("req1");
+        assert_eq!(chain.request_timeout(&request), Some(Duration::from_millis(50)));
+
+        // Downstream work observes the request's cancellation token
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let token = request.cancellation.clone();
+        let flag = Arc::clone(&cancelled);
+        let downstream = tokio::spawn(async move {
+            token.cancelled().await;
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        let slow_handler = async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(HttpResponse {
+                status_code: 200,
+                headers: HashMap::new(),
+                body: None,
+                request_id: "req1".to_string(),
+            })
+        };
+        let response = chain.run_with_timeout(&request, slow_handler).await.unwrap();
+        assert_eq!(response.status_code, 504);
+
+        tokio::time::timeout(Duration::from_secs(1), downstream).await.unwrap().unwrap();
+        assert!(cancelled.load(Ordering::SeqCst));
+
+        // Handlers finishing in time pass through untouched
+        let fast = HttpRequest::new(crate::HttpMethod::POST, "/v1/chat/completions").with_request_id("req2");
+        let fast_handler = async {
+            Ok(HttpResponse {
+                status_code: 200,
+                headers: HashMap::new(),
+                body: None,
+                request_id: "req2".to_string(),
+            })
+        };
+        let response = chain.run_with_timeout(&fast, fast_handler).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(!fast.cancellation.is_cancelled());
+    }
+
+    fn circuit_breaker_chain(config: CircuitBreakerConfig) -> MiddlewareChain {
+        MiddlewareChain::new().add_middleware(CircuitBreakerMiddleware::new(config).with_route_prefix("/v1/"))
+    }
+
+    /// Send a request through the chain to a handler answering with `status_code`, or an error for 0
+    async fn respond(chain: &MiddlewareChain, request_id: &str, status_code: u16) -> u16 {
+        let mut request = HttpRequest::new(crate::HttpMethod::POST, "/v1/chat/completions").with_request_id(request_id);
+        let result = chain.handle(&mut request, |request| async move {
+            if status_code == 0 {
+                return Err(GatewayError::Backend("connection refused".to_string()));
+            }
+            Ok(HttpResponse { status_code, headers: HashMap::new(), body: None, request_id: request.request_id })
+        }).await;
+        result.map_or_else(|e| e.status().as_u16(), |response| response.status_code)
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_opens_and_recovers_through_probe() {
+        let breaker = CircuitBreakerMiddleware::new(CircuitBreakerConfig {
+            failure_rate_threshold: 0.5,
+            min_requests: 4,
+            open_duration: Duration::from_millis(50),
+            ..CircuitBreakerConfig::default()
+        });
+        let chain = MiddlewareChain::new().add_middleware(breaker);
+
+        // 2 failures in 4 requests reach the 50% threshold; handler errors count as failures
+        assert_eq!(respond(&chain, "ok1", 200).await, 200);
+        assert_eq!(respond(&chain, "fail1", 0).await, 502);
+        assert_eq!(respond(&chain, "ok2", 200).await, 200);
+        assert_eq!(respond(&chain, "fail2", 503).await, 503);
+
+        let mut shed = HttpRequest::new(crate::HttpMethod::POST, "/v1/chat/completions").with_request_id("shed");
+        let rejected = chain.handle(&mut shed, |_| async { panic!("an open breaker must not dispatch") }).await.unwrap();
+        assert_eq!(rejected.status_code, 503);
+        assert_eq!(rejected.headers.get("Retry-After"), Some(&"1".to_string()));
+
+        // After the open period a failed probe reopens the breaker
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(respond(&chain, "probe1", 504).await, 504);
+        assert_eq!(respond(&chain, "after_failed_probe", 200).await, 503);
+
+        // A successful probe closes it again
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(respond(&chain, "probe2", 200).await, 200);
+        assert_eq!(respond(&chain, "after", 200).await, 200);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_replaces_an_unanswered_probe() {
+        let breaker = CircuitBreakerMiddleware::new(CircuitBreakerConfig {
+            min_requests: 1,
+            open_duration: Duration::from_millis(20),
+            probe_timeout: Duration::from_millis(20),
+            ..CircuitBreakerConfig::default()
+        });
+        assert_eq!(breaker.state().await, CircuitState::Closed);
+        let chain = MiddlewareChain::new().add_middleware(breaker);
+        assert_eq!(respond(&chain, "fail", 0).await, 502);
+
+        // The probe's client disconnects, so its handler future is dropped unanswered
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let mut probe = HttpRequest::new(crate::HttpMethod::POST, "/v1/chat/completions").with_request_id("probe1");
+        let abandoned = chain.handle(&mut probe, |_| std::future::pending());
+        assert!(tokio::time::timeout(Duration::from_millis(5), abandoned).await.is_err());
+        assert_eq!(respond(&chain, "during_probe", 200).await, 503);
+
+        // Once the probe times out the next request probes instead
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(respond(&chain, "probe2", 200).await, 200);
+        assert_eq!(respond(&chain, "after", 200).await, 200);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_ignores_unguarded_routes() {
+        let chain = circuit_breaker_chain(CircuitBreakerConfig {
+            min_requests: 1,
+            ..CircuitBreakerConfig::default()
+        });
+
+        assert_eq!(respond(&chain, "fail", 500).await, 500);
+        assert_eq!(respond(&chain, "shed", 200).await, 503);
+
+        let health = HttpRequest::new(crate::HttpMethod::GET, "/health").with_request_id("health");
+        assert!(chain.intercept_request(&health).await.unwrap().is_none());
+    }
+
+    fn validation_middleware() -> ValidationMiddleware {
+        let mut router = crate::Router::new();
+        router.add_route(crate::RouteConfig {
+            id: "session_messages".to_string(),
+            path: "/v1/sessions/{id}/messages".to_string(),
+            methods: vec!["GET".to_string(), "POST".to_string()],
+            backend: crate::BackendConfig {
+                name: "sessions".to_string(),
+                url: "http://localhost:3000".to_string(),
+                timeout: 30,
+                retry_count: 0,
+                health_check: None,
+                weight: 1,
+            },
+            middlewares: vec![],
+            priority: 0,
+            enabled: true,
+        }).unwrap();
+
+        ValidationMiddleware::new(Arc::new(RwLock::new(router)))
+            .with_schema(crate::HttpMethod::POST, "session_messages", serde_json::json!({
+                "type": "object",
+                "required": ["role", "content"],
+                "properties": {
+                    "role": { "enum": ["user", "assistant"] },
+                    "content": { "type": "string", "minLength": 1 }
+                }
+            }))
+            .unwrap()
+    }
+
+    fn validated_request(body: &str) -> HttpRequest {
+        HttpRequest::new(crate::HttpMethod::POST, "/v1/sessions/abc/messages")
+            .with_request_id("validate")
+            .with_body(body)
+    }
+
+    #[tokio::test]
+    async fn test_valid_body_reaches_handler() {
+        let middleware = validation_middleware();
+
+        let valid = validated_request(r#"{"role":"user","content":"hi"}"#);
+        assert!(middleware.intercept_request(&valid).await.unwrap().is_none());
+
+        // Routes and methods without a schema are not validated
+        let mut other = validated_request("not json");
+        other.path = "/v1/sessions/abc".to_string();
+        assert!(middleware.intercept_request(&other).await.unwrap().is_none());
+        let mut listing = validated_request("not json");
+        listing.method = crate::HttpMethod::GET;
+        assert!(middleware.intercept_request(&listing).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_body_is_rejected_with_field_errors() {
+        let middleware = validation_middleware();
+
+        let invalid = validated_request(r#"{"role":"system"}"#);
+        let rejected = middleware.intercept_request(&invalid).await.unwrap().unwrap();
+        assert_eq!(rejected.status_code, 422);
+
+        let envelope: serde_json::Value = serde_json::from_slice(&rejected.body.unwrap()).unwrap();
+        assert_eq!(envelope["error"]["code"], "validation_failed");
+        let mut fields: Vec<&str> = envelope["error"]["details"]["fields"].as_array().unwrap()
+            .iter()
+            .map(|error| error["field"].as_str().unwrap())
+            .collect();
+        fields.sort();
+        assert_eq!(fields, vec!["/content", "/role"]);
+
+        let malformed = validated_request("{");
+        assert_eq!(middleware.intercept_request(&malformed).await.unwrap().unwrap().status_code, 400);
+
+        // Any path the router sends to the route is validated, e.g. with a trailing slash
+        let mut trailing = validated_request(r#"{"role":"system"}"#);
+        trailing.path = "/v1/sessions/abc/messages/".to_string();
+        assert_eq!(middleware.intercept_request(&trailing).await.unwrap().unwrap().status_code, 422);
+    }
 }
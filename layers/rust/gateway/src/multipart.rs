@@ -0,0 +1,310 @@
+//! Multipart/form-data uploads
+//!
+//! [`MultipartUpload`] parses a `multipart/form-data` body one part at a time.
+//! Each [`UploadPart`] is read as a stream of chunks or through an
+//! [`AsyncRead`], so handlers and tools can forward a large document without
+//! holding it in memory. A part is refused as soon as it grows past the size
+//! limit or declares a content type the limits do not allow.
+//!
+//! Requests to a path registered with an [`UploadHandler`] are not buffered by
+//! the server: the handler receives their body as a [`MultipartUpload`].
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream, Stream, TryStreamExt};
+use multer::{Constraints, SizeLimit};
+use tokio::io::AsyncRead;
+use tokio_util::io::StreamReader;
+
+use crate::{GatewayError, GatewayResult, HttpRequest, HttpResponse};
+
+/// Handler of multipart uploads to a path, reading the body as it arrives
+#[async_trait]
+pub trait UploadHandler: Send + Sync {
+    /// Handle an upload; `request` carries no body, `upload` streams it
+    async fn handle_upload(&self, request: HttpRequest, upload: MultipartUpload) -> GatewayResult<HttpResponse>;
+}
+
+/// Limits applied while parsing a multipart body
+#[derive(Debug, Clone)]
+pub struct MultipartLimits {
+    /// Largest accepted part, in bytes
+    pub max_part_size: u64,
+    /// Most parts accepted in one body
+    pub max_parts: usize,
+    /// Accepted part content types, e.g. `application/pdf` or `image/*`; empty accepts any
+    pub allowed_content_types: Vec<String>,
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self {
+            max_part_size: 10 * 1024 * 1024,
+            max_parts: 32,
+            allowed_content_types: Vec::new(),
+        }
+    }
+}
+
+impl MultipartLimits {
+    /// Set the largest accepted part, in bytes
+    pub fn with_max_part_size(mut self, bytes: u64) -> Self {
+        self.max_part_size = bytes;
+        self
+    }
+
+    /// Set the most parts accepted in one body
+    pub fn with_max_parts(mut self, parts: usize) -> Self {
+        self.max_parts = parts;
+        self
+    }
+
+    /// Accept parts of a content type; `type/*` accepts every subtype
+    ///
+    /// Once any type is allowed, parts that declare no content type are refused.
+    pub fn allow_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.allowed_content_types.push(content_type.into());
+        self
+    }
+
+    /// Whether a part of this content type is accepted
+    pub fn allows(&self, content_type: &mime::Mime) -> bool {
+        self.allowed_content_types.is_empty()
+            || self.allowed_content_types.iter().any(|allowed| match allowed.split_once('/') {
+                Some((type_, "*")) => content_type.type_().as_str().eq_ignore_ascii_case(type_),
+                _ => content_type.essence_str().eq_ignore_ascii_case(allowed),
+            })
+    }
+}
+
+/// Multipart body being read part by part
+pub struct MultipartUpload {
+    inner: multer::Multipart<'static>,
+    limits: MultipartLimits,
+    parts_read: usize,
+}
+
+impl MultipartUpload {
+    /// Parse a stream of body chunks delimited by `boundary`
+    pub fn new<S, O, E>(body: S, boundary: impl Into<String>, limits: MultipartLimits) -> Self
+    where
+        S: Stream<Item = Result<O, E>> + Send + 'static,
+        O: Into<Bytes> + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        let constraints = Constraints::new().size_limit(SizeLimit::new().per_field(limits.max_part_size));
+        Self {
+            inner: multer::Multipart::with_constraints(body, boundary, constraints),
+            limits,
+            parts_read: 0,
+        }
+    }
+
+    /// Parse the buffered body of a request, taking the boundary from its `Content-Type` header
+    pub fn from_request(request: &HttpRequest, limits: MultipartLimits) -> GatewayResult<Self> {
+        let boundary = Self::boundary(request)?;
+        let body = Bytes::from(request.body.clone().unwrap_or_default());
+        Ok(Self::new(stream::iter([Ok::<_, std::convert::Infallible>(body)]), boundary, limits))
+    }
+
+    /// Parse a request's body as it is received, without buffering it
+    pub fn from_body(request: &HttpRequest, body: hyper::Body, limits: MultipartLimits) -> GatewayResult<Self> {
+        let boundary = Self::boundary(request)?;
+        Ok(Self::new(body, boundary, limits))
+    }
+
+    fn boundary(request: &HttpRequest) -> GatewayResult<String> {
+        let content_type = request.headers.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_default();
+        multer::parse_boundary(content_type).map_err(|_| {
+            GatewayError::UnsupportedMediaType(format!("Expected multipart/form-data, got '{}'", content_type))
+        })
+    }
+
+    /// Next part of the body, or `None` after the last one
+    ///
+    /// The previous part must be dropped before asking for the next one.
+    pub async fn next_part(&mut self) -> GatewayResult<Option<UploadPart>> {
+        let Some(field) = self.inner.next_field().await.map_err(multipart_error)? else {
+            return Ok(None);
+        };
+
+        self.parts_read += 1;
+        if self.parts_read > self.limits.max_parts {
+            return Err(GatewayError::PayloadTooLarge(format!("More than {} parts", self.limits.max_parts)));
+        }
+        match field.content_type() {
+            Some(content_type) if !self.limits.allows(content_type) => {
+                return Err(GatewayError::UnsupportedMediaType(format!(
+                    "Part '{}' has disallowed content type {}",
+                    field.name().unwrap_or_default(),
+                    content_type
+                )));
+            }
+            None if !self.limits.allowed_content_types.is_empty() => {
+                return Err(GatewayError::UnsupportedMediaType(format!(
+                    "Part '{}' declares no content type",
+                    field.name().unwrap_or_default()
+                )));
+            }
+            _ => {}
+        }
+
+        Ok(Some(UploadPart { field }))
+    }
+}
+
+/// One part of a multipart body, read as it arrives
+pub struct UploadPart {
+    field: multer::Field<'static>,
+}
+
+impl UploadPart {
+    /// Form field name of the part
+    pub fn name(&self) -> Option<&str> {
+        self.field.name()
+    }
+
+    /// File name the client gave the part, if it is a file
+    pub fn file_name(&self) -> Option<&str> {
+        self.field.file_name()
+    }
+
+    /// Declared content type of the part
+    pub fn content_type(&self) -> Option<&mime::Mime> {
+        self.field.content_type()
+    }
+
+    /// Next chunk of the part's content, or `None` at its end
+    pub async fn chunk(&mut self) -> GatewayResult<Option<Bytes>> {
+        self.field.chunk().await.map_err(multipart_error)
+    }
+
+    /// Read the part's content through an [`AsyncRead`]
+    ///
+    /// Read errors carry the [`GatewayError`] as their inner error.
+    pub fn into_reader(self) -> impl AsyncRead + Send + Unpin {
+        StreamReader::new(self.field.map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, multipart_error(e))
+        }))
+    }
+}
+
+/// Map a parser error to the gateway error reported to the client
+fn multipart_error(error: multer::Error) -> GatewayError {
+    match error {
+        multer::Error::FieldSizeExceeded { limit, field_name } => GatewayError::PayloadTooLarge(format!(
+            "Part '{}' exceeds {} bytes",
+            field_name.unwrap_or_default(),
+            limit
+        )),
+        multer::Error::StreamSizeExceeded { limit } => {
+            GatewayError::PayloadTooLarge(format!("Body exceeds {} bytes", limit))
+        }
+        other => GatewayError::InvalidRequest(format!("Malformed multipart body: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HttpMethod;
+    use std::collections::HashMap;
+    use tokio::io::AsyncReadExt;
+
+    fn upload_request(parts: &[(&str, &str, &str)]) -> HttpRequest {
+        let mut body = String::new();
+        for (name, content_type, content) in parts {
+            body.push_str(&format!(
+                "--BOUNDARY\r\nContent-Disposition: form-data; name=\"{name}\"; filename=\"{name}.bin\"\r\nContent-Type: {content_type}\r\n\r\n{content}\r\n"
+            ));
+        }
+        body.push_str("--BOUNDARY--\r\n");
+
+        HttpRequest::new(HttpMethod::POST, "/v1/files")
+            .with_request_id("req-1")
+            .with_header("content-type", "multipart/form-data; boundary=BOUNDARY")
+            .with_body(body)
+    }
+
+    #[tokio::test]
+    async fn test_parts_are_streamed_with_their_metadata() {
+        let request = upload_request(&[("notes", "text/plain", "hello multipart"), ("image", "image/png", "PNGDATA")]);
+        let limits = MultipartLimits::default().allow_content_type("text/plain").allow_content_type("image/*");
+        let mut upload = MultipartUpload::from_request(&request, limits).unwrap();
+
+        let notes = upload.next_part().await.unwrap().unwrap();
+        assert_eq!(notes.name(), Some("notes"));
+        assert_eq!(notes.file_name(), Some("notes.bin"));
+        let mut content = String::new();
+        notes.into_reader().read_to_string(&mut content).await.unwrap();
+        assert_eq!(content, "hello multipart");
+
+        let mut image = upload.next_part().await.unwrap().unwrap();
+        assert_eq!(image.content_type().map(|mime| mime.essence_str()), Some("image/png"));
+        let mut content = Vec::new();
+        while let Some(chunk) = image.chunk().await.unwrap() {
+            content.extend_from_slice(&chunk);
+        }
+        assert_eq!(content, b"PNGDATA");
+        drop(image);
+
+        assert!(upload.next_part().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_over_limit_and_disallowed_parts_are_rejected() {
+        let request = upload_request(&[("doc", "text/plain", &"x".repeat(64))]);
+        let mut upload = MultipartUpload::from_request(&request, MultipartLimits::default().with_max_part_size(16)).unwrap();
+        let mut part = upload.next_part().await.unwrap().unwrap();
+        let mut error = None;
+        while error.is_none() {
+            match part.chunk().await {
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(e) => error = Some(e),
+            }
+        }
+        assert!(matches!(error, Some(GatewayError::PayloadTooLarge(_))));
+
+        let request = upload_request(&[("script", "application/x-sh", "rm -rf /")]);
+        let mut upload = MultipartUpload::from_request(&request, MultipartLimits::default().allow_content_type("image/*")).unwrap();
+        let error = upload.next_part().await.err().unwrap();
+        assert_eq!(error.status(), crate::HttpStatus::UnsupportedMediaType);
+
+        // With an allowlist, a part must declare its type to be accepted
+        let mut request = upload_request(&[]);
+        request.body = Some(b"--BOUNDARY\r\nContent-Disposition: form-data; name=\"blob\"\r\n\r\ndata\r\n--BOUNDARY--\r\n".to_vec());
+        let mut upload = MultipartUpload::from_request(&request, MultipartLimits::default().allow_content_type("image/*")).unwrap();
+        let error = upload.next_part().await.err().unwrap();
+        assert_eq!(error.status(), crate::HttpStatus::UnsupportedMediaType);
+    }
+
+    #[tokio::test]
+    async fn test_body_is_parsed_as_it_arrives() {
+        let mut request = upload_request(&[("notes", "text/plain", "streamed across chunks")]);
+        let body = request.body.take().unwrap();
+        let (mut sender, hyper_body) = hyper::Body::channel();
+        let mut upload = MultipartUpload::from_body(&request, hyper_body, MultipartLimits::default()).unwrap();
+
+        // Only the part's headers and the start of its content have arrived when it is opened
+        let split = body.windows(6).position(|window| window == b"across").unwrap();
+        let (head, tail) = body.split_at(split);
+        let (head, tail) = (Bytes::copy_from_slice(head), Bytes::copy_from_slice(tail));
+        let (release_tail, tail_released) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(async move {
+            sender.send_data(head).await.unwrap();
+            tail_released.await.unwrap();
+            sender.send_data(tail).await.unwrap();
+        });
+        let part = upload.next_part().await.unwrap().unwrap();
+        assert_eq!(part.name(), Some("notes"));
+
+        release_tail.send(()).unwrap();
+        let mut content = String::new();
+        part.into_reader().read_to_string(&mut content).await.unwrap();
+        assert_eq!(content, "streamed across chunks");
+    }
+}
@@ -4,6 +4,17 @@ use crate::{GatewayResult, GatewayError, RouteConfig, RouteMatch, HttpRequest, H
 use regex::Regex;
 use std::collections::HashMap;
 
+/// Methods routes can be registered for, in the order they are listed in `Allow`
+const ROUTED_METHODS: [HttpMethod; 7] = [
+    HttpMethod::GET,
+    HttpMethod::HEAD,
+    HttpMethod::POST,
+    HttpMethod::PUT,
+    HttpMethod::PATCH,
+    HttpMethod::DELETE,
+    HttpMethod::OPTIONS,
+];
+
 /// Route node for efficient path matching
 #[derive(Debug)]
 struct RouteNode {
@@ -26,14 +37,9 @@ pub struct Router {
 impl Router {
     /// Create a new router
     pub fn new() -> Self {
-        let mut routes = HashMap::new();
-        routes.insert(HttpMethod::GET, RouteNode::new());
-        routes.insert(HttpMethod::POST, RouteNode::new());
-        routes.insert(HttpMethod::PUT, RouteNode::new());
-        routes.insert(HttpMethod::DELETE, RouteNode::new());
-        routes.insert(HttpMethod::PATCH, RouteNode::new());
-        routes.insert(HttpMethod::HEAD, RouteNode::new());
-        routes.insert(HttpMethod::OPTIONS, RouteNode::new());
+        let routes = ROUTED_METHODS.iter()
+            .map(|method| (*method, RouteNode::new()))
+            .collect();
 
         Self {
             routes,
@@ -65,12 +71,27 @@ impl Router {
     }
 
     /// Match a request to a route
+    ///
+    /// HEAD requests without a HEAD route are served by the GET route. A
+    /// path that is routed only for other methods fails with
+    /// [`GatewayError::MethodNotAllowed`] listing the methods it accepts.
     pub fn match_route(&self, request: &HttpRequest) -> GatewayResult<RouteMatch> {
-        let root = self.routes.get(&request.method).ok_or_else(|| {
-            GatewayError::Routing(format!("Method not supported: {:?}", request.method))
-        })?;
-
-        let (route_id, path_params) = self.find_route(root, &request.path)?;
+        let found = self.find_route_for_method(request.method, &request.path)
+            .or_else(|| match request.method {
+                HttpMethod::HEAD => self.find_route_for_method(HttpMethod::GET, &request.path),
+                _ => None,
+            });
+        let Some((route_id, path_params)) = found else {
+            let allowed = self.allowed_methods(&request.path);
+            if allowed.is_empty() {
+                return Err(GatewayError::Routing(format!("No route found for path: {}", request.path)));
+            }
+            return Err(GatewayError::MethodNotAllowed {
+                method: request.method,
+                path: request.path.clone(),
+                allowed,
+            });
+        };
 
         let route_config = self.route_configs.get(&route_id).ok_or_else(|| {
             GatewayError::Routing(format!("Route config not found: {}", route_id))
@@ -84,6 +105,23 @@ impl Router {
         })
     }
 
+    /// Route id and path parameters of the route for a method and path
+    fn find_route_for_method(&self, method: HttpMethod, path: &str) -> Option<(String, HashMap<String, String>)> {
+        self.routes.get(&method).and_then(|root| self.find_route(root, path).ok())
+    }
+
+    /// Methods with a route for a path, in a stable order; GET implies HEAD
+    pub fn allowed_methods(&self, path: &str) -> Vec<HttpMethod> {
+        let routed: Vec<HttpMethod> = ROUTED_METHODS.iter()
+            .copied()
+            .filter(|method| self.find_route_for_method(*method, path).is_some())
+            .collect();
+        ROUTED_METHODS.iter()
+            .copied()
+            .filter(|method| routed.contains(method) || (*method == HttpMethod::HEAD && routed.contains(&HttpMethod::GET)))
+            .collect()
+    }
+
     /// Get all routes
     pub fn get_routes(&self) -> Vec<&RouteConfig> {
         self.route_configs.values().collect()
@@ -285,6 +323,7 @@ mod tests {
             remote_addr: None,
             request_id: "test".to_string(),
             timestamp: 0,
+            cancellation: tokio_util::sync::CancellationToken::new(),
         };
 
         let route_match = router.match_route(&request).unwrap();
@@ -308,6 +347,7 @@ mod tests {
             remote_addr: None,
             request_id: "test".to_string(),
             timestamp: 0,
+            cancellation: tokio_util::sync::CancellationToken::new(),
         };
 
         let route_match = router.match_route(&request).unwrap();
@@ -328,9 +368,59 @@ mod tests {
             remote_addr: None,
             request_id: "test".to_string(),
             timestamp: 0,
+            cancellation: tokio_util::sync::CancellationToken::new(),
         };
 
         let result = router.match_route(&request);
         assert!(result.is_err());
     }
+
+    fn request(method: HttpMethod, path: &str) -> HttpRequest {
+        HttpRequest::new(method, path).with_request_id("test")
+    }
+
+    #[test]
+    fn test_router_dispatches_by_method() {
+        let mut router = Router::new();
+        router.add_route(create_test_route("list", "/api/v1/items", vec!["GET"])).unwrap();
+        router.add_route(create_test_route("create", "/api/v1/items", vec!["POST"])).unwrap();
+
+        assert_eq!(router.match_route(&request(HttpMethod::GET, "/api/v1/items")).unwrap().route_id, "list");
+        assert_eq!(router.match_route(&request(HttpMethod::POST, "/api/v1/items")).unwrap().route_id, "create");
+    }
+
+    #[test]
+    fn test_router_serves_head_from_get_routes() {
+        let mut router = Router::new();
+        router.add_route(create_test_route("list", "/api/v1/items", vec!["GET"])).unwrap();
+        router.add_route(create_test_route("create", "/api/v1/items", vec!["POST"])).unwrap();
+        router.add_route(create_test_route("probe", "/api/v1/probe", vec!["GET"])).unwrap();
+        router.add_route(create_test_route("probe-head", "/api/v1/probe", vec!["HEAD"])).unwrap();
+
+        assert_eq!(router.match_route(&request(HttpMethod::HEAD, "/api/v1/items")).unwrap().route_id, "list");
+        // An explicit HEAD route takes precedence
+        assert_eq!(router.match_route(&request(HttpMethod::HEAD, "/api/v1/probe")).unwrap().route_id, "probe-head");
+        assert_eq!(router.allowed_methods("/api/v1/items"), vec![HttpMethod::GET, HttpMethod::HEAD, HttpMethod::POST]);
+    }
+
+    #[test]
+    fn test_router_unmatched_method_is_405_with_allow() {
+        let mut router = Router::new();
+        router.add_route(create_test_route("item", "/api/v1/items/{id}", vec!["DELETE", "GET"])).unwrap();
+        router.add_route(create_test_route("update", "/api/v1/items/{id}", vec!["PUT"])).unwrap();
+
+        let error = router.match_route(&request(HttpMethod::POST, "/api/v1/items/7")).unwrap_err();
+        assert!(matches!(error, GatewayError::MethodNotAllowed { method: HttpMethod::POST, .. }));
+        let response = error.into_http_response("req-1".to_string());
+        assert_eq!(response.status_code, 405);
+        assert_eq!(response.headers.get("Allow").map(String::as_str), Some("GET, HEAD, PUT, DELETE"));
+
+        // Methods with no routes at all are rejected the same way
+        let error = router.match_route(&request(HttpMethod::TRACE, "/api/v1/items/7")).unwrap_err();
+        assert_eq!(error.allow_header().as_deref(), Some("GET, HEAD, PUT, DELETE"));
+
+        // Unknown paths are still 404
+        let error = router.match_route(&request(HttpMethod::POST, "/api/v1/other")).unwrap_err();
+        assert!(matches!(error, GatewayError::Routing(_)));
+    }
 }
@@ -2,32 +2,56 @@
 
 use crate::{
     GatewayResult, GatewayError, GatewayConfig, Router, MiddlewareChain, RequestDispatcher,
-    HttpRequest, HttpResponse, HttpMethod, CorsMiddleware, LoggingMiddleware,
-    RateLimitMiddleware, RequestIdMiddleware, WebSocketManager, websocket_routes
+    HttpRequest, HttpResponse, HttpMethod, CompressionMiddleware, CorsMiddleware, LoggingMiddleware,
+    RateLimitMiddleware, RequestIdMiddleware, TimeoutMiddleware, WebSocketManager, websocket_routes,
+    EmbeddingsHandler, KeyStore, RouteTransform, AccessLogMiddleware, TracingAccessLogSink,
+    MultipartLimits, MultipartUpload, UploadHandler
 };
 use sira_ai_backends::AiBackendClient;
+use sira_kernel::Microkernel;
+use sira_kernel::kernel::HealthStatus;
 use sira_session::SessionManager;
+use sira_storage_backends::{GenericStorageClient, MemoryBackend, StorageConfig};
 use axum::{
-    extract::{State, Path, Query},
-    http::{Method, HeaderMap, StatusCode},
-    response::{IntoResponse, Response},
-    routing::any,
+    extract::{State, Path, Query, RawBody},
+    http::{Method, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{any, get},
     Router as AxumRouter,
     body::Body,
 };
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use tower_http::cors::CorsLayer;
 
+/// How long the default in-memory embeddings cache keeps an embedding
+const EMBEDDINGS_CACHE_TTL_SECONDS: u64 = 3600;
+
+/// Longest `/readyz` waits on providers that have never been checked
+const READINESS_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// Gateway server state
 #[derive(Clone)]
 pub struct ServerState {
     router: Arc<RwLock<Router>>,
     middleware_chain: Arc<RwLock<MiddlewareChain>>,
     dispatcher: Arc<RwLock<RequestDispatcher>>,
+    /// Upload handlers by path; their request bodies are streamed, not buffered
+    uploads: Arc<RwLock<HashMap<String, UploadRoute>>>,
     websocket_manager: Option<Arc<WebSocketManager>>,
+    ai_client: Option<Arc<AiBackendClient>>,
+    kernel: Option<Arc<Microkernel>>,
+}
+
+/// Handler and limits of an upload path
+#[derive(Clone)]
+struct UploadRoute {
+    handler: Arc<dyn UploadHandler>,
+    limits: MultipartLimits,
 }
 
 /// HTTP Gateway Server
@@ -44,8 +68,16 @@ impl GatewayServer {
         session_manager: Option<Arc<SessionManager>>,
     ) -> Self {
         let router = Arc::new(RwLock::new(Router::new()));
-        let middleware_chain = Arc::new(RwLock::new(Self::create_default_middlewares()));
-        let dispatcher = Arc::new(RwLock::new(RequestDispatcher::new()));
+        let middleware_chain = Arc::new(RwLock::new(Self::create_default_middlewares(&config)));
+
+        // Serve embeddings if AI client is available, cached in memory until replaced
+        let mut dispatcher = RequestDispatcher::new();
+        if let Some(client) = &ai_client {
+            let cache = GenericStorageClient::new(Box::new(MemoryBackend::new(StorageConfig::default())));
+            let handler = EmbeddingsHandler::new(client.clone()).with_cache(Arc::new(cache), EMBEDDINGS_CACHE_TTL_SECONDS);
+            dispatcher.set_embeddings_handler(handler);
+        }
+        let dispatcher = Arc::new(RwLock::new(dispatcher));
 
         // Initialize WebSocket manager if AI client is available
         let websocket_manager = ai_client.as_ref().map(|client| {
@@ -56,7 +88,10 @@ impl GatewayServer {
             router,
             middleware_chain,
             dispatcher,
+            uploads: Arc::new(RwLock::new(HashMap::new())),
             websocket_manager,
+            ai_client,
+            kernel: None,
         };
 
         Self { config, state }
@@ -67,6 +102,34 @@ impl GatewayServer {
         Self::new(config, None, None)
     }
 
+    /// Report the health of `kernel` in readiness checks
+    pub fn with_kernel(mut self, kernel: Arc<Microkernel>) -> Self {
+        self.state.kernel = Some(kernel);
+        self
+    }
+
+    /// Replace the handler serving `/v1/embeddings`, e.g. to cache in a shared backend
+    pub async fn set_embeddings_handler(&self, handler: EmbeddingsHandler) {
+        self.state.dispatcher.write().await.set_embeddings_handler(handler);
+    }
+
+    /// Append a request/response transform to a route, identified by route ID or path
+    pub async fn add_route_transform(&self, route: impl Into<String>, transform: Arc<dyn RouteTransform>) {
+        self.state.dispatcher.write().await.add_route_transform(route, transform);
+    }
+
+    /// Serve multipart uploads to `path` with `handler`, streaming their bodies
+    pub async fn set_upload_handler(&self, path: impl Into<String>, handler: Arc<dyn UploadHandler>, limits: MultipartLimits) {
+        self.state.uploads.write().await.insert(path.into(), UploadRoute { handler, limits });
+    }
+
+    /// Require WebSocket connections to authenticate with a key from `key_store`
+    pub async fn set_key_store(&self, key_store: Arc<KeyStore>) {
+        if let Some(ws_manager) = &self.state.websocket_manager {
+            ws_manager.set_key_store(key_store).await;
+        }
+    }
+
     /// Get WebSocket connection statistics
     pub async fn get_websocket_stats(&self) -> Option<HashMap<String, usize>> {
         if let Some(ws_manager) = &self.state.websocket_manager {
@@ -103,12 +166,16 @@ impl GatewayServer {
         // Merge with WebSocket routes if WebSocket manager is available
         let app = if let Some(ws_manager) = &self.state.websocket_manager {
             AxumRouter::new()
+                .route("/livez", get(Self::livez))
+                .route("/readyz", get(Self::readyz))
                 .route("/*path", any(Self::handle_request))
                 .merge(websocket_routes(ws_manager.clone()))
                 .layer(CorsLayer::permissive()) // Enable CORS
                 .with_state(self.state)
         } else {
             AxumRouter::new()
+                .route("/livez", get(Self::livez))
+                .route("/readyz", get(Self::readyz))
                 .route("/*path", any(Self::handle_request))
                 .layer(CorsLayer::permissive()) // Enable CORS
                 .with_state(self.state)
@@ -141,12 +208,74 @@ impl GatewayServer {
     }
 
     /// Create default middleware chain
-    fn create_default_middlewares() -> MiddlewareChain {
+    fn create_default_middlewares(config: &GatewayConfig) -> MiddlewareChain {
         MiddlewareChain::new()
             .add_middleware(RequestIdMiddleware::new())
+            .add_middleware(CompressionMiddleware::new())
+            .add_middleware(AccessLogMiddleware::new(Arc::new(TracingAccessLogSink)))
             .add_middleware(LoggingMiddleware::new())
             .add_middleware(CorsMiddleware::new())
             .add_middleware(RateLimitMiddleware::new(100, 60)) // 100 requests per minute
+            .add_middleware(TimeoutMiddleware::new(Duration::from_secs(config.timeout)))
+    }
+
+    /// Liveness probe: the process is up and serving
+    async fn livez() -> Response {
+        (StatusCode::OK, Json(serde_json::json!({ "status": "alive" }))).into_response()
+    }
+
+    /// Readiness probe: 200 when dependencies can serve traffic, 503 otherwise
+    async fn readyz(State(state): State<ServerState>) -> Response {
+        let (ready, checks) = Self::readiness(&state).await;
+        let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        let body = serde_json::json!({
+            "status": if ready { "ready" } else { "not_ready" },
+            "checks": checks,
+        });
+        (status, Json(body)).into_response()
+    }
+
+    /// Whether the gateway is ready, with the result of each check
+    ///
+    /// Ready requires at least one healthy AI backend and, when a kernel is
+    /// attached, a kernel that is healthy or degraded (warning).
+    async fn readiness(state: &ServerState) -> (bool, HashMap<String, bool>) {
+        let mut checks = HashMap::new();
+
+        let ai_backend = match &state.ai_client {
+            Some(client) => Self::ai_backend_ready(client).await,
+            None => false,
+        };
+        checks.insert("ai_backend".to_string(), ai_backend);
+
+        if let Some(kernel) = &state.kernel {
+            let kernel_ready = matches!(
+                kernel.health().await.map(|health| health.status),
+                Ok(HealthStatus::Healthy | HealthStatus::Warning)
+            );
+            checks.insert("kernel".to_string(), kernel_ready);
+        }
+
+        (checks.values().all(|ok| *ok), checks)
+    }
+
+    /// Whether any provider is healthy, going by cached catalog state
+    ///
+    /// Providers are only probed when none has been checked yet, and the
+    /// probe gives up after [`READINESS_PROBE_TIMEOUT`].
+    async fn ai_backend_ready(client: &AiBackendClient) -> bool {
+        let cached = client.cached_health().await;
+        if cached.values().any(|healthy| *healthy == Some(true)) {
+            return true;
+        }
+        if cached.values().any(Option::is_some) {
+            return false;
+        }
+
+        match tokio::time::timeout(READINESS_PROBE_TIMEOUT, client.health_check()).await {
+            Ok(health) => health.values().any(|healthy| *healthy),
+            Err(_) => false,
+        }
     }
 
     /// Handle incoming requests
@@ -156,46 +285,60 @@ impl GatewayServer {
         Path(path): Path<String>,
         Query(query): Query<HashMap<String, String>>,
         headers: HeaderMap,
-        body: String,
+        RawBody(body): RawBody,
     ) -> Response {
+        // Upload bodies are handed over as they arrive; everything else is buffered
+        let upload = state.uploads.read().await.get(&format!("/{}", path.trim_start_matches('/'))).cloned();
+        let (buffered, upload) = match upload {
+            Some(route) => (String::new(), Some((route, body))),
+            None => match Self::read_body(body).await {
+                Ok(body) => (body, None),
+                Err(e) => return e.into_response(),
+            },
+        };
+
         // Convert Axum request to our HttpRequest
-        let request = match Self::convert_request(method, path, query, headers, body).await {
+        let request = match Self::convert_request(method, path, query, headers, buffered).await {
             Ok(req) => req,
-            Err(e) => return Self::error_response(StatusCode::BAD_REQUEST, e.to_string()),
+            Err(e) => return e.into_response(),
         };
 
-        // Process through middleware
+        // Process through middleware, which sees the outcome however the request ends
         let mut request = request;
         let middleware_chain = state.middleware_chain.read().await;
-        if let Err(e) = middleware_chain.process_request(&mut request).await {
-            return Self::error_response(StatusCode::BAD_REQUEST, e.to_string());
-        }
+        let (router, dispatcher) = (&state.router, &state.dispatcher);
+        let result = middleware_chain.handle(&mut request, |request| async move {
+            if let Some((route, body)) = upload {
+                let parts = MultipartUpload::from_body(&request, body, route.limits)?;
+                return route.handler.handle_upload(request, parts).await;
+            }
 
-        // Route the request
-        let router = state.router.read().await;
-        let route_match = match router.match_route(&request) {
-            Ok(route) => Some(route),
-            Err(_) => None, // Will be handled as 404 by dispatcher
-        };
+            // Route the request
+            let router = router.read().await;
+            let route_match = match router.match_route(&request) {
+                Ok(route) => Some(route),
+                Err(e @ GatewayError::MethodNotAllowed { .. }) => return Ok(e.into_http_response(request.request_id.clone())),
+                Err(_) => None, // Will be handled as 404 by dispatcher
+            };
 
-        // Dispatch to handler
-        let dispatcher = state.dispatcher.read().await;
-        let response = match dispatcher.dispatch(request.clone(), route_match).await {
-            Ok(resp) => resp,
-            Err(e) => {
-                tracing::error!("Request handling error: {:?}", e);
-                return Self::error_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error".to_string());
-            }
-        };
+            // Dispatch to handler
+            let dispatcher = dispatcher.read().await;
+            dispatcher.dispatch(request, route_match).await
+        }).await;
 
-        // Process response through middleware
-        let mut response = response;
-        if let Err(e) = middleware_chain.process_response(&mut response).await {
-            tracing::error!("Response middleware error: {:?}", e);
+        // Convert to Axum response
+        match result {
+            Ok(response) => Self::convert_response(response).await,
+            Err(e) => e.into_response_with_correlation_id(&request.request_id),
         }
+    }
 
-        // Convert to Axum response
-        Self::convert_response(response).await
+    /// Read a whole request body, which must be UTF-8
+    async fn read_body(body: Body) -> GatewayResult<String> {
+        let bytes = hyper::body::to_bytes(body).await
+            .map_err(|e| GatewayError::Http(format!("Failed to read request body: {}", e)))?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| GatewayError::InvalidRequest("Request body is not valid UTF-8".to_string()))
     }
 
     /// Convert Axum request to HttpRequest
@@ -234,35 +377,27 @@ impl GatewayServer {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64,
+            cancellation: CancellationToken::new(),
         })
     }
 
     /// Convert HttpResponse to Axum response
+    ///
+    /// The body bytes and headers are sent as handlers and middleware left
+    /// them, so encoded bodies such as compressed ones reach the client intact.
     async fn convert_response(response: HttpResponse) -> Response {
-        use axum::response::Json;
-        use serde_json::json;
-
-        // For now, return a JSON response
-        let response_json = json!({
-            "status_code": response.status_code,
-            "headers": response.headers,
-            "body": response.body.map(|b| String::from_utf8_lossy(&b).to_string())
-        });
-
-        (axum::http::StatusCode::from_u16(response.status_code).unwrap_or(axum::http::StatusCode::OK), Json(response_json)).into_response()
-    }
-
-    /// Create error response
-    fn error_response(status: StatusCode, message: String) -> Response {
-        use axum::response::Json;
-        use serde_json::json;
-
-        let error_json = json!({
-            "error": message,
-            "status_code": status.as_u16()
-        });
-
-        (status, Json(error_json)).into_response()
+        let mut converted = Response::new(axum::body::boxed(axum::body::Full::from(response.body.unwrap_or_default())));
+        *converted.status_mut() = StatusCode::from_u16(response.status_code).unwrap_or(StatusCode::OK);
+
+        for (name, value) in &response.headers {
+            match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+                (Ok(name), Ok(value)) => {
+                    converted.headers_mut().insert(name, value);
+                }
+                _ => tracing::warn!("Dropping invalid response header {}", name),
+            }
+        }
+        converted
     }
 
     /// Shutdown signal handler
@@ -302,6 +437,8 @@ impl Default for GatewayServer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use sira_ai_backends::{MockListing, MockProvider};
+    use sira_kernel::kernel::KernelConfig;
 
     #[test]
     fn test_server_creation() {
@@ -311,4 +448,84 @@ mod tests {
         assert_eq!(server.config.host, "127.0.0.1");
         assert_eq!(server.config.port, 8080);
     }
+
+    #[tokio::test]
+    async fn test_readiness_requires_backend_and_running_kernel() {
+        let status = |server: &GatewayServer| {
+            let state = server.state.clone();
+            async move { GatewayServer::readyz(State(state)).await.status() }
+        };
+
+        // Alive but not ready without an AI backend
+        let server = GatewayServer::new_simple(GatewayConfig::default());
+        assert_eq!(GatewayServer::livez().await.status(), StatusCode::OK);
+        assert_eq!(status(&server).await, StatusCode::SERVICE_UNAVAILABLE);
+
+        let client = AiBackendClient::new();
+        client.register_provider("idle", MockProvider::new("idle", &["idle-model"]).boxed()).await.unwrap();
+        let config = KernelConfig {
+            auto_discover_plugins: false,
+            enable_resource_monitoring: false,
+            ..Default::default()
+        };
+        let kernel = Arc::new(Microkernel::new(config).await.unwrap());
+        let server = GatewayServer::new(GatewayConfig::default(), Some(Arc::new(client)), None)
+            .with_kernel(kernel.clone());
+
+        // A stopped kernel is down
+        assert_eq!(status(&server).await, StatusCode::SERVICE_UNAVAILABLE);
+
+        // Running without services or plugins is degraded, which can still serve
+        kernel.start().await.unwrap();
+        assert_eq!(status(&server).await, StatusCode::OK);
+        kernel.stop().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_readiness_probe_of_unchecked_providers_is_bounded() {
+        let client = AiBackendClient::new();
+        let hanging = MockProvider::new("hanging", &["model"]).with_listing(MockListing::Hanging);
+        let log = hanging.log();
+        client.register_provider("hanging", hanging.boxed()).await.unwrap();
+        let server = GatewayServer::new(GatewayConfig::default(), Some(Arc::new(client)), None);
+
+        let start = tokio::time::Instant::now();
+        let response = GatewayServer::readyz(State(server.state.clone())).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(start.elapsed(), READINESS_PROBE_TIMEOUT);
+        assert_eq!(log.listings(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_large_responses_reach_clients_gzip_compressed() {
+        use std::io::Read;
+
+        let client = AiBackendClient::new();
+        client.register_provider("length", MockProvider::new("length", &["embed-model"]).boxed()).await.unwrap();
+        let server = GatewayServer::new(GatewayConfig::default(), Some(Arc::new(client)), None);
+
+        let input: Vec<String> = (1..=200).map(|length| "x".repeat(length)).collect();
+        let body = serde_json::json!({ "model": "embed-model", "input": input }).to_string();
+        let mut headers = HeaderMap::new();
+        headers.insert("accept-encoding", HeaderValue::from_static("gzip"));
+
+        let response = GatewayServer::handle_request(
+            State(server.state.clone()),
+            Method::POST,
+            Path("v1/embeddings".to_string()),
+            Query(HashMap::new()),
+            headers,
+            RawBody(Body::from(body)),
+        ).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        assert_eq!(response.headers()["content-type"], "application/json");
+
+        let compressed = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(&compressed[..]).read_to_string(&mut decompressed).unwrap();
+        let embeddings: serde_json::Value = serde_json::from_str(&decompressed).unwrap();
+        assert_eq!(embeddings["data"].as_array().unwrap().len(), 200);
+        assert_eq!(embeddings["data"][199]["embedding"], serde_json::json!([200.0]));
+    }
 }
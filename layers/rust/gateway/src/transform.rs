@@ -0,0 +1,270 @@
+//! Per-route request and response transformations
+//!
+//! A [`RouteTransform`] rewrites a request before its handler sees it and/or
+//! the response before it leaves the gateway, e.g. to inject a default system
+//! prompt, resolve model aliases or strip internal fields. Transforms are
+//! attached to a route in a [`RouteTransforms`] registry and run in the order
+//! they were added: request hooks first to last, response hooks last to
+//! first, so each transform wraps the ones added after it.
+//!
+//! A route is identified by its route ID when it matched a configured route,
+//! and by the request path otherwise (e.g. `/v1/embeddings`).
+
+use crate::{GatewayError, GatewayResult, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Transformation of the requests and responses of a route
+#[async_trait]
+pub trait RouteTransform: Send + Sync {
+    /// Transform name
+    fn name(&self) -> &str;
+
+    /// Rewrite a request before it is handled
+    async fn transform_request(&self, _request: &mut HttpRequest) -> GatewayResult<()> {
+        Ok(())
+    }
+
+    /// Rewrite a response before it is returned
+    async fn transform_response(&self, _response: &mut HttpResponse) -> GatewayResult<()> {
+        Ok(())
+    }
+}
+
+/// Ordered transforms attached to each route
+#[derive(Default, Clone)]
+pub struct RouteTransforms {
+    routes: HashMap<String, Vec<Arc<dyn RouteTransform>>>,
+}
+
+impl RouteTransforms {
+    /// Create a registry without transforms
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a transform to a route
+    pub fn add(&mut self, route: impl Into<String>, transform: Arc<dyn RouteTransform>) {
+        self.routes.entry(route.into()).or_default().push(transform);
+    }
+
+    /// Names of the transforms attached to a route, in order
+    pub fn transforms_for(&self, route: &str) -> Vec<&str> {
+        self.routes.get(route)
+            .map(|transforms| transforms.iter().map(|t| t.name()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Run a route's request transforms in order
+    pub async fn apply_request(&self, route: &str, request: &mut HttpRequest) -> GatewayResult<()> {
+        for transform in self.routes.get(route).into_iter().flatten() {
+            transform.transform_request(request).await?;
+        }
+        Ok(())
+    }
+
+    /// Run a route's response transforms in reverse order
+    pub async fn apply_response(&self, route: &str, response: &mut HttpResponse) -> GatewayResult<()> {
+        for transform in self.routes.get(route).into_iter().flatten().rev() {
+            transform.transform_response(response).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Body of a request or response parsed as JSON, if it is JSON
+fn json_body(body: &Option<Vec<u8>>) -> Option<Value> {
+    body.as_ref().and_then(|bytes| serde_json::from_slice(bytes).ok())
+}
+
+fn encode_body(value: &Value) -> GatewayResult<Option<Vec<u8>>> {
+    serde_json::to_vec(value)
+        .map(Some)
+        .map_err(|e| GatewayError::Parse(e.to_string()))
+}
+
+/// Sets a request header, overwriting any value the client sent
+pub struct SetHeaderTransform {
+    name: String,
+    value: String,
+}
+
+impl SetHeaderTransform {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl RouteTransform for SetHeaderTransform {
+    fn name(&self) -> &str {
+        "set_header"
+    }
+
+    async fn transform_request(&self, request: &mut HttpRequest) -> GatewayResult<()> {
+        request.headers.retain(|name, _| !name.eq_ignore_ascii_case(&self.name));
+        request.headers.insert(self.name.clone(), self.value.clone());
+        Ok(())
+    }
+}
+
+/// Replaces model aliases in the `model` field of JSON requests
+pub struct ModelAliasTransform {
+    aliases: HashMap<String, String>,
+}
+
+impl ModelAliasTransform {
+    pub fn new() -> Self {
+        Self { aliases: HashMap::new() }
+    }
+
+    /// Resolve `alias` to `model`
+    pub fn with_alias(mut self, alias: impl Into<String>, model: impl Into<String>) -> Self {
+        self.aliases.insert(alias.into(), model.into());
+        self
+    }
+}
+
+impl Default for ModelAliasTransform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl RouteTransform for ModelAliasTransform {
+    fn name(&self) -> &str {
+        "model_alias"
+    }
+
+    async fn transform_request(&self, request: &mut HttpRequest) -> GatewayResult<()> {
+        let Some(mut body) = json_body(&request.body) else {
+            return Ok(());
+        };
+        let model = body.get("model").and_then(Value::as_str).and_then(|model| self.aliases.get(model));
+        if let Some(model) = model.cloned() {
+            body["model"] = Value::String(model);
+            request.body = encode_body(&body)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prepends a system message to chat requests that have none
+pub struct DefaultSystemPromptTransform {
+    prompt: String,
+}
+
+impl DefaultSystemPromptTransform {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self { prompt: prompt.into() }
+    }
+}
+
+#[async_trait]
+impl RouteTransform for DefaultSystemPromptTransform {
+    fn name(&self) -> &str {
+        "default_system_prompt"
+    }
+
+    async fn transform_request(&self, request: &mut HttpRequest) -> GatewayResult<()> {
+        let Some(mut body) = json_body(&request.body) else {
+            return Ok(());
+        };
+        let Some(messages) = body.get_mut("messages").and_then(Value::as_array_mut) else {
+            return Ok(());
+        };
+        if messages.iter().any(|message| message.get("role").and_then(Value::as_str) == Some("system")) {
+            return Ok(());
+        }
+        messages.insert(0, serde_json::json!({ "role": "system", "content": self.prompt }));
+        request.body = encode_body(&body)?;
+        Ok(())
+    }
+}
+
+/// Removes top-level fields from JSON response bodies
+pub struct StripFieldsTransform {
+    fields: Vec<String>,
+}
+
+impl StripFieldsTransform {
+    pub fn new<I, S>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self { fields: fields.into_iter().map(Into::into).collect() }
+    }
+}
+
+#[async_trait]
+impl RouteTransform for StripFieldsTransform {
+    fn name(&self) -> &str {
+        "strip_fields"
+    }
+
+    async fn transform_response(&self, response: &mut HttpResponse) -> GatewayResult<()> {
+        let Some(Value::Object(mut body)) = json_body(&response.body) else {
+            return Ok(());
+        };
+        let before = body.len();
+        body.retain(|field, _| !self.fields.contains(field));
+        if body.len() != before {
+            response.body = encode_body(&Value::Object(body))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HttpMethod, RequestDispatcher};
+    use tokio_util::sync::CancellationToken;
+
+    fn request(path: &str) -> HttpRequest {
+        HttpRequest::new(HttpMethod::POST, path)
+            .with_request_id("req-1")
+            .with_header("x-team", "client")
+            .with_body(br#"{"model":"fast","messages":[]}"#.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_request_transforms_run_in_order() {
+        let mut transforms = RouteTransforms::new();
+        transforms.add("chat", Arc::new(SetHeaderTransform::new("X-Team", "research")));
+        transforms.add("chat", Arc::new(ModelAliasTransform::new().with_alias("fast", "gpt-3.5-turbo")));
+        transforms.add("chat", Arc::new(DefaultSystemPromptTransform::new("Be brief.")));
+        assert_eq!(transforms.transforms_for("chat"), vec!["set_header", "model_alias", "default_system_prompt"]);
+
+        let mut chat = request("/v1/chat/completions");
+        transforms.apply_request("chat", &mut chat).await.unwrap();
+
+        assert_eq!(chat.headers.len(), 1);
+        assert_eq!(chat.headers.get("X-Team").map(String::as_str), Some("research"));
+        let body = json_body(&chat.body).unwrap();
+        assert_eq!(body["model"], "gpt-3.5-turbo");
+        assert_eq!(body["messages"][0]["content"], "Be brief.");
+
+        // Other routes are left alone
+        let mut other = request("/v1/embeddings");
+        transforms.apply_request("embeddings", &mut other).await.unwrap();
+        assert_eq!(other.headers.get("x-team").map(String::as_str), Some("client"));
+    }
+
+    #[tokio::test]
+    async fn test_response_transform_strips_field() {
+        let mut dispatcher = RequestDispatcher::new();
+        dispatcher.add_route_transform("/health", Arc::new(StripFieldsTransform::new(["timestamp"])));
+
+        let response = dispatcher.dispatch(request("/health"), None).await.unwrap();
+
+        assert_eq!(json_body(&response.body).unwrap(), serde_json::json!({ "status": "ok" }));
+    }
+}
@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 /// HTTP methods
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -44,6 +46,43 @@ pub struct HttpRequest {
     pub remote_addr: Option<String>,
     pub request_id: String,
     pub timestamp: u64,
+    /// Cancelled when the request is abandoned (e.g. timed out); downstream work should stop
+    pub cancellation: CancellationToken,
+}
+
+impl HttpRequest {
+    /// Create a request without query, headers or body, received now
+    pub fn new(method: HttpMethod, path: impl Into<String>) -> Self {
+        Self {
+            method,
+            path: path.into(),
+            query: HashMap::new(),
+            headers: HashMap::new(),
+            body: None,
+            remote_addr: None,
+            request_id: String::new(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|since| since.as_millis() as u64)
+                .unwrap_or(0),
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = request_id.into();
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
 }
 
 /// HTTP response representation
@@ -134,6 +173,26 @@ pub trait Middleware: Send + Sync {
 
     /// Process response
     async fn process_response(&self, response: &mut HttpResponse) -> crate::GatewayResult<()>;
+
+    /// Process the final response to a request this middleware saw
+    ///
+    /// [`crate::MiddlewareChain::handle`] calls this instead of
+    /// `process_response` on every exit path: handled, intercepted, rejected
+    /// by another middleware or failed, with errors turned into their error
+    /// response. Override it when the request is needed.
+    async fn complete_request(&self, _request: &HttpRequest, response: &mut HttpResponse) -> crate::GatewayResult<()> {
+        self.process_response(response).await
+    }
+
+    /// Answer a request without dispatching it, e.g. from a cache
+    async fn intercept_request(&self, _request: &HttpRequest) -> crate::GatewayResult<Option<HttpResponse>> {
+        Ok(None)
+    }
+
+    /// Maximum time the request may take to be handled, if this middleware limits it
+    fn request_timeout(&self, _request: &HttpRequest) -> Option<Duration> {
+        None
+    }
 }
 
 /// Request handler trait
@@ -1,23 +1,123 @@
 //! WebSocket Communication Service for Sira Gateway
 //!
 //! Provides real-time bidirectional communication for AI streaming responses
-//! and interactive conversations.
+//! and interactive conversations. Messages are compressed with
+//! permessage-deflate when the client offers it.
 
-use crate::{GatewayResult, GatewayError};
+use crate::{compressed_text_message, DeflateNegotiation, GatewayResult, GatewayError, InflateStream, KeyStore, Principal, DEFAULT_COMPRESSION_THRESHOLD};
 use axum::{
-    extract::{ws::WebSocketUpgrade, State, Path},
+    extract::{FromRequestParts, State, Path},
+    http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
     response::Response,
     routing::get,
     Router,
 };
 use futures_util::{SinkExt, StreamExt};
+use hyper::upgrade::{OnUpgrade, Upgraded};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
+use tokio_tungstenite::tungstenite::{
+    self,
+    handshake::derive_accept_key,
+    protocol::{frame::coding::CloseCode, CloseFrame, Role},
+};
+use tokio_tungstenite::WebSocketStream;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+/// Subprotocol the server selects during the upgrade handshake
+///
+/// Browsers cannot set headers on WebSocket requests, so clients may offer
+/// their token as a `bearer.<token>` subprotocol next to this one.
+pub const WS_PROTOCOL: &str = "sira";
+
+/// Prefix of a subprotocol carrying an API key
+const BEARER_PROTOCOL_PREFIX: &str = "bearer.";
+
+/// Server side of an upgraded WebSocket connection
+pub type ServerWebSocket = WebSocketStream<InflateStream<Upgraded>>;
+
+/// Validated WebSocket upgrade request, answered by [`WebSocketHandshake::on_upgrade`]
+pub struct WebSocketHandshake {
+    on_upgrade: OnUpgrade,
+    accept_key: String,
+    /// Whether the client offered [`WS_PROTOCOL`]
+    offers_protocol: bool,
+    /// Acceptable permessage-deflate offer, if the client made one
+    deflate_offer: Option<DeflateNegotiation>,
+}
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for WebSocketHandshake {
+    type Rejection = GatewayError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header_has_token = |name: header::HeaderName, token: &str| {
+            parts.headers.get_all(name).iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .any(|value| value.trim().eq_ignore_ascii_case(token))
+        };
+        if !header_has_token(header::CONNECTION, "upgrade") || !header_has_token(header::UPGRADE, "websocket") {
+            return Err(GatewayError::InvalidRequest("Expected a WebSocket upgrade".to_string()));
+        }
+        if parts.headers.get(header::SEC_WEBSOCKET_VERSION).map(|v| v.as_bytes()) != Some(b"13") {
+            return Err(GatewayError::InvalidRequest("Unsupported WebSocket version".to_string()));
+        }
+        let key = parts.headers.get(header::SEC_WEBSOCKET_KEY)
+            .ok_or_else(|| GatewayError::InvalidRequest("Missing Sec-WebSocket-Key".to_string()))?;
+        let on_upgrade = parts.extensions.remove::<OnUpgrade>()
+            .ok_or_else(|| GatewayError::InvalidRequest("Connection cannot be upgraded".to_string()))?;
+
+        Ok(Self {
+            on_upgrade,
+            accept_key: derive_accept_key(key.as_bytes()),
+            offers_protocol: header_has_token(header::SEC_WEBSOCKET_PROTOCOL, WS_PROTOCOL),
+            deflate_offer: DeflateNegotiation::from_offers(&parts.headers),
+        })
+    }
+}
+
+impl WebSocketHandshake {
+    /// Accept the upgrade, running `callback` on the socket once connected
+    ///
+    /// `deflate` is the permessage-deflate negotiation to confirm, if any.
+    pub fn on_upgrade<F, Fut>(self, deflate: Option<DeflateNegotiation>, callback: F) -> Response
+    where
+        F: FnOnce(ServerWebSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let on_upgrade = self.on_upgrade;
+        tokio::spawn(async move {
+            let upgraded = match on_upgrade.await {
+                Ok(upgraded) => upgraded,
+                Err(e) => {
+                    debug!("WebSocket upgrade failed: {:?}", e);
+                    return;
+                }
+            };
+            let socket = WebSocketStream::from_raw_socket(InflateStream::new(upgraded, deflate), Role::Server, None).await;
+            callback(socket).await;
+        });
+
+        let mut response = Response::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header(header::CONNECTION, "upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_ACCEPT, self.accept_key);
+        if self.offers_protocol {
+            response = response.header(header::SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(WS_PROTOCOL));
+        }
+        if let Some(deflate) = deflate {
+            response = response.header(header::SEC_WEBSOCKET_EXTENSIONS, deflate.response_header());
+        }
+        response.body(axum::body::boxed(axum::body::Empty::new())).unwrap()
+    }
+}
+
 /// WebSocket message types for AI communication
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
@@ -39,6 +139,14 @@ pub enum WebSocketMessage {
     /// Client requests to end conversation
     EndConversation,
 
+    /// Client joins a room; responses for any member are sent to all members
+    JoinRoom {
+        room_id: String,
+    },
+
+    /// Client leaves its current room
+    LeaveRoom,
+
     /// Server acknowledges connection
     ConnectionAck {
         connection_id: String,
@@ -66,6 +174,12 @@ pub enum WebSocketMessage {
         details: Option<serde_json::Value>,
     },
 
+    /// Server reports the current members of a room after a join or leave
+    RoomMembership {
+        room_id: String,
+        members: Vec<String>,
+    },
+
     /// Ping/Pong for connection health
     Ping,
     Pong,
@@ -95,6 +209,7 @@ pub struct WebSocketConnection {
     pub id: String,
     pub user_id: Option<String>,
     pub session_id: Option<String>,
+    pub room_id: Option<String>,
     pub state: ConnectionState,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub last_activity: chrono::DateTime<chrono::Utc>,
@@ -103,8 +218,16 @@ pub struct WebSocketConnection {
 /// WebSocket connection manager
 pub struct WebSocketManager {
     connections: Arc<RwLock<HashMap<String, WebSocketConnection>>>,
+    /// Outbound message channel of each connection
+    outbound: Arc<RwLock<HashMap<String, mpsc::Sender<WebSocketMessage>>>>,
+    /// Room ID -> member connection IDs
+    rooms: Arc<RwLock<HashMap<String, HashSet<String>>>>,
     ai_client: Arc<sira_ai_backends::AiBackendClient>,
     session_manager: Option<Arc<sira_session::SessionManager>>,
+    /// API keys connections must present; without one connections are unauthenticated
+    key_store: RwLock<Option<Arc<KeyStore>>>,
+    /// Smallest outbound message compressed when the client negotiated it; `None` disables compression
+    compression_threshold: RwLock<Option<usize>>,
 }
 
 impl WebSocketManager {
@@ -115,50 +238,120 @@ impl WebSocketManager {
     ) -> Self {
         Self {
             connections: Arc::new(RwLock::new(HashMap::new())),
+            outbound: Arc::new(RwLock::new(HashMap::new())),
+            rooms: Arc::new(RwLock::new(HashMap::new())),
             ai_client,
             session_manager,
+            key_store: RwLock::new(None),
+            compression_threshold: RwLock::new(Some(DEFAULT_COMPRESSION_THRESHOLD)),
+        }
+    }
+
+    /// Require connections to authenticate with a key from `key_store`
+    pub async fn set_key_store(&self, key_store: Arc<KeyStore>) {
+        *self.key_store.write().await = Some(key_store);
+    }
+
+    /// Set the smallest outbound message to compress; `None` declines permessage-deflate
+    pub async fn set_compression_threshold(&self, threshold: Option<usize>) {
+        *self.compression_threshold.write().await = threshold;
+    }
+
+    /// Extract the API key offered during the upgrade handshake
+    ///
+    /// The key is read from the `token` query parameter, or from a
+    /// `bearer.<token>` entry of the `Sec-WebSocket-Protocol` header.
+    pub fn upgrade_token(query: &HashMap<String, String>, headers: &HeaderMap) -> Option<String> {
+        if let Some(token) = query.get("token").filter(|token| !token.is_empty()) {
+            return Some(token.clone());
+        }
+
+        headers.get_all("sec-websocket-protocol")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .find_map(|protocol| protocol.trim().strip_prefix(BEARER_PROTOCOL_PREFIX))
+            .filter(|token| !token.is_empty())
+            .map(str::to_string)
+    }
+
+    /// Verify the credentials of an upgrade request
+    ///
+    /// Without a key store every upgrade is accepted unauthenticated. With one,
+    /// a valid token is required, and a user ID requested in the path must be
+    /// the user the token belongs to.
+    pub async fn authenticate_upgrade(&self, token: Option<&str>, requested_user: Option<&str>) -> GatewayResult<Option<Principal>> {
+        let Some(key_store) = self.key_store.read().await.clone() else {
+            return Ok(None);
+        };
+
+        let token = token.ok_or_else(|| GatewayError::Auth("Missing API key".to_string()))?;
+        let principal = key_store.authenticate(token).await?;
+        if let Some(requested_user) = requested_user {
+            if requested_user != principal.user_id {
+                return Err(GatewayError::Auth(format!("API key does not belong to user {}", requested_user)));
+            }
         }
+
+        Ok(Some(principal))
     }
 
     /// Handle WebSocket upgrade and connection
+    ///
+    /// Upgrades failing authentication are completed and then closed with a
+    /// policy violation code, so browser clients can tell why they were refused.
     pub async fn handle_connection(
         self: Arc<Self>,
-        ws: WebSocketUpgrade,
+        ws: WebSocketHandshake,
         user_id: Option<String>,
+        token: Option<String>,
     ) -> Response {
+        let compression_threshold = *self.compression_threshold.read().await;
+        let deflate = ws.deflate_offer.filter(|_| compression_threshold.is_some());
+        let user_id = match self.authenticate_upgrade(token.as_deref(), user_id.as_deref()).await {
+            // The verified principal is bound to the connection
+            Ok(Some(principal)) => Some(principal.user_id),
+            Ok(None) => user_id,
+            Err(e) => {
+                warn!("Rejecting WebSocket upgrade: {}", e);
+                return ws.on_upgrade(deflate, |mut socket| async move {
+                    let close = CloseFrame {
+                        code: CloseCode::Policy,
+                        reason: "Authentication failed".into(),
+                    };
+                    if let Err(e) = socket.send(tungstenite::Message::Close(Some(close))).await {
+                        debug!("Failed to close unauthenticated WebSocket: {:?}", e);
+                    }
+                });
+            }
+        };
+
         let connection_id = Uuid::new_v4().to_string();
 
-        ws.on_upgrade(move |socket| async move {
-            if let Err(e) = self.clone().handle_socket(socket, connection_id.clone(), user_id).await {
+        // Outbound messages are only compressed once the client agreed to it
+        let compression_threshold = compression_threshold.filter(|_| deflate.is_some());
+        ws.on_upgrade(deflate, move |socket| async move {
+            if let Err(e) = self.clone().handle_socket(socket, connection_id.clone(), user_id, compression_threshold).await {
                 error!("WebSocket connection error for {}: {:?}", connection_id, e);
             }
         })
     }
 
     /// Handle individual WebSocket connection
+    ///
+    /// Outbound messages of at least `compression_threshold` bytes are sent compressed.
     async fn handle_socket(
         self: Arc<Self>,
-        socket: axum::extract::ws::WebSocket,
+        socket: ServerWebSocket,
         connection_id: String,
         user_id: Option<String>,
+        compression_threshold: Option<usize>,
     ) -> GatewayResult<()> {
         let (sender, mut receiver) = socket.split();
         let (tx, mut rx) = mpsc::channel::<WebSocketMessage>(100);
 
         // Register connection
-        let connection = WebSocketConnection {
-            id: connection_id.clone(),
-            user_id: user_id.clone(),
-            session_id: None,
-            state: ConnectionState::Connected,
-            created_at: chrono::Utc::now(),
-            last_activity: chrono::Utc::now(),
-        };
-
-        {
-            let mut connections = self.connections.write().await;
-            connections.insert(connection_id.clone(), connection);
-        }
+        self.register_connection(&connection_id, user_id.clone(), tx.clone()).await;
 
         info!("WebSocket connection established: {}", connection_id);
 
@@ -184,7 +377,15 @@ impl WebSocketManager {
                     }
                 };
 
-                if sender.send(axum::extract::ws::Message::Text(json)).await.is_err() {
+                let message = match compression_threshold {
+                    Some(threshold) if json.len() >= threshold => compressed_text_message(&json).unwrap_or_else(|e| {
+                        warn!("Failed to compress WebSocket message: {:?}", e);
+                        tungstenite::Message::Text(json)
+                    }),
+                    _ => tungstenite::Message::Text(json),
+                };
+
+                if sender.send(message).await.is_err() {
                     break;
                 }
             }
@@ -203,15 +404,15 @@ impl WebSocketManager {
             };
 
             let text = match msg {
-                axum::extract::ws::Message::Text(text) => text,
-                axum::extract::ws::Message::Ping(data) => {
+                tungstenite::Message::Text(text) => text,
+                tungstenite::Message::Ping(data) => {
                     if tx.send(WebSocketMessage::Pong).await.is_err() {
                         break;
                     }
                     continue;
                 }
-                axum::extract::ws::Message::Pong(_) => continue,
-                axum::extract::ws::Message::Close(_) => break,
+                tungstenite::Message::Pong(_) => continue,
+                tungstenite::Message::Close(_) => break,
                 _ => continue,
             };
 
@@ -247,10 +448,7 @@ impl WebSocketManager {
         }
 
         // Clean up connection
-        {
-            let mut connections = self.connections.write().await;
-            connections.remove(&connection_id);
-        }
+        self.unregister_connection(&connection_id).await;
 
         info!("WebSocket connection closed: {}", connection_id);
         sender_task.abort();
@@ -279,6 +477,23 @@ impl WebSocketManager {
                 self.handle_end_conversation(tx, connection_id).await?;
             }
 
+            WebSocketMessage::JoinRoom { room_id } => {
+                match self.join_room(connection_id, &room_id).await {
+                    Err(GatewayError::Auth(message)) => {
+                        let _ = tx.send(WebSocketMessage::Error {
+                            code: "FORBIDDEN".to_string(),
+                            message,
+                            details: None,
+                        }).await;
+                    }
+                    result => result?,
+                }
+            }
+
+            WebSocketMessage::LeaveRoom => {
+                self.leave_room(connection_id).await;
+            }
+
             WebSocketMessage::Ping => {
                 let _ = tx.send(WebSocketMessage::Pong).await;
             }
@@ -379,12 +594,7 @@ impl WebSocketManager {
                     .unwrap_or("")
                     .to_string();
 
-                sira_ai_backends::ChatMessage {
-                    role,
-                    content: sira_ai_backends::MessageContent::Text(content),
-                    name: None,
-                    function_call: None,
-                }
+                sira_ai_backends::ChatMessage::new(role, content)
             })
             .collect();
 
@@ -416,7 +626,7 @@ impl WebSocketManager {
                                         usage: None,
                                     };
 
-                                    if tx.send(streaming_msg).await.is_err() {
+                                    if self.deliver(connection_id, streaming_msg).await.is_err() {
                                         break;
                                     }
                                 }
@@ -437,7 +647,7 @@ impl WebSocketManager {
                                 message: format!("Streaming failed: {}", e),
                                 details: None,
                             };
-                            let _ = tx.send(error_msg).await;
+                            let _ = self.deliver(connection_id, error_msg).await;
                             return Ok(());
                         }
                     }
@@ -446,12 +656,12 @@ impl WebSocketManager {
                 // Send completion message
                 if let Some(usage) = usage {
                     let complete_msg = WebSocketMessage::CompleteResponse {
-                        content: full_content,
+                        content: full_content.clone(),
                         usage,
                         finish_reason: "stop".to_string(),
                     };
 
-                    let _ = tx.send(complete_msg).await;
+                    let _ = self.deliver(connection_id, complete_msg).await;
                 } else {
                     // Send final streaming chunk
                     let final_msg = WebSocketMessage::StreamingResponse {
@@ -460,7 +670,7 @@ impl WebSocketManager {
                         usage,
                     };
 
-                    let _ = tx.send(final_msg).await;
+                    let _ = self.deliver(connection_id, final_msg).await;
                 }
 
                 // Add assistant message to context
@@ -500,6 +710,164 @@ impl WebSocketManager {
         Ok(())
     }
 
+    /// Track a connection and the channel its outbound messages are written to
+    pub async fn register_connection(
+        &self,
+        connection_id: &str,
+        user_id: Option<String>,
+        tx: mpsc::Sender<WebSocketMessage>,
+    ) {
+        let connection = WebSocketConnection {
+            id: connection_id.to_string(),
+            user_id,
+            session_id: None,
+            room_id: None,
+            state: ConnectionState::Connected,
+            created_at: chrono::Utc::now(),
+            last_activity: chrono::Utc::now(),
+        };
+
+        self.connections.write().await.insert(connection_id.to_string(), connection);
+        self.outbound.write().await.insert(connection_id.to_string(), tx);
+    }
+
+    /// Forget a connection, leaving its room first
+    pub async fn unregister_connection(&self, connection_id: &str) {
+        self.leave_room(connection_id).await;
+        self.connections.write().await.remove(connection_id);
+        self.outbound.write().await.remove(connection_id);
+    }
+
+    /// Add a connection to a room, leaving any room it was already in
+    ///
+    /// A room belongs to the user of its members: a connection may only join
+    /// an empty room or one whose members all belong to the same user as it.
+    pub async fn join_room(&self, connection_id: &str, room_id: &str) -> GatewayResult<()> {
+        self.authorize_join(connection_id, room_id, false).await?;
+        self.leave_room(connection_id).await;
+
+        // Check again under the room lock in case another user took the room meanwhile
+        self.authorize_join(connection_id, room_id, true).await?;
+
+        if let Some(conn) = self.connections.write().await.get_mut(connection_id) {
+            conn.room_id = Some(room_id.to_string());
+        }
+
+        debug!("Connection {} joined room {}", connection_id, room_id);
+        self.announce_membership(room_id).await;
+        Ok(())
+    }
+
+    /// Fail unless the connection may join the room, adding it if `insert` is set
+    async fn authorize_join(&self, connection_id: &str, room_id: &str, insert: bool) -> GatewayResult<()> {
+        let connections = self.connections.read().await;
+        let user_id = connections.get(connection_id)
+            .ok_or_else(|| GatewayError::Http(format!("Unknown WebSocket connection: {}", connection_id)))?
+            .user_id
+            .clone();
+
+        let mut rooms = self.rooms.write().await;
+        let foreign = rooms.get(room_id).is_some_and(|members| {
+            members.iter().any(|member| connections.get(member).is_some_and(|conn| conn.user_id != user_id))
+        });
+        if foreign {
+            warn!("Connection {} denied joining room {} of another user", connection_id, room_id);
+            return Err(GatewayError::Auth(format!("Room {} belongs to another user", room_id)));
+        }
+
+        if insert {
+            rooms.entry(room_id.to_string()).or_default().insert(connection_id.to_string());
+        }
+        Ok(())
+    }
+
+    /// Remove a connection from its room; empty rooms are dropped
+    pub async fn leave_room(&self, connection_id: &str) {
+        let room_id = match self.connections.write().await.get_mut(connection_id) {
+            Some(conn) => conn.room_id.take(),
+            None => None,
+        };
+        let room_id = match room_id {
+            Some(room_id) => room_id,
+            None => return,
+        };
+
+        let room_is_empty = {
+            let mut rooms = self.rooms.write().await;
+            match rooms.get_mut(&room_id) {
+                Some(members) => {
+                    members.remove(connection_id);
+                    if members.is_empty() {
+                        rooms.remove(&room_id);
+                        true
+                    } else {
+                        false
+                    }
+                }
+                None => true,
+            }
+        };
+
+        debug!("Connection {} left room {}", connection_id, room_id);
+        if !room_is_empty {
+            self.announce_membership(&room_id).await;
+        }
+    }
+
+    /// Get the connection IDs in a room
+    pub async fn room_members(&self, room_id: &str) -> Vec<String> {
+        let rooms = self.rooms.read().await;
+        let mut members: Vec<String> = rooms
+            .get(room_id)
+            .map(|members| members.iter().cloned().collect())
+            .unwrap_or_default();
+        members.sort();
+        members
+    }
+
+    /// Send a message to every member of a room
+    pub async fn broadcast_to_room(&self, room_id: &str, message: WebSocketMessage) {
+        let senders: Vec<(String, mpsc::Sender<WebSocketMessage>)> = {
+            let members = self.room_members(room_id).await;
+            let outbound = self.outbound.read().await;
+            members
+                .into_iter()
+                .filter_map(|id| outbound.get(&id).map(|tx| (id, tx.clone())))
+                .collect()
+        };
+
+        for (member_id, tx) in senders {
+            if tx.send(message.clone()).await.is_err() {
+                debug!("Room {} member {} is no longer receiving", room_id, member_id);
+            }
+        }
+    }
+
+    /// Send a server response for a connection, fanning out to its room if it has one
+    pub async fn deliver(&self, connection_id: &str, message: WebSocketMessage) -> GatewayResult<()> {
+        let room_id = self.connections.read().await
+            .get(connection_id)
+            .and_then(|conn| conn.room_id.clone());
+
+        if let Some(room_id) = room_id {
+            self.broadcast_to_room(&room_id, message).await;
+            return Ok(());
+        }
+
+        let tx = self.outbound.read().await.get(connection_id).cloned()
+            .ok_or_else(|| GatewayError::Http(format!("Unknown WebSocket connection: {}", connection_id)))?;
+        tx.send(message).await
+            .map_err(|_| GatewayError::InternalServerError("Failed to send message".to_string()))
+    }
+
+    async fn announce_membership(&self, room_id: &str) {
+        let members = self.room_members(room_id).await;
+        self.broadcast_to_room(room_id, WebSocketMessage::RoomMembership {
+            room_id: room_id.to_string(),
+            members,
+        }).await;
+    }
+
     /// Get connection statistics
     pub async fn get_stats(&self) -> HashMap<String, usize> {
         let connections = self.connections.read().await;
@@ -508,6 +876,7 @@ impl WebSocketManager {
         stats.insert("total_connections".to_string(), connections.len());
         stats.insert("active_connections".to_string(),
             connections.values().filter(|c| matches!(c.state, ConnectionState::Active { .. })).count());
+        stats.insert("rooms".to_string(), self.rooms.read().await.len());
 
         stats
     }
@@ -515,17 +884,356 @@ impl WebSocketManager {
 
 /// Create WebSocket routes
 pub fn websocket_routes(manager: Arc<WebSocketManager>) -> axum::Router {
-    use axum::{routing::get, extract::Path, Router};
+    use axum::{routing::get, extract::{Path, Query}, Router};
 
     let manager_clone1 = manager.clone();
     let manager_clone2 = manager.clone();
 
     Router::new()
-        .route("/ws", get(move |ws: axum::extract::ws::WebSocketUpgrade| async move {
-            manager_clone1.handle_connection(ws, None).await
+        .route("/ws", get(move |ws: WebSocketHandshake, Query(query): Query<HashMap<String, String>>, headers: HeaderMap| async move {
+            let token = WebSocketManager::upgrade_token(&query, &headers);
+            manager_clone1.handle_connection(ws, None, token).await
         }))
-        .route("/ws/:user_id", get(move |ws: axum::extract::ws::WebSocketUpgrade, Path(user_id): Path<String>| async move {
-            manager_clone2.handle_connection(ws, Some(user_id)).await
+        .route("/ws/:user_id", get(move |ws: WebSocketHandshake, Path(user_id): Path<String>, Query(query): Query<HashMap<String, String>>, headers: HeaderMap| async move {
+            let token = WebSocketManager::upgrade_token(&query, &headers);
+            manager_clone2.handle_connection(ws, Some(user_id), token).await
         }))
         .with_state(manager)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serve the WebSocket routes on an ephemeral port
+    fn serve(manager: Arc<WebSocketManager>) -> std::net::SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = axum::Server::from_tcp(listener).unwrap()
+            .serve(websocket_routes(manager).into_make_service());
+        tokio::spawn(server);
+        addr
+    }
+
+    async fn authenticated_manager() -> Arc<WebSocketManager> {
+        let key_store = Arc::new(KeyStore::new());
+        key_store.add_key("alice-key", "alice").await;
+        let manager = Arc::new(WebSocketManager::new(Arc::new(sira_ai_backends::AiBackendClient::new()), None));
+        manager.set_key_store(key_store).await;
+        manager
+    }
+
+    fn chunk_of(message: WebSocketMessage) -> Option<String> {
+        match message {
+            WebSocketMessage::StreamingResponse { chunk, .. } => Some(chunk),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_room_members_share_streamed_response() {
+        let manager = WebSocketManager::new(Arc::new(sira_ai_backends::AiBackendClient::new()), None);
+        let (tx_a, mut rx_a) = mpsc::channel(16);
+        let (tx_b, mut rx_b) = mpsc::channel(16);
+        manager.register_connection("conn_a", None, tx_a).await;
+        manager.register_connection("conn_b", None, tx_b).await;
+
+        manager.join_room("conn_a", "shared").await.unwrap();
+        manager.join_room("conn_b", "shared").await.unwrap();
+        assert_eq!(manager.room_members("shared").await, vec!["conn_a", "conn_b"]);
+
+        // Both members hear about the second join
+        assert!(matches!(rx_a.recv().await, Some(WebSocketMessage::RoomMembership { members, .. }) if members.len() == 1));
+        assert!(matches!(rx_a.recv().await, Some(WebSocketMessage::RoomMembership { members, .. }) if members.len() == 2));
+        assert!(matches!(rx_b.recv().await, Some(WebSocketMessage::RoomMembership { members, .. }) if members.len() == 2));
+
+        // A response streamed for one member reaches both
+        for chunk in ["Hel", "lo"] {
+            manager.deliver("conn_a", WebSocketMessage::StreamingResponse {
+                chunk: chunk.to_string(),
+                is_done: false,
+                usage: None,
+            }).await.unwrap();
+        }
+        for rx in [&mut rx_a, &mut rx_b] {
+            assert_eq!(chunk_of(rx.recv().await.unwrap()).as_deref(), Some("Hel"));
+            assert_eq!(chunk_of(rx.recv().await.unwrap()).as_deref(), Some("lo"));
+        }
+
+        // Leaving notifies the remaining member; the last one out removes the room
+        manager.unregister_connection("conn_a").await;
+        assert!(matches!(rx_b.recv().await, Some(WebSocketMessage::RoomMembership { members, .. }) if members == vec!["conn_b".to_string()]));
+        manager.leave_room("conn_b").await;
+        assert!(manager.room_members("shared").await.is_empty());
+        assert_eq!(manager.get_stats().await.get("rooms"), Some(&0));
+
+        // Outside a room responses go only to the connection itself
+        manager.deliver("conn_b", WebSocketMessage::Pong).await.unwrap();
+        assert!(matches!(rx_b.recv().await, Some(WebSocketMessage::Pong)));
+    }
+
+    #[tokio::test]
+    async fn test_rooms_only_admit_connections_of_the_same_user() {
+        let manager = WebSocketManager::new(Arc::new(sira_ai_backends::AiBackendClient::new()), None);
+        let (tx, _rx) = mpsc::channel(16);
+        manager.register_connection("alice_phone", Some("alice".to_string()), tx.clone()).await;
+        manager.register_connection("alice_laptop", Some("alice".to_string()), tx.clone()).await;
+        manager.register_connection("bob", Some("bob".to_string()), tx).await;
+
+        manager.join_room("alice_phone", "session-1").await.unwrap();
+        manager.join_room("bob", "bob-room").await.unwrap();
+
+        let denied = manager.join_room("bob", "session-1").await;
+        assert!(matches!(denied, Err(GatewayError::Auth(_))));
+        // A refused join leaves the connection where it was
+        assert_eq!(manager.room_members("bob-room").await, vec!["bob"]);
+
+        manager.join_room("alice_laptop", "session-1").await.unwrap();
+        assert_eq!(manager.room_members("session-1").await, vec!["alice_laptop", "alice_phone"]);
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_with_valid_token_binds_principal() {
+        let manager = authenticated_manager().await;
+        let addr = serve(manager.clone());
+
+        let (mut socket, response) = tokio_tungstenite::connect_async(format!("ws://{}/ws/alice?token=alice-key", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 101);
+
+        let ack = match socket.next().await.unwrap().unwrap() {
+            tungstenite::Message::Text(text) => serde_json::from_str::<WebSocketMessage>(&text).unwrap(),
+            other => panic!("unexpected message: {:?}", other),
+        };
+        let connection_id = match ack {
+            WebSocketMessage::ConnectionAck { connection_id, .. } => connection_id,
+            other => panic!("unexpected message: {:?}", other),
+        };
+        let user_id = manager.connections.read().await.get(&connection_id).and_then(|c| c.user_id.clone());
+        assert_eq!(user_id.as_deref(), Some("alice"));
+
+        // The token may also be offered as a subprotocol
+        let mut request = tungstenite::client::IntoClientRequest::into_client_request(format!("ws://{}/ws", addr)).unwrap();
+        request.headers_mut().insert("sec-websocket-protocol", "sira, bearer.alice-key".parse().unwrap());
+        let (mut socket, response) = tokio_tungstenite::connect_async(request).await.unwrap();
+        assert_eq!(response.headers()["sec-websocket-protocol"], WS_PROTOCOL);
+        assert!(matches!(socket.next().await, Some(Ok(tungstenite::Message::Text(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_with_invalid_token_is_closed() {
+        let manager = authenticated_manager().await;
+        let addr = serve(manager.clone());
+
+        for url in [
+            format!("ws://{}/ws?token=stolen", addr),
+            format!("ws://{}/ws", addr),
+            // A valid key cannot be used to impersonate another user
+            format!("ws://{}/ws/bob?token=alice-key", addr),
+        ] {
+            let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+            match socket.next().await {
+                Some(Ok(tungstenite::Message::Close(Some(frame)))) => assert_eq!(frame.code, CloseCode::Policy),
+                other => panic!("unexpected message: {:?}", other),
+            }
+        }
+        assert_eq!(manager.get_stats().await.get("total_connections"), Some(&0));
+    }
+
+    #[tokio::test]
+    async fn test_permessage_deflate_is_negotiated_and_compresses_frames() {
+        use std::io::Write;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let manager = Arc::new(WebSocketManager::new(Arc::new(sira_ai_backends::AiBackendClient::new()), None));
+        manager.set_compression_threshold(Some(16)).await;
+        let addr = serve(manager);
+
+        // Handshake as a browser would offer the extension
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(format!(
+            "GET /ws HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Version: 13\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Extensions: permessage-deflate; client_max_window_bits\r\n\r\n",
+            addr
+        ).as_bytes()).await.unwrap();
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            response.push(stream.read_u8().await.unwrap());
+        }
+        let response = String::from_utf8(response).unwrap().to_lowercase();
+        assert!(response.starts_with("http/1.1 101"));
+        assert!(response.contains("sec-websocket-extensions: permessage-deflate; server_no_context_takeover"));
+
+        async fn read_frame(stream: &mut tokio::net::TcpStream) -> (u8, Vec<u8>) {
+            let first_byte = stream.read_u8().await.unwrap();
+            let len = match stream.read_u8().await.unwrap() {
+                126 => stream.read_u16().await.unwrap() as usize,
+                len => len as usize,
+            };
+            let mut payload = vec![0; len];
+            stream.read_exact(&mut payload).await.unwrap();
+            (first_byte, payload)
+        }
+        fn inflate(payload: &[u8]) -> String {
+            let mut decoder = flate2::write::DeflateDecoder::new(Vec::new());
+            decoder.write_all(payload).unwrap();
+            decoder.write_all(&[0x00, 0x00, 0xff, 0xff]).unwrap();
+            String::from_utf8(decoder.finish().unwrap()).unwrap()
+        }
+
+        // The acknowledgement exceeds the threshold and arrives compressed
+        let (first_byte, payload) = read_frame(&mut stream).await;
+        assert_eq!(first_byte, 0xC1);
+        let ack: WebSocketMessage = serde_json::from_str(&inflate(&payload)).unwrap();
+        assert!(matches!(ack, WebSocketMessage::ConnectionAck { .. }));
+
+        // A compressed client message is understood; the short reply is sent as is
+        let mask = [7, 7, 7, 7];
+        let mut payload = crate::deflate_message(br#"{"type":"Ping"}"#).unwrap();
+        payload.iter_mut().for_each(|byte| *byte ^= 7);
+        let mut frame = vec![0xC1, 0x80 | payload.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend_from_slice(&payload);
+        stream.write_all(&frame).await.unwrap();
+
+        let (first_byte, payload) = read_frame(&mut stream).await;
+        assert_eq!(first_byte, 0x81);
+        assert!(matches!(serde_json::from_slice(&payload).unwrap(), WebSocketMessage::Pong));
+    }
+
+    /// Node's WebSocket client, which offers permessage-deflate as browsers do
+    ///
+    /// The compressing side is driven through Node's zlib, the same library
+    /// browsers use, with the compression context kept across messages.
+    const NODE_DEFLATE_CLIENT: &str = r#"
+        const net = require('net');
+        const zlib = require('zlib');
+        const url = new URL(process.argv[1]);
+
+        function received() {
+            return new Promise((resolve, reject) => {
+                const ws = new WebSocket(url);
+                const messages = [];
+                ws.onerror = (event) => reject(new Error(event.message || 'websocket error'));
+                ws.onmessage = (event) => {
+                    messages.push(JSON.parse(event.data));
+                    if (messages.length === 1) {
+                        for (let i = 0; i < 3; i++) ws.send(JSON.stringify({ type: 'Ping' }));
+                    } else if (messages.length === 4) {
+                        resolve({ extensions: ws.extensions, types: messages.map((m) => m.type) });
+                        ws.close();
+                    }
+                };
+            });
+        }
+
+        function frame(firstByte, payload) {
+            const mask = Buffer.from([0x12, 0x34, 0x56, 0x78]);
+            const masked = Buffer.from(payload.map((byte, i) => byte ^ mask[i % 4]));
+            return Buffer.concat([Buffer.from([firstByte, 0x80 | 126, payload.length >> 8, payload.length & 0xff]), mask, masked]);
+        }
+
+        function sent() {
+            return new Promise((resolve, reject) => {
+                const socket = net.connect(url.port, url.hostname);
+                const deflate = zlib.createDeflateRaw();
+                let response = Buffer.alloc(0);
+                let upgraded = false;
+                let pongs = 0;
+                const compress = (text) => new Promise((done) => {
+                    const chunks = [];
+                    const collect = (chunk) => chunks.push(chunk);
+                    deflate.on('data', collect);
+                    deflate.write(text);
+                    deflate.flush(zlib.constants.Z_SYNC_FLUSH, () => {
+                        deflate.off('data', collect);
+                        done(Buffer.concat(chunks).subarray(0, -4));
+                    });
+                });
+                socket.on('error', reject);
+                socket.on('connect', () => socket.write(
+                    `GET ${url.pathname} HTTP/1.1\r\nHost: ${url.host}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n` +
+                    'Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n' +
+                    'Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits\r\n\r\n'));
+                socket.on('data', async (data) => {
+                    response = Buffer.concat([response, data]);
+                    if (!upgraded) {
+                        const end = response.indexOf('\r\n\r\n');
+                        if (end < 0) return;
+                        upgraded = true;
+                        response = response.subarray(end + 4);
+                        // Two messages sharing one compression context, each split over two frames
+                        for (const text of ['{"type":"Ping"}', ' '.repeat(64) + '{"type":"Ping"}']) {
+                            const payload = await compress(text);
+                            const half = payload.length >> 1;
+                            socket.write(Buffer.concat([frame(0x40 | 0x01, payload.subarray(0, half)), frame(0x80, payload.subarray(half))]));
+                        }
+                    }
+                    // Server frames are short, unmasked and compressed without context takeover
+                    while (response.length >= 2 && response.length >= 2 + (response[1] & 0x7f)) {
+                        const length = response[1] & 0x7f;
+                        let message = response.subarray(2, 2 + length);
+                        if (response[0] & 0x40) {
+                            message = zlib.inflateRawSync(Buffer.concat([message, Buffer.from([0, 0, 0xff, 0xff])]), { finishFlush: zlib.constants.Z_SYNC_FLUSH });
+                        }
+                        response = response.subarray(2 + length);
+                        message = message.toString();
+                        if (message.includes('"Pong"') && ++pongs === 2) {
+                            socket.end();
+                            resolve(pongs);
+                        }
+                    }
+                });
+            });
+        }
+
+        (async () => {
+            const result = await received();
+            result.pongs = await sent();
+            console.log(JSON.stringify(result));
+            process.exit(0);
+        })().catch((error) => {
+            console.error(error.stack || error);
+            process.exit(1);
+        });
+    "#;
+
+    /// Node with a global `WebSocket`, if one is installed
+    fn node_with_websocket() -> Option<tokio::process::Command> {
+        let has_websocket = |flags: &[&str]| std::process::Command::new("node")
+            .args(flags)
+            .args(["-e", "process.exit(typeof WebSocket === 'function' ? 0 : 1)"])
+            .status()
+            .is_ok_and(|status| status.success());
+
+        [&["--experimental-websocket"][..], &[]].into_iter()
+            .find(|flags| has_websocket(flags))
+            .map(|flags| {
+                let mut command = tokio::process::Command::new("node");
+                command.args(flags);
+                command
+            })
+    }
+
+    #[tokio::test]
+    #[ignore = "needs Node with a WebSocket client; run with --ignored"]
+    async fn test_permessage_deflate_interoperates_with_node_websocket_client() {
+        let mut node = node_with_websocket().expect("no node with a WebSocket client installed");
+
+        let manager = Arc::new(WebSocketManager::new(Arc::new(sira_ai_backends::AiBackendClient::new()), None));
+        manager.set_compression_threshold(Some(1)).await;
+        let addr = serve(manager.clone());
+
+        let output = tokio::time::timeout(
+            std::time::Duration::from_secs(30),
+            node.args(["-e", NODE_DEFLATE_CLIENT, &format!("ws://{}/ws", addr)]).output(),
+        ).await.expect("node client timed out").unwrap();
+        assert!(output.status.success(), "node client failed: {}", String::from_utf8_lossy(&output.stderr));
+
+        let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert!(result["extensions"].as_str().unwrap().starts_with("permessage-deflate"));
+        assert_eq!(result["types"], serde_json::json!(["ConnectionAck", "Pong", "Pong", "Pong"]));
+        assert_eq!(result["pongs"], 2);
+    }
+}
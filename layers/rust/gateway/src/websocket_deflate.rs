@@ -0,0 +1,434 @@
+//! permessage-deflate compression of WebSocket messages (RFC 7692)
+//!
+//! The extension is negotiated from the client's `Sec-WebSocket-Extensions`
+//! offer. Outbound messages above a size threshold are sent as compressed
+//! frames; compressed frames from the client are inflated by
+//! [`InflateStream`] below the WebSocket protocol layer, which would otherwise
+//! reject their reserved bit.
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+use http::HeaderMap;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::tungstenite::protocol::frame::{
+    coding::{Data, OpCode},
+    Frame,
+};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Name of the extension in `Sec-WebSocket-Extensions`
+pub const PERMESSAGE_DEFLATE: &str = "permessage-deflate";
+
+/// Smallest outbound message compressed by default, in bytes
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 256;
+
+/// Largest message accepted after inflating, in bytes
+const MAX_INFLATED_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Empty stored block ending each compressed message, omitted on the wire
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+const FIN: u8 = 0x80;
+const RSV1: u8 = 0x40;
+const MASKED: u8 = 0x80;
+
+/// Accepted permessage-deflate parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeflateNegotiation {
+    /// The client starts each message with a fresh compression context
+    pub client_no_context_takeover: bool,
+}
+
+impl DeflateNegotiation {
+    /// Accept the first permessage-deflate offer the server supports
+    ///
+    /// The server compresses each message on its own with the full window, so
+    /// offers limiting the server's window are declined.
+    pub fn from_offers(headers: &HeaderMap) -> Option<Self> {
+        headers.get_all("sec-websocket-extensions")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .find_map(Self::accept_offer)
+    }
+
+    fn accept_offer(offer: &str) -> Option<Self> {
+        let mut params = offer.split(';').map(str::trim);
+        if !params.next()?.eq_ignore_ascii_case(PERMESSAGE_DEFLATE) {
+            return None;
+        }
+
+        let mut negotiation = Self { client_no_context_takeover: false };
+        for param in params.filter(|param| !param.is_empty()) {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param, None),
+            };
+            match (name.to_ascii_lowercase().as_str(), value) {
+                ("server_no_context_takeover", None) => {}
+                ("client_no_context_takeover", None) => negotiation.client_no_context_takeover = true,
+                ("server_max_window_bits", Some("15")) => {}
+                // Inflating with the full window handles any client window
+                ("client_max_window_bits", _) => {}
+                _ => return None,
+            }
+        }
+        Some(negotiation)
+    }
+
+    /// Value of the `Sec-WebSocket-Extensions` response header
+    pub fn response_header(&self) -> String {
+        let mut header = format!("{}; server_no_context_takeover", PERMESSAGE_DEFLATE);
+        if self.client_no_context_takeover {
+            header.push_str("; client_no_context_takeover");
+        }
+        header
+    }
+}
+
+/// Compress a message payload, leaving out the tail the receiver restores
+pub fn deflate_message(payload: &[u8]) -> io::Result<Vec<u8>> {
+    let mut compress = Compress::new(Compression::default(), false);
+    let mut compressed = Vec::with_capacity(payload.len() / 2 + 64);
+    loop {
+        let consumed = compress.total_in() as usize;
+        compress.compress_vec(&payload[consumed..], &mut compressed, FlushCompress::Sync)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if compress.total_in() as usize == payload.len() && compressed.len() < compressed.capacity() {
+            break;
+        }
+        compressed.reserve(compressed.capacity().max(64));
+    }
+
+    if compressed.ends_with(&DEFLATE_TAIL) {
+        compressed.truncate(compressed.len() - DEFLATE_TAIL.len());
+    }
+    Ok(compressed)
+}
+
+/// Text message sent as a single compressed frame
+pub fn compressed_text_message(text: &str) -> io::Result<Message> {
+    let mut frame = Frame::message(deflate_message(text.as_bytes())?, OpCode::Data(Data::Text), true);
+    frame.header_mut().rsv1 = true;
+    Ok(Message::Frame(frame))
+}
+
+/// Header of a frame in the raw byte stream
+struct RawFrameHeader {
+    first_byte: u8,
+    mask: Option<[u8; 4]>,
+    payload_len: u64,
+    /// Length of the header itself
+    len: usize,
+}
+
+impl RawFrameHeader {
+    /// Parse a header from the start of `bytes`, or `None` if it is incomplete
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let (&first_byte, &second_byte) = (bytes.first()?, bytes.get(1)?);
+        let (payload_len, mut len) = match second_byte & 0x7f {
+            126 => (u16::from_be_bytes(bytes.get(2..4)?.try_into().ok()?) as u64, 4),
+            127 => (u64::from_be_bytes(bytes.get(2..10)?.try_into().ok()?), 10),
+            short => (short as u64, 2),
+        };
+        let mask = if second_byte & MASKED != 0 {
+            let mask = bytes.get(len..len + 4)?.try_into().ok()?;
+            len += 4;
+            Some(mask)
+        } else {
+            None
+        };
+        Some(Self { first_byte, mask, payload_len, len })
+    }
+
+    fn opcode(&self) -> u8 {
+        self.first_byte & 0x0f
+    }
+
+    fn is_final(&self) -> bool {
+        self.first_byte & FIN != 0
+    }
+
+    /// Write the header of the same frame without RSV1, carrying `payload_len` bytes
+    fn write_inflated(&self, payload_len: usize, out: &mut Vec<u8>) {
+        out.push(self.first_byte & !RSV1);
+        let mask_bit = if self.mask.is_some() { MASKED } else { 0 };
+        match payload_len {
+            len if len < 126 => out.push(mask_bit | len as u8),
+            len if len <= u16::MAX as usize => {
+                out.push(mask_bit | 126);
+                out.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                out.push(mask_bit | 127);
+                out.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        if let Some(mask) = self.mask {
+            out.extend_from_slice(&mask);
+        }
+    }
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+/// Byte stream of a server-side WebSocket that inflates compressed client messages
+///
+/// Frames pass through unchanged, except the data frames of compressed
+/// messages, which are replaced by frames carrying the inflated payload.
+/// Without a negotiation the stream is a plain pass-through.
+pub struct InflateStream<S> {
+    inner: S,
+    inflater: Option<Decompress>,
+    client_no_context_takeover: bool,
+    /// Bytes read from `inner` not yet rewritten
+    raw: Vec<u8>,
+    /// Rewritten bytes waiting to be read
+    ready: Vec<u8>,
+    ready_pos: usize,
+    /// Payload bytes of an uncompressed frame still to pass through
+    passthrough: u64,
+    /// Whether the data message being received is compressed
+    compressed_message: bool,
+    /// Inflated size of the compressed message being received
+    inflated_len: usize,
+}
+
+impl<S> InflateStream<S> {
+    /// Wrap the byte stream of an upgraded connection
+    pub fn new(inner: S, negotiation: Option<DeflateNegotiation>) -> Self {
+        Self {
+            inner,
+            inflater: negotiation.map(|_| Decompress::new(false)),
+            client_no_context_takeover: negotiation.is_some_and(|n| n.client_no_context_takeover),
+            raw: Vec::new(),
+            ready: Vec::new(),
+            ready_pos: 0,
+            passthrough: 0,
+            compressed_message: false,
+            inflated_len: 0,
+        }
+    }
+
+    /// Move the next frame, or part of one, from `raw` to `ready`
+    ///
+    /// Returns false when more bytes are needed first.
+    fn rewrite_next(&mut self) -> io::Result<bool> {
+        if self.passthrough > 0 {
+            if self.raw.is_empty() {
+                return Ok(false);
+            }
+            let len = self.passthrough.min(self.raw.len() as u64) as usize;
+            self.ready.extend(self.raw.drain(..len));
+            self.passthrough -= len as u64;
+            return Ok(true);
+        }
+
+        let Some(header) = RawFrameHeader::parse(&self.raw) else {
+            return Ok(false);
+        };
+        let is_data = header.opcode() & 0x08 == 0;
+        if is_data && header.opcode() != 0 {
+            self.compressed_message = header.first_byte & RSV1 != 0;
+        }
+        if !is_data || !self.compressed_message {
+            self.ready.extend(self.raw.drain(..header.len));
+            self.passthrough = header.payload_len;
+            return Ok(true);
+        }
+
+        // A compressed frame is inflated once all of it has arrived
+        if header.payload_len > MAX_INFLATED_MESSAGE_SIZE as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Compressed WebSocket frame is too large"));
+        }
+        let frame_len = header.len + header.payload_len as usize;
+        if self.raw.len() < frame_len {
+            return Ok(false);
+        }
+        let mut payload: Vec<u8> = self.raw.drain(..frame_len).skip(header.len).collect();
+        if let Some(mask) = header.mask {
+            apply_mask(&mut payload, mask);
+        }
+
+        let mut inflated = self.inflate(&payload, header.is_final())?;
+        if let Some(mask) = header.mask {
+            apply_mask(&mut inflated, mask);
+        }
+        header.write_inflated(inflated.len(), &mut self.ready);
+        self.ready.extend_from_slice(&inflated);
+        Ok(true)
+    }
+
+    /// Inflate one frame of the current message
+    fn inflate(&mut self, payload: &[u8], is_final: bool) -> io::Result<Vec<u8>> {
+        let inflater = self.inflater.as_mut().expect("compressed frames are only accepted once negotiated");
+        let mut inflated = Vec::with_capacity(payload.len() * 2 + 64);
+        inflate_into(inflater, payload, &mut inflated)?;
+        if is_final {
+            inflate_into(inflater, &DEFLATE_TAIL, &mut inflated)?;
+            if self.client_no_context_takeover {
+                inflater.reset(false);
+            }
+        }
+
+        self.inflated_len += inflated.len();
+        if self.inflated_len > MAX_INFLATED_MESSAGE_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Inflated WebSocket message is too large"));
+        }
+        if is_final {
+            self.compressed_message = false;
+            self.inflated_len = 0;
+        }
+        Ok(inflated)
+    }
+}
+
+/// Feed all of `input` to the inflater, appending what it produces
+fn inflate_into(inflater: &mut Decompress, mut input: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+    loop {
+        if out.capacity() - out.len() < 1024 {
+            out.reserve(out.capacity().max(1024));
+        }
+        let (total_in, total_out) = (inflater.total_in(), inflater.total_out());
+        inflater.decompress_vec(input, out, FlushDecompress::Sync)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let consumed = (inflater.total_in() - total_in) as usize;
+        input = &input[consumed..];
+
+        if input.is_empty() && out.len() < out.capacity() {
+            return Ok(());
+        }
+        if consumed == 0 && inflater.total_out() == total_out {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Malformed compressed WebSocket message"));
+        }
+        if out.len() > MAX_INFLATED_MESSAGE_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Inflated WebSocket message is too large"));
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for InflateStream<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.inflater.is_none() {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        }
+
+        loop {
+            if this.ready_pos < this.ready.len() {
+                let len = buf.remaining().min(this.ready.len() - this.ready_pos);
+                buf.put_slice(&this.ready[this.ready_pos..this.ready_pos + len]);
+                this.ready_pos += len;
+                if this.ready_pos == this.ready.len() {
+                    this.ready.clear();
+                    this.ready_pos = 0;
+                }
+                return Poll::Ready(Ok(()));
+            }
+            if this.rewrite_next()? {
+                continue;
+            }
+
+            let mut chunk = [0u8; 8192];
+            let mut chunk_buf = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf))?;
+            if chunk_buf.filled().is_empty() {
+                // A truncated frame is passed on for the protocol layer to reject
+                if this.raw.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                this.ready = std::mem::take(&mut this.raw);
+                continue;
+            }
+            this.raw.extend_from_slice(chunk_buf.filled());
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for InflateStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(value: &str) -> Option<DeflateNegotiation> {
+        let mut headers = HeaderMap::new();
+        headers.insert("sec-websocket-extensions", value.parse().unwrap());
+        DeflateNegotiation::from_offers(&headers)
+    }
+
+    #[test]
+    fn test_browser_offers_are_accepted() {
+        // Chrome and Firefox
+        let negotiation = offer("permessage-deflate; client_max_window_bits").unwrap();
+        assert_eq!(negotiation.response_header(), "permessage-deflate; server_no_context_takeover");
+
+        let negotiation = offer("permessage-deflate; client_no_context_takeover").unwrap();
+        assert!(negotiation.response_header().ends_with("; client_no_context_takeover"));
+
+        // A limited server window falls back to the next offer
+        assert!(offer("permessage-deflate; server_max_window_bits=10").is_none());
+        assert!(offer("permessage-deflate; server_max_window_bits=10, permessage-deflate").is_some());
+        assert!(offer("x-webkit-deflate-frame").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_compressed_client_frames_are_inflated() {
+        use tokio::io::AsyncReadExt;
+
+        let text = "inflate me ".repeat(20);
+        let compressed = deflate_message(text.as_bytes()).unwrap();
+        let (first, second) = compressed.split_at(compressed.len() / 2);
+
+        // A masked message split over two frames, with a ping between them
+        let mask = [1, 2, 3, 4];
+        let mut wire = Vec::new();
+        for (first_byte, part) in [(RSV1 | 0x01, first), (FIN, second)] {
+            let mut payload = part.to_vec();
+            apply_mask(&mut payload, mask);
+            wire.extend_from_slice(&[first_byte, MASKED | 126]);
+            wire.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+            wire.extend_from_slice(&mask);
+            wire.extend_from_slice(&payload);
+            if first_byte & FIN == 0 {
+                wire.extend_from_slice(&[FIN | 0x09, 0]);
+            }
+        }
+
+        let mut stream = InflateStream::new(&wire[..], Some(DeflateNegotiation { client_no_context_takeover: false }));
+        let mut rewritten = Vec::new();
+        stream.read_to_end(&mut rewritten).await.unwrap();
+
+        let mut inflated = Vec::new();
+        let mut rest = &rewritten[..];
+        while let Some(header) = RawFrameHeader::parse(rest) {
+            assert_eq!(header.first_byte & RSV1, 0);
+            let mut payload = rest[header.len..header.len + header.payload_len as usize].to_vec();
+            if header.opcode() != 0x09 {
+                apply_mask(&mut payload, header.mask.unwrap());
+                inflated.extend_from_slice(&payload);
+            }
+            rest = &rest[header.len + header.payload_len as usize..];
+        }
+        assert!(rest.is_empty());
+        assert_eq!(String::from_utf8(inflated).unwrap(), text);
+    }
+}
@@ -33,6 +33,7 @@ impl IntelligentContextAnalyzer {
         feature_extractors.push(Box::new(SessionBasedExtractor) as Box<dyn ContextFeatureExtractor>);
         feature_extractors.push(Box::new(PerformanceBasedExtractor) as Box<dyn ContextFeatureExtractor>);
         feature_extractors.push(Box::new(PatternBasedExtractor) as Box<dyn ContextFeatureExtractor>);
+        feature_extractors.push(Box::new(IntentExtractor::new()) as Box<dyn ContextFeatureExtractor>);
 
         Self { feature_extractors }
     }
@@ -387,6 +388,168 @@ impl ContextFeatureExtractor for PatternBasedExtractor {
     }
 }
 
+/// Classifies the intent of a request text
+#[async_trait]
+pub trait IntentClassifier: Send + Sync {
+    /// Score each intent for a text; scores sum to 1, or the map is empty when no intent is recognized
+    async fn classify(&self, text: &str) -> IntelligenceResult<HashMap<String, f64>>;
+
+    /// Get classifier name
+    fn name(&self) -> &str;
+}
+
+/// Keyword-scoring intent classifier
+///
+/// Each intent's share is the fraction of matched cues that belong to it.
+/// Cues made of letters, digits and spaces match whole words; other cues,
+/// such as code punctuation, match anywhere in the text.
+pub struct KeywordIntentClassifier {
+    intents: Vec<(String, Vec<String>)>,
+}
+
+impl KeywordIntentClassifier {
+    /// Create a classifier for the code, creative, factual and chitchat intents
+    pub fn new() -> Self {
+        let intents: [(&str, &[&str]); 4] = [
+            ("code", &[
+                "```", "=>", "->", "::", "();", "};", "code", "function", "fn", "def", "class", "compile",
+                "compiler", "bug", "debug", "stack trace", "exception", "refactor", "rust", "python",
+                "javascript", "typescript", "sql", "regex", "api", "variable", "borrow checker",
+            ]),
+            ("creative", &[
+                "story", "poem", "haiku", "lyrics", "song", "imagine", "creative", "fiction", "character",
+                "plot", "brainstorm", "slogan", "write a",
+            ]),
+            ("factual", &[
+                "what is", "what are", "who was", "who is", "when did", "where is", "how many", "how much",
+                "explain", "define", "definition", "history", "fact", "capital of", "why does", "difference between",
+            ]),
+            ("chitchat", &[
+                "hello", "hi", "hey", "thanks", "thank you", "how are you", "good morning", "good night",
+                "lol", "bye", "nice to meet you",
+            ]),
+        ];
+
+        Self {
+            intents: intents.iter()
+                .map(|(intent, cues)| (intent.to_string(), cues.iter().map(|cue| cue.to_string()).collect()))
+                .collect(),
+        }
+    }
+
+    /// Add cues for an intent, creating it if needed
+    pub fn with_cues(mut self, intent: &str, cues: &[&str]) -> Self {
+        let cues = cues.iter().map(|cue| cue.to_lowercase());
+        match self.intents.iter_mut().find(|(name, _)| name == intent) {
+            Some((_, existing)) => existing.extend(cues),
+            None => self.intents.push((intent.to_string(), cues.collect())),
+        }
+        self
+    }
+}
+
+impl Default for KeywordIntentClassifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl IntentClassifier for KeywordIntentClassifier {
+    async fn classify(&self, text: &str) -> IntelligenceResult<HashMap<String, f64>> {
+        let lower = text.to_lowercase();
+        let words: Vec<&str> = lower.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).collect();
+        let padded_words = format!(" {} ", words.join(" "));
+
+        let hits: Vec<(&str, usize)> = self.intents.iter()
+            .map(|(intent, cues)| {
+                let matched = cues.iter()
+                    .filter(|cue| {
+                        if cue.chars().all(|c| c.is_alphanumeric() || c == ' ') {
+                            padded_words.contains(&format!(" {} ", cue))
+                        } else {
+                            lower.contains(cue.as_str())
+                        }
+                    })
+                    .count();
+                (intent.as_str(), matched)
+            })
+            .collect();
+
+        let total: usize = hits.iter().map(|(_, matched)| matched).sum();
+        if total == 0 {
+            return Ok(HashMap::new());
+        }
+
+        Ok(hits.into_iter()
+            .map(|(intent, matched)| (intent.to_string(), matched as f64 / total as f64))
+            .collect())
+    }
+
+    fn name(&self) -> &str {
+        "keyword_intent_classifier"
+    }
+}
+
+/// User-intent feature extractor
+///
+/// Classifies the request text of each interaction and reports the average
+/// intent distribution as `intent_<name>` features. Interactions without
+/// text, or whose intent is not recognized, are left out of the average.
+pub struct IntentExtractor {
+    classifier: Box<dyn IntentClassifier>,
+}
+
+impl IntentExtractor {
+    /// Create an extractor using keyword scoring
+    pub fn new() -> Self {
+        Self::with_classifier(Box::new(KeywordIntentClassifier::new()))
+    }
+
+    /// Create an extractor using a custom classifier, e.g. an LLM-backed one
+    pub fn with_classifier(classifier: Box<dyn IntentClassifier>) -> Self {
+        Self { classifier }
+    }
+}
+
+impl Default for IntentExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ContextFeatureExtractor for IntentExtractor {
+    async fn extract_features(&self, interactions: &[UserInteraction]) -> IntelligenceResult<HashMap<String, f64>> {
+        let mut totals: HashMap<String, f64> = HashMap::new();
+        let mut classified = 0;
+
+        for text in interactions.iter().filter_map(|i| i.request_text.as_deref()) {
+            let distribution = self.classifier.classify(text).await?;
+            if distribution.is_empty() {
+                continue;
+            }
+            classified += 1;
+            for (intent, share) in distribution {
+                *totals.entry(intent).or_insert(0.0) += share;
+            }
+        }
+
+        if classified == 0 {
+            return Ok(HashMap::new());
+        }
+
+        debug!("Classified intent of {} interactions with {}", classified, self.classifier.name());
+        Ok(totals.into_iter()
+            .map(|(intent, total)| (format!("intent_{}", intent), total / classified as f64))
+            .collect())
+    }
+
+    fn name(&self) -> &str {
+        "intent_extractor"
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -403,6 +566,7 @@ mod tests {
                 response_time: 1500,
                 user_feedback: Some(0.7),
                 context_features: HashMap::new(),
+                request_text: None,
             },
             UserInteraction {
                 user_id: "test_user".to_string(),
@@ -414,6 +578,7 @@ mod tests {
                 response_time: 3000,
                 user_feedback: Some(0.9),
                 context_features: HashMap::new(),
+                request_text: None,
             },
         ]
     }
@@ -476,4 +641,28 @@ mod tests {
         assert!(features.contains_key("avg_response_time"));
         assert!(features.contains_key("quality_consistency"));
     }
+
+    #[tokio::test]
+    async fn test_intent_extractor_scores_code_prompts() {
+        let extractor = IntentExtractor::new();
+        let mut interactions = create_test_interactions();
+        interactions[0].request_text = Some(
+            "My Rust function fails to compile with a borrow checker error:\n```\nfn main() { let v = vec![1]; take(v); println!(\"{:?}\", v); }\n```".to_string(),
+        );
+        interactions[1].request_text = Some("Can you refactor this Python class? def run(self): return self.api.call();".to_string());
+
+        let features = extractor.extract_features(&interactions).await.unwrap();
+        assert!(features["intent_code"] > 0.8, "intent_code = {}", features["intent_code"]);
+        assert!(features["intent_code"] > features["intent_creative"]);
+        let total: f64 = features.values().sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        interactions[0].request_text = Some("hey, thanks! how are you today?".to_string());
+        interactions[1].request_text = None;
+        let features = extractor.extract_features(&interactions).await.unwrap();
+        assert_eq!(features["intent_chitchat"], 1.0);
+
+        // Interactions without text contribute nothing
+        assert!(extractor.extract_features(&create_test_interactions()).await.unwrap().is_empty());
+    }
 }
@@ -2,6 +2,7 @@
 
 use crate::{IntelligenceResult, IntelligenceError, DecisionContext, DecisionResult, ContextFeatures, DecisionConfig, LearningEngine};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use rand::{thread_rng, Rng};
 use tracing::{info, debug, warn};
@@ -21,6 +22,7 @@ pub struct DecisionEngine {
     config: DecisionConfig,
     learning_engine: LearningEngine,
     strategies: HashMap<String, Box<dyn DecisionStrategy>>,
+    rules: Vec<DecisionRule>,
 }
 
 impl DecisionEngine {
@@ -46,9 +48,26 @@ impl DecisionEngine {
             config,
             learning_engine,
             strategies,
+            rules: Vec::new(),
         }
     }
 
+    /// Add an operator rule; rules are evaluated in the order they were added
+    pub fn add_rule(&mut self, rule: DecisionRule) {
+        self.rules.push(rule);
+    }
+
+    /// Add an operator rule
+    pub fn with_rule(mut self, rule: DecisionRule) -> Self {
+        self.add_rule(rule);
+        self
+    }
+
+    /// Get the configured operator rules
+    pub fn rules(&self) -> &[DecisionRule] {
+        &self.rules
+    }
+
     /// Make an intelligent decision
     pub async fn make_decision(&self, context: DecisionContext, options: Vec<String>) -> IntelligenceResult<DecisionResult> {
         if options.is_empty() {
@@ -60,8 +79,72 @@ impl DecisionEngine {
         let strategy = self.strategies.get(&strategy_name)
             .ok_or_else(|| IntelligenceError::Decision(format!("Strategy '{}' not found", strategy_name)))?;
 
-        debug!("Using strategy '{}' for decision", strategy_name);
-        strategy.decide(&context, &options).await
+        let result = match self.apply_pre_rules(&context, &options) {
+            Some(forced) => forced,
+            None => {
+                debug!("Using strategy '{}' for decision", strategy_name);
+                strategy.decide(&context, &options).await?
+            }
+        };
+
+        self.apply_post_rules(&context, &options, result)
+    }
+
+    /// Return a forced decision from the first matching pre-rule, if any
+    fn apply_pre_rules(&self, context: &DecisionContext, options: &[String]) -> Option<DecisionResult> {
+        self.rules.iter()
+            .filter(|rule| rule.condition.matches(context))
+            .find_map(|rule| match &rule.action {
+                RuleAction::Force { option } if options.contains(option) => {
+                    info!("Rule '{}' forced decision '{}'", rule.name, option);
+                    Some(DecisionResult {
+                        decision: option.clone(),
+                        confidence: 1.0,
+                        reasoning: vec![format!("Forced by rule '{}'", rule.name)],
+                        alternatives: vec![],
+                        learning_insights: vec![],
+                    })
+                }
+                RuleAction::Force { option } => {
+                    warn!("Rule '{}' forces unavailable option '{}'; ignoring", rule.name, option);
+                    None
+                }
+                RuleAction::Veto { .. } => None,
+            })
+    }
+
+    /// Reroute a decision vetoed by any matching post-rule
+    fn apply_post_rules(&self, context: &DecisionContext, options: &[String], mut result: DecisionResult) -> IntelligenceResult<DecisionResult> {
+        let vetoes: Vec<(&DecisionRule, &[String], Option<&String>)> = self.rules.iter()
+            .filter(|rule| rule.condition.matches(context))
+            .filter_map(|rule| match &rule.action {
+                RuleAction::Veto { options, reroute } => Some((rule, options.as_slice(), reroute.as_ref())),
+                RuleAction::Force { .. } => None,
+            })
+            .collect();
+        let allowed = |option: &String| vetoes.iter().all(|(_, vetoed, _)| !vetoed.contains(option));
+
+        let Some((rule, _, reroute)) = vetoes.iter().find(|(_, vetoed, _)| vetoed.contains(&result.decision)) else {
+            return Ok(result);
+        };
+
+        // Prefer the rule's reroute target, then the learned alternatives, then any remaining option
+        let replacement = reroute.iter()
+            .copied()
+            .filter(|option| options.contains(option))
+            .chain(result.alternatives.iter().map(|(option, _)| option))
+            .chain(options.iter())
+            .find(|option| allowed(option))
+            .cloned()
+            .ok_or_else(|| IntelligenceError::Decision(format!(
+                "Rule '{}' vetoed '{}' and no permitted option remains", rule.name, result.decision
+            )))?;
+
+        info!("Rule '{}' vetoed '{}'; rerouting to '{}'", rule.name, result.decision, replacement);
+        result.reasoning.push(format!("Rule '{}' vetoed '{}'", rule.name, result.decision));
+        result.alternatives.retain(|(option, _)| allowed(option) && *option != replacement);
+        result.decision = replacement;
+        Ok(result)
     }
 
     /// Get decision recommendations
@@ -98,6 +181,7 @@ impl DecisionEngine {
             response_time: 1000, // Placeholder
             user_feedback: Some(outcome_quality),
             context_features: context_features.custom_features,
+            request_text: context.request_text,
         };
 
         self.learning_engine.process_interaction(interaction).await
@@ -154,6 +238,88 @@ impl DecisionEngine {
     }
 }
 
+/// Declarative condition over a decision context
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleCondition {
+    /// Always matches
+    Always,
+    /// Request type equals the given value
+    RequestType { equals: String },
+    /// User ID equals the given value
+    User { equals: String },
+    /// Context feature is at least `threshold`; missing features never match
+    FeatureAtLeast { feature: String, threshold: f64 },
+    /// Context feature is below `threshold`; missing features never match
+    FeatureBelow { feature: String, threshold: f64 },
+    /// System metric is at least `threshold`; missing metrics never match
+    MetricAtLeast { metric: String, threshold: f64 },
+    /// System metric is below `threshold`; missing metrics never match
+    MetricBelow { metric: String, threshold: f64 },
+    /// Every condition matches
+    All { conditions: Vec<RuleCondition> },
+    /// At least one condition matches
+    Any { conditions: Vec<RuleCondition> },
+    /// The condition does not match
+    Not { condition: Box<RuleCondition> },
+}
+
+impl RuleCondition {
+    /// Evaluate the condition against a context
+    pub fn matches(&self, context: &DecisionContext) -> bool {
+        match self {
+            RuleCondition::Always => true,
+            RuleCondition::RequestType { equals } => context.request_type == *equals,
+            RuleCondition::User { equals } => context.user_id == *equals,
+            RuleCondition::FeatureAtLeast { feature, threshold } => context.context_features.get(feature).is_some_and(|v| v >= threshold),
+            RuleCondition::FeatureBelow { feature, threshold } => context.context_features.get(feature).is_some_and(|v| v < threshold),
+            RuleCondition::MetricAtLeast { metric, threshold } => context.system_metrics.get(metric).is_some_and(|v| v >= threshold),
+            RuleCondition::MetricBelow { metric, threshold } => context.system_metrics.get(metric).is_some_and(|v| v < threshold),
+            RuleCondition::All { conditions } => conditions.iter().all(|c| c.matches(context)),
+            RuleCondition::Any { conditions } => conditions.iter().any(|c| c.matches(context)),
+            RuleCondition::Not { condition } => !condition.matches(context),
+        }
+    }
+}
+
+/// What a matching rule does to the decision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Pre-rule: choose `option` without consulting the learned strategies
+    Force { option: String },
+    /// Post-rule: reject any of `options`, rerouting to `reroute` or the best permitted alternative
+    Veto { options: Vec<String>, reroute: Option<String> },
+}
+
+/// Operator rule layered over the learned decision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionRule {
+    pub name: String,
+    pub condition: RuleCondition,
+    pub action: RuleAction,
+}
+
+impl DecisionRule {
+    /// Rule forcing `option` whenever `condition` matches
+    pub fn force(name: impl Into<String>, condition: RuleCondition, option: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            condition,
+            action: RuleAction::Force { option: option.into() },
+        }
+    }
+
+    /// Rule vetoing `options` whenever `condition` matches
+    pub fn veto(name: impl Into<String>, condition: RuleCondition, options: Vec<String>, reroute: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            condition,
+            action: RuleAction::Veto { options, reroute },
+        }
+    }
+}
+
 /// Weighted Random Strategy - balances exploration and exploitation
 pub struct WeightedRandomStrategy {
     exploration_rate: f64,
@@ -378,6 +544,7 @@ mod tests {
             user_history: vec![],
             system_metrics: HashMap::new(),
             context_features: HashMap::new(),
+            request_text: None,
         }
     }
 
@@ -414,4 +581,88 @@ mod tests {
         assert!(options.contains(&result.decision));
         assert!(result.reasoning.len() > 0);
     }
+
+    #[tokio::test]
+    async fn test_pre_rule_forces_provider() {
+        let engine = DecisionEngine::new(DecisionConfig::default(), LearningEngine::default())
+            .with_rule(DecisionRule::force("code_to_claude", RuleCondition::RequestType { equals: "code".to_string() }, "claude"));
+        let options = vec!["gpt-4".to_string(), "claude".to_string(), "local".to_string()];
+
+        let mut context = create_test_context();
+        context.request_type = "code".to_string();
+        for _ in 0..5 {
+            let result = engine.make_decision(context.clone(), options.clone()).await.unwrap();
+            assert_eq!(result.decision, "claude");
+            assert!(result.reasoning[0].contains("code_to_claude"));
+        }
+
+        // Non-matching contexts fall through to the learned strategies
+        let result = engine.make_decision(create_test_context(), options.clone()).await.unwrap();
+        assert!(!result.reasoning.iter().any(|r| r.contains("code_to_claude")));
+    }
+
+    #[tokio::test]
+    async fn test_post_rule_vetoes_learned_choice() {
+        let pii = RuleCondition::FeatureAtLeast { feature: "contains_pii".to_string(), threshold: 0.5 };
+        let engine = DecisionEngine::new(DecisionConfig::default(), LearningEngine::default())
+            .with_rule(DecisionRule::veto("no_pii_to_external", pii, vec!["gpt-3.5-turbo".to_string()], Some("on_prem".to_string())));
+
+        // Under high load the context-aware strategy prefers the lightweight external model
+        let mut context = create_test_context();
+        context.system_metrics.insert("cpu_usage".to_string(), 0.9);
+        let options = vec!["gpt-3.5-turbo".to_string(), "on_prem".to_string()];
+        let learned = ContextAwareStrategy.decide(&context, &options).await.unwrap();
+        assert_eq!(learned.decision, "gpt-3.5-turbo");
+
+        context.context_features.insert("contains_pii".to_string(), 1.0);
+        let result = engine.make_decision(context.clone(), options).await.unwrap();
+        assert_eq!(result.decision, "on_prem");
+        assert!(result.reasoning.iter().any(|r| r.contains("no_pii_to_external")));
+        assert!(result.alternatives.is_empty());
+
+        // Without the reroute target available, nothing permitted remains
+        let err = engine.make_decision(context.clone(), vec!["gpt-3.5-turbo".to_string()]).await.unwrap_err();
+        assert!(matches!(err, IntelligenceError::Decision(_)));
+
+        context.context_features.insert("contains_pii".to_string(), 0.0);
+        let result = engine.make_decision(context, vec!["gpt-3.5-turbo".to_string()]).await.unwrap();
+        assert_eq!(result.decision, "gpt-3.5-turbo");
+    }
+
+    #[tokio::test]
+    async fn test_outcomes_are_learned_with_the_request_text() {
+        use crate::{LearningAlgorithm, LearningPattern, UserInteraction};
+        use std::sync::{Arc, Mutex};
+
+        /// Keeps the request text of every interaction it learns from
+        struct TextRecorder(Arc<Mutex<Vec<Option<String>>>>);
+
+        #[async_trait]
+        impl LearningAlgorithm for TextRecorder {
+            async fn learn(&self, interaction: &UserInteraction) -> IntelligenceResult<Vec<LearningPattern>> {
+                self.0.lock().unwrap().push(interaction.request_text.clone());
+                Ok(Vec::new())
+            }
+
+            async fn predict(&self, _context: &ContextFeatures) -> IntelligenceResult<HashMap<String, f64>> {
+                Ok(HashMap::new())
+            }
+
+            fn name(&self) -> &str {
+                "text_recorder"
+            }
+        }
+
+        let texts = Arc::new(Mutex::new(Vec::new()));
+        let learning_engine = LearningEngine::default();
+        learning_engine.add_algorithm(Box::new(TextRecorder(texts.clone()))).await.unwrap();
+        let engine = DecisionEngine::new(DecisionConfig::default(), learning_engine);
+
+        let context = DecisionContext {
+            request_text: Some("Refactor this function".to_string()),
+            ..create_test_context()
+        };
+        engine.learn_from_outcome(context, "code-model", 0.9).await.unwrap();
+        assert_eq!(*texts.lock().unwrap(), vec![Some("Refactor this function".to_string())]);
+    }
 }
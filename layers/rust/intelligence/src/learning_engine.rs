@@ -2,6 +2,8 @@
 
 use crate::{IntelligenceResult, IntelligenceError, UserInteraction, LearningPattern, PatternType, ContextFeatures, LearningConfig};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sira_utils::CryptoUtils;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -262,6 +264,206 @@ impl LearningAlgorithm for CollaborativeFiltering {
     }
 }
 
+/// Experiment variant with its share of traffic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: String,
+    pub weight: u32,
+}
+
+impl Variant {
+    /// Create a new variant
+    pub fn new(name: impl Into<String>, weight: u32) -> Self {
+        Self { name: name.into(), weight }
+    }
+}
+
+/// Running outcome statistics for one variant
+#[derive(Debug, Clone, Default)]
+struct VariantMetrics {
+    samples: u64,
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl VariantMetrics {
+    fn record(&mut self, value: f64) {
+        self.samples += 1;
+        self.sum += value;
+        self.sum_squares += value * value;
+    }
+
+    fn mean(&self) -> f64 {
+        if self.samples == 0 { 0.0 } else { self.sum / self.samples as f64 }
+    }
+
+    /// Sample variance (Bessel-corrected)
+    fn variance(&self) -> f64 {
+        if self.samples < 2 {
+            return 0.0;
+        }
+        let n = self.samples as f64;
+        ((self.sum_squares - self.sum * self.sum / n) / (n - 1.0)).max(0.0)
+    }
+}
+
+/// Per-variant section of an experiment report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantReport {
+    pub name: String,
+    pub samples: u64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub lift_vs_control: Option<f64>, // Relative change of the mean against the control
+    pub p_value: Option<f64>,         // Two-sided Welch test against the control
+    pub significant: bool,
+}
+
+/// Snapshot of an experiment's results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentReport {
+    pub experiment_id: String,
+    pub control: String,
+    pub significance_level: f64,
+    pub variants: Vec<VariantReport>,
+}
+
+/// A/B experiment comparing strategies on live traffic; the first variant is the control
+pub struct Experiment {
+    id: String,
+    variants: Vec<Variant>,
+    significance_level: f64,
+    metrics: Arc<RwLock<HashMap<String, VariantMetrics>>>,
+}
+
+impl Experiment {
+    /// Create a new experiment over at least two weighted variants
+    pub fn new(id: impl Into<String>, variants: Vec<Variant>) -> IntelligenceResult<Self> {
+        let id = id.into();
+
+        if variants.len() < 2 {
+            return Err(IntelligenceError::Config(format!("Experiment '{}' needs at least two variants", id)));
+        }
+        if variants.iter().all(|v| v.weight == 0) {
+            return Err(IntelligenceError::Config(format!("Experiment '{}' has no variant with a positive weight", id)));
+        }
+        for (i, variant) in variants.iter().enumerate() {
+            if variants[..i].iter().any(|other| other.name == variant.name) {
+                return Err(IntelligenceError::Config(format!("Duplicate variant '{}' in experiment '{}'", variant.name, id)));
+            }
+        }
+
+        Ok(Self {
+            id,
+            variants,
+            significance_level: 0.05,
+            metrics: Arc::new(RwLock::new(HashMap::new())),
+        })
+    }
+
+    /// Set the p-value below which a difference is reported as significant
+    pub fn with_significance_level(mut self, significance_level: f64) -> Self {
+        self.significance_level = significance_level;
+        self
+    }
+
+    /// Experiment identifier
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Deterministically assign a user or session key to a variant
+    pub fn assign(&self, key: &str) -> Variant {
+        // Salt with the experiment id so concurrent experiments split traffic independently
+        let digest = CryptoUtils::sha256(format!("{}:{}", self.id, key).as_bytes());
+        let hash = u64::from_str_radix(&digest[..16], 16).unwrap_or(0);
+
+        let total_weight: u64 = self.variants.iter().map(|v| v.weight as u64).sum();
+        let mut bucket = hash % total_weight;
+
+        for variant in &self.variants {
+            if bucket < variant.weight as u64 {
+                return variant.clone();
+            }
+            bucket -= variant.weight as u64;
+        }
+
+        unreachable!("bucket is always below the total weight")
+    }
+
+    /// Record an outcome for a key, bucketed into its assigned variant
+    pub async fn record_outcome(&self, key: &str, value: f64) -> Variant {
+        let variant = self.assign(key);
+        self.metrics.write().await
+            .entry(variant.name.clone())
+            .or_default()
+            .record(value);
+        debug!("Recorded outcome {} for variant {} of experiment {}", value, variant.name, self.id);
+        variant
+    }
+
+    /// Summarize per-variant outcomes and their significance against the control
+    pub async fn report(&self) -> ExperimentReport {
+        let metrics = self.metrics.read().await;
+        let control = metrics.get(&self.variants[0].name).cloned().unwrap_or_default();
+
+        let variants = self.variants.iter().enumerate().map(|(i, variant)| {
+            let stats = metrics.get(&variant.name).cloned().unwrap_or_default();
+
+            let (lift_vs_control, p_value) = if i == 0 {
+                (None, None)
+            } else {
+                let lift = (control.samples > 0 && stats.samples > 0 && control.mean() != 0.0)
+                    .then(|| (stats.mean() - control.mean()) / control.mean().abs());
+                (lift, Self::welch_p_value(&control, &stats))
+            };
+
+            VariantReport {
+                name: variant.name.clone(),
+                samples: stats.samples,
+                mean: stats.mean(),
+                std_dev: stats.variance().sqrt(),
+                lift_vs_control,
+                p_value,
+                significant: p_value.is_some_and(|p| p < self.significance_level),
+            }
+        }).collect();
+
+        ExperimentReport {
+            experiment_id: self.id.clone(),
+            control: self.variants[0].name.clone(),
+            significance_level: self.significance_level,
+            variants,
+        }
+    }
+
+    /// Two-sided p-value of Welch's test, using the normal approximation
+    fn welch_p_value(a: &VariantMetrics, b: &VariantMetrics) -> Option<f64> {
+        if a.samples < 2 || b.samples < 2 {
+            return None;
+        }
+
+        let standard_error = (a.variance() / a.samples as f64 + b.variance() / b.samples as f64).sqrt();
+        let difference = b.mean() - a.mean();
+
+        if standard_error == 0.0 {
+            return Some(if difference == 0.0 { 1.0 } else { 0.0 });
+        }
+
+        let z = (difference / standard_error).abs();
+        Some((2.0 * (1.0 - standard_normal_cdf(z))).clamp(0.0, 1.0))
+    }
+}
+
+/// Standard normal CDF via the Abramowitz-Stegun erf approximation
+fn standard_normal_cdf(z: f64) -> f64 {
+    let x = z / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    0.5 * (1.0 + erf.copysign(x))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -303,6 +505,7 @@ mod tests {
             response_time: 1500,
             user_feedback: Some(0.8),
             context_features: HashMap::new(),
+            request_text: None,
         };
 
         engine.process_interaction(interaction).await.unwrap();
@@ -330,4 +533,79 @@ mod tests {
         let predictions = engine.get_predictions("test_user", &context).await.unwrap();
         assert!(!predictions.is_empty());
     }
+
+    fn create_test_experiment() -> Experiment {
+        Experiment::new("routing_v2", vec![
+            Variant::new("control", 50),
+            Variant::new("treatment", 50),
+        ]).unwrap()
+    }
+
+    #[test]
+    fn test_experiment_stable_assignment() {
+        let experiment = create_test_experiment();
+        let replica = create_test_experiment();
+
+        let mut treatment_count = 0;
+        for i in 0..1000 {
+            let key = format!("user_{}", i);
+            let variant = experiment.assign(&key);
+            assert_eq!(variant, experiment.assign(&key));
+            assert_eq!(variant, replica.assign(&key));
+            if variant.name == "treatment" {
+                treatment_count += 1;
+            }
+        }
+
+        // An even split lands near half of the keys
+        assert!((400..600).contains(&treatment_count));
+
+        // A zero-weight variant never receives traffic
+        let holdout = Experiment::new("holdout", vec![
+            Variant::new("control", 1),
+            Variant::new("disabled", 0),
+        ]).unwrap();
+        assert!((0..100).all(|i| holdout.assign(&format!("user_{}", i)).name == "control"));
+
+        assert!(Experiment::new("single", vec![Variant::new("only", 1)]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_experiment_metric_bucketing_and_significance() {
+        let experiment = create_test_experiment();
+        let mut expected: HashMap<String, (u64, f64)> = HashMap::new();
+
+        for i in 0..400 {
+            let key = format!("session_{}", i);
+            // Treatment scores higher, with a little spread in both arms
+            let value = if experiment.assign(&key).name == "treatment" { 0.8 } else { 0.6 } + (i % 5) as f64 * 0.01;
+            let variant = experiment.record_outcome(&key, value).await;
+
+            let entry = expected.entry(variant.name).or_insert((0, 0.0));
+            entry.0 += 1;
+            entry.1 += value;
+        }
+
+        let report = experiment.report().await;
+        assert_eq!(report.control, "control");
+        for variant in &report.variants {
+            let (samples, sum) = expected[&variant.name];
+            assert_eq!(variant.samples, samples);
+            assert!((variant.mean - sum / samples as f64).abs() < 1e-9);
+        }
+
+        let control = &report.variants[0];
+        let treatment = &report.variants[1];
+        assert!(control.p_value.is_none());
+        assert!(treatment.significant);
+        assert!(treatment.p_value.unwrap() < 0.001);
+        assert!(treatment.lift_vs_control.unwrap() > 0.3);
+
+        // Identical arms are not significant
+        let aa = Experiment::new("aa_test", vec![Variant::new("a", 1), Variant::new("b", 1)]).unwrap();
+        for i in 0..400 {
+            aa.record_outcome(&format!("session_{}", i), (i % 5) as f64).await;
+        }
+        assert!(!aa.report().await.variants[1].significant);
+    }
 }
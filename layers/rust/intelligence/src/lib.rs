@@ -7,6 +7,7 @@ pub mod types;
 pub mod learning_engine;
 pub mod decision_engine;
 pub mod context_analyzer;
+pub mod recommender;
 
 /// Result type alias for intelligence operations
 pub type IntelligenceResult<T> = Result<T, IntelligenceError>;
@@ -17,3 +18,4 @@ pub use types::*;
 pub use learning_engine::*;
 pub use decision_engine::*;
 pub use context_analyzer::*;
+pub use recommender::*;
@@ -0,0 +1,216 @@
+//! Recommender for Sira Intelligence
+//!
+//! Ranks candidate models and tools for a user context by combining three
+//! signals: what the learning engine has learned about the user, how well
+//! each candidate served the user before, and how well it fits the intent
+//! the context analyzer reads from the user's requests.
+
+use crate::{IntelligenceResult, DecisionContext, LearningEngine, ContextAnalyzer, IntelligentContextAnalyzer, PatternType};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Weight of the learned preference in a candidate's score
+const LEARNED_WEIGHT: f64 = 0.4;
+/// Weight of the candidate's past response quality for the user
+const HISTORY_WEIGHT: f64 = 0.35;
+/// Weight of the candidate's fit with the user's intent
+const INTENT_WEIGHT: f64 = 0.25;
+
+/// What a recommendation suggests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecommendationKind {
+    Model,
+    Tool,
+}
+
+/// Model or tool the recommender may suggest
+#[derive(Debug, Clone)]
+pub struct RecommendationCandidate {
+    pub name: String,
+    pub kind: RecommendationKind,
+    /// Intents the candidate serves well, as named by the intent extractor
+    pub intents: Vec<String>,
+}
+
+impl RecommendationCandidate {
+    /// Candidate model
+    pub fn model(name: impl Into<String>) -> Self {
+        Self { name: name.into(), kind: RecommendationKind::Model, intents: Vec::new() }
+    }
+
+    /// Candidate tool
+    pub fn tool(name: impl Into<String>) -> Self {
+        Self { name: name.into(), kind: RecommendationKind::Tool, intents: Vec::new() }
+    }
+
+    /// Mark the candidate as suited to an intent
+    pub fn with_intent(mut self, intent: impl Into<String>) -> Self {
+        self.intents.push(intent.into());
+        self
+    }
+}
+
+/// Ranked suggestion with the reasons behind its score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recommendation {
+    pub name: String,
+    pub kind: RecommendationKind,
+    /// Score from 0.0 to 1.0
+    pub score: f64,
+    pub rationale: String,
+}
+
+/// Suggests the next best model or tool for a user context
+pub struct Recommender {
+    learning_engine: Arc<LearningEngine>,
+    context_analyzer: Box<dyn ContextAnalyzer>,
+    candidates: Vec<RecommendationCandidate>,
+}
+
+impl Recommender {
+    /// Create a recommender without candidates, analyzing context with the default analyzer
+    pub fn new(learning_engine: Arc<LearningEngine>) -> Self {
+        Self {
+            learning_engine,
+            context_analyzer: Box::new(IntelligentContextAnalyzer::new()),
+            candidates: Vec::new(),
+        }
+    }
+
+    /// Analyze context with a custom analyzer
+    pub fn with_context_analyzer(mut self, context_analyzer: Box<dyn ContextAnalyzer>) -> Self {
+        self.context_analyzer = context_analyzer;
+        self
+    }
+
+    /// Add a candidate to rank
+    pub fn with_candidate(mut self, candidate: RecommendationCandidate) -> Self {
+        self.candidates.push(candidate);
+        self
+    }
+
+    /// Rank every candidate for a context, best first
+    pub async fn recommend(&self, context: &DecisionContext) -> IntelligenceResult<Vec<Recommendation>> {
+        let mut features = self.context_analyzer.analyze_context(&context.user_history).await?;
+        features.custom_features.extend(context.context_features.clone());
+
+        let predictions = self.learning_engine.get_predictions(&context.user_id, &features).await?;
+        let preferred = self.learning_engine.get_insights(&context.user_id).await?
+            .remove(&PatternType::ModelPreference.to_string())
+            .unwrap_or_default();
+
+        let mut recommendations: Vec<Recommendation> = self.candidates.iter()
+            .map(|candidate| {
+                let mut reasons = Vec::new();
+
+                let prediction = predictions.get(&candidate.name).copied().unwrap_or(0.0).clamp(0.0, 1.0);
+                let learned = if preferred.contains(&candidate.name) {
+                    reasons.push("learned preference of this user".to_string());
+                    1.0
+                } else {
+                    if prediction > 0.0 {
+                        reasons.push(format!("predicted fit {:.2}", prediction));
+                    }
+                    prediction
+                };
+
+                let qualities: Vec<f64> = context.user_history.iter()
+                    .filter(|interaction| interaction.model_used == candidate.name)
+                    .map(|interaction| interaction.response_quality)
+                    .collect();
+                let history = if qualities.is_empty() {
+                    0.0
+                } else {
+                    let average = qualities.iter().sum::<f64>() / qualities.len() as f64;
+                    reasons.push(format!("average quality {:.2} over {} past uses", average, qualities.len()));
+                    average
+                };
+
+                let intent = candidate.intents.iter()
+                    .filter_map(|intent| {
+                        let share = *features.custom_features.get(&format!("intent_{}", intent))?;
+                        (share > 0.0).then(|| {
+                            reasons.push(format!("suits the {} intent ({:.2})", intent, share));
+                            share
+                        })
+                    })
+                    .sum::<f64>()
+                    .min(1.0);
+
+                if reasons.is_empty() {
+                    reasons.push("no signal for this user yet".to_string());
+                }
+
+                Recommendation {
+                    name: candidate.name.clone(),
+                    kind: candidate.kind,
+                    score: LEARNED_WEIGHT * learned + HISTORY_WEIGHT * history + INTENT_WEIGHT * intent,
+                    rationale: reasons.join("; "),
+                }
+            })
+            .collect();
+
+        recommendations.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+        debug!("Ranked {} candidates for user {}", recommendations.len(), context.user_id);
+        Ok(recommendations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UserInteraction;
+    use std::collections::HashMap;
+
+    fn interaction(model: &str, quality: f64, text: &str) -> UserInteraction {
+        UserInteraction {
+            user_id: "test_user".to_string(),
+            session_id: "test_session".to_string(),
+            timestamp: 1640995200,
+            request_type: "chat".to_string(),
+            model_used: model.to_string(),
+            response_quality: quality,
+            response_time: 1000,
+            user_feedback: None,
+            context_features: HashMap::new(),
+            request_text: Some(text.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recommendations_are_ranked_with_rationales() {
+        let recommender = Recommender::new(Arc::new(LearningEngine::default()))
+            .with_candidate(RecommendationCandidate::model("small-model").with_intent("chitchat"))
+            .with_candidate(RecommendationCandidate::model("code-model").with_intent("code"))
+            .with_candidate(RecommendationCandidate::tool("web_search").with_intent("factual"))
+            .with_candidate(RecommendationCandidate::tool("calculator"));
+
+        let context = DecisionContext {
+            user_id: "test_user".to_string(),
+            session_id: "test_session".to_string(),
+            request_type: "chat".to_string(),
+            current_time: 1640995200,
+            user_history: vec![
+                interaction("code-model", 0.9, "Why does the borrow checker reject this function?"),
+                interaction("code-model", 0.8, "Refactor this python class"),
+                interaction("small-model", 0.4, "Debug this stack trace"),
+            ],
+            system_metrics: HashMap::new(),
+            context_features: HashMap::new(),
+            request_text: None,
+        };
+        let recommendations = recommender.recommend(&context).await.unwrap();
+
+        assert_eq!(recommendations.len(), 4);
+        assert!(recommendations.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        assert!(recommendations.iter().all(|r| !r.rationale.is_empty()));
+
+        assert_eq!(recommendations[0].name, "code-model");
+        assert!(recommendations[0].rationale.contains("past uses"));
+        assert!(recommendations[0].rationale.contains("code intent"));
+        let calculator = recommendations.iter().find(|r| r.name == "calculator").unwrap();
+        assert_eq!(calculator.kind, RecommendationKind::Tool);
+        assert_eq!(calculator.rationale, "no signal for this user yet");
+    }
+}
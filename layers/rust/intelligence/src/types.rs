@@ -15,6 +15,9 @@ pub struct UserInteraction {
     pub response_time: u64,    // milliseconds
     pub user_feedback: Option<f64>, // Optional user rating
     pub context_features: HashMap<String, f64>,
+    /// Text of the user's request, when recorded
+    #[serde(default)]
+    pub request_text: Option<String>,
 }
 
 /// Learning pattern extracted from user interactions
@@ -61,6 +64,8 @@ pub struct DecisionContext {
     pub user_history: Vec<UserInteraction>,
     pub system_metrics: HashMap<String, f64>,
     pub context_features: HashMap<String, f64>,
+    /// Text of the request being decided on, if known
+    pub request_text: Option<String>,
 }
 
 /// Decision result with reasoning
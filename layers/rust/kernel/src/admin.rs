@@ -0,0 +1,404 @@
+//! Local admin socket for introspecting a running kernel
+//!
+//! The kernel binary serves an [`AdminServer`] on a loopback address when
+//! `enable_admin_socket` is set. The socket is unauthenticated, so it is only
+//! for hosts whose local users may all see the kernel's services. Each
+//! connection sends the name of one [`AdminCommand`] on a line and receives
+//! one line of JSON, either `{"ok": ...}` with the data or `{"error": ...}`.
+//! The `sira-kernel` subcommands query it with an [`AdminClient`] and print
+//! the answer with [`format_report`].
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, warn};
+
+use crate::error::{KernelError, KernelResult};
+use crate::kernel::Microkernel;
+use crate::message::MessageBus;
+use crate::plugin::{PluginManager, PluginMetadata};
+use crate::resource::{ResourceManager, ResourceUsage};
+use crate::service::{ServiceMetadata, ServiceRegistry};
+
+/// Default address of the admin socket
+pub const DEFAULT_ADMIN_ADDR: &str = "127.0.0.1:7878";
+
+/// Longest command line the server reads
+const MAX_COMMAND_LEN: u64 = 256;
+
+/// How long the server waits for a connection's command
+const COMMAND_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Introspection query answered by the admin socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdminCommand {
+    ListServices,
+    ListPlugins,
+    ResourceUsage,
+    BusStats,
+}
+
+impl AdminCommand {
+    /// Every command, in the order shown in usage
+    pub const ALL: [AdminCommand; 4] = [
+        AdminCommand::ListServices,
+        AdminCommand::ListPlugins,
+        AdminCommand::ResourceUsage,
+        AdminCommand::BusStats,
+    ];
+
+    /// Subcommand name, also sent over the socket
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminCommand::ListServices => "list-services",
+            AdminCommand::ListPlugins => "list-plugins",
+            AdminCommand::ResourceUsage => "resource-usage",
+            AdminCommand::BusStats => "bus-stats",
+        }
+    }
+}
+
+impl FromStr for AdminCommand {
+    type Err = KernelError;
+
+    fn from_str(s: &str) -> KernelResult<Self> {
+        Self::ALL.into_iter()
+            .find(|command| command.as_str() == s)
+            .ok_or_else(|| KernelError::config_error(format!("Unknown command '{}'", s)))
+    }
+}
+
+/// What the `sira-kernel` binary was asked to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+    /// Start the kernel
+    Run,
+    /// Query a running kernel; without an address the configured one is used
+    Admin {
+        command: AdminCommand,
+        addr: Option<String>,
+    },
+}
+
+impl CliCommand {
+    /// Parse the arguments following the program name
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> KernelResult<Self> {
+        let mut command = None;
+        let mut addr = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if let Some(value) = arg.strip_prefix("--admin-addr=") {
+                addr = Some(value.to_string());
+            } else if arg == "--admin-addr" {
+                addr = Some(args.next().ok_or_else(|| KernelError::config_error("--admin-addr needs an address"))?);
+            } else if arg.starts_with('-') {
+                return Err(KernelError::config_error(format!("Unknown option '{}'", arg)));
+            } else if command.is_some() {
+                return Err(KernelError::config_error(format!("Unexpected argument '{}'", arg)));
+            } else {
+                command = Some(arg.parse::<AdminCommand>()?);
+            }
+        }
+
+        match (command, addr) {
+            (Some(command), addr) => Ok(CliCommand::Admin { command, addr }),
+            (None, None) => Ok(CliCommand::Run),
+            (None, Some(_)) => Err(KernelError::config_error("--admin-addr needs a command")),
+        }
+    }
+
+    /// Usage text listing the subcommands
+    pub fn usage() -> String {
+        let commands: Vec<&str> = AdminCommand::ALL.iter().map(AdminCommand::as_str).collect();
+        format!("Usage: sira-kernel [--admin-addr <addr>] [{}]", commands.join(" | "))
+    }
+}
+
+/// Serves introspection queries about a kernel on a loopback socket
+#[derive(Clone)]
+pub struct AdminServer {
+    plugin_manager: Arc<PluginManager>,
+    service_registry: Arc<ServiceRegistry>,
+    message_bus: Arc<MessageBus>,
+    resource_manager: Arc<ResourceManager>,
+}
+
+impl AdminServer {
+    /// Create a server answering for `kernel`
+    pub fn new(kernel: &Microkernel) -> Self {
+        Self {
+            plugin_manager: kernel.plugin_manager(),
+            service_registry: kernel.service_registry(),
+            message_bus: kernel.message_bus(),
+            resource_manager: kernel.resource_manager(),
+        }
+    }
+
+    /// Answer a command
+    pub async fn execute(&self, command: AdminCommand) -> KernelResult<Value> {
+        let data = match command {
+            AdminCommand::ListServices => serde_json::to_value(self.service_registry.list_services().await),
+            AdminCommand::ListPlugins => serde_json::to_value(self.plugin_manager.list_plugins().await),
+            AdminCommand::ResourceUsage => serde_json::to_value(self.resource_manager.get_all_resource_usage().await),
+            AdminCommand::BusStats => serde_json::to_value(self.message_bus.get_stats().await),
+        };
+        data.map_err(|e| KernelError::generic_error(format!("Failed to serialize {}: {}", command.as_str(), e)))
+    }
+
+    /// Listen on `addr` and answer connections in the background
+    ///
+    /// Only loopback addresses are accepted. Returns the bound address.
+    pub async fn bind(self, addr: &str) -> KernelResult<SocketAddr> {
+        let listener = TcpListener::bind(addr).await
+            .map_err(|e| KernelError::communication_error(format!("Failed to bind admin socket {}: {}", addr, e)))?;
+        let local_addr = listener.local_addr()
+            .map_err(|e| KernelError::communication_error(e.to_string()))?;
+        if !local_addr.ip().is_loopback() {
+            return Err(KernelError::security_error(format!("Admin socket must listen on a loopback address, not {}", local_addr)));
+        }
+
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        let server = self.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = server.handle_connection(stream).await {
+                                debug!("Admin connection from {} failed: {}", peer, e);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("Failed to accept admin connection: {}", e),
+                }
+            }
+        });
+        Ok(local_addr)
+    }
+
+    /// Read one command and write its answer
+    ///
+    /// Connections that send no command within [`COMMAND_TIMEOUT`] are closed.
+    async fn handle_connection(&self, stream: TcpStream) -> std::io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut line = String::new();
+        let mut reader = BufReader::new(reader.take(MAX_COMMAND_LEN));
+        tokio::time::timeout(COMMAND_TIMEOUT, reader.read_line(&mut line)).await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "No admin command received"))??;
+
+        let result = match line.trim().parse::<AdminCommand>() {
+            Ok(command) => self.execute(command).await,
+            Err(e) => Err(e),
+        };
+        let response = match result {
+            Ok(data) => serde_json::json!({ "ok": data }),
+            Err(e) => serde_json::json!({ "error": e.to_string() }),
+        };
+
+        writer.write_all(format!("{}\n", response).as_bytes()).await?;
+        writer.shutdown().await
+    }
+}
+
+/// Client of a kernel's admin socket
+pub struct AdminClient {
+    addr: String,
+}
+
+impl AdminClient {
+    /// Create a client for the admin socket at `addr`
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self { addr: addr.into() }
+    }
+
+    /// Send a command and return the kernel's data
+    pub async fn request(&self, command: AdminCommand) -> KernelResult<Value> {
+        let io_error = |e: std::io::Error| KernelError::communication_error(
+            format!("Admin socket {} failed: {}", self.addr, e)
+        );
+
+        let mut stream = TcpStream::connect(&self.addr).await.map_err(io_error)?;
+        stream.write_all(format!("{}\n", command.as_str()).as_bytes()).await.map_err(io_error)?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.map_err(io_error)?;
+
+        let mut response: Value = serde_json::from_str(&response).map_err(|e| KernelError::communication_error(
+            format!("Invalid admin response: {}", e)
+        ))?;
+        if let Some(error) = response.get("error").and_then(Value::as_str) {
+            return Err(KernelError::generic_error(error));
+        }
+        response.get_mut("ok")
+            .map(Value::take)
+            .ok_or_else(|| KernelError::communication_error("Admin response has no data"))
+    }
+}
+
+/// Render a command's data as a table for the terminal
+pub fn format_report(command: AdminCommand, data: Value) -> KernelResult<String> {
+    let report = match command {
+        AdminCommand::ListServices => {
+            let services: Vec<ServiceMetadata> = parse_data(command, data)?;
+            if services.is_empty() {
+                return Ok("No services registered\n".to_string());
+            }
+            let rows = services.iter()
+                .map(|s| vec![
+                    s.instance_key().to_string(),
+                    s.name.clone(),
+                    s.version.clone(),
+                    format!("{:?}", s.status),
+                    s.endpoint.clone(),
+                ])
+                .collect();
+            table(&["ID", "NAME", "VERSION", "STATUS", "ENDPOINT"], rows)
+        }
+        AdminCommand::ListPlugins => {
+            let plugins: Vec<PluginMetadata> = parse_data(command, data)?;
+            if plugins.is_empty() {
+                return Ok("No plugins loaded\n".to_string());
+            }
+            let rows = plugins.iter()
+                .map(|p| vec![p.id.clone(), p.name.clone(), p.version.clone(), p.services.join(",")])
+                .collect();
+            table(&["ID", "NAME", "VERSION", "SERVICES"], rows)
+        }
+        AdminCommand::ResourceUsage => {
+            let usage: Vec<ResourceUsage> = parse_data(command, data)?;
+            let rows = usage.iter()
+                .map(|u| vec![
+                    u.resource_type.to_string(),
+                    u.used.to_string(),
+                    u.total.to_string(),
+                    format!("{:.1}%", u.usage_percentage),
+                ])
+                .collect();
+            table(&["RESOURCE", "USED", "TOTAL", "USAGE"], rows)
+        }
+        AdminCommand::BusStats => {
+            let stats: BTreeMap<String, Value> = parse_data(command, data)?;
+            let rows = stats.into_iter()
+                .map(|(name, value)| vec![name, value.to_string()])
+                .collect();
+            table(&["STAT", "VALUE"], rows)
+        }
+    };
+    Ok(report)
+}
+
+fn parse_data<T: DeserializeOwned>(command: AdminCommand, data: Value) -> KernelResult<T> {
+    serde_json::from_value(data).map_err(|e| KernelError::communication_error(
+        format!("Unexpected {} data: {}", command.as_str(), e)
+    ))
+}
+
+/// Left-aligned columns separated by two spaces
+fn table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header = headers.iter().map(|header| header.to_string()).collect();
+    let mut out = String::new();
+    for row in std::iter::once(header).chain(rows) {
+        let line: Vec<String> = row.iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        out.push_str(line.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{ResourcePriority, ResourceRequest, ResourceType};
+    use crate::testing::{FakeService, TestKernel};
+
+    fn args(args: &[&str]) -> KernelResult<CliCommand> {
+        CliCommand::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_cli_parsing() {
+        assert_eq!(args(&[]).unwrap(), CliCommand::Run);
+        assert_eq!(args(&["bus-stats"]).unwrap(), CliCommand::Admin { command: AdminCommand::BusStats, addr: None });
+        assert_eq!(
+            args(&["--admin-addr", "127.0.0.1:9000", "list-services"]).unwrap(),
+            CliCommand::Admin { command: AdminCommand::ListServices, addr: Some("127.0.0.1:9000".to_string()) },
+        );
+        assert_eq!(
+            args(&["resource-usage", "--admin-addr=[::1]:9000"]).unwrap(),
+            CliCommand::Admin { command: AdminCommand::ResourceUsage, addr: Some("[::1]:9000".to_string()) },
+        );
+
+        assert!(args(&["list-tasks"]).is_err());
+        assert!(args(&["list-plugins", "bus-stats"]).is_err());
+        assert!(args(&["--verbose"]).is_err());
+        assert!(args(&["list-plugins", "--admin-addr"]).is_err());
+        assert!(args(&["--admin-addr", "127.0.0.1:9000"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_admin_socket_reports_kernel_state() {
+        let kernel = TestKernel::builder()
+            .with_capacity(ResourceType::Memory, 1000)
+            .build()
+            .await
+            .unwrap();
+        kernel.register_service(FakeService::new("search")).await.unwrap();
+        kernel.kernel().resource_manager().request_resources(ResourceRequest {
+            requester: "indexer".to_string(),
+            resource_type: ResourceType::Memory,
+            amount: 250,
+            priority: ResourcePriority::Normal,
+            timeout: None,
+            lease: None,
+            metadata: Default::default(),
+        }).await.unwrap();
+
+        let addr = AdminServer::new(kernel.kernel()).bind("127.0.0.1:0").await.unwrap();
+        let client = AdminClient::new(addr.to_string());
+
+        let services = format_report(AdminCommand::ListServices, client.request(AdminCommand::ListServices).await.unwrap()).unwrap();
+        let lines: Vec<&str> = services.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("ID"));
+        assert!(lines[1].starts_with("search"));
+        assert!(lines[1].contains("Healthy"));
+
+        let usage = format_report(AdminCommand::ResourceUsage, client.request(AdminCommand::ResourceUsage).await.unwrap()).unwrap();
+        assert!(usage.lines().any(|line| line.split_whitespace().collect::<Vec<_>>() == ["Memory", "250", "1000", "25.0%"]));
+
+        let plugins = format_report(AdminCommand::ListPlugins, client.request(AdminCommand::ListPlugins).await.unwrap()).unwrap();
+        assert_eq!(plugins, "No plugins loaded\n");
+
+        let stats = format_report(AdminCommand::BusStats, client.request(AdminCommand::BusStats).await.unwrap()).unwrap();
+        assert!(stats.lines().any(|line| line.starts_with("total_subscriptions")));
+
+        assert!(AdminServer::new(kernel.kernel()).bind("0.0.0.0:0").await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_admin_connections_are_closed() {
+        assert!(!crate::kernel::KernelConfig::default().enable_admin_socket);
+
+        let kernel = TestKernel::builder().build().await.unwrap();
+        let addr = AdminServer::new(kernel.kernel()).bind("127.0.0.1:0").await.unwrap();
+
+        let mut idle = TcpStream::connect(addr).await.unwrap();
+        let mut response = String::new();
+        let closed = tokio::time::timeout(COMMAND_TIMEOUT * 2, idle.read_to_string(&mut response)).await;
+        assert_eq!(closed.unwrap().unwrap(), 0);
+    }
+}
@@ -0,0 +1,229 @@
+//! Unified kernel handle for in-process callers
+//!
+//! A [`KernelClient`] bundles the plugin manager, service registry, message
+//! bus and resource manager behind one cheaply cloneable handle, so code that
+//! embeds the kernel can publish, call services, request resources and
+//! discover services without wiring each component separately. Messages and
+//! resource requests made through a client carry its ID as sender/requester,
+//! and its publishes and subscriptions are checked against the bus's topic
+//! ACL as that ID.
+
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::error::KernelResult;
+use crate::message::{Message, MessageBus, MessageHandler, SubscriptionOptions};
+use crate::plugin::{PluginManager, PluginMetadata};
+use crate::proxy::ServiceProxy;
+use crate::resource::{ResourceManager, ResourcePriority, ResourceRequest, ResourceType};
+use crate::service::{ServiceMetadata, ServiceQuery, ServiceRegistry, ServiceRequest, ServiceResponse};
+
+/// Handle to the kernel components
+#[derive(Clone)]
+pub struct KernelClient {
+    client_id: String,
+    plugin_manager: Arc<PluginManager>,
+    service_registry: Arc<ServiceRegistry>,
+    message_bus: Arc<MessageBus>,
+    resource_manager: Arc<ResourceManager>,
+}
+
+impl KernelClient {
+    /// Create a client over existing kernel components
+    pub fn new<S: Into<String>>(
+        client_id: S,
+        plugin_manager: Arc<PluginManager>,
+        service_registry: Arc<ServiceRegistry>,
+        message_bus: Arc<MessageBus>,
+        resource_manager: Arc<ResourceManager>,
+    ) -> Self {
+        Self {
+            client_id: client_id.into(),
+            plugin_manager,
+            service_registry,
+            message_bus,
+            resource_manager,
+        }
+    }
+
+    /// ID used as message sender and resource requester
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Publish a payload to a topic
+    pub async fn publish<S: Into<String>>(&self, topic: S, payload: serde_json::Value) -> KernelResult<()> {
+        self.message_bus.handle(self.client_id.clone()).publish(Message {
+            id: Uuid::new_v4().to_string(),
+            topic: topic.into(),
+            payload,
+            timestamp: Utc::now(),
+            headers: HashMap::new(),
+            priority: Default::default(),
+            ttl: 0,
+            sender: None,
+            recipients: Vec::new(),
+        }).await
+    }
+
+    /// Subscribe a handler to topics under `subscriber_id`
+    pub async fn subscribe<S: Into<String>>(
+        &self,
+        subscriber_id: S,
+        topics: Vec<String>,
+        handler: Arc<dyn MessageHandler>,
+    ) -> KernelResult<()> {
+        self.message_bus.handle(self.client_id.clone()).subscribe(subscriber_id.into(), topics, handler, SubscriptionOptions::default()).await
+    }
+
+    /// Call a service method with untyped params
+    pub async fn call_service(&self, service_id: &str, method: &str, params: serde_json::Value) -> KernelResult<ServiceResponse> {
+        let request = ServiceRequest {
+            id: Uuid::new_v4().to_string(),
+            method: method.to_string(),
+            params,
+            headers: HashMap::new(),
+            timestamp: Utc::now(),
+            timeout: None,
+        };
+        self.service_registry.call_service(service_id, request).await
+    }
+
+    /// Build a typed proxy for a registered service
+    pub async fn service_proxy(&self, service_id: &str) -> KernelResult<ServiceProxy> {
+        ServiceProxy::new(self.service_registry.clone(), service_id).await
+    }
+
+    /// Discover services matching a query
+    pub async fn discover(&self, query: &ServiceQuery) -> KernelResult<Vec<ServiceMetadata>> {
+        self.service_registry.discover_services(query).await
+    }
+
+    /// Request resources at normal priority, waiting until they are available
+    ///
+    /// Returns the allocation ID to pass to [`KernelClient::release_resource`].
+    pub async fn request_resource(&self, resource_type: ResourceType, amount: u64) -> KernelResult<String> {
+        self.resource_manager.request_resources(ResourceRequest {
+            requester: self.client_id.clone(),
+            resource_type,
+            amount,
+            priority: ResourcePriority::Normal,
+            timeout: None,
+            lease: None,
+            metadata: HashMap::new(),
+        }).await
+    }
+
+    /// Release a resource allocation
+    pub async fn release_resource(&self, allocation_id: &str) -> KernelResult<()> {
+        self.resource_manager.release_resources(allocation_id).await
+    }
+
+    /// List loaded plugins
+    pub async fn list_plugins(&self) -> Vec<PluginMetadata> {
+        self.plugin_manager.list_plugins().await
+    }
+
+    /// Get plugin manager
+    pub fn plugin_manager(&self) -> &Arc<PluginManager> {
+        &self.plugin_manager
+    }
+
+    /// Get service registry
+    pub fn service_registry(&self) -> &Arc<ServiceRegistry> {
+        &self.service_registry
+    }
+
+    /// Get message bus
+    pub fn message_bus(&self) -> &Arc<MessageBus> {
+        &self.message_bus
+    }
+
+    /// Get resource manager
+    pub fn resource_manager(&self) -> &Arc<ResourceManager> {
+        &self.resource_manager
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::{KernelConfig, Microkernel};
+    use crate::service::{ResponseStatus, Service, ServiceStatus};
+    use async_trait::async_trait;
+    use tokio::sync::mpsc;
+
+    struct Echo;
+
+    #[async_trait]
+    impl Service for Echo {
+        fn metadata(&self) -> ServiceMetadata {
+            ServiceMetadata::new("echo", "Echo")
+                .with_description("Echoes params")
+                .with_capability("echo")
+        }
+
+        async fn start(&self) -> KernelResult<()> {
+            Ok(())
+        }
+
+        async fn stop(&self) -> KernelResult<()> {
+            Ok(())
+        }
+
+        async fn health(&self) -> KernelResult<ServiceStatus> {
+            Ok(ServiceStatus::Healthy)
+        }
+
+        async fn handle_request(&self, request: ServiceRequest) -> KernelResult<ServiceResponse> {
+            Ok(ServiceResponse {
+                id: request.id,
+                status: ResponseStatus::Success,
+                data: request.params,
+                headers: HashMap::new(),
+                timestamp: Utc::now(),
+                processing_time_ms: 0,
+            })
+        }
+    }
+
+    struct Collector {
+        sender: mpsc::Sender<Message>,
+    }
+
+    #[async_trait]
+    impl MessageHandler for Collector {
+        async fn handle_message(&self, message: &Message) -> KernelResult<Option<Message>> {
+            self.sender.send(message.clone()).await.ok();
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_calls_services_and_publishes() {
+        let kernel = Microkernel::new(KernelConfig::default()).await.unwrap();
+        let client = kernel.client("embedder");
+        client.service_registry().register_service(Arc::new(Echo), serde_json::Value::Null).await.unwrap();
+
+        let response = client.call_service("echo", "echo", serde_json::json!({ "text": "hi" })).await.unwrap();
+        assert_eq!(response.status, ResponseStatus::Success);
+        assert_eq!(response.data["text"], "hi");
+
+        client.message_bus().start().await.unwrap();
+        let (tx, mut rx) = mpsc::channel(1);
+        client.subscribe("listener", vec!["client.test".to_string()], Arc::new(Collector { sender: tx })).await.unwrap();
+        client.message_bus().wait_until_dispatching("client.test").await;
+
+        client.publish("client.test", serde_json::json!({ "n": 1 })).await.unwrap();
+        let received = tokio::time::timeout(std::time::Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.payload["n"], 1);
+        assert_eq!(received.sender.as_deref(), Some("embedder"));
+
+        client.message_bus().stop().await.unwrap();
+    }
+}
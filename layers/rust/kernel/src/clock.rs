@@ -0,0 +1,111 @@
+//! Time source for the Sira microkernel
+//!
+//! Components read the current time through a [`Clock`] instead of calling
+//! `Utc::now()` directly, so time-dependent logic such as heartbeat expiry,
+//! message TTLs, redelivery delays and allocation timeouts can be driven
+//! deterministically in tests with a [`MockClock`].
+
+use chrono::{DateTime, Duration, Utc};
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    /// Current time
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Wait until `duration` has passed on this clock
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration.to_std().unwrap_or_default()))
+    }
+}
+
+/// Clock reading the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Shared handle to the system clock, the default of every component
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// Manually driven clock for tests
+///
+/// Time only moves when [`MockClock::advance`] or [`MockClock::set`] is
+/// called, which also wakes the sleeps it lets finish.
+#[derive(Debug)]
+pub struct MockClock {
+    now: watch::Sender<DateTime<Utc>>,
+}
+
+impl MockClock {
+    /// Create a clock stopped at `start`
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self { now: watch::Sender::new(start) }
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, duration: Duration) {
+        self.now.send_modify(|now| *now += duration);
+    }
+
+    /// Set the clock to a given time
+    pub fn set(&self, time: DateTime<Utc>) {
+        self.now.send_replace(time);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.borrow()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        let wake_at = self.now() + duration;
+        let mut now = self.now.subscribe();
+        Box::pin(async move {
+            // A dropped clock never advances again, so its sleeps end
+            let _ = now.wait_for(|now| *now >= wake_at).await;
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_mock_clock_only_moves_when_told() {
+        let start = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::seconds(90));
+        assert_eq!(clock.now(), start + Duration::seconds(90));
+
+        clock.set(start);
+        assert_eq!(clock.now(), start);
+    }
+
+    #[tokio::test]
+    async fn test_mock_clock_sleeps_until_advanced_past_the_deadline() {
+        let clock = MockClock::new(Utc::now());
+        let mut sleep = clock.sleep(Duration::seconds(60));
+        assert!(futures::poll!(&mut sleep).is_pending());
+
+        clock.advance(Duration::seconds(59));
+        assert!(futures::poll!(&mut sleep).is_pending());
+
+        clock.advance(Duration::seconds(1));
+        tokio::time::timeout(std::time::Duration::from_secs(1), sleep).await.unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+//! Layered kernel configuration
+//!
+//! A [`ConfigLoader`] resolves a [`KernelConfig`] from several layers, each
+//! overriding the one before it:
+//!
+//! 1. built-in defaults
+//! 2. a TOML file such as `kernel.toml`
+//! 3. environment variables, e.g. `SIRA_KERNEL__RESOURCE__MAX_MEMORY=2048`
+//! 4. explicit overrides, e.g. `resource_limits.max_memory=2048`
+//!
+//! Environment variables and overrides are strings; they are coerced to the
+//! type of the setting they replace, and a value that cannot be coerced is
+//! rejected with an error naming its source.
+
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+use crate::error::{KernelError, KernelResult};
+use crate::kernel::KernelConfig;
+
+/// Default prefix of kernel environment variables
+pub const DEFAULT_ENV_PREFIX: &str = "SIRA_KERNEL";
+
+/// Short names accepted for configuration sections
+const SECTION_ALIASES: &[(&str, &str)] = &[("resource", "resource_limits")];
+
+/// Resolves a kernel configuration from defaults, file, environment and overrides
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    file: Option<PathBuf>,
+    env_prefix: Option<String>,
+    overrides: Vec<(String, String)>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self {
+            file: None,
+            env_prefix: Some(DEFAULT_ENV_PREFIX.to_string()),
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl ConfigLoader {
+    /// Create a loader reading environment variables with the default prefix
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a TOML file, if it exists
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.file = Some(path.into());
+        self
+    }
+
+    /// Read environment variables starting with `<prefix>__`
+    pub fn with_env_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Ignore environment variables
+    pub fn without_env(mut self) -> Self {
+        self.env_prefix = None;
+        self
+    }
+
+    /// Override a dotted key such as `resource_limits.max_memory`
+    pub fn with_override(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.overrides.push((key.into(), value.into()));
+        self
+    }
+
+    /// Resolve the configuration
+    pub fn load(&self) -> KernelResult<KernelConfig> {
+        let defaults = to_value(&KernelConfig::default())?;
+        let mut resolved = defaults.clone();
+
+        if let Some(path) = &self.file {
+            if let Ok(content) = std::fs::read_to_string(path) {
+                let file: Value = toml::from_str(&content).map_err(|e| KernelError::config_error(
+                    format!("Failed to parse {}: {}", path.display(), e)
+                ))?;
+                merge(&mut resolved, file);
+            }
+        }
+
+        if let Some(prefix) = &self.env_prefix {
+            let prefix = format!("{}__", prefix);
+            let mut vars: Vec<(String, String)> = std::env::vars()
+                .filter(|(name, _)| name.starts_with(&prefix))
+                .collect();
+            vars.sort();
+
+            for (name, raw) in vars {
+                let path: Vec<String> = name[prefix.len()..]
+                    .split("__")
+                    .map(str::to_lowercase)
+                    .collect();
+                set_path(&mut resolved, &defaults, &path, &raw, &name)?;
+            }
+        }
+
+        for (key, raw) in &self.overrides {
+            let path: Vec<String> = key.split('.').map(str::to_string).collect();
+            set_path(&mut resolved, &defaults, &path, raw, &format!("override '{}'", key))?;
+        }
+
+        serde_json::from_value(resolved).map_err(|e| KernelError::config_error(
+            format!("Invalid kernel configuration: {}", e)
+        ))
+    }
+}
+
+fn to_value(config: &KernelConfig) -> KernelResult<Value> {
+    serde_json::to_value(config).map_err(|e| KernelError::config_error(
+        format!("Failed to serialize kernel configuration: {}", e)
+    ))
+}
+
+/// Recursively merge `layer` into `base`, replacing everything but tables
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Object(base), Value::Object(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// Set a string value at a key path, coerced to the type of the default
+fn set_path(resolved: &mut Value, defaults: &Value, path: &[String], raw: &str, source: &str) -> KernelResult<()> {
+    let path: Vec<&str> = path.iter()
+        .enumerate()
+        .map(|(depth, segment)| match SECTION_ALIASES.iter().find(|(alias, _)| depth == 0 && alias == segment) {
+            Some((_, section)) => *section,
+            None => segment.as_str(),
+        })
+        .collect();
+
+    let key = path.join(".");
+    let default = path.iter()
+        .try_fold(defaults, |value, segment| value.get(*segment))
+        .ok_or_else(|| KernelError::config_error(format!("Unknown configuration key '{}' in {}", key, source)))?;
+    let value = coerce(default, raw).ok_or_else(|| KernelError::config_error(format!(
+        "Invalid value '{}' for '{}' in {}: expected {}", raw, key, source, describe(default)
+    )))?;
+
+    let (last, parents) = path.split_last()
+        .ok_or_else(|| KernelError::config_error(format!("Empty configuration key in {}", source)))?;
+    let mut target = resolved;
+    for segment in parents {
+        target = target.as_object_mut()
+            .map(|object| object.entry(segment.to_string()).or_insert_with(|| Value::Object(Map::new())))
+            .ok_or_else(|| KernelError::config_error(format!("'{}' in {} is not a table", key, source)))?;
+    }
+    target.as_object_mut()
+        .ok_or_else(|| KernelError::config_error(format!("'{}' in {} is not a table", key, source)))?
+        .insert(last.to_string(), value);
+    Ok(())
+}
+
+/// Parse a string as the same type as `default`
+fn coerce(default: &Value, raw: &str) -> Option<Value> {
+    let raw = raw.trim();
+    match default {
+        Value::Bool(_) => match raw.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Some(Value::Bool(true)),
+            "false" | "0" | "no" | "off" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Value::Number(n) if n.is_u64() => raw.parse::<u64>().ok().map(Value::from),
+        Value::Number(n) if n.is_i64() => raw.parse::<i64>().ok().map(Value::from),
+        Value::Number(_) => raw.parse::<f64>().ok().map(Value::from),
+        Value::String(_) => Some(Value::String(raw.to_string())),
+        Value::Array(_) => Some(Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        )),
+        Value::Null | Value::Object(_) => None,
+    }
+}
+
+fn describe(default: &Value) -> &'static str {
+    match default {
+        Value::Bool(_) => "a boolean",
+        Value::Number(n) if n.is_u64() => "a non-negative integer",
+        Value::Number(n) if n.is_i64() => "an integer",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "a comma-separated list",
+        Value::Null | Value::Object(_) => "a table",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_environment_overrides_file_and_explicit_overrides_win() {
+        let path = std::env::temp_dir().join(format!("sira-kernel-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "heartbeat_interval = 10\n\n[resource_limits]\nmax_memory = 4096\nmax_gpu = 2\n").unwrap();
+
+        std::env::set_var("SIRA_KERNEL_TEST_LAYERS__RESOURCE__MAX_MEMORY", "2048");
+        std::env::set_var("SIRA_KERNEL_TEST_LAYERS__AUTO_DISCOVER_PLUGINS", "false");
+        std::env::set_var("SIRA_KERNEL_TEST_LAYERS__HEARTBEAT_INTERVAL", "15");
+
+        let config = ConfigLoader::new()
+            .with_file(&path)
+            .with_env_prefix("SIRA_KERNEL_TEST_LAYERS")
+            .with_override("heartbeat_interval", "20")
+            .with_override("plugin_dirs", "./a, ./b")
+            .load()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.resource_limits.max_memory, 2048);
+        assert_eq!(config.resource_limits.max_gpu, 2);
+        assert_eq!(config.resource_limits.max_disk, KernelConfig::default().resource_limits.max_disk);
+        assert!(!config.auto_discover_plugins);
+        assert_eq!(config.heartbeat_interval, 20);
+        assert_eq!(config.plugin_dirs, vec!["./a", "./b"]);
+    }
+
+    #[test]
+    fn test_malformed_values_are_rejected() {
+        std::env::set_var("SIRA_KERNEL_TEST_MALFORMED__RESOURCE__MAX_MEMORY", "lots");
+        let err = ConfigLoader::new()
+            .with_env_prefix("SIRA_KERNEL_TEST_MALFORMED")
+            .load()
+            .unwrap_err();
+        assert!(err.to_string().contains("SIRA_KERNEL_TEST_MALFORMED__RESOURCE__MAX_MEMORY"));
+        assert!(err.to_string().contains("resource_limits.max_memory"));
+
+        let unknown = ConfigLoader::new().without_env().with_override("resource_limits.max_ram", "1").load();
+        assert!(matches!(unknown, Err(KernelError::ConfigError { .. })));
+
+        let negative = ConfigLoader::new().without_env().with_override("service_timeout", "-5").load();
+        assert!(matches!(negative, Err(KernelError::ConfigError { .. })));
+    }
+}
@@ -25,6 +25,13 @@ pub enum KernelError {
         source: Option<Box<dyn std::error::Error + Send + Sync>>,
     },
 
+    /// Plugin dependencies that are not loaded or depend on each other in a cycle
+    #[error("Plugin '{plugin_id}' has unresolved dependencies: {}", dependencies.join(", "))]
+    PluginDependencyError {
+        plugin_id: String,
+        dependencies: Vec<String>,
+    },
+
     /// Service-related errors
     #[error("Service error: {service_id} - {message}")]
     ServiceError {
@@ -40,6 +47,21 @@ pub enum KernelError {
         source: Option<Box<dyn std::error::Error + Send + Sync>>,
     },
 
+    /// Message payload does not conform to its topic's schema
+    #[error("Schema validation error on topic '{topic}': {}", errors.join("; "))]
+    SchemaValidationError {
+        topic: String,
+        errors: Vec<String>,
+    },
+
+    /// A sender or subscriber is not permitted on a topic
+    #[error("Access denied: '{identity}' may not {action} topic '{topic}'")]
+    TopicAccessDenied {
+        identity: String,
+        topic: String,
+        action: String,
+    },
+
     /// Resource management errors
     #[error("Resource error: {resource_id} - {message}")]
     ResourceError {
@@ -112,6 +134,14 @@ impl KernelError {
         }
     }
 
+    /// Create a new plugin dependency error
+    pub fn plugin_dependency_error<S: Into<String>>(plugin_id: S, dependencies: Vec<String>) -> Self {
+        KernelError::PluginDependencyError {
+            plugin_id: plugin_id.into(),
+            dependencies,
+        }
+    }
+
     /// Create a new service error
     pub fn service_error<S: Into<String>>(service_id: S, message: S) -> Self {
         KernelError::ServiceError {
@@ -128,6 +158,23 @@ impl KernelError {
         }
     }
 
+    /// Create a new schema validation error
+    pub fn schema_validation_error<T: Into<String>>(topic: T, errors: Vec<String>) -> Self {
+        KernelError::SchemaValidationError {
+            topic: topic.into(),
+            errors,
+        }
+    }
+
+    /// Create a new topic access denied error
+    pub fn topic_access_denied<I: Into<String>, T: Into<String>>(identity: I, topic: T, action: &str) -> Self {
+        KernelError::TopicAccessDenied {
+            identity: identity.into(),
+            topic: topic.into(),
+            action: action.to_string(),
+        }
+    }
+
     /// Create a new resource error
     pub fn resource_error<R: Into<String>, S: Into<String>>(resource_id: R, message: S) -> Self {
         KernelError::ResourceError {
@@ -8,6 +8,7 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{info, warn, error};
 
+use crate::client::KernelClient;
 use crate::error::{KernelError, KernelResult};
 use crate::plugin::{PluginManager, PluginContext, KernelState};
 use crate::service::ServiceRegistry;
@@ -31,6 +32,13 @@ pub struct KernelConfig {
     pub auto_discover_plugins: bool,
     /// Enable resource monitoring
     pub enable_resource_monitoring: bool,
+    /// Serve the admin socket from the kernel binary
+    ///
+    /// Off by default: the socket is unauthenticated, so any local user can
+    /// query it while it is served.
+    pub enable_admin_socket: bool,
+    /// Loopback address of the admin socket
+    pub admin_addr: String,
 }
 
 impl Default for KernelConfig {
@@ -55,6 +63,8 @@ impl Default for KernelConfig {
             service_timeout: 90,
             auto_discover_plugins: true,
             enable_resource_monitoring: true,
+            enable_admin_socket: false,
+            admin_addr: crate::admin::DEFAULT_ADMIN_ADDR.to_string(),
         }
     }
 }
@@ -81,39 +91,40 @@ impl Microkernel {
     /// Create a new microkernel with configuration
     pub async fn new(config: KernelConfig) -> KernelResult<Self> {
         // Initialize components
-        let message_bus = Arc::new(MessageBus::new());
+        let message_bus = Arc::new(MessageBus::new().with_buffer_size(config.message_buffer_size));
         let resource_manager = Arc::new(ResourceManager::new(config.resource_limits.clone()));
-        let kernel_state = Arc::new(RwLock::new(KernelState::default()));
-
         let service_registry = Arc::new(ServiceRegistry::new(message_bus.clone()));
-        let plugin_manager = Arc::new(PluginManager::new(
-            message_bus.clone(),
-            resource_manager.clone(),
-            kernel_state.clone(),
-        ));
 
-        // Add plugin directories (clone the manager to modify it)
-        let mut pm_clone = PluginManager::new(
+        Ok(Self::from_components(config, message_bus, resource_manager, service_registry))
+    }
+
+    /// Assemble a kernel around existing components
+    pub(crate) fn from_components(
+        config: KernelConfig,
+        message_bus: Arc<MessageBus>,
+        resource_manager: Arc<ResourceManager>,
+        service_registry: Arc<ServiceRegistry>,
+    ) -> Self {
+        let kernel_state = Arc::new(RwLock::new(KernelState::default()));
+
+        let mut plugin_manager = PluginManager::new(
             message_bus.clone(),
             resource_manager.clone(),
             kernel_state.clone(),
         );
         for dir in &config.plugin_dirs {
-            pm_clone.add_plugin_dir(dir);
+            plugin_manager.add_plugin_dir(dir);
         }
-        let plugin_manager = Arc::new(pm_clone);
 
-        let kernel = Microkernel {
+        Microkernel {
             config,
-            plugin_manager,
+            plugin_manager: Arc::new(plugin_manager),
             service_registry,
             message_bus,
             resource_manager,
             kernel_state,
             running: RwLock::new(false),
-        };
-
-        Ok(kernel)
+        }
     }
 
     /// Start the microkernel
@@ -190,6 +201,17 @@ impl Microkernel {
         self.resource_manager.clone()
     }
 
+    /// Create a client handle over the kernel components
+    pub fn client<S: Into<String>>(&self, client_id: S) -> KernelClient {
+        KernelClient::new(
+            client_id,
+            self.plugin_manager.clone(),
+            self.service_registry.clone(),
+            self.message_bus.clone(),
+            self.resource_manager.clone(),
+        )
+    }
+
     /// Get kernel state
     pub fn kernel_state(&self) -> Arc<RwLock<KernelState>> {
         self.kernel_state.clone()
@@ -400,6 +422,9 @@ impl Microkernel {
 
             loop {
                 interval.tick().await;
+                resource_manager.reclaim_lapsed_leases().await;
+                resource_manager.release_expired_reservations().await;
+                resource_manager.sample_usage().await;
 
         // Log resource usage
         let usage = resource_manager.get_all_resource_usage().await;
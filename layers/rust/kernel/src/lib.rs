@@ -21,21 +21,39 @@
 //! - Register services with the kernel
 //! - Subscribe to and publish messages on the bus
 //! - Request and release resources
-//! - Communicate with other plugins through well-defined interfaces
+//! - Communicate with other plugins through well-defined interfaces, calling
+//!   their services with typed [`ServiceProxy`] clients
+//!
+//! A running kernel binary can be inspected from the command line through its
+//! local admin socket, e.g. `sira-kernel list-services`.
 
 pub mod error;
 pub mod plugin;
 pub mod service;
+pub mod proxy;
 pub mod message;
 pub mod resource;
 pub mod kernel;
+pub mod config_loader;
+pub mod client;
+pub mod clock;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod admin;
 
 pub use error::{KernelError, KernelResult};
-pub use plugin::{Plugin, PluginMetadata, PluginContext, PluginManager};
-pub use service::{Service, ServiceMetadata, ServiceRegistry};
-pub use message::{Message, MessageBus, MessageHandler};
-pub use resource::{ResourceManager, ResourceRequest};
+pub use plugin::{Plugin, PluginMetadata, PluginContext, PluginManager, PluginManifest};
+pub use service::{InstanceSelection, Service, ServiceMetadata, ServiceMethod, ServiceRegistry};
+pub use proxy::ServiceProxy;
+pub use message::{Acknowledgement, BusHandle, DeadLetter, Message, MessageBus, MessageHandler, TopicAccess, TopicAcl, TypedMessageHandler, ACL_AUDIT_TOPIC};
+pub use resource::{ResourceManager, ResourceRequest, ProportionalShareStrategy};
 pub use kernel::Microkernel;
+pub use config_loader::ConfigLoader;
+pub use client::KernelClient;
+pub use clock::{Clock, MockClock, SystemClock};
+#[cfg(any(test, feature = "testing"))]
+pub use testing::{FakeService, TestKernel, TestKernelBuilder};
+pub use admin::{AdminClient, AdminCommand, AdminServer, CliCommand, DEFAULT_ADMIN_ADDR};
 
 /// Re-export commonly used types
 pub use abi_stable;
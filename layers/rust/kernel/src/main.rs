@@ -1,16 +1,37 @@
 //! Sira Kernel - Main entry point
 //!
 //! This is the main entry point for the Sira microkernel.
-//! It initializes the kernel, loads plugins, and starts all services.
+//! Without arguments it initializes the kernel, loads plugins, and starts all
+//! services; the introspection subcommands query a running kernel instead.
 
 use std::sync::Arc;
-use tracing::{info, error, Level};
+use tracing::{info, error, warn, Level};
 use tracing_subscriber::{FmtSubscriber, EnvFilter};
 
-use sira_kernel::{kernel::KernelConfig, Microkernel};
+use sira_kernel::{admin::format_report, kernel::KernelConfig, AdminClient, AdminServer, CliCommand, ConfigLoader, Microkernel};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    match CliCommand::parse(std::env::args().skip(1)) {
+        Ok(CliCommand::Run) => run().await,
+        Ok(CliCommand::Admin { command, addr }) => {
+            let addr = match addr {
+                Some(addr) => addr,
+                None => ConfigLoader::new().with_file("kernel.toml").load()?.admin_addr,
+            };
+            let data = AdminClient::new(addr).request(command).await?;
+            print!("{}", format_report(command, data)?);
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}\n{}", e, CliCommand::usage());
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Run the kernel until Ctrl+C
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing
     let subscriber = FmtSubscriber::builder()
         .with_env_filter(EnvFilter::from_default_env()
@@ -42,6 +63,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(1);
     }
 
+    if kernel.config().enable_admin_socket {
+        match AdminServer::new(&kernel).bind(&kernel.config().admin_addr).await {
+            Ok(addr) => info!("🔧 Admin socket listening on {}", addr),
+            Err(e) => warn!("Admin socket unavailable: {}", e),
+        }
+    }
+
     info!("🎉 Sira Microkernel started successfully!");
     info!("📊 Health check available at: http://localhost:8080/health");
     info!("📈 Metrics available at: http://localhost:8080/metrics");
@@ -53,18 +81,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-/// Load kernel configuration
+/// Load kernel configuration from defaults, kernel.toml and the environment
 fn load_config() -> Result<KernelConfig, Box<dyn std::error::Error>> {
-    // Try to load from config file first
-    if let Ok(config_content) = std::fs::read_to_string("kernel.toml") {
-        let config: KernelConfig = toml::from_str(&config_content)?;
-        info!("Loaded configuration from kernel.toml");
-        return Ok(config);
-    }
-
-    // Fall back to default configuration
-    info!("Using default kernel configuration");
-    Ok(KernelConfig::default())
+    let config = ConfigLoader::new().with_file("kernel.toml").load()?;
+    info!("Loaded kernel configuration");
+    Ok(config)
 }
 
 /// Setup signal handlers for graceful shutdown
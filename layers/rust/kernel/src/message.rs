@@ -4,14 +4,19 @@
 //! different components (plugins, services, layers) to communicate asynchronously.
 
 use async_trait::async_trait;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock, broadcast};
+use tokio::sync::{mpsc, watch, Notify, RwLock, Semaphore, broadcast};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use futures::StreamExt;
+use jsonschema::JSONSchema;
 
+use crate::clock::{system_clock, Clock};
 use crate::error::{KernelError, KernelResult};
 
 /// Message structure for the message bus
@@ -59,11 +64,86 @@ impl Default for MessagePriority {
     }
 }
 
+/// Header carrying the 1-based delivery attempt on manual-ack subscriptions
+pub const DELIVERY_ATTEMPT_HEADER: &str = "x-delivery-attempt";
+
+/// Outcome a handler reports for a message on a manual-ack subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Acknowledgement {
+    /// The message was processed and must not be redelivered
+    Ack,
+    /// The message was not processed and should be redelivered
+    Nack,
+}
+
 /// Message handler trait
 #[async_trait]
 pub trait MessageHandler: Send + Sync {
     /// Handle a message
     async fn handle_message(&self, message: &Message) -> KernelResult<Option<Message>>;
+
+    /// Handle a message on a subscription without `auto_ack`
+    ///
+    /// By default a successfully handled message is acked and an error nacks it.
+    async fn handle_with_ack(&self, message: &Message) -> KernelResult<Acknowledgement> {
+        self.handle_message(message).await.map(|_| Acknowledgement::Ack)
+    }
+}
+
+/// Message that was never acked by a subscriber
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub message: Message,
+    pub subscriber_id: String,
+    /// Deliveries made before giving up
+    pub attempts: u32,
+    /// Why the last delivery failed
+    pub last_error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// Handler for messages whose payload has been deserialized into `T`
+#[async_trait]
+pub trait TypedMessageHandler<T>: Send + Sync {
+    /// Handle a typed payload along with its envelope
+    async fn handle_typed(&self, payload: T, message: &Message) -> KernelResult<Option<Message>>;
+}
+
+/// Adapter deserializing payloads before passing them to a typed handler
+struct TypedHandlerAdapter<T, H> {
+    handler: H,
+    /// Messages whose payload did not deserialize into `T`
+    failures: Arc<AtomicU64>,
+    _payload: PhantomData<fn() -> T>,
+}
+
+#[async_trait]
+impl<T, H> MessageHandler for TypedHandlerAdapter<T, H>
+where
+    T: DeserializeOwned + Send + 'static,
+    H: TypedMessageHandler<T>,
+{
+    async fn handle_message(&self, message: &Message) -> KernelResult<Option<Message>> {
+        match serde_json::from_value::<T>(message.payload.clone()) {
+            Ok(payload) => self.handler.handle_typed(payload, message).await,
+            Err(e) => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!(
+                    "Skipping message {} on '{}': payload does not match {}: {}",
+                    message.id, message.topic, std::any::type_name::<T>(), e
+                );
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// JSON Schema registered for a topic
+struct TopicSchema {
+    /// Schema document as registered
+    schema: serde_json::Value,
+    /// Compiled validator
+    validator: JSONSchema,
 }
 
 /// Subscription information
@@ -73,21 +153,100 @@ struct Subscription {
     id: String,
     /// Subscribed topics (with wildcards support)
     topics: Vec<String>,
-    /// Message handler
-    handler: Arc<dyn MessageHandler>,
     /// Subscription options
     options: SubscriptionOptions,
+    /// Queue of the subscription's delivery worker
+    deliveries: mpsc::UnboundedSender<Message>,
+}
+
+/// Messages a topic channel holds by default before slow subscribers lag
+const DEFAULT_BUFFER_SIZE: usize = 100;
+
+/// How long a batch waits for subscribers to make room on a full topic channel
+const BATCH_BACKPRESSURE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Topic audit events for denied publishes and subscriptions are published to
+pub const ACL_AUDIT_TOPIC: &str = "kernel.acl.denied";
+
+/// Operation checked against a [`TopicAcl`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopicAccess {
+    Publish,
+    Subscribe,
+}
+
+impl TopicAccess {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TopicAccess::Publish => "publish",
+            TopicAccess::Subscribe => "subscribe",
+        }
+    }
+}
+
+/// Topic patterns each identity may publish and subscribe to
+///
+/// Patterns use the same `*` wildcards as subscriptions. Identities without
+/// rules are denied everything. The ACL applies to [`BusHandle`]s, which are
+/// bound to an identity; the kernel using the bus directly is not restricted.
+#[derive(Debug, Clone, Default)]
+pub struct TopicAcl {
+    publish: HashMap<String, Vec<String>>,
+    subscribe: HashMap<String, Vec<String>>,
+}
+
+impl TopicAcl {
+    /// Create an ACL that denies everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow `identity` to publish to topics matching `pattern`
+    pub fn allow_publish(mut self, identity: impl Into<String>, pattern: impl Into<String>) -> Self {
+        self.publish.entry(identity.into()).or_default().push(pattern.into());
+        self
+    }
+
+    /// Allow `identity` to subscribe to topics matching `pattern`
+    pub fn allow_subscribe(mut self, identity: impl Into<String>, pattern: impl Into<String>) -> Self {
+        self.subscribe.entry(identity.into()).or_default().push(pattern.into());
+        self
+    }
+
+    /// Whether `identity` may perform `access` on `topic`
+    ///
+    /// A wildcard subscription is allowed only if a single pattern covers it.
+    pub fn is_allowed(&self, identity: &str, access: TopicAccess, topic: &str) -> bool {
+        let rules = match access {
+            TopicAccess::Publish => &self.publish,
+            TopicAccess::Subscribe => &self.subscribe,
+        };
+        rules.get(identity).is_some_and(|patterns| {
+            patterns.iter().any(|pattern| MessageBus::topic_matches_static(pattern, topic))
+        })
+    }
 }
 
 /// Subscription options
 #[derive(Clone)]
 pub struct SubscriptionOptions {
-    /// Maximum number of concurrent messages to process
+    /// Maximum number of messages handled at once
+    ///
+    /// Messages start in the order they were published; with 1, each one,
+    /// redeliveries included, is done before the next starts.
     pub max_concurrent: usize,
     /// Message processing timeout (in seconds)
     pub timeout: u32,
     /// Whether to acknowledge messages automatically
+    ///
+    /// Without auto-ack a message is redelivered until the handler acks it,
+    /// up to `max_delivery_attempts`, and then moved to the dead-letter queue.
     pub auto_ack: bool,
+    /// Deliveries of a message before it is dead-lettered (manual ack only)
+    pub max_delivery_attempts: u32,
+    /// Delay before redelivering a nacked message (in milliseconds, on the bus clock)
+    pub redelivery_delay_ms: u64,
     /// Message filter function
     pub filter: Option<Arc<dyn Fn(&Message) -> bool + Send + Sync>>,
 }
@@ -98,6 +257,8 @@ impl Default for SubscriptionOptions {
             max_concurrent: 10,
             timeout: 30,
             auto_ack: true,
+            max_delivery_attempts: 3,
+            redelivery_delay_ms: 1000,
             filter: None,
         }
     }
@@ -115,8 +276,28 @@ pub struct MessageBus {
     message_history: Arc<RwLock<VecDeque<Message>>>,
     /// Maximum history size
     max_history_size: usize,
+    /// Messages each topic channel holds until every subscriber received them
+    buffer_size: usize,
+    /// Deserialization failure counters of typed subscribers
+    typed_failures: Arc<RwLock<HashMap<String, Arc<AtomicU64>>>>,
+    /// Payload schemas by topic
+    schemas: Arc<RwLock<HashMap<String, Arc<TopicSchema>>>>,
+    /// Publishes rejected by schema validation
+    schema_rejections: Arc<AtomicU64>,
+    /// Messages manual-ack subscribers never acked
+    dead_letters: Arc<RwLock<VecDeque<DeadLetter>>>,
+    /// Topic permissions of bus handles; without one every identity may use every topic
+    acl: Arc<RwLock<Option<TopicAcl>>>,
+    /// Publishes and subscriptions rejected by the ACL
+    acl_denials: Arc<AtomicU64>,
     /// Running flag
     running: Arc<RwLock<bool>>,
+    /// Topics the message processor dispatches to subscribers
+    dispatching: Arc<watch::Sender<HashSet<String>>>,
+    /// Notified whenever dispatch takes a message off a topic channel
+    room: Arc<Notify>,
+    /// Time source for message timestamps and TTLs
+    clock: Arc<dyn Clock>,
 }
 
 impl MessageBus {
@@ -128,10 +309,32 @@ impl MessageBus {
             message_queue: Arc::new(RwLock::new(VecDeque::new())),
             message_history: Arc::new(RwLock::new(VecDeque::new())),
             max_history_size: 1000,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            typed_failures: Arc::new(RwLock::new(HashMap::new())),
+            schemas: Arc::new(RwLock::new(HashMap::new())),
+            schema_rejections: Arc::new(AtomicU64::new(0)),
+            dead_letters: Arc::new(RwLock::new(VecDeque::new())),
+            acl: Arc::new(RwLock::new(None)),
+            acl_denials: Arc::new(AtomicU64::new(0)),
             running: Arc::new(RwLock::new(false)),
+            dispatching: Arc::new(watch::Sender::new(HashSet::new())),
+            room: Arc::new(Notify::new()),
+            clock: system_clock(),
         }
     }
 
+    /// Use a custom time source for message timestamps and TTLs
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Hold up to `buffer_size` undelivered messages per topic
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size.max(1);
+        self
+    }
+
     /// Start the message bus
     pub async fn start(&self) -> KernelResult<()> {
         let mut running = self.running.write().await;
@@ -159,26 +362,146 @@ impl MessageBus {
         Ok(())
     }
 
-    /// Publish a message to a topic
-    pub async fn publish(&self, mut message: Message) -> KernelResult<()> {
-        // Set message ID if not provided
-        if message.id.is_empty() {
-            message.id = Uuid::new_v4().to_string();
+    /// Restrict what bus handles may publish and subscribe to, or lift the restriction
+    pub async fn set_acl(&self, acl: Option<TopicAcl>) {
+        *self.acl.write().await = acl;
+    }
+
+    /// Handle publishing and subscribing as `identity`, subject to the ACL
+    pub fn handle(self: &Arc<Self>, identity: impl Into<String>) -> BusHandle {
+        BusHandle {
+            bus: Arc::clone(self),
+            identity: identity.into(),
         }
+    }
 
-        // Set timestamp if not provided
-        if message.timestamp == DateTime::<Utc>::MIN_UTC {
-            message.timestamp = Utc::now();
+    /// Check an operation against the ACL, auditing denials on [`ACL_AUDIT_TOPIC`]
+    async fn authorize(&self, identity: &str, access: TopicAccess, topic: &str) -> KernelResult<()> {
+        let allowed = match self.acl.read().await.as_ref() {
+            Some(acl) => acl.is_allowed(identity, access, topic),
+            None => true,
+        };
+        if allowed {
+            return Ok(());
         }
 
-        // Check TTL
-        if message.ttl > 0 {
-            let now = Utc::now();
-            if now.signed_duration_since(message.timestamp).num_seconds() > message.ttl as i64 {
-                tracing::warn!("Message {} expired, dropping", message.id);
+        self.acl_denials.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!("Denied '{}' {} on topic '{}'", identity, access.as_str(), topic);
+
+        let mut audit = Message {
+            id: Uuid::new_v4().to_string(),
+            topic: ACL_AUDIT_TOPIC.to_string(),
+            payload: serde_json::json!({
+                "identity": identity,
+                "action": access,
+                "topic": topic,
+            }),
+            timestamp: self.clock.now(),
+            headers: HashMap::new(),
+            priority: MessagePriority::High,
+            ttl: 0,
+            sender: Some("kernel".to_string()),
+            recipients: vec![],
+        };
+        audit.headers.insert("event".to_string(), "acl_denied".to_string());
+        if let Err(e) = self.publish(audit).await {
+            tracing::warn!("Failed to publish ACL audit event: {}", e);
+        }
+
+        Err(KernelError::topic_access_denied(identity, topic, access.as_str()))
+    }
+
+    /// Publish several messages, taking the topics lock and history lock once for the batch
+    ///
+    /// Each message is checked as by [`Self::publish`] before anything is
+    /// delivered, so a message its topic's schema rejects fails the whole
+    /// batch. Otherwise the result of each message is returned in batch
+    /// order; expired messages are dropped and count as published. Delivered
+    /// messages keep their order within a topic, are acked and dead-lettered
+    /// one by one, and only they are added to the history.
+    ///
+    /// When a topic channel is full the batch waits for subscribers to catch
+    /// up rather than overwrite messages they have not received yet.
+    pub async fn publish_batch(&self, messages: Vec<Message>) -> KernelResult<Vec<KernelResult<()>>> {
+        self.publish_batch_as(None, messages).await
+    }
+
+    /// Publish a batch, checking each message against the ACL when published by a handle
+    async fn publish_batch_as(&self, identity: Option<&str>, messages: Vec<Message>) -> KernelResult<Vec<KernelResult<()>>> {
+        let mut prepared = Vec::with_capacity(messages.len());
+        for message in messages {
+            if let Some(identity) = identity {
+                self.authorize(identity, TopicAccess::Publish, &message.topic).await?;
+            }
+            prepared.push(self.prepare(message).await?);
+        }
+
+        let senders: HashMap<String, broadcast::Sender<Message>> = {
+            let mut topics = self.topics.write().await;
+            prepared.iter()
+                .flatten()
+                .map(|message| {
+                    let sender = topics.entry(message.topic.clone())
+                        .or_insert_with(|| broadcast::channel(self.buffer_size).0)
+                        .clone();
+                    (message.topic.clone(), sender)
+                })
+                .collect()
+        };
+
+        let mut results = Vec::with_capacity(prepared.len());
+        let mut delivered = Vec::new();
+        for message in prepared {
+            let Some(message) = message else {
+                results.push(Ok(()));
+                continue;
+            };
+            let sender = &senders[&message.topic];
+            let result = match self.wait_for_room(sender).await {
+                Ok(()) => sender.send(message.clone())
+                    .map(|_| ())
+                    .map_err(|e| KernelError::message_bus_error(format!("Failed to send message: {}", e))),
+                Err(e) => Err(e),
+            };
+            if result.is_ok() {
+                delivered.push(message);
+            }
+            results.push(result);
+        }
+
+        if !delivered.is_empty() {
+            let mut history = self.message_history.write().await;
+            history.extend(delivered);
+            let excess = history.len().saturating_sub(self.max_history_size);
+            history.drain(..excess);
+        }
+
+        Ok(results)
+    }
+
+    /// Wait until a topic channel can take a message without its subscribers lagging
+    async fn wait_for_room(&self, sender: &broadcast::Sender<Message>) -> KernelResult<()> {
+        let deadline = tokio::time::Instant::now() + BATCH_BACKPRESSURE_TIMEOUT;
+        loop {
+            // Register before checking so room made in between is not missed
+            let made_room = self.room.notified();
+            tokio::pin!(made_room);
+            made_room.as_mut().enable();
+
+            if sender.len() < self.buffer_size {
                 return Ok(());
             }
+            if tokio::time::timeout_at(deadline, made_room).await.is_err() {
+                return Err(KernelError::message_bus_error("Subscribers are not keeping up with the topic"));
+            }
         }
+    }
+
+    /// Publish a message to a topic
+    pub async fn publish(&self, message: Message) -> KernelResult<()> {
+        let Some(message) = self.prepare(message).await? else {
+            return Ok(());
+        };
 
         // Add to history
         self.add_to_history(message.clone()).await;
@@ -199,6 +522,78 @@ impl MessageBus {
         Ok(())
     }
 
+    /// Fill in a message's ID and timestamp and validate its payload
+    ///
+    /// Returns `None` for a message past its TTL.
+    async fn prepare(&self, mut message: Message) -> KernelResult<Option<Message>> {
+        // Set message ID if not provided
+        if message.id.is_empty() {
+            message.id = Uuid::new_v4().to_string();
+        }
+
+        // Set timestamp if not provided
+        if message.timestamp == DateTime::<Utc>::MIN_UTC {
+            message.timestamp = self.clock.now();
+        }
+
+        // Check TTL
+        if message.ttl > 0 {
+            let now = self.clock.now();
+            if now.signed_duration_since(message.timestamp).num_seconds() > message.ttl as i64 {
+                tracing::warn!("Message {} expired, dropping", message.id);
+                return Ok(None);
+            }
+        }
+
+        // Reject payloads that do not match the topic's schema
+        self.validate_payload(&message).await?;
+
+        Ok(Some(message))
+    }
+
+    /// Register a JSON Schema that payloads published to a topic must satisfy
+    pub async fn register_schema(&self, topic: &str, schema: serde_json::Value) -> KernelResult<()> {
+        let validator = JSONSchema::compile(&schema)
+            .map_err(|e| KernelError::config_error(format!("Invalid schema for topic '{}': {}", topic, e)))?;
+
+        self.schemas.write().await.insert(topic.to_string(), Arc::new(TopicSchema { schema, validator }));
+
+        tracing::info!("Registered payload schema for topic '{}'", topic);
+        Ok(())
+    }
+
+    /// Remove a topic's schema, returning it if one was registered
+    pub async fn unregister_schema(&self, topic: &str) -> Option<serde_json::Value> {
+        self.schemas.write().await.remove(topic).map(|entry| entry.schema.clone())
+    }
+
+    /// Get the schema registered for a topic
+    pub async fn get_schema(&self, topic: &str) -> Option<serde_json::Value> {
+        self.schemas.read().await.get(topic).map(|entry| entry.schema.clone())
+    }
+
+    /// Validate a message payload against its topic's schema, if any
+    async fn validate_payload(&self, message: &Message) -> KernelResult<()> {
+        let Some(entry) = self.schemas.read().await.get(&message.topic).cloned() else {
+            return Ok(());
+        };
+
+        if let Err(errors) = entry.validator.validate(&message.payload) {
+            let errors: Vec<String> = errors
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    if path.is_empty() { e.to_string() } else { format!("{}: {}", path, e) }
+                })
+                .collect();
+
+            self.schema_rejections.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("Rejected message {} on '{}': {}", message.id, message.topic, errors.join("; "));
+            return Err(KernelError::schema_validation_error(message.topic.clone(), errors));
+        }
+
+        Ok(())
+    }
+
     /// Subscribe to topics with a handler
     pub async fn subscribe(
         &self,
@@ -207,14 +602,25 @@ impl MessageBus {
         handler: Arc<dyn MessageHandler>,
         options: SubscriptionOptions,
     ) -> KernelResult<()> {
+        let (deliveries, queue) = mpsc::unbounded_channel();
+        tokio::spawn(Self::run_delivery_worker(
+            subscriber_id.clone(),
+            Arc::clone(&handler),
+            options.clone(),
+            queue,
+            Arc::clone(&self.dead_letters),
+            self.max_history_size,
+            Arc::clone(&self.clock),
+        ));
+
         let subscription = Subscription {
             id: subscriber_id.clone(),
             topics: topics.clone(),
-            handler,
             options,
+            deliveries,
         };
 
-        // Store subscription
+        // Store subscription; replacing one closes its worker's queue
         self.subscriptions.write().await.insert(subscriber_id.clone(), subscription);
 
         // Subscribe to topics
@@ -227,9 +633,51 @@ impl MessageBus {
         Ok(())
     }
 
+    /// Subscribe to a topic with a handler receiving payloads deserialized into `T`
+    ///
+    /// Messages whose payload does not deserialize into `T` are skipped and
+    /// counted (see `typed_deserialization_failures`).
+    pub async fn subscribe_typed<T, H>(
+        &self,
+        subscriber_id: String,
+        topic: impl Into<String>,
+        handler: H,
+    ) -> KernelResult<()>
+    where
+        T: DeserializeOwned + Send + 'static,
+        H: TypedMessageHandler<T> + 'static,
+    {
+        let failures = Arc::new(AtomicU64::new(0));
+        let adapter = TypedHandlerAdapter {
+            handler,
+            failures: Arc::clone(&failures),
+            _payload: PhantomData,
+        };
+
+        self.subscribe(
+            subscriber_id.clone(),
+            vec![topic.into()],
+            Arc::new(adapter),
+            SubscriptionOptions::default(),
+        ).await?;
+
+        self.typed_failures.write().await.insert(subscriber_id, failures);
+        Ok(())
+    }
+
+    /// Number of messages a typed subscriber skipped because they did not deserialize
+    pub async fn typed_deserialization_failures(&self, subscriber_id: &str) -> u64 {
+        self.typed_failures
+            .read()
+            .await
+            .get(subscriber_id)
+            .map_or(0, |failures| failures.load(Ordering::Relaxed))
+    }
+
     /// Unsubscribe from topics
     pub async fn unsubscribe(&self, subscriber_id: &str) -> KernelResult<()> {
         let mut subscriptions = self.subscriptions.write().await;
+        self.typed_failures.write().await.remove(subscriber_id);
 
         if subscriptions.remove(subscriber_id).is_some() {
             tracing::info!("Subscriber '{}' unsubscribed", subscriber_id);
@@ -261,6 +709,16 @@ impl MessageBus {
         self.publish(message).await
     }
 
+    /// Wait until the message processor dispatches a topic's messages to subscribers
+    ///
+    /// The processor picks up topics created by subscriptions periodically,
+    /// so messages published before then only reach the history.
+    pub async fn wait_until_dispatching(&self, topic: &str) {
+        let mut dispatching = self.dispatching.subscribe();
+        // The sender lives as long as the bus, so this cannot fail
+        let _ = dispatching.wait_for(|topics| topics.contains(topic)).await;
+    }
+
     /// Request-Response pattern
     pub async fn request(
         &self,
@@ -308,6 +766,16 @@ impl MessageBus {
         history.iter().rev().take(limit).cloned().collect()
     }
 
+    /// Messages that exhausted their delivery attempts, oldest first
+    pub async fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.read().await.iter().cloned().collect()
+    }
+
+    /// Remove and return the dead-lettered messages, e.g. to republish them
+    pub async fn drain_dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.write().await.drain(..).collect()
+    }
+
     /// Get message statistics
     pub async fn get_stats(&self) -> HashMap<String, serde_json::Value> {
         let topics = self.topics.read().await;
@@ -321,6 +789,18 @@ impl MessageBus {
         stats.insert("queued_messages".to_string(), serde_json::json!(message_queue.len()));
         stats.insert("history_size".to_string(), serde_json::json!(history.len()));
 
+        let typed_failures: u64 = self.typed_failures
+            .read()
+            .await
+            .values()
+            .map(|failures| failures.load(Ordering::Relaxed))
+            .sum();
+        stats.insert("typed_deserialization_failures".to_string(), serde_json::json!(typed_failures));
+        stats.insert("schemas".to_string(), serde_json::json!(self.schemas.read().await.len()));
+        stats.insert("schema_rejections".to_string(), serde_json::json!(self.schema_rejections.load(Ordering::Relaxed)));
+        stats.insert("dead_letters".to_string(), serde_json::json!(self.dead_letters.read().await.len()));
+        stats.insert("acl_denials".to_string(), serde_json::json!(self.acl_denials.load(Ordering::Relaxed)));
+
         stats
     }
 
@@ -331,7 +811,7 @@ impl MessageBus {
         if let Some(sender) = topics.get(topic) {
             sender.clone()
         } else {
-            let (sender, _) = broadcast::channel(100); // Buffer size
+            let (sender, _) = broadcast::channel(self.buffer_size);
             topics.insert(topic.to_string(), sender.clone());
             sender
         }
@@ -378,8 +858,13 @@ impl MessageBus {
         let topics = Arc::clone(&self.topics);
         let subscriptions = Arc::clone(&self.subscriptions);
         let running = Arc::clone(&self.running);
+        let dispatching = Arc::clone(&self.dispatching);
+        let room = Arc::clone(&self.room);
 
         tokio::spawn(async move {
+            // Topics that already have a dispatch task
+            let mut dispatched_topics = HashSet::new();
+
             loop {
                 // Check if still running
                 {
@@ -396,6 +881,10 @@ impl MessageBus {
                 };
 
                 for topic_name in topic_names {
+                    if !dispatched_topics.insert(topic_name.clone()) {
+                        continue;
+                    }
+
                     let sender = {
                         let topics_read = topics.read().await;
                         topics_read.get(&topic_name).cloned()
@@ -404,9 +893,22 @@ impl MessageBus {
                     if let Some(sender) = sender {
                         let mut receiver = sender.subscribe();
                         let subscriptions_clone = Arc::clone(&subscriptions);
+                        let room = Arc::clone(&room);
+                        dispatching.send_if_modified(|topics| topics.insert(topic_name.clone()));
 
                         tokio::spawn(async move {
-                            while let Ok(message) = receiver.recv().await {
+                            loop {
+                                let received = receiver.recv().await;
+                                room.notify_waiters();
+                                let message = match received {
+                                    Ok(message) => message,
+                                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                                        tracing::warn!("Dispatch of '{}' lagged behind and skipped {} messages", topic_name, skipped);
+                                        continue;
+                                    }
+                                    Err(broadcast::error::RecvError::Closed) => break,
+                                };
+
                                 // Find matching subscriptions
                                 let subscriptions_read = subscriptions_clone.read().await;
                                 let matching_subs: Vec<_> = subscriptions_read
@@ -417,25 +919,11 @@ impl MessageBus {
                                     })
                                     .collect();
 
-                                // Send to matching subscribers
+                                // Queue for each matching subscriber's delivery worker
                                 for sub in matching_subs {
-                                    let handler = sub.handler.clone();
-                                    let message = message.clone();
-
-                                    tokio::spawn(async move {
-                                        match handler.handle_message(&message).await {
-                                            Ok(Some(_response)) => {
-                                                // Handle response if needed
-                                                tracing::debug!("Handler processed message {}", message.id);
-                                            }
-                                            Ok(None) => {
-                                                tracing::debug!("Handler ignored message {}", message.id);
-                                            }
-                                            Err(e) => {
-                                                tracing::error!("Handler error for message {}: {}", message.id, e);
-                                            }
-                                        }
-                                    });
+                                    if sub.deliveries.send(message.clone()).is_err() {
+                                        tracing::warn!("Delivery worker of '{}' stopped; message {} dropped", sub.id, message.id);
+                                    }
                                 }
                             }
                         });
@@ -448,6 +936,127 @@ impl MessageBus {
         });
     }
 
+    /// Deliver a subscription's queued messages
+    ///
+    /// Messages start in queue order, at most `max_concurrent` at a time;
+    /// the worker stops once the subscription is dropped.
+    async fn run_delivery_worker(
+        subscriber_id: String,
+        handler: Arc<dyn MessageHandler>,
+        options: SubscriptionOptions,
+        mut queue: mpsc::UnboundedReceiver<Message>,
+        dead_letters: Arc<RwLock<VecDeque<DeadLetter>>>,
+        max_dead_letters: usize,
+        clock: Arc<dyn Clock>,
+    ) {
+        let permits = Arc::new(Semaphore::new(options.max_concurrent.max(1)));
+        while let Some(message) = queue.recv().await {
+            let Ok(permit) = Arc::clone(&permits).acquire_owned().await else {
+                break;
+            };
+            let handler = Arc::clone(&handler);
+
+            if options.auto_ack {
+                tokio::spawn(async move {
+                    match handler.handle_message(&message).await {
+                        Ok(Some(_response)) => {
+                            // Handle response if needed
+                            tracing::debug!("Handler processed message {}", message.id);
+                        }
+                        Ok(None) => {
+                            tracing::debug!("Handler ignored message {}", message.id);
+                        }
+                        Err(e) => {
+                            tracing::error!("Handler error for message {}: {}", message.id, e);
+                        }
+                    }
+                    drop(permit);
+                });
+                continue;
+            }
+
+            let delivery = Self::deliver_until_acked(
+                subscriber_id.clone(),
+                handler,
+                message,
+                options.clone(),
+                Arc::clone(&dead_letters),
+                max_dead_letters,
+                Arc::clone(&clock),
+            );
+            tokio::spawn(async move {
+                delivery.await;
+                drop(permit);
+            });
+        }
+    }
+
+    /// Deliver a message to a manual-ack subscriber until it is acked
+    ///
+    /// Errors, panics and timeouts of the handler count as nacks. Once
+    /// `max_delivery_attempts` deliveries were nacked the message is moved
+    /// to the dead-letter queue.
+    async fn deliver_until_acked(
+        subscriber_id: String,
+        handler: Arc<dyn MessageHandler>,
+        mut message: Message,
+        options: SubscriptionOptions,
+        dead_letters: Arc<RwLock<VecDeque<DeadLetter>>>,
+        max_dead_letters: usize,
+        clock: Arc<dyn Clock>,
+    ) {
+        let max_attempts = options.max_delivery_attempts.max(1);
+        let timeout = std::time::Duration::from_secs(options.timeout as u64);
+        let mut last_error = String::new();
+
+        for attempt in 1..=max_attempts {
+            message.headers.insert(DELIVERY_ATTEMPT_HEADER.to_string(), attempt.to_string());
+
+            // Run the handler on its own task so a panic is caught as a nack
+            let delivery = {
+                let handler = Arc::clone(&handler);
+                let message = message.clone();
+                tokio::spawn(async move { handler.handle_with_ack(&message).await })
+            };
+            let abort = delivery.abort_handle();
+
+            last_error = match tokio::time::timeout(timeout, delivery).await {
+                Ok(Ok(Ok(Acknowledgement::Ack))) => {
+                    tracing::debug!("Subscriber '{}' acked message {} on attempt {}", subscriber_id, message.id, attempt);
+                    return;
+                }
+                Ok(Ok(Ok(Acknowledgement::Nack))) => "nacked by handler".to_string(),
+                Ok(Ok(Err(e))) => e.to_string(),
+                Ok(Err(e)) => format!("handler panicked: {}", e),
+                Err(_) => {
+                    abort.abort();
+                    format!("handler timed out after {}s", options.timeout)
+                }
+            };
+            tracing::warn!(
+                "Delivery {}/{} of message {} to '{}' failed: {}",
+                attempt, max_attempts, message.id, subscriber_id, last_error
+            );
+
+            if attempt < max_attempts {
+                clock.sleep(chrono::Duration::milliseconds(options.redelivery_delay_ms as i64)).await;
+            }
+        }
+
+        tracing::error!("Message {} dead-lettered for '{}' after {} attempts", message.id, subscriber_id, max_attempts);
+        let mut dead_letters = dead_letters.write().await;
+        dead_letters.push_back(DeadLetter {
+            message,
+            subscriber_id,
+            attempts: max_attempts,
+            last_error,
+            failed_at: clock.now(),
+        });
+        if dead_letters.len() > max_dead_letters {
+            dead_letters.pop_front();
+        }
+    }
+
     /// Static version of topic_matches for use in async contexts
     fn topic_matches_static(pattern: &str, topic: &str) -> bool {
         if pattern == topic {
@@ -475,6 +1084,55 @@ impl MessageBus {
     }
 }
 
+/// Message bus access bound to an identity
+///
+/// Publishes and subscriptions made through a handle are checked against the
+/// bus's [`TopicAcl`] as its identity, and published messages carry that
+/// identity as sender whatever sender they were built with.
+#[derive(Clone)]
+pub struct BusHandle {
+    bus: Arc<MessageBus>,
+    identity: String,
+}
+
+impl BusHandle {
+    /// Identity the handle publishes and subscribes as
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    /// Publish a message to a topic the identity may publish to
+    pub async fn publish(&self, mut message: Message) -> KernelResult<()> {
+        self.bus.authorize(&self.identity, TopicAccess::Publish, &message.topic).await?;
+        message.sender = Some(self.identity.clone());
+        self.bus.publish(message).await
+    }
+
+    /// Publish several messages as by [`MessageBus::publish_batch`]
+    ///
+    /// Nothing is delivered unless the identity may publish every message.
+    pub async fn publish_batch(&self, messages: Vec<Message>) -> KernelResult<Vec<KernelResult<()>>> {
+        let messages = messages.into_iter()
+            .map(|message| Message { sender: Some(self.identity.clone()), ..message })
+            .collect();
+        self.bus.publish_batch_as(Some(&self.identity), messages).await
+    }
+
+    /// Subscribe a handler to topics the identity may subscribe to
+    pub async fn subscribe(
+        &self,
+        subscriber_id: String,
+        topics: Vec<String>,
+        handler: Arc<dyn MessageHandler>,
+        options: SubscriptionOptions,
+    ) -> KernelResult<()> {
+        for topic in &topics {
+            self.bus.authorize(&self.identity, TopicAccess::Subscribe, topic).await?;
+        }
+        self.bus.subscribe(subscriber_id, topics, handler, options).await
+    }
+}
+
 /// Request-response handler for the request() method
 struct RequestResponseHandler {
     sender: mpsc::Sender<Message>,
@@ -519,3 +1177,370 @@ macro_rules! subscribe_messages {
         ).await
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::service::ServiceEvent;
+
+    struct ServiceEventCollector {
+        sender: mpsc::Sender<ServiceEvent>,
+    }
+
+    #[async_trait]
+    impl TypedMessageHandler<ServiceEvent> for ServiceEventCollector {
+        async fn handle_typed(&self, payload: ServiceEvent, _message: &Message) -> KernelResult<Option<Message>> {
+            self.sender.send(payload).await
+                .map_err(|_| KernelError::message_bus_error("Collector closed"))?;
+            Ok(None)
+        }
+    }
+
+    fn message(topic: &str, payload: serde_json::Value) -> Message {
+        Message {
+            id: String::new(),
+            topic: topic.to_string(),
+            payload,
+            timestamp: Utc::now(),
+            headers: HashMap::new(),
+            priority: MessagePriority::Normal,
+            ttl: 0,
+            sender: None,
+            recipients: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_typed_receives_service_events() {
+        let bus = MessageBus::new();
+        bus.start().await.unwrap();
+
+        let (tx, mut rx) = mpsc::channel(4);
+        bus.subscribe_typed("typed".to_string(), "service.events", ServiceEventCollector { sender: tx })
+            .await
+            .unwrap();
+
+        bus.wait_until_dispatching("service.events").await;
+
+        let timestamp = Utc::now();
+        bus.publish(message("service.events", serde_json::json!({"unexpected": true}))).await.unwrap();
+        let event = ServiceEvent::ServiceHeartbeat { service_id: "svc-1".to_string(), timestamp };
+        bus.publish(message("service.events", serde_json::to_value(&event).unwrap())).await.unwrap();
+
+        let received = tokio::time::timeout(std::time::Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        match received {
+            ServiceEvent::ServiceHeartbeat { service_id, timestamp: received_at } => {
+                assert_eq!(service_id, "svc-1");
+                assert_eq!(received_at, timestamp);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // The mismatched payload is counted rather than delivered
+        for _ in 0..20 {
+            if bus.typed_deserialization_failures("typed").await == 1 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(bus.typed_deserialization_failures("typed").await, 1);
+        assert!(rx.try_recv().is_err());
+
+        bus.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_publish_validates_against_topic_schema() {
+        let bus = MessageBus::new();
+        bus.register_schema("orders.created", serde_json::json!({
+            "type": "object",
+            "required": ["order_id", "amount"],
+            "properties": {
+                "order_id": {"type": "string"},
+                "amount": {"type": "number", "minimum": 0}
+            }
+        })).await.unwrap();
+        let mut created = bus.get_or_create_topic("orders.created").await.subscribe();
+        let _updated = bus.get_or_create_topic("orders.updated").await.subscribe();
+
+        bus.publish(message("orders.created", serde_json::json!({"order_id": "o-1", "amount": 12.5}))).await.unwrap();
+
+        let err = bus.publish(message("orders.created", serde_json::json!({"order_id": 7, "amount": -1})))
+            .await
+            .unwrap_err();
+        match err {
+            KernelError::SchemaValidationError { topic, errors } => {
+                assert_eq!(topic, "orders.created");
+                assert_eq!(errors.len(), 2);
+                assert!(errors.iter().any(|e| e.starts_with("/order_id")));
+                assert!(errors.iter().any(|e| e.starts_with("/amount")));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // Only the conforming message reaches subscribers, and other topics are unaffected
+        assert_eq!(created.try_recv().unwrap().payload["order_id"], "o-1");
+        assert!(created.try_recv().is_err());
+        bus.publish(message("orders.updated", serde_json::json!("anything"))).await.unwrap();
+        assert_eq!(bus.get_history(10).await.len(), 2);
+
+        let stats = bus.get_stats().await;
+        assert_eq!(stats["schema_rejections"], serde_json::json!(1));
+
+        assert!(bus.register_schema("bad", serde_json::json!({"type": 12})).await.is_err());
+        assert!(bus.unregister_schema("orders.created").await.is_some());
+        bus.publish(message("orders.created", serde_json::json!({}))).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_acl_allows_permitted_publish() {
+        let bus = Arc::new(MessageBus::new());
+        bus.set_acl(Some(TopicAcl::new().allow_publish("billing", "invoices.*"))).await;
+        let mut invoices = bus.get_or_create_topic("invoices.created").await.subscribe();
+        let mut events = bus.get_or_create_topic("service.events").await.subscribe();
+
+        bus.handle("billing").publish(message("invoices.created", serde_json::json!({"invoice_id": "i-1"}))).await.unwrap();
+        assert_eq!(invoices.try_recv().unwrap().payload["invoice_id"], "i-1");
+
+        // The kernel publishing on the bus itself is not restricted
+        bus.publish(message("service.events", serde_json::json!("registered"))).await.unwrap();
+        assert_eq!(events.try_recv().unwrap().payload, "registered");
+        assert_eq!(bus.get_stats().await["acl_denials"], serde_json::json!(0));
+    }
+
+    #[tokio::test]
+    async fn test_acl_denies_and_audits_unpermitted_publish() {
+        let bus = Arc::new(MessageBus::new());
+        bus.set_acl(Some(TopicAcl::new().allow_publish("billing", "invoices.*").allow_subscribe("auditor", "invoices.*"))).await;
+        let mut audit = bus.get_or_create_topic(ACL_AUDIT_TOPIC).await.subscribe();
+        let mut invoices = bus.get_or_create_topic("invoices.created").await.subscribe();
+
+        // A handle's identity cannot be changed by the sender the message claims
+        let mut forged = message("invoices.created", serde_json::json!({"invoice_id": "i-2"}));
+        forged.sender = Some("billing".to_string());
+        match bus.handle("weather-plugin").publish(forged).await.unwrap_err() {
+            KernelError::TopicAccessDenied { identity, topic, action } => {
+                assert_eq!(identity, "weather-plugin");
+                assert_eq!(topic, "invoices.created");
+                assert_eq!(action, "publish");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert!(invoices.try_recv().is_err());
+
+        let event = audit.try_recv().unwrap();
+        assert_eq!(event.payload, serde_json::json!({"identity": "weather-plugin", "action": "publish", "topic": "invoices.created"}));
+
+        // Batches are checked as a whole, and subscriptions against the handle's identity
+        let batch = vec![message("invoices.created", serde_json::json!({})), message("payroll", serde_json::json!({}))];
+        assert!(bus.handle("billing").publish_batch(batch).await.is_err());
+        assert!(invoices.try_recv().is_err());
+        let auditor = bus.handle("auditor");
+        let handler = Arc::new(RequestResponseHandler { sender: mpsc::channel(1).0 });
+        assert!(auditor.subscribe("audit-log".to_string(), vec!["invoices.*".to_string()], handler.clone(), SubscriptionOptions::default()).await.is_ok());
+        assert!(auditor.subscribe("audit-log".to_string(), vec!["payroll".to_string()], handler, SubscriptionOptions::default()).await.is_err());
+        assert_eq!(bus.get_stats().await["acl_denials"], serde_json::json!(3));
+    }
+
+    #[tokio::test]
+    async fn test_publish_drops_messages_past_ttl() {
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let bus = MessageBus::new().with_clock(clock.clone());
+        let mut jobs = bus.get_or_create_topic("jobs").await.subscribe();
+
+        let mut expiring = message("jobs", serde_json::json!("stale"));
+        expiring.timestamp = clock.now();
+        expiring.ttl = 30;
+        bus.publish(expiring.clone()).await.unwrap();
+
+        clock.advance(chrono::Duration::seconds(31));
+        bus.publish(expiring).await.unwrap();
+
+        assert_eq!(jobs.try_recv().unwrap().payload, "stale");
+        assert!(jobs.try_recv().is_err());
+        assert_eq!(bus.get_history(10).await.len(), 1);
+    }
+
+    /// Handler recording the payload of every message it receives
+    struct RecordingHandler {
+        received: Arc<std::sync::Mutex<Vec<serde_json::Value>>>,
+    }
+
+    #[async_trait]
+    impl MessageHandler for RecordingHandler {
+        async fn handle_message(&self, message: &Message) -> KernelResult<Option<Message>> {
+            self.received.lock().unwrap().push(message.payload.clone());
+            Ok(None)
+        }
+    }
+
+    /// Wait until a recording handler has received `count` messages
+    async fn wait_for_received(received: &std::sync::Mutex<Vec<serde_json::Value>>, count: usize) {
+        tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while received.lock().unwrap().len() < count {
+                tokio::task::yield_now().await;
+            }
+        }).await.expect("messages were not delivered");
+    }
+
+    #[tokio::test]
+    async fn test_publish_batch_delivers_across_topics() {
+        let bus = MessageBus::new();
+        bus.start().await.unwrap();
+
+        let mut received = HashMap::new();
+        for (id, topic) in [("orders-sub", "orders"), ("audit-sub", "audit"), ("all-sub", "*")] {
+            let log = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let handler = Arc::new(RecordingHandler { received: log.clone() });
+            bus.subscribe(id.to_string(), vec![topic.to_string()], handler, SubscriptionOptions::default()).await.unwrap();
+            received.insert(id, log);
+        }
+
+        for topic in ["orders", "audit", "*"] {
+            bus.wait_until_dispatching(topic).await;
+        }
+        let results = bus.publish_batch(vec![
+            message("orders", serde_json::json!({"order": 1})),
+            message("audit", serde_json::json!({"event": "created"})),
+            message("orders", serde_json::json!({"order": 2})),
+        ]).await.unwrap();
+        assert!(results.iter().all(Result::is_ok));
+        for (id, count) in [("orders-sub", 2), ("audit-sub", 1), ("all-sub", 3)] {
+            wait_for_received(&received[id], count).await;
+        }
+
+        let payloads = |id: &str| received[id].lock().unwrap().clone();
+        assert_eq!(payloads("orders-sub"), vec![serde_json::json!({"order": 1}), serde_json::json!({"order": 2})]);
+        assert_eq!(payloads("audit-sub"), vec![serde_json::json!({"event": "created"})]);
+        assert_eq!(payloads("all-sub").len(), 3);
+        assert_eq!(bus.get_history(10).await.len(), 3);
+
+        bus.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_publish_batch_larger_than_the_topic_buffer_loses_nothing() {
+        let bus = MessageBus::new().with_buffer_size(16);
+        bus.start().await.unwrap();
+
+        let log = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let handler = Arc::new(RecordingHandler { received: log.clone() });
+        bus.subscribe("metrics-sub".to_string(), vec!["metrics".to_string()], handler, SubscriptionOptions::default()).await.unwrap();
+        bus.wait_until_dispatching("metrics").await;
+
+        let batch = (0..100).map(|n| message("metrics", serde_json::json!(n))).collect();
+        let results = bus.publish_batch(batch).await.unwrap();
+        assert_eq!(results.len(), 100);
+        assert!(results.iter().all(Result::is_ok));
+        wait_for_received(&log, 100).await;
+
+        let mut received: Vec<i64> = log.lock().unwrap().iter().map(|n| n.as_i64().unwrap()).collect();
+        received.sort_unstable();
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+
+        bus.stop().await.unwrap();
+    }
+
+    /// Handler that nacks until a given attempt, then acks; records the job and attempt of each delivery
+    struct FlakyHandler {
+        ack_on_attempt: Option<u32>,
+        attempts: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl MessageHandler for FlakyHandler {
+        async fn handle_message(&self, _message: &Message) -> KernelResult<Option<Message>> {
+            Ok(None)
+        }
+
+        async fn handle_with_ack(&self, message: &Message) -> KernelResult<Acknowledgement> {
+            let attempt = message.headers[DELIVERY_ATTEMPT_HEADER].clone();
+            self.attempts.lock().unwrap().push(format!("{}#{}", message.payload["job"], attempt));
+            match self.ack_on_attempt {
+                Some(ack_on) if attempt.parse::<u32>().unwrap() >= ack_on => Ok(Acknowledgement::Ack),
+                Some(_) => Ok(Acknowledgement::Nack),
+                None => Err(KernelError::message_bus_error("handler crashed")),
+            }
+        }
+    }
+
+    /// Start a bus on a mock clock with a manual-ack subscriber on "jobs"
+    async fn manual_ack_bus(ack_on_attempt: Option<u32>, max_concurrent: usize) -> (MessageBus, Arc<MockClock>, Arc<std::sync::Mutex<Vec<String>>>) {
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let bus = MessageBus::new().with_clock(clock.clone());
+        bus.start().await.unwrap();
+
+        let attempts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let handler = FlakyHandler { ack_on_attempt, attempts: attempts.clone() };
+        let options = SubscriptionOptions {
+            max_concurrent,
+            auto_ack: false,
+            max_delivery_attempts: 3,
+            // Only the mock clock can make this pass
+            redelivery_delay_ms: 60_000,
+            ..Default::default()
+        };
+        bus.subscribe("worker".to_string(), vec!["jobs".to_string()], Arc::new(handler), options).await.unwrap();
+        bus.wait_until_dispatching("jobs").await;
+        (bus, clock, attempts)
+    }
+
+    /// Keep moving the clock past redelivery delays until `attempts` reaches `count`
+    async fn advance_until(clock: &MockClock, attempts: &std::sync::Mutex<Vec<String>>, count: usize) {
+        tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while attempts.lock().unwrap().len() < count {
+                clock.advance(chrono::Duration::seconds(60));
+                tokio::task::yield_now().await;
+            }
+        }).await.expect("redeliveries did not happen");
+    }
+
+    #[tokio::test]
+    async fn test_manual_ack_redelivers_until_acked() {
+        let (bus, clock, attempts) = manual_ack_bus(Some(3), 10).await;
+        bus.publish(message("jobs", serde_json::json!({"job": 1}))).await.unwrap();
+
+        advance_until(&clock, &attempts, 3).await;
+        assert_eq!(*attempts.lock().unwrap(), ["1#1", "1#2", "1#3"]);
+        assert!(bus.dead_letters().await.is_empty());
+        bus.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_manual_ack_failures_are_dead_lettered() {
+        let (bus, clock, attempts) = manual_ack_bus(None, 10).await;
+        bus.publish(message("jobs", serde_json::json!({"job": 2}))).await.unwrap();
+
+        advance_until(&clock, &attempts, 3).await;
+        tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while bus.dead_letters().await.is_empty() {
+                tokio::task::yield_now().await;
+            }
+        }).await.unwrap();
+
+        assert_eq!(attempts.lock().unwrap().len(), 3);
+        let dead_letters = bus.drain_dead_letters().await;
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].subscriber_id, "worker");
+        assert_eq!(dead_letters[0].attempts, 3);
+        assert_eq!(dead_letters[0].message.payload["job"], 2);
+        assert!(dead_letters[0].last_error.contains("handler crashed"));
+        assert!(bus.dead_letters().await.is_empty());
+        bus.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_one_at_a_time_redeliveries_finish_before_the_next_message() {
+        let (bus, clock, attempts) = manual_ack_bus(Some(2), 1).await;
+        bus.publish(message("jobs", serde_json::json!({"job": 1}))).await.unwrap();
+        bus.publish(message("jobs", serde_json::json!({"job": 2}))).await.unwrap();
+
+        advance_until(&clock, &attempts, 4).await;
+        assert_eq!(*attempts.lock().unwrap(), ["1#1", "1#2", "2#1", "2#2"]);
+        bus.stop().await.unwrap();
+    }
+}
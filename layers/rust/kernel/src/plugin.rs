@@ -5,14 +5,14 @@
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::error::{KernelError, KernelResult};
 use crate::service::{Service, ServiceMetadata};
-use crate::message::{MessageBus, Message};
+use crate::message::{BusHandle, MessageBus, Message};
 use crate::resource::{ResourceManager, ResourceRequest};
 
 /// Plugin metadata
@@ -68,8 +68,8 @@ pub enum PluginState {
 pub struct PluginContext {
     /// Plugin metadata
     pub metadata: PluginMetadata,
-    /// Message bus for communication
-    pub message_bus: Arc<MessageBus>,
+    /// Message bus access as the plugin's ID, subject to the bus's topic ACL
+    pub message_bus: BusHandle,
     /// Resource manager
     pub resource_manager: Arc<ResourceManager>,
     /// Plugin-specific configuration
@@ -161,6 +161,81 @@ pub struct LoadedPlugin {
     pub registered_services: Vec<String>,
 }
 
+/// Plugins to load together; [`PluginManager::load_all`] orders them by dependency
+#[derive(Default)]
+pub struct PluginManifest {
+    entries: Vec<ManifestEntry>,
+}
+
+enum ManifestEntry {
+    Library(std::path::PathBuf),
+    Instance(Box<dyn Plugin>),
+}
+
+impl PluginManifest {
+    /// Create an empty manifest
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a plugin library to load from disk
+    pub fn with_library<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.entries.push(ManifestEntry::Library(path.into()));
+        self
+    }
+
+    /// Add an in-process plugin
+    pub fn with_plugin(mut self, plugin: Box<dyn Plugin>) -> Self {
+        self.entries.push(ManifestEntry::Instance(plugin));
+        self
+    }
+
+    /// Number of plugins in the manifest
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the manifest lists no plugins
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Plugin instance not yet registered with the manager
+struct PendingPlugin {
+    metadata: PluginMetadata,
+    instance: Box<dyn Plugin>,
+    library: Option<libloading::Library>,
+}
+
+impl PendingPlugin {
+    fn new(instance: Box<dyn Plugin>, library: Option<libloading::Library>) -> Self {
+        Self { metadata: instance.metadata(), instance, library }
+    }
+}
+
+/// Order plugins so each one comes after its dependencies
+///
+/// Returns the load order as indices into `plugins`, and the plugins that
+/// cannot be ordered because a dependency is missing or part of a cycle.
+fn dependency_order(plugins: &[&PluginMetadata], loaded: &HashSet<String>) -> (Vec<usize>, Vec<usize>) {
+    let mut available = loaded.clone();
+    let mut order = Vec::new();
+    let mut remaining: Vec<usize> = (0..plugins.len()).collect();
+
+    loop {
+        let (ready, blocked): (Vec<usize>, Vec<usize>) = remaining.iter()
+            .partition(|&&index| plugins[index].dependencies.iter().all(|dep| available.contains(dep)));
+        if ready.is_empty() {
+            return (order, blocked);
+        }
+
+        available.extend(ready.iter().map(|&index| plugins[index].id.clone()));
+        order.extend(ready);
+        remaining = blocked;
+    }
+}
+
 /// Plugin manager for loading and managing plugins
 pub struct PluginManager {
     /// Loaded plugins
@@ -224,6 +299,84 @@ impl PluginManager {
             ));
         }
 
+        let plugin = Self::open_library(path)?;
+        self.register_plugin(plugin).await
+    }
+
+    /// Register an in-process plugin, e.g. one linked into the binary
+    ///
+    /// Fails when a plugin it depends on is not loaded yet.
+    pub async fn add_plugin(&self, plugin: Box<dyn Plugin>) -> KernelResult<String> {
+        self.register_plugin(PendingPlugin::new(plugin, None)).await
+    }
+
+    /// Load every plugin of a manifest, dependencies first
+    ///
+    /// Dependencies may be in the manifest or already loaded. Nothing is
+    /// loaded when a plugin's dependencies cannot be satisfied, and if a
+    /// plugin fails to register, the ones loaded before it are unloaded
+    /// again. Returns the plugin IDs in the order they were loaded.
+    pub async fn load_all(&self, manifest: PluginManifest) -> KernelResult<Vec<String>> {
+        let mut pending = Vec::with_capacity(manifest.len());
+        for entry in manifest.entries {
+            pending.push(match entry {
+                ManifestEntry::Library(path) => Self::open_library(&path)?,
+                ManifestEntry::Instance(instance) => PendingPlugin::new(instance, None),
+            });
+        }
+
+        let loaded: HashSet<String> = self.plugins.read().await.keys().cloned().collect();
+        let metadata: Vec<&PluginMetadata> = pending.iter().map(|p| &p.metadata).collect();
+        let (order, unresolved) = dependency_order(&metadata, &loaded);
+
+        if let Some(&index) = unresolved.first() {
+            let plugin = metadata[index];
+            let known = |dep: &String| loaded.contains(dep) || metadata.iter().any(|m| &m.id == dep);
+            let missing: Vec<String> = plugin.dependencies.iter().filter(|dep| !known(dep)).cloned().collect();
+            // Without missing dependencies, the unresolved ones form a cycle
+            let dependencies = if missing.is_empty() {
+                plugin.dependencies.iter()
+                    .filter(|dep| unresolved.iter().any(|&i| &metadata[i].id == *dep))
+                    .cloned()
+                    .collect()
+            } else {
+                missing
+            };
+            return Err(KernelError::plugin_dependency_error(plugin.id.clone(), dependencies));
+        }
+
+        let mut pending: Vec<Option<PendingPlugin>> = pending.into_iter().map(Some).collect();
+        let mut plugin_ids = Vec::with_capacity(order.len());
+        for index in order {
+            let Some(plugin) = pending[index].take() else {
+                continue;
+            };
+            match self.register_plugin(plugin).await {
+                Ok(plugin_id) => plugin_ids.push(plugin_id),
+                Err(e) => {
+                    // Dependents go first, so unload in reverse order
+                    for plugin_id in plugin_ids.iter().rev() {
+                        if let Err(unload_error) = self.unload_plugin(plugin_id).await {
+                            tracing::warn!("Failed to roll back plugin '{}': {}", plugin_id, unload_error);
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(plugin_ids)
+    }
+
+    /// Open a plugin library and construct its plugin
+    fn open_library(path: &std::path::Path) -> KernelResult<PendingPlugin> {
+        let plugin_id = path.file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| KernelError::plugin_load_error(
+                path.display().to_string(),
+                "Invalid plugin filename"
+            ))?
+            .to_string();
+
         // Load the dynamic library
         let library = unsafe {
             libloading::Library::new(path)
@@ -245,21 +398,43 @@ impl PluginManager {
         // Create plugin instance
         let instance = constructor();
 
-        // Get plugin metadata
-        let metadata = instance.metadata();
-
         // Validate plugin ID matches filename
-        if metadata.id != plugin_id {
+        let plugin = PendingPlugin::new(instance, None);
+        if plugin.metadata.id != plugin_id {
             return Err(KernelError::plugin_load_error(
                 path.display().to_string(),
-                format!("Plugin ID '{}' does not match filename", metadata.id)
+                format!("Plugin ID '{}' does not match filename", plugin.metadata.id)
             ));
         }
 
+        Ok(PendingPlugin { library: Some(library), ..plugin })
+    }
+
+    /// Store a constructed plugin once its dependencies are loaded
+    async fn register_plugin(&self, plugin: PendingPlugin) -> KernelResult<String> {
+        let PendingPlugin { metadata, instance, library } = plugin;
+        let plugin_id = metadata.id.clone();
+        let mut plugins = self.plugins.write().await;
+
+        if plugins.contains_key(&plugin_id) {
+            return Err(KernelError::plugin_error(
+                plugin_id,
+                "Plugin already loaded".to_string()
+            ));
+        }
+
+        let missing: Vec<String> = metadata.dependencies.iter()
+            .filter(|dep| !plugins.contains_key(*dep))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(KernelError::plugin_dependency_error(plugin_id, missing));
+        }
+
         // Create plugin context
         let context = PluginContext {
             metadata: metadata.clone(),
-            message_bus: self.message_bus.clone(),
+            message_bus: self.message_bus.handle(plugin_id.clone()),
             resource_manager: self.resource_manager.clone(),
             config: serde_json::Value::Object(serde_json::Map::new()),
             kernel_state: self.kernel_state.clone(),
@@ -271,13 +446,13 @@ impl PluginManager {
             instance,
             state: PluginState::Loaded,
             context,
-            library: Some(library),
+            library,
             allocated_resources: Vec::new(),
             registered_services: Vec::new(),
         };
 
         // Store the plugin
-        self.plugins.write().await.insert(plugin_id.clone(), loaded_plugin);
+        plugins.insert(plugin_id.clone(), loaded_plugin);
 
         tracing::info!("Plugin '{}' loaded successfully", plugin_id);
         Ok(plugin_id)
@@ -407,8 +582,12 @@ impl PluginManager {
     }
 
     /// Discover and load plugins from plugin directories
+    ///
+    /// Plugins are loaded dependencies first; plugins that fail to load or
+    /// whose dependencies cannot be satisfied are skipped with a warning.
     pub async fn discover_plugins(&self) -> KernelResult<Vec<String>> {
         let mut loaded_plugins = Vec::new();
+        let mut pending = Vec::new();
 
         for dir in &self.plugin_dirs {
             if !dir.exists() {
@@ -430,10 +609,8 @@ impl PluginManager {
                    path.extension().and_then(|s| s.to_str()) == Some("dll") ||
                    path.extension().and_then(|s| s.to_str()) == Some("dylib") {
 
-                    match self.load_plugin(&path).await {
-                        Ok(plugin_id) => {
-                            loaded_plugins.push(plugin_id);
-                        }
+                    match Self::open_library(&path) {
+                        Ok(plugin) => pending.push(plugin),
                         Err(e) => {
                             tracing::warn!("Failed to load plugin '{}': {}", path.display(), e);
                         }
@@ -442,6 +619,26 @@ impl PluginManager {
             }
         }
 
+        let loaded: HashSet<String> = self.plugins.read().await.keys().cloned().collect();
+        let metadata: Vec<&PluginMetadata> = pending.iter().map(|p| &p.metadata).collect();
+        let (order, unresolved) = dependency_order(&metadata, &loaded);
+        for index in unresolved {
+            tracing::warn!(
+                "Skipping plugin '{}': unresolved dependencies {:?}",
+                metadata[index].id, metadata[index].dependencies
+            );
+        }
+
+        let mut pending: Vec<Option<PendingPlugin>> = pending.into_iter().map(Some).collect();
+        for index in order {
+            let Some(plugin) = pending[index].take() else { continue };
+            let plugin_id = plugin.metadata.id.clone();
+            match self.register_plugin(plugin).await {
+                Ok(plugin_id) => loaded_plugins.push(plugin_id),
+                Err(e) => tracing::warn!("Failed to load plugin '{}': {}", plugin_id, e),
+            }
+        }
+
         Ok(loaded_plugins)
     }
 }
@@ -478,3 +675,114 @@ macro_rules! plugin_metadata {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestPlugin {
+        id: &'static str,
+        dependencies: Vec<&'static str>,
+    }
+
+    #[async_trait]
+    impl Plugin for TestPlugin {
+        fn metadata(&self) -> PluginMetadata {
+            PluginMetadata {
+                id: self.id.to_string(),
+                name: self.id.to_string(),
+                version: "1.0.0".to_string(),
+                description: String::new(),
+                author: String::new(),
+                license: None,
+                dependencies: self.dependencies.iter().map(|dep| dep.to_string()).collect(),
+                services: vec![],
+                required_resources: vec![],
+                capabilities: vec![],
+                config_schema: None,
+            }
+        }
+
+        async fn initialize(&mut self, _context: &PluginContext) -> KernelResult<()> {
+            Ok(())
+        }
+
+        async fn start(&mut self, _context: &PluginContext) -> KernelResult<()> {
+            Ok(())
+        }
+
+        async fn stop(&mut self, _context: &PluginContext) -> KernelResult<()> {
+            Ok(())
+        }
+    }
+
+    fn plugin(id: &'static str, dependencies: &[&'static str]) -> Box<dyn Plugin> {
+        Box::new(TestPlugin { id, dependencies: dependencies.to_vec() })
+    }
+
+    fn manager() -> PluginManager {
+        PluginManager::new(
+            Arc::new(MessageBus::new()),
+            Arc::new(ResourceManager::new(crate::kernel::KernelConfig::default().resource_limits)),
+            Arc::new(RwLock::new(KernelState::default())),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_load_all_orders_plugins_by_dependency() {
+        let manager = manager();
+        let manifest = PluginManifest::new()
+            .with_plugin(plugin("api", &["auth", "storage"]))
+            .with_plugin(plugin("auth", &["storage"]))
+            .with_plugin(plugin("storage", &[]));
+
+        let loaded = manager.load_all(manifest).await.unwrap();
+        assert_eq!(loaded, vec!["storage", "auth", "api"]);
+
+        // Already loaded plugins satisfy later dependencies
+        manager.add_plugin(plugin("metrics", &["api"])).await.unwrap();
+        assert_eq!(manager.list_plugins().await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_missing_dependency_is_rejected() {
+        let manager = manager();
+        let manifest = PluginManifest::new()
+            .with_plugin(plugin("storage", &[]))
+            .with_plugin(plugin("api", &["storage", "auth"]));
+
+        let err = manager.load_all(manifest).await.unwrap_err();
+        assert!(matches!(
+            &err,
+            KernelError::PluginDependencyError { plugin_id, dependencies }
+                if plugin_id == "api" && dependencies == &vec!["auth".to_string()]
+        ));
+        assert!(err.to_string().contains("'api' has unresolved dependencies: auth"));
+        // Nothing from the manifest was loaded
+        assert!(manager.list_plugins().await.is_empty());
+
+        let err = manager.add_plugin(plugin("auth", &["users"])).await.unwrap_err();
+        assert!(matches!(err, KernelError::PluginDependencyError { .. }));
+
+        let cycle = PluginManifest::new()
+            .with_plugin(plugin("a", &["b"]))
+            .with_plugin(plugin("b", &["a"]));
+        assert!(matches!(manager.load_all(cycle).await, Err(KernelError::PluginDependencyError { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_failed_registration_rolls_back_the_manifest() {
+        let manager = manager();
+        manager.add_plugin(plugin("auth", &[])).await.unwrap();
+
+        // "auth" is already loaded, so it fails after "storage" was registered
+        let manifest = PluginManifest::new()
+            .with_plugin(plugin("storage", &[]))
+            .with_plugin(plugin("auth", &["storage"]));
+        let err = manager.load_all(manifest).await.unwrap_err();
+        assert!(err.to_string().contains("already loaded"));
+
+        let loaded: Vec<String> = manager.list_plugins().await.into_iter().map(|p| p.id).collect();
+        assert_eq!(loaded, vec!["auth"]);
+    }
+}
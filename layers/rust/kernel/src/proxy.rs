@@ -0,0 +1,251 @@
+//! Typed service proxies for plugin inter-communication
+//!
+//! A [`ServiceProxy`] wraps a registered service behind typed calls. It is
+//! built from the methods the service declares in its metadata: requests are
+//! serialized and checked against the method's request schema, dispatched
+//! through the [`ServiceRegistry`] under a timeout, and the response data is
+//! checked against the response schema before being deserialized.
+
+use chrono::Utc;
+use jsonschema::JSONSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::error::{KernelError, KernelResult};
+use crate::service::{ResponseStatus, ServiceRegistry, ServiceRequest};
+
+/// Default time allowed for a proxied call
+const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Compiled schemas of a declared method
+struct MethodSchemas {
+    request: Option<JSONSchema>,
+    response: Option<JSONSchema>,
+}
+
+/// Typed client for a registered service
+pub struct ServiceProxy {
+    registry: Arc<ServiceRegistry>,
+    service_id: String,
+    methods: HashMap<String, MethodSchemas>,
+    timeout: Duration,
+}
+
+impl ServiceProxy {
+    /// Build a proxy from the methods a registered service declares
+    pub async fn new(registry: Arc<ServiceRegistry>, service_id: &str) -> KernelResult<Self> {
+        let metadata = registry.get_service(service_id).await?;
+
+        let compile = |method: &str, schema: &Option<serde_json::Value>| {
+            schema.as_ref()
+                .map(|schema| JSONSchema::compile(schema).map_err(|e| KernelError::config_error(
+                    format!("Invalid schema for method '{}' of service '{}': {}", method, service_id, e)
+                )))
+                .transpose()
+        };
+
+        let mut methods = HashMap::new();
+        for method in &metadata.methods {
+            let schemas = MethodSchemas {
+                request: compile(&method.name, &method.request_schema)?,
+                response: compile(&method.name, &method.response_schema)?,
+            };
+            methods.insert(method.name.clone(), schemas);
+        }
+
+        Ok(Self {
+            registry,
+            service_id: service_id.to_string(),
+            methods,
+            timeout: DEFAULT_CALL_TIMEOUT,
+        })
+    }
+
+    /// Set the time allowed for each call
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// ID of the proxied service
+    pub fn service_id(&self) -> &str {
+        &self.service_id
+    }
+
+    /// Whether the service declares a method
+    pub fn has_method(&self, method: &str) -> bool {
+        self.methods.contains_key(method)
+    }
+
+    /// Call a declared method with a typed request and response
+    pub async fn call<Req, Resp>(&self, method: &str, request: &Req) -> KernelResult<Resp>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let schemas = self.methods.get(method).ok_or_else(|| KernelError::service_error(
+            self.service_id.clone(),
+            format!("Method '{}' is not declared", method),
+        ))?;
+
+        let params = serde_json::to_value(request).map_err(|e| KernelError::service_error(
+            self.service_id.clone(),
+            format!("Failed to serialize request for '{}': {}", method, e),
+        ))?;
+        self.validate(method, schemas.request.as_ref(), &params)?;
+
+        let request = ServiceRequest {
+            id: Uuid::new_v4().to_string(),
+            method: method.to_string(),
+            params,
+            headers: HashMap::new(),
+            timestamp: Utc::now(),
+            timeout: Some(self.timeout.as_secs().max(1) as u32),
+        };
+
+        let response = tokio::time::timeout(self.timeout, self.registry.call_service(&self.service_id, request))
+            .await
+            .map_err(|_| KernelError::communication_error(format!(
+                "Call to '{}' on service '{}' timed out after {:?}", method, self.service_id, self.timeout
+            )))??;
+
+        match response.status {
+            ResponseStatus::Success | ResponseStatus::Partial => {}
+            ResponseStatus::Timeout => {
+                return Err(KernelError::communication_error(format!(
+                    "Service '{}' timed out handling '{}'", self.service_id, method
+                )));
+            }
+            status => {
+                let detail = response.data.get("error")
+                    .and_then(|e| e.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| response.data.to_string());
+                return Err(KernelError::service_error(
+                    self.service_id.clone(),
+                    format!("Method '{}' returned {:?}: {}", method, status, detail),
+                ));
+            }
+        }
+
+        self.validate(method, schemas.response.as_ref(), &response.data)?;
+        serde_json::from_value(response.data).map_err(|e| KernelError::service_error(
+            self.service_id.clone(),
+            format!("Failed to deserialize response of '{}': {}", method, e),
+        ))
+    }
+
+    /// Check a payload against a method schema, if one is declared
+    fn validate(&self, method: &str, schema: Option<&JSONSchema>, payload: &serde_json::Value) -> KernelResult<()> {
+        let Some(schema) = schema else {
+            return Ok(());
+        };
+
+        if let Err(errors) = schema.validate(payload) {
+            let errors: Vec<String> = errors
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    if path.is_empty() { e.to_string() } else { format!("{}: {}", path, e) }
+                })
+                .collect();
+            return Err(KernelError::schema_validation_error(format!("{}.{}", self.service_id, method), errors));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::MessageBus;
+    use crate::service::{Service, ServiceMetadata, ServiceMethod, ServiceResponse, ServiceStatus};
+    use async_trait::async_trait;
+    use serde::Deserialize;
+
+    #[derive(Serialize)]
+    struct AddRequest {
+        a: i64,
+        b: i64,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct AddResponse {
+        sum: i64,
+    }
+
+    struct Calculator;
+
+    #[async_trait]
+    impl Service for Calculator {
+        fn metadata(&self) -> ServiceMetadata {
+            ServiceMetadata::new("calculator", "Calculator")
+                .with_description("Adds numbers")
+                .with_method(ServiceMethod::new("add").with_request_schema(serde_json::json!({
+                    "type": "object",
+                    "required": ["a", "b"],
+                    "properties": { "a": { "type": "integer" }, "b": { "type": "integer" } }
+                })))
+                .with_method(ServiceMethod::new("sleep"))
+        }
+
+        async fn start(&self) -> KernelResult<()> {
+            Ok(())
+        }
+
+        async fn stop(&self) -> KernelResult<()> {
+            Ok(())
+        }
+
+        async fn health(&self) -> KernelResult<ServiceStatus> {
+            Ok(ServiceStatus::Healthy)
+        }
+
+        async fn handle_request(&self, request: ServiceRequest) -> KernelResult<ServiceResponse> {
+            let data = match request.method.as_str() {
+                "add" => serde_json::json!({
+                    "sum": request.params["a"].as_i64().unwrap_or(0) + request.params["b"].as_i64().unwrap_or(0)
+                }),
+                _ => {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    serde_json::Value::Null
+                }
+            };
+
+            Ok(ServiceResponse {
+                id: request.id,
+                status: ResponseStatus::Success,
+                data,
+                headers: HashMap::new(),
+                timestamp: Utc::now(),
+                processing_time_ms: 0,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_proxy_calls_service_through_registry() {
+        let registry = Arc::new(ServiceRegistry::new(Arc::new(MessageBus::new())));
+        registry.register_service(Arc::new(Calculator), serde_json::Value::Null).await.unwrap();
+
+        let proxy = ServiceProxy::new(registry, "calculator").await.unwrap()
+            .with_timeout(Duration::from_millis(50));
+
+        let response: AddResponse = proxy.call("add", &AddRequest { a: 2, b: 3 }).await.unwrap();
+        assert_eq!(response, AddResponse { sum: 5 });
+
+        // Requests are checked against the declared schema before dispatch
+        let invalid = proxy.call::<_, AddResponse>("add", &serde_json::json!({ "a": "two", "b": 3 })).await;
+        assert!(matches!(invalid, Err(KernelError::SchemaValidationError { .. })));
+
+        let undeclared = proxy.call::<_, AddResponse>("divide", &AddRequest { a: 1, b: 1 }).await;
+        assert!(matches!(undeclared, Err(KernelError::ServiceError { .. })));
+
+        let timed_out = proxy.call::<_, serde_json::Value>("sleep", &()).await;
+        assert!(matches!(timed_out, Err(KernelError::CommunicationError { .. })));
+    }
+}
@@ -5,12 +5,13 @@
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 
+use crate::clock::{system_clock, Clock};
 use crate::error::{KernelError, KernelResult};
 
 /// Resource types
@@ -61,10 +62,44 @@ pub struct ResourceAllocation {
     pub allocated_at: DateTime<Utc>,
     /// Allocation expiration (optional)
     pub expires_at: Option<DateTime<Utc>>,
+    /// Lease length in seconds, for allocations the owner must keep renewing
+    #[serde(default)]
+    pub lease_duration: Option<u32>,
+    /// When the lease runs out unless renewed
+    #[serde(default)]
+    pub lease_expires_at: Option<DateTime<Utc>>,
     /// Allocation metadata
     pub metadata: HashMap<String, String>,
 }
 
+impl ResourceAllocation {
+    /// Whether the fixed timeout has passed or the lease ran out
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now) || self.is_lease_lapsed(now)
+    }
+
+    /// Whether the lease ran out without being renewed
+    pub fn is_lease_lapsed(&self, now: DateTime<Utc>) -> bool {
+        self.lease_expires_at.is_some_and(|lease_expires_at| lease_expires_at <= now)
+    }
+}
+
+/// Capacity held for a request without allocating it
+///
+/// Reserved capacity is unavailable to other requests until the reservation
+/// is committed into an allocation, cancelled, or expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceReservation {
+    /// Reservation ID
+    pub id: String,
+    /// Request allocated on commit
+    pub request: ResourceRequest,
+    /// Reservation timestamp
+    pub reserved_at: DateTime<Utc>,
+    /// When the reservation lapses unless committed
+    pub expires_at: DateTime<Utc>,
+}
+
 /// Resource request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceRequest {
@@ -78,6 +113,9 @@ pub struct ResourceRequest {
     pub priority: ResourcePriority,
     /// Timeout for allocation (in seconds)
     pub timeout: Option<u32>,
+    /// Lease for allocation (in seconds), renewed with [`ResourceManager::renew_allocation`]
+    #[serde(default)]
+    pub lease: Option<u32>,
     /// Request metadata
     pub metadata: HashMap<String, String>,
 }
@@ -127,7 +165,7 @@ pub struct ResourceUsage {
     pub total: u64,
     /// Currently used
     pub used: u64,
-    /// Reserved (allocated but not used)
+    /// Held by reservations not yet committed
     pub reserved: u64,
     /// Usage percentage (0-100)
     pub usage_percentage: f64,
@@ -135,18 +173,50 @@ pub struct ResourceUsage {
     pub last_updated: DateTime<Utc>,
 }
 
+/// Peak and average usage of a resource type over a window of samples
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSummary {
+    /// Resource type
+    pub resource_type: ResourceType,
+    /// Number of samples in the window
+    pub samples: usize,
+    /// Highest amount used
+    pub peak_used: u64,
+    /// Mean amount used
+    pub average_used: f64,
+    /// Highest usage percentage
+    pub peak_percentage: f64,
+    /// Mean usage percentage
+    pub average_percentage: f64,
+}
+
+/// Default number of usage samples kept per resource type (a day at one per minute)
+pub const DEFAULT_USAGE_HISTORY_CAPACITY: usize = 1440;
+
 /// Resource manager for allocation and monitoring
 pub struct ResourceManager {
     /// Resource limits
     limits: ResourceLimits,
     /// Current allocations
     allocations: RwLock<HashMap<String, ResourceAllocation>>,
+    /// Reservations awaiting commit
+    reservations: RwLock<HashMap<String, ResourceReservation>>,
     /// Resource usage statistics
     usage: RwLock<HashMap<ResourceType, ResourceUsage>>,
     /// Allocation queue for pending requests
     allocation_queue: RwLock<Vec<ResourceRequest>>,
     /// Resource allocation strategies
     strategies: HashMap<ResourceType, Arc<dyn ResourceStrategy>>,
+    /// Serializes queue processing so a queued request is never served twice
+    queue_processing: Mutex<()>,
+    /// Held from an availability check until the allocation or reservation it admits is recorded
+    admission: Mutex<()>,
+    /// Ring buffer of usage samples per resource type, oldest first
+    usage_history: RwLock<HashMap<ResourceType, VecDeque<ResourceUsage>>>,
+    /// Maximum samples kept per resource type
+    history_capacity: usize,
+    /// Time source for allocation expiry and usage samples
+    clock: Arc<dyn Clock>,
 }
 
 impl ResourceManager {
@@ -162,15 +232,60 @@ impl ResourceManager {
         strategies.insert(ResourceType::Gpu, Arc::new(PriorityBasedStrategy) as Arc<dyn ResourceStrategy>);
         strategies.insert(ResourceType::DatabaseConnections, Arc::new(PoolStrategy) as Arc<dyn ResourceStrategy>);
 
+        let clock = system_clock();
+
+        // Track usage of every configured resource type from the start
+        let usage = [
+            (ResourceType::Cpu, limits.max_cpu as u64),
+            (ResourceType::Memory, limits.max_memory),
+            (ResourceType::Disk, limits.max_disk),
+            (ResourceType::Network, limits.max_network as u64),
+            (ResourceType::Gpu, limits.max_gpu as u64),
+            (ResourceType::DatabaseConnections, limits.max_db_connections as u64),
+        ]
+        .into_iter()
+        .map(|(resource_type, total)| (resource_type, ResourceUsage {
+            resource_type,
+            total,
+            used: 0,
+            reserved: 0,
+            usage_percentage: 0.0,
+            last_updated: clock.now(),
+        }))
+        .collect();
+
         ResourceManager {
             limits,
             allocations: RwLock::new(HashMap::new()),
-            usage: RwLock::new(HashMap::new()),
+            reservations: RwLock::new(HashMap::new()),
+            usage: RwLock::new(usage),
             allocation_queue: RwLock::new(Vec::new()),
             strategies,
+            queue_processing: Mutex::new(()),
+            admission: Mutex::new(()),
+            usage_history: RwLock::new(HashMap::new()),
+            history_capacity: DEFAULT_USAGE_HISTORY_CAPACITY,
+            clock,
         }
     }
 
+    /// Use a custom time source for allocation expiry and usage samples
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Set how many usage samples are kept per resource type
+    pub fn with_history_capacity(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity.max(1);
+        self
+    }
+
+    /// Replace the allocation strategy used for a resource type
+    pub fn set_strategy(&mut self, resource_type: ResourceType, strategy: Arc<dyn ResourceStrategy>) {
+        self.strategies.insert(resource_type, strategy);
+    }
+
     /// Request resource allocation
     pub async fn request_resources(&self, request: ResourceRequest) -> KernelResult<String> {
         // Check if request exceeds limits
@@ -184,7 +299,11 @@ impl ResourceManager {
             ))?;
 
         // Try to allocate immediately
-        match strategy.allocate(&request, self).await {
+        let allocated = {
+            let _admission = self.admission.lock().await;
+            strategy.allocate(&request, self).await
+        };
+        match allocated {
             Ok(allocation_id) => {
                 tracing::info!(
                     "Resource allocated: {} {} for {}",
@@ -207,13 +326,126 @@ impl ResourceManager {
         }
     }
 
+    /// Request resource allocation without queueing when resources are unavailable
+    pub async fn try_request_resources(&self, request: ResourceRequest) -> KernelResult<String> {
+        self.validate_request(&request).await?;
+
+        let strategy = self.strategies.get(&request.resource_type)
+            .ok_or_else(|| KernelError::resource_error(
+                request.resource_type.to_string(),
+                "No strategy available for resource type"
+            ))?;
+
+        let allocation_id = {
+            let _admission = self.admission.lock().await;
+            strategy.allocate(&request, self).await?
+        };
+        tracing::info!(
+            "Resource allocated: {} {} for {}",
+            request.amount, self.resource_type_name(request.resource_type), request.requester
+        );
+        Ok(allocation_id)
+    }
+
+    /// Hold capacity for a request without allocating it, for `ttl`
+    ///
+    /// Fails instead of queueing when the capacity is not free. The request's
+    /// timeout and lease apply from [`Self::commit_reservation`] on.
+    pub async fn reserve_resources(&self, request: ResourceRequest, ttl: Duration) -> KernelResult<String> {
+        self.validate_request(&request).await?;
+
+        // Checked and recorded under the lock allocations take, so neither can overbook the other
+        let _admission = self.admission.lock().await;
+        if !self.check_availability(request.resource_type, request.amount).await {
+            return Err(KernelError::resource_error(
+                request.resource_type.to_string(),
+                "Insufficient resources to reserve"
+            ));
+        }
+
+        let now = self.clock.now();
+        let reservation = ResourceReservation {
+            id: Uuid::new_v4().to_string(),
+            request,
+            reserved_at: now,
+            expires_at: now + ttl,
+        };
+        self.update_usage(reservation.request.resource_type, 0, reservation.request.amount as i64).await;
+        tracing::info!(
+            "Resources reserved: {} {} for {}",
+            reservation.request.amount, self.resource_type_name(reservation.request.resource_type), reservation.request.requester
+        );
+
+        let reservation_id = reservation.id.clone();
+        self.reservations.write().await.insert(reservation_id.clone(), reservation);
+        Ok(reservation_id)
+    }
+
+    /// Turn a reservation into an allocation, returning the allocation ID
+    pub async fn commit_reservation(&self, reservation_id: &str) -> KernelResult<String> {
+        let reservation = self.take_reservation(reservation_id).await?;
+        let request = &reservation.request;
+
+        // Allocated before the hold is dropped, so the capacity is never free in between
+        let allocation_id = self.allocate_resource(request).await?;
+        self.update_usage(request.resource_type, 0, -(request.amount as i64)).await;
+        tracing::info!(
+            "Reservation {} committed: {} {} for {}",
+            reservation_id, request.amount, self.resource_type_name(request.resource_type), request.requester
+        );
+        Ok(allocation_id)
+    }
+
+    /// Release a reservation without allocating it
+    pub async fn cancel_reservation(&self, reservation_id: &str) -> KernelResult<()> {
+        let reservation = self.take_reservation(reservation_id).await?;
+        self.update_usage(reservation.request.resource_type, 0, -(reservation.request.amount as i64)).await;
+        tracing::info!("Reservation {} cancelled", reservation_id);
+
+        self.process_allocation_queue().await;
+        Ok(())
+    }
+
+    /// Release every reservation past its expiry, returning their IDs
+    pub async fn release_expired_reservations(&self) -> Vec<String> {
+        let now = self.clock.now();
+        let expired: Vec<ResourceReservation> = {
+            let mut reservations = self.reservations.write().await;
+            let ids: Vec<String> = reservations.values()
+                .filter(|reservation| reservation.expires_at <= now)
+                .map(|reservation| reservation.id.clone())
+                .collect();
+            ids.iter().filter_map(|id| reservations.remove(id)).collect()
+        };
+
+        for reservation in &expired {
+            tracing::info!("Reservation {} expired", reservation.id);
+            self.update_usage(reservation.request.resource_type, 0, -(reservation.request.amount as i64)).await;
+        }
+        if !expired.is_empty() {
+            self.process_allocation_queue().await;
+        }
+        expired.into_iter().map(|reservation| reservation.id).collect()
+    }
+
+    /// Remove a live reservation; expired ones are left for [`Self::release_expired_reservations`]
+    async fn take_reservation(&self, reservation_id: &str) -> KernelResult<ResourceReservation> {
+        let now = self.clock.now();
+        let mut reservations = self.reservations.write().await;
+        match reservations.get(reservation_id) {
+            Some(reservation) if reservation.expires_at > now => Ok(reservations.remove(reservation_id).expect("reservation exists")),
+            Some(_) => Err(KernelError::resource_error(reservation_id.to_string(), "Reservation expired")),
+            None => Err(KernelError::resource_error(reservation_id.to_string(), "Reservation not found")),
+        }
+    }
+
     /// Release resource allocation
     pub async fn release_resources(&self, allocation_id: &str) -> KernelResult<()> {
-        let mut allocations = self.allocations.write().await;
+        let released = self.allocations.write().await.remove(allocation_id);
 
-        if let Some(allocation) = allocations.remove(allocation_id) {
+        if let Some(allocation) = released {
             // Update usage statistics
-            self.update_usage(allocation.resource_type, -(allocation.amount as i64)).await;
+            self.update_usage(allocation.resource_type, -(allocation.amount as i64), 0).await;
 
             let resource_name = self.resource_type_name(allocation.resource_type);
             tracing::info!(
@@ -233,6 +465,53 @@ impl ResourceManager {
         }
     }
 
+    /// Extend a leased allocation by its lease length from now
+    ///
+    /// A lease never extends past the allocation's fixed timeout, if it has one.
+    pub async fn renew_allocation(&self, allocation_id: &str) -> KernelResult<DateTime<Utc>> {
+        let now = self.clock.now();
+        let mut allocations = self.allocations.write().await;
+        let allocation = allocations.get_mut(allocation_id)
+            .filter(|allocation| !allocation.is_expired(now))
+            .ok_or_else(|| KernelError::resource_error(allocation_id.to_string(), "Allocation not found"))?;
+        let lease_duration = allocation.lease_duration
+            .ok_or_else(|| KernelError::resource_error(allocation_id.to_string(), "Allocation is not leased"))?;
+
+        let lease_expires_at = now + Duration::seconds(lease_duration as i64);
+        allocation.lease_expires_at = Some(lease_expires_at);
+        Ok(lease_expires_at)
+    }
+
+    /// Release every allocation whose timeout has passed or whose lease was not renewed, returning their IDs
+    pub async fn release_expired_allocations(&self) -> Vec<String> {
+        let now = self.clock.now();
+        self.release_allocations_where(|allocation| allocation.is_expired(now)).await
+    }
+
+    /// Reclaim every allocation whose lease was not renewed, returning their IDs
+    ///
+    /// Called periodically by the kernel's resource monitor.
+    pub async fn reclaim_lapsed_leases(&self) -> Vec<String> {
+        let now = self.clock.now();
+        self.release_allocations_where(|allocation| allocation.is_lease_lapsed(now)).await
+    }
+
+    /// Release every allocation matching `expired`, returning their IDs
+    async fn release_allocations_where(&self, expired: impl Fn(&ResourceAllocation) -> bool) -> Vec<String> {
+        let expired: Vec<String> = self.allocations.read().await
+            .values()
+            .filter(|allocation| expired(allocation))
+            .map(|allocation| allocation.id.clone())
+            .collect();
+
+        for allocation_id in &expired {
+            tracing::info!("Allocation {} expired", allocation_id);
+            // Already gone if released concurrently
+            let _ = self.release_resources(allocation_id).await;
+        }
+        expired
+    }
+
     /// Get resource usage statistics
     pub async fn get_resource_usage(&self, resource_type: ResourceType) -> KernelResult<ResourceUsage> {
         let usage = self.usage.read().await;
@@ -253,6 +532,56 @@ impl ResourceManager {
         usage.values().cloned().collect()
     }
 
+    /// Record a snapshot of the current usage of every tracked resource type
+    ///
+    /// Called periodically by the kernel's resource monitor; the oldest
+    /// sample is dropped once a type's history is full.
+    pub async fn sample_usage(&self) {
+        let now = self.clock.now();
+        let usage = self.usage.read().await;
+        let mut history = self.usage_history.write().await;
+
+        for stats in usage.values() {
+            let samples = history.entry(stats.resource_type).or_default();
+            if samples.len() >= self.history_capacity {
+                samples.pop_front();
+            }
+            samples.push_back(ResourceUsage { last_updated: now, ..stats.clone() });
+        }
+    }
+
+    /// Get the usage samples of a resource type taken within `window` of now, oldest first
+    pub async fn usage_history(&self, resource_type: ResourceType, window: Duration) -> Vec<ResourceUsage> {
+        let since = self.clock.now() - window;
+        self.usage_history.read().await
+            .get(&resource_type)
+            .map(|samples| samples.iter()
+                .filter(|sample| sample.last_updated >= since)
+                .cloned()
+                .collect())
+            .unwrap_or_default()
+    }
+
+    /// Get peak and average usage of a resource type over `window`
+    ///
+    /// Returns `None` when no samples were taken in the window.
+    pub async fn usage_summary(&self, resource_type: ResourceType, window: Duration) -> Option<UsageSummary> {
+        let history = self.usage_history(resource_type, window).await;
+        if history.is_empty() {
+            return None;
+        }
+
+        let samples = history.len();
+        Some(UsageSummary {
+            resource_type,
+            samples,
+            peak_used: history.iter().map(|sample| sample.used).max().unwrap_or(0),
+            average_used: history.iter().map(|sample| sample.used as f64).sum::<f64>() / samples as f64,
+            peak_percentage: history.iter().map(|sample| sample.usage_percentage).fold(0.0, f64::max),
+            average_percentage: history.iter().map(|sample| sample.usage_percentage).sum::<f64>() / samples as f64,
+        })
+    }
+
     /// Get allocations for a specific owner
     pub async fn get_allocations_for_owner(&self, owner: &str) -> Vec<ResourceAllocation> {
         let allocations = self.allocations.read().await;
@@ -262,19 +591,57 @@ impl ResourceManager {
             .collect()
     }
 
-    /// Check if resources are available
+    /// Check if resources are available, counting reserved capacity as taken
     pub async fn check_availability(&self, resource_type: ResourceType, amount: u64) -> bool {
         let usage = self.usage.read().await;
 
         if let Some(usage_stats) = usage.get(&resource_type) {
-            usage_stats.used + amount <= usage_stats.total
+            usage_stats.used + usage_stats.reserved + amount <= usage_stats.total
         } else {
             false
         }
     }
 
-    /// Update resource usage statistics
-    async fn update_usage(&self, resource_type: ResourceType, delta: i64) {
+    /// Get the amount of a resource type currently allocated to an owner
+    pub async fn allocated_amount(&self, owner: &str, resource_type: ResourceType) -> u64 {
+        let allocations = self.allocations.read().await;
+        allocations.values()
+            .filter(|alloc| alloc.owner == owner && alloc.resource_type == resource_type)
+            .map(|alloc| alloc.amount)
+            .sum()
+    }
+
+    /// Get the current holders of a resource type
+    pub async fn allocation_owners(&self, resource_type: ResourceType) -> Vec<String> {
+        let allocations = self.allocations.read().await;
+        let mut owners: Vec<String> = allocations.values()
+            .filter(|alloc| alloc.resource_type == resource_type)
+            .map(|alloc| alloc.owner.clone())
+            .collect();
+        owners.sort();
+        owners.dedup();
+        owners
+    }
+
+    /// Get the requesters waiting in the allocation queue for a resource type
+    pub async fn queued_requesters(&self, resource_type: ResourceType) -> Vec<String> {
+        let queue = self.allocation_queue.read().await;
+        let mut requesters: Vec<String> = queue.iter()
+            .filter(|request| request.resource_type == resource_type)
+            .map(|request| request.requester.clone())
+            .collect();
+        requesters.sort();
+        requesters.dedup();
+        requesters
+    }
+
+    /// Get the total capacity of a resource type
+    pub fn capacity(&self, resource_type: ResourceType) -> u64 {
+        self.get_total_capacity(resource_type)
+    }
+
+    /// Update resource usage statistics by the change in used and reserved amounts
+    async fn update_usage(&self, resource_type: ResourceType, used_delta: i64, reserved_delta: i64) {
         let mut usage = self.usage.write().await;
 
         let usage_stats = usage.entry(resource_type).or_insert_with(|| {
@@ -285,17 +652,18 @@ impl ResourceManager {
                 used: 0,
                 reserved: 0,
                 usage_percentage: 0.0,
-                last_updated: Utc::now(),
+                last_updated: self.clock.now(),
             }
         });
 
-        usage_stats.used = (usage_stats.used as i64 + delta).max(0) as u64;
+        usage_stats.used = (usage_stats.used as i64 + used_delta).max(0) as u64;
+        usage_stats.reserved = (usage_stats.reserved as i64 + reserved_delta).max(0) as u64;
         usage_stats.usage_percentage = if usage_stats.total > 0 {
             (usage_stats.used as f64 / usage_stats.total as f64) * 100.0
         } else {
             0.0
         };
-        usage_stats.last_updated = Utc::now();
+        usage_stats.last_updated = self.clock.now();
     }
 
     /// Validate resource request
@@ -321,13 +689,40 @@ impl ResourceManager {
     }
 
     /// Process queued allocation requests
+    ///
+    /// Requests are served in the order given by each strategy's
+    /// [`ResourceStrategy::queue_rank`], re-ranked after every grant so
+    /// share-based strategies see the effect of earlier grants. The queue is
+    /// left in place while strategies run so they can inspect contention.
     async fn process_allocation_queue(&self) {
-        let mut queue = self.allocation_queue.write().await;
+        let _processing = self.queue_processing.lock().await;
+        let snapshot = self.allocation_queue.read().await.clone();
+        let mut candidates: Vec<usize> = (0..snapshot.len()).collect();
         let mut fulfilled = Vec::new();
 
-        for (index, request) in queue.iter().enumerate() {
+        while !candidates.is_empty() {
+            let mut best: Option<(usize, f64)> = None;
+            for (position, &index) in candidates.iter().enumerate() {
+                let request = &snapshot[index];
+                let rank = match self.strategies.get(&request.resource_type) {
+                    Some(strategy) => strategy.queue_rank(request, self).await,
+                    None => f64::INFINITY,
+                };
+                if best.is_none_or(|(_, best_rank)| rank < best_rank) {
+                    best = Some((position, rank));
+                }
+            }
+
+            let Some((position, _)) = best else { break };
+            let index = candidates.remove(position);
+            let request = &snapshot[index];
+
             if let Some(strategy) = self.strategies.get(&request.resource_type) {
-                if strategy.allocate(request, self).await.is_ok() {
+                let allocated = {
+                    let _admission = self.admission.lock().await;
+                    strategy.allocate(request, self).await
+                };
+                if allocated.is_ok() {
                     fulfilled.push(index);
                     tracing::info!(
                         "Queued request fulfilled: {} {} for {}",
@@ -337,7 +732,10 @@ impl ResourceManager {
             }
         }
 
-        // Remove fulfilled requests (in reverse order to maintain indices)
+        // Remove fulfilled requests (in reverse order to maintain indices).
+        // New requests are only ever appended, so snapshot indices stay valid.
+        fulfilled.sort_unstable();
+        let mut queue = self.allocation_queue.write().await;
         for index in fulfilled.into_iter().rev() {
             queue.remove(index);
         }
@@ -352,13 +750,15 @@ impl ResourceManager {
             owner: request.requester.clone(),
             resource_type: request.resource_type,
             amount: request.amount,
-            allocated_at: Utc::now(),
-            expires_at: request.timeout.map(|t| Utc::now() + chrono::Duration::seconds(t as i64)),
+            allocated_at: self.clock.now(),
+            expires_at: request.timeout.map(|t| self.clock.now() + Duration::seconds(t as i64)),
+            lease_duration: request.lease,
+            lease_expires_at: request.lease.map(|l| self.clock.now() + Duration::seconds(l as i64)),
             metadata: request.metadata.clone(),
         };
 
         self.allocations.write().await.insert(allocation_id.clone(), allocation);
-        self.update_usage(request.resource_type, request.amount as i64).await;
+        self.update_usage(request.resource_type, request.amount as i64, 0).await;
 
         Ok(allocation_id)
     }
@@ -400,6 +800,12 @@ impl ResourceManager {
 pub trait ResourceStrategy: Send + Sync {
     /// Allocate resources using this strategy
     async fn allocate(&self, request: &ResourceRequest, manager: &ResourceManager) -> KernelResult<String>;
+
+    /// Rank a queued request; lower ranks are served first.
+    /// The default keeps queued requests in arrival order.
+    async fn queue_rank(&self, _request: &ResourceRequest, _manager: &ResourceManager) -> f64 {
+        0.0
+    }
 }
 
 /// Fair share allocation strategy
@@ -453,7 +859,7 @@ impl ResourceStrategy for PoolStrategy {
         let usage = manager.usage.read().await;
         if let Some(usage_stats) = usage.get(&request.resource_type) {
             let available_for_burst = usage_stats.total - (usage_stats.total as f64 * POOL_RESERVE_RATIO) as u64;
-            if usage_stats.used + request.amount <= available_for_burst {
+            if usage_stats.used + usage_stats.reserved + request.amount <= available_for_burst {
                 return manager.allocate_resource(request).await;
             }
         }
@@ -465,6 +871,100 @@ impl ResourceStrategy for PoolStrategy {
     }
 }
 
+/// Proportional share allocation strategy (cgroup-like weights)
+///
+/// Without contention a requester may use any free capacity. While other
+/// requesters are waiting in the allocation queue, each requester is capped at
+/// `capacity * weight / total_weight`, where the total covers every current
+/// holder and waiter. Queued requests are served lowest weighted usage first.
+pub struct ProportionalShareStrategy {
+    /// Configured weights per requester
+    weights: HashMap<String, u32>,
+    /// Weight for requesters without an explicit entry
+    default_weight: u32,
+}
+
+impl ProportionalShareStrategy {
+    /// Create a new proportional share strategy where every requester has weight 1
+    pub fn new() -> Self {
+        ProportionalShareStrategy {
+            weights: HashMap::new(),
+            default_weight: 1,
+        }
+    }
+
+    /// Set the weight of a requester
+    pub fn with_weight<S: Into<String>>(mut self, requester: S, weight: u32) -> Self {
+        self.weights.insert(requester.into(), weight.max(1));
+        self
+    }
+
+    /// Set the weight used for requesters without an explicit entry
+    pub fn with_default_weight(mut self, weight: u32) -> Self {
+        self.default_weight = weight.max(1);
+        self
+    }
+
+    /// Get the weight of a requester
+    pub fn weight_of(&self, requester: &str) -> u32 {
+        self.weights.get(requester).copied().unwrap_or(self.default_weight)
+    }
+
+    /// Compute the share a requester is entitled to under contention, or
+    /// `None` when no other requester is waiting
+    async fn contended_share(&self, request: &ResourceRequest, manager: &ResourceManager) -> Option<f64> {
+        let waiting = manager.queued_requesters(request.resource_type).await;
+        if waiting.iter().all(|requester| requester == &request.requester) {
+            return None;
+        }
+
+        let mut contenders = manager.allocation_owners(request.resource_type).await;
+        contenders.extend(waiting);
+        contenders.push(request.requester.clone());
+        contenders.sort();
+        contenders.dedup();
+
+        let total_weight: u64 = contenders.iter().map(|c| self.weight_of(c) as u64).sum();
+        let capacity = manager.capacity(request.resource_type) as f64;
+        Some(capacity * self.weight_of(&request.requester) as f64 / total_weight as f64)
+    }
+}
+
+impl Default for ProportionalShareStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ResourceStrategy for ProportionalShareStrategy {
+    async fn allocate(&self, request: &ResourceRequest, manager: &ResourceManager) -> KernelResult<String> {
+        if !manager.check_availability(request.resource_type, request.amount).await {
+            return Err(KernelError::resource_error(
+                request.resource_type.to_string(),
+                "Insufficient resources"
+            ));
+        }
+
+        if let Some(share) = self.contended_share(request, manager).await {
+            let held = manager.allocated_amount(&request.requester, request.resource_type).await;
+            if (held + request.amount) as f64 > share {
+                return Err(KernelError::resource_error(
+                    request.resource_type.to_string(),
+                    format!("Request exceeds proportional share of {:.1} for {}", share, request.requester)
+                ));
+            }
+        }
+
+        manager.allocate_resource(request).await
+    }
+
+    async fn queue_rank(&self, request: &ResourceRequest, manager: &ResourceManager) -> f64 {
+        let held = manager.allocated_amount(&request.requester, request.resource_type).await;
+        (held + request.amount) as f64 / self.weight_of(&request.requester) as f64
+    }
+}
+
 /// Helper macro for requesting resources
 #[macro_export]
 macro_rules! request_resource {
@@ -475,6 +975,7 @@ macro_rules! request_resource {
             amount: $amount,
             priority: $crate::resource::ResourcePriority::Normal,
             timeout: None,
+            lease: None,
             metadata: std::collections::HashMap::new(),
         }).await
     };
@@ -487,3 +988,246 @@ macro_rules! release_resource {
         $manager.release_resources($allocation_id).await
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn test_limits(max_cpu: u32) -> ResourceLimits {
+        ResourceLimits {
+            max_cpu,
+            max_memory: 1024,
+            max_disk: 10,
+            max_network: 100,
+            max_gpu: 0,
+            max_db_connections: 10,
+        }
+    }
+
+    fn cpu_request(requester: &str, amount: u64) -> ResourceRequest {
+        ResourceRequest {
+            requester: requester.to_string(),
+            resource_type: ResourceType::Cpu,
+            amount,
+            priority: ResourcePriority::Normal,
+            timeout: None,
+            lease: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn proportional_manager(max_cpu: u32) -> ResourceManager {
+        let mut manager = ResourceManager::new(test_limits(max_cpu));
+        let strategy = ProportionalShareStrategy::new()
+            .with_weight("high", 3)
+            .with_weight("low", 1);
+        manager.set_strategy(ResourceType::Cpu, Arc::new(strategy));
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_proportional_share_uncontended_uses_full_capacity() {
+        let manager = proportional_manager(8);
+
+        assert!(manager.request_resources(cpu_request("low", 8)).await.is_ok());
+        assert_eq!(manager.allocated_amount("low", ResourceType::Cpu).await, 8);
+    }
+
+    #[tokio::test]
+    async fn test_proportional_share_queue_served_by_weight() {
+        let manager = proportional_manager(8);
+
+        let warmup = manager.request_resources(cpu_request("warmup", 8)).await.unwrap();
+
+        // Both requesters contend for single units while capacity is exhausted
+        for _ in 0..8 {
+            assert!(manager.request_resources(cpu_request("high", 1)).await.is_err());
+            assert!(manager.request_resources(cpu_request("low", 1)).await.is_err());
+        }
+
+        manager.release_resources(&warmup).await.unwrap();
+
+        assert_eq!(manager.allocated_amount("high", ResourceType::Cpu).await, 6);
+        assert_eq!(manager.allocated_amount("low", ResourceType::Cpu).await, 2);
+        assert_eq!(manager.queued_requesters(ResourceType::Cpu).await, vec!["high", "low"]);
+    }
+
+    #[tokio::test]
+    async fn test_proportional_share_caps_new_requests_under_contention() {
+        let manager = proportional_manager(8);
+
+        let warmup = manager.request_resources(cpu_request("warmup", 4)).await.unwrap();
+        assert!(manager.request_resources(cpu_request("low", 4)).await.is_ok());
+        assert!(manager.request_resources(cpu_request("high", 6)).await.is_err());
+
+        // "low" holds 4 while "high" waits, so "low" may not grow past its 2-unit share
+        manager.release_resources(&warmup).await.unwrap();
+        assert!(manager.request_resources(cpu_request("low", 1)).await.is_err());
+        assert_eq!(manager.allocated_amount("high", ResourceType::Cpu).await, 0);
+        assert_eq!(manager.allocated_amount("low", ResourceType::Cpu).await, 4);
+    }
+
+    #[tokio::test]
+    async fn test_usage_history_captures_peak() {
+        let manager = ResourceManager::new(test_limits(8)).with_history_capacity(3);
+
+        let first = manager.request_resources(cpu_request("a", 2)).await.unwrap();
+        manager.sample_usage().await;
+        let second = manager.request_resources(cpu_request("b", 4)).await.unwrap();
+        manager.sample_usage().await;
+        manager.release_resources(&second).await.unwrap();
+        manager.sample_usage().await;
+        manager.release_resources(&first).await.unwrap();
+        manager.sample_usage().await;
+
+        // The first sample fell out of the three-sample ring buffer
+        let history = manager.usage_history(ResourceType::Cpu, Duration::minutes(5)).await;
+        let used: Vec<u64> = history.iter().map(|sample| sample.used).collect();
+        assert_eq!(used, vec![6, 2, 0]);
+
+        let summary = manager.usage_summary(ResourceType::Cpu, Duration::minutes(5)).await.unwrap();
+        assert_eq!(summary.samples, 3);
+        assert_eq!(summary.peak_used, 6);
+        assert!((summary.average_used - 8.0 / 3.0).abs() < 1e-9);
+        assert!((summary.peak_percentage - 75.0).abs() < 1e-9);
+        assert!(manager.usage_summary(ResourceType::Custom, Duration::minutes(5)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_allocations_are_released() {
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let manager = ResourceManager::new(test_limits(8)).with_clock(clock.clone());
+
+        let mut timed = cpu_request("batch", 4);
+        timed.timeout = Some(60);
+        let allocation_id = manager.request_resources(timed).await.unwrap();
+        manager.request_resources(cpu_request("service", 2)).await.unwrap();
+
+        clock.advance(Duration::seconds(59));
+        assert!(manager.release_expired_allocations().await.is_empty());
+
+        clock.advance(Duration::seconds(2));
+        // Lease reclamation leaves fixed timeouts to explicit release
+        assert!(manager.reclaim_lapsed_leases().await.is_empty());
+        assert_eq!(manager.release_expired_allocations().await, vec![allocation_id]);
+        assert_eq!(manager.allocated_amount("batch", ResourceType::Cpu).await, 0);
+        assert_eq!(manager.allocated_amount("service", ResourceType::Cpu).await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_renewed_lease_survives() {
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let manager = ResourceManager::new(test_limits(8)).with_clock(clock.clone());
+
+        let mut leased = cpu_request("worker", 4);
+        leased.lease = Some(30);
+        let allocation_id = manager.request_resources(leased).await.unwrap();
+
+        for _ in 0..4 {
+            clock.advance(Duration::seconds(20));
+            manager.renew_allocation(&allocation_id).await.unwrap();
+            assert!(manager.release_expired_allocations().await.is_empty());
+        }
+        assert_eq!(manager.allocated_amount("worker", ResourceType::Cpu).await, 4);
+
+        // Fixed-timeout allocations cannot be renewed
+        let mut timed = cpu_request("batch", 1);
+        timed.timeout = Some(60);
+        let timed_id = manager.request_resources(timed).await.unwrap();
+        assert!(manager.renew_allocation(&timed_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unrenewed_lease_is_reclaimed() {
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let manager = ResourceManager::new(test_limits(8)).with_clock(clock.clone());
+
+        let mut leased = cpu_request("crashed", 8);
+        leased.lease = Some(30);
+        let allocation_id = manager.request_resources(leased).await.unwrap();
+        assert!(manager.request_resources(cpu_request("waiting", 4)).await.is_err());
+
+        clock.advance(Duration::seconds(31));
+        assert!(manager.renew_allocation(&allocation_id).await.is_err());
+        assert_eq!(manager.reclaim_lapsed_leases().await, vec![allocation_id]);
+        assert_eq!(manager.allocated_amount("crashed", ResourceType::Cpu).await, 0);
+        // The reclaimed capacity serves the queued request
+        assert_eq!(manager.allocated_amount("waiting", ResourceType::Cpu).await, 4);
+    }
+
+    #[tokio::test]
+    async fn test_reservation_commit_and_cancel() {
+        let manager = ResourceManager::new(test_limits(8));
+
+        let reservation = manager.reserve_resources(cpu_request("planner", 6), Duration::minutes(1)).await.unwrap();
+        let usage = manager.get_resource_usage(ResourceType::Cpu).await.unwrap();
+        assert_eq!((usage.used, usage.reserved), (0, 6));
+
+        // Reserved capacity is not available to others
+        assert!(manager.try_request_resources(cpu_request("other", 4)).await.is_err());
+        assert!(manager.reserve_resources(cpu_request("other", 4), Duration::minutes(1)).await.is_err());
+
+        let allocation_id = manager.commit_reservation(&reservation).await.unwrap();
+        let usage = manager.get_resource_usage(ResourceType::Cpu).await.unwrap();
+        assert_eq!((usage.used, usage.reserved), (6, 0));
+        assert_eq!(manager.allocated_amount("planner", ResourceType::Cpu).await, 6);
+        assert!(manager.commit_reservation(&reservation).await.is_err());
+        manager.release_resources(&allocation_id).await.unwrap();
+
+        // A cancelled reservation frees its capacity without allocating
+        let reservation = manager.reserve_resources(cpu_request("planner", 8), Duration::minutes(1)).await.unwrap();
+        assert!(manager.request_resources(cpu_request("waiting", 2)).await.is_err());
+        manager.cancel_reservation(&reservation).await.unwrap();
+        assert_eq!(manager.allocated_amount("planner", ResourceType::Cpu).await, 0);
+        assert_eq!(manager.allocated_amount("waiting", ResourceType::Cpu).await, 2);
+        assert_eq!(manager.get_resource_usage(ResourceType::Cpu).await.unwrap().reserved, 0);
+    }
+
+    /// Fair share strategy that yields between its availability check and the allocation
+    struct YieldingStrategy;
+
+    #[async_trait]
+    impl ResourceStrategy for YieldingStrategy {
+        async fn allocate(&self, request: &ResourceRequest, manager: &ResourceManager) -> KernelResult<String> {
+            if !manager.check_availability(request.resource_type, request.amount).await {
+                return Err(KernelError::resource_error(request.resource_type.to_string(), "Insufficient resources"));
+            }
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            manager.allocate_resource(request).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reservations_cannot_overbook_concurrent_allocations() {
+        let mut manager = ResourceManager::new(test_limits(8));
+        manager.set_strategy(ResourceType::Cpu, Arc::new(YieldingStrategy));
+
+        let (allocated, reserved) = tokio::join!(
+            manager.try_request_resources(cpu_request("worker", 8)),
+            manager.reserve_resources(cpu_request("planner", 8), Duration::minutes(1)),
+        );
+        assert!(allocated.is_ok());
+        assert!(reserved.is_err());
+        let usage = manager.get_resource_usage(ResourceType::Cpu).await.unwrap();
+        assert_eq!((usage.used, usage.reserved), (8, 0));
+    }
+
+    #[tokio::test]
+    async fn test_unused_reservation_expires() {
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let manager = ResourceManager::new(test_limits(8)).with_clock(clock.clone());
+
+        let reservation = manager.reserve_resources(cpu_request("planner", 8), Duration::seconds(30)).await.unwrap();
+        clock.advance(Duration::seconds(29));
+        assert!(manager.release_expired_reservations().await.is_empty());
+
+        clock.advance(Duration::seconds(2));
+        assert!(manager.commit_reservation(&reservation).await.is_err());
+        assert_eq!(manager.release_expired_reservations().await, vec![reservation]);
+        assert_eq!(manager.get_resource_usage(ResourceType::Cpu).await.unwrap().reserved, 0);
+        assert!(manager.try_request_resources(cpu_request("other", 8)).await.is_ok());
+    }
+}
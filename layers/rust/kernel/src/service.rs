@@ -11,14 +11,18 @@ use tokio::sync::RwLock;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::clock::{system_clock, Clock};
 use crate::error::{KernelError, KernelResult};
 use crate::message::{MessageBus, Message};
 
 /// Service metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServiceMetadata {
-    /// Unique service identifier
+    /// Service identifier, shared by all instances of a logical service
     pub id: String,
+    /// Identifier distinguishing one instance of a replicated service
+    #[serde(default)]
+    pub instance_id: Option<String>,
     /// Human-readable service name
     pub name: String,
     /// Service version
@@ -31,6 +35,9 @@ pub struct ServiceMetadata {
     pub service_type: ServiceType,
     /// Service capabilities
     pub capabilities: Vec<String>,
+    /// Methods the service accepts, with their payload schemas
+    #[serde(default)]
+    pub methods: Vec<ServiceMethod>,
     /// Service dependencies
     pub dependencies: Vec<String>,
     /// Health check endpoint
@@ -51,6 +58,145 @@ pub struct ServiceMetadata {
     pub region: Option<String>,
 }
 
+/// Separates the service ID from the instance ID in an instance key
+const INSTANCE_KEY_SEPARATOR: char = '/';
+
+impl ServiceMetadata {
+    /// Metadata of a healthy local plugin service without capabilities, methods or tags
+    pub fn new<I: Into<String>, N: Into<String>>(id: I, name: N) -> Self {
+        let now = Utc::now();
+        Self {
+            id: id.into(),
+            instance_id: None,
+            name: name.into(),
+            version: "1.0.0".to_string(),
+            description: String::new(),
+            endpoint: "local".to_string(),
+            service_type: ServiceType::Plugin,
+            capabilities: Vec::new(),
+            methods: Vec::new(),
+            dependencies: Vec::new(),
+            health_check: None,
+            status: ServiceStatus::Healthy,
+            registered_at: now,
+            last_heartbeat: now,
+            tags: Vec::new(),
+            priority: 0,
+            weight: 1,
+            region: None,
+        }
+    }
+
+    /// Mark the metadata as one instance of a replicated service
+    pub fn with_instance_id<S: Into<String>>(mut self, instance_id: S) -> Self {
+        self.instance_id = Some(instance_id.into());
+        self
+    }
+
+    /// Set the version
+    pub fn with_version<S: Into<String>>(mut self, version: S) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Set the description
+    pub fn with_description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Set the endpoint
+    pub fn with_endpoint<S: Into<String>>(mut self, endpoint: S) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// Set the service type
+    pub fn with_service_type(mut self, service_type: ServiceType) -> Self {
+        self.service_type = service_type;
+        self
+    }
+
+    /// Advertise a capability
+    pub fn with_capability<S: Into<String>>(mut self, capability: S) -> Self {
+        self.capabilities.push(capability.into());
+        self
+    }
+
+    /// Declare a method
+    pub fn with_method(mut self, method: ServiceMethod) -> Self {
+        self.methods.push(method);
+        self
+    }
+
+    /// Add a tag
+    pub fn with_tag<S: Into<String>>(mut self, tag: S) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Set the priority
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set the registration and last heartbeat time
+    pub fn with_registered_at(mut self, at: DateTime<Utc>) -> Self {
+        self.registered_at = at;
+        self.last_heartbeat = at;
+        self
+    }
+
+    /// Key the instance is registered under
+    ///
+    /// The service ID, followed by `/` and the instance ID for an instance of
+    /// a replicated service. Service IDs cannot contain `/`, so keys of
+    /// different services never collide.
+    pub fn instance_key(&self) -> String {
+        match &self.instance_id {
+            Some(instance_id) => format!("{}{}{}", self.id, INSTANCE_KEY_SEPARATOR, instance_id),
+            None => self.id.clone(),
+        }
+    }
+}
+
+/// A method declared by a service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceMethod {
+    /// Method name as sent in [`ServiceRequest::method`]
+    pub name: String,
+    /// JSON Schema the request params must satisfy (optional)
+    #[serde(default)]
+    pub request_schema: Option<serde_json::Value>,
+    /// JSON Schema the response data must satisfy (optional)
+    #[serde(default)]
+    pub response_schema: Option<serde_json::Value>,
+}
+
+impl ServiceMethod {
+    /// Declare a method without payload schemas
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            request_schema: None,
+            response_schema: None,
+        }
+    }
+
+    /// Set the request params schema
+    pub fn with_request_schema(mut self, schema: serde_json::Value) -> Self {
+        self.request_schema = Some(schema);
+        self
+    }
+
+    /// Set the response data schema
+    pub fn with_response_schema(mut self, schema: serde_json::Value) -> Self {
+        self.response_schema = Some(schema);
+        self
+    }
+}
+
 /// Service types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ServiceType {
@@ -108,6 +254,15 @@ pub struct ServiceInstance {
     pub config: serde_json::Value,
 }
 
+/// Strategy for choosing among the healthy instances of a service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InstanceSelection {
+    /// Cycle through instances in order
+    RoundRobin,
+    /// Pick the instance selected longest ago
+    LeastRecentlyUsed,
+}
+
 /// Core service trait
 #[async_trait]
 pub trait Service: Send + Sync {
@@ -185,7 +340,7 @@ pub enum ResponseStatus {
 }
 
 /// Service discovery query
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ServiceQuery {
     /// Service name filter (optional)
     pub name: Option<String>,
@@ -201,7 +356,7 @@ pub struct ServiceQuery {
     pub status: Option<ServiceStatus>,
     /// Minimum priority (optional)
     pub min_priority: Option<i32>,
-    /// Maximum results to return
+    /// Maximum services to return, each with all its matching instances
     pub limit: Option<usize>,
 }
 
@@ -215,12 +370,18 @@ pub struct ServiceRegistry {
     services_by_capability: RwLock<HashMap<String, Vec<String>>>,
     /// Services by tag
     services_by_tag: RwLock<HashMap<String, Vec<String>>>,
+    /// Round-robin position per service ID
+    round_robin: RwLock<HashMap<String, usize>>,
+    /// When each instance was last selected
+    last_selected: RwLock<HashMap<String, std::time::Instant>>,
     /// Message bus for service events
     message_bus: Arc<MessageBus>,
     /// Heartbeat interval (in seconds)
     heartbeat_interval: u64,
     /// Service timeout (in seconds)
     service_timeout: u64,
+    /// Time source for heartbeats and expiry
+    clock: Arc<dyn Clock>,
 }
 
 impl ServiceRegistry {
@@ -231,20 +392,38 @@ impl ServiceRegistry {
             services_by_type: RwLock::new(HashMap::new()),
             services_by_capability: RwLock::new(HashMap::new()),
             services_by_tag: RwLock::new(HashMap::new()),
+            round_robin: RwLock::new(HashMap::new()),
+            last_selected: RwLock::new(HashMap::new()),
             message_bus,
             heartbeat_interval: 30, // 30 seconds
             service_timeout: 90,     // 90 seconds
+            clock: system_clock(),
         }
     }
 
-    /// Register a service
+    /// Use a custom time source for heartbeats and expiry
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Register a service, returning the key of the registered instance
+    ///
+    /// Several instances may share a service ID as long as each carries a
+    /// distinct instance ID.
     pub async fn register_service(
         &self,
         service: Arc<dyn Service>,
         config: serde_json::Value,
     ) -> KernelResult<String> {
         let metadata = service.metadata();
-        let service_id = metadata.id.clone();
+        if metadata.id.contains(INSTANCE_KEY_SEPARATOR) {
+            return Err(KernelError::service_error(
+                metadata.id,
+                format!("Service IDs cannot contain '{}'", INSTANCE_KEY_SEPARATOR)
+            ));
+        }
+        let service_id = metadata.instance_key();
 
         // Check if service is already registered
         if self.services.read().await.contains_key(&service_id) {
@@ -284,6 +463,7 @@ impl ServiceRegistry {
         if let Some(instance) = services.remove(service_id) {
             // Update indexes
             self.update_indexes(&instance.metadata, false).await;
+            self.last_selected.write().await.remove(service_id);
 
             // Publish service unregistration event
             let event = ServiceEvent::ServiceUnregistered {
@@ -303,32 +483,84 @@ impl ServiceRegistry {
     }
 
     /// Discover services matching the query
+    ///
+    /// The matching instances of a service are listed together, by priority
+    /// (higher first), and services are ordered by their best instance.
     pub async fn discover_services(&self, query: &ServiceQuery) -> KernelResult<Vec<ServiceMetadata>> {
         let services = self.services.read().await;
-        let mut results = Vec::new();
+        let mut groups: HashMap<&str, Vec<ServiceMetadata>> = HashMap::new();
 
         for instance in services.values() {
             if self.matches_query(&instance.metadata, query) {
-                results.push(instance.metadata.clone());
+                groups.entry(&instance.metadata.id).or_default().push(instance.metadata.clone());
             }
         }
 
-        // Sort by priority (higher first)
-        results.sort_by(|a, b| b.priority.cmp(&a.priority));
+        let mut groups: Vec<Vec<ServiceMetadata>> = groups.into_values().collect();
+        for group in &mut groups {
+            group.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.instance_key().cmp(&b.instance_key())));
+        }
+        groups.sort_by(|a, b| b[0].priority.cmp(&a[0].priority).then_with(|| a[0].id.cmp(&b[0].id)));
 
         // Apply limit
         if let Some(limit) = query.limit {
-            results.truncate(limit);
+            groups.truncate(limit);
         }
 
-        Ok(results)
+        Ok(groups.into_iter().flatten().collect())
+    }
+
+    /// Choose a healthy instance of a service
+    pub async fn select_instance(
+        &self,
+        service_id: &str,
+        strategy: InstanceSelection,
+    ) -> KernelResult<ServiceMetadata> {
+        let mut candidates: Vec<ServiceMetadata> = self.services.read().await
+            .values()
+            .filter(|instance| instance.metadata.id == service_id && instance.metadata.status == ServiceStatus::Healthy)
+            .map(|instance| instance.metadata.clone())
+            .collect();
+
+        if candidates.is_empty() {
+            return Err(KernelError::service_error(
+                service_id.to_string(),
+                "No healthy instance available".to_string()
+            ));
+        }
+        candidates.sort_by_key(ServiceMetadata::instance_key);
+
+        let mut last_selected = self.last_selected.write().await;
+        let index = match strategy {
+            InstanceSelection::RoundRobin => {
+                let mut round_robin = self.round_robin.write().await;
+                let position = round_robin.entry(service_id.to_string()).or_insert(0);
+                let index = *position % candidates.len();
+                *position = position.wrapping_add(1);
+                index
+            }
+            // Instances never selected come first
+            InstanceSelection::LeastRecentlyUsed => candidates.iter()
+                .enumerate()
+                .min_by_key(|(_, metadata)| last_selected.get(&metadata.instance_key()).copied())
+                .map(|(index, _)| index)
+                .unwrap_or(0),
+        };
+
+        let selected = candidates.swap_remove(index);
+        last_selected.insert(selected.instance_key(), std::time::Instant::now());
+        Ok(selected)
     }
 
-    /// Get service by ID
+    /// Get a service instance by key, or the first instance of a service ID
     pub async fn get_service(&self, service_id: &str) -> KernelResult<ServiceMetadata> {
         let services = self.services.read().await;
 
-        if let Some(instance) = services.get(service_id) {
+        let instance = services.get(service_id).or_else(|| services.values()
+            .filter(|instance| instance.metadata.id == service_id)
+            .min_by_key(|instance| instance.metadata.instance_key()));
+
+        if let Some(instance) = instance {
             Ok(instance.metadata.clone())
         } else {
             Err(KernelError::service_error(
@@ -343,7 +575,7 @@ impl ServiceRegistry {
         let mut services = self.services.write().await;
 
         if let Some(instance) = services.get_mut(service_id) {
-            instance.metadata.last_heartbeat = Utc::now();
+            instance.metadata.last_heartbeat = self.clock.now();
             instance.metadata.status = ServiceStatus::Healthy;
 
             // Publish heartbeat event
@@ -393,14 +625,24 @@ impl ServiceRegistry {
     }
 
     /// Call a service method
+    ///
+    /// `service_id` names either a single instance or a replicated service, in
+    /// which case a healthy instance is chosen round-robin.
     pub async fn call_service(
         &self,
         service_id: &str,
         request: ServiceRequest,
     ) -> KernelResult<ServiceResponse> {
+        let registered = self.services.read().await.contains_key(service_id);
+        let instance_key = if registered {
+            service_id.to_string()
+        } else {
+            self.select_instance(service_id, InstanceSelection::RoundRobin).await?.instance_key()
+        };
+
         let services = self.services.read().await;
 
-        if let Some(instance) = services.get(service_id) {
+        if let Some(instance) = services.get(&instance_key) {
             if let Some(service) = &instance.instance {
                 let start_time = std::time::Instant::now();
                 let response = service.handle_request(request).await?;
@@ -427,7 +669,7 @@ impl ServiceRegistry {
     /// Check for expired services and mark them as unhealthy
     pub async fn check_expired_services(&self) -> KernelResult<Vec<String>> {
         let mut expired_services = Vec::new();
-        let now = Utc::now();
+        let now = self.clock.now();
         let timeout_duration = chrono::Duration::seconds(self.service_timeout as i64);
 
         let mut services = self.services.write().await;
@@ -463,20 +705,21 @@ impl ServiceRegistry {
         let mut services_by_type = self.services_by_type.write().await;
         let mut services_by_capability = self.services_by_capability.write().await;
         let mut services_by_tag = self.services_by_tag.write().await;
+        let key = metadata.instance_key();
 
         if add {
             // Add to type index
             services_by_type
                 .entry(metadata.service_type)
                 .or_insert_with(Vec::new)
-                .push(metadata.id.clone());
+                .push(key.clone());
 
             // Add to capability indexes
             for capability in &metadata.capabilities {
                 services_by_capability
                     .entry(capability.clone())
                     .or_insert_with(Vec::new)
-                    .push(metadata.id.clone());
+                    .push(key.clone());
             }
 
             // Add to tag indexes
@@ -484,25 +727,25 @@ impl ServiceRegistry {
                 services_by_tag
                     .entry(tag.clone())
                     .or_insert_with(Vec::new)
-                    .push(metadata.id.clone());
+                    .push(key.clone());
             }
         } else {
             // Remove from type index
             if let Some(services) = services_by_type.get_mut(&metadata.service_type) {
-                services.retain(|id| id != &metadata.id);
+                services.retain(|id| id != &key);
             }
 
             // Remove from capability indexes
             for capability in &metadata.capabilities {
                 if let Some(services) = services_by_capability.get_mut(capability) {
-                    services.retain(|id| id != &metadata.id);
+                    services.retain(|id| id != &key);
                 }
             }
 
             // Remove from tag indexes
             for tag in &metadata.tags {
                 if let Some(services) = services_by_tag.get_mut(tag) {
-                    services.retain(|id| id != &metadata.id);
+                    services.retain(|id| id != &key);
                 }
             }
         }
@@ -607,3 +850,181 @@ pub enum ServiceEvent {
         new_status: ServiceStatus,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    /// Replica of a service that reports which instance handled a request
+    struct Replica {
+        instance_id: &'static str,
+    }
+
+    #[async_trait]
+    impl Service for Replica {
+        fn metadata(&self) -> ServiceMetadata {
+            ServiceMetadata::new("search", "Search")
+                .with_instance_id(self.instance_id)
+                .with_description("Replicated search")
+        }
+
+        async fn start(&self) -> KernelResult<()> {
+            Ok(())
+        }
+
+        async fn stop(&self) -> KernelResult<()> {
+            Ok(())
+        }
+
+        async fn health(&self) -> KernelResult<ServiceStatus> {
+            Ok(ServiceStatus::Healthy)
+        }
+
+        async fn handle_request(&self, request: ServiceRequest) -> KernelResult<ServiceResponse> {
+            Ok(ServiceResponse {
+                id: request.id,
+                status: ResponseStatus::Success,
+                data: serde_json::json!(self.instance_id),
+                headers: HashMap::new(),
+                timestamp: Utc::now(),
+                processing_time_ms: 0,
+            })
+        }
+    }
+
+    async fn replicated_registry() -> ServiceRegistry {
+        let registry = ServiceRegistry::new(Arc::new(MessageBus::new()));
+        for instance_id in ["search-a", "search-b"] {
+            registry.register_service(Arc::new(Replica { instance_id }), serde_json::Value::Null).await.unwrap();
+        }
+        registry
+    }
+
+    #[tokio::test]
+    async fn test_select_instance_rotates_over_healthy_instances() {
+        let registry = replicated_registry().await;
+
+        // The same instance cannot be registered twice
+        let duplicate = registry.register_service(Arc::new(Replica { instance_id: "search-a" }), serde_json::Value::Null).await;
+        assert!(duplicate.is_err());
+
+        let mut selected = Vec::new();
+        for _ in 0..4 {
+            let instance = registry.select_instance("search", InstanceSelection::RoundRobin).await.unwrap();
+            selected.push(instance.instance_key());
+        }
+        assert_eq!(selected, ["search/search-a", "search/search-b", "search/search-a", "search/search-b"]);
+
+        // Unhealthy instances are skipped
+        registry.update_service_status("search/search-b", ServiceStatus::Unhealthy).await.unwrap();
+        for _ in 0..2 {
+            let instance = registry.select_instance("search", InstanceSelection::RoundRobin).await.unwrap();
+            assert_eq!(instance.instance_key(), "search/search-a");
+        }
+
+        registry.update_service_status("search/search-a", ServiceStatus::Unhealthy).await.unwrap();
+        assert!(registry.select_instance("search", InstanceSelection::RoundRobin).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registry_events_are_published_under_a_topic_acl() {
+        let bus = Arc::new(MessageBus::new());
+        bus.set_acl(Some(crate::message::TopicAcl::new())).await;
+        let registry = ServiceRegistry::new(bus.clone());
+        registry.register_service(Arc::new(Replica { instance_id: "search-a" }), serde_json::Value::Null).await.unwrap();
+
+        let published = bus.get_history(1).await.remove(0);
+        assert_eq!(published.topic, "service.events");
+        let event: ServiceEvent = serde_json::from_value(published.payload).unwrap();
+        assert!(matches!(event, ServiceEvent::ServiceRegistered { .. }));
+        assert_eq!(bus.get_stats().await["acl_denials"], serde_json::json!(0));
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_selection_and_grouped_discovery() {
+        let registry = replicated_registry().await;
+
+        let first = registry.select_instance("search", InstanceSelection::LeastRecentlyUsed).await.unwrap();
+        let second = registry.select_instance("search", InstanceSelection::LeastRecentlyUsed).await.unwrap();
+        let third = registry.select_instance("search", InstanceSelection::LeastRecentlyUsed).await.unwrap();
+        assert_ne!(first.instance_key(), second.instance_key());
+        assert_eq!(first.instance_key(), third.instance_key());
+
+        // Calls addressed to the service ID are spread across its instances
+        let mut handled_by = Vec::new();
+        for _ in 0..2 {
+            let request = ServiceRequest {
+                id: Uuid::new_v4().to_string(),
+                method: "query".to_string(),
+                params: serde_json::Value::Null,
+                headers: HashMap::new(),
+                timestamp: Utc::now(),
+                timeout: None,
+            };
+            handled_by.push(registry.call_service("search", request).await.unwrap().data);
+        }
+        handled_by.sort_by_key(|data| data.to_string());
+        assert_eq!(handled_by, [serde_json::json!("search-a"), serde_json::json!("search-b")]);
+
+        // Discovery lists a service's instances together, and limits count services
+        registry.register_service(Arc::new(Named("index")), serde_json::Value::Null).await.unwrap();
+        let query = ServiceQuery { limit: Some(1), ..ServiceQuery::default() };
+        let keys: Vec<String> = registry.discover_services(&query).await.unwrap().iter().map(ServiceMetadata::instance_key).collect();
+        assert_eq!(keys, ["index"]);
+        let keys: Vec<String> = registry.discover_services(&ServiceQuery::default()).await.unwrap().iter().map(ServiceMetadata::instance_key).collect();
+        assert_eq!(keys, ["index", "search/search-a", "search/search-b"]);
+    }
+
+    /// Single-instance service with the given ID
+    struct Named(&'static str);
+
+    #[async_trait]
+    impl Service for Named {
+        fn metadata(&self) -> ServiceMetadata {
+            ServiceMetadata::new(self.0, self.0)
+        }
+
+        async fn start(&self) -> KernelResult<()> {
+            Ok(())
+        }
+
+        async fn stop(&self) -> KernelResult<()> {
+            Ok(())
+        }
+
+        async fn health(&self) -> KernelResult<ServiceStatus> {
+            Ok(ServiceStatus::Healthy)
+        }
+
+        async fn handle_request(&self, request: ServiceRequest) -> KernelResult<ServiceResponse> {
+            Err(KernelError::service_error(self.0.to_string(), format!("Unexpected request {}", request.id)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_instance_keys_of_different_services_do_not_collide() {
+        let registry = replicated_registry().await;
+
+        // A service named like another service's instance is a different key
+        assert_eq!(registry.register_service(Arc::new(Named("search-a")), serde_json::Value::Null).await.unwrap(), "search-a");
+        assert!(registry.register_service(Arc::new(Named("search/search-a")), serde_json::Value::Null).await.is_err());
+        assert_eq!(registry.list_services().await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_services_expire_without_heartbeat() {
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let registry = ServiceRegistry::new(Arc::new(MessageBus::new())).with_clock(clock.clone());
+        for instance_id in ["search-a", "search-b"] {
+            registry.register_service(Arc::new(Replica { instance_id }), serde_json::Value::Null).await.unwrap();
+        }
+
+        clock.advance(chrono::Duration::seconds(60));
+        registry.heartbeat("search/search-a").await.unwrap();
+        assert!(registry.check_expired_services().await.unwrap().is_empty());
+
+        clock.advance(chrono::Duration::seconds(31));
+        assert_eq!(registry.check_expired_services().await.unwrap(), vec!["search/search-b".to_string()]);
+    }
+}
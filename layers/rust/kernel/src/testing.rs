@@ -0,0 +1,317 @@
+//! In-memory kernel for integration tests
+//!
+//! Built for the crate's own tests and, with the `testing` feature, for
+//! tests of dependent crates. A [`TestKernel`] runs a real [`Microkernel`] without touching the
+//! outside world: plugins are not discovered from disk, background resource
+//! monitoring is off, the bus, registry and resource manager read time from a
+//! shared [`MockClock`], and resource capacities are whatever the test configures.
+//! Helpers register [`FakeService`]s, stamped by the same clock, and inspect
+//! the events published on the message bus.
+
+use chrono::{DateTime, TimeZone, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::clock::{Clock, MockClock};
+use crate::error::KernelResult;
+use crate::kernel::{KernelConfig, Microkernel};
+use crate::message::{Message, MessageBus};
+use crate::resource::{ResourceLimits, ResourceManager, ResourceType};
+use crate::service::{
+    ResponseStatus, Service, ServiceMetadata, ServiceRegistry, ServiceRequest, ServiceResponse, ServiceStatus,
+};
+
+/// Time a [`TestKernel`]'s clock and a new [`FakeService`]'s clock start at
+fn test_epoch() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+}
+
+/// Builder of a [`TestKernel`]
+pub struct TestKernelBuilder {
+    limits: ResourceLimits,
+    services: Vec<Arc<dyn Service>>,
+}
+
+impl TestKernelBuilder {
+    fn new() -> Self {
+        Self {
+            limits: ResourceLimits {
+                max_cpu: 4,
+                max_memory: 1024,
+                max_disk: 10,
+                max_network: 100,
+                max_gpu: 0,
+                max_db_connections: 10,
+            },
+            services: Vec::new(),
+        }
+    }
+
+    /// Set the capacity of one resource
+    pub fn with_capacity(mut self, resource_type: ResourceType, capacity: u64) -> Self {
+        match resource_type {
+            ResourceType::Cpu => self.limits.max_cpu = capacity as u32,
+            ResourceType::Memory => self.limits.max_memory = capacity,
+            ResourceType::Disk => self.limits.max_disk = capacity,
+            ResourceType::Network => self.limits.max_network = capacity as u32,
+            ResourceType::Gpu => self.limits.max_gpu = capacity as u32,
+            ResourceType::DatabaseConnections => self.limits.max_db_connections = capacity as u32,
+            ResourceType::Custom => {}
+        }
+        self
+    }
+
+    /// Replace every resource capacity
+    pub fn with_resource_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Register a service once the kernel is up
+    pub fn with_service(mut self, service: Arc<dyn Service>) -> Self {
+        self.services.push(service);
+        self
+    }
+
+    /// Start the kernel and register the configured services
+    pub async fn build(self) -> KernelResult<TestKernel> {
+        let clock = Arc::new(MockClock::new(test_epoch()));
+
+        let message_bus = Arc::new(MessageBus::new().with_clock(clock.clone()));
+        let resource_manager = Arc::new(ResourceManager::new(self.limits.clone()).with_clock(clock.clone()));
+        let service_registry = Arc::new(ServiceRegistry::new(message_bus.clone()).with_clock(clock.clone()));
+        let config = KernelConfig {
+            resource_limits: self.limits,
+            plugin_dirs: Vec::new(),
+            auto_discover_plugins: false,
+            enable_resource_monitoring: false,
+            ..KernelConfig::default()
+        };
+
+        let kernel = Microkernel::from_components(config, message_bus, resource_manager, service_registry);
+        kernel.start().await?;
+
+        let test_kernel = TestKernel { kernel, clock };
+        for service in self.services {
+            test_kernel.kernel.service_registry().register_service(service, serde_json::Value::Null).await?;
+        }
+        Ok(test_kernel)
+    }
+}
+
+/// Running in-memory kernel
+pub struct TestKernel {
+    kernel: Microkernel,
+    clock: Arc<MockClock>,
+}
+
+impl TestKernel {
+    /// Configure a test kernel
+    pub fn builder() -> TestKernelBuilder {
+        TestKernelBuilder::new()
+    }
+
+    /// Start a test kernel with default capacities and no services
+    pub async fn start() -> KernelResult<Self> {
+        Self::builder().build().await
+    }
+
+    /// The kernel under test
+    pub fn kernel(&self) -> &Microkernel {
+        &self.kernel
+    }
+
+    /// Clock driving every kernel component
+    pub fn clock(&self) -> Arc<MockClock> {
+        self.clock.clone()
+    }
+
+    /// Register a fake service on the kernel's clock, returning its instance key
+    pub async fn register_service(&self, service: FakeService) -> KernelResult<String> {
+        let service = service.with_clock(self.clock.clone());
+        self.kernel.service_registry().register_service(Arc::new(service), serde_json::Value::Null).await
+    }
+
+    /// Messages published on `topic`, oldest first
+    pub async fn events(&self, topic: &str) -> Vec<Message> {
+        let mut events: Vec<Message> = self.kernel.message_bus().get_history(usize::MAX).await
+            .into_iter()
+            .filter(|message| message.topic == topic)
+            .collect();
+        events.reverse();
+        events
+    }
+
+    /// First message on `topic` matching `predicate`
+    ///
+    /// # Panics
+    ///
+    /// Panics, listing the topic's messages, when none matches.
+    pub async fn expect_event<F>(&self, topic: &str, predicate: F) -> Message
+    where
+        F: Fn(&Message) -> bool,
+    {
+        let events = self.events(topic).await;
+        match events.iter().find(|message| predicate(message)) {
+            Some(message) => message.clone(),
+            None => panic!(
+                "No matching event on '{}'; published: {:#?}",
+                topic,
+                events.iter().map(|message| &message.payload).collect::<Vec<_>>()
+            ),
+        }
+    }
+
+    /// Stop the kernel
+    pub async fn stop(&self) -> KernelResult<()> {
+        self.kernel.stop().await
+    }
+}
+
+/// Service answering every method with a canned response, recording requests
+pub struct FakeService {
+    metadata: ServiceMetadata,
+    responses: HashMap<String, serde_json::Value>,
+    requests: Arc<Mutex<Vec<ServiceRequest>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl FakeService {
+    /// Create a healthy plugin service on its own clock, stopped at the test epoch
+    pub fn new(id: impl Into<String>) -> Self {
+        let id = id.into();
+        let now = test_epoch();
+        Self {
+            metadata: ServiceMetadata::new(id.clone(), id)
+                .with_version("0.0.0")
+                .with_description("Fake service")
+                .with_endpoint("memory")
+                .with_registered_at(now),
+            responses: HashMap::new(),
+            requests: Arc::new(Mutex::new(Vec::new())),
+            clock: Arc::new(MockClock::new(now)),
+        }
+    }
+
+    /// Stamp the service's metadata and responses with `clock`
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.metadata = self.metadata.with_registered_at(clock.now());
+        self.clock = clock;
+        self
+    }
+
+    /// Advertise a capability
+    pub fn with_capability(mut self, capability: impl Into<String>) -> Self {
+        self.metadata = self.metadata.with_capability(capability);
+        self
+    }
+
+    /// Answer `method` with `data`; other methods echo their params
+    pub fn with_response(mut self, method: impl Into<String>, data: serde_json::Value) -> Self {
+        self.responses.insert(method.into(), data);
+        self
+    }
+
+    /// Handle to the requests the service receives
+    pub fn requests(&self) -> Arc<Mutex<Vec<ServiceRequest>>> {
+        self.requests.clone()
+    }
+}
+
+#[async_trait]
+impl Service for FakeService {
+    fn metadata(&self) -> ServiceMetadata {
+        self.metadata.clone()
+    }
+
+    async fn start(&self) -> KernelResult<()> {
+        Ok(())
+    }
+
+    async fn stop(&self) -> KernelResult<()> {
+        Ok(())
+    }
+
+    async fn health(&self) -> KernelResult<ServiceStatus> {
+        Ok(ServiceStatus::Healthy)
+    }
+
+    async fn handle_request(&self, request: ServiceRequest) -> KernelResult<ServiceResponse> {
+        let data = self.responses.get(&request.method).cloned().unwrap_or_else(|| request.params.clone());
+        let id = request.id.clone();
+        self.requests.lock().unwrap().push(request);
+        Ok(ServiceResponse {
+            id,
+            status: ResponseStatus::Success,
+            data,
+            headers: HashMap::new(),
+            timestamp: self.clock.now(),
+            processing_time_ms: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{ResourcePriority, ResourceRequest};
+
+    #[tokio::test]
+    async fn test_service_registration_emits_events() {
+        let kernel = TestKernel::builder()
+            .with_capacity(ResourceType::Memory, 256)
+            .build()
+            .await
+            .unwrap();
+
+        let service_id = kernel.register_service(FakeService::new("echo").with_capability("echo")).await.unwrap();
+        kernel.kernel().service_registry().unregister_service(&service_id).await.unwrap();
+
+        let registered = kernel.expect_event("service.events", |message| {
+            message.payload["ServiceRegistered"]["service_id"] == "echo"
+        }).await;
+        assert_eq!(registered.payload["ServiceRegistered"]["metadata"]["capabilities"], serde_json::json!(["echo"]));
+        kernel.expect_event("service.events", |message| {
+            message.payload["ServiceUnregistered"]["service_id"] == "echo"
+        }).await;
+        assert_eq!(kernel.events("service.events").await.len(), 2);
+
+        // The configured capacity bounds allocations
+        let request = ResourceRequest {
+            requester: "test".to_string(),
+            resource_type: ResourceType::Memory,
+            amount: 512,
+            priority: ResourcePriority::Normal,
+            timeout: None,
+            lease: None,
+            metadata: HashMap::new(),
+        };
+        assert!(kernel.kernel().resource_manager().try_request_resources(request).await.is_err());
+
+        kernel.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fake_services_follow_the_kernel_clock() {
+        let kernel = TestKernel::start().await.unwrap();
+        kernel.clock().advance(chrono::Duration::hours(1));
+        let service_id = kernel.register_service(FakeService::new("clocked")).await.unwrap();
+
+        let registry = kernel.kernel().service_registry();
+        assert_eq!(registry.get_service(&service_id).await.unwrap().registered_at, kernel.clock().now());
+        let request = ServiceRequest {
+            id: "r-1".to_string(),
+            method: "ping".to_string(),
+            params: serde_json::Value::Null,
+            headers: HashMap::new(),
+            timestamp: kernel.clock().now(),
+            timeout: None,
+        };
+        let response = registry.call_service(&service_id, request).await.unwrap();
+        assert_eq!(response.timestamp, kernel.clock().now());
+
+        kernel.stop().await.unwrap();
+    }
+}
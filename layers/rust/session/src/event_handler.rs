@@ -29,6 +29,9 @@ impl SessionEventHandler for LoggingEventHandler {
             SessionEvent::Cleaned { session_ids, reason } => {
                 info!("Sessions cleaned: {} sessions - {}", session_ids.len(), reason);
             }
+            SessionEvent::Evicted { session_id, reason } => {
+                warn!("Session evicted: {} - {}", session_id, reason);
+            }
         }
         Ok(())
     }
@@ -7,6 +7,7 @@ pub mod types;
 pub mod session_manager;
 pub mod session_store;
 pub mod memory_store;
+pub mod session_lock;
 pub mod event_handler;
 
 /// Result type alias for session operations
@@ -18,4 +19,5 @@ pub use types::*;
 pub use session_manager::*;
 pub use session_store::*;
 pub use memory_store::*;
+pub use session_lock::*;
 pub use event_handler::*;
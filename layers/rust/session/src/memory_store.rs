@@ -1,17 +1,43 @@
 //! Memory-based Session Store for Sira Session
 
-use crate::{SessionResult, Session, SessionQuery, SessionStats, CleanupPolicy, SessionStore, SessionUpdate, SessionState};
+use crate::{SessionResult, Session, SessionQuery, SessionStats, CleanupPolicy, SessionStore, SessionUpdate, SessionState, SessionEvent, SessionEventHandler};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc, Duration};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
+/// Tag marking a session that is never evicted
+pub const PINNED_TAG: &str = "pinned";
+
+/// Recency and size of a stored session
+#[derive(Debug, Clone, Copy)]
+struct AccessRecord {
+    last_access: u64,
+    size_bytes: usize,
+}
+
 /// In-memory session store implementation
+///
+/// The store holds at most `max_capacity` sessions and, if set, `max_bytes`
+/// of serialized session data. Storing past either cap evicts the least
+/// recently accessed sessions that are not tagged [`PINNED_TAG`].
+///
+/// Sessions are indexed by tag, so queries filtering on tags only visit
+/// sessions carrying them.
 pub struct MemorySessionStore {
     sessions: Arc<RwLock<HashMap<String, Session>>>,
     max_capacity: usize,
+    max_bytes: Option<usize>,
+    access: Arc<Mutex<HashMap<String, AccessRecord>>>,
+    /// IDs of the sessions carrying each tag
+    tag_index: Arc<Mutex<HashMap<String, HashSet<String>>>>,
+    /// Largest fencing token that has written each session
+    fencing_tokens: Arc<Mutex<HashMap<String, u64>>>,
+    clock: AtomicU64,
+    event_handlers: Vec<Arc<dyn SessionEventHandler>>,
 }
 
 impl MemorySessionStore {
@@ -20,48 +46,121 @@ impl MemorySessionStore {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             max_capacity,
+            max_bytes: None,
+            access: Arc::new(Mutex::new(HashMap::new())),
+            tag_index: Arc::new(Mutex::new(HashMap::new())),
+            fencing_tokens: Arc::new(Mutex::new(HashMap::new())),
+            clock: AtomicU64::new(0),
+            event_handlers: Vec::new(),
         }
     }
 
-    /// Create with default capacity
-    pub fn default() -> Self {
-        Self::new(10000) // Default 10k sessions
+    /// Cap the serialized size of all stored sessions
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
     }
-}
 
-impl Default for MemorySessionStore {
-    fn default() -> Self {
-        Self::default()
+    /// Notify a handler of evictions
+    pub fn with_event_handler(mut self, handler: Arc<dyn SessionEventHandler>) -> Self {
+        self.event_handlers.push(handler);
+        self
     }
-}
 
-#[async_trait]
-impl SessionStore for MemorySessionStore {
-    async fn store(&self, session: &Session) -> SessionResult<()> {
-        let mut sessions = self.sessions.write().await;
+    /// Record an access to a session
+    fn touch(&self, session_id: &str, size_bytes: Option<usize>) {
+        let tick = self.clock.fetch_add(1, Ordering::Relaxed);
+        let mut access = self.access.lock().unwrap_or_else(|e| e.into_inner());
+        let record = access.entry(session_id.to_string()).or_insert(AccessRecord { last_access: tick, size_bytes: 0 });
+        record.last_access = tick;
+        if let Some(size_bytes) = size_bytes {
+            record.size_bytes = size_bytes;
+        }
+    }
+
+    fn forget(&self, session_id: &str) {
+        self.access.lock().unwrap_or_else(|e| e.into_inner()).remove(session_id);
+        self.fencing_tokens.lock().unwrap_or_else(|e| e.into_inner()).remove(session_id);
+    }
 
-        // Check capacity limit
-        if sessions.len() >= self.max_capacity && !sessions.contains_key(&session.id) {
-            return Err(crate::SessionError::StoreError(
-                format!("Session store capacity exceeded: {}", self.max_capacity)
-            ));
+    fn index_tags(&self, session: &Session) {
+        let mut tag_index = self.tag_index.lock().unwrap_or_else(|e| e.into_inner());
+        for tag in &session.tags {
+            tag_index.entry(tag.clone()).or_default().insert(session.id.clone());
         }
+    }
 
-        sessions.insert(session.id.clone(), session.clone());
-        debug!("Stored session: {}", session.id);
+    fn unindex_tags(&self, session: &Session) {
+        let mut tag_index = self.tag_index.lock().unwrap_or_else(|e| e.into_inner());
+        for tag in &session.tags {
+            if let Some(ids) = tag_index.get_mut(tag) {
+                ids.remove(&session.id);
+                if ids.is_empty() {
+                    tag_index.remove(tag);
+                }
+            }
+        }
+    }
 
-        Ok(())
+    /// IDs of the sessions carrying all of `tags`
+    fn tagged_session_ids(&self, tags: &[String]) -> HashSet<String> {
+        let tag_index = self.tag_index.lock().unwrap_or_else(|e| e.into_inner());
+        let mut sets = tags.iter().map(|tag| tag_index.get(tag));
+        let Some(Some(first)) = sets.next() else {
+            return HashSet::new();
+        };
+
+        let mut ids = first.clone();
+        for set in sets {
+            match set {
+                Some(set) => ids.retain(|id| set.contains(id)),
+                None => return HashSet::new(),
+            }
+        }
+        ids
     }
 
-    async fn get(&self, session_id: &str) -> SessionResult<Option<Session>> {
-        let sessions = self.sessions.read().await;
-        Ok(sessions.get(session_id).cloned())
+    fn serialized_size(session: &Session) -> usize {
+        serde_json::to_vec(session).map(|json| json.len()).unwrap_or(1024)
     }
 
-    async fn update(&self, session_id: &str, updates: &[SessionUpdate]) -> SessionResult<()> {
-        let mut sessions = self.sessions.write().await;
+    /// Sessions to evict so that `incoming` fits within the caps, oldest access first
+    fn plan_eviction(&self, sessions: &HashMap<String, Session>, incoming: &Session, incoming_size: usize) -> SessionResult<Vec<String>> {
+        let access = self.access.lock().unwrap_or_else(|e| e.into_inner());
+        let replaced_size = access.get(&incoming.id).map(|record| record.size_bytes).unwrap_or(0);
+
+        let mut count = sessions.len() + usize::from(!sessions.contains_key(&incoming.id));
+        let mut bytes = access.values().map(|record| record.size_bytes).sum::<usize>() - replaced_size + incoming_size;
+        let over_caps = |count: usize, bytes: usize| {
+            count > self.max_capacity || self.max_bytes.is_some_and(|max_bytes| bytes > max_bytes)
+        };
+
+        let mut candidates: Vec<(&String, AccessRecord)> = sessions.iter()
+            .filter(|(id, session)| **id != incoming.id && !session.tags.iter().any(|tag| tag == PINNED_TAG))
+            .map(|(id, _)| (id, access.get(id).copied().unwrap_or(AccessRecord { last_access: 0, size_bytes: 0 })))
+            .collect();
+        candidates.sort_by_key(|(id, record)| (record.last_access, (*id).clone()));
+
+        let mut victims = Vec::new();
+        let mut candidates = candidates.into_iter();
+        while over_caps(count, bytes) {
+            let (id, record) = candidates.next().ok_or_else(|| crate::SessionError::StoreError(format!(
+                "Session store capacity exceeded: {} sessions{}; remaining sessions are pinned",
+                self.max_capacity,
+                self.max_bytes.map(|max_bytes| format!(", {} bytes", max_bytes)).unwrap_or_default(),
+            )))?;
+            count -= 1;
+            bytes -= record.size_bytes;
+            victims.push(id.clone());
+        }
 
+        Ok(victims)
+    }
+
+    /// Apply updates to a stored session
+    fn apply_updates(&self, sessions: &mut HashMap<String, Session>, session_id: &str, updates: &[SessionUpdate]) -> SessionResult<()> {
         if let Some(session) = sessions.get_mut(session_id) {
+            self.unindex_tags(session);
             for update in updates {
                 match update {
                     SessionUpdate::SetData { key, value } => {
@@ -85,7 +184,7 @@ impl SessionStore for MemorySessionStore {
                         session.state = *state;
                     }
                     SessionUpdate::ExtendExpiry { seconds } => {
-                        session.expires_at = session.expires_at + Duration::seconds(*seconds as i64);
+                        session.expires_at += Duration::seconds(*seconds as i64);
                     }
                     SessionUpdate::IncrementVersion => {
                         session.version += 1;
@@ -94,6 +193,8 @@ impl SessionStore for MemorySessionStore {
             }
 
             session.updated_at = Utc::now();
+            self.index_tags(session);
+            self.touch(session_id, Some(Self::serialized_size(session)));
             debug!("Updated session: {} with {} changes", session_id, updates.len());
 
             Ok(())
@@ -102,15 +203,102 @@ impl SessionStore for MemorySessionStore {
         }
     }
 
+    async fn emit_evictions(&self, session_ids: &[String]) {
+        for session_id in session_ids {
+            let event = SessionEvent::Evicted {
+                session_id: session_id.clone(),
+                reason: "least recently used".to_string(),
+            };
+            for handler in &self.event_handlers {
+                if let Err(e) = handler.handle_event(&event).await {
+                    warn!("Eviction event handler error: {:?}", e);
+                }
+            }
+        }
+    }
+
+    /// Create with default capacity
+    pub fn default() -> Self {
+        Self::new(10000) // Default 10k sessions
+    }
+}
+
+impl Default for MemorySessionStore {
+    fn default() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn store(&self, session: &Session) -> SessionResult<()> {
+        let mut sessions = self.sessions.write().await;
+
+        // Make room by evicting the least recently accessed sessions
+        let size_bytes = Self::serialized_size(session);
+        let evicted = self.plan_eviction(&sessions, session, size_bytes)?;
+        for session_id in &evicted {
+            if let Some(victim) = sessions.remove(session_id) {
+                self.unindex_tags(&victim);
+            }
+            self.forget(session_id);
+            info!("Evicted session: {}", session_id);
+        }
+
+        if let Some(replaced) = sessions.insert(session.id.clone(), session.clone()) {
+            self.unindex_tags(&replaced);
+        }
+        self.index_tags(session);
+        self.touch(&session.id, Some(size_bytes));
+        drop(sessions);
+        debug!("Stored session: {}", session.id);
+
+        self.emit_evictions(&evicted).await;
+        Ok(())
+    }
+
+    async fn get(&self, session_id: &str) -> SessionResult<Option<Session>> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(session_id).cloned();
+        if session.is_some() {
+            self.touch(session_id, None);
+        }
+        Ok(session)
+    }
+
+    async fn update(&self, session_id: &str, updates: &[SessionUpdate]) -> SessionResult<()> {
+        let mut sessions = self.sessions.write().await;
+        self.apply_updates(&mut sessions, session_id, updates)
+    }
+
+    async fn update_fenced(&self, session_id: &str, fencing_token: u64, updates: &[SessionUpdate]) -> SessionResult<()> {
+        let mut sessions = self.sessions.write().await;
+
+        let mut fencing_tokens = self.fencing_tokens.lock().unwrap_or_else(|e| e.into_inner());
+        let latest = fencing_tokens.get(session_id).copied().unwrap_or(0);
+        if fencing_token < latest {
+            return Err(crate::SessionError::ConcurrencyError(format!(
+                "Stale fencing token {} for session {}; {} has already written it",
+                fencing_token, session_id, latest
+            )));
+        }
+
+        self.apply_updates(&mut sessions, session_id, updates)?;
+        fencing_tokens.insert(session_id.to_string(), fencing_token);
+        Ok(())
+    }
+
     async fn delete(&self, session_id: &str) -> SessionResult<bool> {
         let mut sessions = self.sessions.write().await;
-        let removed = sessions.remove(session_id).is_some();
+        let removed = sessions.remove(session_id);
 
-        if removed {
+        if let Some(session) = &removed {
+            self.unindex_tags(session);
+            self.forget(session_id);
             debug!("Deleted session: {}", session_id);
         }
 
-        Ok(removed)
+        Ok(removed.is_some())
     }
 
     async fn exists(&self, session_id: &str) -> SessionResult<bool> {
@@ -120,7 +308,15 @@ impl SessionStore for MemorySessionStore {
 
     async fn query(&self, query: &SessionQuery) -> SessionResult<Vec<Session>> {
         let sessions = self.sessions.read().await;
-        let mut results: Vec<Session> = sessions.values()
+        let candidates: Vec<&Session> = match query.tags.as_deref() {
+            Some(tags) if !tags.is_empty() => self.tagged_session_ids(tags)
+                .iter()
+                .filter_map(|id| sessions.get(id))
+                .collect(),
+            _ => sessions.values().collect(),
+        };
+
+        let mut results: Vec<Session> = candidates.into_iter()
             .filter(|session| {
                 // Apply filters
                 if let Some(user_id) = &query.user_id {
@@ -135,12 +331,6 @@ impl SessionStore for MemorySessionStore {
                     }
                 }
 
-                if let Some(tags) = &query.tags {
-                    if !tags.iter().all(|tag| session.tags.contains(tag)) {
-                        return false;
-                    }
-                }
-
                 if let Some(created_after) = query.created_after {
                     if session.created_at < created_after {
                         return false;
@@ -207,7 +397,10 @@ impl SessionStore for MemorySessionStore {
             .collect();
 
         for session_id in to_remove {
-            sessions.remove(&session_id);
+            if let Some(session) = sessions.remove(&session_id) {
+                self.unindex_tags(&session);
+            }
+            self.forget(&session_id);
         }
 
         let removed_count = initial_count - sessions.len();
@@ -308,7 +501,12 @@ impl crate::SessionStoreFactory for MemorySessionStoreFactory {
             .and_then(|v| v.as_u64())
             .unwrap_or(10000) as usize;
 
-        Ok(Box::new(MemorySessionStore::new(max_capacity)))
+        let mut store = MemorySessionStore::new(max_capacity);
+        if let Some(max_bytes) = config.get("max_bytes").and_then(|v| v.as_u64()) {
+            store = store.with_max_bytes(max_bytes as usize);
+        }
+
+        Ok(Box::new(store))
     }
 
     fn store_type(&self) -> &str {
@@ -323,6 +521,10 @@ impl crate::SessionStoreFactory for MemorySessionStoreFactory {
                     "type": "integer",
                     "description": "Maximum number of sessions to store",
                     "default": 10000
+                },
+                "max_bytes": {
+                    "type": "integer",
+                    "description": "Maximum serialized size of all sessions; unlimited if unset"
                 }
             }
         })
@@ -382,6 +584,25 @@ mod tests {
         assert!(!store.exists("test_session").await.unwrap());
     }
 
+    #[tokio::test]
+    async fn test_memory_store_rejects_stale_fencing_tokens() {
+        let store = MemorySessionStore::default();
+        store.store(&create_test_session()).await.unwrap();
+        let set = |value: &str| [SessionUpdate::SetData { key: "owner".to_string(), value: serde_json::json!(value) }];
+
+        store.update_fenced("test_session", 1, &set("first")).await.unwrap();
+        store.update_fenced("test_session", 2, &set("second")).await.unwrap();
+
+        // The first holder's lease expired mid-update; its late write is refused
+        let result = store.update_fenced("test_session", 1, &set("first")).await;
+        assert!(matches!(result, Err(crate::SessionError::ConcurrencyError(_))));
+        let session = store.get("test_session").await.unwrap().unwrap();
+        assert_eq!(session.data.get("owner"), Some(&serde_json::json!("second")));
+
+        // The current holder keeps writing
+        store.update_fenced("test_session", 2, &set("again")).await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_memory_store_query() {
         let store = MemorySessionStore::default();
@@ -441,4 +662,77 @@ mod tests {
         assert_eq!(stats.active_sessions, 1);
         assert!(stats.storage_size_bytes > 0);
     }
+
+    /// Collects eviction events
+    #[derive(Default)]
+    struct EvictionRecorder {
+        evicted: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl SessionEventHandler for EvictionRecorder {
+        async fn handle_event(&self, event: &SessionEvent) -> SessionResult<()> {
+            if let SessionEvent::Evicted { session_id, .. } = event {
+                self.evicted.lock().unwrap().push(session_id.clone());
+            }
+            Ok(())
+        }
+    }
+
+    fn session_with_id(id: &str) -> Session {
+        let mut session = create_test_session();
+        session.id = id.to_string();
+        session
+    }
+
+    #[tokio::test]
+    async fn test_lru_eviction_past_entry_cap() {
+        let recorder = Arc::new(EvictionRecorder::default());
+        let store = MemorySessionStore::new(3).with_event_handler(recorder.clone());
+
+        let mut pinned = session_with_id("pinned");
+        pinned.tags.push(PINNED_TAG.to_string());
+        store.store(&pinned).await.unwrap();
+        store.store(&session_with_id("idle")).await.unwrap();
+        store.store(&session_with_id("active")).await.unwrap();
+
+        // Touching the active session makes the idle one the least recently used
+        store.get("active").await.unwrap();
+        store.store(&session_with_id("new_1")).await.unwrap();
+        assert!(!store.exists("idle").await.unwrap());
+        assert!(store.exists("active").await.unwrap());
+
+        store.store(&session_with_id("new_2")).await.unwrap();
+        assert!(!store.exists("active").await.unwrap());
+        assert!(store.exists("pinned").await.unwrap());
+        assert!(store.exists("new_1").await.unwrap());
+        assert!(store.exists("new_2").await.unwrap());
+        assert_eq!(*recorder.evicted.lock().unwrap(), vec!["idle", "active"]);
+
+        // Re-storing an existing session never evicts
+        store.store(&session_with_id("new_1")).await.unwrap();
+        assert_eq!(recorder.evicted.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_lru_eviction_past_byte_cap() {
+        let session_size = MemorySessionStore::serialized_size(&session_with_id("s_0"));
+        let store = MemorySessionStore::new(100).with_max_bytes(session_size * 2 + session_size / 2);
+
+        for i in 0..4 {
+            store.store(&session_with_id(&format!("s_{}", i))).await.unwrap();
+        }
+
+        assert!(!store.exists("s_0").await.unwrap());
+        assert!(!store.exists("s_1").await.unwrap());
+        assert!(store.exists("s_2").await.unwrap());
+        assert!(store.exists("s_3").await.unwrap());
+
+        // A store holding only pinned sessions rejects new ones instead of evicting
+        let full = MemorySessionStore::new(1);
+        let mut pinned = session_with_id("pinned");
+        pinned.tags.push(PINNED_TAG.to_string());
+        full.store(&pinned).await.unwrap();
+        assert!(full.store(&session_with_id("other")).await.is_err());
+    }
 }
@@ -0,0 +1,398 @@
+//! Distributed Session Lock for Sira Session
+//!
+//! Serializes session mutations across gateway instances that share a session
+//! store. A lock is a lease in a shared key-value store (SET NX with TTL); every
+//! acquisition is issued a fencing token, and renewal and release only succeed
+//! while the caller's token still owns the lease. [`MemoryLeaseStore`] locks
+//! within one process; with the `redis` feature, [`RedisLeaseStore`] shares
+//! leases between instances through Redis. Session stores reject writes
+//! carrying a token older than the last one that wrote the session.
+
+use crate::{SessionError, SessionResult};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+/// Lease storage trait - the atomic primitives a shared store must provide
+#[async_trait]
+pub trait LeaseStore: Send + Sync {
+    /// Take the lease if it is free or expired, returning a new fencing token
+    async fn try_acquire(&self, key: &str, ttl: Duration) -> SessionResult<Option<u64>>;
+
+    /// Extend the lease if it is still held with the given fencing token
+    async fn renew(&self, key: &str, fencing_token: u64, ttl: Duration) -> SessionResult<bool>;
+
+    /// Delete the lease if it is still held with the given fencing token
+    async fn release(&self, key: &str, fencing_token: u64) -> SessionResult<bool>;
+}
+
+#[derive(Debug, Clone)]
+struct Lease {
+    fencing_token: u64,
+    expires_at: Instant,
+}
+
+/// In-memory lease store, shared between lock clients in a single process
+#[derive(Default)]
+pub struct MemoryLeaseStore {
+    leases: Mutex<HashMap<String, Lease>>,
+    /// Last fencing token issued per key; never reset so tokens stay monotonic
+    tokens: Mutex<HashMap<String, u64>>,
+}
+
+impl MemoryLeaseStore {
+    /// Create a new memory lease store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl LeaseStore for MemoryLeaseStore {
+    async fn try_acquire(&self, key: &str, ttl: Duration) -> SessionResult<Option<u64>> {
+        let mut leases = self.leases.lock().await;
+        let now = Instant::now();
+
+        if leases.get(key).is_some_and(|lease| lease.expires_at > now) {
+            return Ok(None);
+        }
+
+        let mut tokens = self.tokens.lock().await;
+        let token = tokens.entry(key.to_string()).or_insert(0);
+        *token += 1;
+
+        leases.insert(key.to_string(), Lease {
+            fencing_token: *token,
+            expires_at: now + ttl,
+        });
+        Ok(Some(*token))
+    }
+
+    async fn renew(&self, key: &str, fencing_token: u64, ttl: Duration) -> SessionResult<bool> {
+        let mut leases = self.leases.lock().await;
+        let now = Instant::now();
+
+        match leases.get_mut(key) {
+            Some(lease) if lease.fencing_token == fencing_token && lease.expires_at > now => {
+                lease.expires_at = now + ttl;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn release(&self, key: &str, fencing_token: u64) -> SessionResult<bool> {
+        let mut leases = self.leases.lock().await;
+
+        match leases.get(key) {
+            Some(lease) if lease.fencing_token == fencing_token => {
+                leases.remove(key);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Lease store shared between instances through Redis
+///
+/// A lease is a key holding its fencing token with a TTL; tokens come from a
+/// counter key next to it that never expires, so they keep increasing across
+/// holders. Each operation runs as a Lua script, so check and write are atomic.
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct RedisLeaseStore {
+    connection: redis::aio::MultiplexedConnection,
+}
+
+#[cfg(feature = "redis")]
+impl RedisLeaseStore {
+    const ACQUIRE_SCRIPT: &'static str = r#"
+        if redis.call('EXISTS', KEYS[1]) == 1 then
+            return false
+        end
+        local token = redis.call('INCR', KEYS[2])
+        redis.call('SET', KEYS[1], token, 'PX', ARGV[1])
+        return token
+    "#;
+
+    const RENEW_SCRIPT: &'static str = r#"
+        if redis.call('GET', KEYS[1]) == ARGV[1] then
+            return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+        end
+        return 0
+    "#;
+
+    const RELEASE_SCRIPT: &'static str = r#"
+        if redis.call('GET', KEYS[1]) == ARGV[1] then
+            return redis.call('DEL', KEYS[1])
+        end
+        return 0
+    "#;
+
+    /// Connect to the Redis server at `url`
+    pub async fn connect(url: &str) -> SessionResult<Self> {
+        let client = redis::Client::open(url).map_err(Self::store_error)?;
+        let connection = client.get_multiplexed_tokio_connection().await.map_err(Self::store_error)?;
+        Ok(Self { connection })
+    }
+
+    fn store_error(e: redis::RedisError) -> SessionError {
+        SessionError::StoreError(format!("Redis lease store error: {}", e))
+    }
+
+    fn ttl_millis(ttl: Duration) -> u64 {
+        (ttl.as_millis() as u64).max(1)
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl LeaseStore for RedisLeaseStore {
+    async fn try_acquire(&self, key: &str, ttl: Duration) -> SessionResult<Option<u64>> {
+        redis::Script::new(Self::ACQUIRE_SCRIPT)
+            .key(key)
+            .key(format!("{}:fencing_token", key))
+            .arg(Self::ttl_millis(ttl))
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(Self::store_error)
+    }
+
+    async fn renew(&self, key: &str, fencing_token: u64, ttl: Duration) -> SessionResult<bool> {
+        redis::Script::new(Self::RENEW_SCRIPT)
+            .key(key)
+            .arg(fencing_token)
+            .arg(Self::ttl_millis(ttl))
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(Self::store_error)
+    }
+
+    async fn release(&self, key: &str, fencing_token: u64) -> SessionResult<bool> {
+        redis::Script::new(Self::RELEASE_SCRIPT)
+            .key(key)
+            .arg(fencing_token)
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(Self::store_error)
+    }
+}
+
+/// Distributed lock around session mutations
+#[derive(Clone)]
+pub struct SessionLock {
+    store: Arc<dyn LeaseStore>,
+    lease_ttl: Duration,
+    acquire_timeout: Duration,
+    retry_interval: Duration,
+    key_prefix: String,
+}
+
+impl SessionLock {
+    /// Create a new session lock over a shared lease store
+    pub fn new(store: Arc<dyn LeaseStore>, lease_ttl: Duration) -> Self {
+        Self {
+            store,
+            lease_ttl,
+            acquire_timeout: Duration::from_secs(5),
+            retry_interval: Duration::from_millis(50),
+            key_prefix: "session_lock:".to_string(),
+        }
+    }
+
+    /// Set how long `acquire` waits for a held lock
+    pub fn with_acquire_timeout(mut self, timeout: Duration) -> Self {
+        self.acquire_timeout = timeout;
+        self
+    }
+
+    /// Set the delay between acquisition attempts
+    pub fn with_retry_interval(mut self, interval: Duration) -> Self {
+        self.retry_interval = interval;
+        self
+    }
+
+    /// Lease duration
+    pub fn lease_ttl(&self) -> Duration {
+        self.lease_ttl
+    }
+
+    /// Try to take the lock once without waiting
+    pub async fn try_acquire(&self, session_id: &str) -> SessionResult<Option<SessionLockGuard>> {
+        let key = format!("{}{}", self.key_prefix, session_id);
+
+        match self.store.try_acquire(&key, self.lease_ttl).await? {
+            Some(fencing_token) => {
+                debug!("Acquired lock for session {} (token {})", session_id, fencing_token);
+                Ok(Some(SessionLockGuard::new(self.store.clone(), key, fencing_token, self.lease_ttl)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Take the lock, waiting up to the acquire timeout
+    pub async fn acquire(&self, session_id: &str) -> SessionResult<SessionLockGuard> {
+        let deadline = Instant::now() + self.acquire_timeout;
+
+        loop {
+            if let Some(guard) = self.try_acquire(session_id).await? {
+                return Ok(guard);
+            }
+
+            if Instant::now() >= deadline {
+                return Err(SessionError::ConcurrencyError(format!(
+                    "Timed out acquiring lock for session {}",
+                    session_id
+                )));
+            }
+
+            tokio::time::sleep(self.retry_interval).await;
+        }
+    }
+
+    /// Run an operation while holding the lock, renewing the lease as needed
+    pub async fn with_lock<F, Fut, T>(&self, session_id: &str, operation: F) -> SessionResult<T>
+    where
+        F: FnOnce(u64) -> Fut,
+        Fut: Future<Output = SessionResult<T>>,
+    {
+        let guard = self.acquire(session_id).await?;
+        let result = operation(guard.fencing_token()).await;
+
+        if !guard.release().await? {
+            warn!("Lock for session {} expired before release", session_id);
+        }
+
+        result
+    }
+}
+
+/// Held session lock; renews its lease in the background until released
+pub struct SessionLockGuard {
+    store: Arc<dyn LeaseStore>,
+    key: String,
+    fencing_token: u64,
+    lost: Arc<AtomicBool>,
+    renewal_task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl SessionLockGuard {
+    fn new(store: Arc<dyn LeaseStore>, key: String, fencing_token: u64, ttl: Duration) -> Self {
+        let lost = Arc::new(AtomicBool::new(false));
+
+        let renewal_task = {
+            let store = store.clone();
+            let key = key.clone();
+            let lost = lost.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval((ttl / 3).max(Duration::from_millis(1)));
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    match store.renew(&key, fencing_token, ttl).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            warn!("Lost lease {} (token {})", key, fencing_token);
+                            lost.store(true, Ordering::SeqCst);
+                            break;
+                        }
+                        Err(e) => warn!("Failed to renew lease {}: {:?}", key, e),
+                    }
+                }
+            })
+        };
+
+        Self {
+            store,
+            key,
+            fencing_token,
+            lost,
+            renewal_task: Some(renewal_task),
+        }
+    }
+
+    /// Fencing token of this acquisition; later acquisitions get larger tokens
+    pub fn fencing_token(&self) -> u64 {
+        self.fencing_token
+    }
+
+    /// Whether renewal found the lease taken over by another holder
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+
+    /// Release the lock, returning false if the lease was no longer ours
+    pub async fn release(mut self) -> SessionResult<bool> {
+        if let Some(task) = self.renewal_task.take() {
+            task.abort();
+        }
+        self.store.release(&self.key, self.fencing_token).await
+    }
+}
+
+impl Drop for SessionLockGuard {
+    fn drop(&mut self) {
+        // An unreleased lease is left to expire on its own
+        if let Some(task) = self.renewal_task.take() {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_lock_mutual_exclusion() {
+        let store: Arc<dyn LeaseStore> = Arc::new(MemoryLeaseStore::new());
+        let client_a = SessionLock::new(store.clone(), Duration::from_secs(5));
+        let client_b = SessionLock::new(store.clone(), Duration::from_secs(5))
+            .with_acquire_timeout(Duration::from_millis(100))
+            .with_retry_interval(Duration::from_millis(10));
+
+        let guard_a = client_a.try_acquire("sess_1").await.unwrap().unwrap();
+        assert!(client_b.try_acquire("sess_1").await.unwrap().is_none());
+        assert!(client_b.acquire("sess_1").await.is_err());
+
+        // Other sessions are unaffected
+        assert!(client_b.try_acquire("sess_2").await.unwrap().is_some());
+
+        let token_a = guard_a.fencing_token();
+        assert!(guard_a.release().await.unwrap());
+
+        let guard_b = client_b.acquire("sess_1").await.unwrap();
+        assert!(guard_b.fencing_token() > token_a);
+
+        // A stale token can neither renew nor release the new holder's lease
+        assert!(!store.renew("session_lock:sess_1", token_a, Duration::from_secs(5)).await.unwrap());
+        assert!(!store.release("session_lock:sess_1", token_a).await.unwrap());
+        assert!(guard_b.release().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_session_lock_lease_expiry_and_renewal() {
+        let store: Arc<dyn LeaseStore> = Arc::new(MemoryLeaseStore::new());
+        let client_b = SessionLock::new(store.clone(), Duration::from_millis(150));
+
+        // A holder that stops renewing (e.g. a crashed instance) loses the lease after the TTL
+        let token = store.try_acquire("session_lock:sess_1", Duration::from_millis(50)).await.unwrap().unwrap();
+        assert!(client_b.try_acquire("sess_1").await.unwrap().is_none());
+        tokio::time::sleep(Duration::from_millis(80)).await;
+
+        let guard_b = client_b.try_acquire("sess_1").await.unwrap().unwrap();
+        assert!(!store.release("session_lock:sess_1", token).await.unwrap());
+
+        // A live guard keeps renewing past its original TTL
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(!guard_b.is_lost());
+        assert!(client_b.try_acquire("sess_1").await.unwrap().is_none());
+        assert!(guard_b.release().await.unwrap());
+    }
+}
@@ -1,6 +1,6 @@
 //! Session Manager for Sira Session
 
-use crate::{SessionResult, Session, SessionConfig, SessionState, SessionUpdate, SessionQuery, SessionEvent, SessionEventHandler, SessionLifecycleHook, ValidationRules, CleanupPolicy};
+use crate::{SessionResult, Session, SessionConfig, SessionState, QuotaPolicy, SessionUpdate, SessionQuery, SessionEvent, SessionEventHandler, SessionLifecycleHook, ValidationRules, CleanupPolicy, SessionArchive, MergePolicy, ImportSummary, BulkOperationSummary, SESSION_ARCHIVE_VERSION};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc, Duration};
 use std::collections::HashMap;
@@ -17,6 +17,7 @@ pub struct SessionManager {
     event_handlers: Vec<Box<dyn SessionEventHandler>>,
     lifecycle_hooks: Vec<Box<dyn SessionLifecycleHook>>,
     validation_rules: ValidationRules,
+    session_lock: Option<crate::SessionLock>,
     active_cleanup_task: Option<tokio::task::JoinHandle<()>>,
 }
 
@@ -32,10 +33,16 @@ impl SessionManager {
             event_handlers: Vec::new(),
             lifecycle_hooks: Vec::new(),
             validation_rules: ValidationRules::default(),
+            session_lock: None,
             active_cleanup_task: None,
         }
     }
 
+    /// Serialize session updates across instances with a distributed lock
+    pub fn set_session_lock(&mut self, lock: crate::SessionLock) {
+        self.session_lock = Some(lock);
+    }
+
     /// Start the session manager
     pub async fn start(&mut self) -> SessionResult<()> {
         info!("Starting session manager");
@@ -70,9 +77,21 @@ impl SessionManager {
         }).await?;
 
         if existing_sessions.len() >= self.config.max_sessions_per_user {
-            return Err(crate::SessionError::ValidationError(
-                format!("Maximum sessions per user exceeded: {}", self.config.max_sessions_per_user)
-            ));
+            match self.config.quota_policy {
+                QuotaPolicy::EvictOldest if self.config.max_sessions_per_user > 0 => {
+                    let mut existing_sessions = existing_sessions;
+                    existing_sessions.sort_by_key(|session| session.created_at);
+                    let excess = existing_sessions.len() + 1 - self.config.max_sessions_per_user;
+                    for oldest in existing_sessions.iter().take(excess) {
+                        self.terminate_session(&oldest.id, "Evicted by session quota".to_string()).await?;
+                    }
+                }
+                _ => {
+                    return Err(crate::SessionError::ValidationError(
+                        format!("Maximum sessions per user exceeded: {}", self.config.max_sessions_per_user)
+                    ));
+                }
+            }
         }
 
         let session_id = format!("sess_{}", Uuid::new_v4().simple());
@@ -140,7 +159,19 @@ impl SessionManager {
     }
 
     /// Update a session
+    ///
+    /// With a session lock set, the update is written under the lock's
+    /// fencing token.
     pub async fn update_session(&self, session_id: &str, updates: &[SessionUpdate]) -> SessionResult<()> {
+        match &self.session_lock {
+            Some(lock) => lock.with_lock(session_id, |fencing_token| {
+                self.apply_session_updates(session_id, Some(fencing_token), updates)
+            }).await,
+            None => self.apply_session_updates(session_id, None, updates).await,
+        }
+    }
+
+    async fn apply_session_updates(&self, session_id: &str, fencing_token: Option<u64>, updates: &[SessionUpdate]) -> SessionResult<()> {
         // Get current session
         let current_session = self.get_session(session_id).await?
             .ok_or_else(|| crate::SessionError::SessionNotFound(session_id.to_string()))?;
@@ -149,7 +180,10 @@ impl SessionManager {
         self.validate_updates(&current_session, updates).await?;
 
         // Apply updates
-        self.store.update(session_id, updates).await?;
+        match fencing_token {
+            Some(fencing_token) => self.store.update_fenced(session_id, fencing_token, updates).await?,
+            None => self.store.update(session_id, updates).await?,
+        }
 
         // Run lifecycle hooks
         let updated_session = self.get_session(session_id).await?.unwrap();
@@ -169,6 +203,19 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Read a data value of a session as `T`
+    pub async fn get_data<T: serde::de::DeserializeOwned>(&self, session_id: &str, key: &str) -> SessionResult<Option<T>> {
+        self.get_session(session_id).await?
+            .ok_or_else(|| crate::SessionError::SessionNotFound(session_id.to_string()))?
+            .get_data(key)
+    }
+
+    /// Store a data value in a session
+    pub async fn set_data<T: serde::Serialize>(&self, session_id: &str, key: &str, value: &T) -> SessionResult<()> {
+        let value = crate::types::data_value(key, value)?;
+        self.update_session(session_id, &[SessionUpdate::SetData { key: key.to_string(), value }]).await
+    }
+
     /// Delete a session
     pub async fn delete_session(&self, session_id: &str) -> SessionResult<bool> {
         let session = self.get_session(session_id).await?;
@@ -199,6 +246,41 @@ impl SessionManager {
         self.store.query(&query).await
     }
 
+    /// Sessions carrying `tag`
+    pub async fn query_by_tag(&self, tag: &str) -> SessionResult<Vec<Session>> {
+        self.query_sessions(SessionQuery {
+            tags: Some(vec![tag.to_string()]),
+            ..Default::default()
+        }).await
+    }
+
+    /// Terminate every active session carrying `tag`
+    pub async fn terminate_by_tag(&self, tag: &str) -> SessionResult<BulkOperationSummary> {
+        let mut summary = BulkOperationSummary::default();
+        for session in self.query_by_tag(tag).await? {
+            if session.state != SessionState::Active {
+                continue;
+            }
+            let result = self.terminate_session(&session.id, format!("Terminated with all sessions tagged '{}'", tag)).await;
+            summary.record(&session.id, result);
+        }
+
+        info!("Terminated {} sessions tagged '{}' ({} failed)", summary.succeeded.len(), tag, summary.failed.len());
+        Ok(summary)
+    }
+
+    /// Extend the expiry of every session carrying `tag`
+    pub async fn extend_by_tag(&self, tag: &str, seconds: u64) -> SessionResult<BulkOperationSummary> {
+        let mut summary = BulkOperationSummary::default();
+        for session in self.query_by_tag(tag).await? {
+            let result = self.extend_session(&session.id, seconds).await;
+            summary.record(&session.id, result);
+        }
+
+        info!("Extended {} sessions tagged '{}' by {} seconds ({} failed)", summary.succeeded.len(), tag, seconds, summary.failed.len());
+        Ok(summary)
+    }
+
     /// Extend session expiry
     pub async fn extend_session(&self, session_id: &str, seconds: u64) -> SessionResult<()> {
         self.update_session(session_id, &[SessionUpdate::ExtendExpiry { seconds }]).await?;
@@ -244,6 +326,71 @@ impl SessionManager {
         self.store.stats().await
     }
 
+    /// Export every session as a versioned archive
+    pub async fn export_all(&self) -> SessionResult<Vec<u8>> {
+        let mut sessions = self.store.query(&SessionQuery::default()).await?;
+        sessions.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let archive = SessionArchive {
+            version: SESSION_ARCHIVE_VERSION,
+            exported_at: Utc::now(),
+            sessions,
+        };
+
+        info!("Exported {} sessions", archive.sessions.len());
+
+        serde_json::to_vec(&archive)
+            .map_err(|e| crate::SessionError::SerializationError(e.to_string()))
+    }
+
+    /// Import an archive produced by `export_all`, resolving ID conflicts with `merge_policy`
+    pub async fn import_all(&self, data: &[u8], merge_policy: MergePolicy) -> SessionResult<ImportSummary> {
+        let archive: SessionArchive = serde_json::from_slice(data)
+            .map_err(|e| crate::SessionError::SerializationError(e.to_string()))?;
+
+        if archive.version != SESSION_ARCHIVE_VERSION {
+            return Err(crate::SessionError::ValidationError(
+                format!("Unsupported session archive version: {} (expected {})", archive.version, SESSION_ARCHIVE_VERSION)
+            ));
+        }
+
+        let mut summary = ImportSummary::default();
+        for session in &archive.sessions {
+            let existing = self.store.get(&session.id).await?;
+            let replace = match &existing {
+                None => true,
+                Some(existing) => match merge_policy {
+                    MergePolicy::Overwrite => true,
+                    MergePolicy::Skip => false,
+                    MergePolicy::NewestWins => session.updated_at > existing.updated_at,
+                },
+            };
+
+            if !replace {
+                summary.skipped += 1;
+                continue;
+            }
+
+            self.store.store(session).await?;
+            if existing.is_some() {
+                summary.replaced += 1;
+            } else {
+                summary.created += 1;
+            }
+
+            self.emit_event(SessionEvent::Restored {
+                session_id: session.id.clone(),
+            }).await;
+        }
+
+        info!(
+            "Imported session archive: {} created, {} replaced, {} skipped",
+            summary.created, summary.replaced, summary.skipped
+        );
+
+        Ok(summary)
+    }
+
     /// Add event handler
     pub fn add_event_handler(&mut self, handler: Box<dyn SessionEventHandler>) {
         self.event_handlers.push(handler);
@@ -402,6 +549,7 @@ mod tests {
             cleanup_interval_seconds: 300,
             enable_auto_cleanup: false,
             max_sessions_per_user: 5,
+            quota_policy: QuotaPolicy::Reject,
             enable_compression: false,
             enable_encryption: false,
         }
@@ -473,4 +621,164 @@ mod tests {
         let result = manager.create_session("test_user".to_string(), HashMap::new()).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_evict_oldest_policy_makes_room_at_the_limit() {
+        let mut config = create_test_config();
+        config.max_sessions_per_user = 2;
+        config.quota_policy = QuotaPolicy::EvictOldest;
+        let manager = SessionManager::new(config, Box::new(MemorySessionStore::default()));
+
+        let oldest = manager.create_session("test_user".to_string(), HashMap::new()).await.unwrap();
+        let newer = manager.create_session("test_user".to_string(), HashMap::new()).await.unwrap();
+        let newest = manager.create_session("test_user".to_string(), HashMap::new()).await.unwrap();
+
+        let state = |session: Option<Session>| session.unwrap().state;
+        assert_eq!(state(manager.get_session(&oldest).await.unwrap()), SessionState::Terminated);
+        assert_eq!(state(manager.get_session(&newer).await.unwrap()), SessionState::Active);
+        assert_eq!(state(manager.get_session(&newest).await.unwrap()), SessionState::Active);
+
+        // Other users keep their own quota
+        manager.create_session("other_user".to_string(), HashMap::new()).await.unwrap();
+        assert_eq!(state(manager.get_session(&newer).await.unwrap()), SessionState::Active);
+    }
+
+    #[tokio::test]
+    async fn test_update_session_waits_for_session_lock() {
+        use crate::{LeaseStore, MemoryLeaseStore, SessionLock};
+
+        let leases: Arc<dyn LeaseStore> = Arc::new(MemoryLeaseStore::new());
+        let mut manager = SessionManager::new(create_test_config(), Box::new(MemorySessionStore::default()));
+        manager.set_session_lock(
+            SessionLock::new(leases.clone(), std::time::Duration::from_secs(5))
+                .with_acquire_timeout(std::time::Duration::from_millis(100)),
+        );
+
+        let session_id = manager.create_session("test_user".to_string(), HashMap::new()).await.unwrap();
+        let update = [SessionUpdate::AddTag { tag: "locked".to_string() }];
+
+        // Another instance holds the lock, so the update times out
+        let other_instance = SessionLock::new(leases, std::time::Duration::from_secs(5));
+        let guard = other_instance.acquire(&session_id).await.unwrap();
+        let result = manager.update_session(&session_id, &update).await;
+        assert!(matches!(result, Err(crate::SessionError::ConcurrencyError(_))));
+
+        guard.release().await.unwrap();
+        manager.update_session(&session_id, &update).await.unwrap();
+        let session = manager.get_session(&session_id).await.unwrap().unwrap();
+        assert!(session.tags.contains(&"locked".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let manager = SessionManager::new(create_test_config(), Box::new(MemorySessionStore::default()));
+
+        let first = manager.create_session("alice".to_string(), HashMap::new()).await.unwrap();
+        let second = manager.create_session("bob".to_string(), HashMap::new()).await.unwrap();
+        manager.update_session(&first, &[
+            SessionUpdate::SetData { key: "step".to_string(), value: serde_json::json!(3) },
+            SessionUpdate::AddTag { tag: "beta".to_string() },
+        ]).await.unwrap();
+
+        let archive = manager.export_all().await.unwrap();
+        let snapshot = |session: Session| serde_json::to_value(session).unwrap();
+        let original_first = snapshot(manager.get_session(&first).await.unwrap().unwrap());
+        let original_second = snapshot(manager.get_session(&second).await.unwrap().unwrap());
+
+        // Clear the store, then restore from the archive
+        manager.delete_session(&first).await.unwrap();
+        manager.delete_session(&second).await.unwrap();
+        assert!(manager.query_sessions(SessionQuery::default()).await.unwrap().is_empty());
+
+        let summary = manager.import_all(&archive, MergePolicy::Overwrite).await.unwrap();
+        assert_eq!(summary, ImportSummary { created: 2, replaced: 0, skipped: 0 });
+        assert_eq!(snapshot(manager.get_session(&first).await.unwrap().unwrap()), original_first);
+        assert_eq!(snapshot(manager.get_session(&second).await.unwrap().unwrap()), original_second);
+
+        // A newer local change survives NewestWins and Skip, but not Overwrite
+        manager.update_session(&first, &[SessionUpdate::AddTag { tag: "local".to_string() }]).await.unwrap();
+        let summary = manager.import_all(&archive, MergePolicy::NewestWins).await.unwrap();
+        assert_eq!(summary, ImportSummary { created: 0, replaced: 0, skipped: 2 });
+        assert!(manager.get_session(&first).await.unwrap().unwrap().tags.contains(&"local".to_string()));
+
+        let summary = manager.import_all(&archive, MergePolicy::Skip).await.unwrap();
+        assert_eq!(summary.skipped, 2);
+
+        manager.import_all(&archive, MergePolicy::Overwrite).await.unwrap();
+        assert_eq!(snapshot(manager.get_session(&first).await.unwrap().unwrap()), original_first);
+
+        // Archives from an unknown format version are rejected
+        let mut future: SessionArchive = serde_json::from_slice(&archive).unwrap();
+        future.version = SESSION_ARCHIVE_VERSION + 1;
+        let result = manager.import_all(&serde_json::to_vec(&future).unwrap(), MergePolicy::Overwrite).await;
+        assert!(matches!(result, Err(crate::SessionError::ValidationError(_))));
+    }
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Cart {
+        items: Vec<String>,
+        total_cents: u64,
+    }
+
+    #[tokio::test]
+    async fn test_typed_session_data() {
+        let manager = SessionManager::new(create_test_config(), Box::new(MemorySessionStore::default()));
+        let session_id = manager.create_session("test_user".to_string(), HashMap::new()).await.unwrap();
+
+        let cart = Cart { items: vec!["book".to_string()], total_cents: 1299 };
+        manager.set_data(&session_id, "cart", &cart).await.unwrap();
+        assert_eq!(manager.get_data::<Cart>(&session_id, "cart").await.unwrap(), Some(cart));
+        assert_eq!(manager.get_data::<Cart>(&session_id, "missing").await.unwrap(), None);
+
+        match manager.get_data::<u64>(&session_id, "cart").await.unwrap_err() {
+            crate::SessionError::SerializationError(message) => {
+                assert!(message.contains("'cart'"));
+                assert!(message.contains("u64"));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert!(matches!(
+            manager.get_data::<Cart>("sess_unknown", "cart").await,
+            Err(crate::SessionError::SessionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_terminate_by_tag_only_touches_tagged_sessions() {
+        let manager = SessionManager::new(create_test_config(), Box::new(MemorySessionStore::default()));
+
+        let mut beta = Vec::new();
+        for user in ["alice", "bob"] {
+            let session_id = manager.create_session(user.to_string(), HashMap::new()).await.unwrap();
+            manager.update_session(&session_id, &[SessionUpdate::AddTag { tag: "beta-feature".to_string() }]).await.unwrap();
+            beta.push(session_id);
+        }
+        let untagged = manager.create_session("carol".to_string(), HashMap::new()).await.unwrap();
+
+        let mut tagged: Vec<String> = manager.query_by_tag("beta-feature").await.unwrap().into_iter().map(|s| s.id).collect();
+        tagged.sort();
+        beta.sort();
+        assert_eq!(tagged, beta);
+
+        let expires_at = |session: Option<Session>| session.unwrap().expires_at;
+        let before = expires_at(manager.get_session(&beta[0]).await.unwrap());
+        let summary = manager.extend_by_tag("beta-feature", 60).await.unwrap();
+        assert_eq!(summary.succeeded.len(), 2);
+        assert_eq!(expires_at(manager.get_session(&beta[0]).await.unwrap()), before + Duration::seconds(60));
+
+        let mut summary = manager.terminate_by_tag("beta-feature").await.unwrap();
+        summary.succeeded.sort();
+        assert_eq!(summary, BulkOperationSummary { succeeded: beta.clone(), failed: HashMap::new() });
+
+        let state = |session: Option<Session>| session.unwrap().state;
+        for session_id in &beta {
+            assert_eq!(state(manager.get_session(session_id).await.unwrap()), SessionState::Terminated);
+        }
+        assert_eq!(state(manager.get_session(&untagged).await.unwrap()), SessionState::Active);
+
+        // Removed tags and deleted sessions leave the index
+        manager.update_session(&beta[0], &[SessionUpdate::RemoveTag { tag: "beta-feature".to_string() }]).await.unwrap();
+        manager.delete_session(&beta[1]).await.unwrap();
+        assert!(manager.query_by_tag("beta-feature").await.unwrap().is_empty());
+    }
 }
@@ -16,6 +16,13 @@ pub trait SessionStore: Send + Sync {
     /// Update a session
     async fn update(&self, session_id: &str, updates: &[crate::SessionUpdate]) -> SessionResult<()>;
 
+    /// Update a session on behalf of a session lock holder
+    ///
+    /// Fails with a concurrency error if a larger fencing token has already
+    /// written the session, so a holder whose lease expired cannot overwrite
+    /// the writes of the next holder.
+    async fn update_fenced(&self, session_id: &str, fencing_token: u64, updates: &[crate::SessionUpdate]) -> SessionResult<()>;
+
     /// Delete a session
     async fn delete(&self, session_id: &str) -> SessionResult<bool>;
 
@@ -136,6 +143,21 @@ impl SessionStore for CompositeSessionStore {
         Ok(())
     }
 
+    async fn update_fenced(&self, session_id: &str, fencing_token: u64, updates: &[crate::SessionUpdate]) -> SessionResult<()> {
+        // Only the primary is fenced; replicas follow it
+        self.primary.update_fenced(session_id, fencing_token, updates).await?;
+
+        if self.write_to_replicas {
+            for replica in &self.replicas {
+                if let Err(e) = replica.update(session_id, updates).await {
+                    tracing::warn!("Failed to update session in replica: {:?}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     async fn delete(&self, session_id: &str) -> SessionResult<bool> {
         // Delete from primary
         let result = self.primary.delete(session_id).await?;
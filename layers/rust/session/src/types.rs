@@ -1,5 +1,6 @@
 //! Common types for Sira Session
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
@@ -18,6 +19,16 @@ pub enum SessionState {
     Terminated,
 }
 
+/// What creating a session beyond a user's session limit does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum QuotaPolicy {
+    /// Fail the new session
+    #[default]
+    Reject,
+    /// Terminate the user's oldest active sessions to make room
+    EvictOldest,
+}
+
 /// Session configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionConfig {
@@ -26,6 +37,8 @@ pub struct SessionConfig {
     pub cleanup_interval_seconds: u64,
     pub enable_auto_cleanup: bool,
     pub max_sessions_per_user: usize,
+    #[serde(default)]
+    pub quota_policy: QuotaPolicy,
     pub enable_compression: bool,
     pub enable_encryption: bool,
 }
@@ -47,6 +60,35 @@ pub struct Session {
     pub child_session_ids: Vec<String>,
 }
 
+impl Session {
+    /// Read a data value as `T`
+    ///
+    /// Returns `None` when the key is unset and a serialization error naming
+    /// the key and expected type when the stored value does not fit `T`.
+    pub fn get_data<T: DeserializeOwned>(&self, key: &str) -> SessionResult<Option<T>> {
+        self.data.get(key)
+            .map(|value| serde_json::from_value(value.clone()).map_err(|e| {
+                crate::SessionError::SerializationError(format!(
+                    "Session data '{}' is not a {}: {}", key, std::any::type_name::<T>(), e
+                ))
+            }))
+            .transpose()
+    }
+
+    /// Store a data value, replacing any previous value under `key`
+    pub fn set_data<T: Serialize>(&mut self, key: &str, value: &T) -> SessionResult<()> {
+        self.data.insert(key.to_string(), data_value(key, value)?);
+        Ok(())
+    }
+}
+
+/// Serialize a session data value
+pub(crate) fn data_value<T: Serialize>(key: &str, value: &T) -> SessionResult<serde_json::Value> {
+    serde_json::to_value(value).map_err(|e| {
+        crate::SessionError::SerializationError(format!("Session data '{}' cannot be serialized: {}", key, e))
+    })
+}
+
 /// Session statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionStats {
@@ -83,6 +125,11 @@ pub enum SessionEvent {
         session_ids: Vec<String>,
         reason: String,
     },
+    /// Removed from a full store to make room for other sessions
+    Evicted {
+        session_id: String,
+        reason: String,
+    },
 }
 
 /// Session event handler trait
@@ -153,6 +200,62 @@ impl Default for ValidationRules {
     }
 }
 
+/// Current format version of session archives
+pub const SESSION_ARCHIVE_VERSION: u32 = 1;
+
+/// Versioned archive of sessions produced by `SessionManager::export_all`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionArchive {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub sessions: Vec<Session>,
+}
+
+/// How to resolve an imported session whose ID already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MergePolicy {
+    /// Replace the existing session
+    Overwrite,
+    /// Keep the existing session
+    Skip,
+    /// Keep whichever session was updated most recently
+    NewestWins,
+}
+
+/// Outcome of importing a session archive
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    /// Sessions that did not exist before
+    pub created: usize,
+    /// Existing sessions replaced by the imported version
+    pub replaced: usize,
+    /// Imported sessions discarded in favour of the existing version
+    pub skipped: usize,
+}
+
+/// Outcome of an operation applied to many sessions, e.g. all sessions with a tag
+///
+/// Each session is handled on its own, so one failure does not undo or stop
+/// the others.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulkOperationSummary {
+    /// IDs of the sessions the operation was applied to
+    pub succeeded: Vec<String>,
+    /// Error message for each session the operation failed on, by ID
+    pub failed: HashMap<String, String>,
+}
+
+impl BulkOperationSummary {
+    pub(crate) fn record(&mut self, session_id: &str, result: SessionResult<()>) {
+        match result {
+            Ok(()) => self.succeeded.push(session_id.to_string()),
+            Err(e) => {
+                self.failed.insert(session_id.to_string(), e.to_string());
+            }
+        }
+    }
+}
+
 /// Session lifecycle hooks
 #[async_trait::async_trait]
 pub trait SessionLifecycleHook: Send + Sync {
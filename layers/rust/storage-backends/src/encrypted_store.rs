@@ -0,0 +1,366 @@
+//! Encrypted Store - Encryption-at-rest decorator for any storage client
+
+use crate::{StorageResult, StorageError, StorageClient, StorageEntry, StorageQuery, StorageBatch, StorageOp, StorageStats, StorageOperation, Cursor, KeyEventStream};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use sira_utils::CryptoUtils;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+
+/// Set of named encryption keys; new writes use the active key
+#[derive(Clone)]
+pub struct EncryptionKeyring {
+    active_key_id: String,
+    keys: HashMap<String, Vec<u8>>,
+}
+
+impl EncryptionKeyring {
+    /// Create a keyring with a single active key
+    pub fn new(key_id: impl Into<String>, key: Vec<u8>) -> StorageResult<Self> {
+        let key_id = key_id.into();
+        Self::check_key(&key_id, &key)?;
+
+        Ok(Self {
+            active_key_id: key_id.clone(),
+            keys: HashMap::from([(key_id, key)]),
+        })
+    }
+
+    /// Add a key that can decrypt existing data without becoming active
+    pub fn add_key(&mut self, key_id: impl Into<String>, key: Vec<u8>) -> StorageResult<()> {
+        let key_id = key_id.into();
+        Self::check_key(&key_id, &key)?;
+        self.keys.insert(key_id, key);
+        Ok(())
+    }
+
+    /// Add a key and make it the active key for new writes
+    pub fn rotate(&mut self, key_id: impl Into<String>, key: Vec<u8>) -> StorageResult<()> {
+        let key_id = key_id.into();
+        self.add_key(key_id.clone(), key)?;
+        self.active_key_id = key_id;
+        Ok(())
+    }
+
+    /// Remove a retired key; data still encrypted under it becomes unreadable
+    pub fn remove_key(&mut self, key_id: &str) -> StorageResult<bool> {
+        if key_id == self.active_key_id {
+            return Err(StorageError::EncryptionError("Cannot remove the active key".to_string()));
+        }
+        Ok(self.keys.remove(key_id).is_some())
+    }
+
+    /// ID of the key used for new writes
+    pub fn active_key_id(&self) -> &str {
+        &self.active_key_id
+    }
+
+    fn check_key(key_id: &str, key: &[u8]) -> StorageResult<()> {
+        if key.len() != sira_utils::AEAD_KEY_LEN {
+            return Err(StorageError::EncryptionError(format!(
+                "Key '{}' must be {} bytes, got {}",
+                key_id, sira_utils::AEAD_KEY_LEN, key.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Stored form of an encrypted value
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedValue {
+    key_id: String,
+    ciphertext: String,
+}
+
+/// Storage client decorator that encrypts values at rest
+///
+/// Values are sealed with the keyring's active key and tagged with its key ID,
+/// so data written before a rotation stays readable while the old key is kept
+/// in the keyring. The storage key is bound to each ciphertext as associated
+/// data. Keys are stored in plaintext unless key hashing is enabled.
+pub struct EncryptedStore {
+    inner: Arc<dyn StorageClient>,
+    keyring: RwLock<EncryptionKeyring>,
+    hash_keys: bool,
+}
+
+impl EncryptedStore {
+    /// Wrap a storage client
+    pub fn new(inner: Arc<dyn StorageClient>, keyring: EncryptionKeyring) -> Self {
+        Self {
+            inner,
+            keyring: RwLock::new(keyring),
+            hash_keys: false,
+        }
+    }
+
+    /// Store SHA-256 hashes of keys instead of the keys themselves
+    pub fn with_hashed_keys(mut self) -> Self {
+        self.hash_keys = true;
+        self
+    }
+
+    /// Make a new key active for subsequent writes
+    pub async fn rotate_key(&self, key_id: impl Into<String>, key: Vec<u8>) -> StorageResult<()> {
+        let key_id = key_id.into();
+        self.keyring.write().await.rotate(key_id.clone(), key)?;
+        info!("Rotated storage encryption key to {}", key_id);
+        Ok(())
+    }
+
+    /// Add a decryption-only key, e.g. one rotated out on another instance
+    pub async fn add_key(&self, key_id: impl Into<String>, key: Vec<u8>) -> StorageResult<()> {
+        self.keyring.write().await.add_key(key_id, key)
+    }
+
+    /// Remove a retired key
+    pub async fn remove_key(&self, key_id: &str) -> StorageResult<bool> {
+        self.keyring.write().await.remove_key(key_id)
+    }
+
+    fn storage_key(&self, key: &str) -> String {
+        if self.hash_keys {
+            CryptoUtils::sha256(key.as_bytes())
+        } else {
+            key.to_string()
+        }
+    }
+
+    async fn encrypt(&self, key: &str, value: &serde_json::Value) -> StorageResult<serde_json::Value> {
+        let plaintext = serde_json::to_vec(value)?;
+        let keyring = self.keyring.read().await;
+        let cipher_key = &keyring.keys[&keyring.active_key_id];
+
+        let sealed = CryptoUtils::aead_encrypt(cipher_key, &plaintext, key.as_bytes())
+            .map_err(|e| StorageError::EncryptionError(e.to_string()))?;
+
+        Ok(serde_json::to_value(EncryptedValue {
+            key_id: keyring.active_key_id.clone(),
+            ciphertext: BASE64.encode(sealed),
+        })?)
+    }
+
+    async fn decrypt(&self, key: &str, value: serde_json::Value) -> StorageResult<serde_json::Value> {
+        let encrypted: EncryptedValue = serde_json::from_value(value)
+            .map_err(|e| StorageError::DeserializationError(format!("Value for '{}' is not encrypted: {}", key, e)))?;
+
+        let keyring = self.keyring.read().await;
+        let cipher_key = keyring.keys.get(&encrypted.key_id).ok_or_else(|| {
+            StorageError::EncryptionError(format!("Unknown encryption key '{}' for '{}'", encrypted.key_id, key))
+        })?;
+
+        let sealed = BASE64
+            .decode(&encrypted.ciphertext)
+            .map_err(|e| StorageError::DeserializationError(e.to_string()))?;
+        let plaintext = CryptoUtils::aead_decrypt(cipher_key, &sealed, key.as_bytes())
+            .map_err(|e| StorageError::EncryptionError(e.to_string()))?;
+
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn unsupported(operation: StorageOperation) -> StorageError {
+        StorageError::OperationError(format!("{:?} is not supported on encrypted values", operation))
+    }
+}
+
+#[async_trait]
+impl StorageClient for EncryptedStore {
+    async fn get(&self, key: &str) -> StorageResult<Option<StorageEntry>> {
+        match self.inner.get(&self.storage_key(key)).await? {
+            Some(mut entry) => {
+                entry.value = self.decrypt(key, entry.value).await?;
+                entry.key = key.to_string();
+                Ok(Some(entry))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: serde_json::Value, ttl_seconds: Option<u64>) -> StorageResult<()> {
+        let encrypted = self.encrypt(key, &value).await?;
+        self.inner.set(&self.storage_key(key), encrypted, ttl_seconds).await
+    }
+
+    async fn delete(&self, key: &str) -> StorageResult<bool> {
+        self.inner.delete(&self.storage_key(key)).await
+    }
+
+    async fn exists(&self, key: &str) -> StorageResult<bool> {
+        self.inner.exists(&self.storage_key(key)).await
+    }
+
+    async fn list(&self, pattern: Option<&str>, limit: Option<usize>) -> StorageResult<Vec<String>> {
+        self.inner.list(pattern, limit).await
+    }
+
+    async fn scan(&self, prefix: &str, cursor: Option<Cursor>, limit: usize) -> StorageResult<(Vec<String>, Option<Cursor>)> {
+        // Hashed keys cannot be matched against a plaintext prefix
+        if self.hash_keys {
+            return Err(Self::unsupported(StorageOperation::Scan));
+        }
+
+        self.inner.scan(prefix, cursor, limit).await
+    }
+
+    async fn count(&self, pattern: Option<&str>) -> StorageResult<u64> {
+        self.inner.count(pattern).await
+    }
+
+    async fn ttl(&self, key: &str) -> StorageResult<Option<u64>> {
+        self.inner.ttl(&self.storage_key(key)).await
+    }
+
+    async fn expire(&self, key: &str, ttl_seconds: u64) -> StorageResult<bool> {
+        self.inner.expire(&self.storage_key(key), ttl_seconds).await
+    }
+
+    async fn persist(&self, key: &str) -> StorageResult<bool> {
+        self.inner.persist(&self.storage_key(key)).await
+    }
+
+    async fn increment(&self, _key: &str, _delta: i64) -> StorageResult<i64> {
+        Err(Self::unsupported(StorageOperation::Increment))
+    }
+
+    async fn decrement(&self, _key: &str, _delta: i64) -> StorageResult<i64> {
+        Err(Self::unsupported(StorageOperation::Decrement))
+    }
+
+    async fn append(&self, _key: &str, _value: &str) -> StorageResult<usize> {
+        Err(Self::unsupported(StorageOperation::Append))
+    }
+
+    async fn prepend(&self, _key: &str, _value: &str) -> StorageResult<usize> {
+        Err(Self::unsupported(StorageOperation::Prepend))
+    }
+
+    async fn batch_execute(&self, mut batch: StorageBatch) -> StorageResult<Vec<StorageResult<()>>> {
+        for operation in &mut batch.operations {
+            if let Some(value) = &operation.value {
+                operation.value = Some(self.encrypt(&operation.key, value).await?);
+            }
+            operation.key = self.storage_key(&operation.key);
+        }
+        self.inner.batch_execute(batch).await
+    }
+
+    async fn transaction(&self, ops: Vec<StorageOp>) -> StorageResult<()> {
+        let mut sealed = Vec::with_capacity(ops.len());
+        for op in ops {
+            sealed.push(match op {
+                StorageOp::Set { key, value, ttl_seconds } => StorageOp::Set {
+                    value: self.encrypt(&key, &value).await?,
+                    key: self.storage_key(&key),
+                    ttl_seconds,
+                },
+                StorageOp::Delete { key } => StorageOp::Delete { key: self.storage_key(&key) },
+            });
+        }
+        self.inner.transaction(sealed).await
+    }
+
+    async fn watch(&self, prefix: &str) -> StorageResult<KeyEventStream> {
+        // Hashed keys cannot be matched against a plaintext prefix
+        if self.hash_keys {
+            return Err(StorageError::OperationError("Watching keys is not supported with hashed keys".to_string()));
+        }
+
+        self.inner.watch(prefix).await
+    }
+
+    async fn query(&self, query: StorageQuery) -> StorageResult<Vec<StorageEntry>> {
+        // Hashed keys cannot be mapped back to the associated data used for encryption
+        if self.hash_keys {
+            return Err(Self::unsupported(StorageOperation::Search));
+        }
+
+        let mut entries = self.inner.query(query).await?;
+        for entry in &mut entries {
+            let value = std::mem::take(&mut entry.value);
+            entry.value = self.decrypt(&entry.key, value).await?;
+        }
+        Ok(entries)
+    }
+
+    async fn stats(&self) -> StorageResult<StorageStats> {
+        self.inner.stats().await
+    }
+
+    async fn health_check(&self) -> StorageResult<()> {
+        self.inner.health_check().await
+    }
+
+    async fn backup(&self, location: &str) -> StorageResult<()> {
+        self.inner.backup(location).await
+    }
+
+    async fn restore(&self, location: &str) -> StorageResult<()> {
+        self.inner.restore(location).await
+    }
+
+    async fn flush_all(&self) -> StorageResult<()> {
+        self.inner.flush_all().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GenericStorageClient, MemoryBackend, StorageConfig};
+
+    fn create_memory_client() -> Arc<dyn StorageClient> {
+        let config = StorageConfig {
+            enable_encryption: true,
+            ..StorageConfig::default()
+        };
+        Arc::new(GenericStorageClient::new(Box::new(MemoryBackend::new(config))))
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store_round_trip() {
+        let inner = create_memory_client();
+        let keyring = EncryptionKeyring::new("k1", CryptoUtils::generate_aead_key()).unwrap();
+        let store = EncryptedStore::new(inner.clone(), keyring);
+
+        let value = serde_json::json!({"user": "alice", "balance": 42});
+        store.set("account:1", value.clone(), None).await.unwrap();
+
+        // The backend only ever sees ciphertext
+        let raw = inner.get("account:1").await.unwrap().unwrap();
+        assert_eq!(raw.value["key_id"], "k1");
+        assert!(!raw.value.to_string().contains("alice"));
+
+        let entry = store.get("account:1").await.unwrap().unwrap();
+        assert_eq!(entry.value, value);
+        assert!(store.get("account:2").await.unwrap().is_none());
+
+        // Hashed keys hide the key itself as well
+        let hashed = EncryptedStore::new(inner.clone(), EncryptionKeyring::new("k1", CryptoUtils::generate_aead_key()).unwrap())
+            .with_hashed_keys();
+        hashed.set("account:3", value.clone(), None).await.unwrap();
+        assert!(!inner.exists("account:3").await.unwrap());
+        assert_eq!(hashed.get("account:3").await.unwrap().unwrap().value, value);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store_reads_data_under_rotated_key() {
+        let old_key = CryptoUtils::generate_aead_key();
+        let store = EncryptedStore::new(create_memory_client(), EncryptionKeyring::new("k1", old_key).unwrap());
+
+        store.set("before", serde_json::json!("old secret"), None).await.unwrap();
+        store.rotate_key("k2", CryptoUtils::generate_aead_key()).await.unwrap();
+        store.set("after", serde_json::json!("new secret"), None).await.unwrap();
+
+        assert_eq!(store.get("before").await.unwrap().unwrap().value, serde_json::json!("old secret"));
+        assert_eq!(store.get("after").await.unwrap().unwrap().value, serde_json::json!("new secret"));
+
+        // Once the old key is dropped from the keyring its data can no longer be read
+        assert!(store.remove_key("k2").await.is_err());
+        assert!(store.remove_key("k1").await.unwrap());
+        assert!(matches!(store.get("before").await, Err(StorageError::EncryptionError(_))));
+    }
+}
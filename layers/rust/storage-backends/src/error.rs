@@ -53,6 +53,9 @@ pub enum StorageError {
     #[error("Storage permission denied: {0}")]
     PermissionDenied(String),
 
+    #[error("Storage encryption error: {0}")]
+    EncryptionError(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
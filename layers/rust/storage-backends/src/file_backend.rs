@@ -1,22 +1,39 @@
 //! File Storage Backend - File system based key-value storage
 
-use crate::{StorageResult, StorageConfig, StorageBackend, StorageBackendType, StorageOperation};
+use crate::{StorageResult, StorageConfig, StorageBackend, StorageBackendType, StorageOperation, WalRecord, WriteAheadLog};
+use crate::wal::write_atomically;
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::fs;
-use std::io::{Read, Write};
+use std::io::Read;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use chrono::{Utc, Duration};
 use serde_json;
 use tracing::{debug, info};
 
+/// Write-ahead log file in the storage directory
+const WAL_FILE: &str = "wal.log";
+/// Index snapshot written at each checkpoint
+const INDEX_FILE: &str = "index.json";
+/// Log records after which a checkpoint is taken
+const DEFAULT_CHECKPOINT_INTERVAL: usize = 1000;
+
 /// File system storage backend
+///
+/// Mutations go through a write-ahead log; a checkpoint snapshots the index
+/// and compacts the log, and startup replays whatever the log still holds.
+/// Disk writes run on the blocking thread pool, and the index is only locked
+/// to update it once they are done.
 pub struct FileBackend {
     config: StorageConfig,
     base_path: PathBuf,
     index: Arc<RwLock<HashMap<String, crate::StorageEntry>>>,
+    wal: Arc<WriteAheadLog>,
+    /// Serializes mutations so they reach the log and the index in the same order
+    writer: Mutex<()>,
+    checkpoint_interval: usize,
 }
 
 impl FileBackend {
@@ -29,18 +46,33 @@ impl FileBackend {
             fs::create_dir_all(&base_path)?;
         }
 
+        let wal = WriteAheadLog::open(base_path.join(WAL_FILE))?;
+
         Ok(Self {
             config,
             base_path,
             index: Arc::new(RwLock::new(HashMap::new())),
+            wal: Arc::new(wal),
+            writer: Mutex::new(()),
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
         })
     }
 
+    /// Take a checkpoint whenever the write-ahead log reaches `records` records
+    pub fn with_checkpoint_interval(mut self, records: usize) -> Self {
+        self.checkpoint_interval = records.max(1);
+        self
+    }
+
+    /// Persist the index and compact the write-ahead log
+    pub async fn checkpoint(&self) -> StorageResult<()> {
+        let _writer = self.writer.lock().await;
+        self.write_checkpoint().await
+    }
+
     /// Get file path for a key
     fn get_file_path(&self, key: &str) -> PathBuf {
-        // Create a safe filename from the key
-        let safe_key = key.replace("/", "_").replace("\\", "_").replace(":", "_");
-        self.base_path.join(format!("{}.json", safe_key))
+        entry_path(&self.base_path, key)
     }
 
     /// Load entry from file
@@ -54,47 +86,133 @@ impl FileBackend {
         Ok(entry)
     }
 
-    /// Save entry to file
-    fn save_entry_to_file(&self, entry: &crate::StorageEntry) -> StorageResult<()> {
-        let file_path = self.get_file_path(&entry.key);
-        let json = serde_json::to_string_pretty(entry)?;
-        let mut file = fs::File::create(file_path)?;
-        file.write_all(json.as_bytes())?;
+    /// Log a mutation, then apply it, checkpointing once the log is long enough
+    ///
+    /// Callers hold the writer lock.
+    async fn commit(&self, record: WalRecord) -> StorageResult<()> {
+        let wal = self.wal.clone();
+        let logged = record.clone();
+        blocking(move || wal.append(&logged)).await?;
+
+        let base_path = self.base_path.clone();
+        match record {
+            WalRecord::Set { entry } => {
+                let saved = entry.clone();
+                blocking(move || save_entry_file(&base_path, &saved)).await?;
+                self.index.write().await.insert(entry.key.clone(), entry);
+            }
+            WalRecord::Delete { key } => {
+                self.index.write().await.remove(&key);
+                blocking(move || delete_entry_file(&base_path, &key)).await?;
+            }
+        }
+
+        if self.wal.len() >= self.checkpoint_interval {
+            self.write_checkpoint().await?;
+        }
         Ok(())
     }
 
-    /// Delete entry file
-    fn delete_entry_file(&self, key: &str) -> StorageResult<()> {
-        let file_path = self.get_file_path(key);
-        if file_path.exists() {
-            fs::remove_file(file_path)?;
-        }
+    /// Persist the index, then truncate the log; callers hold the writer lock
+    async fn write_checkpoint(&self) -> StorageResult<()> {
+        let (snapshot, entries) = {
+            let index = self.index.read().await;
+            (serde_json::to_vec(&*index)?, index.len())
+        };
+        let index_path = self.base_path.join(INDEX_FILE);
+        let wal = self.wal.clone();
+        blocking(move || {
+            write_atomically(&index_path, &snapshot)?;
+            wal.truncate()
+        }).await?;
+        debug!("Checkpointed file backend with {} entries", entries);
         Ok(())
     }
 }
 
+/// Path of the file holding a key's entry
+fn entry_path(base_path: &Path, key: &str) -> PathBuf {
+    // Create a safe filename from the key
+    let safe_key = key.replace("/", "_").replace("\\", "_").replace(":", "_");
+    base_path.join(format!("{}.json", safe_key))
+}
+
+/// Save entry to file
+fn save_entry_file(base_path: &Path, entry: &crate::StorageEntry) -> StorageResult<()> {
+    let json = serde_json::to_string_pretty(entry)?;
+    write_atomically(&entry_path(base_path, &entry.key), json.as_bytes())
+}
+
+/// Delete entry file
+fn delete_entry_file(base_path: &Path, key: &str) -> StorageResult<()> {
+    let file_path = entry_path(base_path, key);
+    if file_path.exists() {
+        fs::remove_file(file_path)?;
+    }
+    Ok(())
+}
+
+/// Load the checkpointed index and redo the mutations logged after it
+fn recover(base_path: &Path, wal: &WriteAheadLog) -> StorageResult<HashMap<String, crate::StorageEntry>> {
+    let mut index = HashMap::new();
+    let index_path = base_path.join(INDEX_FILE);
+    if index_path.exists() {
+        let mut file = fs::File::open(&index_path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        index = serde_json::from_str(&contents)?;
+    }
+
+    // Applying a record twice is harmless, so records the store already saw are simply redone
+    let records = wal.records()?;
+    if !records.is_empty() {
+        info!("Replaying {} write-ahead log records", records.len());
+        for record in records {
+            match record {
+                WalRecord::Set { entry } => {
+                    save_entry_file(base_path, &entry)?;
+                    index.insert(entry.key.clone(), entry);
+                }
+                WalRecord::Delete { key } => {
+                    index.remove(&key);
+                    delete_entry_file(base_path, &key)?;
+                }
+            }
+        }
+        write_atomically(&index_path, &serde_json::to_vec(&index)?)?;
+        wal.truncate()?;
+    }
+    Ok(index)
+}
+
+/// Run blocking file I/O on the blocking thread pool
+async fn blocking<T, F>(f: F) -> StorageResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> StorageResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| crate::StorageError::OperationError(format!("File I/O task failed: {}", e)))?
+}
+
 #[async_trait]
 impl StorageBackend for FileBackend {
     async fn init(&self) -> StorageResult<()> {
         info!("Initializing file storage backend at: {}", self.base_path.display());
 
-        // Load existing index if it exists
-        let index_path = self.base_path.join("index.json");
-        if index_path.exists() {
-            let mut file = fs::File::open(index_path)?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)?;
-            let index_data: HashMap<String, crate::StorageEntry> = serde_json::from_str(&contents)?;
-            let mut index = self.index.write().await;
-            *index = index_data;
-        }
+        let _writer = self.writer.lock().await;
+        let base_path = self.base_path.clone();
+        let wal = self.wal.clone();
+        let index = blocking(move || recover(&base_path, &wal)).await?;
+        *self.index.write().await = index;
 
         Ok(())
     }
 
     async fn shutdown(&self) -> StorageResult<()> {
         info!("Shutting down file storage backend");
-        Ok(())
+        self.checkpoint().await
     }
 
     fn backend_type(&self) -> StorageBackendType {
@@ -119,11 +237,15 @@ impl StorageBackend for FileBackend {
                     // Check TTL
                     if let Some(ttl) = metadata.ttl_seconds {
                         if metadata.created_at + Duration::seconds(ttl as i64) <= now {
-                            // Entry expired, remove it
+                            // Entry expired, remove it unless it was rewritten meanwhile
                             drop(index);
-                            let mut index_write = self.index.write().await;
-                            index_write.remove(key);
-                            self.delete_entry_file(key)?;
+                            let _writer = self.writer.lock().await;
+                            let still_expired = self.index.read().await.get(key)
+                                .and_then(|metadata| metadata.ttl_seconds.map(|ttl| metadata.created_at + Duration::seconds(ttl as i64) <= now))
+                                .unwrap_or(false);
+                            if still_expired {
+                                self.commit(WalRecord::Delete { key: key.to_string() }).await?;
+                            }
                             return Err(crate::StorageError::KeyNotFound(key.to_string()));
                         }
                     }
@@ -154,12 +276,8 @@ impl StorageBackend for FileBackend {
                     metadata: HashMap::new(),
                 };
 
-                // Save to file
-                self.save_entry_to_file(&entry)?;
-
-                // Update index
-                let mut index = self.index.write().await;
-                index.insert(key.to_string(), entry);
+                let _writer = self.writer.lock().await;
+                self.commit(WalRecord::Set { entry }).await?;
 
                 debug!("Set key: {} in file backend", key);
 
@@ -171,12 +289,11 @@ impl StorageBackend for FileBackend {
                     .and_then(|v| v.as_str())
                     .ok_or_else(|| crate::StorageError::OperationError("Missing key parameter".to_string()))?;
 
-                let mut index = self.index.write().await;
-                let removed = index.remove(key).is_some();
+                let _writer = self.writer.lock().await;
+                let removed = self.index.read().await.contains_key(key);
 
                 if removed {
-                    // Delete file
-                    self.delete_entry_file(key)?;
+                    self.commit(WalRecord::Delete { key: key.to_string() }).await?;
                     debug!("Deleted key: {} from file backend", key);
                 }
 
@@ -275,16 +392,7 @@ mod tests {
         StorageConfig {
             backend_type: StorageBackendType::File,
             connection_string: temp_dir.path().to_string_lossy().to_string(),
-            pool_size: None,
-            timeout_seconds: None,
-            retry_attempts: None,
-            enable_compression: false,
-            enable_encryption: false,
-            max_connections: None,
-            max_key_size_bytes: None,
-            max_value_size_bytes: None,
-            default_ttl_seconds: None,
-            namespace: None,
+            ..StorageConfig::default()
         }
     }
 
@@ -323,4 +431,95 @@ mod tests {
         let exists_after_delete = backend.execute_operation(StorageOperation::Exists, &get_params).await.unwrap();
         assert_eq!(exists_after_delete, serde_json::json!(false));
     }
+
+    async fn set(backend: &FileBackend, key: &str, value: serde_json::Value) {
+        let params = HashMap::from([
+            ("key".to_string(), serde_json::json!(key)),
+            ("value".to_string(), value),
+        ]);
+        backend.execute_operation(StorageOperation::Set, &params).await.unwrap();
+    }
+
+    async fn get_value(backend: &FileBackend, key: &str) -> Option<serde_json::Value> {
+        let params = HashMap::from([("key".to_string(), serde_json::json!(key))]);
+        let entry = backend.execute_operation(StorageOperation::Get, &params).await.ok()?;
+        Some(serde_json::from_value::<crate::StorageEntry>(entry).unwrap().value)
+    }
+
+    #[tokio::test]
+    async fn test_recovery_replays_wal_over_stale_store() {
+        let temp_dir = tempdir().unwrap();
+        let backend = FileBackend::new(create_test_config(&temp_dir)).unwrap();
+        backend.init().await.unwrap();
+        set(&backend, "a", serde_json::json!(1)).await;
+        set(&backend, "b", serde_json::json!(1)).await;
+        backend.checkpoint().await.unwrap();
+        drop(backend);
+
+        // Crash after logging two mutations but before applying them, the
+        // last append torn halfway
+        let now = Utc::now();
+        let wal = WriteAheadLog::open(temp_dir.path().join(WAL_FILE)).unwrap();
+        wal.append(&WalRecord::Set {
+            entry: crate::StorageEntry {
+                key: "a".to_string(),
+                value: serde_json::json!(2),
+                ttl_seconds: None,
+                created_at: now,
+                updated_at: now,
+                version: 1,
+                metadata: HashMap::new(),
+            },
+        }).unwrap();
+        wal.append(&WalRecord::Delete { key: "b".to_string() }).unwrap();
+        drop(wal);
+        let mut log = fs::OpenOptions::new().append(true).open(temp_dir.path().join(WAL_FILE)).unwrap();
+        std::io::Write::write_all(&mut log, br#"{"op":"set","ent"#).unwrap();
+
+        let recovered = FileBackend::new(create_test_config(&temp_dir)).unwrap();
+        recovered.init().await.unwrap();
+
+        assert_eq!(get_value(&recovered, "a").await, Some(serde_json::json!(2)));
+        assert_eq!(get_value(&recovered, "b").await, None);
+        assert!(recovered.wal.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_writes_after_a_torn_only_wal_survive_restart() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(WAL_FILE), br#"{"op":"set","ent"#).unwrap();
+
+        let backend = FileBackend::new(create_test_config(&temp_dir)).unwrap();
+        backend.init().await.unwrap();
+        set(&backend, "a", serde_json::json!(1)).await;
+        drop(backend);
+
+        let recovered = FileBackend::new(create_test_config(&temp_dir)).unwrap();
+        assert_eq!(recovered.wal.len(), 1);
+        recovered.init().await.unwrap();
+        assert_eq!(get_value(&recovered, "a").await, Some(serde_json::json!(1)));
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_compacts_wal_and_unsaved_writes_survive_restart() {
+        let temp_dir = tempdir().unwrap();
+        let backend = FileBackend::new(create_test_config(&temp_dir)).unwrap().with_checkpoint_interval(2);
+        backend.init().await.unwrap();
+
+        set(&backend, "a", serde_json::json!(1)).await;
+        set(&backend, "b", serde_json::json!(2)).await;
+        assert!(backend.wal.is_empty());
+        assert!(temp_dir.path().join(INDEX_FILE).exists());
+
+        // Not yet checkpointed when the process dies
+        set(&backend, "c", serde_json::json!(3)).await;
+        assert_eq!(backend.wal.len(), 1);
+        drop(backend);
+
+        let recovered = FileBackend::new(create_test_config(&temp_dir)).unwrap();
+        recovered.init().await.unwrap();
+        for (key, value) in [("a", 1), ("b", 2), ("c", 3)] {
+            assert_eq!(get_value(&recovered, key).await, Some(serde_json::json!(value)));
+        }
+    }
 }
@@ -9,6 +9,11 @@ pub mod types;
 pub mod storage_client;
 pub mod memory_backend;
 pub mod file_backend;
+pub mod wal;
+pub mod encrypted_store;
+pub mod namespaced_store;
+#[cfg(feature = "redis")]
+pub mod redis_backend;
 
 /// Result type alias for storage operations
 pub type StorageResult<T> = Result<T, StorageError>;
@@ -19,3 +24,8 @@ pub use types::*;
 pub use storage_client::*;
 pub use memory_backend::*;
 pub use file_backend::*;
+pub use wal::*;
+pub use encrypted_store::*;
+pub use namespaced_store::*;
+#[cfg(feature = "redis")]
+pub use redis_backend::*;
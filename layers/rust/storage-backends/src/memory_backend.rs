@@ -1,17 +1,26 @@
 //! Memory Storage Backend - In-memory key-value storage
 
-use crate::{StorageResult, StorageConfig, StorageBackend, StorageBackendType, StorageOperation};
+use crate::{StorageResult, StorageConfig, StorageBackend, StorageBackendType, StorageOperation, StorageOp, KeyEvent, KeyEventKind, KeyEventStream};
 use async_trait::async_trait;
+use futures::StreamExt;
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use tokio::sync::{broadcast, RwLock};
 use chrono::{Utc, Duration};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+/// Changes buffered for each watcher before the oldest are dropped
+const WATCH_BUFFER_SIZE: usize = 1024;
+/// How often expired keys are removed while someone is watching
+const EXPIRY_SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
 
 /// In-memory storage backend
 pub struct MemoryBackend {
     config: StorageConfig,
     data: Arc<RwLock<HashMap<String, crate::StorageEntry>>>,
+    events: Arc<broadcast::Sender<KeyEvent>>,
+    sweeping: AtomicBool,
 }
 
 impl MemoryBackend {
@@ -20,7 +29,73 @@ impl MemoryBackend {
         Self {
             config,
             data: Arc::new(RwLock::new(HashMap::new())),
+            events: Arc::new(broadcast::channel(WATCH_BUFFER_SIZE).0),
+            sweeping: AtomicBool::new(false),
+        }
+    }
+
+    /// Tell watchers about a change; nobody may be listening
+    fn notify(&self, key: &str, kind: KeyEventKind) {
+        let _ = self.events.send(KeyEvent { key: key.to_string(), kind });
+    }
+
+    /// Remove expired entries, telling watchers they were deleted
+    ///
+    /// Runs periodically once a watch is open; reads skip expired entries
+    /// either way.
+    pub async fn purge_expired(&self) {
+        Self::purge(&self.data, &self.events).await;
+    }
+
+    async fn purge(data: &RwLock<HashMap<String, crate::StorageEntry>>, events: &broadcast::Sender<KeyEvent>) {
+        let now = Utc::now();
+        let mut data = data.write().await;
+        data.retain(|key, entry| {
+            let expired = entry.ttl_seconds.is_some_and(|ttl| entry.created_at + Duration::seconds(ttl as i64) <= now);
+            if expired {
+                debug!("Expired key: {} in memory backend", key);
+                let _ = events.send(KeyEvent { key: key.clone(), kind: KeyEventKind::Delete });
+            }
+            !expired
+        });
+    }
+
+    /// Start purging expired entries in the background, once per backend
+    ///
+    /// The sweeper only holds weak references, so it stops with the backend.
+    fn start_expiry_sweeper(&self) {
+        if self.sweeping.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let data = Arc::downgrade(&self.data);
+        let events = Arc::downgrade(&self.events);
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(EXPIRY_SWEEP_INTERVAL);
+            loop {
+                ticks.tick().await;
+                let (Some(data), Some(events)) = (Weak::upgrade(&data), Weak::upgrade(&events)) else {
+                    break;
+                };
+                Self::purge(&data, &events).await;
+            }
+        });
+    }
+
+    /// Check a write against the configured key and value size limits
+    fn check_limits(&self, key: &str, value: &serde_json::Value) -> StorageResult<()> {
+        if let Some(max) = self.config.max_key_size_bytes {
+            if key.len() > max {
+                return Err(crate::StorageError::QuotaExceeded(format!("Key '{}' exceeds {} bytes", key, max)));
+            }
         }
+        if let Some(max) = self.config.max_value_size_bytes {
+            let size = serde_json::to_string(value).map(|s| s.len()).unwrap_or(0);
+            if size > max {
+                return Err(crate::StorageError::QuotaExceeded(format!("Value for '{}' is {} bytes, limit is {}", key, size, max)));
+            }
+        }
+        Ok(())
     }
 }
 
@@ -88,6 +163,7 @@ impl StorageBackend for MemoryBackend {
                 };
 
                 data.insert(key.to_string(), entry);
+                self.notify(key, KeyEventKind::Set);
                 debug!("Set key: {} in memory backend", key);
 
                 serde_json::to_value(true).map_err(|e| crate::StorageError::SerializationError(e.to_string()))
@@ -102,6 +178,7 @@ impl StorageBackend for MemoryBackend {
                 let removed = data.remove(key).is_some();
 
                 if removed {
+                    self.notify(key, KeyEventKind::Delete);
                     debug!("Deleted key: {} from memory backend", key);
                 }
 
@@ -141,6 +218,36 @@ impl StorageBackend for MemoryBackend {
                 serde_json::to_value(keys).map_err(|e| crate::StorageError::SerializationError(e.to_string()))
             }
 
+            StorageOperation::Scan => {
+                let prefix = params.get("prefix").and_then(|v| v.as_str()).unwrap_or("");
+                let after = params.get("cursor").and_then(|v| v.as_str());
+                let limit = params.get("limit").and_then(|v| v.as_u64()).unwrap_or(u64::MAX) as usize;
+
+                // Keys are visited in sorted order and the cursor is the last key
+                // returned, so each page resumes strictly after the previous one
+                let data = self.data.read().await;
+                let mut keys: Vec<&String> = data.iter()
+                    .filter(|(key, _)| key.starts_with(prefix))
+                    .filter(|(key, _)| after.is_none_or(|after| key.as_str() > after))
+                    .filter(|(_, entry)| match entry.ttl_seconds {
+                        Some(ttl) => entry.created_at + Duration::seconds(ttl as i64) > now,
+                        None => true,
+                    })
+                    .map(|(key, _)| key)
+                    .collect();
+                keys.sort();
+
+                let has_more = keys.len() > limit;
+                keys.truncate(limit);
+
+                let page = crate::ScanPage {
+                    cursor: if has_more { keys.last().map(|key| crate::Cursor::new(key.as_str())) } else { None },
+                    keys: keys.into_iter().cloned().collect(),
+                };
+
+                serde_json::to_value(page).map_err(|e| crate::StorageError::SerializationError(e.to_string()))
+            }
+
             StorageOperation::Count => {
                 let pattern = params.get("pattern").and_then(|v| v.as_str());
 
@@ -208,11 +315,118 @@ impl StorageBackend for MemoryBackend {
                 }
             }
 
+            StorageOperation::Increment | StorageOperation::Decrement => {
+                let key = params.get("key")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| crate::StorageError::OperationError("Missing key parameter".to_string()))?;
+                let delta = params.get("delta")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| crate::StorageError::OperationError("Missing delta parameter".to_string()))?;
+                let delta = if matches!(operation, StorageOperation::Decrement) { -delta } else { delta };
+
+                // Read and write under one lock so concurrent increments never lose updates
+                let mut data = self.data.write().await;
+                let live = data.get(key).filter(|entry| {
+                    entry.ttl_seconds.is_none_or(|ttl| entry.created_at + Duration::seconds(ttl as i64) > now)
+                });
+                let current = match live {
+                    Some(entry) => Some(entry.value.as_i64()
+                        .ok_or_else(|| crate::StorageError::OperationError(format!("Value of key {} is not an integer", key)))?),
+                    None => None,
+                };
+                let value = current.unwrap_or(0).checked_add(delta)
+                    .ok_or_else(|| crate::StorageError::OperationError(format!("Counter {} would overflow", key)))?;
+
+                match data.get_mut(key) {
+                    // A live counter keeps its TTL; an absent or expired one starts over
+                    Some(entry) if current.is_some() => {
+                        entry.value = serde_json::json!(value);
+                        entry.updated_at = now;
+                        entry.version += 1;
+                    }
+                    _ => {
+                        data.insert(key.to_string(), crate::StorageEntry {
+                            key: key.to_string(),
+                            value: serde_json::json!(value),
+                            ttl_seconds: None,
+                            created_at: now,
+                            updated_at: now,
+                            version: 1,
+                            metadata: HashMap::new(),
+                        });
+                    }
+                }
+                self.notify(key, KeyEventKind::Set);
+                debug!("Changed counter {} by {} to {}", key, delta, value);
+
+                Ok(serde_json::json!(value))
+            }
+
             _ => {
                 Err(crate::StorageError::OperationError(format!("Unsupported operation: {:?}", operation)))
             }
         }
     }
+
+    async fn transaction(&self, ops: Vec<StorageOp>) -> StorageResult<()> {
+        // Every write is checked before the first is applied, all under one lock
+        let mut data = self.data.write().await;
+        for op in &ops {
+            if let StorageOp::Set { key, value, .. } = op {
+                self.check_limits(key, value)?;
+            }
+        }
+
+        let now = Utc::now();
+        for op in ops {
+            match op {
+                StorageOp::Set { key, value, ttl_seconds } => {
+                    let previous = data.get(&key).filter(|entry| {
+                        entry.ttl_seconds.is_none_or(|ttl| entry.created_at + Duration::seconds(ttl as i64) > now)
+                    });
+                    let mut entry = crate::StorageEntry::replacing(previous, &key, value, ttl_seconds, now);
+                    // TTLs count from creation here, so a kept creation time
+                    // stretches the TTL by the entry's age, rounded up to whole seconds
+                    if let Some(ttl) = entry.ttl_seconds.as_mut() {
+                        let age_ms = (now - entry.created_at).num_milliseconds().max(0) as u64;
+                        *ttl += age_ms.div_ceil(1000);
+                    }
+                    self.notify(&key, KeyEventKind::Set);
+                    data.insert(key, entry);
+                }
+                StorageOp::Delete { key } => {
+                    if data.remove(&key).is_some() {
+                        self.notify(&key, KeyEventKind::Delete);
+                    }
+                }
+            }
+        }
+        debug!("Committed transaction in memory backend");
+
+        Ok(())
+    }
+
+    async fn watch(&self, prefix: &str) -> StorageResult<KeyEventStream> {
+        self.start_expiry_sweeper();
+        let prefix = prefix.to_string();
+        let receiver = self.events.subscribe();
+        let events = futures::stream::unfold(receiver, move |mut receiver| {
+            let prefix = prefix.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) if event.key.starts_with(&prefix) => return Some((Ok(event), receiver)),
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            warn!("Watcher of '{}' fell behind and missed {} key events", prefix, missed);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+        Ok(events.boxed())
+    }
 }
 
 /// Memory backend factory
@@ -249,20 +463,7 @@ mod tests {
     use std::collections::HashMap;
 
     fn create_test_config() -> StorageConfig {
-        StorageConfig {
-            backend_type: StorageBackendType::Memory,
-            connection_string: "memory://".to_string(),
-            pool_size: None,
-            timeout_seconds: None,
-            retry_attempts: None,
-            enable_compression: false,
-            enable_encryption: false,
-            max_connections: None,
-            max_key_size_bytes: None,
-            max_value_size_bytes: None,
-            default_ttl_seconds: None,
-            namespace: None,
-        }
+        StorageConfig::default()
     }
 
     #[tokio::test]
@@ -298,4 +499,182 @@ mod tests {
         let exists_after_delete = backend.execute_operation(StorageOperation::Exists, &get_params).await.unwrap();
         assert_eq!(exists_after_delete, serde_json::json!(false));
     }
+
+    #[tokio::test]
+    async fn test_memory_backend_scan_pagination() {
+        use crate::{GenericStorageClient, StorageClient};
+        use std::collections::HashSet;
+
+        let client = GenericStorageClient::new(Box::new(MemoryBackend::new(create_test_config())));
+        for i in 0..300 {
+            client.set(&format!("user:{:04}", i), serde_json::json!(i), None).await.unwrap();
+        }
+        for i in 0..50 {
+            client.set(&format!("session:{}", i), serde_json::json!(i), None).await.unwrap();
+        }
+
+        let mut seen = HashSet::new();
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let (keys, next) = client.scan("user:", cursor, 32).await.unwrap();
+            assert!(keys.len() <= 32);
+            for key in keys {
+                assert!(key.starts_with("user:"));
+                assert!(seen.insert(key), "key returned twice");
+            }
+
+            // Writes during the scan never cause already returned keys to repeat
+            if pages == 2 {
+                client.set("user:0000a", serde_json::json!("late"), None).await.unwrap();
+            }
+
+            pages += 1;
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages, 10);
+        assert_eq!(seen.len(), 300);
+        assert!(client.scan("user:", None, 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_transaction_commits_and_rolls_back() {
+        use crate::{GenericStorageClient, StorageClient};
+
+        let mut config = create_test_config();
+        config.max_value_size_bytes = Some(64);
+        let client = GenericStorageClient::new(Box::new(MemoryBackend::new(config)));
+        client.set("session:stale", serde_json::json!("old"), None).await.unwrap();
+
+        client.transaction(vec![
+            StorageOp::Set { key: "session:1".to_string(), value: serde_json::json!({ "user": "alice" }), ttl_seconds: None },
+            StorageOp::Set { key: "index:alice".to_string(), value: serde_json::json!(["session:1"]), ttl_seconds: None },
+            StorageOp::Delete { key: "session:stale".to_string() },
+        ]).await.unwrap();
+
+        assert_eq!(client.get("index:alice").await.unwrap().unwrap().value, serde_json::json!(["session:1"]));
+        assert!(!client.exists("session:stale").await.unwrap());
+
+        // The oversized last write aborts the whole batch
+        let result = client.transaction(vec![
+            StorageOp::Delete { key: "session:1".to_string() },
+            StorageOp::Set { key: "index:alice".to_string(), value: serde_json::json!([]), ttl_seconds: None },
+            StorageOp::Set { key: "blob".to_string(), value: serde_json::json!("x".repeat(100)), ttl_seconds: None },
+        ]).await;
+        assert!(matches!(result, Err(crate::StorageError::QuotaExceeded(_))));
+
+        assert!(client.exists("session:1").await.unwrap());
+        assert_eq!(client.get("index:alice").await.unwrap().unwrap().value, serde_json::json!(["session:1"]));
+        assert!(!client.exists("blob").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_keeps_the_history_of_overwritten_entries() {
+        let backend = MemoryBackend::new(create_test_config());
+        let created_at = Utc::now() - Duration::hours(1);
+        backend.data.write().await.insert("index:alice".to_string(), crate::StorageEntry {
+            key: "index:alice".to_string(),
+            value: serde_json::json!(["session:1"]),
+            ttl_seconds: None,
+            created_at,
+            updated_at: created_at,
+            version: 3,
+            metadata: HashMap::from([("owner".to_string(), serde_json::json!("alice"))]),
+        });
+
+        backend.transaction(vec![
+            StorageOp::Set { key: "index:alice".to_string(), value: serde_json::json!([]), ttl_seconds: Some(60) },
+        ]).await.unwrap();
+
+        let entry = backend.data.read().await["index:alice"].clone();
+        assert_eq!(entry.version, 4);
+        assert_eq!(entry.created_at, created_at);
+        assert_eq!(entry.metadata["owner"], "alice");
+
+        // The TTL still counts from the write, not from the kept creation time
+        let params = HashMap::from([("key".to_string(), serde_json::json!("index:alice"))]);
+        let ttl = backend.execute_operation(StorageOperation::TTL, &params).await.unwrap();
+        assert!(matches!(ttl.as_u64(), Some(59..=60)), "{}", ttl);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_increments_are_not_lost() {
+        use crate::{GenericStorageClient, StorageClient};
+
+        let client = Arc::new(GenericStorageClient::new(Box::new(MemoryBackend::new(create_test_config()))));
+        let tasks: Vec<_> = (0..20)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.increment("lock", 1).await.unwrap() })
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        for task in tasks {
+            results.push(task.await.unwrap());
+        }
+        results.sort();
+        assert_eq!(results, (1..=20).collect::<Vec<_>>());
+        assert_eq!(client.decrement("lock", 5).await.unwrap(), 15);
+
+        client.set("name", serde_json::json!("alice"), None).await.unwrap();
+        assert!(client.increment("name", 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watcher_receives_events_for_matching_keys() {
+        use crate::{GenericStorageClient, StorageClient};
+
+        let client = GenericStorageClient::new(Box::new(MemoryBackend::new(create_test_config())));
+        let mut events = client.watch("session:").await.unwrap();
+
+        client.set("session:1", serde_json::json!("alice"), None).await.unwrap();
+        client.set("user:1", serde_json::json!("alice"), None).await.unwrap();
+        client.delete("session:1").await.unwrap();
+        // Deleting a missing key changes nothing
+        client.delete("session:2").await.unwrap();
+        client.transaction(vec![
+            StorageOp::Set { key: "session:3".to_string(), value: serde_json::json!("bob"), ttl_seconds: None },
+            StorageOp::Delete { key: "user:1".to_string() },
+        ]).await.unwrap();
+
+        let expected = [
+            ("session:1", KeyEventKind::Set),
+            ("session:1", KeyEventKind::Delete),
+            ("session:3", KeyEventKind::Set),
+        ];
+        for (key, kind) in expected {
+            let event = tokio::time::timeout(std::time::Duration::from_secs(1), events.next()).await.unwrap().unwrap().unwrap();
+            assert_eq!(event, KeyEvent { key: key.to_string(), kind });
+        }
+        assert!(tokio::time::timeout(std::time::Duration::from_millis(20), events.next()).await.is_err());
+
+        // The stream ends with the backend
+        drop(client);
+        assert!(events.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_watcher_is_told_when_keys_expire() {
+        let backend = MemoryBackend::new(create_test_config());
+        let mut events = backend.watch("session:").await.unwrap();
+
+        let params = HashMap::from([
+            ("key".to_string(), serde_json::json!("session:1")),
+            ("value".to_string(), serde_json::json!("alice")),
+            ("ttl_seconds".to_string(), serde_json::json!(0)),
+        ]);
+        backend.execute_operation(StorageOperation::Set, &params).await.unwrap();
+        backend.purge_expired().await;
+
+        for kind in [KeyEventKind::Set, KeyEventKind::Delete] {
+            let event = tokio::time::timeout(std::time::Duration::from_secs(1), events.next()).await.unwrap().unwrap().unwrap();
+            assert_eq!(event, KeyEvent { key: "session:1".to_string(), kind });
+        }
+        assert_eq!(backend.data.read().await.len(), 0);
+    }
 }
@@ -0,0 +1,567 @@
+//! Namespaced Store - Key prefixing and quotas for subsystems sharing a storage client
+
+use crate::{StorageResult, StorageError, StorageClient, StorageEntry, StorageQuery, StorageBatch, StorageOp, StorageOperation, StorageStats, Cursor, KeyEventStream};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Limits on what one namespace may store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NamespaceQuota {
+    pub max_keys: Option<u64>,
+    /// Limit on the serialized size of all values
+    pub max_bytes: Option<u64>,
+}
+
+impl NamespaceQuota {
+    /// Create a quota without limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the number of keys
+    pub fn with_max_keys(mut self, max_keys: u64) -> Self {
+        self.max_keys = Some(max_keys);
+        self
+    }
+
+    /// Limit the total serialized size of values
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+}
+
+/// Keys and value bytes a namespace stores
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NamespaceUsage {
+    pub keys: u64,
+    pub bytes: u64,
+}
+
+/// Value size of a tracked key and when its TTL runs out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TrackedValue {
+    size: u64,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl TrackedValue {
+    fn new(size: u64, ttl_seconds: Option<u64>) -> Self {
+        Self {
+            size,
+            expires_at: ttl_seconds.map(|ttl| Utc::now() + Duration::seconds(ttl as i64)),
+        }
+    }
+}
+
+/// Tracked value per key, with running totals
+#[derive(Default)]
+struct UsageTracker {
+    values: HashMap<String, TrackedValue>,
+    bytes: u64,
+}
+
+impl UsageTracker {
+    fn usage(&self) -> NamespaceUsage {
+        NamespaceUsage { keys: self.values.len() as u64, bytes: self.bytes }
+    }
+
+    /// Record a key's new value, or its removal, returning what was recorded before
+    fn record(&mut self, key: &str, value: Option<TrackedValue>) -> Option<TrackedValue> {
+        let old = self.values.remove(key);
+        if let Some(old) = old {
+            self.bytes -= old.size;
+        }
+        if let Some(value) = value {
+            self.bytes += value.size;
+            self.values.insert(key.to_string(), value);
+        }
+        old
+    }
+
+    /// Forget keys whose TTL has run out
+    fn drop_expired(&mut self) {
+        let now = Utc::now();
+        let expired: Vec<String> = self.values.iter()
+            .filter(|(_, value)| value.expires_at.is_some_and(|expires_at| expires_at <= now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.record(&key, None);
+        }
+    }
+}
+
+/// A write recorded ahead of the inner store, and what it replaced
+type Reservation = Vec<(String, Option<TrackedValue>, Option<TrackedValue>)>;
+
+/// Serialized size of a value
+fn value_size(value: &serde_json::Value) -> u64 {
+    serde_json::to_string(value).map(|s| s.len()).unwrap_or(0) as u64
+}
+
+/// Storage client decorator confining a subsystem to a key namespace
+///
+/// Keys are stored as `<namespace>:<key>`. Writes that would take the
+/// namespace over its quota fail with [`StorageError::QuotaExceeded`] and
+/// leave the store unchanged. Usage is counted from the namespace's data
+/// when the store is created, then tracked from the writes made through it;
+/// keys stop counting once their TTL runs out. [`Self::recount`] picks up
+/// changes made to the namespace by other clients.
+///
+/// A write's usage is recorded before it reaches the inner store and undone
+/// if it fails, so concurrent writes cannot overshoot the quota together.
+pub struct NamespacedStore {
+    inner: Arc<dyn StorageClient>,
+    namespace: String,
+    prefix: String,
+    quota: NamespaceQuota,
+    usage: Mutex<UsageTracker>,
+}
+
+impl NamespacedStore {
+    /// Wrap a storage client, confining keys to `namespace` and counting what it already holds
+    pub async fn new(inner: Arc<dyn StorageClient>, namespace: impl Into<String>) -> StorageResult<Self> {
+        let namespace = namespace.into();
+        let store = Self {
+            inner,
+            prefix: format!("{}:", namespace),
+            namespace,
+            quota: NamespaceQuota::default(),
+            usage: Mutex::new(UsageTracker::default()),
+        };
+        store.recount().await?;
+        Ok(store)
+    }
+
+    /// Limit what the namespace may store
+    pub fn with_quota(mut self, quota: NamespaceQuota) -> Self {
+        self.quota = quota;
+        self
+    }
+
+    /// Namespace the store's keys live in
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// Keys and value bytes the namespace currently stores
+    pub async fn usage(&self) -> NamespaceUsage {
+        let mut usage = self.usage.lock().unwrap();
+        usage.drop_expired();
+        usage.usage()
+    }
+
+    /// Rebuild usage from the data in the namespace
+    pub async fn recount(&self) -> StorageResult<NamespaceUsage> {
+        let mut tracker = UsageTracker::default();
+        for key in self.namespace_keys().await? {
+            let value = self.measure(&key).await?;
+            tracker.record(&key, value);
+        }
+        let usage = tracker.usage();
+        *self.usage.lock().unwrap() = tracker;
+        Ok(usage)
+    }
+
+    fn storage_key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    fn local_key(&self, storage_key: &str) -> Option<String> {
+        storage_key.strip_prefix(&self.prefix).map(str::to_string)
+    }
+
+    /// Every key of the namespace, as stored
+    async fn namespace_keys(&self) -> StorageResult<Vec<String>> {
+        let mut keys = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = self.inner.scan(&self.prefix, cursor, 1000).await?;
+            keys.extend(page);
+            match next {
+                Some(next) => cursor = Some(next),
+                None => return Ok(keys),
+            }
+        }
+    }
+
+    /// Fail unless the namespace stays within its quota after `writes`
+    ///
+    /// Each write is a stored key and its new value size, or `None` for a delete.
+    fn check_quota(&self, usage: &UsageTracker, writes: &[(String, Option<u64>)]) -> StorageResult<()> {
+        let mut projected: HashMap<&str, Option<u64>> = HashMap::new();
+        for (key, size) in writes {
+            projected.insert(key, *size);
+        }
+
+        let mut keys = usage.values.len() as u64;
+        let mut bytes = usage.bytes;
+        for (key, size) in projected {
+            if let Some(old) = usage.values.get(key) {
+                keys -= 1;
+                bytes -= old.size;
+            }
+            if let Some(size) = size {
+                keys += 1;
+                bytes += size;
+            }
+        }
+
+        if let Some(max) = self.quota.max_keys.filter(|max| keys > *max) {
+            return Err(StorageError::QuotaExceeded(format!(
+                "Namespace '{}' would hold {} keys, limit is {}", self.namespace, keys, max
+            )));
+        }
+        if let Some(max) = self.quota.max_bytes.filter(|max| bytes > *max) {
+            return Err(StorageError::QuotaExceeded(format!(
+                "Namespace '{}' would hold {} bytes, limit is {}", self.namespace, bytes, max
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check `writes` against the quota and record them ahead of the inner store
+    fn reserve(&self, writes: Vec<(String, Option<TrackedValue>)>) -> StorageResult<Reservation> {
+        let mut usage = self.usage.lock().unwrap();
+        usage.drop_expired();
+        let sizes: Vec<(String, Option<u64>)> = writes.iter()
+            .map(|(key, value)| (key.clone(), value.map(|value| value.size)))
+            .collect();
+        self.check_quota(&usage, &sizes)?;
+        Ok(writes.into_iter()
+            .map(|(key, value)| {
+                let previous = usage.record(&key, value);
+                (key, value, previous)
+            })
+            .collect())
+    }
+
+    /// Undo the reservation of failed writes, except for keys written again since
+    fn release(&self, reservation: Reservation) {
+        let mut usage = self.usage.lock().unwrap();
+        for (key, value, previous) in reservation.into_iter().rev() {
+            if usage.values.get(&key).copied() == value {
+                usage.record(&key, previous);
+            }
+        }
+    }
+
+    /// Reserve room for the counter the first increment of `key` creates
+    fn reserve_new_counter(&self, key: &str, initial: i64) -> StorageResult<()> {
+        if self.usage.lock().unwrap().values.contains_key(key) {
+            return Ok(());
+        }
+        self.reserve(vec![(key.to_string(), Some(TrackedValue::new(value_size(&initial.into()), None)))])?;
+        Ok(())
+    }
+
+    /// Reserve room for `value` added to the string at `key`
+    fn reserve_growth(&self, key: &str, value: &str) -> StorageResult<()> {
+        let current = self.usage.lock().unwrap().values.get(key).copied();
+        let grown = current.map_or(2, |current| current.size) + value_size(&value.into()) - 2;
+        let expires_at = current.and_then(|current| current.expires_at);
+        self.reserve(vec![(key.to_string(), Some(TrackedValue { size: grown, expires_at }))])?;
+        Ok(())
+    }
+
+    /// Size and expiry of a stored key, if it exists
+    async fn measure(&self, storage_key: &str) -> StorageResult<Option<TrackedValue>> {
+        let Some(entry) = self.inner.get(storage_key).await? else {
+            return Ok(None);
+        };
+        let expires_at = self.inner.ttl(storage_key).await?
+            .map(|remaining| Utc::now() + Duration::seconds(remaining as i64));
+        Ok(Some(TrackedValue { size: value_size(&entry.value), expires_at }))
+    }
+
+    /// Re-read a stored key after a write whose result is not known up front
+    async fn remeasure(&self, storage_key: &str) -> StorageResult<()> {
+        let value = self.measure(storage_key).await?;
+        self.usage.lock().unwrap().record(storage_key, value);
+        Ok(())
+    }
+
+    /// Backups cover the whole inner store, beyond what this namespace may touch
+    fn whole_store_denied(&self, operation: &str) -> StorageError {
+        StorageError::PermissionDenied(format!("{} spans the whole store, not namespace '{}'", operation, self.namespace))
+    }
+}
+
+#[async_trait]
+impl StorageClient for NamespacedStore {
+    async fn get(&self, key: &str) -> StorageResult<Option<StorageEntry>> {
+        Ok(self.inner.get(&self.storage_key(key)).await?.map(|mut entry| {
+            entry.key = key.to_string();
+            entry
+        }))
+    }
+
+    async fn set(&self, key: &str, value: serde_json::Value, ttl_seconds: Option<u64>) -> StorageResult<()> {
+        let key = self.storage_key(key);
+        let reservation = self.reserve(vec![(key.clone(), Some(TrackedValue::new(value_size(&value), ttl_seconds)))])?;
+        if let Err(e) = self.inner.set(&key, value, ttl_seconds).await {
+            self.release(reservation);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> StorageResult<bool> {
+        let key = self.storage_key(key);
+        let deleted = self.inner.delete(&key).await?;
+        self.usage.lock().unwrap().record(&key, None);
+        Ok(deleted)
+    }
+
+    async fn exists(&self, key: &str) -> StorageResult<bool> {
+        self.inner.exists(&self.storage_key(key)).await
+    }
+
+    async fn list(&self, pattern: Option<&str>, limit: Option<usize>) -> StorageResult<Vec<String>> {
+        let keys = self.inner.list(Some(&self.prefix), None).await?;
+        Ok(keys.iter()
+            .filter_map(|key| self.local_key(key))
+            .filter(|key| pattern.is_none_or(|pattern| key.contains(pattern)))
+            .take(limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    async fn scan(&self, prefix: &str, cursor: Option<Cursor>, limit: usize) -> StorageResult<(Vec<String>, Option<Cursor>)> {
+        let (keys, cursor) = self.inner.scan(&self.storage_key(prefix), cursor, limit).await?;
+        Ok((keys.iter().filter_map(|key| self.local_key(key)).collect(), cursor))
+    }
+
+    async fn count(&self, pattern: Option<&str>) -> StorageResult<u64> {
+        Ok(self.list(pattern, None).await?.len() as u64)
+    }
+
+    async fn ttl(&self, key: &str) -> StorageResult<Option<u64>> {
+        self.inner.ttl(&self.storage_key(key)).await
+    }
+
+    async fn expire(&self, key: &str, ttl_seconds: u64) -> StorageResult<bool> {
+        let key = self.storage_key(key);
+        let expired = self.inner.expire(&key, ttl_seconds).await?;
+        self.remeasure(&key).await?;
+        Ok(expired)
+    }
+
+    async fn persist(&self, key: &str) -> StorageResult<bool> {
+        let key = self.storage_key(key);
+        let persisted = self.inner.persist(&key).await?;
+        self.remeasure(&key).await?;
+        Ok(persisted)
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> StorageResult<i64> {
+        let key = self.storage_key(key);
+        // A new counter is at most as large as the delta
+        self.reserve_new_counter(&key, delta)?;
+        let result = self.inner.increment(&key, delta).await;
+        self.remeasure(&key).await?;
+        result
+    }
+
+    async fn decrement(&self, key: &str, delta: i64) -> StorageResult<i64> {
+        let key = self.storage_key(key);
+        self.reserve_new_counter(&key, -delta)?;
+        let result = self.inner.decrement(&key, delta).await;
+        self.remeasure(&key).await?;
+        result
+    }
+
+    async fn append(&self, key: &str, value: &str) -> StorageResult<usize> {
+        let key = self.storage_key(key);
+        self.reserve_growth(&key, value)?;
+        let result = self.inner.append(&key, value).await;
+        self.remeasure(&key).await?;
+        result
+    }
+
+    async fn prepend(&self, key: &str, value: &str) -> StorageResult<usize> {
+        let key = self.storage_key(key);
+        self.reserve_growth(&key, value)?;
+        let result = self.inner.prepend(&key, value).await;
+        self.remeasure(&key).await?;
+        result
+    }
+
+    async fn batch_execute(&self, mut batch: StorageBatch) -> StorageResult<Vec<StorageResult<()>>> {
+        let mut writes = Vec::new();
+        for operation in &mut batch.operations {
+            operation.key = self.storage_key(&operation.key);
+            match (&operation.operation, &operation.value) {
+                (StorageOperation::Set, Some(value)) => writes.push((
+                    operation.key.clone(),
+                    Some(TrackedValue::new(value_size(value), operation.ttl_seconds)),
+                )),
+                (StorageOperation::Delete, _) => writes.push((operation.key.clone(), None)),
+                _ => {}
+            }
+        }
+
+        self.reserve(writes)?;
+        let keys: Vec<String> = batch.operations.iter().map(|operation| operation.key.clone()).collect();
+        let results = self.inner.batch_execute(batch).await;
+        for key in keys {
+            self.remeasure(&key).await?;
+        }
+        results
+    }
+
+    async fn transaction(&self, ops: Vec<StorageOp>) -> StorageResult<()> {
+        let ops: Vec<StorageOp> = ops.into_iter()
+            .map(|op| match op {
+                StorageOp::Set { key, value, ttl_seconds } => StorageOp::Set { key: self.storage_key(&key), value, ttl_seconds },
+                StorageOp::Delete { key } => StorageOp::Delete { key: self.storage_key(&key) },
+            })
+            .collect();
+        let writes = ops.iter()
+            .map(|op| match op {
+                StorageOp::Set { key, value, ttl_seconds } => (key.clone(), Some(TrackedValue::new(value_size(value), *ttl_seconds))),
+                StorageOp::Delete { key } => (key.clone(), None),
+            })
+            .collect();
+
+        let reservation = self.reserve(writes)?;
+        if let Err(e) = self.inner.transaction(ops).await {
+            self.release(reservation);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn watch(&self, prefix: &str) -> StorageResult<KeyEventStream> {
+        let namespace_prefix = self.prefix.clone();
+        let events = self.inner.watch(&self.storage_key(prefix)).await?;
+        Ok(events
+            .map(move |event| event.map(|mut event| {
+                event.key = event.key.strip_prefix(&namespace_prefix).unwrap_or(&event.key).to_string();
+                event
+            }))
+            .boxed())
+    }
+
+    async fn query(&self, mut query: StorageQuery) -> StorageResult<Vec<StorageEntry>> {
+        query.prefix = Some(self.storage_key(query.prefix.as_deref().unwrap_or_default()));
+        let entries = self.inner.query(query).await?;
+        Ok(entries.into_iter()
+            .filter_map(|mut entry| {
+                entry.key = self.local_key(&entry.key)?;
+                Some(entry)
+            })
+            .collect())
+    }
+
+    async fn stats(&self) -> StorageResult<StorageStats> {
+        let usage = self.usage().await;
+        let mut stats = self.inner.stats().await?;
+        stats.total_keys = usage.keys;
+        stats.total_size_bytes = usage.bytes;
+        Ok(stats)
+    }
+
+    async fn health_check(&self) -> StorageResult<()> {
+        self.inner.health_check().await
+    }
+
+    async fn backup(&self, _location: &str) -> StorageResult<()> {
+        Err(self.whole_store_denied("Backup"))
+    }
+
+    async fn restore(&self, _location: &str) -> StorageResult<()> {
+        Err(self.whole_store_denied("Restore"))
+    }
+
+    /// Delete every key of the namespace, leaving other namespaces alone
+    async fn flush_all(&self) -> StorageResult<()> {
+        for key in self.namespace_keys().await? {
+            if let Err(e) = self.inner.delete(&key).await {
+                warn!("Failed to flush '{}' from namespace '{}': {}", key, self.namespace, e);
+                return Err(e);
+            }
+            self.usage.lock().unwrap().record(&key, None);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GenericStorageClient, MemoryBackend, StorageConfig};
+
+    fn create_memory_client() -> Arc<dyn StorageClient> {
+        let config = StorageConfig::default();
+        Arc::new(GenericStorageClient::new(Box::new(MemoryBackend::new(config))))
+    }
+
+    #[tokio::test]
+    async fn test_writes_up_to_byte_quota_succeed_and_over_it_fail() {
+        let inner = create_memory_client();
+        // Each value below serializes to 10 bytes: the 8 characters plus quotes
+        let store = NamespacedStore::new(inner.clone(), "sessions").await.unwrap().with_quota(NamespaceQuota::new().with_max_bytes(30));
+
+        for key in ["a", "b", "c"] {
+            store.set(key, serde_json::json!("12345678"), None).await.unwrap();
+        }
+        assert_eq!(store.usage().await, NamespaceUsage { keys: 3, bytes: 30 });
+        assert!(inner.exists("sessions:a").await.unwrap());
+
+        let err = store.set("d", serde_json::json!("x"), None).await.unwrap_err();
+        assert!(matches!(err, StorageError::QuotaExceeded(_)));
+        assert!(!store.exists("d").await.unwrap());
+
+        // Growing an existing value counts only the growth
+        assert!(matches!(store.set("a", serde_json::json!("123456789"), None).await, Err(StorageError::QuotaExceeded(_))));
+        store.set("a", serde_json::json!("1234"), None).await.unwrap();
+        assert_eq!(store.usage().await, NamespaceUsage { keys: 3, bytes: 26 });
+
+        // Freed space can be reused
+        store.delete("b").await.unwrap();
+        store.set("d", serde_json::json!("12345678"), None).await.unwrap();
+        assert_eq!(store.usage().await, NamespaceUsage { keys: 3, bytes: 26 });
+
+        // Other namespaces of the same client are not limited by it
+        let other = NamespacedStore::new(inner, "cache").await.unwrap();
+        other.set("a", serde_json::json!("x".repeat(100)), None).await.unwrap();
+        assert_eq!(store.get("a").await.unwrap().unwrap().value, serde_json::json!("1234"));
+    }
+
+    #[tokio::test]
+    async fn test_key_quota_applies_to_transactions_as_a_whole() {
+        let store = NamespacedStore::new(create_memory_client(), "jobs").await.unwrap().with_quota(NamespaceQuota::new().with_max_keys(2));
+        store.set("one", serde_json::json!(1), None).await.unwrap();
+
+        let ops = |keys: &[&str]| keys.iter()
+            .map(|key| StorageOp::Set { key: key.to_string(), value: serde_json::json!(0), ttl_seconds: None })
+            .collect::<Vec<_>>();
+        assert!(matches!(store.transaction(ops(&["two", "three"])).await, Err(StorageError::QuotaExceeded(_))));
+        assert!(!store.exists("two").await.unwrap());
+
+        store.transaction(ops(&["one", "two"])).await.unwrap();
+        assert_eq!(store.usage().await.keys, 2);
+        assert_eq!(store.recount().await.unwrap(), store.usage().await);
+    }
+
+    #[tokio::test]
+    async fn test_existing_data_counts_and_expired_keys_do_not() {
+        let inner = create_memory_client();
+        inner.set("jobs:old", serde_json::json!("12345678"), None).await.unwrap();
+        inner.set("other:key", serde_json::json!("12345678"), None).await.unwrap();
+
+        let store = NamespacedStore::new(inner, "jobs").await.unwrap().with_quota(NamespaceQuota::new().with_max_keys(2));
+        assert_eq!(store.usage().await, NamespaceUsage { keys: 1, bytes: 10 });
+
+        // A key whose TTL has run out no longer takes up quota
+        store.set("gone", serde_json::json!(1), Some(0)).await.unwrap();
+        assert_eq!(store.usage().await.keys, 1);
+        store.set("new", serde_json::json!(1), None).await.unwrap();
+        assert!(matches!(store.set("more", serde_json::json!(1), None).await, Err(StorageError::QuotaExceeded(_))));
+    }
+}
@@ -0,0 +1,407 @@
+//! Redis Storage Backend - Redis based key-value storage
+
+use crate::{StorageResult, StorageConfig, StorageBackend, StorageBackendType, StorageOperation, StorageOp, StorageError, Cursor, ScanPage, KeyEvent, KeyEventKind, KeyEventStream};
+use async_trait::async_trait;
+use futures::StreamExt;
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::Mutex;
+use chrono::Utc;
+use tracing::{debug, info, warn};
+
+/// Prefix of the keys holding scan state, which scans skip
+const SCAN_STATE_PREFIX: &str = "__sira_scan:";
+/// Seconds a scan's state outlives its last page
+const SCAN_STATE_TTL_SECONDS: usize = 600;
+/// Times a transaction is retried after a concurrent write to one of its keys
+const MAX_TRANSACTION_ATTEMPTS: usize = 16;
+
+/// Position of a scan, carried in its cursor
+///
+/// The keys a scan has seen are kept in a Redis set rather than in this
+/// process, so any client of the same server can resume the scan.
+#[derive(Serialize, Deserialize)]
+struct ScanToken {
+    id: String,
+    redis_cursor: u64,
+    /// Keys fetched from Redis but not yet returned
+    pending: VecDeque<String>,
+    /// Whether the set of seen keys was created
+    recorded: bool,
+}
+
+/// Redis storage backend
+pub struct RedisBackend {
+    config: StorageConfig,
+    client: redis::Client,
+    /// Shared connection, opened lazily and dropped when it breaks
+    connection: Mutex<Option<MultiplexedConnection>>,
+}
+
+impl RedisBackend {
+    /// Create a new Redis backend; the connection is opened on first use
+    pub fn new(config: StorageConfig) -> StorageResult<Self> {
+        let client = redis::Client::open(config.connection_string.as_str())
+            .map_err(|e| StorageError::ConfigurationError(format!("Invalid Redis URL: {}", e)))?;
+
+        Ok(Self {
+            config,
+            client,
+            connection: Mutex::new(None),
+        })
+    }
+
+    /// Get the shared multiplexed connection, opening it if needed
+    async fn connection(&self) -> StorageResult<MultiplexedConnection> {
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = connection.as_ref() {
+            return Ok(connection.clone());
+        }
+
+        let opened = self.client.get_multiplexed_tokio_connection()
+            .await
+            .map_err(|e| StorageError::ConnectionError(e.to_string()))?;
+        *connection = Some(opened.clone());
+        Ok(opened)
+    }
+
+    /// Map a Redis error, reporting broken connections as connection errors
+    fn map_error(e: redis::RedisError) -> StorageError {
+        if e.is_io_error() || e.is_connection_dropped() || e.is_connection_refusal() || e.is_timeout() {
+            StorageError::ConnectionError(e.to_string())
+        } else {
+            StorageError::BackendError(e.to_string())
+        }
+    }
+
+    /// Escape glob metacharacters so a prefix matches literally
+    fn escape_pattern(prefix: &str) -> String {
+        let mut escaped = String::with_capacity(prefix.len() + 1);
+        for c in prefix.chars() {
+            if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped.push('*');
+        escaped
+    }
+
+    /// Fetch the next page of a scan
+    async fn scan(&self, prefix: &str, cursor: Option<&str>, limit: usize) -> StorageResult<ScanPage> {
+        let mut token = match cursor {
+            Some(cursor) => serde_json::from_str::<ScanToken>(cursor)
+                .map_err(|_| StorageError::OperationError(format!("Invalid scan cursor: {}", cursor)))?,
+            None => ScanToken {
+                id: uuid::Uuid::new_v4().to_string(),
+                redis_cursor: 0,
+                pending: VecDeque::new(),
+                recorded: false,
+            },
+        };
+
+        let seen_key = format!("{}{}", SCAN_STATE_PREFIX, token.id);
+        let pattern = Self::escape_pattern(prefix);
+        let mut connection = self.connection().await?;
+
+        if token.recorded {
+            let alive: bool = connection.expire(&seen_key, SCAN_STATE_TTL_SECONDS).await.map_err(Self::map_error)?;
+            if !alive {
+                return Err(StorageError::OperationError("Scan cursor expired".to_string()));
+            }
+        }
+
+        // Redis cursor 0 marks both the start and the end of an iteration
+        let mut started = cursor.is_some();
+        while token.pending.len() < limit && (!started || token.redis_cursor != 0) {
+            let (next, batch): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(token.redis_cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(limit)
+                .query_async(&mut connection)
+                .await
+                .map_err(Self::map_error)?;
+            token.redis_cursor = next;
+            started = true;
+
+            let batch: Vec<String> = batch.into_iter().filter(|key| !key.starts_with(SCAN_STATE_PREFIX)).collect();
+            if batch.is_empty() {
+                continue;
+            }
+
+            // SCAN may report a key more than once; SADD tells which are new
+            let mut pipe = redis::pipe();
+            for key in &batch {
+                pipe.sadd(&seen_key, key);
+            }
+            pipe.expire(&seen_key, SCAN_STATE_TTL_SECONDS).ignore();
+            let added: Vec<bool> = pipe.query_async(&mut connection).await.map_err(Self::map_error)?;
+            token.pending.extend(batch.into_iter().zip(added).filter(|(_, added)| *added).map(|(key, _)| key));
+            token.recorded = true;
+        }
+
+        let take = limit.min(token.pending.len());
+        let keys: Vec<String> = token.pending.drain(..take).collect();
+
+        let cursor = if token.pending.is_empty() && token.redis_cursor == 0 {
+            if token.recorded {
+                connection.del::<_, ()>(&seen_key).await.map_err(Self::map_error)?;
+            }
+            None
+        } else {
+            Some(Cursor::new(serde_json::to_string(&token)?))
+        };
+
+        Ok(ScanPage { keys, cursor })
+    }
+}
+
+#[async_trait]
+impl StorageBackend for RedisBackend {
+    async fn init(&self) -> StorageResult<()> {
+        info!("Initializing Redis storage backend");
+        self.connection().await?;
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> StorageResult<()> {
+        info!("Shutting down Redis storage backend");
+        Ok(())
+    }
+
+    fn backend_type(&self) -> StorageBackendType {
+        StorageBackendType::Redis
+    }
+
+    fn config(&self) -> &StorageConfig {
+        &self.config
+    }
+
+    async fn health_check(&self) -> StorageResult<()> {
+        let mut connection = self.connection().await?;
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut connection)
+            .await
+            .map_err(Self::map_error)?;
+        Ok(())
+    }
+
+    async fn reconnect(&self) -> StorageResult<()> {
+        info!("Reconnecting to Redis");
+        self.connection.lock().await.take();
+        self.connection().await?;
+        Ok(())
+    }
+
+    async fn transaction(&self, ops: Vec<StorageOp>) -> StorageResult<()> {
+        // Overwritten entries keep their history, so the keys written are
+        // watched while they are read and the batch is retried if another
+        // client changes one before EXEC. WATCH is per connection, so this
+        // one is not shared.
+        let keys: Vec<&str> = ops.iter()
+            .filter_map(|op| match op {
+                StorageOp::Set { key, .. } => Some(key.as_str()),
+                StorageOp::Delete { .. } => None,
+            })
+            .collect();
+        let mut connection = self.client.get_async_connection().await.map_err(Self::map_error)?;
+
+        for _ in 0..MAX_TRANSACTION_ATTEMPTS {
+            let mut previous: HashMap<String, crate::StorageEntry> = HashMap::new();
+            if !keys.is_empty() {
+                redis::cmd("WATCH").arg(&keys).query_async::<_, ()>(&mut connection).await.map_err(Self::map_error)?;
+                let stored: Vec<Option<String>> = redis::cmd("MGET").arg(&keys).query_async(&mut connection).await.map_err(Self::map_error)?;
+                for (key, stored) in keys.iter().zip(stored) {
+                    if let Some(entry) = stored.and_then(|stored| serde_json::from_str(&stored).ok()) {
+                        previous.insert(key.to_string(), entry);
+                    }
+                }
+            }
+
+            // Entries are serialized before MULTI so a bad value aborts nothing half-done
+            let now = Utc::now();
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            for op in &ops {
+                match op {
+                    StorageOp::Set { key, value, ttl_seconds } => {
+                        let entry = crate::StorageEntry::replacing(previous.get(key), key, value.clone(), *ttl_seconds, now);
+                        let stored = serde_json::to_string(&entry)?;
+                        match ttl_seconds {
+                            Some(ttl) => pipe.set_ex(key, stored, *ttl as usize).ignore(),
+                            None => pipe.set(key, stored).ignore(),
+                        };
+                        previous.insert(key.clone(), entry);
+                    }
+                    StorageOp::Delete { key } => {
+                        pipe.del(key).ignore();
+                        previous.remove(key);
+                    }
+                }
+            }
+
+            // EXEC replies nil when a watched key changed
+            let committed: Option<()> = pipe.query_async(&mut connection).await.map_err(Self::map_error)?;
+            if committed.is_some() {
+                debug!("Committed transaction with MULTI/EXEC");
+                return Ok(());
+            }
+            debug!("Transaction raced a concurrent write, retrying");
+        }
+
+        Err(StorageError::TransactionError(format!(
+            "Keys kept changing concurrently; gave up after {} attempts", MAX_TRANSACTION_ATTEMPTS
+        )))
+    }
+
+    /// Watch keys through keyspace notifications
+    ///
+    /// The server must publish them, e.g. with `notify-keyspace-events K$gxe`
+    /// (`x` for expired and `e` for evicted keys, which are reported as
+    /// deletes). If the notification connection drops, the stream yields a
+    /// connection error and ends.
+    async fn watch(&self, prefix: &str) -> StorageResult<KeyEventStream> {
+        let channel_prefix = format!("__keyspace@{}__:", self.client.get_connection_info().redis.db);
+        let mut pubsub = self.client.get_async_connection()
+            .await
+            .map_err(Self::map_error)?
+            .into_pubsub();
+        pubsub.psubscribe(format!("{}{}", channel_prefix, Self::escape_pattern(prefix)))
+            .await
+            .map_err(Self::map_error)?;
+        debug!("Watching Redis keyspace for prefix: {}", prefix);
+        let watched_prefix = prefix.to_string();
+
+        let events = pubsub.into_on_message().filter_map(move |message| {
+            let key = message.get_channel_name().strip_prefix(&channel_prefix).map(str::to_string);
+            let kind = match message.get_payload::<String>().ok().as_deref() {
+                Some("set") => Some(KeyEventKind::Set),
+                Some("del" | "expired" | "evicted") => Some(KeyEventKind::Delete),
+                _ => None,
+            };
+            futures::future::ready(key.zip(kind).map(|(key, kind)| Ok(KeyEvent { key, kind })))
+        });
+        let disconnected = futures::stream::once(async move {
+            warn!("Redis keyspace notification connection for prefix '{}' closed", watched_prefix);
+            Err(StorageError::ConnectionError("Keyspace notification connection closed".to_string()))
+        });
+        Ok(events.chain(disconnected).boxed())
+    }
+
+    async fn execute_operation(&self, operation: StorageOperation, params: &HashMap<String, serde_json::Value>) -> StorageResult<serde_json::Value> {
+        if operation == StorageOperation::Scan {
+            let prefix = params.get("prefix").and_then(|v| v.as_str()).unwrap_or("");
+            let cursor = params.get("cursor").and_then(|v| v.as_str());
+            let limit = params.get("limit").and_then(|v| v.as_u64()).unwrap_or(100) as usize;
+
+            let page = self.scan(prefix, cursor, limit).await?;
+            return serde_json::to_value(page).map_err(|e| StorageError::SerializationError(e.to_string()));
+        }
+
+        let key = params.get("key")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| StorageError::OperationError("Missing key parameter".to_string()))?;
+        let mut connection = self.connection().await?;
+
+        match operation {
+            StorageOperation::Get => {
+                let stored: Option<String> = connection.get(key).await.map_err(Self::map_error)?;
+                let stored = stored.ok_or_else(|| StorageError::KeyNotFound(key.to_string()))?;
+                Ok(serde_json::from_str(&stored)?)
+            }
+
+            StorageOperation::Set => {
+                let value = params.get("value")
+                    .ok_or_else(|| StorageError::OperationError("Missing value parameter".to_string()))?;
+                let ttl_seconds = params.get("ttl_seconds").and_then(|v| v.as_u64());
+
+                let now = Utc::now();
+                let entry = crate::StorageEntry {
+                    key: key.to_string(),
+                    value: value.clone(),
+                    ttl_seconds,
+                    created_at: now,
+                    updated_at: now,
+                    version: 1,
+                    metadata: HashMap::new(),
+                };
+                let stored = serde_json::to_string(&entry)?;
+
+                match ttl_seconds {
+                    Some(ttl) => connection.set_ex::<_, _, ()>(key, stored, ttl as usize).await,
+                    None => connection.set::<_, _, ()>(key, stored).await,
+                }
+                .map_err(Self::map_error)?;
+                debug!("Set key: {} in Redis backend", key);
+
+                Ok(serde_json::json!(true))
+            }
+
+            StorageOperation::Delete => {
+                let removed: u64 = connection.del(key).await.map_err(Self::map_error)?;
+                if removed == 0 {
+                    return Err(StorageError::KeyNotFound(key.to_string()));
+                }
+                Ok(serde_json::json!(true))
+            }
+
+            StorageOperation::Exists => {
+                let exists: bool = connection.exists(key).await.map_err(Self::map_error)?;
+                Ok(serde_json::json!(exists))
+            }
+
+            StorageOperation::TTL => {
+                // -2: missing key, -1: no expiry
+                let ttl: i64 = connection.ttl(key).await.map_err(Self::map_error)?;
+                match ttl {
+                    -2 => Err(StorageError::KeyNotFound(key.to_string())),
+                    ttl if ttl < 0 => Ok(serde_json::Value::Null),
+                    ttl => Ok(serde_json::json!(ttl)),
+                }
+            }
+
+            StorageOperation::Expire => {
+                let ttl_seconds = params.get("ttl_seconds")
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| StorageError::OperationError("Missing ttl_seconds parameter".to_string()))?;
+                let updated: bool = connection.expire(key, ttl_seconds as usize).await.map_err(Self::map_error)?;
+                Ok(serde_json::json!(updated))
+            }
+
+            StorageOperation::Persist => {
+                let updated: bool = connection.persist(key).await.map_err(Self::map_error)?;
+                Ok(serde_json::json!(updated))
+            }
+
+            _ => {
+                Err(StorageError::OperationError(format!("Unsupported operation: {:?}", operation)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_pattern_escapes_glob_characters() {
+        assert_eq!(RedisBackend::escape_pattern("user:"), "user:*");
+        assert_eq!(RedisBackend::escape_pattern("a*b?[c]"), r"a\*b\?\[c\]*");
+    }
+
+    #[tokio::test]
+    async fn test_scan_rejects_cursors_it_did_not_issue() {
+        let backend = RedisBackend::new(StorageConfig {
+            connection_string: "redis://127.0.0.1:1".to_string(),
+            ..StorageConfig::default()
+        }).unwrap();
+
+        let result = backend.scan("user:", Some("not-a-cursor"), 10).await;
+        assert!(matches!(result, Err(StorageError::OperationError(_))));
+    }
+}
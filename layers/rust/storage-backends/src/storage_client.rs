@@ -1,8 +1,10 @@
 //! Storage Client - Unified interface for all storage backends
 
-use crate::{StorageResult, StorageConfig, StorageEntry, StorageQuery, StorageBatch, StorageStats, StorageEvent, StorageEventHandler};
+use crate::{StorageResult, StorageConfig, StorageEntry, StorageError, StorageOperation, StorageQuery, StorageBatch, StorageOp, StorageStats, StorageEvent, StorageEventHandler, Cursor, ScanPage, KeyEventStream};
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
 
 /// Unified storage client interface
 #[async_trait]
@@ -22,6 +24,13 @@ pub trait StorageClient: Send + Sync {
     /// List keys with optional pattern
     async fn list(&self, pattern: Option<&str>, limit: Option<usize>) -> StorageResult<Vec<String>>;
 
+    /// Iterate keys starting with `prefix`, at most `limit` per page
+    ///
+    /// Start with no cursor and pass each returned cursor back until `None`
+    /// is returned. Each key is returned at most once within a full scan;
+    /// keys written or deleted during the scan may or may not be included.
+    async fn scan(&self, prefix: &str, cursor: Option<Cursor>, limit: usize) -> StorageResult<(Vec<String>, Option<Cursor>)>;
+
     /// Count keys matching pattern
     async fn count(&self, pattern: Option<&str>) -> StorageResult<u64>;
 
@@ -49,14 +58,24 @@ pub trait StorageClient: Send + Sync {
     /// Execute batch operations
     async fn batch_execute(&self, batch: StorageBatch) -> StorageResult<Vec<StorageResult<()>>>;
 
+    /// Apply a batch of set/delete operations all-or-nothing
+    ///
+    /// If any operation fails none of them take effect.
+    async fn transaction(&self, ops: Vec<StorageOp>) -> StorageResult<()>;
+
+    /// Watch for sets and deletes of keys starting with `prefix`
+    ///
+    /// Fails if the backend has no change notifications.
+    async fn watch(&self, prefix: &str) -> StorageResult<KeyEventStream>;
+
     /// Query storage with advanced filters
     async fn query(&self, query: StorageQuery) -> StorageResult<Vec<StorageEntry>>;
 
     /// Get storage statistics
     async fn stats(&self) -> StorageResult<StorageStats>;
 
-    /// Health check
-    async fn health_check(&self) -> StorageResult<bool>;
+    /// Check that the underlying store is reachable
+    async fn health_check(&self) -> StorageResult<()>;
 
     /// Backup storage
     async fn backup(&self, location: &str) -> StorageResult<()>;
@@ -69,21 +88,57 @@ pub trait StorageClient: Send + Sync {
 }
 
 
+/// How a client recovers from connection errors
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Reconnect attempts before the error is returned
+    pub max_attempts: u32,
+    /// Delay before the first reconnect, doubled after each failure
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between reconnects
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
 /// Generic storage client implementation
 pub struct GenericStorageClient {
     backend: Box<dyn crate::StorageBackend>,
     event_handlers: Vec<Box<dyn StorageEventHandler>>,
+    reconnect_policy: ReconnectPolicy,
 }
 
 impl GenericStorageClient {
     /// Create a new storage client with a backend
+    ///
+    /// The backend's `retry_attempts` setting bounds reconnects on connection errors.
     pub fn new(backend: Box<dyn crate::StorageBackend>) -> Self {
+        let mut reconnect_policy = ReconnectPolicy::default();
+        if let Some(attempts) = backend.config().retry_attempts {
+            reconnect_policy.max_attempts = attempts;
+        }
+
         Self {
             backend,
             event_handlers: Vec::new(),
+            reconnect_policy,
         }
     }
 
+    /// Set how connection errors are recovered from
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
     /// Add an event handler
     pub fn add_event_handler(&mut self, handler: Box<dyn StorageEventHandler>) {
         self.event_handlers.push(handler);
@@ -97,6 +152,53 @@ impl GenericStorageClient {
             }
         }
     }
+
+    /// Execute a backend operation, reconnecting on connection errors
+    ///
+    /// Only idempotent operations are retried. A connection error may hit
+    /// after the backend applied a write, so retrying an increment or append
+    /// could apply it twice; those reconnect for the next call and fail.
+    async fn execute(&self, operation: StorageOperation, params: &HashMap<String, serde_json::Value>) -> StorageResult<serde_json::Value> {
+        if operation.is_idempotent() {
+            return self.with_reconnect(|| self.backend.execute_operation(operation, params)).await;
+        }
+
+        let result = self.backend.execute_operation(operation, params).await;
+        if let Err(StorageError::ConnectionError(e)) = &result {
+            tracing::warn!("Storage connection error during {:?}, reconnecting without retrying: {}", operation, e);
+            if let Err(e) = self.backend.reconnect().await {
+                tracing::warn!("Storage reconnect failed: {}", e);
+            }
+        }
+        result
+    }
+
+    /// Run a backend call, reconnecting with backoff while it fails to connect
+    async fn with_reconnect<T, F, Fut>(&self, mut call: F) -> StorageResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = StorageResult<T>>,
+    {
+        let mut backoff = self.reconnect_policy.initial_backoff;
+        let mut attempt = 0;
+
+        loop {
+            match call().await {
+                Err(StorageError::ConnectionError(e)) if attempt < self.reconnect_policy.max_attempts => {
+                    attempt += 1;
+                    tracing::warn!("Storage connection error, reconnecting (attempt {}): {}", attempt, e);
+
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.reconnect_policy.max_backoff);
+
+                    if let Err(e) = self.backend.reconnect().await {
+                        tracing::warn!("Storage reconnect failed: {}", e);
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -105,7 +207,7 @@ impl StorageClient for GenericStorageClient {
         let mut params = HashMap::new();
         params.insert("key".to_string(), serde_json::json!(key));
 
-        match self.backend.execute_operation(crate::StorageOperation::Get, &params).await {
+        match self.execute(crate::StorageOperation::Get, &params).await {
             Ok(value) => {
                 let entry: StorageEntry = serde_json::from_value(value)?;
                 Ok(Some(entry))
@@ -123,7 +225,7 @@ impl StorageClient for GenericStorageClient {
             params.insert("ttl_seconds".to_string(), serde_json::json!(ttl));
         }
 
-        self.backend.execute_operation(crate::StorageOperation::Set, &params).await?;
+        self.execute(crate::StorageOperation::Set, &params).await?;
 
         // Emit event
         let size_bytes = serde_json::to_string(&value).map(|s| s.len()).unwrap_or(0);
@@ -139,7 +241,7 @@ impl StorageClient for GenericStorageClient {
         let mut params = HashMap::new();
         params.insert("key".to_string(), serde_json::json!(key));
 
-        match self.backend.execute_operation(crate::StorageOperation::Delete, &params).await {
+        match self.execute(crate::StorageOperation::Delete, &params).await {
             Ok(_) => {
                 self.emit_event(StorageEvent::KeyDeleted {
                     key: key.to_string(),
@@ -155,7 +257,7 @@ impl StorageClient for GenericStorageClient {
         let mut params = HashMap::new();
         params.insert("key".to_string(), serde_json::json!(key));
 
-        match self.backend.execute_operation(crate::StorageOperation::Exists, &params).await {
+        match self.execute(crate::StorageOperation::Exists, &params).await {
             Ok(value) => Ok(value.as_bool().unwrap_or(false)),
             Err(_) => Ok(false),
         }
@@ -170,18 +272,35 @@ impl StorageClient for GenericStorageClient {
             params.insert("limit".to_string(), serde_json::json!(l));
         }
 
-        let result = self.backend.execute_operation(crate::StorageOperation::List, &params).await?;
+        let result = self.execute(crate::StorageOperation::List, &params).await?;
         let keys: Vec<String> = serde_json::from_value(result)?;
         Ok(keys)
     }
 
+    async fn scan(&self, prefix: &str, cursor: Option<Cursor>, limit: usize) -> StorageResult<(Vec<String>, Option<Cursor>)> {
+        if limit == 0 {
+            return Err(crate::StorageError::OperationError("Scan limit must be positive".to_string()));
+        }
+
+        let mut params = HashMap::new();
+        params.insert("prefix".to_string(), serde_json::json!(prefix));
+        params.insert("limit".to_string(), serde_json::json!(limit));
+        if let Some(c) = cursor {
+            params.insert("cursor".to_string(), serde_json::json!(c));
+        }
+
+        let result = self.execute(crate::StorageOperation::Scan, &params).await?;
+        let page: ScanPage = serde_json::from_value(result)?;
+        Ok((page.keys, page.cursor))
+    }
+
     async fn count(&self, pattern: Option<&str>) -> StorageResult<u64> {
         let mut params = HashMap::new();
         if let Some(p) = pattern {
             params.insert("pattern".to_string(), serde_json::json!(p));
         }
 
-        let result = self.backend.execute_operation(crate::StorageOperation::Count, params).await?;
+        let result = self.execute(crate::StorageOperation::Count, &params).await?;
         Ok(result.as_u64().unwrap_or(0))
     }
 
@@ -189,7 +308,7 @@ impl StorageClient for GenericStorageClient {
         let mut params = HashMap::new();
         params.insert("key".to_string(), serde_json::json!(key));
 
-        match self.backend.execute_operation(crate::StorageOperation::TTL, params).await {
+        match self.execute(crate::StorageOperation::TTL, &params).await {
             Ok(value) => {
                 if let Some(ttl) = value.as_u64() {
                     Ok(Some(ttl))
@@ -207,7 +326,7 @@ impl StorageClient for GenericStorageClient {
         params.insert("key".to_string(), serde_json::json!(key));
         params.insert("ttl_seconds".to_string(), serde_json::json!(ttl_seconds));
 
-        match self.backend.execute_operation(crate::StorageOperation::Expire, params).await {
+        match self.execute(crate::StorageOperation::Expire, &params).await {
             Ok(value) => Ok(value.as_bool().unwrap_or(false)),
             Err(crate::StorageError::KeyNotFound(_)) => Ok(false),
             Err(e) => Err(e),
@@ -218,7 +337,7 @@ impl StorageClient for GenericStorageClient {
         let mut params = HashMap::new();
         params.insert("key".to_string(), serde_json::json!(key));
 
-        match self.backend.execute_operation(crate::StorageOperation::Persist, params).await {
+        match self.execute(crate::StorageOperation::Persist, &params).await {
             Ok(value) => Ok(value.as_bool().unwrap_or(false)),
             Err(crate::StorageError::KeyNotFound(_)) => Ok(false),
             Err(e) => Err(e),
@@ -230,7 +349,7 @@ impl StorageClient for GenericStorageClient {
         params.insert("key".to_string(), serde_json::json!(key));
         params.insert("delta".to_string(), serde_json::json!(delta));
 
-        let result = self.backend.execute_operation(crate::StorageOperation::Increment, params).await?;
+        let result = self.execute(crate::StorageOperation::Increment, &params).await?;
         Ok(result.as_i64().unwrap_or(0))
     }
 
@@ -239,7 +358,7 @@ impl StorageClient for GenericStorageClient {
         params.insert("key".to_string(), serde_json::json!(key));
         params.insert("delta".to_string(), serde_json::json!(delta));
 
-        let result = self.backend.execute_operation(crate::StorageOperation::Decrement, params).await?;
+        let result = self.execute(crate::StorageOperation::Decrement, &params).await?;
         Ok(result.as_i64().unwrap_or(0))
     }
 
@@ -248,7 +367,7 @@ impl StorageClient for GenericStorageClient {
         params.insert("key".to_string(), serde_json::json!(key));
         params.insert("value".to_string(), serde_json::json!(value));
 
-        let result = self.backend.execute_operation(crate::StorageOperation::Append, params).await?;
+        let result = self.execute(crate::StorageOperation::Append, &params).await?;
         Ok(result.as_u64().unwrap_or(0) as usize)
     }
 
@@ -257,20 +376,45 @@ impl StorageClient for GenericStorageClient {
         params.insert("key".to_string(), serde_json::json!(key));
         params.insert("value".to_string(), serde_json::json!(value));
 
-        let result = self.backend.execute_operation(crate::StorageOperation::Prepend, params).await?;
+        let result = self.execute(crate::StorageOperation::Prepend, &params).await?;
         Ok(result.as_u64().unwrap_or(0) as usize)
     }
 
     async fn batch_execute(&self, batch: StorageBatch) -> StorageResult<Vec<StorageResult<()>>> {
         let params = HashMap::new(); // Batch operations would need special handling
-        let _ = self.backend.execute_operation(crate::StorageOperation::Batch, params).await?;
+        let _ = self.execute(crate::StorageOperation::Batch, &params).await?;
         // For now, return empty vec - full implementation would handle batch operations
         Ok(vec![])
     }
 
+    async fn transaction(&self, ops: Vec<StorageOp>) -> StorageResult<()> {
+        if ops.is_empty() {
+            return Ok(());
+        }
+
+        self.with_reconnect(|| self.backend.transaction(ops.clone())).await?;
+
+        for op in ops {
+            let event = match op {
+                StorageOp::Set { key, value, .. } => StorageEvent::KeySet {
+                    size_bytes: serde_json::to_string(&value).map(|s| s.len()).unwrap_or(0),
+                    key,
+                },
+                StorageOp::Delete { key } => StorageEvent::KeyDeleted { key },
+            };
+            self.emit_event(event).await;
+        }
+
+        Ok(())
+    }
+
+    async fn watch(&self, prefix: &str) -> StorageResult<KeyEventStream> {
+        self.with_reconnect(|| self.backend.watch(prefix)).await
+    }
+
     async fn query(&self, query: StorageQuery) -> StorageResult<Vec<StorageEntry>> {
         let params = HashMap::new(); // Query operations would need special handling
-        let _ = self.backend.execute_operation(crate::StorageOperation::Search, params).await?;
+        let _ = self.execute(crate::StorageOperation::Search, &params).await?;
         // For now, return empty vec - full implementation would handle query operations
         Ok(vec![])
     }
@@ -292,9 +436,8 @@ impl StorageClient for GenericStorageClient {
         })
     }
 
-    async fn health_check(&self) -> StorageResult<bool> {
-        // Basic health check - backends should override this
-        Ok(true)
+    async fn health_check(&self) -> StorageResult<()> {
+        self.with_reconnect(|| self.backend.health_check()).await
     }
 
     async fn backup(&self, location: &str) -> StorageResult<()> {
@@ -323,3 +466,127 @@ impl StorageClient for GenericStorageClient {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryBackend, StorageBackend, StorageBackendType};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn memory_config() -> StorageConfig {
+        StorageConfig::default()
+    }
+
+    fn fast_reconnects() -> ReconnectPolicy {
+        ReconnectPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        }
+    }
+
+    /// Network-like backend over memory storage whose connection can be dropped
+    struct FlakyBackend {
+        inner: MemoryBackend,
+        connected: Arc<AtomicBool>,
+        /// Whether reconnecting succeeds
+        reachable: Arc<AtomicBool>,
+        reconnects: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl StorageBackend for FlakyBackend {
+        async fn init(&self) -> StorageResult<()> {
+            Ok(())
+        }
+
+        async fn shutdown(&self) -> StorageResult<()> {
+            Ok(())
+        }
+
+        fn backend_type(&self) -> StorageBackendType {
+            StorageBackendType::Redis
+        }
+
+        fn config(&self) -> &StorageConfig {
+            self.inner.config()
+        }
+
+        async fn execute_operation(&self, operation: StorageOperation, params: &HashMap<String, serde_json::Value>) -> StorageResult<serde_json::Value> {
+            self.health_check().await?;
+            self.inner.execute_operation(operation, params).await
+        }
+
+        async fn health_check(&self) -> StorageResult<()> {
+            if self.connected.load(Ordering::SeqCst) {
+                Ok(())
+            } else {
+                Err(StorageError::ConnectionError("connection reset by peer".to_string()))
+            }
+        }
+
+        async fn reconnect(&self) -> StorageResult<()> {
+            self.reconnects.fetch_add(1, Ordering::SeqCst);
+            if !self.reachable.load(Ordering::SeqCst) {
+                return Err(StorageError::ConnectionError("connection refused".to_string()));
+            }
+            self.connected.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_backend_is_always_healthy() {
+        let client = GenericStorageClient::new(Box::new(MemoryBackend::new(memory_config())));
+        client.health_check().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_reconnects_after_disconnect() {
+        let connected = Arc::new(AtomicBool::new(true));
+        let reachable = Arc::new(AtomicBool::new(true));
+        let reconnects = Arc::new(AtomicUsize::new(0));
+        let client = GenericStorageClient::new(Box::new(FlakyBackend {
+            inner: MemoryBackend::new(memory_config()),
+            connected: connected.clone(),
+            reachable: reachable.clone(),
+            reconnects: reconnects.clone(),
+        })).with_reconnect_policy(fast_reconnects());
+
+        client.set("greeting", serde_json::json!("hello"), None).await.unwrap();
+
+        // A dropped connection is re-established transparently
+        connected.store(false, Ordering::SeqCst);
+        let entry = client.get("greeting").await.unwrap().unwrap();
+        assert_eq!(entry.value, serde_json::json!("hello"));
+        assert_eq!(reconnects.load(Ordering::SeqCst), 1);
+
+        // While the server stays unreachable the error surfaces once retries run out
+        connected.store(false, Ordering::SeqCst);
+        reachable.store(false, Ordering::SeqCst);
+        assert!(matches!(client.health_check().await, Err(StorageError::ConnectionError(_))));
+        assert_eq!(reconnects.load(Ordering::SeqCst), 4);
+
+        reachable.store(true, Ordering::SeqCst);
+        client.health_check().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_writes_that_are_not_idempotent_are_not_retried() {
+        let connected = Arc::new(AtomicBool::new(true));
+        let reconnects = Arc::new(AtomicUsize::new(0));
+        let client = GenericStorageClient::new(Box::new(FlakyBackend {
+            inner: MemoryBackend::new(memory_config()),
+            connected: connected.clone(),
+            reachable: Arc::new(AtomicBool::new(true)),
+            reconnects: reconnects.clone(),
+        })).with_reconnect_policy(fast_reconnects());
+
+        // The failed increment is reported rather than retried, but the connection is restored
+        connected.store(false, Ordering::SeqCst);
+        assert!(matches!(client.increment("counter", 1).await, Err(StorageError::ConnectionError(_))));
+        assert_eq!(reconnects.load(Ordering::SeqCst), 1);
+        assert_eq!(client.increment("counter", 1).await.unwrap(), 1);
+    }
+}
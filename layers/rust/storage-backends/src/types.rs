@@ -24,6 +24,34 @@ pub trait StorageBackend: Send + Sync {
 
     /// Execute raw storage operations
     async fn execute_operation(&self, operation: StorageOperation, params: &HashMap<String, serde_json::Value>) -> StorageResult<serde_json::Value>;
+
+    /// Check that the backend is reachable; local backends are always healthy
+    async fn health_check(&self) -> StorageResult<()> {
+        Ok(())
+    }
+
+    /// Re-establish the backend's connection after a connection error
+    async fn reconnect(&self) -> StorageResult<()> {
+        Ok(())
+    }
+
+    /// Apply a batch of writes all-or-nothing
+    ///
+    /// Backends without atomic multi-key writes refuse transactions.
+    async fn transaction(&self, _ops: Vec<StorageOp>) -> StorageResult<()> {
+        Err(crate::StorageError::TransactionError(format!(
+            "{:?} backend does not support transactions", self.backend_type()
+        )))
+    }
+
+    /// Watch for sets and deletes of keys starting with `prefix`
+    ///
+    /// Backends without change notifications refuse watches.
+    async fn watch(&self, _prefix: &str) -> StorageResult<KeyEventStream> {
+        Err(crate::StorageError::OperationError(format!(
+            "{:?} backend does not support watching keys", self.backend_type()
+        )))
+    }
 }
 
 /// Storage backend types
@@ -57,6 +85,16 @@ pub enum StorageOperation {
     Prepend,
     Search,
     Batch,
+    Scan,
+}
+
+impl StorageOperation {
+    /// Whether running the operation twice has the same effect as running it once
+    ///
+    /// A batch may hold increments, so it counts as not idempotent.
+    pub fn is_idempotent(&self) -> bool {
+        !matches!(self, Self::Increment | Self::Decrement | Self::Append | Self::Prepend | Self::Batch)
+    }
 }
 
 /// Storage configuration
@@ -76,6 +114,26 @@ pub struct StorageConfig {
     pub namespace: Option<String>,
 }
 
+impl Default for StorageConfig {
+    /// In-memory storage without limits, compression or encryption
+    fn default() -> Self {
+        Self {
+            backend_type: StorageBackendType::Memory,
+            connection_string: "memory://".to_string(),
+            pool_size: None,
+            timeout_seconds: None,
+            retry_attempts: None,
+            enable_compression: false,
+            enable_encryption: false,
+            max_connections: None,
+            max_key_size_bytes: None,
+            max_value_size_bytes: None,
+            default_ttl_seconds: None,
+            namespace: None,
+        }
+    }
+}
+
 /// Storage key-value pair
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageEntry {
@@ -88,6 +146,24 @@ pub struct StorageEntry {
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
+impl StorageEntry {
+    /// Entry written to `key`, replacing `previous` if the key held one
+    ///
+    /// A replaced entry keeps its creation time and metadata and moves to
+    /// the next version.
+    pub(crate) fn replacing(previous: Option<&StorageEntry>, key: &str, value: serde_json::Value, ttl_seconds: Option<u64>, now: DateTime<Utc>) -> Self {
+        Self {
+            key: key.to_string(),
+            value,
+            ttl_seconds,
+            created_at: previous.map_or(now, |previous| previous.created_at),
+            updated_at: now,
+            version: previous.map_or(1, |previous| previous.version + 1),
+            metadata: previous.map(|previous| previous.metadata.clone()).unwrap_or_default(),
+        }
+    }
+}
+
 /// Storage query parameters
 #[derive(Debug, Clone, Default)]
 pub struct StorageQuery {
@@ -100,6 +176,76 @@ pub struct StorageQuery {
     pub filters: HashMap<String, serde_json::Value>,
 }
 
+/// Opaque position in a key scan
+///
+/// Returned with each page of [`StorageClient::scan`](crate::StorageClient::scan)
+/// and passed back to fetch the next one. Its contents are backend specific.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Cursor(String);
+
+impl Cursor {
+    /// Create a cursor from a backend token
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(token.into())
+    }
+
+    /// Backend token of the cursor
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// One page of a key scan
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanPage {
+    pub keys: Vec<String>,
+    /// Cursor for the next page, `None` once the scan is complete
+    pub cursor: Option<Cursor>,
+}
+
+/// Kind of change reported to watchers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyEventKind {
+    Set,
+    Delete,
+}
+
+/// Change to a watched key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyEvent {
+    pub key: String,
+    pub kind: KeyEventKind,
+}
+
+/// Stream of changes to watched keys, ending when the backend goes away
+///
+/// A watch that loses its connection yields the error and then ends, so
+/// callers know to watch again.
+pub type KeyEventStream = futures::stream::BoxStream<'static, StorageResult<KeyEvent>>;
+
+/// Write applied as part of a transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StorageOp {
+    Set {
+        key: String,
+        value: serde_json::Value,
+        ttl_seconds: Option<u64>,
+    },
+    Delete {
+        key: String,
+    },
+}
+
+impl StorageOp {
+    /// Key the operation writes
+    pub fn key(&self) -> &str {
+        match self {
+            StorageOp::Set { key, .. } | StorageOp::Delete { key } => key,
+        }
+    }
+}
+
 /// Storage batch operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageBatch {
@@ -0,0 +1,154 @@
+//! Write-ahead log for the file backend
+//!
+//! Every mutation is appended to the log and synced to disk before it is
+//! applied to the store, so a crash between the two loses nothing: replaying
+//! the log on startup redoes the mutations the store may have missed.
+//! Replaying is idempotent, which lets a checkpoint simply persist the store
+//! and then truncate the log.
+
+use crate::{StorageEntry, StorageResult};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Mutation recorded in the log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum WalRecord {
+    Set { entry: StorageEntry },
+    Delete { key: String },
+}
+
+/// Append-only log of mutations, one JSON record per line
+pub struct WriteAheadLog {
+    path: PathBuf,
+    file: Mutex<File>,
+    records: Mutex<usize>,
+}
+
+impl WriteAheadLog {
+    /// Open the log at `path`, creating it if missing
+    ///
+    /// A torn record left by a crash is cut off here, so records appended
+    /// from now on follow the last good one and are not lost behind it.
+    pub fn open(path: impl AsRef<Path>) -> StorageResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let created = !path.exists();
+        let (records, valid_len) = Self::read(&path)?;
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if file.metadata()?.len() > valid_len {
+            warn!("Truncating write-ahead log {} to its last good record", path.display());
+            file.set_len(valid_len)?;
+            file.sync_all()?;
+        }
+        if created {
+            sync_parent_dir(&path)?;
+        }
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            records: Mutex::new(records.len()),
+        })
+    }
+
+    /// Append a record and sync it to disk
+    pub fn append(&self, record: &WalRecord) -> StorageResult<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&line)?;
+        file.sync_data()?;
+        *self.records.lock().unwrap() += 1;
+        Ok(())
+    }
+
+    /// Records in the log, oldest first
+    pub fn records(&self) -> StorageResult<Vec<WalRecord>> {
+        let _file = self.file.lock().unwrap();
+        Ok(Self::read(&self.path)?.0)
+    }
+
+    /// Number of records in the log
+    pub fn len(&self) -> usize {
+        *self.records.lock().unwrap()
+    }
+
+    /// Whether the log holds no records
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every record, once the store they describe is persisted
+    pub fn truncate(&self) -> StorageResult<()> {
+        let file = self.file.lock().unwrap();
+        file.set_len(0)?;
+        file.sync_all()?;
+        *self.records.lock().unwrap() = 0;
+        Ok(())
+    }
+
+    /// Read the records of a log file and the length in bytes they take up
+    ///
+    /// Reading stops at the first unreadable or unterminated line: a crash
+    /// while appending leaves a torn last record, which was never applied and
+    /// is discarded.
+    fn read(path: &Path) -> StorageResult<(Vec<WalRecord>, u64)> {
+        if !path.exists() {
+            return Ok((Vec::new(), 0));
+        }
+
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut records = Vec::new();
+        let mut valid_len = 0;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line)?;
+            if read == 0 {
+                break;
+            }
+
+            let record = line.strip_suffix(b"\n").and_then(|line| serde_json::from_slice(line).ok());
+            match record {
+                Some(record) => {
+                    records.push(record);
+                    valid_len += read as u64;
+                }
+                None => {
+                    warn!("Ignoring write-ahead log {} from line {}: torn or corrupt record", path.display(), records.len() + 1);
+                    break;
+                }
+            }
+        }
+        Ok((records, valid_len))
+    }
+}
+
+/// Replace `path` with `contents` so a crash leaves either the old or the new file
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> StorageResult<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(tmp_path, path)?;
+    sync_parent_dir(path)
+}
+
+/// Sync the directory holding `path`, so a file created or renamed there survives a crash
+fn sync_parent_dir(path: &Path) -> StorageResult<()> {
+    #[cfg(unix)]
+    {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
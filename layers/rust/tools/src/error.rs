@@ -29,6 +29,9 @@ pub enum ToolsError {
     #[error("Tool timeout error: {0}")]
     Timeout(String),
 
+    #[error("Tool execution cancelled: {0}")]
+    Cancelled(String),
+
     #[error("Unknown tool error: {0}")]
     Unknown(String),
 }
@@ -0,0 +1,163 @@
+//! Sanitization of tool inputs
+//!
+//! Tool inputs often come straight from LLM output and may carry injection
+//! attempts aimed at the shell or database a tool talks to. An
+//! [`InputSanitizer`] rejects inputs matching a denylist of known attack
+//! patterns and escapes the characters its target interpreter treats as
+//! syntax, before the tool runs.
+
+use crate::{ToolInput, ToolsError, ToolsResult};
+use regex::Regex;
+
+/// Interpreter a tool passes its input to, deciding how input is escaped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanitizationProfile {
+    /// Shell commands: metacharacters are backslash-escaped, newlines flattened
+    Shell,
+    /// Standard SQL string literals: single quotes are doubled
+    Sql,
+    /// MySQL string literals, where backslashes also escape by default
+    MySql,
+    /// Free text: only control characters are stripped
+    Text,
+}
+
+/// Patterns rejected in shell inputs
+const SHELL_DENYLIST: &[&str] = &[
+    r"\$\(",                                   // command substitution
+    r"`",                                      // legacy command substitution
+    r"(?i)\brm\s+-[a-z]*(rf|fr)",              // recursive forced delete
+    r"(?i)\b(curl|wget)\b[^|]*\|\s*(ba|z)?sh\b", // download and execute
+    r"(?i)\bmkfs\b",
+    r"(?i)/etc/(passwd|shadow|sudoers)",
+    r":\(\)\s*\{",                             // fork bomb
+];
+
+/// Patterns rejected in SQL inputs
+const SQL_DENYLIST: &[&str] = &[
+    r"(?i);\s*(drop|delete|truncate|alter|insert|update|create|grant)\b", // stacked statements
+    r"(?i)\bunion\s+(all\s+)?select\b",
+    r#"['";]\s*(--|/\*)"#,                     // close a literal or statement, comment out the rest
+    r"(?i)\bor\s+'?1'?\s*=\s*'?1",             // tautology
+    r"(?i)\b(sleep|benchmark|pg_sleep)\s*\(",  // timing attacks
+    r"(?i)\bxp_cmdshell\b",
+];
+
+/// Rejects and escapes dangerous patterns in tool inputs
+#[derive(Debug, Clone)]
+pub struct InputSanitizer {
+    profile: SanitizationProfile,
+    denylist: Vec<Regex>,
+}
+
+impl InputSanitizer {
+    /// Create a sanitizer with the default denylist of a profile
+    pub fn new(profile: SanitizationProfile) -> Self {
+        let patterns = match profile {
+            SanitizationProfile::Shell => SHELL_DENYLIST,
+            SanitizationProfile::Sql | SanitizationProfile::MySql => SQL_DENYLIST,
+            SanitizationProfile::Text => &[],
+        };
+        Self {
+            profile,
+            denylist: patterns.iter()
+                .map(|pattern| Regex::new(pattern).expect("built-in denylist pattern is valid"))
+                .collect(),
+        }
+    }
+
+    /// Sanitizer for shell-like tools
+    pub fn shell() -> Self {
+        Self::new(SanitizationProfile::Shell)
+    }
+
+    /// Sanitizer for SQL-like tools
+    pub fn sql() -> Self {
+        Self::new(SanitizationProfile::Sql)
+    }
+
+    /// Sanitizer for MySQL-like tools
+    pub fn mysql() -> Self {
+        Self::new(SanitizationProfile::MySql)
+    }
+
+    /// Also reject inputs matching `pattern`
+    pub fn with_denied_pattern(mut self, pattern: &str) -> ToolsResult<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| ToolsError::Configuration(format!("Invalid denylist pattern '{}': {}", pattern, e)))?;
+        self.denylist.push(regex);
+        Ok(self)
+    }
+
+    /// Reject an input matching the denylist, otherwise escape its text in place
+    ///
+    /// Every string in the parameters, however deeply nested, and stdin are
+    /// checked; file contents are passed through.
+    pub fn sanitize(&self, input: &mut ToolInput) -> ToolsResult<()> {
+        for (name, value) in input.parameters.iter_mut() {
+            self.sanitize_value(name, value)?;
+        }
+        if let Some(stdin) = &mut input.stdin {
+            *stdin = self.sanitize_text("stdin", stdin)?;
+        }
+        Ok(())
+    }
+
+    fn sanitize_value(&self, name: &str, value: &mut serde_json::Value) -> ToolsResult<()> {
+        match value {
+            serde_json::Value::String(text) => *text = self.sanitize_text(name, text)?,
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    self.sanitize_value(name, item)?;
+                }
+            }
+            serde_json::Value::Object(fields) => {
+                for item in fields.values_mut() {
+                    self.sanitize_value(name, item)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn sanitize_text(&self, name: &str, text: &str) -> ToolsResult<String> {
+        if let Some(pattern) = self.denylist.iter().find(|pattern| pattern.is_match(text)) {
+            return Err(ToolsError::Security(format!(
+                "Input '{}' matches denied pattern '{}'", name, pattern.as_str()
+            )));
+        }
+
+        let mut sanitized = String::with_capacity(text.len());
+        for c in text.chars() {
+            match self.profile {
+                SanitizationProfile::Shell if c == '\n' || c == '\r' => sanitized.push(' '),
+                SanitizationProfile::Shell if "\\;&|<>$`\"'(){}[]*?!#~".contains(c) => {
+                    sanitized.push('\\');
+                    sanitized.push(c);
+                }
+                SanitizationProfile::Sql | SanitizationProfile::MySql if c == '\'' => sanitized.push_str("''"),
+                SanitizationProfile::MySql if c == '\\' => sanitized.push_str("\\\\"),
+                _ if c.is_control() && c != '\n' && c != '\t' => {}
+                _ => sanitized.push(c),
+            }
+        }
+        Ok(sanitized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sql_profiles_escape_per_dialect_and_allow_benign_dashes() {
+        let standard = InputSanitizer::sql();
+        assert_eq!(standard.sanitize_text("q", r"O'Brien \ co").unwrap(), r"O''Brien \ co");
+        assert_eq!(InputSanitizer::mysql().sanitize_text("q", r"O'Brien \ co").unwrap(), r"O''Brien \\ co");
+
+        assert_eq!(standard.sanitize_text("q", "pages 10--12 -- see notes").unwrap(), "pages 10--12 -- see notes");
+        assert!(standard.sanitize_text("q", "admin' --").is_err());
+        assert!(standard.sanitize_text("q", "1; /* rest").is_err());
+    }
+}
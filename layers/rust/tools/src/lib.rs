@@ -7,6 +7,7 @@ pub mod types;
 pub mod tool_plugin;
 pub mod tool_registry;
 pub mod tool_executor;
+pub mod input_sanitizer;
 pub mod orchestration_engine;
 pub mod builtin_tools;
 
@@ -19,5 +20,6 @@ pub use types::*;
 pub use tool_plugin::*;
 pub use tool_registry::*;
 pub use tool_executor::*;
+pub use input_sanitizer::*;
 pub use orchestration_engine::*;
 pub use builtin_tools::*;
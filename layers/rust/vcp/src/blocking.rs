@@ -0,0 +1,108 @@
+//! Blocking facade for VCP
+//!
+//! Runs reasoning to completion on an internal runtime so that VCP can be used
+//! from synchronous code such as CLI tools and FFI bindings.
+
+use crate::{VcpResult, VcpError, RecursiveEngine, NodeExecutor, ThinkingChain, ThinkingContext, ChainExecutionResult};
+use std::sync::Arc;
+use tokio::runtime::{Builder, Handle, Runtime};
+use tokio_util::sync::CancellationToken;
+
+/// Synchronous wrapper around the recursive engine
+pub struct BlockingVcp {
+    engine: RecursiveEngine,
+    runtime: Runtime,
+}
+
+impl BlockingVcp {
+    /// Create a blocking facade with a default engine
+    pub fn new(node_executor: Arc<dyn NodeExecutor>) -> VcpResult<Self> {
+        Self::from_engine(RecursiveEngine::new(node_executor))
+    }
+
+    /// Create a blocking facade around a configured engine
+    pub fn from_engine(engine: RecursiveEngine) -> VcpResult<Self> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| VcpError::Configuration(format!("Failed to build VCP runtime: {}", e)))?;
+
+        Ok(Self { engine, runtime })
+    }
+
+    /// Get the wrapped engine
+    pub fn engine(&self) -> &RecursiveEngine {
+        &self.engine
+    }
+
+    /// Execute a thinking chain to completion
+    pub fn execute_chain(&self, chain: ThinkingChain, context: &ThinkingContext) -> VcpResult<ChainExecutionResult> {
+        // Blocking inside a runtime would stall its worker threads (or panic)
+        if Handle::try_current().is_ok() {
+            return Err(VcpError::Configuration(
+                "BlockingVcp cannot be called from within a Tokio runtime; use RecursiveEngine::execute_chain instead".to_string(),
+            ));
+        }
+
+        self.runtime.block_on(self.engine.execute_chain(chain, context, 0, &CancellationToken::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thinking_node::BasicNodeExecutor;
+    use std::collections::HashMap;
+
+    fn create_test_context() -> ThinkingContext {
+        ThinkingContext {
+            session_id: "test".to_string(),
+            user_id: "user".to_string(),
+            task_type: "reasoning".to_string(),
+            complexity_level: crate::ComplexityLevel::Simple,
+            time_constraint: Some(30),
+            resource_limits: crate::ResourceLimits {
+                max_depth: 5,
+                max_branches: 3,
+                max_iterations: 10,
+                time_budget_ms: 5000,
+                memory_budget_mb: 100,
+            },
+            domain_knowledge: HashMap::new(),
+            emotional_state: crate::EmotionalState {
+                confidence: 0.8,
+                curiosity: 0.7,
+                frustration: 0.1,
+                satisfaction: 0.9,
+            },
+            cognitive_load: 0.3,
+        }
+    }
+
+    #[test]
+    fn test_blocking_execute_chain() {
+        let vcp = BlockingVcp::new(Arc::new(BasicNodeExecutor)).unwrap();
+        let chain = ThinkingChain::new(
+            "Test Chain".to_string(),
+            "Test".to_string(),
+            "Test input".to_string(),
+        );
+        let chain_id = chain.id.clone();
+
+        let result = vcp.execute_chain(chain, &create_test_context()).unwrap();
+
+        assert_eq!(result.chain_id, chain_id);
+        assert!(result.execution_stats.total_nodes >= 1);
+    }
+
+    #[test]
+    fn test_blocking_rejects_nested_runtime() {
+        let vcp = BlockingVcp::new(Arc::new(BasicNodeExecutor)).unwrap();
+        let outer = Builder::new_current_thread().build().unwrap();
+        let chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+
+        let result = outer.block_on(async { vcp.execute_chain(chain, &create_test_context()) });
+
+        assert!(matches!(result, Err(VcpError::Configuration(_))));
+    }
+}
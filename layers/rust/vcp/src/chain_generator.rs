@@ -1,9 +1,26 @@
 //! Chain Generator for VCP
 
-use crate::{VcpResult, VcpError, ThinkingChain, ChainBuilder, ChainGenerationParams, ThinkingStrategy, ComplexityLevel, ReasoningGoal};
+use crate::{VcpResult, VcpError, ThinkingChain, ThinkingNode, NodeType, NodeContent, ChainBuilder, ChainGenerationParams, ThinkingStrategy, ComplexityLevel, ReasoningGoal, ReasoningQuality, SeededRng};
 use async_trait::async_trait;
-use std::collections::HashMap;
-use tracing::{debug, info};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sira_ai_backends::{AiProviderTrait, ChatMessage, ChatRequest, RequestPriority};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Built-in strategies, leanest first; on equal node counts the one listed later is preferred
+const STRATEGY_RICHNESS: [&str; 4] = ["linear", "iterative", "tree", "adaptive"];
+
+/// Outcome of automatic strategy selection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategySelection {
+    pub strategy: String,
+    pub preferred_strategy: String,
+    pub estimated_cost: f64,
+    pub estimated_latency_ms: u64,
+    pub reasoning: String,
+}
 
 /// Chain generation strategy
 #[async_trait]
@@ -18,6 +35,9 @@ pub trait ChainGenerationStrategy: Send + Sync {
 /// Dynamic chain generator
 pub struct DynamicChainGenerator {
     strategies: HashMap<String, Box<dyn ChainGenerationStrategy>>,
+    cost_per_node: f64,
+    latency_per_node_ms: u64,
+    rng: Option<SeededRng>,
 }
 
 impl DynamicChainGenerator {
@@ -43,7 +63,25 @@ impl DynamicChainGenerator {
             Box::new(AdaptiveStrategy) as Box<dyn ChainGenerationStrategy>,
         );
 
-        Self { strategies }
+        Self {
+            strategies,
+            cost_per_node: 1.0,
+            latency_per_node_ms: 2000,
+            rng: None,
+        }
+    }
+
+    /// Generate chains from a seeded RNG so that the same input yields identical chains
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Some(SeededRng::new(seed));
+        self
+    }
+
+    /// Set the estimated cost and latency of executing one node
+    pub fn with_node_estimates(mut self, cost_per_node: f64, latency_per_node_ms: u64) -> Self {
+        self.cost_per_node = cost_per_node;
+        self.latency_per_node_ms = latency_per_node_ms;
+        self
     }
 
     /// Add a custom strategy
@@ -59,23 +97,102 @@ impl DynamicChainGenerator {
             .ok_or_else(|| VcpError::ChainGeneration(format!("Strategy '{}' not found", strategy_name)))?;
 
         debug!("Generating chain using strategy: {}", strategy_name);
-        strategy.generate_chain(params).await
+        match &self.rng {
+            Some(rng) => rng.scope(strategy.generate_chain(params)).await,
+            None => strategy.generate_chain(params).await,
+        }
     }
 
     /// Auto-select and generate chain based on context
     pub async fn auto_generate_chain(&self, params: &ChainGenerationParams) -> VcpResult<ThinkingChain> {
-        let strategy_name = self.select_strategy(params);
-        self.generate_chain(params, &strategy_name).await
+        let (selection, mut chain) = self.select_and_generate(params).await?;
+
+        chain.metadata.insert(
+            "strategy_selection".to_string(),
+            serde_json::to_value(&selection).map_err(|e| VcpError::ChainGeneration(e.to_string()))?,
+        );
+        Ok(chain)
     }
 
-    /// Select appropriate strategy based on context
-    fn select_strategy(&self, params: &ChainGenerationParams) -> String {
-        match params.context.complexity_level {
-            ComplexityLevel::Simple => "linear".to_string(),
-            ComplexityLevel::Moderate => "tree".to_string(),
-            ComplexityLevel::Complex => "iterative".to_string(),
-            ComplexityLevel::UltraComplex => "adaptive".to_string(),
+    /// Select a strategy from task complexity, stepping down to leaner ones to fit time and cost budgets
+    ///
+    /// Estimates come from the node count of each candidate's generated
+    /// chain, so nodes added for heuristics and domain experts are included.
+    pub async fn select_strategy(&self, params: &ChainGenerationParams) -> VcpResult<StrategySelection> {
+        Ok(self.select_and_generate(params).await?.0)
+    }
+
+    async fn select_and_generate(&self, params: &ChainGenerationParams) -> VcpResult<(StrategySelection, ThinkingChain)> {
+        let preferred = match params.context.complexity_level {
+            ComplexityLevel::Simple => "linear",
+            ComplexityLevel::Moderate => "tree",
+            ComplexityLevel::Complex => "iterative",
+            ComplexityLevel::UltraComplex => "adaptive",
+        };
+
+        let time_budget_ms = params.context.time_constraint.map(|seconds| seconds * 1000);
+        let cost_budget = params.cost_budget;
+        let budgeted = time_budget_ms.is_some() || cost_budget.is_some();
+        let fits = |nodes: u32| {
+            time_budget_ms.is_none_or(|budget| self.estimated_latency_ms(nodes) <= budget)
+                && cost_budget.is_none_or(|budget| self.estimated_cost(nodes) <= budget)
+        };
+
+        // Candidates no richer than the preferred strategy, richest first
+        let preferred_rank = STRATEGY_RICHNESS.iter().position(|name| *name == preferred).unwrap_or(0);
+        let mut cheapest = None;
+        let mut selected = None;
+        for name in STRATEGY_RICHNESS[..=preferred_rank].iter().rev() {
+            let chain = self.generate_chain(params, name).await?;
+            let nodes = chain.nodes.len() as u32;
+            if fits(nodes) {
+                selected = Some((*name, nodes, chain));
+                break;
+            }
+            cheapest = Some((*name, nodes, chain));
         }
+
+        let complexity = params.context.complexity_level;
+        let (strategy, nodes, chain, reasoning) = match selected {
+            Some((name, nodes, chain)) if name == preferred && budgeted => {
+                (name, nodes, chain, format!("{:?} complexity prefers '{}', which fits the budget", complexity, name))
+            }
+            Some((name, nodes, chain)) if name == preferred => {
+                (name, nodes, chain, format!("{:?} complexity prefers '{}'", complexity, name))
+            }
+            Some((name, nodes, chain)) => (
+                name,
+                nodes,
+                chain,
+                format!(
+                    "{:?} complexity prefers '{}', but its estimate exceeds the budget; '{}' is the richest strategy that fits",
+                    complexity, preferred, name
+                ),
+            ),
+            None => {
+                let (name, nodes, chain) = cheapest.expect("at least one candidate strategy");
+                (name, nodes, chain, format!("No strategy fits the budget; falling back to the cheapest, '{}'", name))
+            }
+        };
+
+        debug!("Strategy selection: {}", reasoning);
+
+        let selection = StrategySelection {
+            strategy: strategy.to_string(),
+            preferred_strategy: preferred.to_string(),
+            estimated_cost: self.estimated_cost(nodes),
+            estimated_latency_ms: self.estimated_latency_ms(nodes),
+            reasoning,
+        };
+        Ok((selection, chain))
+    }
+
+    fn estimated_cost(&self, nodes: u32) -> f64 {
+        nodes as f64 * self.cost_per_node
+    }
+
+    fn estimated_latency_ms(&self, nodes: u32) -> u64 {
+        nodes as u64 * self.latency_per_node_ms
     }
 
     /// Get available strategies
@@ -105,7 +222,7 @@ impl ChainGenerationStrategy for LinearStrategy {
         .with_max_depth(params.strategy.recursion_depth);
 
         // Root analysis node
-        let root_id = format!("root_{}", uuid::Uuid::new_v4().simple());
+        let root_id = builder.root_id().to_string();
         builder = builder.add_analysis(
             "Analyze the problem".to_string(),
             params.goal.description.clone(),
@@ -133,7 +250,8 @@ impl ChainGenerationStrategy for LinearStrategy {
             ],
         )?;
 
-        let chain = builder.build()?;
+        let mut chain = builder.build()?;
+        HeuristicLibrary.apply(&mut chain, params)?;
         info!("Generated linear chain with {} nodes", chain.nodes.len());
 
         Ok(chain)
@@ -158,7 +276,7 @@ impl ChainGenerationStrategy for TreeStrategy {
         .with_quality_threshold(params.strategy.quality_threshold)
         .with_max_depth(params.strategy.recursion_depth);
 
-        let root_id = format!("root_{}", uuid::Uuid::new_v4().simple());
+        let root_id = builder.root_id().to_string();
 
         // Initial analysis
         builder = builder.add_analysis(
@@ -168,26 +286,26 @@ impl ChainGenerationStrategy for TreeStrategy {
         )?;
 
         // Multiple analysis branches
-        let branch1_id = format!("branch1_{}", uuid::Uuid::new_v4().simple());
         builder = builder.add_analysis(
             "Analyze technical aspects".to_string(),
             "Technical feasibility and requirements".to_string(),
             root_id.clone(),
         )?;
+        let branch1_id = builder.last_node_id().to_string();
 
-        let branch2_id = format!("branch2_{}", uuid::Uuid::new_v4().simple());
         builder = builder.add_analysis(
             "Analyze business aspects".to_string(),
             "Business impact and benefits".to_string(),
             root_id.clone(),
         )?;
+        let branch2_id = builder.last_node_id().to_string();
 
-        let branch3_id = format!("branch3_{}", uuid::Uuid::new_v4().simple());
         builder = builder.add_analysis(
             "Analyze risks".to_string(),
             "Potential risks and mitigation".to_string(),
             root_id.clone(),
         )?;
+        let branch3_id = builder.last_node_id().to_string();
 
         // Synthesis node combining all branches
         builder = builder.add_synthesis(
@@ -209,7 +327,8 @@ impl ChainGenerationStrategy for TreeStrategy {
             ],
         )?;
 
-        let chain = builder.build()?;
+        let mut chain = builder.build()?;
+        HeuristicLibrary.apply(&mut chain, params)?;
         info!("Generated tree chain with {} nodes", chain.nodes.len());
 
         Ok(chain)
@@ -234,7 +353,7 @@ impl ChainGenerationStrategy for IterativeStrategy {
         .with_quality_threshold(params.strategy.quality_threshold)
         .with_max_depth(params.strategy.recursion_depth);
 
-        let root_id = format!("root_{}", uuid::Uuid::new_v4().simple());
+        let root_id = builder.root_id().to_string();
 
         // Initial hypothesis
         builder = builder.add_analysis(
@@ -244,12 +363,12 @@ impl ChainGenerationStrategy for IterativeStrategy {
         )?;
 
         // First iteration
-        let iter1_id = format!("iter1_{}", uuid::Uuid::new_v4().simple());
         builder = builder.add_analysis(
             "Gather evidence and test hypothesis".to_string(),
             "First iteration of hypothesis testing".to_string(),
             root_id.clone(),
         )?;
+        let iter1_id = builder.last_node_id().to_string();
 
         // Evaluation and refinement
         builder = builder.add_analysis(
@@ -272,7 +391,8 @@ impl ChainGenerationStrategy for IterativeStrategy {
             root_id.clone(),
         )?;
 
-        let chain = builder.build()?;
+        let mut chain = builder.build()?;
+        HeuristicLibrary.apply(&mut chain, params)?;
         info!("Generated iterative chain with {} nodes", chain.nodes.len());
 
         Ok(chain)
@@ -323,6 +443,261 @@ impl ChainGenerationStrategy for AdaptiveStrategy {
     }
 }
 
+/// Instructions sent to the LLM describing the plan format
+const PLAN_SYSTEM_PROMPT: &str = "You plan step-by-step reasoning. Reply with JSON only, in the form \
+{\"steps\": [{\"id\": \"s1\", \"node_type\": \"Analysis\", \"description\": \"...\", \"depends_on\": []}]}. \
+node_type is one of Input, Analysis, Synthesis, Evaluation, Generation, Critique, MetaAnalysis, \
+Decision, Execution, Reflection; depends_on lists the ids of steps that must come first.";
+
+/// Reasoning plan proposed by an LLM
+#[derive(Debug, Clone, Deserialize)]
+struct ReasoningPlan {
+    steps: Vec<PlanStep>,
+}
+
+/// One step of a reasoning plan
+#[derive(Debug, Clone, Deserialize)]
+struct PlanStep {
+    id: String,
+    node_type: NodeType,
+    description: String,
+    #[serde(default)]
+    depends_on: Vec<String>,
+}
+
+/// LLM strategy - asks an AI backend to plan the reasoning for the goal
+///
+/// The model replies with a JSON plan of typed steps and their dependencies,
+/// which is turned into a validated chain. Unreachable backends and malformed
+/// or inconsistent plans fall back to a built-in strategy.
+pub struct LlmChainGenerator {
+    provider: Arc<dyn AiProviderTrait>,
+    model: String,
+    fallback: Box<dyn ChainGenerationStrategy>,
+}
+
+impl LlmChainGenerator {
+    /// Create a generator planning with `model` on `provider`, falling back to the linear strategy
+    pub fn new<S: Into<String>>(provider: Arc<dyn AiProviderTrait>, model: S) -> Self {
+        Self {
+            provider,
+            model: model.into(),
+            fallback: Box::new(LinearStrategy),
+        }
+    }
+
+    /// Set the strategy used when the LLM cannot produce a usable plan
+    pub fn with_fallback(mut self, fallback: Box<dyn ChainGenerationStrategy>) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Ask the LLM for a plan and build a chain from it
+    async fn generate_planned_chain(&self, params: &ChainGenerationParams) -> VcpResult<ThinkingChain> {
+        let request = ChatRequest {
+            messages: vec![
+                ChatMessage::system(PLAN_SYSTEM_PROMPT),
+                ChatMessage::user(format!("Goal: {}", params.goal.description)),
+            ],
+            model: self.model.clone(),
+            temperature: Some(0.2),
+            top_p: None,
+            max_tokens: None,
+            stream: Some(false),
+            functions: None,
+            function_call: None,
+            tools: None,
+            stop: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            logit_bias: None,
+            user: None,
+            response_format: None,
+            priority: RequestPriority::Interactive,
+        };
+
+        let response = self.provider.chat_completion(&request).await
+            .map_err(|e| VcpError::ChainGeneration(format!("Planning request failed: {}", e)))?;
+        let reply = response.choices.first()
+            .map(|choice| choice.message.content.text())
+            .ok_or_else(|| VcpError::ChainGeneration("Planning response has no choices".to_string()))?;
+
+        let plan = Self::parse_plan(&reply)?;
+        Self::build_chain(&plan, params)
+    }
+
+    /// Parse the JSON plan out of a reply, ignoring any surrounding prose or code fences
+    fn parse_plan(reply: &str) -> VcpResult<ReasoningPlan> {
+        let json = match (reply.find('{'), reply.rfind('}')) {
+            (Some(start), Some(end)) if start < end => &reply[start..=end],
+            _ => return Err(VcpError::ChainGeneration("Planning reply contains no JSON object".to_string())),
+        };
+
+        let plan: ReasoningPlan = serde_json::from_str(json)
+            .map_err(|e| VcpError::ChainGeneration(format!("Malformed plan: {}", e)))?;
+        if plan.steps.is_empty() {
+            return Err(VcpError::ChainGeneration("Plan has no steps".to_string()));
+        }
+        Ok(plan)
+    }
+
+    /// Build a chain from a plan, adding steps after the steps they depend on
+    ///
+    /// Each step hangs off its first dependency, or the root when it has none.
+    fn build_chain(plan: &ReasoningPlan, params: &ChainGenerationParams) -> VcpResult<ThinkingChain> {
+        let mut chain = ThinkingChain::new(
+            format!("LLM Chain for {}", params.goal.description),
+            "LLM-planned reasoning".to_string(),
+            params.goal.description.clone(),
+        );
+        chain.quality_threshold = params.strategy.quality_threshold;
+        chain.max_depth = params.strategy.recursion_depth;
+
+        let mut step_ids = HashSet::new();
+        for step in &plan.steps {
+            if !step_ids.insert(step.id.as_str()) {
+                return Err(VcpError::ChainGeneration(format!("Duplicate plan step '{}'", step.id)));
+            }
+        }
+        for step in &plan.steps {
+            if let Some(unknown) = step.depends_on.iter().find(|dep| !step_ids.contains(dep.as_str())) {
+                return Err(VcpError::ChainGeneration(format!("Step '{}' depends on unknown step '{}'", step.id, unknown)));
+            }
+        }
+
+        // Plan step ID -> node ID
+        let mut node_ids: HashMap<&str, String> = HashMap::new();
+        let mut pending: Vec<&PlanStep> = plan.steps.iter().collect();
+        while !pending.is_empty() {
+            let ready = pending.iter()
+                .position(|step| step.depends_on.iter().all(|dep| node_ids.contains_key(dep.as_str())))
+                .ok_or_else(|| VcpError::ChainGeneration("Plan dependencies form a cycle".to_string()))?;
+            let step = pending.remove(ready);
+
+            let prerequisites: Vec<String> = step.depends_on.iter()
+                .map(|dep| node_ids[dep.as_str()].clone())
+                .collect();
+            let parent_id = prerequisites.first().cloned().unwrap_or_else(|| chain.root_node_id.clone());
+
+            let mut metadata = HashMap::new();
+            metadata.insert("plan_step".to_string(), serde_json::Value::String(step.id.clone()));
+
+            let node = ThinkingNode {
+                id: crate::generate_id("plan"),
+                node_type: step.node_type,
+                content: NodeContent::Text(step.description.clone()),
+                confidence: 0.5,
+                quality: ReasoningQuality {
+                    logical_consistency: 0.7,
+                    completeness: 0.3,
+                    relevance: 0.8,
+                    novelty: 0.5,
+                    efficiency: 0.7,
+                    adaptability: 0.7,
+                },
+                metadata,
+                created_at: Utc::now(),
+                executed_at: None,
+                execution_time_ms: None,
+                parent_id: Some(parent_id),
+                children_ids: Vec::new(),
+                dependencies: prerequisites.clone(),
+                prerequisites,
+            };
+            node_ids.insert(step.id.as_str(), node.id.clone());
+            chain.add_node(node)?;
+        }
+
+        chain.validate()?;
+        Ok(chain)
+    }
+}
+
+#[async_trait]
+impl ChainGenerationStrategy for LlmChainGenerator {
+    async fn generate_chain(&self, params: &ChainGenerationParams) -> VcpResult<ThinkingChain> {
+        // The fallback strategy applies the heuristics itself
+        match self.generate_planned_chain(params).await.and_then(|mut chain| {
+            HeuristicLibrary.apply(&mut chain, params)?;
+            Ok(chain)
+        }) {
+            Ok(chain) => {
+                info!("Generated LLM-planned chain with {} nodes", chain.nodes.len());
+                Ok(chain)
+            }
+            Err(e) => {
+                warn!("LLM planning failed, falling back to '{}': {}", self.fallback.name(), e);
+                let mut chain = self.fallback.generate_chain(params).await?;
+                chain.metadata.insert("llm_fallback_reason".to_string(), serde_json::Value::String(e.to_string()));
+                Ok(chain)
+            }
+        }
+    }
+
+    fn name(&self) -> &str {
+        "llm"
+    }
+}
+
+/// Adds the nodes that the heuristics and domain experts of a request call for
+///
+/// Each domain expert gets an analysis node from their perspective. Known
+/// heuristics add nodes of their own: `first-principles` an analysis of the
+/// fundamentals, `self-critique` and `pre-mortem` critique nodes reviewing the
+/// chain's conclusions. Heuristic names are matched ignoring case, spaces and
+/// underscores; unknown ones are skipped.
+pub struct HeuristicLibrary;
+
+impl HeuristicLibrary {
+    /// Add nodes for the heuristics and domain experts in `params` to a generated chain
+    pub fn apply(&self, chain: &mut ThinkingChain, params: &ChainGenerationParams) -> VcpResult<()> {
+        let root_id = chain.root_node_id.clone();
+        let heuristics: Vec<String> = params.available_heuristics.iter()
+            .map(|heuristic| heuristic.trim().to_lowercase().replace([' ', '_'], "-"))
+            .collect();
+
+        for expert in &params.domain_experts {
+            let mut node = crate::NodeFactory::create_analysis_node(
+                format!("How would a {} expert assess this?", expert),
+                params.goal.description.clone(),
+                root_id.clone(),
+            );
+            node.metadata.insert("domain_expert".to_string(), serde_json::json!(expert));
+            chain.add_node(node)?;
+        }
+
+        if heuristics.iter().any(|heuristic| heuristic == "first-principles") {
+            let mut node = crate::NodeFactory::create_analysis_node(
+                "What fundamental facts does this problem rest on?".to_string(),
+                params.goal.description.clone(),
+                root_id,
+            );
+            node.metadata.insert("heuristic".to_string(), serde_json::json!("first-principles"));
+            chain.add_node(node)?;
+        }
+
+        // Critiques review the chain's conclusions, so they run after everything else
+        let mut conclusions: Vec<String> = chain.get_leaf_nodes().into_iter().map(|node| node.id.clone()).collect();
+        conclusions.sort();
+        for heuristic in &heuristics {
+            let focus = match heuristic.as_str() {
+                "self-critique" => "Find gaps, errors and unsupported claims in the reasoning so far",
+                "pre-mortem" => "Assume the conclusion turned out wrong: what most likely caused it?",
+                "first-principles" => continue,
+                _ => {
+                    debug!("Skipping unknown heuristic '{}'", heuristic);
+                    continue;
+                }
+            };
+            let mut node = crate::NodeFactory::create_critique_node(focus.to_string(), conclusions.clone());
+            node.metadata.insert("heuristic".to_string(), serde_json::json!(heuristic));
+            chain.add_node(node)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Heuristic-based chain optimizer
 pub struct ChainOptimizer;
 
@@ -331,6 +706,9 @@ impl ChainOptimizer {
     pub async fn optimize_chain(&self, chain: &mut ThinkingChain, params: &ChainGenerationParams) -> VcpResult<()> {
         debug!("Optimizing chain: {}", chain.id);
 
+        // Remove nodes execution can never get to
+        self.prune_unreachable_nodes(chain)?;
+
         // Remove redundant nodes
         self.remove_redundant_nodes(chain)?;
 
@@ -369,6 +747,25 @@ impl ChainOptimizer {
         Ok(())
     }
 
+    /// Remove nodes not reachable from the root, and their entries in child lists
+    ///
+    /// No reachable node has an unreachable prerequisite, so the surviving
+    /// nodes' prerequisites and dependencies are left as they are.
+    fn prune_unreachable_nodes(&self, chain: &mut ThinkingChain) -> VcpResult<()> {
+        let unreachable: HashSet<String> = chain.unreachable_nodes().into_iter().collect();
+        if unreachable.is_empty() {
+            return Ok(());
+        }
+
+        chain.nodes.retain(|node_id, _| !unreachable.contains(node_id));
+        for node in chain.nodes.values_mut() {
+            node.children_ids.retain(|id| !unreachable.contains(id));
+        }
+
+        debug!("Pruned {} unreachable nodes from chain {}", unreachable.len(), chain.id);
+        Ok(())
+    }
+
     /// Optimize execution order for better parallelism
     fn optimize_execution_order(&self, chain: &mut ThinkingChain) -> VcpResult<()> {
         // For now, this is a placeholder
@@ -402,6 +799,7 @@ impl ChainOptimizer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use sira_ai_backends::MockProvider;
 
     fn create_test_params() -> ChainGenerationParams {
         ChainGenerationParams {
@@ -444,6 +842,7 @@ mod tests {
             },
             available_heuristics: vec![],
             domain_experts: vec![],
+            cost_budget: None,
         }
     }
 
@@ -523,4 +922,177 @@ mod tests {
         // Low confidence node should be removed
         assert!(chain.nodes.len() < original_count);
     }
+
+    #[tokio::test]
+    async fn test_optimizer_prunes_unreachable_nodes() {
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        let reachable = crate::NodeFactory::create_analysis_node(
+            "What limits throughput?".to_string(),
+            "Service".to_string(),
+            chain.root_node_id.clone(),
+        );
+        let reachable_id = reachable.id.clone();
+        chain.add_node(reachable).unwrap();
+
+        // An orphan with a child of its own, neither reachable from the root
+        let mut orphan = crate::NodeFactory::create_analysis_node("Stray".to_string(), "None".to_string(), String::new());
+        orphan.id = "orphan".to_string();
+        orphan.parent_id = None;
+        chain.add_node(orphan).unwrap();
+        let mut orphan_child = crate::NodeFactory::create_analysis_node("Stray child".to_string(), "None".to_string(), "orphan".to_string());
+        orphan_child.id = "orphan_child".to_string();
+        chain.add_node(orphan_child).unwrap();
+
+        // Reached through its prerequisite even without a parent
+        let mut follower = crate::NodeFactory::create_analysis_node("Then what?".to_string(), "Service".to_string(), String::new());
+        follower.parent_id = None;
+        follower.prerequisites = vec![reachable_id.clone()];
+        follower.dependencies = vec!["orphan".to_string()];
+        let follower_id = follower.id.clone();
+        chain.add_node(follower).unwrap();
+
+        // Execution waits for every prerequisite, so one unreachable prerequisite is enough
+        let mut blocked = crate::NodeFactory::create_analysis_node("And then?".to_string(), "Service".to_string(), chain.root_node_id.clone());
+        blocked.id = "blocked".to_string();
+        blocked.prerequisites = vec![reachable_id.clone(), "orphan".to_string()];
+        chain.add_node(blocked).unwrap();
+
+        assert_eq!(chain.unreachable_nodes(), vec!["blocked".to_string(), "orphan".to_string(), "orphan_child".to_string()]);
+
+        ChainOptimizer.optimize_chain(&mut chain, &create_test_params()).await.unwrap();
+        assert!(chain.unreachable_nodes().is_empty());
+        assert!(["orphan", "orphan_child", "blocked"].iter().all(|id| !chain.nodes.contains_key(*id)));
+        assert!(!chain.nodes[&chain.root_node_id].children_ids.contains(&"blocked".to_string()));
+
+        // Surviving nodes are not rewritten
+        assert_eq!(chain.nodes[&follower_id].prerequisites, vec![reachable_id]);
+        assert_eq!(chain.nodes[&follower_id].dependencies, vec!["orphan".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_strategy_selection_respects_budgets() {
+        let generator = DynamicChainGenerator::new();
+        let mut params = create_test_params();
+        params.context.complexity_level = ComplexityLevel::UltraComplex;
+
+        let unconstrained = generator.select_strategy(&params).await.unwrap();
+        assert_eq!(unconstrained.strategy, "adaptive");
+        assert!(!unconstrained.reasoning.contains("budget"));
+
+        // 10s at the default 2s per node only leaves room for the linear chain
+        params.context.time_constraint = Some(10);
+        let time_constrained = generator.select_strategy(&params).await.unwrap();
+        assert_eq!(time_constrained.strategy, "linear");
+        assert_eq!(time_constrained.preferred_strategy, "adaptive");
+        assert!(time_constrained.estimated_cost < unconstrained.estimated_cost);
+        assert!(time_constrained.reasoning.contains("exceeds the budget"));
+
+        // A cost budget alone also steps down, to the richest strategy that fits
+        params.context.time_constraint = None;
+        params.cost_budget = Some(6.0);
+        assert_eq!(generator.select_strategy(&params).await.unwrap().strategy, "iterative");
+
+        // Nodes added for experts count against the budget too
+        params.domain_experts = vec!["security".to_string(), "finance".to_string()];
+        assert_eq!(generator.select_strategy(&params).await.unwrap().strategy, "linear");
+
+        // Budgets never push a simple task onto a richer strategy
+        params.context.complexity_level = ComplexityLevel::Simple;
+        params.cost_budget = Some(1.0);
+        assert_eq!(generator.select_strategy(&params).await.unwrap().strategy, "linear");
+    }
+
+    #[tokio::test]
+    async fn test_moderate_task_without_budget_gets_its_preferred_strategy() {
+        let generator = DynamicChainGenerator::new();
+        let mut params = create_test_params();
+        params.context.complexity_level = ComplexityLevel::Moderate;
+
+        let selection = generator.select_strategy(&params).await.unwrap();
+        assert_eq!(selection.strategy, "tree");
+        assert_eq!(selection.reasoning, "Moderate complexity prefers 'tree'");
+    }
+
+    struct RootOnlyStrategy;
+
+    #[async_trait]
+    impl ChainGenerationStrategy for RootOnlyStrategy {
+        async fn generate_chain(&self, params: &ChainGenerationParams) -> VcpResult<ThinkingChain> {
+            Ok(ThinkingChain::new("Root Only".to_string(), String::new(), params.goal.description.clone()))
+        }
+
+        fn name(&self) -> &str {
+            "root_only"
+        }
+    }
+
+    fn planning_generator(reply: &str) -> LlmChainGenerator {
+        LlmChainGenerator::new(Arc::new(MockProvider::new("planner", &["planner-1"]).with_reply(reply)), "planner-1")
+            .with_fallback(Box::new(RootOnlyStrategy))
+    }
+
+    #[tokio::test]
+    async fn test_llm_generator_builds_chain_from_plan() {
+        let params = create_test_params();
+        let reply = r#"Here is the plan:
+```json
+{"steps": [
+    {"id": "understand", "node_type": "Analysis", "description": "Understand the goal"},
+    {"id": "options", "node_type": "Generation", "description": "Propose options", "depends_on": ["understand"]},
+    {"id": "risks", "node_type": "Critique", "description": "Find risks", "depends_on": ["understand"]},
+    {"id": "choose", "node_type": "Decision", "description": "Pick an option", "depends_on": ["options", "risks"]}
+]}
+```"#;
+
+        let chain = planning_generator(reply).generate_chain(&params).await.unwrap();
+        assert_eq!(chain.nodes.len(), 5);
+        assert!(chain.metadata.get("llm_fallback_reason").is_none());
+
+        let by_step = |step: &str| chain.nodes.values()
+            .find(|node| node.metadata.get("plan_step") == Some(&serde_json::json!(step)))
+            .unwrap();
+        let understand = by_step("understand");
+        assert_eq!(understand.parent_id.as_deref(), Some(chain.root_node_id.as_str()));
+        assert_eq!(by_step("risks").node_type, NodeType::Critique);
+
+        let choose = by_step("choose");
+        assert_eq!(choose.node_type, NodeType::Decision);
+        assert_eq!(choose.prerequisites, vec![by_step("options").id.clone(), by_step("risks").id.clone()]);
+        assert_eq!(choose.parent_id.as_ref(), Some(&by_step("options").id));
+
+        // A plan that references a missing step falls back to the configured strategy
+        let broken = r#"{"steps": [{"id": "a", "node_type": "Analysis", "description": "x", "depends_on": ["b"]}]}"#;
+        let fallback = planning_generator(broken).generate_chain(&params).await.unwrap();
+        assert_eq!(fallback.name, "Root Only");
+        assert!(fallback.metadata.contains_key("llm_fallback_reason"));
+    }
+
+    #[tokio::test]
+    async fn test_heuristics_and_experts_add_nodes() {
+        let mut params = create_test_params();
+        params.available_heuristics = vec!["Self Critique".to_string(), "astrology".to_string()];
+        params.domain_experts = vec!["security".to_string()];
+        let reply = r#"{"steps": [
+            {"id": "understand", "node_type": "Analysis", "description": "Understand the goal"},
+            {"id": "choose", "node_type": "Decision", "description": "Pick an option", "depends_on": ["understand"]}
+        ]}"#;
+
+        let chain = planning_generator(reply).generate_chain(&params).await.unwrap();
+        assert_eq!(chain.nodes.len(), 5);
+
+        let expert = chain.nodes.values()
+            .find(|node| node.metadata.get("domain_expert") == Some(&serde_json::json!("security")))
+            .unwrap();
+        assert_eq!(expert.parent_id.as_ref(), Some(&chain.root_node_id));
+
+        // The critique reviews every conclusion, including the expert's
+        let critique = chain.nodes.values().find(|node| node.node_type == NodeType::Critique).unwrap();
+        assert_eq!(critique.metadata.get("heuristic"), Some(&serde_json::json!("self-critique")));
+        let choose = chain.nodes.values()
+            .find(|node| node.metadata.get("plan_step") == Some(&serde_json::json!("choose")))
+            .unwrap();
+        assert!(critique.prerequisites.contains(&choose.id));
+        assert!(critique.prerequisites.contains(&expert.id));
+        assert_eq!(critique.prerequisites.len(), 2);
+    }
 }
@@ -26,6 +26,12 @@ pub enum VcpError {
     #[error("Context error: {0}")]
     Context(String),
 
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Checkpoint error: {0}")]
+    Checkpoint(String),
+
     #[error("Configuration error: {0}")]
     Configuration(String),
 
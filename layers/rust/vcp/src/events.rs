@@ -0,0 +1,45 @@
+//! Metacognitive events published to the kernel message bus
+//!
+//! Engines and controllers given a [`MessageBus`] publish their assessments,
+//! adaptations and interventions so external observers can react to them.
+//! Without a bus nothing is published and VCP runs standalone.
+
+use sira_kernel::{Message, MessageBus};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Topic of metacognitive assessments made during a chain run
+pub const ASSESSMENT_TOPIC: &str = "vcp.assessment";
+/// Topic of adaptations applied to a running chain
+pub const ADAPTATION_TOPIC: &str = "vcp.adaptation";
+/// Topic of interventions triggered by the metacognitive controller
+pub const INTERVENTION_TOPIC: &str = "vcp.intervention";
+
+/// Sender name used on published events
+const EVENT_SENDER: &str = "vcp";
+
+/// Publish an event if a bus is attached
+///
+/// Events are best-effort: a failed publish is logged and never interrupts
+/// reasoning.
+pub async fn publish_event(bus: Option<&Arc<MessageBus>>, topic: &str, payload: serde_json::Value) {
+    let Some(bus) = bus else {
+        return;
+    };
+
+    let message = Message {
+        id: String::new(),
+        topic: topic.to_string(),
+        payload,
+        timestamp: chrono::Utc::now(),
+        headers: Default::default(),
+        priority: Default::default(),
+        ttl: 0,
+        sender: Some(EVENT_SENDER.to_string()),
+        recipients: Vec::new(),
+    };
+
+    if let Err(e) = bus.publish(message).await {
+        debug!("Failed to publish {} event: {}", topic, e);
+    }
+}
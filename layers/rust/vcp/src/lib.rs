@@ -10,6 +10,13 @@ pub mod chain_generator;
 pub mod recursive_engine;
 pub mod metacognition;
 pub mod adaptive_controller;
+pub mod blocking;
+pub mod rng;
+pub mod events;
+pub mod observer;
+pub mod report;
+pub mod quality;
+pub mod refinement;
 
 /// Result type alias for VCP operations
 pub type VcpResult<T> = Result<T, VcpError>;
@@ -23,3 +30,10 @@ pub use chain_generator::*;
 pub use recursive_engine::*;
 pub use metacognition::*;
 pub use adaptive_controller::*;
+pub use blocking::*;
+pub use rng::*;
+pub use events::*;
+pub use observer::*;
+pub use report::*;
+pub use quality::*;
+pub use refinement::*;
@@ -1,17 +1,52 @@
 //! Meta-cognition for VCP
 
-use crate::{VcpResult, VcpError, MetacognitiveAssessment, ThinkingContext, ChainExecutionResult, QualityTrend, RecommendedAction, VcpExecutionStats};
+use crate::{VcpResult, VcpError, MetacognitiveAssessment, ThinkingContext, ChainExecutionResult, QualityTrend, RecommendedAction, VcpExecutionStats, NodeContent, INTERVENTION_TOPIC, publish_event};
 use async_trait::async_trait;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use sira_kernel::MessageBus;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 use tracing::{debug, info, warn};
 
+/// Abort reason of a chain stopped because its outputs kept repeating
+pub const LOOP_DETECTED: &str = "loop_detected";
+
+/// Outcome of checking recent node outputs for a reasoning loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopVerdict {
+    /// Recent outputs are varied enough
+    Progressing,
+    /// Recent outputs repeat; another strategy may break the loop
+    ChangeStrategy,
+    /// Outputs kept repeating after a strategy change
+    Abort,
+}
+
+/// Fingerprint of a node output, ignoring case and whitespace
+fn output_fingerprint(output: &NodeContent) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let text = serde_json::to_string(output).unwrap_or_default().to_lowercase();
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for word in text.split_whitespace() {
+        word.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
 /// Meta-cognitive monitor
+#[derive(Clone, Serialize, Deserialize)]
 pub struct MetacognitiveMonitor {
     confidence_history: Vec<f64>,
     quality_history: Vec<f64>,
     time_history: Vec<u64>,
     adaptation_history: Vec<String>,
     monitoring_enabled: bool,
+    /// Fingerprints of the latest node outputs, oldest first
+    recent_outputs: VecDeque<u64>,
+    loop_window: usize,
+    loop_threshold: f64,
+    loop_detections: u32,
 }
 
 impl MetacognitiveMonitor {
@@ -23,7 +58,54 @@ impl MetacognitiveMonitor {
             time_history: Vec::new(),
             adaptation_history: Vec::new(),
             monitoring_enabled: true,
+            recent_outputs: VecDeque::new(),
+            loop_window: 6,
+            loop_threshold: 0.5,
+            loop_detections: 0,
+        }
+    }
+
+    /// Record a node output and check the latest outputs for a loop
+    ///
+    /// Once the window is full and the share of repeated outputs in it
+    /// reaches the threshold, the first detection recommends a strategy
+    /// change and starts a fresh window; the next one recommends aborting.
+    pub fn observe_output(&mut self, output: &NodeContent) -> LoopVerdict {
+        if !self.monitoring_enabled {
+            return LoopVerdict::Progressing;
+        }
+
+        self.recent_outputs.push_back(output_fingerprint(output));
+        if self.recent_outputs.len() > self.loop_window {
+            self.recent_outputs.pop_front();
+        }
+        if self.recent_outputs.len() < self.loop_window || self.repetition_ratio() < self.loop_threshold {
+            return LoopVerdict::Progressing;
+        }
+
+        self.recent_outputs.clear();
+        self.loop_detections += 1;
+        warn!("Reasoning loop detected ({} so far)", self.loop_detections);
+        if self.loop_detections == 1 {
+            LoopVerdict::ChangeStrategy
+        } else {
+            LoopVerdict::Abort
+        }
+    }
+
+    /// Share of the latest outputs repeating another one in the window
+    pub fn repetition_ratio(&self) -> f64 {
+        if self.recent_outputs.is_empty() {
+            return 0.0;
         }
+        let distinct: HashSet<_> = self.recent_outputs.iter().collect();
+        1.0 - distinct.len() as f64 / self.recent_outputs.len() as f64
+    }
+
+    /// Set how many outputs loop detection looks back over and the share of repeats that counts as a loop
+    pub fn set_loop_detection(&mut self, window: usize, threshold: f64) {
+        self.loop_window = window.max(2);
+        self.loop_threshold = threshold;
     }
 
     /// Monitor reasoning progress
@@ -155,6 +237,8 @@ impl MetacognitiveMonitor {
         self.quality_history.clear();
         self.time_history.clear();
         self.adaptation_history.clear();
+        self.recent_outputs.clear();
+        self.loop_detections = 0;
     }
 
     /// Enable/disable monitoring
@@ -163,31 +247,82 @@ impl MetacognitiveMonitor {
     }
 }
 
+/// Observed effect of one intervention trigger within one context
+#[derive(Debug, Clone, Default)]
+pub struct InterventionEffectiveness {
+    /// Number of outcomes attributed to this intervention
+    pub applications: u64,
+    /// Sum of confidence changes observed after the intervention
+    pub total_confidence_delta: f64,
+}
+
+impl InterventionEffectiveness {
+    /// Mean confidence change following the intervention
+    pub fn mean_effect(&self) -> f64 {
+        if self.applications == 0 {
+            0.0
+        } else {
+            self.total_confidence_delta / self.applications as f64
+        }
+    }
+}
+
+/// Intervention awaiting an outcome to be attributed to it
+#[derive(Debug, Clone)]
+struct PendingIntervention {
+    trigger: String,
+    context_key: String,
+    confidence_before: f64,
+}
+
 /// Meta-cognitive controller
 pub struct MetacognitiveController {
     monitor: MetacognitiveMonitor,
     intervention_thresholds: HashMap<String, f64>,
+    context_thresholds: HashMap<String, HashMap<String, f64>>, // context -> trigger -> threshold
+    effectiveness: HashMap<(String, String), InterventionEffectiveness>, // (trigger, context)
+    pending_interventions: Vec<PendingIntervention>,
     intervention_cooldown: u64, // milliseconds
     last_intervention: u64,
+    event_bus: Option<Arc<MessageBus>>,
 }
 
 impl MetacognitiveController {
+    /// Minimum attributed outcomes before an intervention's threshold is tuned
+    const MIN_SAMPLES_FOR_TUNING: u64 = 3;
+    /// Mean confidence gain above which an intervention counts as helpful
+    const HELPFUL_EFFECT: f64 = 0.01;
+
     /// Create a new meta-cognitive controller
     pub fn new() -> Self {
         let mut thresholds = HashMap::new();
         thresholds.insert("stuck_probability".to_string(), 0.7);
         thresholds.insert("quality_drop".to_string(), 0.2);
         thresholds.insert("time_pressure".to_string(), 0.8);
+        thresholds.insert("cognitive_load".to_string(), 0.8);
 
         Self {
             monitor: MetacognitiveMonitor::new(),
             intervention_thresholds: thresholds,
+            context_thresholds: HashMap::new(),
+            effectiveness: HashMap::new(),
+            pending_interventions: Vec::new(),
             intervention_cooldown: 5000, // 5 seconds
             last_intervention: 0,
+            event_bus: None,
         }
     }
 
     /// Assess and potentially intervene in reasoning process
+    ///
+    /// Each trigger fires when its measure exceeds the trigger's threshold
+    /// for the context, see [`Self::threshold_for`]:
+    /// - `stuck_probability`: the assessment's stuck probability
+    /// - `quality_drop`: on a declining quality trend, how far confidence is
+    ///   below 1.0; a decline alone no longer fires it
+    /// - `time_pressure`: average execution time as a share of the context's
+    ///   time constraint, both in milliseconds
+    /// - `cognitive_load`: the context's cognitive load
     pub async fn assess_and_intervene(
         &mut self,
         assessment: &MetacognitiveAssessment,
@@ -204,45 +339,118 @@ impl MetacognitiveController {
             return Ok(Vec::new());
         }
 
+        let context_key = Self::context_key(context);
         let mut interventions = Vec::new();
+        let mut triggered = Vec::new();
 
         // Assess stuck situation
-        if let Some(&threshold) = self.intervention_thresholds.get("stuck_probability") {
-            if assessment.stuck_probability > threshold {
-                interventions.push(RecommendedAction::ChangeStrategy);
-                interventions.push(RecommendedAction::TakeBreak);
-            }
+        if assessment.stuck_probability > self.threshold_for("stuck_probability", &context_key) {
+            interventions.push(RecommendedAction::ChangeStrategy);
+            interventions.push(RecommendedAction::TakeBreak);
+            triggered.push("stuck_probability");
         }
 
         // Assess quality decline
-        if matches!(assessment.quality_trend, QualityTrend::Declining) {
-            if let Some(&threshold) = self.intervention_thresholds.get("quality_drop") {
-                interventions.push(RecommendedAction::AddHeuristic("quality_focus".to_string()));
-            }
+        if matches!(assessment.quality_trend, QualityTrend::Declining)
+            && 1.0 - assessment.current_confidence > self.threshold_for("quality_drop", &context_key)
+        {
+            interventions.push(RecommendedAction::AddHeuristic("quality_focus".to_string()));
+            triggered.push("quality_drop");
         }
 
         // Assess time pressure
         if let Some(time_limit) = context.time_constraint {
-            let elapsed_ratio = stats.average_execution_time_ms as f64 / time_limit as f64;
-            if elapsed_ratio > 0.8 {
+            let elapsed_ratio = stats.average_execution_time_ms / (time_limit as f64 * 1000.0);
+            if elapsed_ratio > self.threshold_for("time_pressure", &context_key) {
                 interventions.push(RecommendedAction::AdjustExploration(0.1)); // Reduce exploration
+                triggered.push("time_pressure");
             }
         }
 
         // Assess cognitive load
-        if context.cognitive_load > 0.8 {
+        if context.cognitive_load > self.threshold_for("cognitive_load", &context_key) {
             interventions.push(RecommendedAction::ReduceBranching);
+            triggered.push("cognitive_load");
         }
 
         // Apply interventions
         if !interventions.is_empty() {
             self.last_intervention = current_time;
+            publish_event(self.event_bus.as_ref(), INTERVENTION_TOPIC, serde_json::json!({
+                "context": context_key,
+                "triggers": triggered,
+                "actions": interventions,
+            })).await;
+            for trigger in triggered {
+                self.pending_interventions.push(PendingIntervention {
+                    trigger: trigger.to_string(),
+                    context_key: context_key.clone(),
+                    confidence_before: assessment.current_confidence,
+                });
+            }
             info!("Meta-cognitive interventions triggered: {:?}", interventions);
         }
 
         Ok(interventions)
     }
 
+    /// Attribute the outcome observed after the last interventions to them
+    ///
+    /// Each pending intervention is credited with the change in confidence
+    /// since it fired. Once an intervention has enough outcomes in a context,
+    /// its threshold for that context is lowered if it tends to help and
+    /// raised if it does not, independently of the other interventions.
+    pub fn record_intervention_outcome(&mut self, outcome: &MetacognitiveAssessment) {
+        for pending in std::mem::take(&mut self.pending_interventions) {
+            let key = (pending.trigger.clone(), pending.context_key.clone());
+            let stats = self.effectiveness.entry(key).or_default();
+            stats.applications += 1;
+            stats.total_confidence_delta += outcome.current_confidence - pending.confidence_before;
+
+            if stats.applications >= Self::MIN_SAMPLES_FOR_TUNING {
+                let factor = if stats.mean_effect() > Self::HELPFUL_EFFECT { 0.95 } else { 1.05 };
+                let current = self.threshold_for(&pending.trigger, &pending.context_key);
+                let tuned = (current * factor).clamp(0.05, 1.0);
+                debug!("Tuning '{}' threshold for '{}': {:.3} -> {:.3}",
+                       pending.trigger, pending.context_key, current, tuned);
+                self.context_thresholds
+                    .entry(pending.context_key)
+                    .or_default()
+                    .insert(pending.trigger, tuned);
+            }
+        }
+    }
+
+    /// Get the threshold of an intervention trigger in a context
+    pub fn threshold_for(&self, trigger: &str, context_key: &str) -> f64 {
+        self.context_thresholds
+            .get(context_key)
+            .and_then(|thresholds| thresholds.get(trigger))
+            .or_else(|| self.intervention_thresholds.get(trigger))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Get the observed effectiveness of an intervention trigger in a context
+    pub fn intervention_effectiveness(&self, trigger: &str, context_key: &str) -> Option<&InterventionEffectiveness> {
+        self.effectiveness.get(&(trigger.to_string(), context_key.to_string()))
+    }
+
+    /// Set the minimum time between interventions
+    pub fn set_intervention_cooldown(&mut self, cooldown_ms: u64) {
+        self.intervention_cooldown = cooldown_ms;
+    }
+
+    /// Publish triggered interventions to a message bus
+    pub fn set_event_bus(&mut self, bus: Arc<MessageBus>) {
+        self.event_bus = Some(bus);
+    }
+
+    /// Key used to group intervention effectiveness by context
+    pub fn context_key(context: &ThinkingContext) -> String {
+        context.task_type.clone()
+    }
+
     /// Update intervention thresholds based on experience
     pub async fn update_thresholds(&mut self, success: bool, interventions_used: &[RecommendedAction]) {
         if success && interventions_used.is_empty() {
@@ -266,6 +474,7 @@ impl MetacognitiveController {
     /// Reset controller state
     pub fn reset(&mut self) {
         self.monitor.reset();
+        self.pending_interventions.clear();
         self.last_intervention = 0;
     }
 }
@@ -407,6 +616,23 @@ mod tests {
         assert!(!insights.is_empty());
     }
 
+    #[test]
+    fn test_repeating_outputs_are_detected_as_a_loop() {
+        let mut monitor = MetacognitiveMonitor::new();
+        monitor.set_loop_detection(4, 0.5);
+        let text = |t: &str| NodeContent::Text(t.to_string());
+
+        for output in ["a", "b", "c", "d", "e"] {
+            assert_eq!(monitor.observe_output(&text(output)), LoopVerdict::Progressing);
+        }
+
+        let verdicts: Vec<_> = ["same answer", "other", "Same  answer", "other", "same answer", "other", "same answer", "other"].iter()
+            .map(|output| monitor.observe_output(&text(output)))
+            .collect();
+        assert_eq!(verdicts[3], LoopVerdict::ChangeStrategy);
+        assert_eq!(verdicts[7], LoopVerdict::Abort);
+    }
+
     #[tokio::test]
     async fn test_metacognitive_controller() {
         let mut controller = MetacognitiveController::new();
@@ -434,9 +660,11 @@ mod tests {
 
         let execution_result = ChainExecutionResult {
             chain_id: "test_chain".to_string(),
+            run_id: "test_run".to_string(),
             success: true,
             final_answer: Some("Test answer".to_string()),
             confidence: 0.85,
+            confidence_interval: crate::ConfidenceInterval::default(),
             quality_metrics: crate::ReasoningQuality {
                 logical_consistency: 0.8,
                 completeness: 0.9,
@@ -445,6 +673,7 @@ mod tests {
                 efficiency: 0.7,
                 adaptability: 0.75,
             },
+            quality_score: 0.7667,
             execution_stats: crate::ExecutionStats {
                 total_nodes: 5,
                 executed_nodes: 5,
@@ -457,6 +686,9 @@ mod tests {
             },
             metacognitive_history: vec![],
             adaptation_log: vec!["Adapted strategy".to_string()],
+            node_outcomes: vec![],
+            cancelled: false,
+            abort_reason: None,
         };
 
         let reflections = analyzer.analyze_and_reflect(&execution_result, &context).await.unwrap();
@@ -465,4 +697,61 @@ mod tests {
         let improvements = analyzer.generate_improvements(&reflections).await;
         assert!(!improvements.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_intervention_thresholds_tuned_by_effectiveness() {
+        let mut controller = MetacognitiveController::new();
+        controller.set_intervention_cooldown(0);
+        let stats = VcpExecutionStats::for_test(0.0);
+        let key = MetacognitiveController::context_key(&create_test_context());
+
+        for _ in 0..3 {
+            // Being stuck fires only the stuck intervention, which raises confidence
+            let mut stuck = create_test_assessment();
+            stuck.stuck_probability = 0.9;
+            stuck.current_confidence = 0.4;
+            let actions = controller.assess_and_intervene(&stuck, &create_test_context(), &stats).await.unwrap();
+            assert!(matches!(actions[..], [RecommendedAction::ChangeStrategy, RecommendedAction::TakeBreak]));
+            let mut after = create_test_assessment();
+            after.current_confidence = 0.7;
+            controller.record_intervention_outcome(&after);
+
+            // High load fires only branching reduction, which changes nothing
+            let mut loaded = create_test_context();
+            loaded.cognitive_load = 0.9;
+            let actions = controller.assess_and_intervene(&create_test_assessment(), &loaded, &stats).await.unwrap();
+            assert!(matches!(actions[..], [RecommendedAction::ReduceBranching]));
+            controller.record_intervention_outcome(&create_test_assessment());
+        }
+
+        assert!(controller.intervention_effectiveness("stuck_probability", &key).unwrap().mean_effect() > 0.0);
+        assert_eq!(controller.intervention_effectiveness("cognitive_load", &key).unwrap().applications, 3);
+        assert!(controller.threshold_for("stuck_probability", &key) < 0.7);
+        assert!(controller.threshold_for("cognitive_load", &key) > 0.8);
+        // Other contexts keep the global thresholds
+        assert_eq!(controller.threshold_for("stuck_probability", "other_task"), 0.7);
+    }
+
+    #[tokio::test]
+    async fn test_triggers_compare_their_measures_with_thresholds() {
+        let mut controller = MetacognitiveController::new();
+        controller.set_intervention_cooldown(0);
+        let mut stats = VcpExecutionStats::for_test(0.0);
+        let context = create_test_context();
+
+        // A decline from high confidence is within the quality_drop threshold
+        let mut declining = create_test_assessment();
+        declining.quality_trend = QualityTrend::Declining;
+        assert!(controller.assess_and_intervene(&declining, &context, &stats).await.unwrap().is_empty());
+        declining.current_confidence = 0.5;
+        let actions = controller.assess_and_intervene(&declining, &context, &stats).await.unwrap();
+        assert!(matches!(actions[..], [RecommendedAction::AddHeuristic(_)]));
+
+        // 50s of a 60s constraint is past the 0.8 time_pressure threshold
+        stats.average_execution_time_ms = 40_000.0;
+        assert!(controller.assess_and_intervene(&create_test_assessment(), &context, &stats).await.unwrap().is_empty());
+        stats.average_execution_time_ms = 50_000.0;
+        let actions = controller.assess_and_intervene(&create_test_assessment(), &context, &stats).await.unwrap();
+        assert!(matches!(actions[..], [RecommendedAction::AdjustExploration(_)]));
+    }
 }
@@ -0,0 +1,26 @@
+//! Live progress callbacks for reasoning runs
+//!
+//! A [`ReasoningObserver`] attached to a [`RecursiveEngine`](crate::RecursiveEngine)
+//! is told when each node starts and finishes and about every metacognitive
+//! assessment, so a UI can render progress while a long chain runs. Every
+//! callback defaults to doing nothing.
+
+use crate::{MetacognitiveAssessment, ThinkingNode};
+use async_trait::async_trait;
+
+/// Receives progress of chain runs as it happens
+///
+/// Callbacks run inline on the reasoning task and should return quickly.
+#[async_trait]
+pub trait ReasoningObserver: Send + Sync {
+    /// A node is about to execute
+    async fn on_node_started(&self, _chain_id: &str, _node: &ThinkingNode) {}
+
+    /// A node finished executing; failed and timed out nodes report `success = false`
+    ///
+    /// Not called for a node abandoned because its chain was cancelled.
+    async fn on_node_completed(&self, _chain_id: &str, _node_id: &str, _success: bool, _confidence: f64) {}
+
+    /// The engine assessed the chain's progress
+    async fn on_assessment(&self, _chain_id: &str, _assessment: &MetacognitiveAssessment) {}
+}
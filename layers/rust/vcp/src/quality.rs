@@ -0,0 +1,132 @@
+//! Scalar scoring of reasoning quality
+
+use crate::ReasoningQuality;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One dimension of [`ReasoningQuality`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum QualityDimension {
+    LogicalConsistency,
+    Completeness,
+    Relevance,
+    Novelty,
+    Efficiency,
+    Adaptability,
+}
+
+impl QualityDimension {
+    /// Every dimension, in declaration order
+    pub const ALL: [QualityDimension; 6] = [
+        QualityDimension::LogicalConsistency,
+        QualityDimension::Completeness,
+        QualityDimension::Relevance,
+        QualityDimension::Novelty,
+        QualityDimension::Efficiency,
+        QualityDimension::Adaptability,
+    ];
+
+    /// Value of this dimension in `quality`
+    pub fn value(&self, quality: &ReasoningQuality) -> f64 {
+        match self {
+            QualityDimension::LogicalConsistency => quality.logical_consistency,
+            QualityDimension::Completeness => quality.completeness,
+            QualityDimension::Relevance => quality.relevance,
+            QualityDimension::Novelty => quality.novelty,
+            QualityDimension::Efficiency => quality.efficiency,
+            QualityDimension::Adaptability => quality.adaptability,
+        }
+    }
+}
+
+/// Weighted mean of quality dimensions, in 0.0..=1.0
+///
+/// Dimensions without a weight count as 1.0, so the default scorer is the
+/// plain mean of all six.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct QualityScorer {
+    weights: HashMap<QualityDimension, f64>,
+}
+
+impl QualityScorer {
+    /// Create a scorer weighting every dimension equally
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the weight of a dimension; negative weights count as 0.0
+    pub fn with_weight(mut self, dimension: QualityDimension, weight: f64) -> Self {
+        self.weights.insert(dimension, weight.max(0.0));
+        self
+    }
+
+    /// Weight of a dimension
+    pub fn weight(&self, dimension: QualityDimension) -> f64 {
+        self.weights.get(&dimension).copied().unwrap_or(1.0)
+    }
+
+    /// Score a quality; all-zero weights score 0.0
+    pub fn score(&self, quality: &ReasoningQuality) -> f64 {
+        let total_weight: f64 = QualityDimension::ALL.iter().map(|d| self.weight(*d)).sum();
+        if total_weight == 0.0 {
+            return 0.0;
+        }
+
+        let weighted: f64 = QualityDimension::ALL.iter()
+            .map(|d| self.weight(*d) * d.value(quality).clamp(0.0, 1.0))
+            .sum();
+        weighted / total_weight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform_quality(value: f64) -> ReasoningQuality {
+        ReasoningQuality {
+            logical_consistency: value,
+            completeness: value,
+            relevance: value,
+            novelty: value,
+            efficiency: value,
+            adaptability: value,
+        }
+    }
+
+    #[test]
+    fn test_weighted_score_follows_changed_dimension() {
+        let scorer = QualityScorer::new()
+            .with_weight(QualityDimension::LogicalConsistency, 4.0)
+            .with_weight(QualityDimension::Novelty, 0.0);
+        let base = uniform_quality(0.5);
+        assert!((scorer.score(&base) - 0.5).abs() < 1e-9);
+
+        // Logical consistency carries 4 of 8 weight units
+        let mut consistent = base.clone();
+        consistent.logical_consistency = 1.0;
+        assert!((scorer.score(&consistent) - (0.5 + 0.5 * 4.0 / 8.0)).abs() < 1e-9);
+
+        // A weightless dimension does not move the score
+        let mut novel = base.clone();
+        novel.novelty = 1.0;
+        assert_eq!(scorer.score(&novel), scorer.score(&base));
+
+        // The same change to an unweighted dimension moves it less
+        let mut complete = base.clone();
+        complete.completeness = 1.0;
+        assert!((scorer.score(&complete) - (0.5 + 0.5 / 8.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_score_is_normalized() {
+        let scorer = QualityScorer::new().with_weight(QualityDimension::Efficiency, 10.0);
+        assert_eq!(scorer.score(&uniform_quality(1.0)), 1.0);
+        assert_eq!(scorer.score(&uniform_quality(0.0)), 0.0);
+        assert_eq!(scorer.score(&uniform_quality(3.0)), 1.0);
+
+        let weightless = QualityDimension::ALL.iter()
+            .fold(QualityScorer::new(), |scorer, d| scorer.with_weight(*d, 0.0));
+        assert_eq!(weightless.score(&uniform_quality(0.8)), 0.0);
+    }
+}
@@ -1,13 +1,120 @@
 //! Recursive Engine for VCP
 
-use crate::{VcpResult, VcpError, ThinkingChain, ChainExecutionState, NodeExecutor, NodeExecutionResult, ChainExecutionResult, ThinkingContext, MetacognitiveAssessment, RecommendedAction};
+use crate::{VcpResult, VcpError, ThinkingChain, ChainExecutionState, NodeExecutor, NodeExecutionResult, ChainExecutionResult, ConfidenceInterval, ExecutionEstimate, ThinkingContext, MetacognitiveAssessment, RecommendedAction, ConfidenceCalibrator, NodeOutcome, NodeType, ReasoningQuality, QualityScorer, ThinkingNode, SeededRng, ReasoningObserver, MetacognitiveMonitor, LoopVerdict, LOOP_DETECTED, ASSESSMENT_TOPIC, ADAPTATION_TOPIC, publish_event, StopCriterion, ContextConfidence, RefinementProgress};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sira_ai_backends::AiProviderTrait;
+use sira_kernel::MessageBus;
+use sira_storage_backends::StorageClient;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::{timeout, Duration};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn, error};
 
+/// How the qualities of completed nodes combine into a chain's overall quality
+///
+/// Each quality dimension is aggregated separately.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum QualityAggregator {
+    /// Arithmetic mean over nodes
+    #[default]
+    Mean,
+    /// Mean weighted per node type; types without a weight count as 1.0
+    WeightedByNodeType(HashMap<NodeType, f64>),
+    /// Worst node, so a single weak link bounds the chain
+    Min,
+    /// Harmonic mean, which is pulled strongly towards weak nodes
+    HarmonicMean,
+}
+
+impl QualityAggregator {
+    /// Aggregate node qualities into one
+    pub fn aggregate(&self, nodes: &[(NodeType, &ReasoningQuality)]) -> ReasoningQuality {
+        let dimension = |value: fn(&ReasoningQuality) -> f64| {
+            self.combine(nodes.iter().map(|(node_type, quality)| (*node_type, value(quality), 1.0)))
+        };
+
+        ReasoningQuality {
+            logical_consistency: dimension(|q| q.logical_consistency),
+            completeness: dimension(|q| q.completeness),
+            relevance: dimension(|q| q.relevance),
+            novelty: dimension(|q| q.novelty),
+            efficiency: dimension(|q| q.efficiency),
+            adaptability: dimension(|q| q.adaptability),
+        }
+    }
+
+    /// Aggregate one score per node, such as node confidences, into one
+    ///
+    /// Each node is given as its type, score and weight; the means weigh
+    /// nodes by it, on top of any node type weight.
+    pub fn aggregate_scores(&self, nodes: &[(NodeType, f64, f64)]) -> f64 {
+        self.combine(nodes.iter().copied())
+    }
+
+    /// Combine weighted values; no values or no weight combine to 0.0
+    fn combine(&self, values: impl Iterator<Item = (NodeType, f64, f64)>) -> f64 {
+        let type_weight = |node_type: &NodeType| match self {
+            QualityAggregator::WeightedByNodeType(weights) => weights.get(node_type).copied().unwrap_or(1.0).max(0.0),
+            _ => 1.0,
+        };
+        let values: Vec<(f64, f64)> = values
+            .map(|(node_type, value, weight)| (value, type_weight(&node_type) * weight.max(0.0)))
+            .collect();
+        let total_weight: f64 = values.iter().map(|(_, w)| w).sum();
+        if values.is_empty() || total_weight == 0.0 {
+            return 0.0;
+        }
+
+        match self {
+            QualityAggregator::Mean | QualityAggregator::WeightedByNodeType(_) => {
+                values.iter().map(|(v, w)| w * v).sum::<f64>() / total_weight
+            }
+            QualityAggregator::Min => values.iter().map(|(v, _)| *v).fold(f64::INFINITY, f64::min),
+            QualityAggregator::HarmonicMean => {
+                // Any zero-quality node makes the harmonic mean zero
+                if values.iter().any(|(v, _)| *v <= 0.0) {
+                    return 0.0;
+                }
+                total_weight / values.iter().map(|(v, w)| w / v).sum::<f64>()
+            }
+        }
+    }
+}
+
+/// Memoization key of a node: a hash of the structural key chain merging deduplicates by
+///
+/// Content is compared case-insensitively with whitespace collapsed, so
+/// trivially different phrasings of the same sub-question share a key.
+fn memo_key(node: &ThinkingNode) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    ThinkingChain::structural_key(node).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Storage key of a chain run's checkpoint
+fn checkpoint_key(run_id: &str) -> String {
+    format!("vcp:checkpoint:{}", run_id)
+}
+
+/// Progress of a chain run, as checkpointed and resumed
+#[derive(Serialize, Deserialize)]
+struct RunProgress {
+    run_id: String,
+    state: ChainExecutionState,
+    node_outcomes: Vec<NodeOutcome>,
+    recursion_depth: u32,
+    metacognitive_history: Vec<MetacognitiveAssessment>,
+    memo: HashMap<u64, NodeExecutionResult>,
+    monitor: MetacognitiveMonitor,
+    /// Time the run spent executing before this point, counted against its time budget
+    elapsed_ms: u64,
+}
+
 /// Recursive reasoning engine
 pub struct RecursiveEngine {
     node_executor: Arc<dyn NodeExecutor>,
@@ -15,6 +122,20 @@ pub struct RecursiveEngine {
     metacognition_enabled: bool,
     max_recursion_depth: u32,
     adaptation_enabled: bool,
+    confidence_calibrator: Arc<Mutex<ConfidenceCalibrator>>,
+    rng: Option<SeededRng>,
+    quality_aggregator: QualityAggregator,
+    quality_scorer: QualityScorer,
+    event_bus: Option<Arc<MessageBus>>,
+    observer: Option<Arc<dyn ReasoningObserver>>,
+    memoization_enabled: bool,
+    /// Outputs looked back over and share of repeats counting as a loop
+    loop_detection: (usize, f64),
+    checkpoint_store: Option<Arc<dyn StorageClient>>,
+    /// Shortest time between two checkpoints of a run
+    checkpoint_interval: Duration,
+    /// Tokens one API call is assumed to use and the price per 1000 tokens
+    api_call_estimates: (u64, Option<f64>),
 }
 
 impl RecursiveEngine {
@@ -26,15 +147,137 @@ impl RecursiveEngine {
             metacognition_enabled: true,
             max_recursion_depth: 10,
             adaptation_enabled: true,
+            confidence_calibrator: Arc::new(Mutex::new(ConfidenceCalibrator::new())),
+            rng: None,
+            quality_aggregator: QualityAggregator::default(),
+            quality_scorer: QualityScorer::default(),
+            event_bus: None,
+            observer: None,
+            memoization_enabled: false,
+            loop_detection: (6, 0.5),
+            checkpoint_store: None,
+            checkpoint_interval: Duration::from_secs(1),
+            api_call_estimates: (1000, None),
+        }
+    }
+
+    /// Predict the time, tokens and cost of executing a chain without running it
+    ///
+    /// Nodes are planned in the order [`Self::execute_chain`] would run them,
+    /// one at a time, so their estimated times add up. Each node's time is
+    /// capped at the per-node timeout, and with memoization enabled repeated
+    /// nodes cost nothing. Every node is assumed to succeed.
+    pub fn estimate(&self, chain: &ThinkingChain, context: &ThinkingContext) -> ExecutionEstimate {
+        let node_timeout_ms = (context.resource_limits.time_budget_ms / 10).max(1000);
+        let (tokens_per_call, cost_per_1k_tokens) = self.api_call_estimates;
+
+        let mut estimate = ExecutionEstimate {
+            chain_id: chain.id.clone(),
+            execution_plan: Vec::new(),
+            memoized_nodes: 0,
+            time_estimate_ms: 0,
+            api_calls_estimate: 0,
+            token_estimate: 0,
+            cost_estimate: None,
+        };
+        let mut memo_keys = std::collections::HashSet::new();
+        let mut state = ChainExecutionState::new(chain.clone());
+
+        while !state.is_complete() {
+            let Some(node_id) = state.get_next_node() else {
+                break;
+            };
+            let Some(node) = chain.get_node(&node_id) else {
+                state.mark_failed(&node_id);
+                continue;
+            };
+
+            if !self.memoization_enabled || memo_keys.insert(memo_key(node)) {
+                let cost = self.node_executor.estimate_cost(node);
+                estimate.time_estimate_ms += cost.time_estimate_ms.min(node_timeout_ms);
+                estimate.api_calls_estimate += cost.api_calls_estimate as u64;
+            } else {
+                estimate.memoized_nodes += 1;
+            }
+            estimate.execution_plan.push(node_id.clone());
+            state.mark_completed(&node_id, node.confidence);
         }
+
+        estimate.token_estimate = estimate.api_calls_estimate * tokens_per_call;
+        estimate.cost_estimate = cost_per_1k_tokens.map(|price| estimate.token_estimate as f64 / 1000.0 * price);
+        estimate
     }
 
     /// Execute a thinking chain with recursive reasoning
+    ///
+    /// Cancelling `cancellation` stops execution between nodes or during the
+    /// running node; the partial result is returned with `cancelled` set.
     pub async fn execute_chain(
         &self,
         chain: ThinkingChain,
         context: &ThinkingContext,
         recursion_depth: u32,
+        cancellation: &CancellationToken,
+    ) -> VcpResult<ChainExecutionResult> {
+        self.execute_run(&crate::generate_id("run"), chain, context, recursion_depth, cancellation).await
+    }
+
+    /// Execute a thinking chain as the run `run_id`
+    ///
+    /// Like [`Self::execute_chain`], but the caller picks the run ID up front,
+    /// so the run can be resumed with [`Self::resume_chain`] even if this
+    /// process does not live to see its result.
+    pub async fn execute_run(
+        &self,
+        run_id: &str,
+        chain: ThinkingChain,
+        context: &ThinkingContext,
+        recursion_depth: u32,
+        cancellation: &CancellationToken,
+    ) -> VcpResult<ChainExecutionResult> {
+        self.seeded(self.run_chain(run_id, chain, context, recursion_depth, cancellation)).await
+    }
+
+    /// Resume a chain run from its last checkpoint
+    ///
+    /// `run_id` is the [`ChainExecutionResult::run_id`] of the interrupted
+    /// run. Nodes the earlier run already completed or failed are not executed
+    /// again, and its time budget, memoized nodes, loop detection and
+    /// metacognitive history carry over.
+    pub async fn resume_chain(
+        &self,
+        run_id: &str,
+        context: &ThinkingContext,
+        cancellation: &CancellationToken,
+    ) -> VcpResult<ChainExecutionResult> {
+        let store = self.checkpoint_store.as_ref()
+            .ok_or_else(|| VcpError::Checkpoint("No checkpoint store configured".to_string()))?;
+        let entry = store.get(&checkpoint_key(run_id)).await
+            .map_err(|e| VcpError::Checkpoint(format!("Failed to load checkpoint {}: {}", run_id, e)))?
+            .ok_or_else(|| VcpError::Checkpoint(format!("No checkpoint for run {}", run_id)))?;
+        let progress: RunProgress = serde_json::from_value(entry.value)
+            .map_err(|e| VcpError::Checkpoint(format!("Invalid checkpoint {}: {}", run_id, e)))?;
+
+        info!("Resuming run {} of chain {} with {} of {} nodes processed",
+              run_id, progress.state.chain.id, progress.state.completed_nodes.len(), progress.state.chain.nodes.len());
+        self.seeded(self.run_state(progress, context, cancellation)).await
+    }
+
+    /// Run a future under the engine's seeded RNG, if any
+    async fn seeded<F: std::future::Future>(&self, future: F) -> F::Output {
+        match &self.rng {
+            Some(rng) => rng.scope(future).await,
+            None => future.await,
+        }
+    }
+
+    async fn run_chain(
+        &self,
+        run_id: &str,
+        chain: ThinkingChain,
+        context: &ThinkingContext,
+        recursion_depth: u32,
+        cancellation: &CancellationToken,
     ) -> VcpResult<ChainExecutionResult> {
         if recursion_depth > self.max_recursion_depth {
             return Err(VcpError::RecursiveReasoning(
@@ -44,15 +287,56 @@ impl RecursiveEngine {
 
         info!("Executing chain {} at depth {}", chain.id, recursion_depth);
 
-        let mut execution_state = ChainExecutionState::new(chain);
-        let mut metacognitive_history = Vec::new();
-        let start_time = std::time::Instant::now();
+        let progress = RunProgress {
+            run_id: run_id.to_string(),
+            state: ChainExecutionState::new(chain),
+            node_outcomes: Vec::new(),
+            recursion_depth,
+            metacognitive_history: Vec::new(),
+            memo: HashMap::new(),
+            monitor: MetacognitiveMonitor::new(),
+            elapsed_ms: 0,
+        };
+        self.run_state(progress, context, cancellation).await
+    }
+
+    async fn run_state(
+        &self,
+        progress: RunProgress,
+        context: &ThinkingContext,
+        cancellation: &CancellationToken,
+    ) -> VcpResult<ChainExecutionResult> {
+        let RunProgress {
+            run_id,
+            state: mut execution_state,
+            mut node_outcomes,
+            recursion_depth,
+            mut metacognitive_history,
+            mut memo,
+            mut monitor,
+            elapsed_ms,
+        } = progress;
+        let mut cancelled = false;
+        let mut abort_reason = None;
+        monitor.set_loop_detection(self.loop_detection.0, self.loop_detection.1);
+        monitor.set_enabled(self.metacognition_enabled);
+        // A resumed run continues the clock of the interrupted one; the run is
+        // timed on tokio's clock like the node timeouts are
+        let now = tokio::time::Instant::now();
+        let start_time = now.checked_sub(Duration::from_millis(elapsed_ms)).unwrap_or(now);
+        let mut last_checkpoint = now;
 
         // Execute nodes iteratively
         while !execution_state.is_complete() {
+            if cancellation.is_cancelled() {
+                cancelled = true;
+                break;
+            }
+
             // Metacognitive assessment
             if self.metacognition_enabled {
                 let assessment = self.assess_progress(&execution_state, context).await?;
+                self.report_assessment(&execution_state.chain.id, &assessment).await;
                 metacognitive_history.push(assessment.clone());
 
                 // Apply metacognitive interventions
@@ -65,18 +349,109 @@ impl RecursiveEngine {
                 None => break, // No more nodes to execute
             };
 
-            // Execute node with timeout
-            let execution_result = self.execute_node_with_timeout(
-                &next_node_id,
-                &execution_state.chain,
-                context,
-            ).await;
+            if let (Some(observer), Some(node)) = (&self.observer, execution_state.chain.get_node(&next_node_id)) {
+                observer.on_node_started(&execution_state.chain.id, node).await;
+            }
+
+            let node_started = tokio::time::Instant::now();
+
+            let memo_key = execution_state.chain.get_node(&next_node_id)
+                .filter(|_| self.memoization_enabled)
+                .map(memo_key);
+            let memoized = memo_key.and_then(|key| memo.get(&key)).map(|result| NodeExecutionResult {
+                node_id: next_node_id.clone(),
+                ..result.clone()
+            });
+
+            let reused = memoized.is_some();
+            let execution_result = match memoized {
+                Some(result) => {
+                    debug!("Node {} reuses a memoized execution", next_node_id);
+                    Ok(result)
+                }
+                // Execute node with timeout, abandoning it if the chain is cancelled.
+                // Cancellation is polled first so a node that stopped because of it
+                // is not reported as a failure.
+                None => tokio::select! {
+                    biased;
+                    _ = cancellation.cancelled() => {
+                        cancelled = true;
+                        break;
+                    }
+                    result = self.execute_node_with_timeout(
+                        &next_node_id,
+                        &execution_state.chain,
+                        context,
+                        cancellation,
+                    ) => result,
+                },
+            };
+
+            // Failures are not memoized, so an identical node gets its own attempt
+            if let (Some(key), Ok(result)) = (memo_key, &execution_result) {
+                if result.success {
+                    memo.entry(key).or_insert_with(|| result.clone());
+                }
+            }
+
+            let execution_time_ms = node_started.elapsed().as_millis() as u64;
+            if let Some(node) = execution_state.chain.get_node_mut(&next_node_id) {
+                node.executed_at = Some(chrono::Utc::now());
+                node.execution_time_ms = Some(execution_time_ms);
+            }
+
+            if let Some(node) = execution_state.chain.get_node(&next_node_id) {
+                let (output, confidence, error) = match &execution_result {
+                    Ok(result) => (result.output.clone(), result.confidence, result.error_message.clone()),
+                    Err(e) => (None, 0.0, Some(e.to_string())),
+                };
+                let api_calls = match &execution_result {
+                    Ok(result) if !reused => result.execution_cost.api_calls_estimate,
+                    _ => 0,
+                };
+                node_outcomes.push(NodeOutcome {
+                    node_id: next_node_id.clone(),
+                    node_type: node.node_type,
+                    predicted_confidence: node.confidence,
+                    success: execution_result.as_ref().is_ok_and(|result| result.success),
+                    input: Some(node.content.clone()),
+                    output,
+                    confidence,
+                    execution_time_ms,
+                    error,
+                    api_calls,
+                });
+            }
+
+            if let Some(observer) = &self.observer {
+                let (success, confidence) = match &execution_result {
+                    Ok(result) => (result.success, result.confidence),
+                    Err(_) => (false, 0.0),
+                };
+                observer.on_node_completed(&execution_state.chain.id, &next_node_id, success, confidence).await;
+            }
 
             match execution_result {
                 Ok(result) => {
                     if result.success {
                         execution_state.mark_completed(&next_node_id, result.confidence);
                         debug!("Node {} completed successfully", next_node_id);
+
+                        match result.output.as_ref().map(|output| monitor.observe_output(output)) {
+                            Some(LoopVerdict::ChangeStrategy) => {
+                                // The engine has no other strategy to switch to, so the
+                                // verdict is passed on as a recommendation
+                                let assessment = self.loop_assessment(&execution_state, context).await?;
+                                self.report_assessment(&execution_state.chain.id, &assessment).await;
+                                metacognitive_history.push(assessment);
+                            }
+                            Some(LoopVerdict::Abort) => {
+                                warn!("Chain {} keeps repeating itself, aborting", execution_state.chain.id);
+                                abort_reason = Some(LOOP_DETECTED.to_string());
+                                break;
+                            }
+                            _ => {}
+                        }
                     } else {
                         execution_state.mark_failed(&next_node_id);
                         warn!("Node {} failed: {:?}", next_node_id, result.error_message);
@@ -94,6 +469,20 @@ impl RecursiveEngine {
                 }
             }
 
+            if self.checkpoint_store.is_some() && last_checkpoint.elapsed() >= self.checkpoint_interval {
+                self.save_checkpoint(&RunProgress {
+                    run_id: run_id.clone(),
+                    state: execution_state.clone(),
+                    node_outcomes: node_outcomes.clone(),
+                    recursion_depth,
+                    metacognitive_history: metacognitive_history.clone(),
+                    memo: memo.clone(),
+                    monitor: monitor.clone(),
+                    elapsed_ms: start_time.elapsed().as_millis() as u64,
+                }).await;
+                last_checkpoint = tokio::time::Instant::now();
+            }
+
             // Check resource limits
             if self.check_resource_limits(context, start_time).await? {
                 warn!("Resource limits exceeded, stopping execution");
@@ -101,17 +490,41 @@ impl RecursiveEngine {
             }
         }
 
+        // A cancelled run keeps an up to date checkpoint so it can be resumed
+        if cancelled {
+            self.save_checkpoint(&RunProgress {
+                run_id: run_id.clone(),
+                state: execution_state.clone(),
+                node_outcomes: node_outcomes.clone(),
+                recursion_depth,
+                metacognitive_history: metacognitive_history.clone(),
+                memo,
+                monitor,
+                elapsed_ms: start_time.elapsed().as_millis() as u64,
+            }).await;
+        } else {
+            self.clear_checkpoint(&run_id).await;
+        }
+
         // Calculate final result
         let execution_time = start_time.elapsed();
-        let final_quality = execution_state.get_average_quality();
+        let node_confidences = self.node_confidences(&execution_state, &node_outcomes);
+        let final_quality = self.quality_aggregator.aggregate_scores(&node_confidences);
         let progress = execution_state.get_progress();
+        let quality_metrics = self.calculate_overall_quality(&execution_state);
 
         let result = ChainExecutionResult {
             chain_id: execution_state.chain.id.clone(),
-            success: progress >= 0.8 && final_quality >= execution_state.chain.quality_threshold,
+            run_id,
+            success: !cancelled && abort_reason.is_none() && progress >= 0.8 && final_quality >= execution_state.chain.quality_threshold,
             final_answer: self.extract_final_answer(&execution_state),
             confidence: final_quality,
-            quality_metrics: self.calculate_overall_quality(&execution_state),
+            confidence_interval: ConfidenceInterval::from_node_confidences(
+                final_quality,
+                &node_confidences.iter().map(|(_, confidence, _)| *confidence).collect::<Vec<_>>(),
+            ),
+            quality_score: self.quality_scorer.score(&quality_metrics),
+            quality_metrics,
             execution_stats: crate::ExecutionStats {
                 total_nodes: execution_state.chain.nodes.len() as u64,
                 executed_nodes: execution_state.completed_nodes.len() as u64,
@@ -120,27 +533,63 @@ impl RecursiveEngine {
                 max_depth_reached: execution_state.current_depth,
                 total_execution_time_ms: execution_time.as_millis() as u64,
                 memory_peak_mb: 50, // Placeholder
-                api_calls_made: 0,  // Placeholder
+                api_calls_made: node_outcomes.iter().map(|outcome| outcome.api_calls as u64).sum(),
             },
             metacognitive_history,
             adaptation_log: execution_state.adaptation_events,
+            node_outcomes,
+            cancelled,
+            abort_reason,
         };
 
         // Store in history
         self.store_execution_result(&result).await?;
+        self.confidence_calibrator.lock().await.observe_execution(&result);
 
-        info!("Chain execution completed: success={}, quality={:.2}, progress={:.2}",
-              result.success, result.confidence, progress);
+        if cancelled {
+            info!("Chain execution cancelled: quality={:.2}, progress={:.2}", result.confidence, progress);
+        } else {
+            info!("Chain execution completed: success={}, quality={:.2}, progress={:.2}",
+                  result.success, result.confidence, progress);
+        }
 
         Ok(result)
     }
 
+    /// Save a run's progress to the checkpoint store, if any
+    ///
+    /// A failed save is logged rather than failing the run.
+    async fn save_checkpoint(&self, progress: &RunProgress) {
+        let Some(store) = &self.checkpoint_store else {
+            return;
+        };
+
+        let saved = match serde_json::to_value(progress) {
+            Ok(checkpoint) => store.set(&checkpoint_key(&progress.run_id), checkpoint, None).await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = saved {
+            warn!("Failed to checkpoint run {}: {}", progress.run_id, e);
+        }
+    }
+
+    /// Remove the checkpoint of a finished run
+    async fn clear_checkpoint(&self, run_id: &str) {
+        if let Some(store) = &self.checkpoint_store {
+            if let Err(e) = store.delete(&checkpoint_key(run_id)).await {
+                warn!("Failed to remove checkpoint of run {}: {}", run_id, e);
+            }
+        }
+    }
+
     /// Execute a single node with timeout
     async fn execute_node_with_timeout(
         &self,
         node_id: &str,
         chain: &ThinkingChain,
         context: &ThinkingContext,
+        cancellation: &CancellationToken,
     ) -> VcpResult<NodeExecutionResult> {
         let node = chain.get_node(node_id)
             .ok_or_else(|| VcpError::RecursiveReasoning(format!("Node {} not found", node_id)))?;
@@ -148,7 +597,7 @@ impl RecursiveEngine {
         let timeout_duration = context.resource_limits.time_budget_ms / 10; // Per-node timeout
         let timeout_duration = Duration::from_millis(timeout_duration.max(1000)); // Minimum 1 second
 
-        match timeout(timeout_duration, self.node_executor.execute_node(node, context)).await {
+        match timeout(timeout_duration, self.node_executor.execute_node(node, context, cancellation)).await {
             Ok(result) => result,
             Err(_) => Err(VcpError::RecursiveReasoning(format!("Node {} timed out", node_id))),
         }
@@ -202,6 +651,31 @@ impl RecursiveEngine {
         })
     }
 
+    /// Assessment of a chain whose outputs started repeating, recommending a strategy change
+    async fn loop_assessment(
+        &self,
+        state: &ChainExecutionState,
+        context: &ThinkingContext,
+    ) -> VcpResult<MetacognitiveAssessment> {
+        let assessment = self.assess_progress(state, context).await?;
+        Ok(MetacognitiveAssessment {
+            stuck_probability: 1.0,
+            recommended_actions: vec![RecommendedAction::ChangeStrategy],
+            ..assessment
+        })
+    }
+
+    /// Pass an assessment to the observer and publish it
+    async fn report_assessment(&self, chain_id: &str, assessment: &MetacognitiveAssessment) {
+        if let Some(observer) = &self.observer {
+            observer.on_assessment(chain_id, assessment).await;
+        }
+        publish_event(self.event_bus.as_ref(), ASSESSMENT_TOPIC, serde_json::json!({
+            "chain_id": chain_id,
+            "assessment": assessment,
+        })).await;
+    }
+
     /// Apply metacognitive interventions
     async fn apply_metacognitive_actions(
         &self,
@@ -212,15 +686,15 @@ impl RecursiveEngine {
         for action in &assessment.recommended_actions {
             match action {
                 RecommendedAction::ChangeStrategy => {
-                    state.adaptation_events.push("Changed strategy due to being stuck".to_string());
+                    self.record_adaptation(state, "Changed strategy due to being stuck".to_string()).await;
                     // In a real implementation, this would switch to a different execution strategy
                 }
                 RecommendedAction::AddHeuristic(heuristic) => {
-                    state.adaptation_events.push(format!("Added heuristic: {}", heuristic));
+                    self.record_adaptation(state, format!("Added heuristic: {}", heuristic)).await;
                     // Add new nodes or modify existing ones
                 }
                 RecommendedAction::ReduceBranching => {
-                    state.adaptation_events.push("Reduced branching factor".to_string());
+                    self.record_adaptation(state, "Reduced branching factor".to_string()).await;
                     // Limit future node expansion
                 }
                 _ => {
@@ -232,6 +706,15 @@ impl RecursiveEngine {
         Ok(())
     }
 
+    /// Log an adaptation of the running chain and publish it
+    async fn record_adaptation(&self, state: &mut ChainExecutionState, event: String) {
+        publish_event(self.event_bus.as_ref(), ADAPTATION_TOPIC, serde_json::json!({
+            "chain_id": state.chain.id,
+            "event": event,
+        })).await;
+        state.adaptation_events.push(event);
+    }
+
     /// Attempt recovery from failed node
     async fn attempt_recovery(
         &self,
@@ -242,7 +725,7 @@ impl RecursiveEngine {
         // Simple recovery: try to create alternative path
         // In a real implementation, this would be more sophisticated
 
-        state.adaptation_events.push(format!("Attempting recovery for failed node: {}", failed_node_id));
+        self.record_adaptation(state, format!("Attempting recovery for failed node: {}", failed_node_id)).await;
 
         // For now, just mark as non-blocking failure
         Ok(true)
@@ -252,7 +735,7 @@ impl RecursiveEngine {
     async fn check_resource_limits(
         &self,
         context: &ThinkingContext,
-        start_time: std::time::Instant,
+        start_time: tokio::time::Instant,
     ) -> VcpResult<bool> {
         let elapsed_ms = start_time.elapsed().as_millis() as u64;
 
@@ -289,34 +772,34 @@ impl RecursiveEngine {
             })
     }
 
-    /// Calculate overall quality metrics
+    /// Confidences of a run's processed nodes, which aggregate into the run's confidence
+    ///
+    /// A node counts with the confidence of its latest successful outcome, or
+    /// 0.0 if it failed, weighted by the quality score of its reasoning.
+    fn node_confidences(&self, state: &ChainExecutionState, node_outcomes: &[NodeOutcome]) -> Vec<(NodeType, f64, f64)> {
+        state.completed_nodes.iter()
+            .filter_map(|(node_id, completed)| {
+                let node = state.chain.get_node(node_id)?;
+                let confidence = if *completed {
+                    node_outcomes.iter().rev()
+                        .find(|outcome| outcome.node_id == *node_id && outcome.success)
+                        .map_or(node.confidence, |outcome| outcome.confidence)
+                } else {
+                    0.0
+                };
+                Some((node.node_type, confidence, self.quality_scorer.score(&node.quality)))
+            })
+            .collect()
+    }
+
+    /// Calculate overall quality metrics with the engine's aggregator
     fn calculate_overall_quality(&self, state: &ChainExecutionState) -> crate::ReasoningQuality {
-        let node_qualities: Vec<&crate::ReasoningQuality> = state.completed_nodes.keys()
+        let node_qualities: Vec<(NodeType, &crate::ReasoningQuality)> = state.completed_nodes.keys()
             .filter_map(|node_id| state.chain.get_node(node_id))
-            .map(|node| &node.quality)
+            .map(|node| (node.node_type, &node.quality))
             .collect();
 
-        if node_qualities.is_empty() {
-            return crate::ReasoningQuality {
-                logical_consistency: 0.0,
-                completeness: 0.0,
-                relevance: 0.0,
-                novelty: 0.0,
-                efficiency: 0.0,
-                adaptability: 0.0,
-            };
-        }
-
-        let count = node_qualities.len() as f64;
-
-        crate::ReasoningQuality {
-            logical_consistency: node_qualities.iter().map(|q| q.logical_consistency).sum::<f64>() / count,
-            completeness: node_qualities.iter().map(|q| q.completeness).sum::<f64>() / count,
-            relevance: node_qualities.iter().map(|q| q.relevance).sum::<f64>() / count,
-            novelty: node_qualities.iter().map(|q| q.novelty).sum::<f64>() / count,
-            efficiency: node_qualities.iter().map(|q| q.efficiency).sum::<f64>() / count,
-            adaptability: node_qualities.iter().map(|q| q.adaptability).sum::<f64>() / count,
-        }
+        self.quality_aggregator.aggregate(&node_qualities)
     }
 
     /// Store execution result in history
@@ -340,20 +823,99 @@ impl RecursiveEngine {
         history.get(chain_id).cloned().unwrap_or_default()
     }
 
+    /// Confidence of a node, calibrated by how its node type has performed so far
+    pub async fn calibrated_confidence(&self, node: &ThinkingNode) -> f64 {
+        self.confidence_calibrator.lock().await.calibrate(node.node_type, node.confidence)
+    }
+
+    /// Snapshot of the engine's confidence calibrator
+    pub async fn confidence_calibrator(&self) -> ConfidenceCalibrator {
+        self.confidence_calibrator.lock().await.clone()
+    }
+
     /// Enable/disable metacognition
     pub fn set_metacognition(&mut self, enabled: bool) {
         self.metacognition_enabled = enabled;
     }
 
+    /// Seed the engine's RNG so that runs are reproducible
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Some(SeededRng::new(seed));
+    }
+
     /// Set maximum recursion depth
     pub fn set_max_recursion_depth(&mut self, depth: u32) {
         self.max_recursion_depth = depth;
     }
+
+    /// Set how node qualities and confidences combine into a chain's overall quality and confidence
+    pub fn set_quality_aggregator(&mut self, aggregator: QualityAggregator) {
+        self.quality_aggregator = aggregator;
+    }
+
+    /// Set how qualities are scored, for the quality score and weighing node confidences
+    pub fn set_quality_scorer(&mut self, scorer: QualityScorer) {
+        self.quality_scorer = scorer;
+    }
+
+    /// Publish assessments and adaptations to a message bus
+    pub fn set_event_bus(&mut self, bus: Arc<MessageBus>) {
+        self.event_bus = Some(bus);
+    }
+
+    /// Report node progress and assessments to an observer as they happen
+    pub fn set_observer(&mut self, observer: Arc<dyn ReasoningObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Reuse the result of an identical node executed earlier in the same run
+    ///
+    /// Nodes are identical when their type and normalized content match.
+    pub fn set_memoization(&mut self, enabled: bool) {
+        self.memoization_enabled = enabled;
+    }
+
+    /// Set how many node outputs loop detection looks back over and the share of repeats that counts as a loop
+    ///
+    /// A first loop adds an assessment recommending
+    /// [`RecommendedAction::ChangeStrategy`] to the metacognitive history; a
+    /// second one aborts the chain with [`LOOP_DETECTED`] as its abort reason.
+    pub fn set_loop_detection(&mut self, window: usize, threshold: f64) {
+        self.loop_detection = (window, threshold);
+    }
+
+    /// Checkpoint each run to storage as it goes, so it can be resumed with [`Self::resume_chain`]
+    ///
+    /// A run is checkpointed after a node once the checkpoint interval has
+    /// passed since its last checkpoint, and when it is cancelled.
+    pub fn set_checkpoint_store(&mut self, store: Arc<dyn StorageClient>) {
+        self.checkpoint_store = Some(store);
+    }
+
+    /// Set the shortest time between two checkpoints of a run, 1 second by default
+    pub fn set_checkpoint_interval(&mut self, interval: Duration) {
+        self.checkpoint_interval = interval;
+    }
+
+    /// Set the tokens one API call is assumed to use and the price per 1000 tokens, for [`Self::estimate`]
+    pub fn set_api_call_estimates(&mut self, tokens_per_call: u64, cost_per_1k_tokens: f64) {
+        self.api_call_estimates = (tokens_per_call, Some(cost_per_1k_tokens));
+    }
+
+    /// Price API calls for [`Self::estimate`] at a provider's price for `model`
+    ///
+    /// Returns false, leaving the price unset, if the provider has no price for the model.
+    pub fn set_api_pricing_from(&mut self, provider: &dyn AiProviderTrait, model: &str) -> bool {
+        let price = provider.get_model_pricing(model);
+        self.api_call_estimates.1 = price;
+        price.is_some()
+    }
 }
 
 /// Recursive strategy executor
 pub struct RecursiveStrategyExecutor {
     engine: RecursiveEngine,
+    stop_criteria: Vec<Arc<dyn StopCriterion>>,
 }
 
 impl RecursiveStrategyExecutor {
@@ -361,40 +923,97 @@ impl RecursiveStrategyExecutor {
     pub fn new(node_executor: Arc<dyn NodeExecutor>) -> Self {
         Self {
             engine: RecursiveEngine::new(node_executor),
+            stop_criteria: vec![Arc::new(ContextConfidence)],
         }
     }
 
+    /// Replace the criteria that end refinement before `max_iterations`; any one met stops it
+    pub fn set_stop_criteria(&mut self, criteria: Vec<Arc<dyn StopCriterion>>) {
+        self.stop_criteria = criteria;
+    }
+
+    /// Seed the executor's RNG so that refinement runs are reproducible
+    pub fn set_seed(&mut self, seed: u64) {
+        self.engine.set_seed(seed);
+    }
+
+    /// Set how node qualities and confidences combine into a chain's overall quality and confidence
+    pub fn set_quality_aggregator(&mut self, aggregator: QualityAggregator) {
+        self.engine.set_quality_aggregator(aggregator);
+    }
+
+    /// Set how qualities are scored, for the quality score and weighing node confidences
+    pub fn set_quality_scorer(&mut self, scorer: QualityScorer) {
+        self.engine.set_quality_scorer(scorer);
+    }
+
+    /// Publish assessments and adaptations to a message bus
+    pub fn set_event_bus(&mut self, bus: Arc<MessageBus>) {
+        self.engine.set_event_bus(bus);
+    }
+
+    /// Report node progress and assessments to an observer as they happen
+    pub fn set_observer(&mut self, observer: Arc<dyn ReasoningObserver>) {
+        self.engine.set_observer(observer);
+    }
+
+    /// Reuse the result of an identical node executed earlier in the same run
+    pub fn set_memoization(&mut self, enabled: bool) {
+        self.engine.set_memoization(enabled);
+    }
+
     /// Execute with recursive refinement
+    ///
+    /// Iterations run until a stop criterion is met or `max_iterations` is
+    /// reached, and the most confident iteration's result is returned. Once
+    /// `cancellation` is cancelled no further iterations start and the
+    /// interrupted iteration's partial result is returned.
     pub async fn execute_with_refinement(
         &self,
         initial_chain: ThinkingChain,
         context: &ThinkingContext,
         max_iterations: u32,
+        cancellation: &CancellationToken,
     ) -> VcpResult<ChainExecutionResult> {
-        let mut best_result: Option<ChainExecutionResult> = None;
+        self.engine.seeded(self.refine_until_done(initial_chain, context, max_iterations, cancellation)).await
+    }
+
+    async fn refine_until_done(
+        &self,
+        initial_chain: ThinkingChain,
+        context: &ThinkingContext,
+        max_iterations: u32,
+        cancellation: &CancellationToken,
+    ) -> VcpResult<ChainExecutionResult> {
+        let started = std::time::Instant::now();
+        let mut results: Vec<ChainExecutionResult> = Vec::new();
         let mut current_chain = initial_chain;
 
         for iteration in 0..max_iterations {
             info!("Refinement iteration {}", iteration + 1);
 
-            let result = self.engine.execute_chain(current_chain.clone(), context, 0).await?;
-
-            // Check if this is better than previous results
-            if best_result.is_none() ||
-               result.confidence > best_result.as_ref().unwrap().confidence {
-                best_result = Some(result.clone());
+            let result = self.engine.execute_chain(current_chain.clone(), context, 0, cancellation).await?;
+            if result.cancelled {
+                return Ok(result);
             }
+            results.push(result);
 
             // Check if we should continue refining
-            if result.success && result.confidence >= context.emotional_state.confidence {
-                break; // Good enough result
+            let progress = RefinementProgress { context, results: &results, elapsed: started.elapsed() };
+            if self.stop_criteria.iter().any(|criterion| criterion.should_stop(&progress)) {
+                debug!("Refinement stopped after {} iterations", iteration + 1);
+                break;
             }
 
             // Generate refined chain based on results
-            current_chain = self.refine_chain(&current_chain, &result, context).await?;
+            let latest = results.last().expect("an iteration result was just recorded");
+            current_chain = self.refine_chain(&current_chain, latest, context).await?;
         }
 
-        best_result.ok_or_else(|| VcpError::RecursiveReasoning("No valid results after refinement".to_string()))
+        // The earliest of equally confident results wins
+        results.into_iter()
+            .reduce(|best, result| if result.confidence > best.confidence { result } else { best })
+            .ok_or_else(|| VcpError::RecursiveReasoning("No valid results after refinement".to_string()))
     }
 
     /// Refine chain based on execution results
@@ -408,7 +1027,7 @@ impl RecursiveStrategyExecutor {
         // This is a simplified implementation
 
         let mut refined_chain = original_chain.clone();
-        refined_chain.id = format!("refined_{}", uuid::Uuid::new_v4().simple());
+        refined_chain.id = crate::generate_id("refined");
         refined_chain.quality_threshold = (original_chain.quality_threshold + result.confidence) / 2.0;
 
         // Add reflection node if quality was low
@@ -479,10 +1098,10 @@ mod tests {
 
         let context = create_test_context();
 
-        let result = engine.execute_chain(chain, &context, 0).await.unwrap();
+        let result = engine.execute_chain(chain, &context, 0, &CancellationToken::new()).await.unwrap();
 
         assert!(result.execution_stats.total_nodes >= 1);
-        assert!(result.execution_stats.execution_time_ms > 0);
+        assert!(result.execution_stats.total_execution_time_ms > 0);
     }
 
     #[tokio::test]
@@ -497,10 +1116,604 @@ mod tests {
         );
 
         let context = create_test_context();
-        let result = engine.execute_chain(chain.clone(), &context, 0).await.unwrap();
+        let result = engine.execute_chain(chain.clone(), &context, 0, &CancellationToken::new()).await.unwrap();
 
         let history = engine.get_execution_history(&chain.id).await;
         assert_eq!(history.len(), 1);
         assert_eq!(history[0].chain_id, result.chain_id);
     }
+
+    #[tokio::test]
+    async fn test_metacognitive_events_are_published() {
+        let bus = Arc::new(MessageBus::new());
+        let mut engine = RecursiveEngine::new(Arc::new(BasicNodeExecutor));
+        engine.set_event_bus(Arc::clone(&bus));
+
+        let chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        let context = create_test_context();
+        let result = engine.execute_chain(chain, &context, 0, &CancellationToken::new()).await.unwrap();
+
+        let mut controller = crate::MetacognitiveController::new();
+        controller.set_event_bus(Arc::clone(&bus));
+        let stuck = MetacognitiveAssessment {
+            current_confidence: 0.4,
+            progress_rate: 0.0,
+            stuck_probability: 0.9,
+            quality_trend: crate::QualityTrend::Stable,
+            resource_efficiency: 0.5,
+            recommended_actions: vec![],
+        };
+        let interventions = controller
+            .assess_and_intervene(&stuck, &context, &crate::VcpExecutionStats::for_test(10.0))
+            .await
+            .unwrap();
+        assert!(!interventions.is_empty());
+
+        let history = bus.get_history(100).await;
+        let published = |topic: &str| history.iter().filter(|m| m.topic == topic).count();
+        assert_eq!(published(crate::ASSESSMENT_TOPIC), result.metacognitive_history.len());
+        assert_eq!(published(crate::ADAPTATION_TOPIC), result.adaptation_log.len());
+        assert_eq!(published(crate::INTERVENTION_TOPIC), 1);
+        assert!(!result.adaptation_log.is_empty());
+
+        let assessment = history.iter().find(|m| m.topic == crate::ASSESSMENT_TOPIC).unwrap();
+        assert_eq!(assessment.payload["chain_id"], result.chain_id);
+        assert_eq!(assessment.sender.as_deref(), Some("vcp"));
+    }
+
+    /// Basic executor whose results are adjusted by `adjust` before they are returned
+    struct AdjustedExecutor<F> {
+        adjust: F,
+    }
+
+    impl<F> AdjustedExecutor<F>
+    where
+        F: Fn(&crate::ThinkingNode, &mut NodeExecutionResult, &CancellationToken) + Send + Sync + 'static,
+    {
+        fn shared(adjust: F) -> Arc<dyn NodeExecutor> {
+            Arc::new(Self { adjust })
+        }
+    }
+
+    #[async_trait]
+    impl<F> NodeExecutor for AdjustedExecutor<F>
+    where
+        F: Fn(&crate::ThinkingNode, &mut NodeExecutionResult, &CancellationToken) + Send + Sync,
+    {
+        async fn execute_node(
+            &self,
+            node: &crate::ThinkingNode,
+            context: &ThinkingContext,
+            cancellation: &CancellationToken,
+        ) -> VcpResult<NodeExecutionResult> {
+            let mut result = BasicNodeExecutor.execute_node(node, context, cancellation).await?;
+            (self.adjust)(node, &mut result, cancellation);
+            Ok(result)
+        }
+
+        fn supported_types(&self) -> Vec<crate::NodeType> {
+            BasicNodeExecutor.supported_types()
+        }
+
+        fn estimate_cost(&self, node: &crate::ThinkingNode) -> crate::ExecutionCost {
+            BasicNodeExecutor.estimate_cost(node)
+        }
+    }
+
+    /// Executor whose analysis nodes never deliver
+    fn failing_analysis_executor() -> Arc<dyn NodeExecutor> {
+        AdjustedExecutor::shared(|node, result, _| {
+            if node.node_type == crate::NodeType::Analysis {
+                result.success = false;
+                result.error_message = Some("analysis came up empty".to_string());
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_confidence_calibration_from_history() {
+        let engine = RecursiveEngine::new(failing_analysis_executor());
+        let context = create_test_context();
+
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        let analysis = crate::NodeFactory::create_analysis_node(
+            "Why?".to_string(),
+            "Test".to_string(),
+            chain.root_node_id.clone(),
+        );
+        chain.add_node(analysis.clone()).unwrap();
+        let root = chain.get_node(&chain.root_node_id).unwrap().clone();
+
+        // Before any history the heuristic confidence is reported unchanged
+        assert_eq!(engine.calibrated_confidence(&analysis).await, analysis.confidence);
+
+        for _ in 0..10 {
+            let result = engine.execute_chain(chain.clone(), &context, 0, &CancellationToken::new()).await.unwrap();
+            assert!(result.node_outcomes.iter().any(|o| o.node_type == crate::NodeType::Analysis && !o.success));
+        }
+
+        let calibrator = engine.confidence_calibrator().await;
+        assert_eq!(calibrator.observations(crate::NodeType::Analysis), 10);
+
+        // The under-delivering node type is discounted; the reliable one is not
+        let calibrated = engine.calibrated_confidence(&analysis).await;
+        assert!(calibrated < analysis.confidence * 0.6);
+        assert!(engine.calibrated_confidence(&root).await >= root.confidence);
+    }
+
+    #[tokio::test]
+    async fn test_seeded_runs_are_reproducible() {
+        let context = create_test_context();
+
+        let run = |seed: u64| {
+            let context = context.clone();
+            async move {
+                // Build the chain under the same seed so its node IDs match too
+                let chain = crate::SeededRng::new(seed).scope(async {
+                    let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+                    for question in ["Who?", "What?", "Why?"] {
+                        let node = crate::NodeFactory::create_analysis_node(
+                            question.to_string(),
+                            "Test".to_string(),
+                            chain.root_node_id.clone(),
+                        );
+                        chain.add_node(node).unwrap();
+                    }
+                    chain
+                }).await;
+
+                let mut executor = RecursiveStrategyExecutor::new(failing_analysis_executor());
+                executor.set_seed(seed);
+                executor.execute_with_refinement(chain, &context, 3, &CancellationToken::new()).await.unwrap()
+            }
+        };
+
+        let first = run(42).await;
+        let second = run(42).await;
+
+        // Same refined chain, same node execution order and outcomes
+        let outcomes = |result: &ChainExecutionResult| {
+            result.node_outcomes.iter().map(|o| (o.node_id.clone(), o.success)).collect::<Vec<_>>()
+        };
+        assert!(first.chain_id.starts_with("refined_"));
+        assert_eq!(first.chain_id, second.chain_id);
+        assert_eq!(outcomes(&first), outcomes(&second));
+        assert_eq!(first.final_answer, second.final_answer);
+        assert_eq!(first.adaptation_log, second.adaptation_log);
+
+        assert_ne!(run(43).await.chain_id, first.chain_id);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancel_returns_partial_result_promptly() {
+        let (executor, executed) = interrupting_executor(3);
+        let engine = RecursiveEngine::new(executor);
+        let context = create_test_context();
+
+        // Each analysis node takes 50ms, so the whole chain takes about a second
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        for i in 0..20 {
+            let node = crate::NodeFactory::create_analysis_node(
+                format!("Question {}", i),
+                "Test".to_string(),
+                chain.root_node_id.clone(),
+            );
+            chain.add_node(node).unwrap();
+        }
+
+        // The third node cancels the run; nothing after it starts
+        let cancellation = CancellationToken::new();
+        let start = tokio::time::Instant::now();
+        let result = engine.execute_chain(chain.clone(), &context, 0, &cancellation).await.unwrap();
+
+        assert!(result.cancelled);
+        assert!(!result.success);
+        assert_eq!(executed.lock().unwrap().len(), 3);
+        assert_eq!(result.execution_stats.executed_nodes, 3);
+        assert_eq!(start.elapsed(), Duration::from_millis(150));
+
+        // Refinement stops without starting another iteration
+        let executor = RecursiveStrategyExecutor::new(Arc::new(BasicNodeExecutor));
+        let result = executor.execute_with_refinement(chain, &context, 3, &cancellation).await.unwrap();
+        assert!(result.cancelled);
+        assert_eq!(result.execution_stats.executed_nodes, 0);
+    }
+
+    #[tokio::test]
+    async fn test_min_aggregation_flags_a_single_weak_node() {
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        for (question, logical_consistency) in [("Solid", 0.9), ("Weak", 0.3)] {
+            let mut node = crate::NodeFactory::create_analysis_node(
+                question.to_string(),
+                "Test".to_string(),
+                chain.root_node_id.clone(),
+            );
+            node.quality.logical_consistency = logical_consistency;
+            chain.add_node(node).unwrap();
+        }
+
+        let mut state = ChainExecutionState::new(chain.clone());
+        for node_id in chain.nodes.keys() {
+            state.completed_nodes.insert(node_id.clone(), true);
+        }
+
+        // The root scores 1.0, so the mean of (1.0, 0.9, 0.3) clears a 0.7 bar
+        let mut engine = RecursiveEngine::new(Arc::new(BasicNodeExecutor));
+        let mean = engine.calculate_overall_quality(&state).logical_consistency;
+        assert!(mean > 0.7);
+
+        engine.set_quality_aggregator(QualityAggregator::Min);
+        let worst = engine.calculate_overall_quality(&state).logical_consistency;
+        assert!((worst - 0.3).abs() < 1e-9);
+
+        engine.set_quality_aggregator(QualityAggregator::HarmonicMean);
+        let harmonic = engine.calculate_overall_quality(&state).logical_consistency;
+        assert!(harmonic > worst && harmonic < 0.7);
+
+        // Weighting analysis nodes down moves the result towards the root
+        engine.set_quality_aggregator(QualityAggregator::WeightedByNodeType(HashMap::from([(NodeType::Analysis, 0.5)])));
+        let weighted = engine.calculate_overall_quality(&state).logical_consistency;
+        assert!((weighted - (1.0 + 0.5 * 0.9 + 0.5 * 0.3) / 2.0).abs() < 1e-9);
+    }
+
+    /// Basic executor that is unsure about questions mentioning "Weak"
+    fn weak_link_executor() -> Arc<dyn NodeExecutor> {
+        AdjustedExecutor::shared(|node, result, _| {
+            if matches!(&node.content, crate::NodeContent::Question { question, .. } if question.contains("Weak")) {
+                result.confidence = 0.2;
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_min_aggregation_fails_a_run_with_one_unsure_node() {
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        chain.quality_threshold = 0.6;
+        for question in ["Who?", "What?", "Why?", "Weak"] {
+            let node = crate::NodeFactory::create_analysis_node(question.to_string(), "Test".to_string(), chain.root_node_id.clone());
+            chain.add_node(node).unwrap();
+        }
+        let context = create_test_context();
+
+        // The mean of (0.8, 0.7, 0.7, 0.7, 0.2) clears the bar
+        let engine = RecursiveEngine::new(weak_link_executor());
+        let result = engine.execute_chain(chain.clone(), &context, 0, &CancellationToken::new()).await.unwrap();
+        assert!(result.confidence > 0.6 && result.confidence < 0.7);
+        assert!(result.success);
+
+        let mut engine = RecursiveEngine::new(weak_link_executor());
+        engine.set_quality_aggregator(QualityAggregator::Min);
+        let result = engine.execute_chain(chain, &context, 0, &CancellationToken::new()).await.unwrap();
+        assert!((result.confidence - 0.2).abs() < 1e-9);
+        assert!(!result.success);
+
+        // The interval describes the reported worst-case confidence
+        assert!(result.confidence_interval.lower < 0.2 && result.confidence_interval.upper > 0.2);
+    }
+
+    #[tokio::test]
+    async fn test_confidence_weighs_nodes_by_quality_score() {
+        let run = |weak_quality: f64| async move {
+            let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+            let mut node = crate::NodeFactory::create_analysis_node("Weak".to_string(), "Test".to_string(), chain.root_node_id.clone());
+            node.quality.completeness = weak_quality;
+            chain.add_node(node).unwrap();
+
+            let mut engine = RecursiveEngine::new(weak_link_executor());
+            engine.set_quality_scorer(QualityScorer::new().with_weight(crate::QualityDimension::Completeness, 10.0));
+            let result = engine.execute_chain(chain, &create_test_context(), 0, &CancellationToken::new()).await.unwrap();
+            result.confidence
+        };
+
+        // The unsure node counts for less the poorer its reasoning scores
+        let poor = run(0.0).await;
+        let good = run(1.0).await;
+        assert!(poor > good, "poor {} good {}", poor, good);
+        assert!(poor < 0.8 && good > 0.2);
+    }
+
+    /// Observer that records callbacks in the order they arrive
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl ReasoningObserver for RecordingObserver {
+        async fn on_node_started(&self, _chain_id: &str, node: &ThinkingNode) {
+            self.events.lock().unwrap().push(format!("started {}", node.id));
+        }
+
+        async fn on_node_completed(&self, _chain_id: &str, node_id: &str, success: bool, _confidence: f64) {
+            self.events.lock().unwrap().push(format!("completed {} {}", node_id, success));
+        }
+
+        async fn on_assessment(&self, _chain_id: &str, _assessment: &MetacognitiveAssessment) {
+            self.events.lock().unwrap().push("assessment".to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observer_sees_each_node_start_and_complete() {
+        let observer = Arc::new(RecordingObserver::default());
+        let mut engine = RecursiveEngine::new(Arc::new(BasicNodeExecutor));
+        engine.set_observer(observer.clone());
+
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        let analysis = crate::NodeFactory::create_analysis_node(
+            "Why?".to_string(),
+            "Test".to_string(),
+            chain.root_node_id.clone(),
+        );
+        chain.add_node(analysis).unwrap();
+        let result = engine.execute_chain(chain, &create_test_context(), 0, &CancellationToken::new()).await.unwrap();
+
+        // Each executed node is announced after an assessment, then completed before the next starts
+        let expected: Vec<String> = result.node_outcomes.iter()
+            .flat_map(|outcome| [
+                "assessment".to_string(),
+                format!("started {}", outcome.node_id),
+                format!("completed {} {}", outcome.node_id, outcome.success),
+            ])
+            .collect();
+        let events = observer.events.lock().unwrap().clone();
+        assert_eq!(result.node_outcomes.len(), 2);
+        assert_eq!(events[..expected.len()], expected[..]);
+        assert!(events[expected.len()..].iter().all(|event| event == "assessment"));
+    }
+
+    #[tokio::test]
+    async fn test_report_transcribes_each_executed_node() {
+        let engine = RecursiveEngine::new(Arc::new(BasicNodeExecutor));
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        let mut decision = crate::NodeFactory::create_decision_node(
+            vec!["Ship".to_string(), "Wait".to_string()],
+            vec!["Risk".to_string()],
+        );
+        decision.content = crate::NodeContent::Decision {
+            options: vec!["Ship".to_string(), "Wait".to_string()],
+            criteria: vec!["Risk".to_string()],
+            chosen_option: Some("Ship".to_string()),
+        };
+        decision.parent_id = Some(chain.root_node_id.clone());
+        chain.add_node(decision).unwrap();
+        let result = engine.execute_chain(chain, &create_test_context(), 0, &CancellationToken::new()).await.unwrap();
+
+        let report = result.to_report();
+        assert_eq!(report.steps.len(), result.node_outcomes.len());
+        for (step, outcome) in report.steps.iter().zip(&result.node_outcomes) {
+            assert_eq!(step.node_id, outcome.node_id);
+            assert!(step.input.is_some());
+        }
+        assert_eq!(report.final_answer.as_deref(), Some("Ship"));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["steps"].as_array().unwrap().len(), 2);
+        assert!(json["steps"].as_array().unwrap().iter().any(|step| step["input"] == "chose Ship from [Ship, Wait]"));
+        assert_eq!(json["final_answer"], "Ship");
+    }
+
+    #[tokio::test]
+    async fn test_memoized_identical_nodes_execute_once() {
+        // Counts how often analysis nodes are executed
+        let executed = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = executed.clone();
+        let mut engine = RecursiveEngine::new(AdjustedExecutor::shared(move |node, _, _| {
+            if node.node_type == crate::NodeType::Analysis {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        }));
+        engine.set_memoization(true);
+
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        for question in ["What limits  throughput?", "what limits throughput?"] {
+            chain.add_node(crate::NodeFactory::create_analysis_node(
+                question.to_string(),
+                "Service".to_string(),
+                chain.root_node_id.clone(),
+            )).unwrap();
+        }
+        let result = engine.execute_chain(chain, &create_test_context(), 0, &CancellationToken::new()).await.unwrap();
+
+        let analyses: Vec<_> = result.node_outcomes.iter()
+            .filter(|outcome| outcome.node_type == crate::NodeType::Analysis)
+            .collect();
+        assert_eq!(analyses.len(), 2);
+        assert!(analyses.iter().all(|outcome| outcome.success));
+        assert_eq!(executed.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_repeating_outputs_abort_with_loop_detected() {
+        // Every node answers with the same conclusion
+        let mut engine = RecursiveEngine::new(AdjustedExecutor::shared(|_, result, _| {
+            result.output = Some(crate::NodeContent::Text("The cache is the bottleneck".to_string()));
+        }));
+        engine.set_loop_detection(3, 0.5);
+
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        for i in 0..8 {
+            chain.add_node(crate::NodeFactory::create_analysis_node(
+                format!("What limits throughput in stage {}?", i),
+                "Service".to_string(),
+                chain.root_node_id.clone(),
+            )).unwrap();
+        }
+        let result = engine.execute_chain(chain, &create_test_context(), 0, &CancellationToken::new()).await.unwrap();
+
+        assert_eq!(result.abort_reason.as_deref(), Some(LOOP_DETECTED));
+        assert!(!result.success);
+        assert_eq!(result.node_outcomes.len(), 6);
+        let loop_assessments: Vec<_> = result.metacognitive_history.iter()
+            .filter(|assessment| assessment.stuck_probability == 1.0)
+            .collect();
+        assert_eq!(loop_assessments.len(), 1);
+        assert!(matches!(loop_assessments[0].recommended_actions[..], [RecommendedAction::ChangeStrategy]));
+        assert!(!result.adaptation_log.iter().any(|event| event.contains("strategy")));
+    }
+
+    /// Executor recording each executed node, cancelling the run once `stop_after` nodes are done
+    fn interrupting_executor(stop_after: usize) -> (Arc<dyn NodeExecutor>, Arc<std::sync::Mutex<Vec<String>>>) {
+        let executed = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = executed.clone();
+        let executor = AdjustedExecutor::shared(move |node, _, cancellation| {
+            let mut executed = recorded.lock().unwrap();
+            executed.push(node.id.clone());
+            if executed.len() == stop_after {
+                cancellation.cancel();
+            }
+        });
+        (executor, executed)
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_run_resumes_from_checkpoint() {
+        use sira_storage_backends::{GenericStorageClient, MemoryBackend, StorageConfig};
+
+        let store: Arc<dyn StorageClient> = Arc::new(GenericStorageClient::new(Box::new(MemoryBackend::new(StorageConfig::default()))));
+        let context = create_test_context();
+
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        for i in 0..5 {
+            chain.add_node(crate::NodeFactory::create_analysis_node(
+                format!("Question {}", i),
+                "Test".to_string(),
+                chain.root_node_id.clone(),
+            )).unwrap();
+        }
+
+        // Another run of the same chain keeps a checkpoint of its own
+        let (other, _) = interrupting_executor(2);
+        let mut engine = RecursiveEngine::new(other);
+        engine.set_checkpoint_store(store.clone());
+        let other = engine.execute_chain(chain.clone(), &context, 0, &CancellationToken::new()).await.unwrap();
+
+        // The first engine is interrupted halfway through and dropped
+        let (executor, first) = interrupting_executor(3);
+        let mut engine = RecursiveEngine::new(executor);
+        engine.set_checkpoint_store(store.clone());
+        let partial = engine.execute_chain(chain, &context, 0, &CancellationToken::new()).await.unwrap();
+        assert!(partial.cancelled);
+        assert_eq!(partial.execution_stats.executed_nodes, 3);
+        assert_ne!(partial.run_id, other.run_id);
+        drop(engine);
+
+        let (executor, second) = interrupting_executor(0);
+        let mut engine = RecursiveEngine::new(executor);
+        engine.set_checkpoint_store(store.clone());
+        let result = engine.resume_chain(&partial.run_id, &context, &CancellationToken::new()).await.unwrap();
+
+        // Completed nodes are not run again
+        let first = first.lock().unwrap().clone();
+        let second = second.lock().unwrap().clone();
+        assert_eq!(second.len(), 3);
+        assert!(second.iter().all(|node_id| !first.contains(node_id)));
+        assert!(!result.cancelled);
+        assert_eq!(result.execution_stats.executed_nodes, 6);
+        assert_eq!(result.node_outcomes.len(), 6);
+        assert_eq!(result.run_id, partial.run_id);
+
+        // The resumed run carries on the assessments and clock of the interrupted one
+        assert!(result.metacognitive_history.len() > partial.metacognitive_history.len());
+        assert_eq!(
+            result.metacognitive_history[..partial.metacognitive_history.len()].iter().map(|a| a.current_confidence).collect::<Vec<_>>(),
+            partial.metacognitive_history.iter().map(|a| a.current_confidence).collect::<Vec<_>>(),
+        );
+        assert!(result.execution_stats.total_execution_time_ms >= partial.execution_stats.total_execution_time_ms);
+
+        // A finished run leaves no checkpoint behind, the other run's remains
+        assert!(store.get(&checkpoint_key(&partial.run_id)).await.unwrap().is_none());
+        assert!(matches!(
+            engine.resume_chain(&partial.run_id, &context, &CancellationToken::new()).await,
+            Err(VcpError::Checkpoint(_))
+        ));
+        assert!(store.get(&checkpoint_key(&other.run_id)).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_diminishing_returns_stops_refinement_early() {
+        /// Records the chain of every started node, one chain per iteration
+        #[derive(Default)]
+        struct IterationCounter {
+            chains: std::sync::Mutex<Vec<String>>,
+        }
+
+        #[async_trait]
+        impl ReasoningObserver for IterationCounter {
+            async fn on_node_started(&self, chain_id: &str, _node: &ThinkingNode) {
+                let mut chains = self.chains.lock().unwrap();
+                if !chains.iter().any(|id| id == chain_id) {
+                    chains.push(chain_id.to_string());
+                }
+            }
+        }
+
+        let context = create_test_context();
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        let analysis = crate::NodeFactory::create_analysis_node("Why?".to_string(), "Test".to_string(), chain.root_node_id.clone());
+        chain.add_node(analysis).unwrap();
+
+        // Failing analysis never reaches the context's confidence, so only
+        // max_iterations ends refinement by default
+        let run = |criteria: Option<Vec<Arc<dyn StopCriterion>>>| {
+            let (chain, context) = (chain.clone(), context.clone());
+            async move {
+                let counter = Arc::new(IterationCounter::default());
+                let mut executor = RecursiveStrategyExecutor::new(failing_analysis_executor());
+                executor.set_observer(counter.clone());
+                if let Some(criteria) = criteria {
+                    executor.set_stop_criteria(criteria);
+                }
+                let result = executor.execute_with_refinement(chain, &context, 8, &CancellationToken::new()).await.unwrap();
+                let iterations = counter.chains.lock().unwrap().len();
+                (result, iterations)
+            }
+        };
+
+        let (_, iterations) = run(None).await;
+        assert_eq!(iterations, 8);
+
+        // Confidence gains shrink from 0.06 to 0.03 to 0.02, so the second
+        // and third refinements are the two gains below 0.05
+        let (result, iterations) = run(Some(vec![Arc::new(crate::DiminishingReturns::new(0.05).with_patience(2))])).await;
+        assert_eq!(iterations, 4);
+        assert!(!result.cancelled);
+
+        // Each iteration's analysis makes one API call
+        let budget = crate::RefinementBudget::new().with_max_api_calls(2);
+        let (result, iterations) = run(Some(vec![Arc::new(budget)])).await;
+        assert_eq!(iterations, 2);
+        assert_eq!(result.execution_stats.api_calls_made, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_estimate_matches_actual_run() {
+        let mut engine = RecursiveEngine::new(Arc::new(BasicNodeExecutor));
+        let context = create_test_context();
+
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        for question in ["Who?", "What?", "Why?"] {
+            let node = crate::NodeFactory::create_analysis_node(question.to_string(), "Test".to_string(), chain.root_node_id.clone());
+            chain.add_node(node).unwrap();
+        }
+
+        // Without a price the cost is unknown rather than free
+        assert_eq!(engine.estimate(&chain, &context).cost_estimate, None);
+
+        let unpriced = sira_ai_backends::MockProvider::new("unpriced", &["model-1"]);
+        assert!(!engine.set_api_pricing_from(&unpriced, "model-1"));
+        let priced = sira_ai_backends::MockProvider::new("priced", &["model-1"]).with_pricing(0.01);
+        assert!(engine.set_api_pricing_from(&priced, "model-1"));
+
+        let estimate = engine.estimate(&chain, &context);
+        assert_eq!(estimate.execution_plan.len(), 4);
+        assert_eq!(estimate.time_estimate_ms, 151);
+        assert_eq!(estimate.api_calls_estimate, 3);
+        assert_eq!(estimate.token_estimate, 3000);
+        assert!((estimate.cost_estimate.unwrap() - 0.03).abs() < 1e-9);
+
+        let result = engine.execute_chain(chain, &context, 0, &CancellationToken::new()).await.unwrap();
+        assert_eq!(result.execution_stats.executed_nodes, estimate.execution_plan.len() as u64);
+        assert_eq!(result.execution_stats.total_execution_time_ms, estimate.time_estimate_ms);
+    }
 }
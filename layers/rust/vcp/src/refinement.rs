@@ -0,0 +1,132 @@
+//! When recursive refinement ends
+//!
+//! After every iteration a [`RecursiveStrategyExecutor`](crate::RecursiveStrategyExecutor)
+//! asks its [`StopCriterion`]s whether to stop, and stops as soon as one
+//! says so or `max_iterations` is reached. The default criterion is
+//! [`ContextConfidence`].
+
+use crate::{ChainExecutionResult, ThinkingContext};
+use std::time::Duration;
+
+/// Progress of a refinement run after an iteration
+pub struct RefinementProgress<'a> {
+    pub context: &'a ThinkingContext,
+    /// Result of every iteration so far, oldest first; never empty
+    pub results: &'a [ChainExecutionResult],
+    /// Time since refinement started
+    pub elapsed: Duration,
+}
+
+impl RefinementProgress<'_> {
+    /// Result of the iteration that just finished
+    pub fn latest(&self) -> &ChainExecutionResult {
+        self.results.last().expect("refinement progress has at least one result")
+    }
+
+    /// API calls made over all iterations
+    pub fn api_calls_made(&self) -> u64 {
+        self.results.iter().map(|result| result.execution_stats.api_calls_made).sum()
+    }
+}
+
+/// Decides whether refinement ends after an iteration
+pub trait StopCriterion: Send + Sync {
+    /// Whether to stop refining
+    fn should_stop(&self, progress: &RefinementProgress<'_>) -> bool;
+}
+
+/// Stop once a successful iteration reaches the context's emotional confidence
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContextConfidence;
+
+impl StopCriterion for ContextConfidence {
+    fn should_stop(&self, progress: &RefinementProgress<'_>) -> bool {
+        let latest = progress.latest();
+        latest.success && latest.confidence >= progress.context.emotional_state.confidence
+    }
+}
+
+/// Stop once a successful iteration reaches a fixed confidence
+#[derive(Debug, Clone, Copy)]
+pub struct TargetConfidence(pub f64);
+
+impl StopCriterion for TargetConfidence {
+    fn should_stop(&self, progress: &RefinementProgress<'_>) -> bool {
+        let latest = progress.latest();
+        latest.success && latest.confidence >= self.0
+    }
+}
+
+/// Stop when confidence stops improving by at least `min_gain` per iteration
+///
+/// Refinement ends once each of the last `patience` iterations gained less
+/// than `min_gain` over the one before it.
+#[derive(Debug, Clone, Copy)]
+pub struct DiminishingReturns {
+    pub min_gain: f64,
+    pub patience: usize,
+}
+
+impl DiminishingReturns {
+    /// Stop after a single iteration gaining less than `min_gain`
+    pub fn new(min_gain: f64) -> Self {
+        Self { min_gain, patience: 1 }
+    }
+
+    /// Set how many consecutive small gains end refinement
+    pub fn with_patience(mut self, patience: usize) -> Self {
+        self.patience = patience.max(1);
+        self
+    }
+}
+
+impl StopCriterion for DiminishingReturns {
+    fn should_stop(&self, progress: &RefinementProgress<'_>) -> bool {
+        if progress.results.len() <= self.patience {
+            return false;
+        }
+
+        progress.results
+            .windows(2)
+            .rev()
+            .take(self.patience)
+            .all(|pair| pair[1].confidence - pair[0].confidence < self.min_gain)
+    }
+}
+
+/// Stop once refinement has used up a time or API call budget
+///
+/// Budgets are checked between iterations, so the iteration that crosses
+/// one still completes. API calls are counted as the node executions
+/// report them in [`crate::ExecutionCost::api_calls_estimate`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RefinementBudget {
+    pub max_elapsed: Option<Duration>,
+    pub max_api_calls: Option<u64>,
+}
+
+impl RefinementBudget {
+    /// Create a budget without limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the total time spent refining
+    pub fn with_max_elapsed(mut self, max_elapsed: Duration) -> Self {
+        self.max_elapsed = Some(max_elapsed);
+        self
+    }
+
+    /// Limit the API calls made over all iterations
+    pub fn with_max_api_calls(mut self, max_api_calls: u64) -> Self {
+        self.max_api_calls = Some(max_api_calls);
+        self
+    }
+}
+
+impl StopCriterion for RefinementBudget {
+    fn should_stop(&self, progress: &RefinementProgress<'_>) -> bool {
+        self.max_elapsed.is_some_and(|max| progress.elapsed >= max)
+            || self.max_api_calls.is_some_and(|max| progress.api_calls_made() >= max)
+    }
+}
@@ -0,0 +1,105 @@
+//! Reasoning reports for audit and sharing
+//!
+//! A [`ReasoningReport`] is a self-contained transcript of one chain
+//! execution: every executed node with its input, output, confidence and
+//! timing, followed by the metacognitive assessments, the adaptations made
+//! along the way and the final answer. It serializes to JSON as is.
+
+use crate::{ChainExecutionResult, MetacognitiveAssessment, NodeContent, NodeType};
+use serde::{Deserialize, Serialize};
+
+/// Transcript of a chain execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReasoningReport {
+    pub chain_id: String,
+    pub success: bool,
+    pub cancelled: bool,
+    pub abort_reason: Option<String>,
+    pub final_answer: Option<String>,
+    pub confidence: f64,
+    #[serde(default)]
+    pub quality_score: f64,
+    pub total_execution_time_ms: u64,
+    /// Executed nodes in execution order
+    pub steps: Vec<ReportStep>,
+    pub assessments: Vec<MetacognitiveAssessment>,
+    pub adaptations: Vec<String>,
+}
+
+/// One executed node of a report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportStep {
+    /// Position in execution order, starting at 1
+    pub step: usize,
+    pub node_id: String,
+    pub node_type: NodeType,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub predicted_confidence: f64,
+    pub confidence: f64,
+    pub success: bool,
+    pub execution_time_ms: u64,
+    pub error: Option<String>,
+}
+
+impl ReasoningReport {
+    /// Report as pretty-printed JSON
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl ChainExecutionResult {
+    /// Assemble a human-readable report of this execution
+    pub fn to_report(&self) -> ReasoningReport {
+        let steps = self.node_outcomes.iter()
+            .enumerate()
+            .map(|(index, outcome)| ReportStep {
+                step: index + 1,
+                node_id: outcome.node_id.clone(),
+                node_type: outcome.node_type,
+                input: outcome.input.as_ref().map(render_content),
+                output: outcome.output.as_ref().map(render_content),
+                predicted_confidence: outcome.predicted_confidence,
+                confidence: outcome.confidence,
+                success: outcome.success,
+                execution_time_ms: outcome.execution_time_ms,
+                error: outcome.error.clone(),
+            })
+            .collect();
+
+        ReasoningReport {
+            chain_id: self.chain_id.clone(),
+            success: self.success,
+            cancelled: self.cancelled,
+            abort_reason: self.abort_reason.clone(),
+            final_answer: self.final_answer.clone(),
+            confidence: self.confidence,
+            quality_score: self.quality_score,
+            total_execution_time_ms: self.execution_stats.total_execution_time_ms,
+            steps,
+            assessments: self.metacognitive_history.clone(),
+            adaptations: self.adaptation_log.clone(),
+        }
+    }
+}
+
+/// Node content as one line of readable text
+fn render_content(content: &NodeContent) -> String {
+    match content {
+        NodeContent::Text(text) => text.clone(),
+        NodeContent::Structured { title, content, .. } => format!("{}: {}", title, content),
+        NodeContent::Data { data_type, value } => format!("{} {}", data_type, value),
+        NodeContent::Question { question, context, .. } => format!("{} (context: {})", question, context),
+        NodeContent::Hypothesis { statement, evidence, confidence } => {
+            format!("{} (confidence {:.2}; evidence: {})", statement, confidence, evidence.join("; "))
+        }
+        NodeContent::Decision { options, chosen_option, .. } => match chosen_option {
+            Some(choice) => format!("chose {} from [{}]", choice, options.join(", ")),
+            None => format!("undecided between [{}]", options.join(", ")),
+        },
+        NodeContent::Action { action_type, expected_outcome, .. } => {
+            format!("{} expecting {}", action_type, expected_outcome)
+        }
+    }
+}
@@ -2,7 +2,7 @@
 
 use crate::{VcpResult, VcpError, ThinkingNode, NodeType, ChainExecutionResult, ExecutionStats};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use chrono::{DateTime, Utc};
 use tracing::{debug, info, warn};
 
@@ -37,7 +37,7 @@ pub enum ChainStatus {
 }
 
 /// Chain execution state
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChainExecutionState {
     pub chain: ThinkingChain,
     pub execution_queue: VecDeque<String>, // Node IDs to execute
@@ -52,7 +52,7 @@ impl ThinkingChain {
     /// Create a new thinking chain
     pub fn new(name: String, description: String, root_content: String) -> Self {
         let root_node = ThinkingNode {
-            id: format!("root_{}", uuid::Uuid::new_v4().simple()),
+            id: crate::generate_id("root"),
             node_type: NodeType::Input,
             content: crate::NodeContent::Text(root_content),
             confidence: 1.0,
@@ -78,7 +78,7 @@ impl ThinkingChain {
         nodes.insert(root_node.id.clone(), root_node.clone());
 
         Self {
-            id: format!("chain_{}", uuid::Uuid::new_v4().simple()),
+            id: crate::generate_id("chain"),
             name,
             description,
             nodes,
@@ -144,9 +144,9 @@ impl ThinkingChain {
             .collect()
     }
 
-    /// Get nodes ready for execution (all prerequisites met)
+    /// Get nodes ready for execution (all prerequisites met), ordered by ID
     pub fn get_executable_nodes(&self, executed_nodes: &HashMap<String, bool>) -> Vec<String> {
-        self.nodes.keys()
+        let mut executable: Vec<String> = self.nodes.keys()
             .filter(|node_id| {
                 let node = &self.nodes[*node_id];
                 // Node not yet executed
@@ -155,17 +155,60 @@ impl ThinkingChain {
                 node.prerequisites.iter().all(|prereq| executed_nodes.contains_key(prereq))
             })
             .cloned()
-            .collect()
+            .collect();
+
+        // Map iteration order varies between runs; sorting keeps execution reproducible
+        executable.sort();
+        executable
     }
 
-    /// Calculate overall chain quality
+    /// IDs of nodes the root never leads to, sorted
+    ///
+    /// A node is reached through its parent's child list or through its
+    /// prerequisites, and only once all of its prerequisites are reached, as
+    /// execution waits for every one of them.
+    pub fn unreachable_nodes(&self) -> Vec<String> {
+        let mut followers: HashMap<&str, Vec<&str>> = HashMap::new();
+        for node in self.nodes.values() {
+            for prereq in &node.prerequisites {
+                followers.entry(prereq.as_str()).or_default().push(node.id.as_str());
+            }
+        }
+
+        let mut reached: HashSet<&str> = HashSet::new();
+        let mut queue: VecDeque<&str> = VecDeque::from([self.root_node_id.as_str()]);
+        while let Some(node_id) = queue.pop_front() {
+            let Some(node) = self.nodes.get(node_id) else { continue };
+            // A node with prerequisites still to reach is queued again by the last of them
+            if reached.contains(node_id) || !node.prerequisites.iter().all(|prereq| reached.contains(prereq.as_str())) {
+                continue;
+            }
+            reached.insert(node_id);
+            queue.extend(node.children_ids.iter().map(String::as_str));
+            queue.extend(followers.get(node_id).into_iter().flatten().copied());
+        }
+
+        let mut unreachable: Vec<String> = self.nodes.keys()
+            .filter(|node_id| !reached.contains(node_id.as_str()))
+            .cloned()
+            .collect();
+        unreachable.sort();
+        unreachable
+    }
+
+    /// Calculate overall chain quality with the default quality scorer
     pub fn calculate_quality(&self) -> f64 {
+        self.calculate_quality_with(&crate::QualityScorer::default())
+    }
+
+    /// Calculate overall chain quality: the mean node quality score, scaled by node confidence
+    pub fn calculate_quality_with(&self, scorer: &crate::QualityScorer) -> f64 {
         if self.nodes.is_empty() {
             return 0.0;
         }
 
         let total_quality: f64 = self.nodes.values()
-            .map(|node| node.quality.logical_consistency * node.confidence)
+            .map(|node| scorer.score(&node.quality) * node.confidence)
             .sum();
 
         total_quality / self.nodes.len() as f64
@@ -232,6 +275,133 @@ impl ThinkingChain {
         Ok(())
     }
 
+    /// Graft another chain into this one
+    ///
+    /// Nodes structurally equivalent to an existing node (same type and
+    /// content after normalizing case and whitespace) are merged into it
+    /// rather than duplicated; the other chain's root becomes this chain's
+    /// root when equivalent, and a child of it otherwise. Remaining nodes keep
+    /// their IDs unless they collide, in which case they get fresh ones.
+    /// Returns the ID each of `other`'s nodes ended up with.
+    pub fn merge(&mut self, other: ThinkingChain) -> HashMap<String, String> {
+        let mut existing: HashMap<(NodeType, String), String> = HashMap::new();
+        let mut ids: Vec<&String> = self.nodes.keys().collect();
+        ids.sort();
+        for id in ids {
+            existing.entry(Self::structural_key(&self.nodes[id])).or_insert_with(|| id.clone());
+        }
+
+        // Parents are placed before their children
+        let depth_of = |node: &ThinkingNode| {
+            let mut depth = 0;
+            let mut parent = node.parent_id.as_ref();
+            while let Some(parent_node) = parent.and_then(|id| other.nodes.get(id)) {
+                depth += 1;
+                if depth > other.nodes.len() {
+                    break;
+                }
+                parent = parent_node.parent_id.as_ref();
+            }
+            depth
+        };
+        let mut order: Vec<&ThinkingNode> = other.nodes.values().collect();
+        order.sort_by(|a, b| depth_of(a).cmp(&depth_of(b)).then_with(|| a.id.cmp(&b.id)));
+
+        let mut mapping = HashMap::new();
+        let mut grafted = Vec::new();
+        let mut deduplicated = Vec::new();
+        for node in order {
+            let key = Self::structural_key(node);
+            if let Some(id) = existing.get(&key) {
+                mapping.insert(node.id.clone(), id.clone());
+                deduplicated.push(node);
+                continue;
+            }
+
+            let id = if self.nodes.contains_key(&node.id) || mapping.values().any(|mapped| *mapped == node.id) {
+                crate::generate_id(node.id.split('_').next().unwrap_or("node"))
+            } else {
+                node.id.clone()
+            };
+            existing.insert(key, id.clone());
+            mapping.insert(node.id.clone(), id);
+            grafted.push(node);
+        }
+
+        let remap = |ids: &[String]| -> Vec<String> {
+            let mut remapped: Vec<String> = Vec::new();
+            for id in ids.iter().filter_map(|id| mapping.get(id)) {
+                if !remapped.contains(id) {
+                    remapped.push(id.clone());
+                }
+            }
+            remapped
+        };
+
+        let grafted_count = grafted.len();
+        for node in grafted {
+            let id = mapping[&node.id].clone();
+            let parent_id = match &node.parent_id {
+                Some(parent) => mapping.get(parent).cloned(),
+                None if node.id == other.root_node_id => Some(self.root_node_id.clone()),
+                None => None,
+            };
+            if let Some(parent) = parent_id.as_ref().and_then(|parent| self.nodes.get_mut(parent)) {
+                parent.children_ids.push(id.clone());
+            }
+
+            let mut grafted_node = node.clone();
+            grafted_node.id = id.clone();
+            grafted_node.parent_id = parent_id;
+            grafted_node.children_ids = Vec::new();
+            grafted_node.prerequisites = remap(&node.prerequisites);
+            grafted_node.dependencies = remap(&node.dependencies);
+            self.nodes.insert(id, grafted_node);
+            self.execution_stats.total_nodes += 1;
+        }
+
+        // Merged nodes keep the ordering constraints of both chains
+        for node in deduplicated {
+            let id = &mapping[&node.id];
+            let prerequisites = remap(&node.prerequisites);
+            let dependencies = remap(&node.dependencies);
+            if let Some(target) = self.nodes.get_mut(id) {
+                for prereq in prerequisites {
+                    if prereq != *id && !target.prerequisites.contains(&prereq) {
+                        target.prerequisites.push(prereq);
+                    }
+                }
+                for dependency in dependencies {
+                    if dependency != *id && !target.dependencies.contains(&dependency) {
+                        target.dependencies.push(dependency);
+                    }
+                }
+            }
+        }
+
+        debug!("Merged chain {} into {}: {} of {} nodes grafted", other.id, self.id, grafted_count, other.nodes.len());
+        mapping
+    }
+
+    /// Identity of a node for deduplication: its type and normalized content
+    pub(crate) fn structural_key(node: &ThinkingNode) -> (NodeType, String) {
+        fn normalize(value: serde_json::Value) -> serde_json::Value {
+            match value {
+                serde_json::Value::String(text) => serde_json::Value::String(
+                    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+                ),
+                serde_json::Value::Array(items) => serde_json::Value::Array(items.into_iter().map(normalize).collect()),
+                serde_json::Value::Object(fields) => serde_json::Value::Object(
+                    fields.into_iter().map(|(key, value)| (key, normalize(value))).collect()
+                ),
+                other => other,
+            }
+        }
+
+        let content = serde_json::to_value(&node.content).map(normalize).unwrap_or_default();
+        (node.node_type, content.to_string())
+    }
+
     /// Export chain as JSON
     pub fn to_json(&self) -> VcpResult<String> {
         serde_json::to_string_pretty(self)
@@ -330,19 +500,33 @@ impl ChainExecutionState {
 /// Chain builder for fluent construction
 pub struct ChainBuilder {
     chain: ThinkingChain,
+    last_node_id: String,
 }
 
 impl ChainBuilder {
     /// Create a new chain builder
     pub fn new(name: String, description: String, root_content: String) -> Self {
+        let chain = ThinkingChain::new(name, description, root_content);
         Self {
-            chain: ThinkingChain::new(name, description, root_content),
+            last_node_id: chain.root_node_id.clone(),
+            chain,
         }
     }
 
+    /// ID of the chain's root node, to attach the first nodes to
+    pub fn root_id(&self) -> &str {
+        &self.chain.root_node_id
+    }
+
+    /// ID of the node added last, or of the root before any are added
+    pub fn last_node_id(&self) -> &str {
+        &self.last_node_id
+    }
+
     /// Add an analysis node
     pub fn add_analysis(mut self, question: String, context: String, parent_id: String) -> VcpResult<Self> {
         let node = crate::NodeFactory::create_analysis_node(question, context, parent_id);
+        self.last_node_id = node.id.clone();
         self.chain.add_node(node)?;
         Ok(self)
     }
@@ -350,6 +534,7 @@ impl ChainBuilder {
     /// Add a synthesis node
     pub fn add_synthesis(mut self, sources: Vec<String>, goal: String) -> VcpResult<Self> {
         let node = crate::NodeFactory::create_synthesis_node(sources, goal);
+        self.last_node_id = node.id.clone();
         self.chain.add_node(node)?;
         Ok(self)
     }
@@ -357,6 +542,7 @@ impl ChainBuilder {
     /// Add a decision node
     pub fn add_decision(mut self, options: Vec<String>, criteria: Vec<String>) -> VcpResult<Self> {
         let node = crate::NodeFactory::create_decision_node(options, criteria);
+        self.last_node_id = node.id.clone();
         self.chain.add_node(node)?;
         Ok(self)
     }
@@ -436,6 +622,23 @@ mod tests {
         assert_eq!(state.get_average_quality(), 0.8);
     }
 
+    #[test]
+    fn test_chain_quality_uses_the_quality_scorer() {
+        let mut chain = ThinkingChain::new("Test".to_string(), "Test".to_string(), "Test".to_string());
+        let root = chain.get_node_mut(&chain.root_node_id.clone()).unwrap();
+        root.confidence = 0.5;
+        root.quality.novelty = 0.0;
+
+        let default = chain.calculate_quality();
+        assert!((default - 0.5 * crate::QualityScorer::default().score(&chain.nodes[&chain.root_node_id].quality)).abs() < 1e-9);
+
+        // Weighting only novelty scores the root by its novelty alone
+        let novelty_only = crate::QualityDimension::ALL.iter()
+            .fold(crate::QualityScorer::new(), |scorer, d| scorer.with_weight(*d, 0.0))
+            .with_weight(crate::QualityDimension::Novelty, 1.0);
+        assert_eq!(chain.calculate_quality_with(&novelty_only), 0.0);
+    }
+
     #[test]
     fn test_chain_validation() {
         let mut chain = ThinkingChain::new(
@@ -474,4 +677,44 @@ mod tests {
         chain.add_node(invalid_node).unwrap();
         assert!(chain.validate().is_err());
     }
+
+    #[test]
+    fn test_merge_deduplicates_equivalent_nodes() {
+        let mut first = ThinkingChain::new("A".to_string(), "A".to_string(), "Plan the launch".to_string());
+        let mut second = ThinkingChain::new("B".to_string(), "B".to_string(), "plan  the LAUNCH".to_string());
+
+        let shared = |chain: &ThinkingChain, context: &str| crate::NodeFactory::create_analysis_node(
+            "What are the risks?".to_string(), context.to_string(), chain.root_node_id.clone(),
+        );
+        first.add_node(shared(&first, "Launch context")).unwrap();
+        let duplicate = shared(&second, "launch   context");
+        let duplicate_id = duplicate.id.clone();
+        second.add_node(duplicate).unwrap();
+
+        // Give the second chain a node whose ID collides with one in the first
+        let mut follow_up = crate::NodeFactory::create_analysis_node(
+            "Who is affected?".to_string(), "Launch context".to_string(), duplicate_id.clone(),
+        );
+        follow_up.id = first.root_node_id.clone();
+        follow_up.prerequisites = vec![duplicate_id.clone()];
+        second.add_node(follow_up).unwrap();
+
+        let mapping = first.merge(second.clone());
+
+        let risks: Vec<_> = first.nodes.values()
+            .filter(|node| matches!(&node.content, crate::NodeContent::Question { question, .. } if question == "What are the risks?"))
+            .collect();
+        assert_eq!(risks.len(), 1);
+        assert_eq!(first.nodes.len(), 3);
+        assert_eq!(mapping[&second.root_node_id], first.root_node_id);
+        assert_eq!(mapping[&duplicate_id], risks[0].id);
+
+        let follow_up_id = &mapping[&first.root_node_id];
+        assert_ne!(follow_up_id, &first.root_node_id);
+        let follow_up = &first.nodes[follow_up_id];
+        assert_eq!(follow_up.parent_id.as_ref(), Some(&risks[0].id));
+        assert_eq!(follow_up.prerequisites, vec![risks[0].id.clone()]);
+        assert!(risks[0].children_ids.contains(follow_up_id));
+        assert!(first.validate().is_ok());
+    }
 }
@@ -1,10 +1,11 @@
 //! Thinking Node for VCP
 
-use crate::{VcpResult, VcpError, ThinkingContext, ReasoningQuality};
+use crate::{VcpResult, VcpError, ThinkingContext, ReasoningQuality, ChainExecutionResult};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info};
 
 /// Thinking node - fundamental unit of reasoning
@@ -78,8 +79,13 @@ pub enum NodeContent {
 /// Node execution trait
 #[async_trait]
 pub trait NodeExecutor: Send + Sync {
-    /// Execute a thinking node
-    async fn execute_node(&self, node: &ThinkingNode, context: &ThinkingContext) -> VcpResult<NodeExecutionResult>;
+    /// Execute a thinking node, stopping early once `cancellation` is cancelled
+    async fn execute_node(
+        &self,
+        node: &ThinkingNode,
+        context: &ThinkingContext,
+        cancellation: &CancellationToken,
+    ) -> VcpResult<NodeExecutionResult>;
 
     /// Get supported node types
     fn supported_types(&self) -> Vec<NodeType>;
@@ -111,6 +117,102 @@ pub struct ExecutionCost {
     pub api_calls_estimate: u32,
 }
 
+/// Predicted confidence and actual outcome of one executed node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeOutcome {
+    pub node_id: String,
+    pub node_type: NodeType,
+    pub predicted_confidence: f64,
+    pub success: bool,
+    /// Content the node was executed on
+    #[serde(default)]
+    pub input: Option<NodeContent>,
+    #[serde(default)]
+    pub output: Option<NodeContent>,
+    /// Confidence reported by the execution
+    #[serde(default)]
+    pub confidence: f64,
+    #[serde(default)]
+    pub execution_time_ms: u64,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// API calls the execution reported making; none for a memoized node
+    #[serde(default)]
+    pub api_calls: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CalibrationStats {
+    observations: u64,
+    predicted_sum: f64,
+    success_sum: f64,
+}
+
+/// Calibrates heuristic node confidences against historical success rates
+#[derive(Debug, Clone)]
+pub struct ConfidenceCalibrator {
+    stats: HashMap<NodeType, CalibrationStats>,
+    /// Pseudo-observations of perfect calibration, so early outcomes move confidences gradually
+    prior_strength: f64,
+}
+
+impl ConfidenceCalibrator {
+    /// Create a new calibrator
+    pub fn new() -> Self {
+        Self {
+            stats: HashMap::new(),
+            prior_strength: 5.0,
+        }
+    }
+
+    /// Set how many observations a node type needs before its history dominates
+    pub fn with_prior_strength(mut self, prior_strength: f64) -> Self {
+        self.prior_strength = prior_strength.max(0.0);
+        self
+    }
+
+    /// Record the outcome of one node execution
+    pub fn record(&mut self, node_type: NodeType, predicted_confidence: f64, success: bool) {
+        let stats = self.stats.entry(node_type).or_default();
+        stats.observations += 1;
+        stats.predicted_sum += predicted_confidence.clamp(0.0, 1.0);
+        stats.success_sum += if success { 1.0 } else { 0.0 };
+    }
+
+    /// Record every node outcome of a chain execution
+    pub fn observe_execution(&mut self, result: &ChainExecutionResult) {
+        for outcome in &result.node_outcomes {
+            self.record(outcome.node_type, outcome.predicted_confidence, outcome.success);
+        }
+    }
+
+    /// Ratio of observed success to predicted confidence for a node type
+    pub fn calibration_factor(&self, node_type: NodeType) -> f64 {
+        match self.stats.get(&node_type) {
+            Some(stats) if stats.predicted_sum + self.prior_strength > 0.0 => {
+                (stats.success_sum + self.prior_strength) / (stats.predicted_sum + self.prior_strength)
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Adjust a reported confidence by its node type's track record
+    pub fn calibrate(&self, node_type: NodeType, confidence: f64) -> f64 {
+        (confidence * self.calibration_factor(node_type)).clamp(0.0, 1.0)
+    }
+
+    /// Number of outcomes recorded for a node type
+    pub fn observations(&self, node_type: NodeType) -> u64 {
+        self.stats.get(&node_type).map(|s| s.observations).unwrap_or(0)
+    }
+}
+
+impl Default for ConfidenceCalibrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Node factory for creating different types of nodes
 pub struct NodeFactory;
 
@@ -118,7 +220,7 @@ impl NodeFactory {
     /// Create an input processing node
     pub fn create_input_node(content: String, context: &ThinkingContext) -> ThinkingNode {
         ThinkingNode {
-            id: format!("input_{}", uuid::Uuid::new_v4().simple()),
+            id: crate::generate_id("input"),
             node_type: NodeType::Input,
             content: NodeContent::Text(content),
             confidence: 1.0, // Input is assumed to be accurate
@@ -144,7 +246,7 @@ impl NodeFactory {
     /// Create an analysis node
     pub fn create_analysis_node(question: String, context: String, parent_id: String) -> ThinkingNode {
         ThinkingNode {
-            id: format!("analysis_{}", uuid::Uuid::new_v4().simple()),
+            id: crate::generate_id("analysis"),
             node_type: NodeType::Analysis,
             content: NodeContent::Question {
                 question,
@@ -176,7 +278,7 @@ impl NodeFactory {
         let content = format!("Synthesize information from {} sources to achieve: {}", sources.len(), goal);
 
         ThinkingNode {
-            id: format!("synthesis_{}", uuid::Uuid::new_v4().simple()),
+            id: crate::generate_id("synthesis"),
             node_type: NodeType::Synthesis,
             content: NodeContent::Structured {
                 title: "Synthesis".to_string(),
@@ -208,10 +310,44 @@ impl NodeFactory {
         }
     }
 
+    /// Create a critique node reviewing the outcome of `reviewed` nodes
+    pub fn create_critique_node(focus: String, reviewed: Vec<String>) -> ThinkingNode {
+        ThinkingNode {
+            id: crate::generate_id("critique"),
+            node_type: NodeType::Critique,
+            content: NodeContent::Structured {
+                title: "Critique".to_string(),
+                content: focus,
+                structure_type: "critique".to_string(),
+            },
+            confidence: 0.5,
+            quality: ReasoningQuality {
+                logical_consistency: 0.8,
+                completeness: 0.5,
+                relevance: 0.8,
+                novelty: 0.5,
+                efficiency: 0.6,
+                adaptability: 0.7,
+            },
+            metadata: {
+                let mut meta = HashMap::new();
+                meta.insert("reviewed".to_string(), serde_json::json!(reviewed));
+                meta
+            },
+            created_at: Utc::now(),
+            executed_at: None,
+            execution_time_ms: None,
+            parent_id: None,
+            children_ids: Vec::new(),
+            prerequisites: reviewed,
+            dependencies: Vec::new(),
+        }
+    }
+
     /// Create a decision node
     pub fn create_decision_node(options: Vec<String>, criteria: Vec<String>) -> ThinkingNode {
         ThinkingNode {
-            id: format!("decision_{}", uuid::Uuid::new_v4().simple()),
+            id: crate::generate_id("decision"),
             node_type: NodeType::Decision,
             content: NodeContent::Decision {
                 options: options.clone(),
@@ -248,7 +384,7 @@ impl NodeFactory {
         let content = format!("Reflect on: {} with {} insights", topic, insights.len());
 
         ThinkingNode {
-            id: format!("reflection_{}", uuid::Uuid::new_v4().simple()),
+            id: crate::generate_id("reflection"),
             node_type: NodeType::Reflection,
             content: NodeContent::Structured {
                 title: "Reflection".to_string(),
@@ -284,27 +420,43 @@ impl NodeFactory {
 /// Basic node executor implementation
 pub struct BasicNodeExecutor;
 
+impl BasicNodeExecutor {
+    /// Time spent simulating the execution of a node, based on its type's complexity
+    fn simulated_time_ms(node_type: NodeType) -> u64 {
+        match node_type {
+            NodeType::Input => 1,
+            NodeType::Analysis => 50,
+            NodeType::Synthesis => 80,
+            NodeType::Evaluation => 30,
+            NodeType::Generation => 60,
+            NodeType::Critique => 40,
+            NodeType::MetaAnalysis => 20,
+            NodeType::Decision => 35,
+            NodeType::Execution => 70,
+            NodeType::Reflection => 25,
+        }
+    }
+}
+
 #[async_trait]
 impl NodeExecutor for BasicNodeExecutor {
-    async fn execute_node(&self, node: &ThinkingNode, context: &ThinkingContext) -> VcpResult<NodeExecutionResult> {
+    async fn execute_node(
+        &self,
+        node: &ThinkingNode,
+        context: &ThinkingContext,
+        cancellation: &CancellationToken,
+    ) -> VcpResult<NodeExecutionResult> {
         debug!("Executing node: {} of type {:?}", node.id, node.node_type);
 
-        // Simulate execution time based on node type and complexity
-        let execution_time = match node.node_type {
-            NodeType::Input => 10,
-            NodeType::Analysis => 500,
-            NodeType::Synthesis => 800,
-            NodeType::Evaluation => 300,
-            NodeType::Generation => 600,
-            NodeType::Critique => 400,
-            NodeType::MetaAnalysis => 200,
-            NodeType::Decision => 350,
-            NodeType::Execution => 700,
-            NodeType::Reflection => 250,
-        };
+        let execution_time = Self::simulated_time_ms(node.node_type);
 
         // Simulate processing
-        tokio::time::sleep(std::time::Duration::from_millis(execution_time / 10)).await;
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_millis(execution_time)) => {}
+            _ = cancellation.cancelled() => {
+                return Err(VcpError::Cancelled(format!("Node {} cancelled", node.id)));
+            }
+        }
 
         let result = match &node.content {
             NodeContent::Text(content) => {
@@ -382,21 +534,8 @@ impl NodeExecutor for BasicNodeExecutor {
     }
 
     fn estimate_cost(&self, node: &ThinkingNode) -> ExecutionCost {
-        let base_time = match node.node_type {
-            NodeType::Input => 50,
-            NodeType::Analysis => 1000,
-            NodeType::Synthesis => 1500,
-            NodeType::Evaluation => 600,
-            NodeType::Generation => 1200,
-            NodeType::Critique => 800,
-            NodeType::MetaAnalysis => 400,
-            NodeType::Decision => 700,
-            NodeType::Execution => 1400,
-            NodeType::Reflection => 500,
-        };
-
         ExecutionCost {
-            time_estimate_ms: base_time,
+            time_estimate_ms: Self::simulated_time_ms(node.node_type),
             cognitive_load: 0.5,
             resource_intensity: 0.4,
             api_calls_estimate: if matches!(node.node_type, NodeType::Analysis | NodeType::Generation) { 1 } else { 0 },
@@ -474,7 +613,7 @@ mod tests {
 
         let node = NodeFactory::create_input_node("Test content".to_string(), &context);
 
-        let result = executor.execute_node(&node, &context).await.unwrap();
+        let result = executor.execute_node(&node, &context, &CancellationToken::new()).await.unwrap();
         assert!(result.success);
         assert!(result.confidence > 0.0);
         assert!(result.execution_cost.time_estimate_ms > 0);
@@ -113,6 +113,8 @@ pub struct ChainGenerationParams {
     pub strategy: ThinkingStrategy,
     pub available_heuristics: Vec<String>,
     pub domain_experts: Vec<String>,
+    #[serde(default)]
+    pub cost_budget: Option<f64>,      // Max estimated cost, in per-node cost units
 }
 
 /// Reasoning quality metrics
@@ -192,17 +194,98 @@ pub struct VcpExecutionStats {
     pub metacognitive_interventions: u64,
 }
 
+#[cfg(test)]
+impl VcpExecutionStats {
+    /// Stats with only an average execution time, the one field the controllers read
+    pub(crate) fn for_test(average_execution_time_ms: f64) -> Self {
+        Self {
+            total_chains_generated: 0,
+            successful_chains: 0,
+            average_chain_length: 0.0,
+            average_execution_time_ms,
+            average_quality_score: 0.0,
+            adaptation_events: 0,
+            metacognitive_interventions: 0,
+        }
+    }
+}
+
 /// Chain execution result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChainExecutionResult {
     pub chain_id: String,
+    /// ID of this run of the chain, its checkpoint key for [`crate::RecursiveEngine::resume_chain`]
+    #[serde(default)]
+    pub run_id: String,
     pub success: bool,
     pub final_answer: Option<String>,
     pub confidence: f64,
+    /// How far `confidence` can be relied on, see [`ConfidenceInterval::from_node_confidences`]
+    #[serde(default)]
+    pub confidence_interval: ConfidenceInterval,
     pub quality_metrics: ReasoningQuality,
+    /// `quality_metrics` as one score, see [`crate::QualityScorer`]
+    #[serde(default)]
+    pub quality_score: f64,
     pub execution_stats: ExecutionStats,
     pub metacognitive_history: Vec<MetacognitiveAssessment>,
     pub adaptation_log: Vec<String>,
+    #[serde(default)]
+    pub node_outcomes: Vec<crate::NodeOutcome>,
+    /// Execution was stopped early by its cancellation token
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Why execution stopped early on its own, e.g. [`crate::LOOP_DETECTED`]
+    #[serde(default)]
+    pub abort_reason: Option<String>,
+}
+
+/// Range the confidence of an execution likely lies in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceInterval {
+    pub lower: f64,
+    pub upper: f64,
+    /// Probability the range covers the confidence, e.g. 0.95
+    pub level: f64,
+}
+
+/// z-score of a two-sided 95% normal interval
+const Z_95: f64 = 1.96;
+
+impl ConfidenceInterval {
+    /// 95% interval around a chain's confidence from its nodes' confidences
+    ///
+    /// Centred on `confidence` and as wide as the spread of the node
+    /// confidences, failed nodes counting as 0.0, so runs whose nodes disagree
+    /// get wider intervals however long they are. Fewer than two nodes say
+    /// nothing about the spread.
+    pub fn from_node_confidences(confidence: f64, node_confidences: &[f64]) -> Self {
+        if node_confidences.len() < 2 {
+            return Self::default();
+        }
+
+        let n = node_confidences.len() as f64;
+        let mean = node_confidences.iter().sum::<f64>() / n;
+        let variance = node_confidences.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let margin = Z_95 * variance.sqrt();
+
+        Self {
+            lower: (confidence - margin).clamp(0.0, 1.0),
+            upper: (confidence + margin).clamp(0.0, 1.0),
+            level: 0.95,
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.upper - self.lower
+    }
+}
+
+impl Default for ConfidenceInterval {
+    /// Nothing known: every confidence is possible
+    fn default() -> Self {
+        Self { lower: 0.0, upper: 1.0, level: 0.95 }
+    }
 }
 
 /// Execution statistics
@@ -217,3 +300,44 @@ pub struct ExecutionStats {
     pub memory_peak_mb: u64,
     pub api_calls_made: u64,
 }
+
+/// Predicted cost of executing a chain, see [`crate::RecursiveEngine::estimate`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionEstimate {
+    pub chain_id: String,
+    /// Nodes in the order the engine would execute them
+    pub execution_plan: Vec<String>,
+    /// Nodes in the plan that would reuse a memoized result instead of executing
+    pub memoized_nodes: u64,
+    pub time_estimate_ms: u64,
+    pub api_calls_estimate: u64,
+    pub token_estimate: u64,
+    /// Dollar cost of the tokens, or `None` when no API price is set
+    pub cost_estimate: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_high_variance_runs_get_wider_confidence_intervals() {
+        let steady = ConfidenceInterval::from_node_confidences(0.7, &[0.7, 0.72, 0.68, 0.7]);
+        let erratic = ConfidenceInterval::from_node_confidences(0.625, &[0.2, 0.95, 0.45, 0.9]);
+
+        assert!(steady.lower < 0.7 && steady.upper > 0.7);
+        assert!(erratic.width() > steady.width() * 5.0);
+        assert!(erratic.lower >= 0.0 && erratic.upper <= 1.0);
+
+        // A long erratic run is no more certain than a short one
+        let long_erratic = ConfidenceInterval::from_node_confidences(0.625, &[0.2, 0.95, 0.45, 0.9].repeat(10));
+        assert!(long_erratic.width() >= erratic.width() * 0.9);
+
+        // The interval is around the reported confidence, not the mean
+        let worst_case = ConfidenceInterval::from_node_confidences(0.2, &[0.2, 0.95, 0.45, 0.9]);
+        assert!(worst_case.lower <= 0.2 && worst_case.upper >= 0.2);
+
+        // A single node says nothing about the spread
+        assert_eq!(ConfidenceInterval::from_node_confidences(0.9, &[0.9]), ConfidenceInterval::default());
+    }
+}